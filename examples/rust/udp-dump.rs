@@ -52,6 +52,8 @@ pub const SOCK_DGRAM: i32 = libc::SOCK_DGRAM;
 struct ProgramArguments {
     /// Local socket IPv4 address.
     local: SocketAddr,
+    /// Maximum number of datagrams to pop at once, if batching.
+    batch: Option<usize>,
 }
 
 /// Associate functions for Program Arguments
@@ -72,11 +74,20 @@ impl ProgramArguments {
                     .value_name("ADDRESS:PORT")
                     .help("Sets local address"),
             )
+            .arg(
+                Arg::new("batch")
+                    .long("batch")
+                    .value_parser(clap::value_parser!(usize))
+                    .required(false)
+                    .value_name("COUNT")
+                    .help("Pops up to COUNT datagrams at once"),
+            )
             .get_matches();
 
         // Default arguments.
         let mut args: ProgramArguments = ProgramArguments {
             local: SocketAddr::from_str(Self::DEFAULT_LOCAL)?,
+            batch: None,
         };
 
         // Local address.
@@ -84,6 +95,11 @@ impl ProgramArguments {
             args.set_local_addr(addr)?;
         }
 
+        // Batch size.
+        if let Some(batch) = matches.get_one::<usize>("batch") {
+            args.batch = Some(*batch);
+        }
+
         Ok(args)
     }
 
@@ -92,6 +108,11 @@ impl ProgramArguments {
         self.local
     }
 
+    /// Returns the batch size parameter stored in the target program arguments.
+    pub fn get_batch(&self) -> Option<usize> {
+        self.batch
+    }
+
     /// Sets the local address and port number parameters in the target program arguments.
     fn set_local_addr(&mut self, addr: &str) -> Result<()> {
         self.local = SocketAddr::from_str(addr)?;
@@ -109,6 +130,8 @@ struct Application {
     libos: LibOS,
     /// Local socket descriptor.
     sockqd: QDesc,
+    /// Maximum number of datagrams to pop at once, if batching.
+    batch: Option<usize>,
 }
 
 /// Associated Functions for the Application
@@ -142,23 +165,42 @@ impl Application {
 
         println!("Local Address: {:?}", local);
 
-        Ok(Self { libos, sockqd })
+        Ok(Self {
+            libos,
+            sockqd,
+            batch: args.get_batch(),
+        })
     }
 
     /// Runs the target application.
     pub fn run(&mut self) -> Result<()> {
         let start: Instant = Instant::now();
         let mut nbytes: usize = 0;
+        let mut npackets: usize = 0;
         let mut last_log: Instant = Instant::now();
 
         loop {
             // Dump statistics.
             if last_log.elapsed() > Duration::from_secs(Self::LOG_INTERVAL) {
                 let elapsed: Duration = Instant::now() - start;
-                println!("{:?} B / {:?} us", nbytes, elapsed.as_micros());
+                println!(
+                    "{:?} B / {:?} packets / {:?} us ({:.0} packets/s)",
+                    nbytes,
+                    npackets,
+                    elapsed.as_micros(),
+                    npackets as f64 / elapsed.as_secs_f64()
+                );
                 last_log = Instant::now();
             }
 
+            // Drain packets in batches, if requested.
+            if let Some(max) = self.batch {
+                let (n, m): (usize, usize) = self.pop_batch(max)?;
+                nbytes += n;
+                npackets += m;
+                continue;
+            }
+
             // Drain packets.
             let qt: QToken = match self.libos.pop(self.sockqd, None) {
                 Ok(qt) => qt,
@@ -168,6 +210,7 @@ impl Application {
                 Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP => {
                     let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
                     nbytes += sga.sga_segs[0].sgaseg_len as usize;
+                    npackets += 1;
                     if let Err(e) = self.libos.sgafree(sga) {
                         println!("ERROR: sgafree() failed (error={:?})", e);
                         println!("WARN: leaking sga");
@@ -178,6 +221,38 @@ impl Application {
             }
         }
     }
+
+    /// Pops a batch of up to `max` packets, and returns the number of bytes and packets that were popped.
+    fn pop_batch(&mut self, max: usize) -> Result<(usize, usize)> {
+        let qt: QToken = match self.libos.pop_batch(self.sockqd, max) {
+            Ok(qt) => qt,
+            Err(e) => anyhow::bail!("failed to pop data from socket: {:?}", e),
+        };
+        match self.libos.wait(qt, None) {
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP_BATCH => {
+                let batch: demi_sgarray_t = unsafe { qr.qr_value.sga };
+                let npackets: usize = qr.qr_ret as usize;
+                let sgas: &[demi_sgarray_t] = unsafe {
+                    ::std::slice::from_raw_parts(batch.sga_segs[0].sgaseg_buf as *const demi_sgarray_t, npackets)
+                };
+                let mut nbytes: usize = 0;
+                for sga in sgas {
+                    nbytes += sga.sga_segs[0].sgaseg_len as usize;
+                    if let Err(e) = self.libos.sgafree(*sga) {
+                        println!("ERROR: sgafree() failed (error={:?})", e);
+                        println!("WARN: leaking sga");
+                    }
+                }
+                if let Err(e) = self.libos.sgafree(batch) {
+                    println!("ERROR: sgafree() failed (error={:?})", e);
+                    println!("WARN: leaking sga");
+                }
+                Ok((nbytes, npackets))
+            },
+            Ok(_) => anyhow::bail!("unexpected result"),
+            Err(e) => anyhow::bail!("operation failed: {:?}", e),
+        }
+    }
 }
 //======================================================================================================================
 // Trait Implementations
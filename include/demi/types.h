@@ -81,6 +81,9 @@ extern "C"
         DEMI_OPC_CONNECT,     /**< Connect operation. */
         DEMI_OPC_CLOSE,       /**< Close operation. */
         DEMI_OPC_FAILED,      /**< Operation failed.  */
+        DEMI_OPC_SPLICE,      /**< Splice operation. The number of bytes moved is stored in qr_ret. */
+        DEMI_OPC_POP_STREAM,  /**< Streaming pop operation. The number of bytes streamed is stored in qr_ret. */
+        DEMI_OPC_POP_BATCH,   /**< Batched pop operation. The number of datagrams is stored in qr_ret. */
     } demi_opcode_t;
 
     /**
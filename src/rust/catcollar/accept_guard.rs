@@ -0,0 +1,158 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::SharedObject;
+use ::std::{
+    fs::File,
+    ops::{
+        Deref,
+        DerefMut,
+    },
+    os::unix::prelude::{
+        IntoRawFd,
+        RawFd,
+    },
+    ptr,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// File descriptor that is held in reserve, so that a pending connection can still be accepted when the process runs
+/// out of file descriptors.
+pub struct AcceptGuard {
+    /// Reserved file descriptor, if any.
+    fd: Option<RawFd>,
+}
+
+/// Accept guard that is shared by all accept coroutines of a LibOS.
+#[derive(Clone)]
+pub struct SharedAcceptGuard(SharedObject<AcceptGuard>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl AcceptGuard {
+    /// Reserves a file descriptor. This is best effort: the guard is empty if no file descriptor is available.
+    fn reserve() -> Option<RawFd> {
+        // The file is closed on exec, so that the reserved file descriptor does not leak into child processes.
+        match File::open("/dev/null") {
+            Ok(file) => Some(file.into_raw_fd()),
+            Err(e) => {
+                warn!("failed to reserve a file descriptor ({:?})", e);
+                None
+            },
+        }
+    }
+}
+
+impl SharedAcceptGuard {
+    /// Creates an accept guard.
+    pub fn new() -> Self {
+        Self(SharedObject::new(AcceptGuard {
+            fd: AcceptGuard::reserve(),
+        }))
+    }
+
+    /// Accepts the connection that is pending on the listening socket `fd` and closes it right away, so that its peer
+    /// is notified and the listener does not keep on failing on it. The reserved file descriptor is released to make
+    /// room for the connection, and reserved again afterwards. Returns true if a connection was shed.
+    pub fn shed(&mut self, fd: RawFd) -> bool {
+        let reserved_fd: RawFd = match self.fd.take() {
+            Some(reserved_fd) => reserved_fd,
+            None => return false,
+        };
+        unsafe { libc::close(reserved_fd) };
+
+        let shed: bool = match unsafe { libc::accept4(fd, ptr::null_mut(), ptr::null_mut(), libc::SOCK_CLOEXEC) } {
+            new_fd if new_fd >= 0 => {
+                warn!("out of file descriptors, dropping connection ({:?})", new_fd);
+                unsafe { libc::close(new_fd) };
+                true
+            },
+            _ => false,
+        };
+
+        self.fd = AcceptGuard::reserve();
+        shed
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Drop for AcceptGuard {
+    fn drop(&mut self) {
+        if let Some(fd) = self.fd.take() {
+            unsafe { libc::close(fd) };
+        }
+    }
+}
+
+impl Deref for SharedAcceptGuard {
+    type Target = AcceptGuard;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedAcceptGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::SharedAcceptGuard;
+    use ::anyhow::Result;
+    use ::std::{
+        io::{
+            ErrorKind,
+            Read,
+        },
+        net::{
+            TcpListener,
+            TcpStream,
+        },
+        os::unix::prelude::AsRawFd,
+    };
+
+    // Sheds a pending connection and checks that its peer sees it closed while the guard is reserved again.
+    #[test]
+    fn shed_closes_pending_connection() -> Result<()> {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+        let mut client: TcpStream = TcpStream::connect(listener.local_addr()?)?;
+
+        let mut guard: SharedAcceptGuard = SharedAcceptGuard::new();
+        crate::ensure_eq!(guard.fd.is_some(), true);
+        crate::ensure_eq!(guard.shed(listener.as_raw_fd()), true);
+        crate::ensure_eq!(guard.fd.is_some(), true);
+
+        // The peer sees the connection closed.
+        let mut buf: [u8; 1] = [0; 1];
+        match client.read(&mut buf) {
+            Ok(0) => {},
+            Err(e) if e.kind() == ErrorKind::ConnectionReset => {},
+            result => anyhow::bail!("connection should be closed (result={:?})", result),
+        }
+
+        // Nothing is left pending on the listener.
+        listener.set_nonblocking(true)?;
+        crate::ensure_eq!(listener.accept().err().map(|e| e.kind()), Some(ErrorKind::WouldBlock));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::demikernel::config::Config;
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+/// Catcollar associated functions for Demikernel configuration object.
+impl Config {
+    /// Reads the "TCP_NODELAY on accepted sockets" parameter from the underlying configuration file. If it is not set,
+    /// TCP_NODELAY is enabled.
+    pub fn accept_tcp_nodelay(&self) -> bool {
+        self.0["catcollar"]["accept_tcp_nodelay"].as_bool().unwrap_or(true)
+    }
+
+    /// Reads the "SO_REUSEPORT on accepted sockets" parameter from the underlying configuration file. If it is not
+    /// set, SO_REUSEPORT is enabled.
+    pub fn accept_so_reuseport(&self) -> bool {
+        self.0["catcollar"]["accept_so_reuseport"].as_bool().unwrap_or(true)
+    }
+
+    /// Reads the "close-on-exec on accepted sockets" parameter from the underlying configuration file. If it is not
+    /// set, accepted sockets are closed on exec, so that they do not leak into child processes.
+    pub fn accept_cloexec(&self) -> bool {
+        self.0["catcollar"]["accept_cloexec"].as_bool().unwrap_or(true)
+    }
+}
@@ -17,13 +17,16 @@ use crate::{
     runtime::{
         fail::Fail,
         liburing,
-        memory::DemiBuffer,
+        memory::{
+            DemiBuffer,
+            DemiBufferChain,
+        },
     },
 };
 use ::std::{
     ffi::{
         c_void,
-        CString,
+        CStr,
     },
     mem,
     mem::MaybeUninit,
@@ -36,8 +39,30 @@ use ::std::{
         self,
         null_mut,
     },
+    time::Duration,
 };
 
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Links a submission queue entry to the next one (see `IOSQE_IO_LINK` in `linux/io_uring.h`).
+const IOSQE_IO_LINK: u32 = 1 << 2;
+
+/// Lets the kernel pick a buffer from a provided-buffer group (see `IOSQE_BUFFER_SELECT` in `linux/io_uring.h`).
+const IOSQE_BUFFER_SELECT: u32 = 1 << 5;
+
+/// Set on completions that consumed a provided buffer (see `IORING_CQE_F_BUFFER` in `linux/io_uring.h`).
+const IORING_CQE_F_BUFFER: u32 = 1 << 0;
+
+/// Position of the selected buffer ID in the flags of a completion (see `IORING_CQE_BUFFER_SHIFT` in
+/// `linux/io_uring.h`).
+const IORING_CQE_BUFFER_SHIFT: u32 = 16;
+
+/// Offset of the `buf_group` field in a submission queue entry (see `struct io_uring_sqe` in `linux/io_uring.h`). The
+/// field lives in an anonymous union, so it is written through its offset instead of its generated binding name.
+const IO_URING_SQE_BUF_GROUP_OFFSET: usize = 40;
+
 //==============================================================================
 // Structures
 //==============================================================================
@@ -53,8 +78,10 @@ pub struct IoUring {
 //==============================================================================
 
 impl IoUring {
-    /// Instantiates an IO user ring.
+    /// Instantiates an IO user ring. Fails with `ENOSYS` if the kernel does not support io_uring.
     pub fn new(nentries: u32) -> Result<Self, Fail> {
+        Self::probe()?;
+
         unsafe {
             let mut params: MaybeUninit<liburing::io_uring_params> = MaybeUninit::zeroed();
             let mut io_uring: MaybeUninit<liburing::io_uring> = MaybeUninit::zeroed();
@@ -62,7 +89,7 @@ impl IoUring {
             // Failed to initialize io_uring structure.
             if ret < 0 {
                 let errno: i32 = -ret;
-                let strerror: CString = CString::from_raw(libc::strerror(errno));
+                let strerror: &CStr = CStr::from_ptr(libc::strerror(errno));
                 let cause: &str = strerror.to_str().unwrap_or("failed to initialize io_uring");
                 return Err(Fail::new(errno, cause));
             }
@@ -73,10 +100,50 @@ impl IoUring {
         }
     }
 
-    /// Pushes a buffer to the target IO user ring.
-    pub fn push(&mut self, sockfd: RawFd, buf: DemiBuffer) -> Result<*mut liburing::msghdr, Fail> {
-        let len: usize = buf.len();
-        let data_ptr: *const u8 = buf.as_ptr();
+    /// Checks whether io_uring is available. The io_uring_setup() system call is issued with an empty ring, which
+    /// kernels that support io_uring reject with `EINVAL` rather than `ENOSYS`. No ring is ever created.
+    pub fn probe() -> Result<(), Fail> {
+        let ret: libc::c_long = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                0 as u32,
+                null_mut::<liburing::io_uring_params>(),
+            )
+        };
+        if ret >= 0 {
+            // Should not happen, but do not leak the ring.
+            unsafe { libc::close(ret as c_int) };
+            return Ok(());
+        }
+        let errno: c_int = unsafe { *libc::__errno_location() };
+        Self::check_probe_errno(errno)
+    }
+
+    /// Translates the error number of the io_uring_setup() probe into a descriptive failure.
+    fn check_probe_errno(errno: c_int) -> Result<(), Fail> {
+        match errno {
+            libc::ENOSYS => {
+                let cause: &str = "io_uring is not supported by this kernel";
+                error!("probe(): {}", cause);
+                Err(Fail::new(libc::ENOSYS, cause))
+            },
+            libc::EPERM => {
+                let cause: &str = "io_uring is disabled on this system (see the kernel.io_uring_disabled sysctl)";
+                error!("probe(): {}", cause);
+                Err(Fail::new(libc::EPERM, cause))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Pushes a chain of buffers to the target IO user ring. The buffers are gathered by the kernel, so that they are
+    /// sent as a single message without being merged first. The completion of the operation carries `user_data`.
+    pub fn push(
+        &mut self,
+        sockfd: RawFd,
+        bufs: &DemiBufferChain,
+        user_data: u64,
+    ) -> Result<*mut liburing::msghdr, Fail> {
         let io_uring: &mut liburing::io_uring = &mut self.io_uring;
 
         unsafe {
@@ -88,38 +155,99 @@ impl IoUring {
                 return Err(Fail::new(errno, "operation failed"));
             }
 
-            // Submit operation.
-            let iov: Box<liburing::iovec> = Box::new(liburing::iovec {
-                iov_base: data_ptr as *mut c_void,
-                iov_len: len as u64,
-            });
-            let iov_ptr: *mut liburing::iovec = Box::into_raw(iov);
+            // Submit operation, with one I/O vector for each buffer of the chain.
+            let (iov_ptr, iovcnt): (*mut liburing::iovec, usize) = Self::chain_to_iovecs(bufs);
             let msg: Box<liburing::msghdr> = Box::new(liburing::msghdr {
                 msg_name: ptr::null_mut() as *mut _,
                 msg_namelen: 0,
                 msg_iov: iov_ptr,
-                msg_iovlen: 1,
+                msg_iovlen: iovcnt as _,
                 msg_control: ptr::null_mut() as *mut _,
                 msg_controllen: 0,
                 msg_flags: 0,
             });
             let msg_ptr: *mut liburing::msghdr = Box::into_raw(msg);
-            liburing::io_uring_sqe_set_data(sqe, msg_ptr as *mut c_void);
+            liburing::io_uring_sqe_set_data(sqe, user_data as *mut c_void);
             liburing::io_uring_prep_sendmsg(sqe, sockfd, msg_ptr, 0);
-            if liburing::io_uring_submit(io_uring) != 1 {
-                return Err(Fail::new(libc::EIO, "failed to submit push operation"));
+            Self::check_submitted(
+                liburing::io_uring_submit(io_uring),
+                1,
+                msg_ptr,
+                "failed to submit push operation",
+            )
+        }
+    }
+
+    /// Pushes a chain of buffers to the target IO user ring. The operation is linked to a timeout, so that the kernel
+    /// cancels it with `ECANCELED` if it does not complete within `timeout`. The completion of the operation carries
+    /// `user_data`, while the completion of the timeout itself carries no user data.
+    pub fn push_timeout(
+        &mut self,
+        sockfd: RawFd,
+        bufs: &DemiBufferChain,
+        timeout: Duration,
+        user_data: u64,
+    ) -> Result<*mut liburing::msghdr, Fail> {
+        let io_uring: &mut liburing::io_uring = &mut self.io_uring;
+
+        unsafe {
+            // Allocate submission queue entries for the operation and its timeout.
+            let sqe: *mut liburing::io_uring_sqe = liburing::io_uring_get_sqe(io_uring);
+            if sqe.is_null() {
+                let errno: libc::c_int = *libc::__errno_location();
+                error!("push_timeout(): failed to get sqe (errno={:?})", errno);
+                return Err(Fail::new(errno, "operation failed"));
+            }
+            let timeout_sqe: *mut liburing::io_uring_sqe = liburing::io_uring_get_sqe(io_uring);
+            if timeout_sqe.is_null() {
+                let errno: libc::c_int = *libc::__errno_location();
+                error!("push_timeout(): failed to get sqe (errno={:?})", errno);
+                // The first entry is already taken, so turn it into a no-op that gets ignored on completion.
+                liburing::io_uring_prep_nop(sqe);
+                liburing::io_uring_sqe_set_data(sqe, ptr::null_mut());
+                liburing::io_uring_submit(io_uring);
+                return Err(Fail::new(errno, "operation failed"));
             }
 
-            Ok(msg_ptr)
+            // Submit operation, with one I/O vector for each buffer of the chain.
+            let (iov_ptr, iovcnt): (*mut liburing::iovec, usize) = Self::chain_to_iovecs(bufs);
+            let msg: Box<liburing::msghdr> = Box::new(liburing::msghdr {
+                msg_name: ptr::null_mut() as *mut _,
+                msg_namelen: 0,
+                msg_iov: iov_ptr,
+                msg_iovlen: iovcnt as _,
+                msg_control: ptr::null_mut() as *mut _,
+                msg_controllen: 0,
+                msg_flags: 0,
+            });
+            let msg_ptr: *mut liburing::msghdr = Box::into_raw(msg);
+            liburing::io_uring_sqe_set_data(sqe, user_data as *mut c_void);
+            liburing::io_uring_prep_sendmsg(sqe, sockfd, msg_ptr, 0);
+            liburing::io_uring_sqe_set_flags(sqe, IOSQE_IO_LINK);
+
+            // Submit timeout. The kernel copies the timespec on submission, so it may live on the stack.
+            let mut ts: liburing::__kernel_timespec = liburing::__kernel_timespec {
+                tv_sec: timeout.as_secs() as _,
+                tv_nsec: timeout.subsec_nanos() as _,
+            };
+            liburing::io_uring_prep_link_timeout(timeout_sqe, &mut ts, 0);
+            liburing::io_uring_sqe_set_data(timeout_sqe, ptr::null_mut());
+            Self::check_submitted(
+                liburing::io_uring_submit(io_uring),
+                2,
+                msg_ptr,
+                "failed to submit push operation",
+            )
         }
     }
 
-    /// Pushes a buffer to the target IO user ring.
+    /// Pushes a buffer to the target IO user ring. The completion of the operation carries `user_data`.
     pub fn pushto(
         &mut self,
         sockfd: RawFd,
         addr: SocketAddrV4,
         buf: DemiBuffer,
+        user_data: u64,
     ) -> Result<*mut liburing::msghdr, Fail> {
         let len: usize = buf.len();
         let data_ptr: *const u8 = buf.as_ptr();
@@ -138,11 +266,11 @@ impl IoUring {
             }
 
             // Submit operation.
-            let iov: Box<liburing::iovec> = Box::new(liburing::iovec {
+            let iovs: Box<[liburing::iovec]> = Box::new([liburing::iovec {
                 iov_base: data_ptr as *mut c_void,
                 iov_len: len as u64,
-            });
-            let iov_ptr: *mut liburing::iovec = Box::into_raw(iov);
+            }]);
+            let iov_ptr: *mut liburing::iovec = Box::into_raw(iovs) as *mut liburing::iovec;
             let msg: Box<liburing::msghdr> = Box::new(liburing::msghdr {
                 msg_name: saddr_ptr as *mut c_void,
                 msg_namelen: addrlen as u32,
@@ -153,18 +281,19 @@ impl IoUring {
                 msg_flags: 0,
             });
             let msg_ptr: *mut liburing::msghdr = Box::into_raw(msg);
-            liburing::io_uring_sqe_set_data(sqe, msg_ptr as *mut c_void);
+            liburing::io_uring_sqe_set_data(sqe, user_data as *mut c_void);
             liburing::io_uring_prep_sendmsg(sqe, sockfd, msg_ptr, 0);
-            if liburing::io_uring_submit(io_uring) != 1 {
-                return Err(Fail::new(libc::EIO, "failed to submit pushto operation"));
-            }
-
-            Ok(msg_ptr)
+            Self::check_submitted(
+                liburing::io_uring_submit(io_uring),
+                1,
+                msg_ptr,
+                "failed to submit pushto operation",
+            )
         }
     }
 
-    /// Pops a buffer from the target IO user ring.
-    pub fn pop(&mut self, sockfd: RawFd, buf: DemiBuffer) -> Result<*mut liburing::msghdr, Fail> {
+    /// Pops a buffer from the target IO user ring. The completion of the operation carries `user_data`.
+    pub fn pop(&mut self, sockfd: RawFd, buf: DemiBuffer, user_data: u64) -> Result<*mut liburing::msghdr, Fail> {
         let len: usize = buf.len();
         let data_ptr: *const u8 = buf.as_ptr();
         let io_uring: &mut liburing::io_uring = &mut self.io_uring;
@@ -179,11 +308,11 @@ impl IoUring {
             }
 
             // Submit operation.
-            let iov: Box<liburing::iovec> = Box::new(liburing::iovec {
+            let iovs: Box<[liburing::iovec]> = Box::new([liburing::iovec {
                 iov_base: data_ptr as *mut c_void,
                 iov_len: len as u64,
-            });
-            let iov_ptr: *mut liburing::iovec = Box::into_raw(iov);
+            }]);
+            let iov_ptr: *mut liburing::iovec = Box::into_raw(iovs) as *mut liburing::iovec;
             let msg: Box<liburing::msghdr> = Box::new(liburing::msghdr {
                 msg_name: ptr::null_mut() as *mut _,
                 msg_namelen: 0,
@@ -194,18 +323,229 @@ impl IoUring {
                 msg_flags: 0,
             });
             let msg_ptr: *mut liburing::msghdr = Box::into_raw(msg);
-            liburing::io_uring_sqe_set_data(sqe, msg_ptr as *mut c_void);
+            liburing::io_uring_sqe_set_data(sqe, user_data as *mut c_void);
             liburing::io_uring_prep_recvmsg(sqe, sockfd, msg_ptr as *mut liburing::msghdr, 0);
+            Self::check_submitted(
+                liburing::io_uring_submit(io_uring),
+                1,
+                msg_ptr,
+                "failed to submit pop operation",
+            )
+        }
+    }
+
+    /// Pops data from the target IO user ring into multiple buffers at once. The completion of the operation carries
+    /// `user_data`.
+    pub fn pop_vectored(
+        &mut self,
+        sockfd: RawFd,
+        segments: &[(*mut u8, usize)],
+        user_data: u64,
+    ) -> Result<*mut liburing::msghdr, Fail> {
+        let io_uring: &mut liburing::io_uring = &mut self.io_uring;
+
+        unsafe {
+            // Allocate a submission queue entry.
+            let sqe: *mut liburing::io_uring_sqe = liburing::io_uring_get_sqe(io_uring);
+            if sqe.is_null() {
+                let errno: libc::c_int = *libc::__errno_location();
+                error!("pop_vectored(): failed to get sqe (errno={:?})", errno);
+                return Err(Fail::new(errno, "operation failed"));
+            }
+
+            // Submit operation.
+            let iovs: Box<[liburing::iovec]> = segments
+                .iter()
+                .map(|(data_ptr, len)| liburing::iovec {
+                    iov_base: *data_ptr as *mut c_void,
+                    iov_len: *len as u64,
+                })
+                .collect();
+            let iovcnt: usize = iovs.len();
+            let iov_ptr: *mut liburing::iovec = Box::into_raw(iovs) as *mut liburing::iovec;
+            // The message header is not used by the read operation itself, but it keeps track of the I/O vectors, so
+            // that they can be released once the operation completes.
+            let msg: Box<liburing::msghdr> = Box::new(liburing::msghdr {
+                msg_name: ptr::null_mut() as *mut _,
+                msg_namelen: 0,
+                msg_iov: iov_ptr,
+                msg_iovlen: iovcnt as _,
+                msg_control: ptr::null_mut() as *mut _,
+                msg_controllen: 0,
+                msg_flags: 0,
+            });
+            let msg_ptr: *mut liburing::msghdr = Box::into_raw(msg);
+            liburing::io_uring_sqe_set_data(sqe, user_data as *mut c_void);
+            liburing::io_uring_prep_readv(sqe, sockfd, iov_ptr, iovcnt as u32, 0);
+            Self::check_submitted(
+                liburing::io_uring_submit(io_uring),
+                1,
+                msg_ptr,
+                "failed to submit pop operation",
+            )
+        }
+    }
+
+    /// Builds one I/O vector for each buffer of `bufs`. The vectors are leaked, and are released along with the message
+    /// header that refers to them once the operation completes.
+    fn chain_to_iovecs(bufs: &DemiBufferChain) -> (*mut liburing::iovec, usize) {
+        let iovs: Box<[liburing::iovec]> = bufs
+            .iter()
+            .map(|buf| liburing::iovec {
+                iov_base: buf.as_ptr() as *mut c_void,
+                iov_len: buf.len() as u64,
+            })
+            .collect();
+        let iovcnt: usize = iovs.len();
+        (Box::into_raw(iovs) as *mut liburing::iovec, iovcnt)
+    }
+
+    /// Checks that all `expected` submission queue entries of an operation whose message header is `msg_ptr` made it
+    /// to the kernel, given that `submitted` did. Otherwise, the operation never completes, so its message header is
+    /// released here.
+    unsafe fn check_submitted(
+        submitted: c_int,
+        expected: c_int,
+        msg_ptr: *mut liburing::msghdr,
+        cause: &str,
+    ) -> Result<*mut liburing::msghdr, Fail> {
+        if submitted != expected {
+            error!(
+                "check_submitted(): {} (submitted={:?}, expected={:?})",
+                cause, submitted, expected
+            );
+            Self::release_msg(msg_ptr);
+            return Err(Fail::new(libc::EIO, cause));
+        }
+        Ok(msg_ptr)
+    }
+
+    /// Releases the message header `msg_ptr` of an operation, along with its I/O vectors, once the kernel is done with
+    /// them. Message headers and their I/O vectors are always allocated as boxes, and boxed slices respectively.
+    pub unsafe fn release_msg(msg_ptr: *mut liburing::msghdr) {
+        let msg: Box<liburing::msghdr> = Box::from_raw(msg_ptr);
+        let _: Box<[liburing::iovec]> =
+            Box::from_raw(ptr::slice_from_raw_parts_mut(msg.msg_iov, msg.msg_iovlen as usize));
+    }
+
+    /// Hands `count` buffers of `size` bytes each, laid out back-to-back starting at `addr`, to the provided-buffer group
+    /// `bgid`. The buffers are identified by consecutive IDs starting at `bid`. The kernel takes ownership of the
+    /// buffers until it selects them for a receive operation. The completion of this operation carries no user data.
+    pub fn provide_buffers(&mut self, addr: *mut u8, size: usize, count: u16, bgid: u16, bid: u16) -> Result<(), Fail> {
+        let io_uring: &mut liburing::io_uring = &mut self.io_uring;
+
+        unsafe {
+            // Allocate a submission queue entry.
+            let sqe: *mut liburing::io_uring_sqe = liburing::io_uring_get_sqe(io_uring);
+            if sqe.is_null() {
+                let errno: libc::c_int = *libc::__errno_location();
+                error!("provide_buffers(): failed to get sqe (errno={:?})", errno);
+                return Err(Fail::new(errno, "operation failed"));
+            }
+
+            // Submit operation.
+            liburing::io_uring_prep_provide_buffers(
+                sqe,
+                addr as *mut c_void,
+                size as c_int,
+                count as c_int,
+                bgid as c_int,
+                bid as c_int,
+            );
+            liburing::io_uring_sqe_set_data(sqe, ptr::null_mut());
             if liburing::io_uring_submit(io_uring) != 1 {
-                return Err(Fail::new(libc::EIO, "failed to submit pop operation"));
+                return Err(Fail::new(libc::EIO, "failed to submit provide buffers operation"));
             }
 
-            Ok(msg_ptr)
+            Ok(())
         }
     }
 
-    /// Waits for an operation to complete in the target IO user ring.
-    pub fn wait(&mut self) -> Result<(*mut liburing::msghdr, i32), Fail> {
+    /// Pops up to `len` bytes from the target IO user ring into a buffer that the kernel selects from the
+    /// provided-buffer group `bgid`. The completion of the operation carries `user_data`, and the ID of the selected
+    /// buffer is reported in its flags.
+    pub fn pop_selected(
+        &mut self,
+        sockfd: RawFd,
+        len: usize,
+        bgid: u16,
+        user_data: u64,
+    ) -> Result<*mut liburing::msghdr, Fail> {
+        let io_uring: &mut liburing::io_uring = &mut self.io_uring;
+
+        unsafe {
+            // Allocate a submission queue entry.
+            let sqe: *mut liburing::io_uring_sqe = liburing::io_uring_get_sqe(io_uring);
+            if sqe.is_null() {
+                let errno: libc::c_int = *libc::__errno_location();
+                error!("pop_selected(): failed to get sqe (errno={:?})", errno);
+                return Err(Fail::new(errno, "operation failed"));
+            }
+
+            // Submit operation.
+            // The message header is not used by the receive operation itself, but it is released along with those of
+            // other operations. It has no I/O vectors, as the kernel picks the buffer.
+            let iovs: Box<[liburing::iovec]> = Box::new([]);
+            let msg: Box<liburing::msghdr> = Box::new(liburing::msghdr {
+                msg_name: ptr::null_mut() as *mut _,
+                msg_namelen: 0,
+                msg_iov: Box::into_raw(iovs) as *mut liburing::iovec,
+                msg_iovlen: 0,
+                msg_control: ptr::null_mut() as *mut _,
+                msg_controllen: 0,
+                msg_flags: 0,
+            });
+            let msg_ptr: *mut liburing::msghdr = Box::into_raw(msg);
+            liburing::io_uring_sqe_set_data(sqe, user_data as *mut c_void);
+            liburing::io_uring_prep_recv(sqe, sockfd, ptr::null_mut(), len as _, 0);
+            liburing::io_uring_sqe_set_flags(sqe, IOSQE_BUFFER_SELECT);
+            ptr::write_unaligned((sqe as *mut u8).add(IO_URING_SQE_BUF_GROUP_OFFSET) as *mut u16, bgid);
+            Self::check_submitted(
+                liburing::io_uring_submit(io_uring),
+                1,
+                msg_ptr,
+                "failed to submit pop operation",
+            )
+        }
+    }
+
+    /// Asks the kernel to cancel the operation that carries `user_data`. If it is still in flight, the operation then
+    /// completes with `ECANCELED`. The completion of the cancellation itself carries no user data.
+    pub fn cancel(&mut self, user_data: u64) -> Result<(), Fail> {
+        let io_uring: &mut liburing::io_uring = &mut self.io_uring;
+
+        unsafe {
+            // Allocate a submission queue entry.
+            let sqe: *mut liburing::io_uring_sqe = liburing::io_uring_get_sqe(io_uring);
+            if sqe.is_null() {
+                let errno: libc::c_int = *libc::__errno_location();
+                error!("cancel(): failed to get sqe (errno={:?})", errno);
+                return Err(Fail::new(errno, "operation failed"));
+            }
+
+            // Submit cancellation.
+            liburing::io_uring_prep_cancel(sqe, user_data as *mut c_void, 0);
+            liburing::io_uring_sqe_set_data(sqe, ptr::null_mut());
+            if liburing::io_uring_submit(io_uring) != 1 {
+                return Err(Fail::new(libc::EIO, "failed to submit cancel operation"));
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Extracts the ID of the provided buffer that was consumed by an operation from the flags of its completion.
+    pub fn selected_buffer(flags: u32) -> Option<u16> {
+        if flags & IORING_CQE_F_BUFFER != 0 {
+            Some((flags >> IORING_CQE_BUFFER_SHIFT) as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Waits for an operation to complete in the target IO user ring. Along with the user data of the operation, this
+    /// returns the result and the flags of the completion.
+    pub fn wait(&mut self) -> Result<(u64, i32, u32), Fail> {
         let io_uring: &mut liburing::io_uring = &mut self.io_uring;
         unsafe {
             let mut cqe_ptr: *mut liburing::io_uring_cqe = null_mut();
@@ -217,12 +557,109 @@ impl IoUring {
                 return Err(Fail::new(errno, "operation in progress"));
             } else if wait_nr == 0 {
                 let size: i32 = (*cqe_ptr).res;
-                let msg_ptr: *mut liburing::msghdr = liburing::io_uring_cqe_get_data(cqe_ptr) as *mut liburing::msghdr;
+                let flags: u32 = (*cqe_ptr).flags;
+                let user_data: u64 = liburing::io_uring_cqe_get_data(cqe_ptr) as u64;
                 liburing::io_uring_cqe_seen(io_uring, cqe_ptr);
-                return Ok((msg_ptr, size));
+                return Ok((user_data, size, flags));
             }
         }
 
         unreachable!("should not happen")
     }
+
+    /// Waits for an operation to complete in the target IO user ring, like [wait], but gives up once `timeout` expires,
+    /// if one is given. The calling thread sleeps in the kernel in the meantime. This returns `None` on timeout.
+    pub fn wait_timeout(&mut self, timeout: Option<Duration>) -> Result<Option<(u64, i32, u32)>, Fail> {
+        let timeout: Duration = match timeout {
+            Some(timeout) => timeout,
+            None => return self.wait().map(Some),
+        };
+        let io_uring: &mut liburing::io_uring = &mut self.io_uring;
+        unsafe {
+            let mut cqe_ptr: *mut liburing::io_uring_cqe = null_mut();
+            let cqe_ptr_ptr: *mut *mut liburing::io_uring_cqe = ptr::addr_of_mut!(cqe_ptr);
+            let mut ts: liburing::__kernel_timespec = liburing::__kernel_timespec {
+                tv_sec: timeout.as_secs() as _,
+                tv_nsec: timeout.subsec_nanos() as _,
+            };
+            let wait_nr: c_int = liburing::io_uring_wait_cqe_timeout(io_uring, cqe_ptr_ptr, &mut ts);
+            if wait_nr == -libc::ETIME {
+                return Ok(None);
+            } else if wait_nr < 0 {
+                let errno: i32 = -wait_nr;
+                warn!("io_uring_wait_cqe_timeout() failed ({:?})", errno);
+                return Err(Fail::new(errno, "operation in progress"));
+            }
+            let size: i32 = (*cqe_ptr).res;
+            let flags: u32 = (*cqe_ptr).flags;
+            let user_data: u64 = liburing::io_uring_cqe_get_data(cqe_ptr) as u64;
+            liburing::io_uring_cqe_seen(io_uring, cqe_ptr);
+            Ok(Some((user_data, size, flags)))
+        }
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::IoUring;
+    use crate::runtime::{
+        liburing,
+        memory::{
+            DemiBuffer,
+            DemiBufferChain,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::ptr;
+
+    // Kernels without io_uring are reported with a clean error instead of a panic.
+    #[test]
+    fn probe_unsupported_kernel() -> Result<()> {
+        match IoUring::check_probe_errno(libc::ENOSYS) {
+            Err(e) if e.errno == libc::ENOSYS => {},
+            r => anyhow::bail!("probe should have failed with ENOSYS (result={:?})", r),
+        }
+        match IoUring::check_probe_errno(libc::EPERM) {
+            Err(e) if e.errno == libc::EPERM => {},
+            r => anyhow::bail!("probe should have failed with EPERM (result={:?})", r),
+        }
+        crate::ensure_eq!(IoUring::check_probe_errno(libc::EINVAL).is_ok(), true);
+        Ok(())
+    }
+
+    // Operations that do not make it to the kernel give their message header back.
+    #[test]
+    fn check_submitted_releases_msg() -> Result<()> {
+        let mut bufs: DemiBufferChain = DemiBufferChain::new();
+        bufs.push(DemiBuffer::new(8));
+        bufs.push(DemiBuffer::new(16));
+        let (iov_ptr, iovcnt): (*mut liburing::iovec, usize) = IoUring::chain_to_iovecs(&bufs);
+        let msg_ptr: *mut liburing::msghdr = Box::into_raw(Box::new(liburing::msghdr {
+            msg_name: ptr::null_mut(),
+            msg_namelen: 0,
+            msg_iov: iov_ptr,
+            msg_iovlen: iovcnt as _,
+            msg_control: ptr::null_mut(),
+            msg_controllen: 0,
+            msg_flags: 0,
+        }));
+        match unsafe { IoUring::check_submitted(0, 1, msg_ptr, "failed to submit operation") } {
+            Err(e) if e.errno == libc::EIO => {},
+            r => anyhow::bail!("submission should have failed with EIO (result={:?})", r),
+        }
+        Ok(())
+    }
+
+    // The ID of a provided buffer is only reported by completions that consumed one.
+    #[test]
+    fn selected_buffer() -> Result<()> {
+        crate::ensure_eq!(IoUring::selected_buffer(0), None);
+        crate::ensure_eq!(IoUring::selected_buffer((7 << 16) | 1), Some(7));
+        crate::ensure_eq!(IoUring::selected_buffer(7 << 16), None);
+        Ok(())
+    }
 }
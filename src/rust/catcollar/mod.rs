@@ -1,8 +1,12 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod accept_guard;
+mod config;
 mod iouring;
 mod queue;
+mod rate_limiter;
+mod receiver;
 mod runtime;
 
 //======================================================================================================================
@@ -10,7 +14,10 @@ mod runtime;
 //======================================================================================================================
 
 pub use self::{
+    accept_guard::SharedAcceptGuard,
     queue::CatcollarQueue,
+    rate_limiter::SharedRateLimiter,
+    receiver::SharedContinuousReceiver,
     runtime::{
         RequestId,
         SharedIoUringRuntime,
@@ -24,7 +31,12 @@ pub use self::{
 use crate::{
     demikernel::config::Config,
     pal::{
-        constants::SOMAXCONN,
+        constants::{
+            SOMAXCONN,
+            TCP_STATE_CLOSE,
+            TCP_STATE_FIN_WAIT2,
+            TCP_STATE_TIME_WAIT,
+        },
         data_structures::{
             SockAddr,
             SockAddrIn,
@@ -37,23 +49,35 @@ use crate::{
         limits,
         memory::{
             DemiBuffer,
+            DemiBufferChain,
             MemoryRuntime,
         },
         network::unwrap_socketaddr,
         queue::{
+            IoQueue,
+            NetworkQueue,
             Operation,
             OperationResult,
             QDesc,
             QToken,
             QType,
+            QueueInfo,
+            Readiness,
         },
         scheduler::Yielder,
-        types::demi_sgarray_t,
+        timer::SharedTimer,
+        types::{
+            demi_qresult_t,
+            demi_sgarray_t,
+            demi_sgaseg_t,
+            DEMI_SGARRAY_MAXLEN,
+        },
         DemiRuntime,
         SharedDemiRuntime,
     },
 };
 use ::std::{
+    cmp,
     mem,
     net::{
         SocketAddr,
@@ -61,30 +85,110 @@ use ::std::{
     },
     os::unix::prelude::RawFd,
     pin::Pin,
+    ptr::{
+        self,
+        NonNull,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Highest socket priority that can be set without the CAP_NET_ADMIN capability.
+const SO_PRIORITY_MAX: u32 = 6;
+
+/// Longest time that the LibOS sleeps for when it has nothing to run. Coroutines that wait on timers are only woken up
+/// once the clock is advanced, so this bounds how late they may be.
+const IDLE_SLEEP_MAX: Duration = Duration::from_millis(1);
+
+/// Shortest time that accepting is backed off for when the process runs out of file descriptors.
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(10);
+
+/// Longest time that accepting is backed off for when the process runs out of file descriptors.
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
 
+/// Socket options applied to accepted sockets. Accepted sockets are always non-blocking.
+#[derive(Clone, Copy, Debug)]
+pub struct AcceptOptions {
+    /// Enable TCP_NODELAY?
+    pub tcp_nodelay: bool,
+    /// Enable SO_REUSEPORT?
+    pub so_reuseport: bool,
+    /// Close on exec?
+    pub cloexec: bool,
+}
+
+/// Deadline of an operation, as an instant on the clock of a timer.
+type Deadline = (SharedTimer, Instant);
+
 /// Catcollar LibOS
 pub struct CatcollarLibOS {
     /// Shared DemiRuntime.
     runtime: SharedDemiRuntime,
     /// Underlying runtime.
     transport: SharedIoUringRuntime,
+    /// Options for accepted sockets.
+    accept_options: AcceptOptions,
+    /// Reserved file descriptor to shed connections with when the process runs out of file descriptors.
+    accept_guard: SharedAcceptGuard,
 }
 
 //======================================================================================================================
 // Associated Functions
 //======================================================================================================================
 
+/// Associate Functions for Accept Options
+impl AcceptOptions {
+    /// Returns the flags to accept a socket with.
+    fn flags(&self) -> libc::c_int {
+        if self.cloexec {
+            libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC
+        } else {
+            libc::SOCK_NONBLOCK
+        }
+    }
+
+    /// Applies these options to an accepted socket. Options are set explicitly, instead of being inherited from the
+    /// listening socket.
+    fn apply(&self, fd: RawFd) {
+        unsafe {
+            if linux::set_tcp_nodelay(fd, self.tcp_nodelay) != 0 {
+                let errno: libc::c_int = *libc::__errno_location();
+                warn!("cannot set TCP_NONDELAY option (errno={:?})", errno);
+            }
+            if linux::set_so_reuseport(fd, self.so_reuseport) != 0 {
+                let errno: libc::c_int = *libc::__errno_location();
+                warn!("cannot set SO_REUSEPORT option (errno={:?})", errno);
+            }
+        }
+    }
+}
+
 /// Associate Functions for Catcollar LibOS
 impl CatcollarLibOS {
     /// Instantiates a Catcollar LibOS.
-    pub fn new(_config: &Config, runtime: SharedDemiRuntime) -> Self {
-        let transport: SharedIoUringRuntime = SharedIoUringRuntime::default();
-        Self { runtime, transport }
+    pub fn new(config: &Config, runtime: SharedDemiRuntime) -> Result<Self, Fail> {
+        let transport: SharedIoUringRuntime = SharedIoUringRuntime::new()?;
+        let accept_options: AcceptOptions = AcceptOptions {
+            tcp_nodelay: config.accept_tcp_nodelay(),
+            so_reuseport: config.accept_so_reuseport(),
+            cloexec: config.accept_cloexec(),
+        };
+        Ok(Self {
+            runtime,
+            transport,
+            accept_options,
+            accept_guard: SharedAcceptGuard::new(),
+        })
     }
 
     /// Creates a socket.
@@ -101,8 +205,8 @@ impl CatcollarLibOS {
             return Err(Fail::new(libc::ENOTSUP, "socket type not supported"));
         }
 
-        // Create socket.
-        match unsafe { libc::socket(domain, typ, 0) } {
+        // Create socket. It is closed on exec, so that it does not leak into child processes.
+        match unsafe { libc::socket(domain, typ | libc::SOCK_CLOEXEC, 0) } {
             fd if fd >= 0 => {
                 let qtype: QType = match typ {
                     libc::SOCK_STREAM => QType::TcpSocket,
@@ -113,16 +217,22 @@ impl CatcollarLibOS {
                 // Set socket options.
                 unsafe {
                     if typ == libc::SOCK_STREAM {
-                        if linux::set_tcp_nodelay(fd) != 0 {
+                        if linux::set_tcp_nodelay(fd, true) != 0 {
                             let errno: libc::c_int = *libc::__errno_location();
                             warn!("cannot set TCP_NONDELAY option (errno={:?})", errno);
                         }
                     }
+                    if typ == libc::SOCK_DGRAM {
+                        if linux::set_ip_recverr(fd, true) != 0 {
+                            let errno: libc::c_int = *libc::__errno_location();
+                            warn!("cannot set IP_RECVERR option (errno={:?})", errno);
+                        }
+                    }
                     if linux::set_nonblock(fd) != 0 {
                         let errno: libc::c_int = *libc::__errno_location();
                         warn!("cannot set O_NONBLOCK option (errno={:?})", errno);
                     }
-                    if linux::set_so_reuseport(fd) != 0 {
+                    if linux::set_so_reuseport(fd, true) != 0 {
                         let errno: libc::c_int = *libc::__errno_location();
                         warn!("cannot set SO_REUSEPORT option (errno={:?})", errno);
                     }
@@ -131,7 +241,10 @@ impl CatcollarLibOS {
                 trace!("socket: {:?}, domain: {:?}, typ: {:?}", fd, domain, typ);
                 let mut queue: CatcollarQueue = CatcollarQueue::new(qtype);
                 queue.set_fd(fd);
-                Ok(self.runtime.alloc_queue::<CatcollarQueue>(queue))
+                self.runtime.alloc_queue::<CatcollarQueue>(queue).map_err(|e| {
+                    unsafe { libc::close(fd) };
+                    e
+                })
             },
             _ => {
                 let errno: libc::c_int = unsafe { *libc::__errno_location() };
@@ -140,6 +253,35 @@ impl CatcollarLibOS {
         }
     }
 
+    /// Duplicates a socket. The new queue descriptor refers to the same underlying socket as `qd`, and the socket is
+    /// only released once all queue descriptors that refer to it are closed. The new queue descriptor does not inherit
+    /// the user context, the rate limit, or the continuous receive mode of `qd`.
+    pub fn dup(&mut self, qd: QDesc) -> Result<QDesc, Fail> {
+        trace!("dup() qd={:?}", qd);
+
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+
+        // Duplicate underlying file descriptor. The kernel reference counts the socket, so closing either file
+        // descriptor leaves the other one usable. As for new sockets, the duplicate is closed on exec.
+        match unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) } {
+            new_fd if new_fd >= 0 => {
+                trace!("dup: {:?} -> {:?}", fd, new_fd);
+                self.runtime
+                    .alloc_queue::<CatcollarQueue>(queue.duplicate(new_fd))
+                    .map_err(|e| {
+                        unsafe { libc::close(new_fd) };
+                        e
+                    })
+            },
+            _ => {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                error!("failed to duplicate socket (fd={:?}, errno={:?})", fd, errno);
+                Err(Fail::new(errno, "operation failed"))
+            },
+        }
+    }
+
     /// Binds a socket to a local endpoint.
     pub fn bind(&mut self, qd: QDesc, local: SocketAddr) -> Result<(), Fail> {
         trace!("bind() qd={:?}, local={:?}", qd, local);
@@ -168,7 +310,11 @@ impl CatcollarLibOS {
         match unsafe { libc::bind(fd, &saddr as *const SockAddr, mem::size_of::<SockAddrIn>() as Socklen) } {
             stats if stats == 0 => {
                 // Expect is safe here because we already looked up the queue in get_queue_fd().
-                self.get_shared_queue(&qd).expect("queue should exist").set_addr(local);
+                self.runtime
+                    .get_mut_qtable()
+                    .get_mut::<CatcollarQueue>(&qd)
+                    .expect("queue should exist")
+                    .set_addr(local);
                 Ok(())
             },
             _ => {
@@ -196,6 +342,33 @@ impl CatcollarLibOS {
         Ok(())
     }
 
+    /// Changes the backlog of a passive socket by listening on it again.
+    pub fn set_backlog(&mut self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
+        trace!("set_backlog() qd={:?}, backlog={:?}", qd, backlog);
+
+        // We just assert backlog here, because it was previously checked at PDPIX layer.
+        debug_assert!((backlog > 0) && (backlog <= SOMAXCONN as usize));
+
+        // Calling listen() on a socket that is not listening would make it passive, so check for that first.
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        match unsafe { linux::get_so_acceptconn(fd) } {
+            1 => {},
+            0 => return Err(Fail::new(libc::EINVAL, "socket is not listening")),
+            _ => {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                error!("failed to get SO_ACCEPTCONN ({:?})", errno);
+                return Err(Fail::new(errno, "operation failed"));
+            },
+        }
+
+        if unsafe { libc::listen(fd, backlog as i32) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to listen ({:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+        Ok(())
+    }
+
     /// Accepts connections on a socket.
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("accept(): qd={:?}", qd);
@@ -204,7 +377,14 @@ impl CatcollarLibOS {
 
         // Issue accept operation.
         let yielder: Yielder = Yielder::new();
-        let coroutine: Pin<Box<Operation>> = Box::pin(Self::accept_coroutine(self.runtime.clone(), qd, fd, yielder));
+        let coroutine: Pin<Box<Operation>> = Box::pin(Self::accept_coroutine(
+            self.runtime.clone(),
+            qd,
+            fd,
+            self.accept_options,
+            self.accept_guard.clone(),
+            yielder,
+        ));
         let task_id: String = format!("Catcollar::accept for qd={:?}", qd);
         Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
     }
@@ -213,48 +393,46 @@ impl CatcollarLibOS {
         mut runtime: SharedDemiRuntime,
         qd: QDesc,
         fd: RawFd,
+        options: AcceptOptions,
+        guard: SharedAcceptGuard,
         yielder: Yielder,
     ) -> (QDesc, OperationResult) {
+        let timer: SharedTimer = runtime.get_timer();
         // Borrow the queue table to either update the queue metadata or free the queue on error.
-        match Self::do_accept(fd, yielder).await {
+        match Self::do_accept(fd, options, guard, timer, yielder).await {
             Ok((new_fd, addr)) => {
-                let mut queue: CatcollarQueue = CatcollarQueue::new(QType::TcpSocket);
-                queue.set_addr(addr);
-                queue.set_fd(new_fd);
-                let new_qd: QDesc = runtime.alloc_queue::<CatcollarQueue>(queue);
-                (qd, OperationResult::Accept((new_qd, addr)))
+                let queue: CatcollarQueue = Self::new_accepted_queue(new_fd, addr);
+                match runtime.alloc_queue::<CatcollarQueue>(queue) {
+                    Ok(new_qd) => (qd, OperationResult::Accept((new_qd, addr))),
+                    Err(e) => {
+                        unsafe { libc::close(new_fd) };
+                        (qd, OperationResult::Failed(e))
+                    },
+                }
             },
             Err(e) => (qd, OperationResult::Failed(e)),
         }
     }
 
-    async fn do_accept(fd: RawFd, yielder: Yielder) -> Result<(RawFd, SocketAddrV4), Fail> {
+    async fn do_accept(
+        fd: RawFd,
+        options: AcceptOptions,
+        mut guard: SharedAcceptGuard,
+        timer: SharedTimer,
+        yielder: Yielder,
+    ) -> Result<(RawFd, SocketAddrV4), Fail> {
         // Socket address of accept connection.
         let mut saddr: SockAddr = unsafe { mem::zeroed() };
         let mut address_len: Socklen = mem::size_of::<SockAddrIn>() as u32;
+        // Time to back off for if the process runs out of file descriptors.
+        let mut backoff: Duration = ACCEPT_BACKOFF_MIN;
 
         loop {
-            match unsafe { libc::accept(fd, &mut saddr as *mut SockAddr, &mut address_len) } {
+            match unsafe { libc::accept4(fd, &mut saddr as *mut SockAddr, &mut address_len, options.flags()) } {
                 // Operation completed.
                 new_fd if new_fd >= 0 => {
                     trace!("connection accepted ({:?})", new_fd);
-
-                    // Set socket options.
-                    unsafe {
-                        if linux::set_tcp_nodelay(new_fd) != 0 {
-                            let errno: libc::c_int = *libc::__errno_location();
-                            warn!("cannot set TCP_NONDELAY option (errno={:?})", errno);
-                        }
-                        if linux::set_nonblock(new_fd) != 0 {
-                            let errno: libc::c_int = *libc::__errno_location();
-                            warn!("cannot set O_NONBLOCK option (errno={:?})", errno);
-                        }
-                        if linux::set_so_reuseport(new_fd) != 0 {
-                            let errno: libc::c_int = *libc::__errno_location();
-                            warn!("cannot set SO_REUSEPORT option (errno={:?})", errno);
-                        }
-                    }
-
+                    options.apply(new_fd);
                     let addr: SocketAddrV4 = linux::sockaddr_to_socketaddrv4(&saddr);
                     break Ok((new_fd, addr));
                 },
@@ -270,6 +448,17 @@ impl CatcollarLibOS {
                             error!("{}", message);
                             break Err(Fail::new(libc::ECANCELED, &message));
                         }
+                    } else if errno == libc::EMFILE || errno == libc::ENFILE {
+                        // Out of file descriptors. Shed the pending connection, so that the listener does not keep on
+                        // failing on it, and stop accepting until file descriptors are hopefully released.
+                        warn!("accept(): out of file descriptors, backing off for {:?}", backoff);
+                        guard.shed(fd);
+                        if let Err(e) = timer.clone().wait(backoff, &yielder).await {
+                            let message: String = format!("accept(): operation canceled (err={:?})", e);
+                            error!("{}", message);
+                            break Err(Fail::new(libc::ECANCELED, &message));
+                        }
+                        backoff = cmp::min(backoff * 2, ACCEPT_BACKOFF_MAX);
                     } else {
                         // Operation failed.
                         let message: String = format!("accept(): operation failed (errno={:?})", errno);
@@ -281,6 +470,64 @@ impl CatcollarLibOS {
         }
     }
 
+    /// Attempts to accept a connection on a socket without blocking. If there is no pending connection, `None` is
+    /// returned. If the process is out of file descriptors, the pending connection is dropped and `EMFILE` or `ENFILE`
+    /// is returned, so that the caller may back off.
+    pub fn try_accept(&mut self, qd: QDesc) -> Result<Option<(QDesc, SocketAddrV4)>, Fail> {
+        trace!("try_accept(): qd={:?}", qd);
+
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+
+        // Socket address of accept connection.
+        let mut saddr: SockAddr = unsafe { mem::zeroed() };
+        let mut address_len: Socklen = mem::size_of::<SockAddrIn>() as u32;
+
+        match unsafe {
+            libc::accept4(
+                fd,
+                &mut saddr as *mut SockAddr,
+                &mut address_len,
+                self.accept_options.flags(),
+            )
+        } {
+            // Operation completed.
+            new_fd if new_fd >= 0 => {
+                trace!("connection accepted ({:?})", new_fd);
+                self.accept_options.apply(new_fd);
+
+                let addr: SocketAddrV4 = linux::sockaddr_to_socketaddrv4(&saddr);
+                let queue: CatcollarQueue = Self::new_accepted_queue(new_fd, addr);
+                let new_qd: QDesc = self.runtime.alloc_queue::<CatcollarQueue>(queue).map_err(|e| {
+                    unsafe { libc::close(new_fd) };
+                    e
+                })?;
+                Ok(Some((new_qd, addr)))
+            },
+
+            // Operation not completed, thus parse errno to find out what happened.
+            _ => {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+
+                // No pending connection.
+                if errno == libc::EAGAIN || errno == libc::EWOULDBLOCK {
+                    Ok(None)
+                } else if errno == libc::EMFILE || errno == libc::ENFILE {
+                    // Out of file descriptors. Shed the pending connection, so that the listener does not stay readable
+                    // and callers that wait for it to be readable do not spin on it.
+                    self.accept_guard.shed(fd);
+                    let message: String = format!("try_accept(): out of file descriptors (errno={:?})", errno);
+                    warn!("{}", message);
+                    Err(Fail::new(errno, &message))
+                } else {
+                    // Operation failed.
+                    let message: String = format!("try_accept(): operation failed (errno={:?})", errno);
+                    error!("{}", message);
+                    Err(Fail::new(errno, &message))
+                }
+            },
+        }
+    }
+
     /// Establishes a connection to a remote endpoint.
     pub fn connect(&mut self, qd: QDesc, remote: SocketAddr) -> Result<QToken, Fail> {
         trace!("connect() qd={:?}, remote={:?}", qd, remote);
@@ -290,12 +537,14 @@ impl CatcollarLibOS {
         let remote: SocketAddrV4 = unwrap_socketaddr(remote)?;
         let fd: RawFd = self.get_queue_fd(&qd)?;
         let yielder: Yielder = Yielder::new();
-        let coroutine: Pin<Box<Operation>> = Box::pin(Self::connect_coroutine(qd, fd, remote, yielder));
+        let coroutine: Pin<Box<Operation>> =
+            Box::pin(Self::connect_coroutine(self.runtime.clone(), qd, fd, remote, yielder));
         let task_id: String = format!("Catcollar::connect for qd={:?}", qd);
         Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
     }
 
     async fn connect_coroutine(
+        mut runtime: SharedDemiRuntime,
         qd: QDesc,
         fd: RawFd,
         remote: SocketAddrV4,
@@ -303,7 +552,18 @@ impl CatcollarLibOS {
     ) -> (QDesc, OperationResult) {
         // Handle the result.
         match Self::do_connect(fd, remote, yielder).await {
-            Ok(()) => (qd, OperationResult::Connect),
+            Ok(()) => {
+                // Record the local address, as the kernel picks an ephemeral port if the socket was not bound.
+                let local: Result<SocketAddrV4, Fail> = Self::do_getsockname(fd);
+                if let Ok(queue) = runtime.get_mut_qtable().get_mut::<CatcollarQueue>(&qd) {
+                    match local {
+                        Ok(local) => queue.set_addr(local),
+                        Err(e) => warn!("connect(): failed to get local address (err={:?})", e),
+                    }
+                    queue.set_remote(remote);
+                }
+                (qd, OperationResult::Connect)
+            },
             Err(e) => (qd, OperationResult::Failed(e)),
         }
     }
@@ -339,10 +599,42 @@ impl CatcollarLibOS {
         }
     }
 
+    /// Gets the local address of the socket `qd`. For a socket that was connected without being bound first, this
+    /// includes the ephemeral port that was picked by the kernel.
+    pub fn getsockname(&self, qd: QDesc) -> Result<SocketAddrV4, Fail> {
+        trace!("getsockname() qd={:?}", qd);
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        Self::do_getsockname(fd)
+    }
+
+    fn do_getsockname(fd: RawFd) -> Result<SocketAddrV4, Fail> {
+        let mut saddr: SockAddr = unsafe { mem::zeroed() };
+        let mut address_len: Socklen = mem::size_of::<SockAddrIn>() as Socklen;
+        if unsafe { libc::getsockname(fd, &mut saddr as *mut SockAddr, &mut address_len) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to get socket name ({:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+        Ok(linux::sockaddr_to_socketaddrv4(&saddr))
+    }
+
+    /// Creates the metadata of a queue for socket `fd`, which was accepted from a connection of `remote`.
+    fn new_accepted_queue(fd: RawFd, remote: SocketAddrV4) -> CatcollarQueue {
+        let mut queue: CatcollarQueue = CatcollarQueue::new(QType::TcpSocket);
+        match Self::do_getsockname(fd) {
+            Ok(local) => queue.set_addr(local),
+            Err(e) => warn!("accept(): failed to get local address (err={:?})", e),
+        }
+        queue.set_remote(remote);
+        queue.set_fd(fd);
+        queue
+    }
+
     /// Closes a socket.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         trace!("close() qd={:?}", qd);
         let fd: RawFd = self.get_queue_fd(&qd)?;
+        self.stop_continuous_recv(&qd)?;
         match unsafe { libc::close(fd) } {
             stats if stats == 0 => {
                 // Expect is safe here because we looked up the queue to schedule this coroutine and no other close
@@ -364,6 +656,7 @@ impl CatcollarLibOS {
     pub fn async_close(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("close() qd={:?}", qd);
         let fd: RawFd = self.get_queue_fd(&qd)?;
+        self.stop_continuous_recv(&qd)?;
         let yielder: Yielder = Yielder::new();
         let coroutine: Pin<Box<Operation>> = Box::pin(Self::close_coroutine(self.runtime.clone(), qd, fd, yielder));
         let task_id: String = format!("Catcollar::close for qd={:?}", qd);
@@ -389,6 +682,82 @@ impl CatcollarLibOS {
         }
     }
 
+    /// Asynchronous close that lingers until the peer acknowledges our FIN, or until `timeout` expires. In the
+    /// latter case, the connection is forcefully closed and the peer receives a reset.
+    pub fn async_close_with_linger(&mut self, qd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("close() qd={:?}, timeout={:?}", qd, timeout);
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        self.stop_continuous_recv(&qd)?;
+        let yielder: Yielder = Yielder::new();
+        let coroutine: Pin<Box<Operation>> = Box::pin(Self::linger_close_coroutine(
+            self.runtime.clone(),
+            qd,
+            fd,
+            timeout,
+            yielder,
+        ));
+        let task_id: String = format!("Catcollar::close for qd={:?}", qd);
+        Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
+    }
+
+    async fn linger_close_coroutine(
+        runtime: SharedDemiRuntime,
+        qd: QDesc,
+        fd: RawFd,
+        timeout: Duration,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        let deadline: Instant = runtime.get_now() + timeout;
+        if let Err(e) = Self::do_linger(&runtime, fd, deadline, &yielder).await {
+            return (qd, OperationResult::Failed(e));
+        }
+        Self::close_coroutine(runtime, qd, fd, yielder).await
+    }
+
+    /// Sends a FIN and waits for the peer to acknowledge it. If that does not happen before `deadline`, the socket
+    /// is set up to reset the connection when it gets closed.
+    async fn do_linger(
+        runtime: &SharedDemiRuntime,
+        fd: RawFd,
+        deadline: Instant,
+        yielder: &Yielder,
+    ) -> Result<(), Fail> {
+        if unsafe { libc::shutdown(fd, libc::SHUT_WR) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            // Socket is not connected, so there is nothing to linger on.
+            if errno == libc::ENOTCONN {
+                return Ok(());
+            }
+            let message: String = format!("close(): failed to shutdown socket (errno={:?})", errno);
+            error!("{}", message);
+            return Err(Fail::new(errno, &message));
+        }
+
+        loop {
+            match unsafe { linux::get_tcp_state(fd) } {
+                TCP_STATE_FIN_WAIT2 | TCP_STATE_TIME_WAIT | TCP_STATE_CLOSE => {
+                    trace!("peer acknowledged close fd={:?}", fd);
+                    return Ok(());
+                },
+                _ if runtime.get_now() >= deadline => {
+                    warn!("close(): linger timeout expired, resetting connection (fd={:?})", fd);
+                    if unsafe { linux::set_so_linger_abort(fd) } != 0 {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        warn!("cannot set SO_LINGER option (errno={:?})", errno);
+                    }
+                    return Ok(());
+                },
+                _ => {
+                    if let Err(e) = yielder.yield_once().await {
+                        let message: String = format!("close(): operation canceled (err={:?})", e);
+                        error!("{}", message);
+                        return Err(Fail::new(libc::ECANCELED, &message));
+                    }
+                },
+            }
+        }
+    }
+
     async fn do_close(fd: RawFd, yielder: Yielder) -> Result<(), Fail> {
         loop {
             match unsafe { libc::close(fd) } {
@@ -429,31 +798,94 @@ impl CatcollarLibOS {
             return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
         }
 
+        self.push_chain(qd, DemiBufferChain::from(buf))
+    }
+
+    /// Pushes a chain of buffers to a socket. The buffers are gathered into a single send by the kernel, so that they
+    /// do not need to be merged first.
+    pub fn push_chain(&mut self, qd: QDesc, chain: DemiBufferChain) -> Result<QToken, Fail> {
+        trace!("push_chain() qd={:?}, len={:?}", qd, chain.len());
+
+        if chain.is_empty() {
+            return Err(Fail::new(libc::EINVAL, "zero-length buffer chain"));
+        }
+
         // Issue push operation.
         let fd: RawFd = self.get_queue_fd(&qd)?;
+        let rate_limiter: Option<SharedRateLimiter> = self.get_shared_queue(&qd)?.get_rate_limiter();
         // Issue operation.
         let yielder: Yielder = Yielder::new();
-        let coroutine: Pin<Box<Operation>> =
-            Box::pin(Self::push_coroutine(self.transport.clone(), qd, fd, buf, yielder));
+        let coroutine: Pin<Box<Operation>> = Box::pin(Self::push_coroutine(
+            self.transport.clone(),
+            qd,
+            fd,
+            chain,
+            None,
+            rate_limiter,
+            yielder,
+        ));
         let task_id: String = format!("Catcollar::push for qd={:?}", qd);
         Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
     }
 
+    /// Pushes a scatter-gather array to a socket, failing with `ETIMEDOUT` if it cannot be sent within `timeout`. On
+    /// timeout, the kernel cancels the underlying send.
+    pub fn push_timeout(&mut self, qd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("push_timeout() qd={:?}, timeout={:?}", qd, timeout);
+
+        let buf: DemiBuffer = self.runtime.clone_sgarray(sga)?;
+
+        if buf.len() == 0 {
+            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
+        }
+
+        // Issue push operation.
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        let rate_limiter: Option<SharedRateLimiter> = self.get_shared_queue(&qd)?.get_rate_limiter();
+        // Issue operation.
+        let yielder: Yielder = Yielder::new();
+        let coroutine: Pin<Box<Operation>> = Box::pin(Self::push_coroutine(
+            self.transport.clone(),
+            qd,
+            fd,
+            DemiBufferChain::from(buf),
+            Some(timeout),
+            rate_limiter,
+            yielder,
+        ));
+        let task_id: String = format!("Catcollar::push_timeout for qd={:?}", qd);
+        Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
+    }
+
     async fn push_coroutine(
         rt: SharedIoUringRuntime,
         qd: QDesc,
         fd: RawFd,
-        buf: DemiBuffer,
+        bufs: DemiBufferChain,
+        timeout: Option<Duration>,
+        rate_limiter: Option<SharedRateLimiter>,
         yielder: Yielder,
     ) -> (QDesc, OperationResult) {
-        match Self::do_push(rt, fd, buf, yielder).await {
+        match Self::do_push(rt, fd, bufs, timeout, rate_limiter, yielder).await {
             Ok(()) => (qd, OperationResult::Push),
             Err(e) => (qd, OperationResult::Failed(e)),
         }
     }
 
-    async fn do_push(mut rt: SharedIoUringRuntime, fd: RawFd, buf: DemiBuffer, yielder: Yielder) -> Result<(), Fail> {
-        let request_id: RequestId = rt.push(fd, buf.clone())?;
+    async fn do_push(
+        mut rt: SharedIoUringRuntime,
+        fd: RawFd,
+        bufs: DemiBufferChain,
+        timeout: Option<Duration>,
+        rate_limiter: Option<SharedRateLimiter>,
+        yielder: Yielder,
+    ) -> Result<(), Fail> {
+        Self::wait_rate_limit(rate_limiter, bufs.len(), &yielder).await?;
+        // The buffers are kept alive here until the kernel is done with them.
+        let request_id: RequestId = match timeout {
+            Some(timeout) => rt.push_timeout(fd, &bufs, timeout)?,
+            None => rt.push(fd, &bufs)?,
+        };
         loop {
             match rt.peek(request_id) {
                 // Operation completed.
@@ -471,6 +903,11 @@ impl CatcollarLibOS {
                             error!("{}", message);
                             return Err(Fail::new(libc::ECANCELED, &message));
                         }
+                    } else if errno == libc::ECANCELED && timeout.is_some() {
+                        // The linked timeout has expired and the kernel has cancelled the send.
+                        let message: String = format!("push(): operation timed out (timeout={:?})", timeout);
+                        error!("{}", message);
+                        return Err(Fail::new(libc::ETIMEDOUT, &message));
                     } else {
                         let message: String = format!("push(): operation failed (errno={:?})", errno);
                         error!("{}", message);
@@ -503,6 +940,7 @@ impl CatcollarLibOS {
                 }
                 // Issue push operation.
                 let fd: RawFd = self.get_queue_fd(&qd)?;
+                let rate_limiter: Option<SharedRateLimiter> = self.get_shared_queue(&qd)?.get_rate_limiter();
                 // Issue operation.
                 let yielder: Yielder = Yielder::new();
                 let coroutine: Pin<Box<Operation>> = Box::pin(Self::pushto_coroutine(
@@ -511,6 +949,7 @@ impl CatcollarLibOS {
                     fd,
                     remote,
                     buf,
+                    rate_limiter,
                     yielder,
                 ));
                 let task_id: String = format!("Catcollar::pushto for qd={:?}", qd);
@@ -520,15 +959,94 @@ impl CatcollarLibOS {
         }
     }
 
+    /// Pushes a batch of datagrams, each to its own destination, through the UDP socket `qd`. As many datagrams as
+    /// possible are sent at once in a single system call (sendmmsg), and the remaining ones are pushed one by one. There
+    /// is one operation per datagram, so partial sends show up in their individual results.
+    pub fn pushto_batch(&mut self, qd: QDesc, items: &[(demi_sgarray_t, SocketAddr)]) -> Result<Vec<QToken>, Fail> {
+        trace!("pushto_batch() qd={:?}, count={:?}", qd, items.len());
+
+        // We just assert the batch size here, because it was previously checked at PDPIX layer.
+        debug_assert!(!items.is_empty() && (items.len() <= limits::PUSH_BATCH_MAX));
+
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        if queue.get_qtype() != QType::UdpSocket {
+            return Err(Fail::new(libc::EINVAL, "invalid queue type"));
+        }
+
+        // Check all datagrams before sending any of them.
+        let mut datagrams: Vec<(DemiBuffer, SocketAddrV4)> = Vec::with_capacity(items.len());
+        for (sga, remote) in items {
+            // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
+            let remote: SocketAddrV4 = unwrap_socketaddr(*remote)?;
+            let buf: DemiBuffer = self.runtime.clone_sgarray(sga)?;
+            if buf.len() == 0 {
+                return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
+            }
+            datagrams.push((buf, remote));
+        }
+
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        let rate_limiter: Option<SharedRateLimiter> = queue.get_rate_limiter();
+
+        // Send as many datagrams as the socket takes right away, unless they have to be paced.
+        let sent: usize = match rate_limiter {
+            Some(_) => 0,
+            None => {
+                let msgs: Vec<(&[u8], SocketAddrV4)> =
+                    datagrams.iter().map(|(buf, remote)| (&buf[..], *remote)).collect();
+                match unsafe { linux::send_mmsg(fd, &msgs) } {
+                    count if count >= 0 => count as usize,
+                    _ => {
+                        let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                        // Datagrams that were not sent are still pushed one by one, which reports any error.
+                        if !DemiRuntime::should_retry(errno) {
+                            warn!("pushto_batch(): failed to send datagrams (errno={:?})", errno);
+                        }
+                        0
+                    },
+                }
+            },
+        };
+        trace!("datagrams sent ({:?} datagrams)", sent);
+
+        let mut qts: Vec<QToken> = Vec::with_capacity(datagrams.len());
+        for (i, (buf, remote)) in datagrams.into_iter().enumerate() {
+            let coroutine: Pin<Box<Operation>> = if i < sent {
+                Box::pin(Self::pushed_coroutine(qd))
+            } else {
+                let yielder: Yielder = Yielder::new();
+                Box::pin(Self::pushto_coroutine(
+                    self.transport.clone(),
+                    qd,
+                    fd,
+                    remote,
+                    buf,
+                    rate_limiter.clone(),
+                    yielder,
+                ))
+            };
+            let task_id: String = format!("Catcollar::pushto_batch for qd={:?}", qd);
+            qts.push(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into());
+        }
+
+        Ok(qts)
+    }
+
+    /// Completes the push of a datagram that was already sent.
+    async fn pushed_coroutine(qd: QDesc) -> (QDesc, OperationResult) {
+        (qd, OperationResult::Push)
+    }
+
     async fn pushto_coroutine(
         rt: SharedIoUringRuntime,
         qd: QDesc,
         fd: RawFd,
         remote: SocketAddrV4,
         buf: DemiBuffer,
+        rate_limiter: Option<SharedRateLimiter>,
         yielder: Yielder,
     ) -> (QDesc, OperationResult) {
-        match Self::do_pushto(rt, fd, remote, buf, yielder).await {
+        match Self::do_pushto(rt, fd, remote, buf, rate_limiter, yielder).await {
             Ok(()) => (qd, OperationResult::Push),
             Err(e) => (qd, OperationResult::Failed(e)),
         }
@@ -539,8 +1057,10 @@ impl CatcollarLibOS {
         fd: RawFd,
         remote: SocketAddrV4,
         buf: DemiBuffer,
+        rate_limiter: Option<SharedRateLimiter>,
         yielder: Yielder,
     ) -> Result<(), Fail> {
+        Self::wait_rate_limit(rate_limiter, buf.len(), &yielder).await?;
         let request_id: RequestId = rt.pushto(fd, remote, buf.clone())?;
         loop {
             match rt.peek(request_id) {
@@ -577,24 +1097,64 @@ impl CatcollarLibOS {
         }
     }
 
-    /// Pops data from a socket.
-    pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
-        trace!("pop() qd={:?}, size={:?}", qd, size);
+    /// Waits until the rate limiter of a queue, if any, lets `len` bytes through.
+    async fn wait_rate_limit(
+        rate_limiter: Option<SharedRateLimiter>,
+        len: usize,
+        yielder: &Yielder,
+    ) -> Result<(), Fail> {
+        if let Some(mut rate_limiter) = rate_limiter {
+            if let Err(e) = rate_limiter.acquire(len, yielder).await {
+                let message: String = format!("push(): operation canceled (err={:?})", e);
+                error!("{}", message);
+                return Err(Fail::new(libc::ECANCELED, &message));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pops data from a socket. If `deadline` is given, the pop fails with `ETIMEDOUT` once the clock of the runtime
+    /// reaches it without data having arrived.
+    pub fn pop(&mut self, qd: QDesc, size: Option<usize>, deadline: Option<Instant>) -> Result<QToken, Fail> {
+        trace!("pop() qd={:?}, size={:?}, deadline={:?}", qd, size, deadline);
 
         // We just assert 'size' here, because it was previously checked at PDPIX layer.
         debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
 
-        let buf: DemiBuffer = {
-            let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
-            DemiBuffer::new(size as u16)
-        };
+        let size: usize = size.unwrap_or(self.transport.get_recv_chunk_size().unwrap_or(limits::RECVBUF_SIZE_MAX));
+
+        // In continuous receive mode, data is already on its way, so just wait for it.
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        if let Some(receiver) = queue.get_receiver() {
+            if deadline.is_some() {
+                let cause: String = format!("deadlines are not supported in continuous receive mode (qd={:?})", qd);
+                error!("pop(): {}", &cause);
+                return Err(Fail::new(libc::ENOTSUP, &cause));
+            }
+            let stream: bool = queue.get_qtype() == QType::TcpSocket;
+            let yielder: Yielder = Yielder::new();
+            let coroutine: Pin<Box<Operation>> =
+                Box::pin(Self::pop_backlog_coroutine(qd, receiver, size, stream, yielder));
+            let task_id: String = format!("Catcollar::pop for qd={:?}", qd);
+            return Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into());
+        }
 
         // Issue pop operation.
-        // Issue push operation.
         let fd: RawFd = self.get_queue_fd(&qd)?;
+        let quickack: bool = queue.get_quickack();
+        let stream: bool = queue.get_qtype() == QType::TcpSocket;
+        let deadline: Option<Deadline> = deadline.map(|deadline| (self.runtime.get_timer(), deadline));
         let yielder: Yielder = Yielder::new();
-        let coroutine: Pin<Box<Operation>> =
-            Box::pin(Self::pop_coroutine(self.transport.clone(), qd, fd, buf, yielder));
+        let coroutine: Pin<Box<Operation>> = Box::pin(Self::pop_coroutine(
+            self.transport.clone(),
+            qd,
+            fd,
+            size,
+            stream,
+            quickack,
+            deadline,
+            yielder,
+        ));
         let task_id: String = format!("Catcollar::pop for qd={:?}", qd);
         Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
     }
@@ -603,60 +1163,925 @@ impl CatcollarLibOS {
         rt: SharedIoUringRuntime,
         qd: QDesc,
         fd: RawFd,
-        buf: DemiBuffer,
+        size: usize,
+        stream: bool,
+        quickack: bool,
+        deadline: Option<Deadline>,
         yielder: Yielder,
     ) -> (QDesc, OperationResult) {
+        // Have the kernel pick a buffer from the receive buffer group, if one is registered. Buffer-selected pops do not
+        // report the source address, so datagram sockets always supply their own buffer.
+        let result: Result<(Option<SocketAddrV4>, DemiBuffer), Fail> = match (stream, rt.get_recv_buffer_size()) {
+            (true, Some(_)) => Self::do_pop_selected(rt, fd, size, deadline, &yielder).await,
+            _ => {
+                let buf: DemiBuffer = rt.alloc_recv_buffer(size);
+                Self::do_pop(rt, fd, buf, deadline, &yielder).await
+            },
+        };
+
         // Handle the result: if successful, return the addr and buffer.
-        match Self::do_pop(rt, fd, buf, yielder).await {
+        match result {
+            Ok((addr, buf)) => {
+                // The kernel may fall back to delayed ACKs at any time, so keep re-enabling quick ACKs as we go.
+                if quickack && unsafe { linux::set_tcp_quickack(fd, true) } != 0 {
+                    let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                    warn!("cannot set TCP_QUICKACK option (errno={:?})", errno);
+                }
+                (qd, OperationResult::Pop(addr, buf))
+            },
+            Err(e) => (qd, OperationResult::Failed(e)),
+        }
+    }
+
+    async fn pop_backlog_coroutine(
+        qd: QDesc,
+        mut receiver: SharedContinuousReceiver,
+        size: usize,
+        stream: bool,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        match receiver.pop(size, stream, &yielder).await {
             Ok((addr, buf)) => (qd, OperationResult::Pop(addr, buf)),
             Err(e) => (qd, OperationResult::Failed(e)),
         }
     }
 
-    async fn do_pop(
-        mut rt: SharedIoUringRuntime,
-        fd: RawFd,
-        buf: DemiBuffer,
+    /// Moves data from TCP socket `src_qd` to TCP socket `dst_qd`, until `size` bytes have been moved or `src_qd`
+    /// reaches end of file. Data is relayed through the buffers of the libOS, one pop and one push at a time.
+    pub fn splice(&mut self, src_qd: QDesc, dst_qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        trace!("splice() src_qd={:?}, dst_qd={:?}, size={:?}", src_qd, dst_qd, size);
+
+        if src_qd == dst_qd {
+            let cause: String = format!("cannot splice a queue into itself (qd={:?})", src_qd);
+            error!("splice(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if size == Some(0) {
+            let cause: String = format!("zero-length splice (src_qd={:?})", src_qd);
+            error!("splice(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        let src_queue: CatcollarQueue = self.get_shared_queue(&src_qd)?;
+        let dst_queue: CatcollarQueue = self.get_shared_queue(&dst_qd)?;
+        if src_queue.get_qtype() != QType::TcpSocket || dst_queue.get_qtype() != QType::TcpSocket {
+            let cause: String = format!("can only splice TCP sockets (src_qd={:?}, dst_qd={:?})", src_qd, dst_qd);
+            error!("splice(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        // Data that is received in continuous receive mode goes to the backlog, not to us.
+        if src_queue.get_receiver().is_some() {
+            let cause: String = format!("cannot splice in continuous receive mode (qd={:?})", src_qd);
+            error!("splice(): {}", cause);
+            return Err(Fail::new(libc::ENOTSUP, &cause));
+        }
+
+        let src_fd: RawFd = self.get_queue_fd(&src_qd)?;
+        let dst_fd: RawFd = self.get_queue_fd(&dst_qd)?;
+        let rate_limiter: Option<SharedRateLimiter> = dst_queue.get_rate_limiter();
+        let yielder: Yielder = Yielder::new();
+        let coroutine: Pin<Box<Operation>> = Box::pin(Self::splice_coroutine(
+            self.transport.clone(),
+            src_qd,
+            src_fd,
+            dst_fd,
+            size,
+            rate_limiter,
+            yielder,
+        ));
+        let task_id: String = format!("Catcollar::splice for src_qd={:?} dst_qd={:?}", src_qd, dst_qd);
+        Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
+    }
+
+    async fn splice_coroutine(
+        rt: SharedIoUringRuntime,
+        src_qd: QDesc,
+        src_fd: RawFd,
+        dst_fd: RawFd,
+        size: Option<usize>,
+        rate_limiter: Option<SharedRateLimiter>,
         yielder: Yielder,
-    ) -> Result<(Option<SocketAddrV4>, DemiBuffer), Fail> {
-        let request_id: RequestId = rt.pop(fd, buf.clone())?;
+    ) -> (QDesc, OperationResult) {
+        // Each chunk is pushed before the next one is popped, thus a single receive buffer serves all of them.
+        let recv_buf: DemiBuffer =
+            DemiBuffer::new(size.map_or(limits::RECVBUF_SIZE_MAX, |size| size.min(limits::RECVBUF_SIZE_MAX)) as u16);
+        let mut nbytes: usize = 0;
         loop {
-            match rt.peek(request_id) {
-                // Operation completed.
-                Ok((addr, size)) if size >= 0 => {
-                    trace!("data received ({:?} bytes)", size);
-                    let trim_size: usize = buf.len() - (size as usize);
-                    let mut buf: DemiBuffer = buf.clone();
-                    buf.trim(trim_size)?;
-                    break Ok((addr, buf));
-                },
-                // Operation not completed, thus parse errno to find out what happened.
-                Ok((None, size)) if size < 0 => {
-                    let errno: i32 = -size;
-                    if DemiRuntime::should_retry(errno) {
-                        if let Err(e) = yielder.yield_once().await {
-                            let message: String = format!("pop(): operation canceled (err={:?})", e);
-                            error!("{}", message);
-                            break Err(Fail::new(libc::ECANCELED, &message));
+            // Pop at most what is left to move.
+            let pop_size: usize = match size {
+                Some(size) if size == nbytes => break,
+                Some(size) => (size - nbytes).min(limits::RECVBUF_SIZE_MAX),
+                None => limits::RECVBUF_SIZE_MAX,
+            };
+            let mut chunk: DemiBuffer = recv_buf.clone();
+            if let Err(e) = chunk.trim(recv_buf.len() - pop_size) {
+                return (src_qd, OperationResult::Failed(e));
+            }
+            let buf: DemiBuffer = match Self::do_pop(rt.clone(), src_fd, chunk, None, &yielder).await {
+                Ok((_, buf)) => buf,
+                Err(e) => return (src_qd, OperationResult::Failed(e)),
+            };
+            // The remote closed the connection.
+            if buf.len() == 0 {
+                break;
+            }
+
+            let len: usize = buf.len();
+            let push_yielder: Yielder = Yielder::new();
+            if let Err(e) = Self::do_push(
+                rt.clone(),
+                dst_fd,
+                DemiBufferChain::from(buf),
+                None,
+                rate_limiter.clone(),
+                push_yielder,
+            )
+            .await
+            {
+                return (src_qd, OperationResult::Failed(e));
+            }
+            nbytes += len;
+        }
+
+        trace!("data spliced ({:?} bytes)", nbytes);
+        (src_qd, OperationResult::Splice(nbytes))
+    }
+
+    /// Pops up to `max` datagrams from the UDP socket `qd` at once. The operation completes as soon as one datagram
+    /// arrives, along with any other datagrams that are already queued on the socket by then, which are pulled in a
+    /// single system call (recvmmsg).
+    pub fn pop_batch(&mut self, qd: QDesc, max: usize) -> Result<QToken, Fail> {
+        trace!("pop_batch() qd={:?}, max={:?}", qd, max);
+
+        // We just assert 'max' here, because it was previously checked at PDPIX layer.
+        debug_assert!((max > 0) && (max <= limits::POP_BATCH_MAX));
+
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        if queue.get_qtype() != QType::UdpSocket {
+            return Err(Fail::new(libc::EINVAL, "invalid queue type"));
+        }
+        self.check_not_continuous_recv(&qd)?;
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        let yielder: Yielder = Yielder::new();
+        let coroutine: Pin<Box<Operation>> =
+            Box::pin(Self::pop_batch_coroutine(self.transport.clone(), qd, fd, max, yielder));
+        let task_id: String = format!("Catcollar::pop_batch for qd={:?}", qd);
+        Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
+    }
+
+    async fn pop_batch_coroutine(
+        rt: SharedIoUringRuntime,
+        qd: QDesc,
+        fd: RawFd,
+        max: usize,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        // Wait for the first datagram.
+        let mut datagrams: Vec<(Option<SocketAddrV4>, DemiBuffer)> = Vec::with_capacity(max);
+        match Self::do_pop(rt, fd, DemiBuffer::new(limits::RECVBUF_SIZE_MAX as u16), None, &yielder).await {
+            Ok(datagram) => datagrams.push(datagram),
+            Err(e) => return (qd, OperationResult::Failed(e)),
+        }
+
+        // Then pull in whatever else is already there.
+        if max > 1 {
+            let mut bufs: Vec<DemiBuffer> = (1..max)
+                .map(|_| DemiBuffer::new(limits::RECVBUF_SIZE_MAX as u16))
+                .collect();
+            let mut slices: Vec<&mut [u8]> = bufs.iter_mut().map(|buf| &mut buf[..]).collect();
+            let mut received: Vec<(SocketAddrV4, usize)> = Vec::with_capacity(max - 1);
+            if unsafe { linux::recv_mmsg(fd, &mut slices, &mut received) } < 0 {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                // Datagrams that were received so far are still handed out.
+                if !DemiRuntime::should_retry(errno) {
+                    warn!("pop_batch(): failed to receive more datagrams (errno={:?})", errno);
+                }
+            }
+            for ((addr, len), mut buf) in received.into_iter().zip(bufs.into_iter()) {
+                let trim_size: usize = buf.len() - len;
+                if let Err(e) = buf.trim(trim_size) {
+                    return (qd, OperationResult::Failed(e));
+                }
+                datagrams.push((Some(addr), buf));
+            }
+        }
+        trace!("datagrams received ({:?} datagrams)", datagrams.len());
+
+        (qd, OperationResult::PopBatch(datagrams))
+    }
+
+    /// Streams data from the socket `qd` into `f`, one chunk at a time, until `f` returns false or the connection is
+    /// closed by the remote end. Chunks are received into a single buffer, which is reused as soon as `f` returns, so
+    /// memory usage stays bounded regardless of the size of the stream.
+    pub fn pop_stream<F: FnMut(&[u8]) -> bool + 'static>(&mut self, qd: QDesc, f: F) -> Result<QToken, Fail> {
+        trace!("pop_stream() qd={:?}", qd);
+
+        self.check_not_continuous_recv(&qd)?;
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        let yielder: Yielder = Yielder::new();
+        let coroutine: Pin<Box<Operation>> =
+            Box::pin(Self::pop_stream_coroutine(self.transport.clone(), qd, fd, f, yielder));
+        let task_id: String = format!("Catcollar::pop_stream for qd={:?}", qd);
+        Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
+    }
+
+    async fn pop_stream_coroutine<F: FnMut(&[u8]) -> bool>(
+        rt: SharedIoUringRuntime,
+        qd: QDesc,
+        fd: RawFd,
+        mut f: F,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        let recv_buf: DemiBuffer = DemiBuffer::new(limits::RECVBUF_SIZE_MAX as u16);
+        let mut nbytes: usize = 0;
+        loop {
+            let buf: DemiBuffer = match Self::do_pop(rt.clone(), fd, recv_buf.clone(), None, &yielder).await {
+                Ok((_, buf)) => buf,
+                Err(e) => return (qd, OperationResult::Failed(e)),
+            };
+            // A zero-length read means that the remote end closed the connection.
+            if buf.len() == 0 {
+                break;
+            }
+            nbytes += buf.len();
+            if !f(&buf[..]) {
+                break;
+            }
+        }
+
+        trace!("data streamed ({:?} bytes)", nbytes);
+        (qd, OperationResult::PopStream(nbytes))
+    }
+
+    async fn do_pop(
+        mut rt: SharedIoUringRuntime,
+        fd: RawFd,
+        buf: DemiBuffer,
+        deadline: Option<Deadline>,
+        yielder: &Yielder,
+    ) -> Result<(Option<SocketAddrV4>, DemiBuffer), Fail> {
+        let request_id: RequestId = rt.pop(fd, buf.clone())?;
+        // Sleep until the operation completes, instead of spinning on it.
+        match Self::wait_request(&mut rt, request_id, &deadline, yielder).await {
+            // Operation completed.
+            Ok((addr, size)) if size >= 0 => {
+                trace!("data received ({:?} bytes)", size);
+                let trim_size: usize = buf.len() - (size as usize);
+                let mut buf: DemiBuffer = buf.clone();
+                buf.trim(trim_size)?;
+                Ok((addr, buf))
+            },
+            // Operation failed, thus parse errno to find out what happened.
+            Ok((_, size)) => {
+                let errno: i32 = -size;
+                let message: String = format!("pop(): operation failed (errno={:?})", errno);
+                error!("{}", message);
+                Err(Fail::new(errno, &message))
+            },
+            // Operation canceled or timed out.
+            Err(e) => {
+                let message: String = format!("pop(): operation failed (err={:?})", e);
+                error!("{}", message);
+                Err(e)
+            },
+        }
+    }
+
+    async fn do_pop_selected(
+        mut rt: SharedIoUringRuntime,
+        fd: RawFd,
+        len: usize,
+        deadline: Option<Deadline>,
+        yielder: &Yielder,
+    ) -> Result<(Option<SocketAddrV4>, DemiBuffer), Fail> {
+        loop {
+            let request_id: RequestId = rt.pop_selected(fd, len)?;
+            // Sleep until the operation completes, instead of spinning on it.
+            match Self::wait_request(&mut rt, request_id, &deadline, yielder).await {
+                // Operation completed.
+                Ok((addr, size)) if size >= 0 => {
+                    trace!("data received ({:?} bytes)", size);
+                    let buf: DemiBuffer = rt.take_selected(request_id, size as usize)?;
+                    break Ok((addr, buf));
+                },
+                // All buffers of the group are in use, so wait for the user to release some and try again.
+                Ok((_, size)) if -size == libc::ENOBUFS => {
+                    if let Some((timer, deadline)) = &deadline {
+                        if timer.now() >= *deadline {
+                            let cause: &str = "deadline expired";
+                            debug!("pop(): {}", cause);
+                            break Err(Fail::new(libc::ETIMEDOUT, cause));
                         }
-                    } else {
-                        let message: String = format!("pop(): operation failed (errno={:?})", errno);
+                    }
+                    if let Err(e) = yielder.yield_once().await {
+                        let message: String = format!("pop(): operation canceled (err={:?})", e);
                         error!("{}", message);
-                        break Err(Fail::new(errno, &message));
+                        break Err(Fail::new(libc::ECANCELED, &message));
                     }
                 },
-                // Operation failed.
+                // Operation failed, thus parse errno to find out what happened.
+                Ok((_, size)) => {
+                    let errno: i32 = -size;
+                    let message: String = format!("pop(): operation failed (errno={:?})", errno);
+                    error!("{}", message);
+                    break Err(Fail::new(errno, &message));
+                },
+                // Operation canceled or timed out.
                 Err(e) => {
                     let message: String = format!("pop(): operation failed (err={:?})", e);
                     error!("{}", message);
                     break Err(e);
                 },
-                // Should not happen.
-                _ => panic!("pop failed: unknown error"),
             }
         }
     }
 
+    /// Waits for the request `request_id` to complete, giving up once its deadline expires, if it has one.
+    async fn wait_request(
+        rt: &mut SharedIoUringRuntime,
+        request_id: RequestId,
+        deadline: &Option<Deadline>,
+        yielder: &Yielder,
+    ) -> Result<(Option<SocketAddrV4>, i32), Fail> {
+        match deadline {
+            Some((timer, deadline)) => {
+                rt.wait_request_until(request_id, yielder, timer.clone(), *deadline)
+                    .await
+            },
+            None => rt.wait_request(request_id, yielder).await,
+        }
+    }
+
+    /// Pops data from a socket directly into the segments of a list of scatter-gather arrays. The segments are filled
+    /// in order, and each of them may live in a buffer of its own. The caller must keep `sgas` alive until the
+    /// operation completes. On completion, one buffer is returned for each segment, which spans the bytes that were
+    /// read into it. The total may be less than the total length of all segments.
+    pub fn pop_vectored(&mut self, qd: QDesc, sgas: &[demi_sgarray_t]) -> Result<QToken, Fail> {
+        trace!("pop_vectored() qd={:?}", qd);
+
+        // Check arguments.
+        self.check_not_continuous_recv(&qd)?;
+        if sgas.is_empty() {
+            let cause: String = format!("no scatter-gather array to pop into (qd={:?})", qd);
+            error!("pop_vectored(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        // Take a buffer that spans each segment, so that the buffers that back them outlive the operation.
+        let mut bufs: Vec<DemiBuffer> = Vec::new();
+        for sga in sgas {
+            bufs.extend(Self::sga_to_segments(qd, sga)?);
+        }
+
+        // Issue pop operation.
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        let yielder: Yielder = Yielder::new();
+        let coroutine: Pin<Box<Operation>> = Box::pin(Self::pop_vectored_coroutine(
+            self.transport.clone(),
+            qd,
+            fd,
+            bufs,
+            yielder,
+        ));
+        let task_id: String = format!("Catcollar::pop_vectored for qd={:?}", qd);
+        Ok(self.runtime.insert_coroutine(&task_id, coroutine)?.get_task_id().into())
+    }
+
+    /// Takes a buffer that spans each segment of `sga`, once it checks that they all lie within the buffer that backs
+    /// `sga`.
+    fn sga_to_segments(qd: QDesc, sga: &demi_sgarray_t) -> Result<Vec<DemiBuffer>, Fail> {
+        let numsegs: usize = sga.sga_numsegs as usize;
+        if numsegs == 0 || numsegs > DEMI_SGARRAY_MAXLEN {
+            let cause: String = format!("demi_sgarray_t has invalid segment count (qd={:?})", qd);
+            error!("pop_vectored(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if sga.sga_buf == ptr::null_mut() {
+            let cause: String = format!("demi_sgarray_t has invalid DemiBuffer token (qd={:?})", qd);
+            error!("pop_vectored(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        // Safety: The `NonNull::new_unchecked()` call is safe, as we verified `sga.sga_buf` is not null above.
+        let token: NonNull<u8> = unsafe { NonNull::new_unchecked(sga.sga_buf as *mut u8) };
+        // Safety: The `sga_buf` field in the `demi_sgarray_t` contains a valid `DemiBuffer` token.
+        let backing: DemiBuffer = unsafe { DemiBuffer::from_raw(token) };
+        let buf: DemiBuffer = backing.clone();
+        // Don't drop backing, as it holds the same reference to the data as the sgarray (which should keep it).
+        mem::forget(backing);
+
+        let sga_segs: [demi_sgaseg_t; DEMI_SGARRAY_MAXLEN] = sga.sga_segs;
+        let mut segments: Vec<DemiBuffer> = Vec::with_capacity(numsegs);
+        for seg in &sga_segs[..numsegs] {
+            let data: *const u8 = seg.sgaseg_buf as *const u8;
+            let len: usize = seg.sgaseg_len as usize;
+            if len == 0 || data < buf.as_ptr() || data.addr() + len > buf.as_ptr().addr() + buf.len() {
+                let cause: String = format!("demi_sgarray_t describes data outside backing buffer (qd={:?})", qd);
+                error!("pop_vectored(): {}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+            let mut segment: DemiBuffer = buf.clone();
+            segment.adjust(data.addr() - buf.as_ptr().addr())?;
+            segment.trim(segment.len() - len)?;
+            segments.push(segment);
+        }
+        Ok(segments)
+    }
+
+    async fn pop_vectored_coroutine(
+        rt: SharedIoUringRuntime,
+        qd: QDesc,
+        fd: RawFd,
+        bufs: Vec<DemiBuffer>,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        // Handle the result: if successful, return the segments trimmed to the number of bytes read into each of them.
+        match Self::do_pop_vectored(rt, fd, bufs, yielder).await {
+            Ok(bufs) => (
+                qd,
+                OperationResult::PopBatch(bufs.into_iter().map(|buf| (None, buf)).collect()),
+            ),
+            Err(e) => (qd, OperationResult::Failed(e)),
+        }
+    }
+
+    async fn do_pop_vectored(
+        mut rt: SharedIoUringRuntime,
+        fd: RawFd,
+        mut bufs: Vec<DemiBuffer>,
+        yielder: Yielder,
+    ) -> Result<Vec<DemiBuffer>, Fail> {
+        let segments: Vec<(*mut u8, usize)> = bufs.iter().map(|buf| (buf.as_ptr() as *mut u8, buf.len())).collect();
+        let request_id: RequestId = rt.pop_vectored(fd, &segments)?;
+        let total: usize = segments.iter().map(|(_, len)| len).sum();
+        // Sleep until the operation completes, instead of spinning on it.
+        match rt.wait_request(request_id, &yielder).await {
+            // Operation completed.
+            Ok((_, size)) if size >= 0 => {
+                trace!("data received ({:?} out of {:?} bytes)", size, total);
+                // Segments are filled in order, so each one holds what is left of the data, up to its length.
+                let mut remaining: usize = size as usize;
+                for buf in bufs.iter_mut() {
+                    let filled: usize = remaining.min(buf.len());
+                    buf.trim(buf.len() - filled)?;
+                    remaining -= filled;
+                }
+                Ok(bufs)
+            },
+            // Operation failed, thus parse errno to find out what happened.
+            Ok((_, size)) => {
+                let errno: i32 = -size;
+                let message: String = format!("pop_vectored(): operation failed (errno={:?})", errno);
+                error!("{}", message);
+                Err(Fail::new(errno, &message))
+            },
+            // Operation canceled. The kernel may still complete it, so the request is only released then.
+            Err(e) => {
+                let message: String = format!("pop_vectored(): operation failed (err={:?})", e);
+                error!("{}", message);
+                rt.abandon(request_id);
+                Err(e)
+            },
+        }
+    }
+
+    /// Lists all I/O queues that are currently open in Catcollar. This function is read-only and intended for
+    /// diagnostics. Catcollar does not track connection state, so it is not reported.
+    pub fn list_queues(&self) -> Vec<QueueInfo> {
+        let mut queues: Vec<QueueInfo> = Vec::new();
+        for qd in self.runtime.get_qtable().get_qds() {
+            if let Ok(queue) = self.get_shared_queue(&qd) {
+                queues.push(QueueInfo {
+                    qd,
+                    qtype: queue.get_qtype(),
+                    local: queue.local(),
+                    remote: queue.remote(),
+                    state: None,
+                });
+            }
+        }
+        queues
+    }
+
+    /// Turns quick ACK mode on or off for a TCP socket. While it is on, incoming data is acknowledged right away instead
+    /// of being delayed.
+    pub fn set_quickack(&mut self, qd: QDesc, enable: bool) -> Result<(), Fail> {
+        trace!("set_quickack() qd={:?}, enable={:?}", qd, enable);
+
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        if queue.get_qtype() != QType::TcpSocket {
+            return Err(Fail::new(libc::EINVAL, "invalid queue type"));
+        }
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        if unsafe { linux::set_tcp_quickack(fd, enable) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to set TCP_QUICKACK ({:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+        self.runtime
+            .get_mut_qtable()
+            .get_mut::<CatcollarQueue>(&qd)?
+            .set_quickack(enable);
+        Ok(())
+    }
+
+    /// Corks or uncorks a TCP socket (TCP_CORK). While it is corked, the kernel holds back partial segments, so that
+    /// small pushes go out together once it is uncorked.
+    pub fn cork(&mut self, qd: QDesc, enabled: bool) -> Result<(), Fail> {
+        trace!("cork() qd={:?}, enabled={:?}", qd, enabled);
+
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        if queue.get_qtype() != QType::TcpSocket {
+            return Err(Fail::new(libc::EINVAL, "invalid queue type"));
+        }
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        if unsafe { linux::set_tcp_cork(fd, enabled) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to set TCP_CORK ({:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+        Ok(())
+    }
+
+    /// Gets the maximum segment size of the connection of a TCP socket (TCP_MAXSEG), which is what the kernel fills
+    /// each segment up to.
+    pub fn connection_mss(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("connection_mss() qd={:?}", qd);
+
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        if queue.get_qtype() != QType::TcpSocket {
+            return Err(Fail::new(libc::EINVAL, "invalid queue type"));
+        }
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        match unsafe { linux::get_tcp_maxseg(fd) } {
+            mss if mss >= 0 => Ok(mss as usize),
+            _ => {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                error!("failed to get TCP_MAXSEG ({:?})", errno);
+                Err(Fail::new(errno, "operation failed"))
+            },
+        }
+    }
+
+    /// Registers a group of `count` receive buffers of `size` bytes each with the kernel. From then on, pops have the
+    /// kernel pick one of these buffers when data arrives, instead of setting up a buffer for each of them. Received
+    /// data is handed to the user without a copy, and each buffer goes back to the kernel once the user frees the
+    /// scatter-gather array that holds it.
+    pub fn register_recv_buffer_group(&mut self, count: u16, size: u16) -> Result<(), Fail> {
+        trace!("register_recv_buffer_group() count={:?}, size={:?}", count, size);
+        self.transport.register_recv_buffer_group(count, size)
+    }
+
+    /// Sets up a pool of `count` receive chunks of `chunk_size` bytes each. From then on, pops draw their buffers from
+    /// the pool instead of allocating [limits::RECVBUF_SIZE_MAX] bytes for each of them, and buffers go back to the pool
+    /// once the user frees the scatter-gather array that holds them. Pops without an explicit size receive at most
+    /// `chunk_size` bytes.
+    pub fn set_recv_buffer_pool(&mut self, chunk_size: u16, count: usize) -> Result<(), Fail> {
+        trace!("set_recv_buffer_pool() chunk_size={:?}, count={:?}", chunk_size, count);
+        self.transport.set_recv_buffer_pool(chunk_size, count)
+    }
+
+    /// Allocates a scatter-gather array, from the receive buffer pool if one is set up.
+    pub fn sgaalloc(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
+        match self.transport.get_recv_chunk_size() {
+            Some(_) => self.transport.sgaalloc(size),
+            None => self.runtime.sgaalloc(size),
+        }
+    }
+
+    /// Releases a scatter-gather array, back to the receive buffer pool if one is set up.
+    pub fn sgafree(&self, sga: demi_sgarray_t) -> Result<(), Fail> {
+        match self.transport.get_recv_chunk_size() {
+            Some(_) => self.transport.sgafree(sga),
+            None => self.runtime.sgafree(sga),
+        }
+    }
+
+    /// Caps the send rate of a queue to `bytes_per_sec`. Pushes wait until the rate limit lets them through before they
+    /// are submitted. A zero rate removes the limit.
+    pub fn set_rate_limit(&mut self, qd: QDesc, bytes_per_sec: u64) -> Result<(), Fail> {
+        trace!("set_rate_limit() qd={:?}, bytes_per_sec={:?}", qd, bytes_per_sec);
+
+        let rate_limiter: Option<SharedRateLimiter> = match bytes_per_sec {
+            0 => None,
+            rate => Some(SharedRateLimiter::new(self.runtime.clone(), rate)),
+        };
+        self.runtime
+            .get_mut_qtable()
+            .get_mut::<CatcollarQueue>(&qd)?
+            .set_rate_limiter(rate_limiter);
+        Ok(())
+    }
+
+    /// Turns on continuous receive mode on a queue. From then on, a receive operation is kept armed on the socket at
+    /// all times, so that incoming data is moved into a backlog of up to `count` receive buffers as soon as it arrives,
+    /// and pops are served from that backlog. Receiving stops while the backlog is full, until pops drain it. Batched,
+    /// streamed, and vectored pops are not available in this mode, and it stays on until the queue is closed.
+    pub fn set_continuous_recv(&mut self, qd: QDesc, count: usize) -> Result<(), Fail> {
+        trace!("set_continuous_recv() qd={:?}, count={:?}", qd, count);
+
+        // We just assert 'count' here, because it was previously checked at PDPIX layer.
+        debug_assert!((count > 0) && (count <= limits::RECV_BACKLOG_MAX));
+
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        if queue.get_receiver().is_some() {
+            let cause: String = format!("continuous receive mode is already on (qd={:?})", qd);
+            error!("set_continuous_recv(): {}", &cause);
+            return Err(Fail::new(libc::EBUSY, &cause));
+        }
+
+        let yielder: Yielder = Yielder::new();
+        let receiver: SharedContinuousReceiver = SharedContinuousReceiver::new(count, yielder.get_handle());
+        let stream: bool = queue.get_qtype() == QType::TcpSocket;
+        let future = Self::receive_coroutine(self.transport.clone(), fd, stream, receiver.clone(), yielder);
+        let task_name: String = format!("Catcollar::receive for qd={:?}", qd);
+        self.runtime.insert_background_coroutine(&task_name, Box::pin(future))?;
+        self.runtime
+            .get_mut_qtable()
+            .get_mut::<CatcollarQueue>(&qd)?
+            .set_receiver(receiver);
+        Ok(())
+    }
+
+    /// Keeps a receive operation armed on `fd` and moves received data into the backlog of `receiver`, until the queue
+    /// is closed, a receive operation fails, or, on a stream socket, the peer shuts down its side of the connection.
+    async fn receive_coroutine(
+        rt: SharedIoUringRuntime,
+        fd: RawFd,
+        stream: bool,
+        mut receiver: SharedContinuousReceiver,
+        yielder: Yielder,
+    ) {
+        loop {
+            // Sleep while the backlog is full. Pops wake us up once they make room, and so does closing the queue.
+            if receiver.wait_for_room(&yielder).await.is_err() || receiver.is_stopped() {
+                return;
+            }
+
+            let buf: DemiBuffer = DemiBuffer::new(limits::RECVBUF_SIZE_MAX as u16);
+            let result: Result<(Option<SocketAddrV4>, DemiBuffer), Fail> =
+                Self::do_pop(rt.clone(), fd, buf, None, &yielder).await;
+            if receiver.is_stopped() {
+                return;
+            }
+            match result {
+                // Nothing more is coming once the peer shut down its side of the connection. Empty datagrams, on the
+                // other hand, are just data.
+                Ok((_, ref buf)) if stream && buf.is_empty() => {
+                    receiver.push_eof();
+                    return;
+                },
+                Ok(received) => receiver.push(Ok(received)),
+                Err(e) => {
+                    receiver.push(Err(e));
+                    return;
+                },
+            }
+        }
+    }
+
+    /// Sets the priority of the packets sent on a socket (SO_PRIORITY). Linux queueing disciplines use it to classify
+    /// outgoing traffic.
+    pub fn set_priority(&mut self, qd: QDesc, priority: u32) -> Result<(), Fail> {
+        trace!("set_priority() qd={:?}, priority={:?}", qd, priority);
+
+        // Higher priorities require CAP_NET_ADMIN, so we do not let applications request them.
+        if priority > SO_PRIORITY_MAX {
+            let cause: String = format!("priority is out of range (priority={:?})", priority);
+            error!("set_priority(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        if unsafe { linux::set_so_priority(fd, priority) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to set SO_PRIORITY ({:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+        Ok(())
+    }
+
+    /// Gets the priority of the packets sent on a socket (SO_PRIORITY).
+    pub fn get_priority(&self, qd: QDesc) -> Result<u32, Fail> {
+        trace!("get_priority() qd={:?}", qd);
+
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        match unsafe { linux::get_so_priority(fd) } {
+            priority if priority >= 0 => Ok(priority as u32),
+            _ => {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                error!("failed to get SO_PRIORITY ({:?})", errno);
+                Err(Fail::new(errno, "operation failed"))
+            },
+        }
+    }
+
+    /// Sets the key that signs the segments that a socket exchanges with `remote` (TCP_MD5SIG), as required by peers that
+    /// authenticate sessions with the TCP MD5 signature option (e.g., BGP routers). Only the address of `remote` is
+    /// taken into account, and an empty key stops signing segments.
+    pub fn set_tcp_md5sig(&mut self, qd: QDesc, remote: SocketAddrV4, key: &[u8]) -> Result<(), Fail> {
+        trace!(
+            "set_tcp_md5sig() qd={:?}, remote={:?}, key_len={:?}",
+            qd,
+            remote,
+            key.len()
+        );
+
+        if key.len() > linux::TCP_MD5SIG_MAXKEYLEN {
+            let cause: String = format!("key is too long (key_len={:?})", key.len());
+            error!("set_tcp_md5sig(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        if unsafe { linux::set_tcp_md5sig(fd, &remote, key) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to set TCP_MD5SIG ({:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+        Ok(())
+    }
+
+    /// Sets the receive low-water mark of a socket (SO_RCVLOWAT), so that receives wait for at least `bytes` bytes
+    /// unless the connection is closed first.
+    pub fn set_rcvlowat(&mut self, qd: QDesc, bytes: usize) -> Result<(), Fail> {
+        trace!("set_rcvlowat() qd={:?}, bytes={:?}", qd, bytes);
+
+        let bytes: i32 = match i32::try_from(bytes) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                let cause: String = format!("low-water mark is out of range (bytes={:?})", bytes);
+                error!("set_rcvlowat(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        };
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        if unsafe { linux::set_so_rcvlowat(fd, bytes) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to set SO_RCVLOWAT ({:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+        Ok(())
+    }
+
+    /// Binds a socket to the network interface named `ifname` (SO_BINDTODEVICE), so that it only sends and receives
+    /// packets through that interface. An empty name removes the binding.
+    pub fn bind_to_device(&mut self, qd: QDesc, ifname: &str) -> Result<(), Fail> {
+        trace!("bind_to_device() qd={:?}, ifname={:?}", qd, ifname);
+
+        // Interface names are NUL-terminated and at most IFNAMSIZ bytes long.
+        if ifname.len() >= libc::IFNAMSIZ || ifname.contains('\0') {
+            let cause: String = format!("invalid interface name (ifname={:?})", ifname);
+            error!("bind_to_device(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        // The kernel fails with ENODEV if there is no such interface.
+        if unsafe { linux::set_so_bindtodevice(fd, ifname.as_bytes()) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to set SO_BINDTODEVICE ({:?})", errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+        Ok(())
+    }
+
+    /// Gets the name of the network interface that a socket is bound to (SO_BINDTODEVICE). If the socket is not bound
+    /// to an interface, an empty name is returned.
+    pub fn get_bound_device(&self, qd: QDesc) -> Result<String, Fail> {
+        trace!("get_bound_device() qd={:?}", qd);
+
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        let mut ifname: [u8; libc::IFNAMSIZ] = [0; libc::IFNAMSIZ];
+        match unsafe { linux::get_so_bindtodevice(fd, &mut ifname) } {
+            len if len >= 0 => {
+                // Drop the NUL terminator.
+                let name: &[u8] = &ifname[..len as usize];
+                let name: &[u8] = name.strip_suffix(&[0]).unwrap_or(name);
+                Ok(String::from_utf8_lossy(name).into_owned())
+            },
+            _ => {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                error!("failed to get SO_BINDTODEVICE ({:?})", errno);
+                Err(Fail::new(errno, "operation failed"))
+            },
+        }
+    }
+
+    /// Retrieves the oldest asynchronous error reported on a UDP socket, such as a port or host unreachable message sent
+    /// back by the network. If no error is pending, `None` is returned.
+    pub fn poll_error(&mut self, qd: QDesc) -> Result<Option<Fail>, Fail> {
+        trace!("poll_error() qd={:?}", qd);
+
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        if queue.get_qtype() != QType::UdpSocket {
+            return Err(Fail::new(libc::EINVAL, "invalid queue type"));
+        }
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        match unsafe { linux::recv_ip_error(fd) } {
+            0 => Ok(None),
+            errno if errno > 0 => {
+                let cause: String = format!("asynchronous error reported on socket (errno={:?})", errno);
+                Ok(Some(Fail::new(errno, &cause)))
+            },
+            _ => {
+                let errno: libc::c_int = unsafe { *libc::__errno_location() };
+                error!("failed to read error queue ({:?})", errno);
+                Err(Fail::new(errno, "operation failed"))
+            },
+        }
+    }
+
+    /// Checks which operations would make progress right away on a queue, from the state of the underlying socket. In
+    /// continuous receive mode, a queue is readable when its backlog has something to pop.
+    pub fn readiness(&self, qd: QDesc) -> Result<Readiness, Fail> {
+        trace!("readiness() qd={:?}", qd);
+
+        let queue: CatcollarQueue = self.get_shared_queue(&qd)?;
+        let fd: RawFd = self.get_queue_fd(&qd)?;
+        let mut pollfd: libc::pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN | libc::POLLOUT | libc::POLLRDHUP,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut pollfd, 1, 0) } < 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            error!("failed to poll socket (fd={:?}, errno={:?})", fd, errno);
+            return Err(Fail::new(errno, "operation failed"));
+        }
+
+        let readable: bool = match queue.get_receiver() {
+            Some(receiver) => receiver.is_readable(),
+            None => (pollfd.revents & libc::POLLIN) != 0,
+        };
+        Ok(Readiness {
+            readable,
+            writable: (pollfd.revents & libc::POLLOUT) != 0,
+            error: (pollfd.revents & libc::POLLERR) != 0,
+            hangup: (pollfd.revents & (libc::POLLHUP | libc::POLLRDHUP)) != 0,
+        })
+    }
+
+    /// Polls the scheduler once and packs the results of up to `max` operations that completed, so that many completions
+    /// are reaped at the cost of a single poll. Each operation is reported exactly once, as it is removed from the
+    /// scheduler along the way.
+    pub fn poll_and_collect(&mut self, max: usize) -> Vec<(QToken, demi_qresult_t)> {
+        trace!("poll_and_collect() max={:?}", max);
+        self.reap_completions();
+        self.runtime.poll_and_advance_clock();
+        self.runtime.remove_completed_coroutines(max)
+    }
+
+    /// Reaps the operations that completed in the I/O user ring, and wakes the coroutines that wait for them. This must
+    /// run before polling the scheduler, as pops sleep until their operation completes.
+    pub fn reap_completions(&mut self) {
+        if let Err(e) = self.transport.poll_completions() {
+            warn!("reap_completions(): failed to reap completions (error={:?})", e);
+        }
+    }
+
+    /// Sleeps until an operation completes in the I/O user ring, or until `timeout` expires, and wakes the coroutine
+    /// that waits for it. This is meant to be called when no coroutine is ready to run, so that waiting does not spin.
+    pub fn wait_completions(&mut self, timeout: Option<Duration>) {
+        let timeout: Duration = timeout.map_or(IDLE_SLEEP_MAX, |timeout| timeout.min(IDLE_SLEEP_MAX));
+        if let Err(e) = self.transport.poll_blocking(Some(timeout)) {
+            warn!("wait_completions(): failed to wait for completions (error={:?})", e);
+        }
+    }
+
+    /// Attaches an opaque user context to a queue. The context is discarded when the queue is closed.
+    pub fn set_user_context(&mut self, qd: QDesc, ctx: u64) -> Result<(), Fail> {
+        trace!("set_user_context() qd={:?}, ctx={:?}", qd, ctx);
+        self.runtime
+            .get_mut_qtable()
+            .get_mut::<CatcollarQueue>(&qd)?
+            .set_user_context(ctx);
+        Ok(())
+    }
+
+    /// Gets the opaque user context attached to a queue. If none was attached, zero is returned.
+    pub fn get_user_context(&self, qd: QDesc) -> Result<u64, Fail> {
+        trace!("get_user_context() qd={:?}", qd);
+        Ok(self.get_shared_queue(&qd)?.get_user_context())
+    }
+
+    /// Fails if continuous receive mode is on for a queue, as its receive operations would race with the background
+    /// receiver.
+    fn check_not_continuous_recv(&self, qd: &QDesc) -> Result<(), Fail> {
+        if self.get_shared_queue(qd)?.get_receiver().is_some() {
+            let cause: String = format!("queue is in continuous receive mode (qd={:?})", qd);
+            error!("check_not_continuous_recv(): {}", &cause);
+            return Err(Fail::new(libc::EBUSY, &cause));
+        }
+        Ok(())
+    }
+
+    /// Stops the continuous receiver of a queue, if any.
+    fn stop_continuous_recv(&self, qd: &QDesc) -> Result<(), Fail> {
+        if let Some(mut receiver) = self.get_shared_queue(qd)?.get_receiver() {
+            receiver.stop();
+        }
+        Ok(())
+    }
+
     fn get_shared_queue(&self, qd: &QDesc) -> Result<CatcollarQueue, Fail> {
         Ok(self.runtime.get_shared_queue::<CatcollarQueue>(qd)?.clone())
     }
@@ -672,3 +2097,560 @@ impl CatcollarLibOS {
         }
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::CatcollarLibOS;
+    use crate::{
+        demikernel::config::Config,
+        ensure_eq,
+        runtime::{
+            fail::Fail,
+            limits,
+            memory::{
+                DemiBuffer,
+                MemoryRuntime,
+            },
+            queue::QueueInfo,
+            types::demi_sgarray_t,
+            OperationResult,
+            OperationTask,
+            QDesc,
+            QToken,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        env,
+        io::{
+            ErrorKind,
+            Read,
+            Write,
+        },
+        mem,
+        net::{
+            Shutdown,
+            SocketAddr,
+            SocketAddrV4,
+            TcpStream,
+            UdpSocket,
+        },
+        os::unix::prelude::RawFd,
+        process::{
+            Command,
+            Output,
+        },
+        time::{
+            Duration,
+            Instant,
+        },
+    };
+    use ::yaml_rust::YamlLoader;
+
+    /// Creates a Catcollar LibOS with configuration `config_s`, unless the kernel does not support io_uring.
+    fn new_libos(config_s: &str) -> Result<Option<CatcollarLibOS>> {
+        let config: Config = Config(YamlLoader::load_from_str(config_s)?.remove(0));
+        match CatcollarLibOS::new(&config, SharedDemiRuntime::default()) {
+            Ok(libos) => Ok(Some(libos)),
+            Err(e) if e.errno == libc::ENOSYS => Ok(None),
+            Err(e) => anyhow::bail!("failed to create catcollar libos: {:?}", e),
+        }
+    }
+
+    /// Creates a Catcollar LibOS with configuration `$config_s`, or skips the calling test if the kernel does not
+    /// support io_uring.
+    macro_rules! new_libos_or_skip {
+        ($config_s:expr) => {
+            match new_libos($config_s)? {
+                Some(libos) => libos,
+                None => return Ok(()),
+            }
+        };
+    }
+
+    /// Checks if file descriptor `fd` is closed on exec.
+    fn is_cloexec(fd: RawFd) -> Result<bool> {
+        match unsafe { libc::fcntl(fd, libc::F_GETFD) } {
+            flags if flags >= 0 => Ok(flags & libc::FD_CLOEXEC != 0),
+            _ => anyhow::bail!("fcntl() failed (errno={:?})", unsafe { *libc::__errno_location() }),
+        }
+    }
+
+    /// Checks if file descriptor `fd` is in nonblocking mode.
+    fn is_nonblocking(fd: RawFd) -> Result<bool> {
+        match unsafe { libc::fcntl(fd, libc::F_GETFL) } {
+            flags if flags >= 0 => Ok(flags & libc::O_NONBLOCK != 0),
+            _ => anyhow::bail!("fcntl() failed (errno={:?})", unsafe { *libc::__errno_location() }),
+        }
+    }
+
+    /// Checks if boolean socket option `name` at `level` is enabled on socket `fd`.
+    fn is_sockopt_set(fd: RawFd, level: libc::c_int, name: libc::c_int) -> Result<bool> {
+        let mut value: libc::c_int = 0;
+        let mut len: libc::socklen_t = ::std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        match unsafe { libc::getsockopt(fd, level, name, &mut value as *mut _ as *mut libc::c_void, &mut len) } {
+            0 => Ok(value != 0),
+            _ => anyhow::bail!("getsockopt() failed (errno={:?})", unsafe { *libc::__errno_location() }),
+        }
+    }
+
+    /// Accepts a connection on listening socket `listen_qd` of `libos`, and returns the accepted socket along with the
+    /// address of its peer. Fails if no connection is accepted within 10 seconds.
+    fn wait_accept(libos: &mut CatcollarLibOS, listen_qd: QDesc) -> Result<(QDesc, SocketAddrV4)> {
+        let start: Instant = Instant::now();
+        loop {
+            if let Some(accepted) = libos.try_accept(listen_qd)? {
+                return Ok(accepted);
+            }
+            ensure_eq!(start.elapsed() < Duration::from_secs(10), true);
+        }
+    }
+
+    /// Accepts a connection on a new socket of `libos` that listens on `port`, and returns the listening and accepted
+    /// sockets. Fails if no connection is accepted within 10 seconds.
+    fn accept_one(libos: &mut CatcollarLibOS, port: u16) -> Result<(QDesc, QDesc)> {
+        let local: SocketAddr = SocketAddr::V4(SocketAddrV4::new("127.0.0.1".parse()?, port));
+        let listen_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+        let _client: TcpStream = TcpStream::connect(libos.getsockname(listen_qd)?)?;
+        let (qd, _): (QDesc, SocketAddrV4) = wait_accept(libos, listen_qd)?;
+        Ok((listen_qd, qd))
+    }
+
+    /// Polls `libos` until the operation of `qt` completes, and returns its task. Fails if it does not complete within
+    /// 10 seconds.
+    fn wait(libos: &mut CatcollarLibOS, qt: QToken) -> Result<OperationTask> {
+        let mut runtime: SharedDemiRuntime = libos.runtime.clone();
+        let start: Instant = Instant::now();
+        loop {
+            libos.reap_completions();
+            runtime.poll_and_advance_clock();
+            if let Some(task) = runtime.try_remove_coroutine(&runtime.from_task_id(qt)?) {
+                return Ok(task);
+            }
+            ensure_eq!(start.elapsed() < Duration::from_secs(10), true);
+        }
+    }
+
+    /// Tests that accepting on a listening socket without pending connections returns right away with nothing, and that
+    /// a connection is accepted once one comes in.
+    #[test]
+    fn try_accept_returns_none_when_idle() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let local: SocketAddr = SocketAddr::V4(SocketAddrV4::new("127.0.0.1".parse()?, 23466));
+        let listen_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+
+        // Nobody connected yet, so there is nothing to accept, however many times we try.
+        for _ in 0..3 {
+            ensure_eq!(libos.try_accept(listen_qd)?.is_none(), true);
+        }
+
+        let client: TcpStream = TcpStream::connect(local)?;
+        let (_, addr): (QDesc, SocketAddrV4) = wait_accept(&mut libos, listen_qd)?;
+        ensure_eq!(SocketAddr::V4(addr), client.local_addr()?);
+
+        // The connection was accepted, so the listener is idle again.
+        ensure_eq!(libos.try_accept(listen_qd)?.is_none(), true);
+
+        Ok(())
+    }
+
+    /// Tests that queues are listed with the local address of their socket and the address of its peer.
+    #[test]
+    fn list_queues_reports_local_and_remote() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let local: SocketAddrV4 = SocketAddrV4::new("127.0.0.1".parse()?, 23468);
+        let listen_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(listen_qd, SocketAddr::V4(local))?;
+        libos.listen(listen_qd, 1)?;
+        let client: TcpStream = TcpStream::connect(local)?;
+        let (qd, _): (QDesc, SocketAddrV4) = wait_accept(&mut libos, listen_qd)?;
+
+        let queues: Vec<QueueInfo> = libos.list_queues();
+        let listener: &QueueInfo = match queues.iter().find(|info| info.qd == listen_qd) {
+            Some(info) => info,
+            None => anyhow::bail!("listening queue should be listed"),
+        };
+        ensure_eq!(listener.local, Some(local));
+        ensure_eq!(listener.remote, None);
+        let accepted: &QueueInfo = match queues.iter().find(|info| info.qd == qd) {
+            Some(info) => info,
+            None => anyhow::bail!("accepted queue should be listed"),
+        };
+        ensure_eq!(accepted.local, Some(local));
+        ensure_eq!(accepted.remote.map(SocketAddr::V4), Some(client.local_addr()?));
+
+        Ok(())
+    }
+
+    /// Tests that accepting without blocking fails with `EMFILE` once the process is out of file descriptors, and that
+    /// the pending connection is dropped, so that the listener is not left readable. Limiting file descriptors affects
+    /// the whole process, thus the test runs alone in a child process.
+    #[test]
+    fn try_accept_sheds_connection_when_out_of_fds() -> Result<()> {
+        const CHILD_ENV: &str = "CATCOLLAR_TEST_EMFILE_CHILD";
+        if env::var_os(CHILD_ENV).is_none() {
+            let output: Output = Command::new(env::current_exe()?)
+                .args([
+                    "catcollar::tests::try_accept_sheds_connection_when_out_of_fds",
+                    "--exact",
+                    "--test-threads=1",
+                ])
+                .env(CHILD_ENV, "1")
+                .output()?;
+            ensure_eq!(output.status.success(), true);
+            return Ok(());
+        }
+
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let local: SocketAddr = SocketAddr::V4(SocketAddrV4::new("127.0.0.1".parse()?, 23469));
+        let listen_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+        let mut client: TcpStream = TcpStream::connect(local)?;
+        let start: Instant = Instant::now();
+        while !libos.readiness(listen_qd)?.readable {
+            ensure_eq!(start.elapsed() < Duration::from_secs(10), true);
+        }
+
+        // Use up all file descriptors: the lowest free one is past the limit.
+        let free_fd: RawFd = unsafe { libc::dup(0) };
+        ensure_eq!(free_fd >= 0, true);
+        unsafe { libc::close(free_fd) };
+        let mut limit: libc::rlimit = unsafe { mem::zeroed() };
+        ensure_eq!(unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) }, 0);
+        let low_limit: libc::rlimit = libc::rlimit {
+            rlim_cur: free_fd as libc::rlim_t,
+            rlim_max: limit.rlim_max,
+        };
+        ensure_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &low_limit) }, 0);
+        let result: Result<Option<(QDesc, SocketAddrV4)>, Fail> = libos.try_accept(listen_qd);
+        let readable: Result<bool, Fail> = libos.readiness(listen_qd).map(|readiness| readiness.readable);
+        ensure_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) }, 0);
+
+        match result {
+            Err(e) => ensure_eq!(e.errno, libc::EMFILE),
+            Ok(_) => anyhow::bail!("try_accept should have run out of file descriptors"),
+        }
+        ensure_eq!(readable?, false);
+
+        // The peer sees the connection closed.
+        let mut buf: [u8; 1] = [0; 1];
+        match client.read(&mut buf) {
+            Ok(0) => {},
+            Err(e) if e.kind() == ErrorKind::ConnectionReset => {},
+            result => anyhow::bail!("connection should be closed (result={:?})", result),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that sockets are closed on exec by default.
+    #[test]
+    fn accepted_sockets_are_cloexec() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let (listen_qd, qd): (QDesc, QDesc) = accept_one(&mut libos, 23456)?;
+        ensure_eq!(is_cloexec(libos.get_queue_fd(&listen_qd)?)?, true);
+        ensure_eq!(is_cloexec(libos.get_queue_fd(&qd)?)?, true);
+
+        let dup_qd: QDesc = libos.dup(qd)?;
+        ensure_eq!(is_cloexec(libos.get_queue_fd(&dup_qd)?)?, true);
+
+        Ok(())
+    }
+
+    /// Tests that accepted sockets are inherited by child processes when asked to.
+    #[test]
+    fn accepted_sockets_are_not_cloexec_when_disabled() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar:\n  accept_cloexec: false\n");
+        let (_, qd): (QDesc, QDesc) = accept_one(&mut libos, 23457)?;
+        ensure_eq!(is_cloexec(libos.get_queue_fd(&qd)?)?, false);
+
+        Ok(())
+    }
+
+    /// Tests that accepted sockets are nonblocking and have TCP_NODELAY and SO_REUSEPORT enabled by default.
+    #[test]
+    fn accepted_sockets_are_nonblocking_with_default_options() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let (_, qd): (QDesc, QDesc) = accept_one(&mut libos, 23463)?;
+        let fd: RawFd = libos.get_queue_fd(&qd)?;
+        ensure_eq!(is_nonblocking(fd)?, true);
+        ensure_eq!(is_sockopt_set(fd, libc::IPPROTO_TCP, libc::TCP_NODELAY)?, true);
+        ensure_eq!(is_sockopt_set(fd, libc::SOL_SOCKET, libc::SO_REUSEPORT)?, true);
+
+        Ok(())
+    }
+
+    /// Tests that accepted sockets stay nonblocking, but do not get TCP_NODELAY and SO_REUSEPORT when asked not to.
+    #[test]
+    fn accepted_sockets_honor_disabled_options() -> Result<()> {
+        let config_s: &str = "catcollar:\n  accept_tcp_nodelay: false\n  accept_so_reuseport: false\n";
+        let mut libos: CatcollarLibOS = new_libos_or_skip!(config_s);
+        let (_, qd): (QDesc, QDesc) = accept_one(&mut libos, 23464)?;
+        let fd: RawFd = libos.get_queue_fd(&qd)?;
+        ensure_eq!(is_nonblocking(fd)?, true);
+        ensure_eq!(is_sockopt_set(fd, libc::IPPROTO_TCP, libc::TCP_NODELAY)?, false);
+        ensure_eq!(is_sockopt_set(fd, libc::SOL_SOCKET, libc::SO_REUSEPORT)?, false);
+
+        Ok(())
+    }
+
+    /// Tests that continuous receive mode stops receiving once the peer shuts down its side of the connection, and that
+    /// every pop from then on sees the end of file.
+    #[test]
+    fn continuous_recv_stops_at_eof() -> Result<()> {
+        const BACKLOG_SIZE: usize = 4;
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let mut runtime: SharedDemiRuntime = libos.runtime.clone();
+
+        // The client is gone as soon as the connection is accepted.
+        let (_, qd): (QDesc, QDesc) = accept_one(&mut libos, 23459)?;
+        libos.set_continuous_recv(qd, BACKLOG_SIZE)?;
+
+        // There are more pops than the backlog holds, and they all see the end of file.
+        for _ in 0..(2 * BACKLOG_SIZE) {
+            let qt: QToken = libos.pop(qd, None, None)?;
+            let task: OperationTask = wait(&mut libos, qt)?;
+            match task.get_result() {
+                Some((_, OperationResult::Pop(_, buf))) => ensure_eq!(buf.len(), 0),
+                _ => anyhow::bail!("pop should have seen the end of file"),
+            }
+        }
+
+        // The background receive coroutine is done, so nothing is left to run.
+        libos.reap_completions();
+        runtime.poll_and_advance_clock();
+        ensure_eq!(runtime.has_ready_coroutines(), false);
+
+        Ok(())
+    }
+
+    /// Tests that a lingering close completes once the peer acknowledges that the connection is closed, and that the
+    /// socket is released.
+    #[test]
+    fn async_close_with_linger_completes() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let (_, qd): (QDesc, QDesc) = accept_one(&mut libos, 23461)?;
+
+        let qt: QToken = libos.async_close_with_linger(qd, Duration::from_secs(10))?;
+        let task: OperationTask = wait(&mut libos, qt)?;
+        match task.get_result() {
+            Some((_, OperationResult::Close)) => {},
+            _ => anyhow::bail!("close should have completed"),
+        }
+        ensure_eq!(libos.get_queue_fd(&qd).is_err(), true);
+
+        Ok(())
+    }
+
+    /// Tests that splicing relays everything that one connection receives to another one, until end of file.
+    #[test]
+    fn splice_between_sockets() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let local: SocketAddr = SocketAddr::V4(SocketAddrV4::new("127.0.0.1".parse()?, 23462));
+        let listen_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 2)?;
+
+        // Accept one connection at a time, so that we know which client is on which end.
+        let mut clients: Vec<TcpStream> = Vec::with_capacity(2);
+        let mut qds: Vec<QDesc> = Vec::with_capacity(2);
+        for _ in 0..2 {
+            clients.push(TcpStream::connect(local)?);
+            let (qd, _): (QDesc, SocketAddrV4) = wait_accept(&mut libos, listen_qd)?;
+            qds.push(qd);
+        }
+
+        // Send more than a single pop can take, then close the sending side.
+        let data: Vec<u8> = (0..(3 * limits::RECVBUF_SIZE_MAX)).map(|i| i as u8).collect();
+        clients[0].write_all(&data)?;
+        clients[0].shutdown(Shutdown::Write)?;
+
+        let qt: QToken = libos.splice(qds[0], qds[1], None)?;
+        let task: OperationTask = wait(&mut libos, qt)?;
+        match task.get_result() {
+            Some((_, OperationResult::Splice(nbytes))) => ensure_eq!(nbytes, data.len()),
+            _ => anyhow::bail!("splice should have completed"),
+        }
+
+        // Everything made it to the other end.
+        let mut received: Vec<u8> = vec![0; data.len()];
+        clients[1].read_exact(&mut received)?;
+        ensure_eq!(received, data);
+
+        Ok(())
+    }
+
+    /// Tests that a batch pop hands out all datagrams that are already queued on the socket at once.
+    #[test]
+    fn pop_batch_receives_queued_datagrams() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        let local: SocketAddrV4 = SocketAddrV4::new("127.0.0.1".parse()?, 23460);
+        libos.bind(qd, SocketAddr::V4(local))?;
+
+        // Queue a few datagrams before popping.
+        let sender: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+        for i in 0..4u8 {
+            sender.send_to(&vec![i; 16 + i as usize], local)?;
+        }
+
+        let qt: QToken = libos.pop_batch(qd, 8)?;
+        let task: OperationTask = wait(&mut libos, qt)?;
+        let datagrams: Vec<(Option<SocketAddrV4>, DemiBuffer)> = match task.get_result() {
+            Some((_, OperationResult::PopBatch(datagrams))) => datagrams,
+            _ => anyhow::bail!("pop_batch should have completed"),
+        };
+        ensure_eq!(datagrams.len(), 4);
+        for (i, (addr, buf)) in datagrams.iter().enumerate() {
+            ensure_eq!(addr.map(SocketAddr::V4), Some(sender.local_addr()?));
+            ensure_eq!(buf.len(), 16 + i);
+            ensure_eq!(buf.iter().all(|b| *b == i as u8), true);
+        }
+
+        Ok(())
+    }
+
+    /// Tests that a pop on an idle socket fails with `ETIMEDOUT` once the clock of the runtime reaches its deadline, and
+    /// not before.
+    #[test]
+    fn pop_deadline_expires() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let mut runtime: SharedDemiRuntime = libos.runtime.clone();
+        runtime.set_manual_clock(true);
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 0)?;
+        libos.bind(qd, SocketAddr::V4(SocketAddrV4::new("127.0.0.1".parse()?, 23458)))?;
+
+        let timeout: Duration = Duration::from_millis(100);
+        let qt: QToken = libos.pop(qd, None, Some(runtime.get_now() + timeout))?;
+
+        // Nothing arrives, but the deadline is not there yet.
+        for _ in 0..1024 {
+            libos.reap_completions();
+            runtime.poll_and_advance_clock();
+        }
+        ensure_eq!(runtime.from_task_id(qt)?.has_completed(), false);
+
+        // Once the deadline is reached, the pop is canceled, which takes a round trip through the kernel.
+        runtime.advance_clock_by(timeout);
+        let task: OperationTask = wait(&mut libos, qt)?;
+        match task.get_result() {
+            Some((_, OperationResult::Failed(e))) => ensure_eq!(e.errno, libc::ETIMEDOUT),
+            _ => anyhow::bail!("pop should have timed out"),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that a duplicated socket has a user context of its own, and keeps working once the queue descriptor that it
+    /// was duplicated from is closed.
+    #[test]
+    fn dup_survives_close_of_original() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let runtime: SharedDemiRuntime = libos.runtime.clone();
+        let local: SocketAddr = SocketAddr::V4(SocketAddrV4::new("127.0.0.1".parse()?, 23465));
+        let listen_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+        let mut client: TcpStream = TcpStream::connect(local)?;
+        let (qd, _): (QDesc, SocketAddrV4) = wait_accept(&mut libos, listen_qd)?;
+
+        // The duplicate starts without a user context, and setting one leaves the original alone.
+        libos.set_user_context(qd, 42)?;
+        let dup_qd: QDesc = libos.dup(qd)?;
+        ensure_eq!(libos.get_user_context(dup_qd)?, 0);
+        libos.set_user_context(dup_qd, 7)?;
+        ensure_eq!(libos.get_user_context(qd)?, 42);
+
+        // Closing the original leaves the socket open for the duplicate.
+        libos.close(qd)?;
+        client.write_all(b"hello")?;
+        let qt: QToken = libos.pop(dup_qd, None, None)?;
+        let task: OperationTask = wait(&mut libos, qt)?;
+        match task.get_result() {
+            Some((_, OperationResult::Pop(_, buf))) => ensure_eq!(&buf[..], b"hello"),
+            _ => anyhow::bail!("pop should have completed"),
+        }
+
+        let sga: demi_sgarray_t = runtime.into_sgarray(DemiBuffer::from_slice(b"world")?)?;
+        let qt: QToken = libos.push(dup_qd, &sga)?;
+        let task: OperationTask = wait(&mut libos, qt)?;
+        runtime.sgafree(sga)?;
+        match task.get_result() {
+            Some((_, OperationResult::Push)) => {},
+            _ => anyhow::bail!("push should have completed"),
+        }
+        let mut received: [u8; 5] = [0; 5];
+        client.read_exact(&mut received)?;
+        ensure_eq!(&received, b"world");
+
+        Ok(())
+    }
+
+    /// Tests that the user context of a queue reads back as it was set, and that it does not outlive the queue.
+    #[test]
+    fn user_context_is_cleared_on_close() -> Result<()> {
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+
+        // A new queue has no user context, and reads back the last one that was set.
+        let qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        ensure_eq!(libos.get_user_context(qd)?, 0);
+        libos.set_user_context(qd, 0xdead_beef)?;
+        ensure_eq!(libos.get_user_context(qd)?, 0xdead_beef);
+        libos.set_user_context(qd, u64::MAX)?;
+        ensure_eq!(libos.get_user_context(qd)?, u64::MAX);
+
+        // Once closed, the queue has no user context anymore, and a new queue in its place starts without one.
+        libos.close(qd)?;
+        ensure_eq!(libos.get_user_context(qd).is_err(), true);
+        ensure_eq!(libos.set_user_context(qd, 1).is_err(), true);
+        let new_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        ensure_eq!(libos.get_user_context(new_qd)?, 0);
+
+        Ok(())
+    }
+
+    /// Tests that a vectored pop fills segments that live in buffers of their own in order, so that a header and its
+    /// payload land apart, and that each segment is reported with the bytes that were read into it.
+    #[test]
+    fn pop_vectored_into_separate_buffers() -> Result<()> {
+        const HEADER_SIZE: usize = 4;
+        const PAYLOAD_SIZE: usize = 12;
+        let mut libos: CatcollarLibOS = new_libos_or_skip!("catcollar: {}");
+        let local: SocketAddr = SocketAddr::V4(SocketAddrV4::new("127.0.0.1".parse()?, 23467));
+        let listen_qd: QDesc = libos.socket(libc::AF_INET, libc::SOCK_STREAM, 0)?;
+        libos.bind(listen_qd, local)?;
+        libos.listen(listen_qd, 1)?;
+        let mut client: TcpStream = TcpStream::connect(local)?;
+        let (qd, _): (QDesc, SocketAddrV4) = wait_accept(&mut libos, listen_qd)?;
+
+        // The payload buffer is larger than the payload, so only part of it is filled.
+        let data: Vec<u8> = (0..(HEADER_SIZE + PAYLOAD_SIZE)).map(|i| i as u8).collect();
+        client.write_all(&data)?;
+        let header: demi_sgarray_t = libos.sgaalloc(HEADER_SIZE)?;
+        let payload: demi_sgarray_t = libos.sgaalloc(2 * PAYLOAD_SIZE)?;
+        let qt: QToken = libos.pop_vectored(qd, &[header, payload])?;
+        let task: OperationTask = wait(&mut libos, qt)?;
+        match task.get_result() {
+            Some((_, OperationResult::PopBatch(bufs))) => {
+                ensure_eq!(bufs.len(), 2);
+                ensure_eq!(&bufs[0].1[..], &data[..HEADER_SIZE]);
+                ensure_eq!(&bufs[1].1[..], &data[HEADER_SIZE..]);
+            },
+            _ => anyhow::bail!("vectored pop should have completed"),
+        }
+        libos.sgafree(header)?;
+        libos.sgafree(payload)?;
+
+        Ok(())
+    }
+}
@@ -5,12 +5,18 @@
 // Imports
 //======================================================================================================================
 
-use crate::runtime::{
-    queue::{
-        IoQueue,
-        NetworkQueue,
+use crate::{
+    catcollar::{
+        rate_limiter::SharedRateLimiter,
+        receiver::SharedContinuousReceiver,
+    },
+    runtime::{
+        queue::{
+            IoQueue,
+            NetworkQueue,
+        },
+        QType,
     },
-    QType,
 };
 use ::std::{
     any::Any,
@@ -23,11 +29,16 @@ use ::std::{
 //======================================================================================================================
 
 /// Catcollar control block: meta data stored per queue.
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct CatcollarQueue {
     qtype: QType,
     fd: Option<RawFd>,
-    addr: Option<SocketAddrV4>,
+    local: Option<SocketAddrV4>,
+    remote: Option<SocketAddrV4>,
+    user_context: u64,
+    quickack: bool,
+    rate_limiter: Option<SharedRateLimiter>,
+    receiver: Option<SharedContinuousReceiver>,
 }
 
 //======================================================================================================================
@@ -40,7 +51,28 @@ impl CatcollarQueue {
         Self {
             qtype: qtype,
             fd: None,
-            addr: None,
+            local: None,
+            remote: None,
+            user_context: 0,
+            quickack: false,
+            rate_limiter: None,
+            receiver: None,
+        }
+    }
+
+    /// Creates a new metadata structure for a queue that refers to the same socket as `self` through file descriptor
+    /// `fd`. Only what belongs to the socket is carried over: the new queue starts without a user context, a rate limit,
+    /// or a continuous receiver of its own.
+    pub fn duplicate(&self, fd: RawFd) -> Self {
+        Self {
+            qtype: self.qtype,
+            fd: Some(fd),
+            local: self.local,
+            remote: self.remote,
+            user_context: 0,
+            quickack: self.quickack,
+            rate_limiter: None,
+            receiver: None,
         }
     }
 
@@ -54,9 +86,54 @@ impl CatcollarQueue {
         self.fd = Some(fd);
     }
 
-    /// Sets underlying socket address.
+    /// Sets the local address of the underlying socket.
     pub fn set_addr(&mut self, addr: SocketAddrV4) {
-        self.addr = Some(addr);
+        self.local = Some(addr);
+    }
+
+    /// Sets the address of the remote end of the underlying socket.
+    pub fn set_remote(&mut self, addr: SocketAddrV4) {
+        self.remote = Some(addr);
+    }
+
+    /// Gets the opaque user context attached to the queue.
+    pub fn get_user_context(&self) -> u64 {
+        self.user_context
+    }
+
+    /// Attaches an opaque user context to the queue.
+    pub fn set_user_context(&mut self, ctx: u64) {
+        self.user_context = ctx;
+    }
+
+    /// Checks whether quick ACK mode was requested for the queue.
+    pub fn get_quickack(&self) -> bool {
+        self.quickack
+    }
+
+    /// Records whether quick ACK mode was requested for the queue.
+    pub fn set_quickack(&mut self, enable: bool) {
+        self.quickack = enable;
+    }
+
+    /// Gets the rate limiter of the queue, if any.
+    pub fn get_rate_limiter(&self) -> Option<SharedRateLimiter> {
+        self.rate_limiter.clone()
+    }
+
+    /// Installs or removes the rate limiter of the queue.
+    pub fn set_rate_limiter(&mut self, rate_limiter: Option<SharedRateLimiter>) {
+        self.rate_limiter = rate_limiter;
+    }
+
+    /// Gets the continuous receiver of the queue, if continuous receive mode is on.
+    pub fn get_receiver(&self) -> Option<SharedContinuousReceiver> {
+        self.receiver.clone()
+    }
+
+    /// Installs the continuous receiver of the queue.
+    pub fn set_receiver(&mut self, receiver: SharedContinuousReceiver) {
+        self.receiver = Some(receiver);
     }
 }
 
@@ -85,11 +162,11 @@ impl IoQueue for CatcollarQueue {
 impl NetworkQueue for CatcollarQueue {
     /// Returns the local address to which the target queue is bound.
     fn local(&self) -> Option<SocketAddrV4> {
-        self.addr
+        self.local
     }
 
     /// Returns the remote address to which the target queue is connected to.
     fn remote(&self) -> Option<SocketAddrV4> {
-        None
+        self.remote
     }
 }
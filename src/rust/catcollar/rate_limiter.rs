@@ -0,0 +1,158 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    scheduler::Yielder,
+    SharedDemiRuntime,
+    SharedObject,
+};
+use ::std::{
+    cmp,
+    time::Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Smallest burst size of a rate limiter (in bytes), so that low rates do not hold back every single push.
+const MIN_BURST_SIZE: u64 = 64 * 1024;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Token bucket that caps the send rate of a queue. Tokens are bytes: they accumulate at the configured rate, up to
+/// the burst size, and each push consumes as many tokens as it sends. A push may overdraw the bucket, so that buffers
+/// larger than the burst size still go through, but subsequent pushes wait until the debt is paid back.
+pub struct TokenBucket {
+    /// Refill rate (in bytes per second).
+    rate: u64,
+    /// Maximum number of tokens in the bucket.
+    burst: u64,
+    /// Number of tokens in the bucket. Negative if the bucket was overdrawn.
+    tokens: i64,
+    /// Last time at which tokens were added to the bucket.
+    last_refill: Instant,
+}
+
+/// Rate limiter that is shared by all push coroutines of a queue.
+#[derive(Clone)]
+pub struct SharedRateLimiter {
+    /// Shared DemiRuntime, which provides the clock.
+    runtime: SharedDemiRuntime,
+    /// Underlying token bucket.
+    bucket: SharedObject<TokenBucket>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TokenBucket {
+    /// Creates a full token bucket that is refilled at `rate` bytes per second.
+    pub fn new(rate: u64, now: Instant) -> Self {
+        // Allow bursts of 100 ms worth of data.
+        let burst: u64 = cmp::max(rate / 10, MIN_BURST_SIZE);
+        Self {
+            rate,
+            burst,
+            tokens: burst as i64,
+            last_refill: now,
+        }
+    }
+
+    /// Takes `len` tokens from the bucket. This fails if the bucket is empty at time `now`.
+    pub fn try_consume(&mut self, len: usize, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens <= 0 {
+            return false;
+        }
+        self.tokens -= len as i64;
+        true
+    }
+
+    /// Adds the tokens accumulated since the last refill.
+    fn refill(&mut self, now: Instant) {
+        if now <= self.last_refill {
+            return;
+        }
+        let elapsed_ns: u128 = (now - self.last_refill).as_nanos();
+        let new_tokens: u128 = elapsed_ns * (self.rate as u128) / 1_000_000_000;
+        // Keep accumulating time until at least one token is due.
+        if new_tokens == 0 {
+            return;
+        }
+        self.tokens = cmp::min((self.tokens as i128) + (new_tokens as i128), self.burst as i128) as i64;
+        self.last_refill = now;
+    }
+}
+
+impl SharedRateLimiter {
+    /// Creates a rate limiter that caps the send rate to `rate` bytes per second.
+    pub fn new(runtime: SharedDemiRuntime, rate: u64) -> Self {
+        let now: Instant = runtime.get_now();
+        Self {
+            runtime,
+            bucket: SharedObject::new(TokenBucket::new(rate, now)),
+        }
+    }
+
+    /// Waits until `len` bytes may be sent.
+    pub async fn acquire(&mut self, len: usize, yielder: &Yielder) -> Result<(), Fail> {
+        loop {
+            let now: Instant = self.runtime.get_now();
+            if self.bucket.try_consume(len, now) {
+                return Ok(());
+            }
+            yielder.yield_once().await?;
+        }
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::TokenBucket;
+    use ::anyhow::Result;
+    use ::std::time::{
+        Duration,
+        Instant,
+    };
+
+    // Pushes as fast as possible through a rate-limited bucket and checks the achieved throughput against the cap.
+    #[test]
+    fn throughput_stays_under_cap() -> Result<()> {
+        const RATE: u64 = 1024 * 1024;
+        const PUSH_SIZE: usize = 4096;
+        const DURATION: Duration = Duration::from_secs(10);
+        const STEP: Duration = Duration::from_micros(100);
+
+        let start: Instant = Instant::now();
+        let mut now: Instant = start;
+        let mut bucket: TokenBucket = TokenBucket::new(RATE, now);
+        let mut nbytes: u64 = 0;
+        while now - start < DURATION {
+            while bucket.try_consume(PUSH_SIZE, now) {
+                nbytes += PUSH_SIZE as u64;
+            }
+            now += STEP;
+        }
+
+        // Everything that went through is accounted for by the rate, the initial burst and one overdrawn push.
+        let cap: u64 = RATE * DURATION.as_secs() + bucket.burst + PUSH_SIZE as u64;
+        crate::ensure_eq!(nbytes <= cap, true);
+        // The limiter does not throttle much more than requested either.
+        crate::ensure_eq!(nbytes >= RATE * DURATION.as_secs() * 9 / 10, true);
+
+        Ok(())
+    }
+}
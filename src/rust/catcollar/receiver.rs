@@ -0,0 +1,151 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    memory::DemiBuffer,
+    scheduler::{
+        Yielder,
+        YielderHandle,
+    },
+    SharedObject,
+};
+use ::std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Received data that is waiting to be popped, along with the address of the sender.
+type Received = (Option<SocketAddrV4>, DemiBuffer);
+
+/// Backlog of data that was received on a queue in continuous receive mode, but not popped yet.
+pub struct RecvBacklog {
+    /// Data that was received, in order of arrival.
+    ready: VecDeque<Received>,
+    /// Failure of the last receive operation, if any. Pops see it once they have drained all data that came in before.
+    error: Option<Fail>,
+    /// Did the peer shut down its side of the connection? Pops see the end of file once they have drained all data that
+    /// came in before, as many times as they are issued.
+    eof: bool,
+    /// Maximum number of receive buffers that the backlog holds.
+    capacity: usize,
+    /// Was the queue closed?
+    stopped: bool,
+    /// Is the background receive coroutine sleeping until pops make room in the backlog?
+    waiting_for_room: bool,
+    /// Handle to wake the background receive coroutine when the queue is closed or when room is made in the backlog.
+    yielder_handle: YielderHandle,
+}
+
+/// Continuous receiver that is shared by the background receive coroutine and all pop coroutines of a queue.
+#[derive(Clone)]
+pub struct SharedContinuousReceiver(SharedObject<RecvBacklog>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SharedContinuousReceiver {
+    /// Creates a continuous receiver that holds up to `capacity` receive buffers. `yielder_handle` is the handle of the
+    /// background coroutine that feeds the receiver.
+    pub fn new(capacity: usize, yielder_handle: YielderHandle) -> Self {
+        Self(SharedObject::new(RecvBacklog {
+            ready: VecDeque::with_capacity(capacity),
+            error: None,
+            eof: false,
+            capacity,
+            stopped: false,
+            waiting_for_room: false,
+            yielder_handle,
+        }))
+    }
+
+    /// Checks if the backlog is out of receive buffers.
+    pub fn is_full(&self) -> bool {
+        self.0.ready.len() >= self.0.capacity
+    }
+
+    /// Checks if a pop would complete right away.
+    pub fn is_readable(&self) -> bool {
+        !self.0.ready.is_empty() || self.0.error.is_some() || self.0.eof
+    }
+
+    /// Checks if the receiver was stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.0.stopped
+    }
+
+    /// Stops the receiver and cancels the receive operation that is in flight. Data that is still in the backlog is
+    /// dropped.
+    pub fn stop(&mut self) {
+        self.0.stopped = true;
+        self.0.ready.clear();
+        self.0
+            .yielder_handle
+            .wake_with(Err(Fail::new(libc::ECANCELED, "queue was closed")));
+    }
+
+    /// Appends the result of a receive operation to the backlog.
+    pub fn push(&mut self, result: Result<Received, Fail>) {
+        match result {
+            Ok(received) => self.0.ready.push_back(received),
+            Err(e) => self.0.error = Some(e),
+        }
+    }
+
+    /// Records that the peer shut down its side of the connection, so nothing more is to be received.
+    pub fn push_eof(&mut self) {
+        self.0.eof = true;
+    }
+
+    /// Sleeps until pops make room in the backlog, or until the receiver is stopped. This is meant for the background
+    /// coroutine that feeds the receiver, which `yielder` must belong to.
+    pub async fn wait_for_room(&mut self, yielder: &Yielder) -> Result<(), Fail> {
+        while self.is_full() {
+            self.0.waiting_for_room = true;
+            let result: Result<(), Fail> = yielder.yield_until_wake().await;
+            self.0.waiting_for_room = false;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Waits for the oldest data in the backlog and takes up to `size` bytes of it. On a stream socket, bytes in excess
+    /// stay in the backlog for the next pop. On a datagram socket, they are discarded, as recvmsg() would.
+    pub async fn pop(&mut self, size: usize, stream: bool, yielder: &Yielder) -> Result<Received, Fail> {
+        loop {
+            if let Some((addr, mut buf)) = self.0.ready.pop_front() {
+                if buf.len() > size {
+                    let rest: DemiBuffer = buf.split_back(size)?;
+                    if stream {
+                        self.0.ready.push_front((addr, rest));
+                    }
+                }
+                // Let the background receive coroutine know that there is room again.
+                if self.0.waiting_for_room && !self.is_full() {
+                    self.0.waiting_for_room = false;
+                    self.0.yielder_handle.wake_with(Ok(()));
+                }
+                return Ok((addr, buf));
+            }
+            if let Some(e) = &self.0.error {
+                return Err(e.clone());
+            }
+            if self.0.eof {
+                return Ok((None, DemiBuffer::new(0)));
+            }
+            if self.0.stopped {
+                return Err(Fail::new(libc::EBADF, "queue was closed"));
+            }
+            yielder.yield_once().await?;
+        }
+    }
+}
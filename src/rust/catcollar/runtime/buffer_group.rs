@@ -0,0 +1,130 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    memory::DemiBuffer,
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Group of receive buffers that are provided to the kernel, so that it picks one whenever data arrives instead of
+/// having a buffer set up for each pop. The data of a selected buffer is handed to the user without a copy, and the
+/// buffer is handed back to the kernel once the user has released it.
+pub struct RecvBufferGroup {
+    /// Group ID.
+    bgid: u16,
+    /// Size of each buffer (in bytes).
+    size: usize,
+    /// Buffers of the group, indexed by buffer ID.
+    buffers: Vec<DemiBuffer>,
+    /// IDs of the buffers that were selected by the kernel and are still referenced by the user.
+    in_use: Vec<u16>,
+}
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+impl RecvBufferGroup {
+    /// Allocates `count` buffers of `size` bytes each for the group `bgid`.
+    pub fn new(bgid: u16, count: u16, size: u16) -> Self {
+        Self {
+            bgid,
+            size: size as usize,
+            buffers: (0..count).map(|_| DemiBuffer::new(size)).collect(),
+            in_use: Vec::new(),
+        }
+    }
+
+    /// Gets the ID of the group.
+    pub fn get_bgid(&self) -> u16 {
+        self.bgid
+    }
+
+    /// Gets the size of each buffer of the group.
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// Gets the number of buffers in the group.
+    pub fn get_count(&self) -> u16 {
+        self.buffers.len() as u16
+    }
+
+    /// Gets a pointer to the data area of the buffer `bid`, which is handed to the kernel.
+    pub fn get_ptr(&self, bid: u16) -> *mut u8 {
+        self.buffers[bid as usize].as_ptr() as *mut u8
+    }
+
+    /// Takes the first `nbytes` bytes that the kernel received into the buffer `bid`. The returned buffer shares the
+    /// data of the group's buffer, which stays out of the kernel's reach until it is released.
+    pub fn take(&mut self, bid: u16, nbytes: usize) -> Result<DemiBuffer, Fail> {
+        let buf: &DemiBuffer = match self.buffers.get(bid as usize) {
+            Some(buf) => buf,
+            None => {
+                let cause: String = format!("invalid buffer id (bgid={:?}, bid={:?})", self.bgid, bid);
+                error!("take(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        };
+        debug_assert!(nbytes <= self.size);
+        let mut clone: DemiBuffer = buf.clone();
+        clone.trim(self.size - nbytes)?;
+        self.in_use.push(bid);
+        Ok(clone)
+    }
+
+    /// Removes the buffers that are no longer referenced by the user from the set of buffers in use, and returns their
+    /// IDs, so that they can be handed back to the kernel.
+    pub fn reclaim(&mut self) -> Vec<u16> {
+        let buffers: &Vec<DemiBuffer> = &self.buffers;
+        let (released, in_use): (Vec<u16>, Vec<u16>) = self
+            .in_use
+            .drain(..)
+            .partition(|bid| !buffers[*bid as usize].is_shared());
+        self.in_use = in_use;
+        released
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::RecvBufferGroup;
+    use crate::runtime::memory::DemiBuffer;
+    use ::anyhow::Result;
+
+    // Buffers are only reclaimed once the user has released the data that was received into them.
+    #[test]
+    fn reclaim_after_release() -> Result<()> {
+        let mut group: RecvBufferGroup = RecvBufferGroup::new(0, 4, 64);
+        crate::ensure_eq!(group.get_count(), 4);
+
+        let first: DemiBuffer = group.take(1, 16)?;
+        crate::ensure_eq!(first.len(), 16);
+        crate::ensure_eq!(first.as_ptr(), group.get_ptr(1) as *const u8);
+        let second: DemiBuffer = group.take(3, 64)?;
+        crate::ensure_eq!(group.reclaim().is_empty(), true);
+
+        drop(second);
+        crate::ensure_eq!(group.reclaim(), vec![3]);
+        drop(first);
+        crate::ensure_eq!(group.reclaim(), vec![1]);
+        crate::ensure_eq!(group.reclaim().is_empty(), true);
+
+        // Unknown buffers are rejected.
+        crate::ensure_eq!(group.take(4, 16).is_err(), true);
+
+        Ok(())
+    }
+}
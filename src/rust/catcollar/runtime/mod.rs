@@ -1,12 +1,24 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod buffer_group;
 mod network;
+mod request;
+
+//==============================================================================
+// Exports
+//==============================================================================
+
+pub use self::request::RequestId;
 
 //==============================================================================
 // Imports
 //==============================================================================
 
+use self::{
+    buffer_group::RecvBufferGroup,
+    request::RequestTable,
+};
 use super::iouring::IoUring;
 use crate::{
     pal::{
@@ -18,23 +30,33 @@ use crate::{
         liburing,
         memory::{
             DemiBuffer,
+            DemiBufferChain,
             MemoryRuntime,
+            SharedBufferPool,
+        },
+        scheduler::{
+            Scheduler,
+            Yielder,
+            YielderHandle,
         },
-        scheduler::Scheduler,
+        timer::SharedTimer,
         SharedObject,
     },
 };
+use ::futures::FutureExt;
 use ::std::{
-    collections::{
-        HashMap,
-        HashSet,
-    },
+    collections::HashMap,
+    ffi::c_void,
     net::SocketAddrV4,
     ops::{
         Deref,
         DerefMut,
     },
     os::unix::prelude::RawFd,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 //==============================================================================
@@ -44,24 +66,29 @@ use ::std::{
 /// Number of slots in an I/O User ring.
 const CATCOLLAR_NUM_RINGS: u32 = 128;
 
+/// ID of the provided-buffer group that is used for receiving data.
+const CATCOLLAR_RECV_BUFFER_GROUP_ID: u16 = 0;
+
 //==============================================================================
 // Structures
 //==============================================================================
 
-/// Request ID
-#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
-pub struct RequestId(pub *const liburing::msghdr);
-
 /// I/O User Ring Runtime
 pub struct IoUringRuntime {
     /// Scheduler
     pub scheduler: Scheduler,
     /// Underlying io_uring.
     io_uring: IoUring,
-    /// Pending requests.
-    pending: HashSet<RequestId>,
-    /// Completed requests.
-    completed: HashMap<RequestId, (Option<SocketAddrV4>, i32)>,
+    /// Requests that are in flight or whose result was not peeked yet.
+    requests: RequestTable,
+    /// Group of buffers provided to the kernel for receiving data, if any.
+    recv_buffer_group: Option<RecvBufferGroup>,
+    /// IDs of the provided buffers that were selected by completed requests.
+    selected: HashMap<RequestId, u16>,
+    /// Pool of fixed-size chunks that receive buffers are drawn from, if any.
+    recv_buffer_pool: Option<SharedBufferPool>,
+    /// Coroutines that sleep until their request completes.
+    waiters: HashMap<RequestId, YielderHandle>,
 }
 
 #[derive(Clone)]
@@ -73,69 +100,332 @@ pub struct SharedIoUringRuntime(SharedObject<IoUringRuntime>);
 
 /// Associate Functions for I/O User Ring Runtime
 impl SharedIoUringRuntime {
-    /// Pushes a buffer to the target I/O user ring.
-    pub fn push(&mut self, sockfd: RawFd, buf: DemiBuffer) -> Result<RequestId, Fail> {
-        let msg_ptr: *const liburing::msghdr = self.io_uring.push(sockfd, buf)?;
-        let request_id: RequestId = RequestId(msg_ptr);
-        self.pending.insert(request_id);
-        Ok(request_id)
+    /// Creates an I/O user ring runtime. Fails with `ENOSYS` if the kernel does not support io_uring.
+    pub fn new() -> Result<Self, Fail> {
+        let io_uring: IoUring = IoUring::new(CATCOLLAR_NUM_RINGS)?;
+        Ok(Self(SharedObject::<IoUringRuntime>::new(IoUringRuntime {
+            scheduler: Scheduler::default(),
+            io_uring: io_uring,
+            requests: RequestTable::new(CATCOLLAR_NUM_RINGS),
+            recv_buffer_group: None,
+            selected: HashMap::new(),
+            recv_buffer_pool: None,
+            waiters: HashMap::new(),
+        })))
+    }
+
+    /// Registers a group of `count` receive buffers of `size` bytes each with the kernel. Once registered, pops may
+    /// have the kernel pick a buffer from the group (see `pop_selected()`) instead of supplying one. Such pops do not
+    /// report the source address, so they are only meant for stream sockets.
+    pub fn register_recv_buffer_group(&mut self, count: u16, size: u16) -> Result<(), Fail> {
+        if count == 0 || size == 0 {
+            let cause: String = format!("invalid receive buffer group (count={:?}, size={:?})", count, size);
+            error!("register_recv_buffer_group(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if self.recv_buffer_group.is_some() {
+            let cause: &str = "a receive buffer group is already registered";
+            error!("register_recv_buffer_group(): {}", cause);
+            return Err(Fail::new(libc::EBUSY, cause));
+        }
+
+        let group: RecvBufferGroup = RecvBufferGroup::new(CATCOLLAR_RECV_BUFFER_GROUP_ID, count, size);
+        // Buffers are allocated separately, so they are handed to the kernel one at a time.
+        for bid in 0..group.get_count() {
+            self.io_uring
+                .provide_buffers(group.get_ptr(bid), group.get_size(), 1, group.get_bgid(), bid)?;
+        }
+        self.recv_buffer_group = Some(group);
+        Ok(())
+    }
+
+    /// Gets the size of the buffers in the receive buffer group, if one is registered.
+    pub fn get_recv_buffer_size(&self) -> Option<usize> {
+        self.recv_buffer_group.as_ref().map(|group| group.get_size())
+    }
+
+    /// Sets up a pool that keeps up to `count` free receive chunks of `chunk_size` bytes each. Once set up, receive
+    /// buffers are drawn from the pool, and go back to it when the user frees the scatter-gather array that holds them.
+    /// Buffers are allocated off of the heap when the pool runs out of them.
+    pub fn set_recv_buffer_pool(&mut self, chunk_size: u16, count: usize) -> Result<(), Fail> {
+        if chunk_size == 0 || count == 0 {
+            let cause: String = format!(
+                "invalid receive buffer pool (chunk_size={:?}, count={:?})",
+                chunk_size, count
+            );
+            error!("set_recv_buffer_pool(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if self.recv_buffer_pool.is_some() {
+            let cause: &str = "a receive buffer pool is already set up";
+            error!("set_recv_buffer_pool(): {}", cause);
+            return Err(Fail::new(libc::EBUSY, cause));
+        }
+
+        self.recv_buffer_pool = Some(SharedBufferPool::new(chunk_size, count));
+        Ok(())
+    }
+
+    /// Gets the size of the chunks in the receive buffer pool, if one is set up.
+    pub fn get_recv_chunk_size(&self) -> Option<usize> {
+        self.recv_buffer_pool.as_ref().map(|pool| pool.buffer_size() as usize)
+    }
+
+    /// Allocates a buffer of `size` bytes for receiving data, preferably from the receive buffer pool.
+    pub fn alloc_recv_buffer(&self, size: usize) -> DemiBuffer {
+        match self.recv_buffer_pool.clone() {
+            Some(mut pool) => pool.alloc(size as u16),
+            None => DemiBuffer::new(size as u16),
+        }
+    }
+
+    /// Pops up to `len` bytes from the target I/O user ring into a buffer of the receive buffer group. Buffers that were
+    /// released by the user since the last pop are handed back to the kernel first.
+    pub fn pop_selected(&mut self, sockfd: RawFd, len: usize) -> Result<RequestId, Fail> {
+        let runtime: &mut IoUringRuntime = self.deref_mut();
+        let group: &mut RecvBufferGroup = match runtime.recv_buffer_group.as_mut() {
+            Some(group) => group,
+            None => {
+                let cause: &str = "no receive buffer group is registered";
+                error!("pop_selected(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, cause));
+            },
+        };
+        for bid in group.reclaim() {
+            runtime
+                .io_uring
+                .provide_buffers(group.get_ptr(bid), group.get_size(), 1, group.get_bgid(), bid)?;
+        }
+        let len: usize = len.min(group.get_size());
+        let bgid: u16 = group.get_bgid();
+        self.submit(|io_uring, user_data| io_uring.pop_selected(sockfd, len, bgid, user_data))
+    }
+
+    /// Takes the `nbytes` bytes received by the completed request `request_id` out of the buffer that the kernel picked
+    /// from the receive buffer group. Fails with `ENOBUFS` if no buffer was picked for that request.
+    pub fn take_selected(&mut self, request_id: RequestId, nbytes: usize) -> Result<DemiBuffer, Fail> {
+        match (self.selected.remove(&request_id), self.recv_buffer_group.as_mut()) {
+            (Some(bid), Some(group)) => group.take(bid, nbytes),
+            // A zero-length read may complete without consuming a buffer.
+            (None, _) if nbytes == 0 => Ok(DemiBuffer::new(0)),
+            _ => {
+                let cause: &str = "no receive buffer was selected";
+                error!("take_selected(): {}", cause);
+                Err(Fail::new(libc::ENOBUFS, cause))
+            },
+        }
+    }
+
+    /// Pushes a chain of buffers to the target I/O user ring.
+    pub fn push(&mut self, sockfd: RawFd, bufs: &DemiBufferChain) -> Result<RequestId, Fail> {
+        self.submit(|io_uring, user_data| io_uring.push(sockfd, bufs, user_data))
+    }
+
+    /// Pushes a chain of buffers to the target I/O user ring, cancelling the operation if it does not complete within
+    /// `timeout`.
+    pub fn push_timeout(
+        &mut self,
+        sockfd: RawFd,
+        bufs: &DemiBufferChain,
+        timeout: Duration,
+    ) -> Result<RequestId, Fail> {
+        self.submit(|io_uring, user_data| io_uring.push_timeout(sockfd, bufs, timeout, user_data))
     }
 
     /// Pushes a buffer to the target I/O user ring.
     pub fn pushto(&mut self, sockfd: i32, addr: SocketAddrV4, buf: DemiBuffer) -> Result<RequestId, Fail> {
-        let msg_ptr: *const liburing::msghdr = self.io_uring.pushto(sockfd, addr, buf)?;
-        let request_id: RequestId = RequestId(msg_ptr);
-        self.pending.insert(request_id);
-        Ok(request_id)
+        self.submit(|io_uring, user_data| io_uring.pushto(sockfd, addr, buf, user_data))
     }
 
     /// Pops a buffer from the target I/O user ring.
     pub fn pop(&mut self, sockfd: RawFd, buf: DemiBuffer) -> Result<RequestId, Fail> {
-        let msg_ptr: *const liburing::msghdr = self.io_uring.pop(sockfd, buf)?;
-        let request_id: RequestId = RequestId(msg_ptr);
-        self.pending.insert(request_id);
-        Ok(request_id)
+        self.submit(|io_uring, user_data| io_uring.pop(sockfd, buf, user_data))
     }
 
-    /// Peeks for the completion of an operation in the target I/O user ring.
+    /// Pops data from the target I/O user ring into multiple buffers at once.
+    pub fn pop_vectored(&mut self, sockfd: RawFd, segments: &[(*mut u8, usize)]) -> Result<RequestId, Fail> {
+        self.submit(|io_uring, user_data| io_uring.pop_vectored(sockfd, segments, user_data))
+    }
+
+    /// Peeks for the completion of an operation in the target I/O user ring. This reports `EAGAIN` if the operation
+    /// has not completed yet. Once its result is returned, `request_id` is no longer valid.
     pub fn peek(&mut self, request_id: RequestId) -> Result<(Option<SocketAddrV4>, i32), Fail> {
-        // Check if pending request has completed.
-        match self.completed.remove(&request_id) {
-            // The target request has already completed.
-            Some(result) => Ok(result),
-            // The target request may not be completed.
+        // Check if the target request has already completed.
+        if let Some(result) = self.requests.take(request_id) {
+            return Ok(result);
+        }
+        if !self.requests.is_pending(request_id) {
+            let cause: String = format!("unknown request (request_id={:?})", request_id);
+            error!("peek(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        // Peek the underlying io_uring.
+        let (user_data, size, flags): (u64, i32, u32) = self.io_uring.wait()?;
+        match self.complete(user_data, size, flags) {
+            // This is the request that we are waiting for.
+            Some(other_request_id) if other_request_id == request_id => Ok(self
+                .requests
+                .take(request_id)
+                .expect("completed request should be in the request table")),
+            // Some other operation has completed, or a linked timeout that carries no request.
+            _ => Ok((None, -libc::EAGAIN)),
+        }
+    }
+
+    /// Sleeps until an operation completes in the target I/O user ring, or until `timeout` expires, if one is given.
+    /// The completion is kept for the next [peek] on its request, so that coroutines only get polled once there is
+    /// progress to make, instead of spinning. This returns right away if no operation is pending.
+    pub fn poll_blocking(&mut self, timeout: Option<Duration>) -> Result<(), Fail> {
+        if self.requests.num_pending() == 0 {
+            return Ok(());
+        }
+        // Nothing to do if the timeout expired.
+        if let Some((user_data, size, flags)) = self.io_uring.wait_timeout(timeout)? {
+            self.complete(user_data, size, flags);
+        }
+        Ok(())
+    }
+
+    /// Waits for the request `request_id` to complete and returns its result. Unlike [peek], the calling coroutine
+    /// sleeps until the completion of its request is reaped (see [poll_completions]), instead of being polled over and
+    /// over. Once its result is returned, `request_id` is no longer valid.
+    pub async fn wait_request(
+        &mut self,
+        request_id: RequestId,
+        yielder: &Yielder,
+    ) -> Result<(Option<SocketAddrV4>, i32), Fail> {
+        loop {
+            if let Some(result) = self.requests.take(request_id) {
+                return Ok(result);
+            }
+            if !self.requests.is_pending(request_id) {
+                let cause: String = format!("unknown request (request_id={:?})", request_id);
+                error!("wait_request(): {}", cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+
+            self.waiters.insert(request_id, yielder.get_handle());
+            if let Err(e) = yielder.yield_until_wake().await {
+                self.waiters.remove(&request_id);
+                let cause: String = format!("operation canceled (err={:?})", e);
+                error!("wait_request(): {}", cause);
+                return Err(Fail::new(libc::ECANCELED, &cause));
+            }
+        }
+    }
+
+    /// Waits for the request `request_id` to complete, like [wait_request], but gives up once the clock of `timer`
+    /// reaches `deadline`. The operation is then canceled, and this fails with `ETIMEDOUT`, unless the operation
+    /// completed in the meantime.
+    pub async fn wait_request_until(
+        &mut self,
+        request_id: RequestId,
+        yielder: &Yielder,
+        timer: SharedTimer,
+        deadline: Instant,
+    ) -> Result<(Option<SocketAddrV4>, i32), Fail> {
+        let timer_yielder: Yielder = Yielder::new();
+        let expired = timer.wait_until(deadline, &timer_yielder).fuse();
+        futures::pin_mut!(expired);
+        let mut rt: SharedIoUringRuntime = self.clone();
+        let completed = rt.wait_request(request_id, yielder).fuse();
+        futures::pin_mut!(completed);
+        futures::select_biased! {
+            result = completed => return result,
+            _ = expired => {},
+        }
+
+        // The kernel may still write into the buffers of the operation until it completes, so wait for that.
+        self.io_uring.cancel(request_id.to_user_data())?;
+        match self.wait_request(request_id, yielder).await? {
+            (_, size) if size == -libc::ECANCELED => {
+                let cause: String = format!("deadline expired (request_id={:?})", request_id);
+                debug!("wait_request_until(): {}", cause);
+                Err(Fail::new(libc::ETIMEDOUT, &cause))
+            },
+            result => Ok(result),
+        }
+    }
+
+    /// Gives up on the request `request_id`, whose result is no longer wanted. If its operation is still in flight, the
+    /// kernel is asked to cancel it, and the request is released once it completes.
+    pub fn abandon(&mut self, request_id: RequestId) {
+        self.waiters.remove(&request_id);
+        if self.requests.is_pending(request_id) {
+            if let Err(e) = self.io_uring.cancel(request_id.to_user_data()) {
+                warn!(
+                    "abandon(): failed to cancel operation (request_id={:?}, err={:?})",
+                    request_id, e
+                );
+            }
+        }
+        self.requests.abandon(request_id);
+    }
+
+    /// Reaps all operations that completed in the target I/O user ring, without blocking, and wakes the coroutines
+    /// that wait for them. This returns the number of operations that were reaped.
+    pub fn poll_completions(&mut self) -> Result<usize, Fail> {
+        let mut reaped: usize = 0;
+        while self.requests.num_pending() > 0 {
+            match self.io_uring.wait_timeout(Some(Duration::ZERO))? {
+                Some((user_data, size, flags)) => {
+                    self.complete(user_data, size, flags);
+                    reaped += 1;
+                },
+                None => break,
+            }
+        }
+        Ok(reaped)
+    }
+
+    /// Reserves a request and submits its operation with `submit`, which is handed the user data of the request.
+    fn submit<F>(&mut self, submit: F) -> Result<RequestId, Fail>
+    where
+        F: FnOnce(&mut IoUring, u64) -> Result<*mut liburing::msghdr, Fail>,
+    {
+        let request_id: RequestId = self.requests.reserve();
+        match submit(&mut self.io_uring, request_id.to_user_data()) {
+            Ok(msg_ptr) => {
+                self.requests.submit(request_id, msg_ptr);
+                Ok(request_id)
+            },
+            Err(e) => {
+                self.requests.release(request_id);
+                Err(e)
+            },
+        }
+    }
+
+    /// Records the result of the completed operation that carries `user_data`, so that it is kept until it is peeked
+    /// for, and releases its message header. This returns the request of the operation, if it carries one.
+    fn complete(&mut self, user_data: u64, size: i32, flags: u32) -> Option<RequestId> {
+        let request_id: RequestId = RequestId::from_user_data(user_data)?;
+        let msg_ptr: *mut liburing::msghdr = match self.requests.get_msg(request_id) {
+            Some(msg_ptr) => msg_ptr,
             None => {
-                // Peek the underlying io_uring.
-                match self.io_uring.wait() {
-                    // Some operation has completed.
-                    Ok((other_request_id, size)) => {
-                        let msg: Box<liburing::msghdr> = unsafe { Box::from_raw(other_request_id) };
-                        let _: Box<liburing::iovec> = unsafe { Box::from_raw(msg.msg_iov) };
-                        let addr: Option<SocketAddrV4> = if msg.msg_name.is_null() {
-                            None
-                        } else {
-                            let saddr: *const SockAddr = msg.msg_name as *const SockAddr;
-                            Some(linux::sockaddr_to_socketaddrv4(unsafe { &*saddr }))
-                        };
-
-                        // This is not the request that we are waiting for.
-                        if request_id.0 != other_request_id {
-                            let other_request_id: RequestId = RequestId(other_request_id);
-                            if self.pending.remove(&other_request_id) {
-                                self.completed.insert(other_request_id, (addr, size));
-                            } else {
-                                warn!("spurious event?");
-                            }
-                        }
-
-                        // Done.
-                        Ok((addr, size))
-                    },
-                    // Something bad has happened.
-                    Err(e) => Err(e),
-                }
+                warn!("spurious event?");
+                return None;
             },
+        };
+
+        // Remember which provided buffer the operation consumed, if any.
+        if let Some(bid) = IoUring::selected_buffer(flags) {
+            self.selected.insert(request_id, bid);
+        }
+        let msg_name: *mut c_void = unsafe { (*msg_ptr).msg_name };
+        let addr: Option<SocketAddrV4> = if msg_name.is_null() {
+            None
+        } else {
+            let saddr: *const SockAddr = msg_name as *const SockAddr;
+            Some(linux::sockaddr_to_socketaddrv4(unsafe { &*saddr }))
+        };
+        unsafe { IoUring::release_msg(msg_ptr) };
+        self.requests.complete(request_id, (addr, size));
+        if let Some(mut yielder_handle) = self.waiters.remove(&request_id) {
+            yielder_handle.wake_with(Ok(()));
         }
+        Some(request_id)
     }
 }
 
@@ -144,18 +434,9 @@ impl SharedIoUringRuntime {
 //==============================================================================
 
 /// Memory Runtime Trait Implementation for IoUring Runtime
-impl MemoryRuntime for IoUringRuntime {}
-
-impl Default for SharedIoUringRuntime {
-    /// Creates an I/O user ring runtime.
-    fn default() -> Self {
-        let io_uring: IoUring = IoUring::new(CATCOLLAR_NUM_RINGS).expect("cannot create io_uring");
-        Self(SharedObject::<IoUringRuntime>::new(IoUringRuntime {
-            scheduler: Scheduler::default(),
-            io_uring: io_uring,
-            pending: HashSet::new(),
-            completed: HashMap::new(),
-        }))
+impl MemoryRuntime for IoUringRuntime {
+    fn get_buffer_pool(&self) -> Option<SharedBufferPool> {
+        self.recv_buffer_pool.clone()
     }
 }
 
@@ -172,3 +453,416 @@ impl DerefMut for SharedIoUringRuntime {
         self.0.deref_mut()
     }
 }
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RequestId,
+        SharedIoUringRuntime,
+    };
+    use crate::runtime::{
+        limits,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+        },
+        scheduler::Yielder,
+        types::demi_sgarray_t,
+    };
+    use ::anyhow::Result;
+    use ::futures::{
+        task::{
+            self,
+            ArcWake,
+        },
+        FutureExt,
+    };
+    use ::std::{
+        os::unix::prelude::RawFd,
+        sync::{
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+            Arc,
+        },
+        task::{
+            Context,
+            Poll,
+            Waker,
+        },
+        time::{
+            Duration,
+            Instant,
+        },
+    };
+    use ::test::{
+        black_box,
+        Bencher,
+    };
+
+    /// Size of the chunks in receive buffer pools.
+    const RECV_CHUNK_SIZE: u16 = 2048;
+
+    /// Creates an io_uring runtime, unless the kernel does not support io_uring.
+    fn new_runtime() -> Result<Option<SharedIoUringRuntime>> {
+        match SharedIoUringRuntime::new() {
+            Ok(runtime) => Ok(Some(runtime)),
+            Err(e) if e.errno == libc::ENOSYS => Ok(None),
+            Err(e) => anyhow::bail!("failed to create io_uring runtime: {:?}", e),
+        }
+    }
+
+    /// Waker that counts how many times it was woken.
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Gets the CPU time consumed by the calling thread so far.
+    fn thread_cpu_time() -> Duration {
+        let mut ts: libc::timespec = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) };
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+
+    // Idle polls sleep in the kernel instead of burning CPU, and a completion still wakes them up.
+    #[test]
+    fn poll_blocking_sleeps_until_completion() -> Result<()> {
+        const IDLE_TIMEOUT: Duration = Duration::from_millis(200);
+
+        let mut runtime: SharedIoUringRuntime = match SharedIoUringRuntime::new() {
+            Ok(runtime) => runtime,
+            // Nothing to test on kernels without io_uring.
+            Err(e) if e.errno == libc::ENOSYS => return Ok(()),
+            Err(e) => anyhow::bail!("failed to create io_uring runtime: {:?}", e),
+        };
+        let mut fds: [RawFd; 2] = [0; 2];
+        crate::ensure_eq!(
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) },
+            0
+        );
+
+        // Nothing is pending, so there is nothing to wait for.
+        let start: Instant = Instant::now();
+        runtime.poll_blocking(None)?;
+        crate::ensure_eq!(start.elapsed() < IDLE_TIMEOUT, true);
+
+        // Wait for data that does not come: the whole timeout elapses, but hardly any CPU time is used.
+        let request_id: RequestId = runtime.pop(fds[0], DemiBuffer::new(64))?;
+        let start: Instant = Instant::now();
+        let cpu_start: Duration = thread_cpu_time();
+        runtime.poll_blocking(Some(IDLE_TIMEOUT))?;
+        crate::ensure_eq!(start.elapsed() >= IDLE_TIMEOUT, true);
+        crate::ensure_eq!(thread_cpu_time() - cpu_start < IDLE_TIMEOUT / 10, true);
+
+        // Data arrives, which wakes up the runtime long before the timeout expires.
+        let data: &[u8] = b"hello";
+        crate::ensure_eq!(
+            unsafe { libc::send(fds[1], data.as_ptr() as *const libc::c_void, data.len(), 0) },
+            data.len() as isize
+        );
+        let start: Instant = Instant::now();
+        runtime.poll_blocking(Some(Duration::from_secs(10)))?;
+        crate::ensure_eq!(start.elapsed() < Duration::from_secs(10), true);
+        crate::ensure_eq!(runtime.peek(request_id)?.1, data.len() as i32);
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    // Completions are reported to the requests that they belong to, whatever the order in which they arrive.
+    #[test]
+    fn completions_map_to_their_requests() -> Result<()> {
+        let mut runtime: SharedIoUringRuntime = match new_runtime()? {
+            Some(runtime) => runtime,
+            // Nothing to test on kernels without io_uring.
+            None => return Ok(()),
+        };
+        let mut first_fds: [RawFd; 2] = [0; 2];
+        let mut second_fds: [RawFd; 2] = [0; 2];
+        for fds in [&mut first_fds, &mut second_fds] {
+            crate::ensure_eq!(
+                unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) },
+                0
+            );
+        }
+        let send =
+            |fd: RawFd, data: &[u8]| unsafe { libc::send(fd, data.as_ptr() as *const libc::c_void, data.len(), 0) };
+
+        let first: RequestId = runtime.pop(first_fds[0], DemiBuffer::new(64))?;
+        let second: RequestId = runtime.pop(second_fds[0], DemiBuffer::new(64))?;
+        crate::ensure_neq!(first, second);
+
+        // The second request completes first, and its result is kept for it.
+        crate::ensure_eq!(send(second_fds[1], b"hello"), 5);
+        runtime.poll_blocking(Some(Duration::from_secs(10)))?;
+        crate::ensure_eq!(send(first_fds[1], b"hi"), 2);
+        loop {
+            match runtime.peek(first)? {
+                (None, size) if size == -libc::EAGAIN => continue,
+                (_, size) => {
+                    crate::ensure_eq!(size, 2);
+                    break;
+                },
+            }
+        }
+        crate::ensure_eq!(runtime.peek(second)?.1, 5);
+
+        // Once peeked, the requests are gone, and their slots are reused under other IDs.
+        crate::ensure_eq!(runtime.peek(first).unwrap_err().errno, libc::EINVAL);
+        let third: RequestId = runtime.pop(first_fds[0], DemiBuffer::new(64))?;
+        crate::ensure_neq!(third, first);
+        crate::ensure_neq!(third, second);
+        crate::ensure_eq!(send(first_fds[1], b"hey"), 3);
+        crate::ensure_eq!(runtime.peek(third)?.1, 3);
+
+        unsafe {
+            for fd in first_fds.iter().chain(second_fds.iter()) {
+                libc::close(*fd);
+            }
+        }
+        Ok(())
+    }
+
+    // A vectored pop fills its segments in order, so that a header and its payload land in buffers of their own.
+    #[test]
+    fn pop_vectored_splits_header_and_payload() -> Result<()> {
+        const HEADER_SIZE: usize = 4;
+        const PAYLOAD_SIZE: usize = 12;
+        let mut runtime: SharedIoUringRuntime = match new_runtime()? {
+            Some(runtime) => runtime,
+            // Nothing to test on kernels without io_uring.
+            None => return Ok(()),
+        };
+        let mut fds: [RawFd; 2] = [0; 2];
+        crate::ensure_eq!(
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) },
+            0
+        );
+
+        // The payload buffer is larger than the payload, so only part of it is filled.
+        let data: Vec<u8> = (0..(HEADER_SIZE + PAYLOAD_SIZE)).map(|i| i as u8).collect();
+        crate::ensure_eq!(
+            unsafe { libc::send(fds[1], data.as_ptr() as *const libc::c_void, data.len(), 0) },
+            data.len() as isize
+        );
+        let mut header: [u8; HEADER_SIZE] = [0; HEADER_SIZE];
+        let mut payload: [u8; 2 * PAYLOAD_SIZE] = [0; 2 * PAYLOAD_SIZE];
+        let segments: [(*mut u8, usize); 2] = [
+            (header.as_mut_ptr(), header.len()),
+            (payload.as_mut_ptr(), payload.len()),
+        ];
+        let request_id: RequestId = runtime.pop_vectored(fds[0], &segments)?;
+        let size: i32 = loop {
+            match runtime.peek(request_id)? {
+                (None, size) if size == -libc::EAGAIN => continue,
+                (_, size) => break size,
+            }
+        };
+        crate::ensure_eq!(size, data.len() as i32);
+        crate::ensure_eq!(&header[..], &data[..HEADER_SIZE]);
+        crate::ensure_eq!(&payload[..PAYLOAD_SIZE], &data[HEADER_SIZE..]);
+        crate::ensure_eq!(payload[PAYLOAD_SIZE..].iter().all(|b| *b == 0), true);
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    // A coroutine that waits for a request is only woken once that request completes, not after every poll.
+    #[test]
+    fn wait_request_wakes_on_completion() -> Result<()> {
+        let mut runtime: SharedIoUringRuntime = match new_runtime()? {
+            Some(runtime) => runtime,
+            // Nothing to test on kernels without io_uring.
+            None => return Ok(()),
+        };
+        let mut fds: [RawFd; 2] = [0; 2];
+        crate::ensure_eq!(
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) },
+            0
+        );
+
+        let request_id: RequestId = runtime.pop(fds[0], DemiBuffer::new(64))?;
+        let yielder: Yielder = Yielder::new();
+        let mut rt: SharedIoUringRuntime = runtime.clone();
+        let mut future = Box::pin(async move { rt.wait_request(request_id, &yielder).await });
+        let wakes: Arc<CountingWaker> = Arc::new(CountingWaker::default());
+        let waker: Waker = task::waker(wakes.clone());
+        let mut ctx: Context = Context::from_waker(&waker);
+
+        // Nothing completed, so the coroutine sleeps and nothing wakes it up.
+        crate::ensure_eq!(future.poll_unpin(&mut ctx).is_pending(), true);
+        crate::ensure_eq!(runtime.poll_completions()?, 0);
+        crate::ensure_eq!(wakes.0.load(Ordering::SeqCst), 0);
+
+        // Data arrives, and reaping its completion wakes the coroutine exactly once.
+        let data: &[u8] = b"hello";
+        crate::ensure_eq!(
+            unsafe { libc::send(fds[1], data.as_ptr() as *const libc::c_void, data.len(), 0) },
+            data.len() as isize
+        );
+        runtime.poll_blocking(Some(Duration::from_secs(10)))?;
+        crate::ensure_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        match future.poll_unpin(&mut ctx) {
+            Poll::Ready(result) => crate::ensure_eq!(result?.1, data.len() as i32),
+            Poll::Pending => anyhow::bail!("request should have completed"),
+        }
+        crate::ensure_eq!(wakes.0.load(Ordering::SeqCst), 1);
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        Ok(())
+    }
+
+    // Receive buffers go back to the pool once their scatter-gather array is freed, and are handed out again.
+    #[test]
+    fn recv_buffer_pool_recycles_buffers() -> Result<()> {
+        let mut runtime: SharedIoUringRuntime = match new_runtime()? {
+            Some(runtime) => runtime,
+            // Nothing to test on kernels without io_uring.
+            None => return Ok(()),
+        };
+
+        // Without a pool, receive buffers come straight from the heap.
+        crate::ensure_eq!(runtime.get_recv_chunk_size(), None);
+        crate::ensure_eq!(
+            runtime.alloc_recv_buffer(limits::RECVBUF_SIZE_MAX).len(),
+            limits::RECVBUF_SIZE_MAX
+        );
+
+        // Invalid pools are rejected, and so is a second pool.
+        crate::ensure_eq!(runtime.set_recv_buffer_pool(0, 1).unwrap_err().errno, libc::EINVAL);
+        crate::ensure_eq!(
+            runtime.set_recv_buffer_pool(RECV_CHUNK_SIZE, 0).unwrap_err().errno,
+            libc::EINVAL
+        );
+        runtime.set_recv_buffer_pool(RECV_CHUNK_SIZE, 1)?;
+        crate::ensure_eq!(runtime.get_recv_chunk_size(), Some(RECV_CHUNK_SIZE as usize));
+        crate::ensure_eq!(
+            runtime.set_recv_buffer_pool(RECV_CHUNK_SIZE, 1).unwrap_err().errno,
+            libc::EBUSY
+        );
+
+        // Receive into a chunk, hand it to the user, and have the user free it.
+        let buf: DemiBuffer = runtime.alloc_recv_buffer(RECV_CHUNK_SIZE as usize);
+        let ptr: *const u8 = buf.as_ptr();
+        let sga: demi_sgarray_t = runtime.into_sgarray(buf)?;
+        runtime.sgafree(sga)?;
+
+        // The same chunk is handed out again, with the requested size.
+        let buf: DemiBuffer = runtime.alloc_recv_buffer(100);
+        crate::ensure_eq!(buf.as_ptr(), ptr);
+        crate::ensure_eq!(buf.len(), 100);
+
+        // The pool is now empty, so another buffer comes from the heap. Only one of them is kept once freed.
+        let other: DemiBuffer = runtime.alloc_recv_buffer(100);
+        crate::ensure_neq!(other.as_ptr(), ptr);
+        runtime.sgafree(runtime.into_sgarray(buf)?)?;
+        runtime.sgafree(runtime.into_sgarray(other)?)?;
+        crate::ensure_eq!(runtime.alloc_recv_buffer(100).as_ptr(), ptr);
+
+        // Buffers larger than a chunk always come from the heap.
+        let buf: DemiBuffer = runtime.alloc_recv_buffer(RECV_CHUNK_SIZE as usize + 1);
+        crate::ensure_eq!(buf.len(), RECV_CHUNK_SIZE as usize + 1);
+
+        Ok(())
+    }
+
+    /// Measures allocating a receive buffer of `size` bytes and freeing it as a user would.
+    fn bench_recv_buffer(b: &mut Bencher, runtime: &SharedIoUringRuntime, size: usize) {
+        b.iter(|| {
+            let buf: DemiBuffer = runtime.alloc_recv_buffer(black_box(size));
+            let sga: demi_sgarray_t = runtime.into_sgarray(buf).expect("into_sgarray() should succeed");
+            runtime.sgafree(black_box(sga)).expect("sgafree() should succeed");
+        });
+    }
+
+    #[bench]
+    fn bench_recv_buffer_unpooled(b: &mut Bencher) {
+        if let Ok(Some(runtime)) = new_runtime() {
+            bench_recv_buffer(b, &runtime, limits::RECVBUF_SIZE_MAX);
+        }
+    }
+
+    #[bench]
+    fn bench_recv_buffer_pooled(b: &mut Bencher) {
+        if let Ok(Some(mut runtime)) = new_runtime() {
+            runtime
+                .set_recv_buffer_pool(RECV_CHUNK_SIZE, 1)
+                .expect("set_recv_buffer_pool() should succeed");
+            bench_recv_buffer(b, &runtime, RECV_CHUNK_SIZE as usize);
+        }
+    }
+
+    /// Measures receiving `RECV_CHUNK_SIZE` bytes over a stream socket, either into a buffer of the receive buffer group
+    /// or into one that is supplied with the pop.
+    fn bench_pop(b: &mut Bencher, runtime: &mut SharedIoUringRuntime, selected: bool) {
+        let mut fds: [RawFd; 2] = [0; 2];
+        assert_eq!(
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) },
+            0
+        );
+        let data: Vec<u8> = vec![0xab; RECV_CHUNK_SIZE as usize];
+        b.iter(|| {
+            let nsent: isize = unsafe { libc::send(fds[1], data.as_ptr() as *const libc::c_void, data.len(), 0) };
+            assert_eq!(nsent, data.len() as isize);
+            let request_id: RequestId = if selected {
+                runtime.pop_selected(fds[0], data.len())
+            } else {
+                runtime.pop(fds[0], runtime.alloc_recv_buffer(data.len()))
+            }
+            .expect("pop should succeed");
+            let nbytes: i32 = loop {
+                match runtime.peek(request_id).expect("peek() should succeed") {
+                    (None, size) if size == -libc::EAGAIN => continue,
+                    (_, size) => break size,
+                }
+            };
+            assert_eq!(nbytes, data.len() as i32);
+            if selected {
+                let buf: DemiBuffer = runtime
+                    .take_selected(request_id, nbytes as usize)
+                    .expect("take_selected() should succeed");
+                black_box(buf);
+            }
+        });
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+    }
+
+    #[bench]
+    fn bench_pop_supplied(b: &mut Bencher) {
+        if let Ok(Some(mut runtime)) = new_runtime() {
+            bench_pop(b, &mut runtime, false);
+        }
+    }
+
+    #[bench]
+    fn bench_pop_selected(b: &mut Bencher) {
+        if let Ok(Some(mut runtime)) = new_runtime() {
+            runtime
+                .register_recv_buffer_group(16, RECV_CHUNK_SIZE)
+                .expect("register_recv_buffer_group() should succeed");
+            bench_pop(b, &mut runtime, true);
+        }
+    }
+}
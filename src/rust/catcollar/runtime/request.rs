@@ -0,0 +1,356 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::liburing;
+use ::std::{
+    net::SocketAddrV4,
+    ptr,
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Request ID. It refers to the slot of a request in a request table, along with the generation of that slot, so that
+/// the ID of a request that is gone never refers to a later request that reuses its slot.
+#[derive(Clone, Copy, Hash, Debug, Eq, PartialEq)]
+pub struct RequestId {
+    /// Index of the slot.
+    index: u32,
+    /// Generation of the slot.
+    generation: u32,
+}
+
+/// State of a slot in a request table.
+#[derive(Clone, Copy, Debug)]
+enum RequestState {
+    /// The slot is free.
+    Free,
+    /// The request is in flight. This holds the message header of the request, which is released on completion.
+    Pending(*mut liburing::msghdr),
+    /// The request is in flight, but its result is no longer wanted, so its slot is released on completion. This holds
+    /// the message header of the request.
+    Abandoned(*mut liburing::msghdr),
+    /// The request has completed, but its result was not taken yet.
+    Completed(Option<SocketAddrV4>, i32),
+}
+
+/// Slot in a request table.
+struct RequestSlot {
+    /// Generation of the slot. It is bumped every time the slot is released.
+    generation: u32,
+    /// State of the slot.
+    state: RequestState,
+}
+
+/// Table of requests that are in flight in an I/O user ring. Slots are recycled through a free list, so that tracking a
+/// request is an array lookup, and takes no allocation once the table holds as many slots as there are requests in
+/// flight.
+pub struct RequestTable {
+    /// Slots, indexed by request ID.
+    slots: Vec<RequestSlot>,
+    /// Indexes of the free slots.
+    free: Vec<u32>,
+    /// Number of requests in flight.
+    num_pending: usize,
+}
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+impl RequestId {
+    /// Converts the target request ID into the user data of an I/O user ring operation. The user data is never zero,
+    /// so that it is told apart from operations that carry no request.
+    pub fn to_user_data(&self) -> u64 {
+        ((self.generation as u64) << 32) | (self.index as u64 + 1)
+    }
+
+    /// Converts the user data of an I/O user ring operation into a request ID. This returns `None` if the operation
+    /// carries no request.
+    pub fn from_user_data(user_data: u64) -> Option<Self> {
+        match user_data as u32 {
+            0 => None,
+            index => Some(Self {
+                index: index - 1,
+                generation: (user_data >> 32) as u32,
+            }),
+        }
+    }
+}
+
+impl RequestTable {
+    /// Creates a request table with `capacity` free slots.
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            slots: (0..capacity)
+                .map(|_| RequestSlot {
+                    generation: 0,
+                    state: RequestState::Free,
+                })
+                .collect(),
+            free: (0..capacity).rev().collect(),
+            num_pending: 0,
+        }
+    }
+
+    /// Reserves a slot for a new request. The table grows if all slots are taken.
+    pub fn reserve(&mut self) -> RequestId {
+        let index: u32 = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(RequestSlot {
+                    generation: 0,
+                    state: RequestState::Free,
+                });
+                (self.slots.len() - 1) as u32
+            },
+        };
+        let slot: &mut RequestSlot = &mut self.slots[index as usize];
+        slot.state = RequestState::Pending(ptr::null_mut());
+        self.num_pending += 1;
+        RequestId {
+            index,
+            generation: slot.generation,
+        }
+    }
+
+    /// Attaches the message header `msg_ptr` to the request `request_id`, once it has been submitted.
+    pub fn submit(&mut self, request_id: RequestId, msg_ptr: *mut liburing::msghdr) {
+        if let Some(slot) = self.get_mut(request_id) {
+            slot.state = RequestState::Pending(msg_ptr);
+        }
+    }
+
+    /// Marks the request `request_id` as completed with `result`, if it is in flight. Abandoned requests are released
+    /// instead.
+    pub fn complete(&mut self, request_id: RequestId, result: (Option<SocketAddrV4>, i32)) {
+        let abandoned: bool = match self.get_mut(request_id) {
+            Some(slot) => match slot.state {
+                RequestState::Pending(_) => {
+                    slot.state = RequestState::Completed(result.0, result.1);
+                    self.num_pending -= 1;
+                    false
+                },
+                RequestState::Abandoned(_) => true,
+                _ => false,
+            },
+            None => return,
+        };
+        if abandoned {
+            self.release(request_id);
+        }
+    }
+
+    /// Gives up on the result of the request `request_id`. Its slot is released right away if it has completed, or once
+    /// it completes otherwise.
+    pub fn abandon(&mut self, request_id: RequestId) {
+        let completed: bool = match self.get_mut(request_id) {
+            Some(slot) => match slot.state {
+                RequestState::Pending(msg_ptr) => {
+                    slot.state = RequestState::Abandoned(msg_ptr);
+                    false
+                },
+                RequestState::Completed(..) => true,
+                _ => false,
+            },
+            None => return,
+        };
+        if completed {
+            self.release(request_id);
+        }
+    }
+
+    /// Takes the result of the request `request_id` and releases its slot, if the request has completed.
+    pub fn take(&mut self, request_id: RequestId) -> Option<(Option<SocketAddrV4>, i32)> {
+        match self.get_mut(request_id)?.state {
+            RequestState::Completed(addr, size) => {
+                self.release(request_id);
+                Some((addr, size))
+            },
+            _ => None,
+        }
+    }
+
+    /// Releases the slot of the request `request_id`, whatever its state.
+    pub fn release(&mut self, request_id: RequestId) {
+        let pending: bool = match self.get_mut(request_id) {
+            Some(slot) => {
+                let pending: bool = matches!(slot.state, RequestState::Pending(_) | RequestState::Abandoned(_));
+                slot.state = RequestState::Free;
+                slot.generation = slot.generation.wrapping_add(1);
+                pending
+            },
+            None => return,
+        };
+        if pending {
+            self.num_pending -= 1;
+        }
+        self.free.push(request_id.index);
+    }
+
+    /// Gets the message header of the request `request_id`, if it is in flight.
+    pub fn get_msg(&self, request_id: RequestId) -> Option<*mut liburing::msghdr> {
+        match self.slots.get(request_id.index as usize) {
+            Some(slot) if slot.generation == request_id.generation => match slot.state {
+                RequestState::Pending(msg_ptr) | RequestState::Abandoned(msg_ptr) => Some(msg_ptr),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Checks if the request `request_id` is in flight.
+    pub fn is_pending(&self, request_id: RequestId) -> bool {
+        self.get_msg(request_id).is_some()
+    }
+
+    /// Gets the number of requests in flight.
+    pub fn num_pending(&self) -> usize {
+        self.num_pending
+    }
+
+    /// Gets the slot of the request `request_id`, unless the slot was released since.
+    fn get_mut(&mut self, request_id: RequestId) -> Option<&mut RequestSlot> {
+        match self.slots.get_mut(request_id.index as usize) {
+            Some(slot) if slot.generation == request_id.generation && !matches!(slot.state, RequestState::Free) => {
+                Some(slot)
+            },
+            _ => None,
+        }
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RequestId,
+        RequestTable,
+    };
+    use crate::runtime::liburing;
+    use ::anyhow::Result;
+    use ::std::{
+        collections::{
+            HashMap,
+            HashSet,
+        },
+        mem,
+        net::SocketAddrV4,
+        ptr,
+    };
+    use ::test::{
+        black_box,
+        Bencher,
+    };
+
+    /// Number of requests in flight in benchmarks.
+    const BENCH_NUM_REQUESTS: usize = 64;
+
+    // Slots are recycled, and IDs of released requests do not refer to the requests that reuse their slots.
+    #[test]
+    fn request_table_recycles_slots() -> Result<()> {
+        let mut table: RequestTable = RequestTable::new(1);
+
+        let first: RequestId = table.reserve();
+        crate::ensure_eq!(table.num_pending(), 1);
+        crate::ensure_eq!(RequestId::from_user_data(first.to_user_data()), Some(first));
+        crate::ensure_eq!(table.get_msg(first), Some(ptr::null_mut()));
+        crate::ensure_eq!(table.take(first), None);
+        table.complete(first, (None, 42));
+        crate::ensure_eq!(table.num_pending(), 0);
+        crate::ensure_eq!(table.get_msg(first), None);
+        table.complete(first, (None, 0));
+        crate::ensure_eq!(table.take(first), Some((None, 42)));
+        crate::ensure_eq!(table.take(first), None);
+
+        // The slot is reused, but under a different ID.
+        let second: RequestId = table.reserve();
+        crate::ensure_neq!(second, first);
+        crate::ensure_eq!(second.index, first.index);
+        crate::ensure_eq!(table.is_pending(first), false);
+        crate::ensure_eq!(table.is_pending(second), true);
+        table.complete(first, (None, 0));
+        crate::ensure_eq!(table.is_pending(second), true);
+
+        // The table grows once all slots are taken.
+        let third: RequestId = table.reserve();
+        crate::ensure_neq!(third.index, second.index);
+        crate::ensure_eq!(table.num_pending(), 2);
+        table.release(second);
+        table.release(third);
+        crate::ensure_eq!(table.num_pending(), 0);
+
+        // Operations that carry no request have no ID.
+        crate::ensure_eq!(RequestId::from_user_data(0), None);
+
+        Ok(())
+    }
+
+    // Abandoned requests are released once they complete, or right away if they did already.
+    #[test]
+    fn request_table_releases_abandoned_requests() -> Result<()> {
+        let mut table: RequestTable = RequestTable::new(1);
+
+        let first: RequestId = table.reserve();
+        table.abandon(first);
+        crate::ensure_eq!(table.num_pending(), 1);
+        crate::ensure_eq!(table.get_msg(first), Some(ptr::null_mut()));
+        table.complete(first, (None, 42));
+        crate::ensure_eq!(table.num_pending(), 0);
+        crate::ensure_eq!(table.take(first), None);
+
+        let second: RequestId = table.reserve();
+        crate::ensure_eq!(second.index, first.index);
+        table.complete(second, (None, 42));
+        table.abandon(second);
+        crate::ensure_eq!(table.take(second), None);
+        crate::ensure_eq!(table.reserve().index, first.index);
+
+        Ok(())
+    }
+
+    #[bench]
+    fn bench_request_table(b: &mut Bencher) {
+        let mut table: RequestTable = RequestTable::new(BENCH_NUM_REQUESTS as u32);
+        let mut request_ids: Vec<RequestId> = Vec::with_capacity(BENCH_NUM_REQUESTS);
+        b.iter(|| {
+            for _ in 0..BENCH_NUM_REQUESTS {
+                request_ids.push(table.reserve());
+            }
+            for request_id in request_ids.drain(..) {
+                table.complete(request_id, (None, 0));
+                black_box(table.take(black_box(request_id)));
+            }
+        });
+    }
+
+    /// Measures tracking requests in hash maps, as done before the request table.
+    #[bench]
+    fn bench_request_hash_maps(b: &mut Bencher) {
+        let mut pending: HashSet<*const liburing::msghdr> = HashSet::new();
+        let mut completed: HashMap<*const liburing::msghdr, (Option<SocketAddrV4>, i32)> = HashMap::new();
+        let msgs: Vec<Box<liburing::msghdr>> = (0..BENCH_NUM_REQUESTS)
+            .map(|_| Box::new(unsafe { mem::zeroed() }))
+            .collect();
+        b.iter(|| {
+            for msg in msgs.iter() {
+                pending.insert(msg.as_ref() as *const liburing::msghdr);
+            }
+            for msg in msgs.iter() {
+                let request_id: *const liburing::msghdr = msg.as_ref();
+                if pending.remove(&request_id) {
+                    completed.insert(request_id, (None, 0));
+                }
+                black_box(completed.remove(black_box(&request_id)));
+            }
+        });
+    }
+}
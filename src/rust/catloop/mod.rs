@@ -5,6 +5,7 @@
 // Exports
 //======================================================================================================================
 
+mod mux;
 mod queue;
 mod socket;
 
@@ -12,7 +13,16 @@ mod socket;
 // Imports
 //======================================================================================================================
 
-use self::queue::SharedCatloopQueue;
+use self::{
+    mux::{
+        ConnectionId,
+        Multiplexing,
+        SharedAcceptQueue,
+        SharedTrunk,
+    },
+    queue::SharedCatloopQueue,
+    socket::Socket,
+};
 use crate::{
     catmem::SharedCatmemLibOS,
     demi_sgarray_t,
@@ -26,9 +36,13 @@ use crate::{
             MemoryRuntime,
         },
         network::{
-            socket::SocketId,
+            socket::{
+                state::SocketState,
+                SocketId,
+            },
             unwrap_socketaddr,
         },
+        queue::QueueInfo,
         scheduler::{
             TaskHandle,
             Yielder,
@@ -44,6 +58,7 @@ use crate::{
     QType,
 };
 use ::std::{
+    collections::HashMap,
     net::{
         Ipv4Addr,
         SocketAddr,
@@ -74,6 +89,9 @@ pub struct CatloopLibOS {
     runtime: SharedDemiRuntime,
     /// Configuration.
     config: Config,
+    /// Trunks that carry the logical connections of this libOS to remote addresses. A trunk that is being opened has no
+    /// entry yet.
+    trunks: HashMap<SocketAddrV4, Option<SharedTrunk>>,
 }
 
 #[derive(Clone)]
@@ -90,6 +108,7 @@ impl CatloopLibOS {
             catmem: SharedCatmemLibOS::new(config, runtime.clone()),
             runtime,
             config: config.clone(),
+            trunks: HashMap::new(),
         }
     }
 }
@@ -127,7 +146,7 @@ impl SharedCatloopLibOS {
         let catmem: SharedCatmemLibOS = self.catmem.clone();
         let qd: QDesc = self
             .runtime
-            .alloc_queue::<SharedCatloopQueue>(SharedCatloopQueue::new(qtype, runtime, catmem)?);
+            .alloc_queue::<SharedCatloopQueue>(SharedCatloopQueue::new(qtype, runtime, catmem)?)?;
         Ok(qd)
     }
 
@@ -169,7 +188,7 @@ impl SharedCatloopLibOS {
             return Err(Fail::new(libc::EADDRINUSE, &cause));
         }
         // Check if this is an ephemeral port.
-        if SharedDemiRuntime::is_private_ephemeral_port(local.port()) {
+        if self.runtime.is_private_ephemeral_port(local.port()) {
             // Allocate ephemeral port from the pool, to leave ephemeral port allocator in a consistent state.
             self.runtime.reserve_ephemeral_port(local.port())?;
         }
@@ -199,7 +218,104 @@ impl SharedCatloopLibOS {
 
         // Check if the queue descriptor is registered in the sockets table.
         let mut queue: SharedCatloopQueue = self.get_queue(&qd)?;
-        queue.listen(backlog)
+        queue.listen(backlog)?;
+
+        // Accept trunks in the background, if connections are multiplexed.
+        if let Multiplexing::On = queue.get_multiplexing() {
+            let mut accept_queue: SharedAcceptQueue = SharedAcceptQueue::default();
+            let yielder: Yielder = Yielder::new();
+            accept_queue.set_acceptor(yielder.get_handle());
+            let task_name: String = format!("Catloop::mux::accept for qd={:?}", qd);
+            let future = self.clone().accept_trunks_coroutine(qd, accept_queue.clone(), yielder);
+            self.runtime.insert_background_coroutine(&task_name, Box::pin(future))?;
+            queue.set_multiplexing(Multiplexing::Listener(accept_queue));
+        }
+
+        Ok(())
+    }
+
+    /// Sets a SharedCatloopQueue to multiplex its connections over trunks, instead of opening a pair of shared memory
+    /// rings for each of them. A client multiplexes all of its connections to the same remote address over a single
+    /// trunk, and a server accepts connections as they are opened on any trunk. Both ends of a connection must be set
+    /// to multiplex it, before they listen or connect.
+    pub fn set_multiplexed(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("set_multiplexed() qd={:?}", qd);
+
+        let mut queue: SharedCatloopQueue = self.get_queue(&qd)?;
+        match (queue.get_state(), queue.get_multiplexing()) {
+            (SocketState::Unbound | SocketState::Bound, Multiplexing::Off | Multiplexing::On) => {
+                queue.set_multiplexing(Multiplexing::On);
+                Ok(())
+            },
+            (state, _) => {
+                let cause: String = format!("socket is already in use (qd={:?}, state={:?})", qd, state);
+                error!("set_multiplexed(): {}", cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    /// Background coroutine that accepts the trunks that remotes open to listening queue `qd`. Logical connections that
+    /// are opened on these trunks are queued in `accept_queue`. The coroutine exits once the listening queue is closed,
+    /// which wakes up `yielder`.
+    async fn accept_trunks_coroutine(mut self, qd: QDesc, accept_queue: SharedAcceptQueue, yielder: Yielder) {
+        loop {
+            // Stop once the listening queue is gone.
+            if accept_queue.is_closed() {
+                return;
+            }
+            let mut queue: SharedCatloopQueue = match self.get_queue(&qd) {
+                Ok(queue) => queue,
+                Err(_) => return,
+            };
+            let new_port: u16 = match self.runtime.alloc_ephemeral_port() {
+                Ok(new_port) => new_port,
+                Err(e) => {
+                    warn!("accept_trunks(): stopped accepting trunks (qd={:?}, error={:?})", qd, e);
+                    return;
+                },
+            };
+            let catmem_qd: QDesc = match queue.do_accept_trunk(new_port, &yielder).await {
+                Ok(catmem_qd) => catmem_qd,
+                Err(e) => {
+                    if self.runtime.free_ephemeral_port(new_port).is_err() {
+                        warn!("accept_trunks(): leaking ephemeral port (port={})", new_port);
+                    }
+                    debug!("accept_trunks(): stopped accepting trunks (qd={:?}, error={:?})", qd, e);
+                    return;
+                },
+            };
+
+            // Demultiplex the new trunk in the background, until the remote closes it.
+            let remote: SocketAddrV4 = SocketAddrV4::new(
+                *queue
+                    .local()
+                    .expect("Should be bound to a local address to accept connections")
+                    .ip(),
+                new_port,
+            );
+            let trunk: SharedTrunk = SharedTrunk::new(
+                self.runtime.clone(),
+                self.catmem.clone(),
+                catmem_qd,
+                remote,
+                Some(accept_queue.clone()),
+            );
+            let mut runtime: SharedDemiRuntime = self.runtime.clone();
+            let future = async move {
+                trunk.poll().await;
+                if runtime.free_ephemeral_port(new_port).is_err() {
+                    warn!("accept_trunks(): leaking ephemeral port (port={})", new_port);
+                }
+            };
+            let task_name: String = format!("Catloop::mux::poll for port={:?}", new_port);
+            if let Err(e) = self.runtime.insert_background_coroutine(&task_name, Box::pin(future)) {
+                warn!(
+                    "accept_trunks(): failed to poll trunk (port={:?}, error={:?})",
+                    new_port, e
+                );
+            }
+        }
     }
 
     /// Synchronous cross-queue code to start accepting a connection. This function schedules the asynchronous
@@ -208,6 +324,11 @@ impl SharedCatloopLibOS {
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("accept() qd={:?}", qd);
 
+        // Connections that are multiplexed over trunks do not need a port of their own.
+        if let Multiplexing::Listener(accept_queue) = self.get_queue(&qd)?.get_multiplexing() {
+            return self.accept_multiplexed(qd, accept_queue);
+        }
+
         // Allocate ephemeral port.
         let new_port: u16 = self.runtime.alloc_ephemeral_port()?;
         let mut queue: SharedCatloopQueue = self.get_queue(&qd)?;
@@ -237,7 +358,10 @@ impl SharedCatloopLibOS {
         // Handle result: if successful, borrow the state to update state.
         match result {
             Ok(new_queue) => {
-                let new_qd: QDesc = self.runtime.alloc_queue::<SharedCatloopQueue>(new_queue);
+                let new_qd: QDesc = match self.runtime.alloc_queue::<SharedCatloopQueue>(new_queue) {
+                    Ok(new_qd) => new_qd,
+                    Err(e) => return (qd, OperationResult::Failed(e)),
+                };
                 // TODO: insert into socket id to queue descriptor table?
                 let new_addr: SocketAddrV4 = SocketAddrV4::new(
                     *queue
@@ -261,6 +385,53 @@ impl SharedCatloopLibOS {
         }
     }
 
+    /// Synchronous code to start accepting a logical connection on a listening queue whose connections are multiplexed
+    /// over trunks.
+    fn accept_multiplexed(&mut self, qd: QDesc, accept_queue: SharedAcceptQueue) -> Result<QToken, Fail> {
+        let mut queue: SharedCatloopQueue = self.get_queue(&qd)?;
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("Catloop::accept for qd={:?}", qd);
+            let yielder: Yielder = Yielder::new();
+            let yielder_handle: YielderHandle = yielder.get_handle();
+            let coroutine: Pin<Box<Operation>> =
+                Box::pin(self.clone().accept_multiplexed_coroutine(qd, accept_queue, yielder));
+            self.runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine, yielder_handle, qd)
+        };
+
+        queue.accept(coroutine_constructor)
+    }
+
+    /// Asynchronous code for accepting a logical connection that is opened on any trunk of a listening queue.
+    async fn accept_multiplexed_coroutine(
+        mut self,
+        qd: QDesc,
+        mut accept_queue: SharedAcceptQueue,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        // Wait for a logical connection to be opened.
+        let (trunk, id): (SharedTrunk, ConnectionId) = loop {
+            if let Some(connection) = accept_queue.pop() {
+                break connection;
+            }
+            if let Err(e) = yielder.yield_once().await {
+                return (qd, OperationResult::Failed(e));
+            }
+        };
+
+        let qtype: QType = match self.runtime.get_queue_type(&qd) {
+            Ok(qtype) => qtype,
+            Err(e) => return (qd, OperationResult::Failed(e)),
+        };
+        let remote: SocketAddrV4 = trunk.remote();
+        let socket: Socket = Socket::alloc(self.runtime.clone(), self.catmem.clone(), None, None, Some(remote));
+        let new_queue: SharedCatloopQueue = SharedCatloopQueue::alloc_multiplexed(qtype, socket, trunk, id);
+        match self.runtime.alloc_queue::<SharedCatloopQueue>(new_queue) {
+            Ok(new_qd) => (qd, OperationResult::Accept((new_qd, remote))),
+            Err(e) => (qd, OperationResult::Failed(e)),
+        }
+    }
+
     /// Synchronous code to establish a connection to a remote endpoint. This function schedules the asynchronous
     /// coroutine and performs any necessary synchronous, multi-queue operations at the libOS-level before beginning
     /// the connect.
@@ -286,13 +457,36 @@ impl SharedCatloopLibOS {
     /// Asynchronous code to establish a connection to a remote endpoint. This function returns a coroutine that runs
     /// asynchronously to connect a queue and performs any necessary multi-queue operations at the libOS-level after
     /// the connect succeeds or fails.
-    async fn connect_coroutine(self, qd: QDesc, remote: SocketAddrV4, yielder: Yielder) -> (QDesc, OperationResult) {
+    async fn connect_coroutine(
+        mut self,
+        qd: QDesc,
+        remote: SocketAddrV4,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
         // Make sure the queue still exists.
         let mut queue: SharedCatloopQueue = match self.get_queue(&qd) {
             Ok(queue) => queue,
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
 
+        // Open a logical connection over the trunk to the remote, if connections are multiplexed.
+        if let Multiplexing::On = queue.get_multiplexing() {
+            let result: Result<(SharedTrunk, ConnectionId), Fail> = match self.get_trunk(remote, &yielder).await {
+                Ok(mut trunk) => match trunk.open(&yielder).await {
+                    Ok(id) => Ok((trunk, id)),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            return match queue.complete_multiplexed_connect(result) {
+                Ok(()) => (qd, OperationResult::Connect),
+                Err(e) => {
+                    warn!("connect() failed (qd={:?}, error={:?})", qd, e.cause);
+                    (qd, OperationResult::Failed(e))
+                },
+            };
+        }
+
         // Wait for connect operation to complete.
         match queue.do_connect(remote, &yielder).await {
             // TODO: insert into socket id to queue descriptor table?
@@ -311,7 +505,7 @@ impl SharedCatloopLibOS {
         let mut queue: SharedCatloopQueue = self.get_queue(&qd)?;
         queue.close()?;
         if let Some(addr) = queue.local() {
-            if SharedDemiRuntime::is_private_ephemeral_port(addr.port()) {
+            if self.runtime.is_private_ephemeral_port(addr.port()) {
                 if self.runtime.free_ephemeral_port(addr.port()).is_err() {
                     // We fail if and only if we attempted to free a port that was not allocated.
                     // This is unexpected, but if it happens, issue a warning and keep going,
@@ -361,7 +555,7 @@ impl SharedCatloopLibOS {
         match queue.do_close(yielder).await {
             Ok((_, OperationResult::Close)) => {
                 if let Some(addr) = queue.local() {
-                    if SharedDemiRuntime::is_private_ephemeral_port(addr.port()) {
+                    if self.runtime.is_private_ephemeral_port(addr.port()) {
                         if self.runtime.free_ephemeral_port(addr.port()).is_err() {
                             // We fail if and only if we attempted to free a port that was not allocated.
                             // This is unexpected, but if it happens, issue a warning and keep going,
@@ -471,6 +665,70 @@ impl SharedCatloopLibOS {
         }
     }
 
+    /// Lists all I/O queues that are currently open in Catloop. This function is read-only and intended for
+    /// diagnostics. Shared memory queues that back Catloop sockets are not reported.
+    pub fn list_queues(&self) -> Vec<QueueInfo> {
+        let mut queues: Vec<QueueInfo> = Vec::new();
+        for qd in self.runtime.get_qtable().get_qds() {
+            match self.runtime.get_queue_type(&qd) {
+                Ok(QType::TcpSocket) => (),
+                _ => continue,
+            }
+            if let Ok(queue) = self.get_queue(&qd) {
+                queues.push(QueueInfo {
+                    qd,
+                    qtype: QType::TcpSocket,
+                    local: queue.local(),
+                    remote: queue.remote(),
+                    state: Some(queue.get_state()),
+                });
+            }
+        }
+        queues
+    }
+
+    /// Returns the trunk to `remote`, opening it if there is none yet. Trunks stay open for the lifetime of the libOS,
+    /// unless the remote closes them.
+    async fn get_trunk(&mut self, remote: SocketAddrV4, yielder: &Yielder) -> Result<SharedTrunk, Fail> {
+        // Wait for other connects that are opening the same trunk.
+        loop {
+            match self.trunks.get(&remote) {
+                Some(Some(trunk)) if !trunk.is_closed() => return Ok(trunk.clone()),
+                Some(None) => yielder.yield_once().await?,
+                _ => break,
+            }
+        }
+
+        self.trunks.insert(remote, None);
+        let socket: Socket = match Socket::new(self.runtime.clone(), self.catmem.clone()) {
+            Ok(socket) => socket,
+            Err(e) => {
+                self.trunks.remove(&remote);
+                return Err(e);
+            },
+        };
+        let catmem_qd: QDesc = match socket.do_open_trunk(remote, yielder).await {
+            Ok(catmem_qd) => catmem_qd,
+            Err(e) => {
+                self.trunks.remove(&remote);
+                return Err(e);
+            },
+        };
+
+        // Demultiplex the new trunk in the background.
+        let trunk: SharedTrunk = SharedTrunk::new(self.runtime.clone(), self.catmem.clone(), catmem_qd, remote, None);
+        let task_name: String = format!("Catloop::mux::poll for remote={:?}", remote);
+        if let Err(e) = self
+            .runtime
+            .insert_background_coroutine(&task_name, Box::pin(trunk.clone().poll()))
+        {
+            self.trunks.remove(&remote);
+            return Err(e);
+        }
+        self.trunks.insert(remote, Some(trunk.clone()));
+        Ok(trunk)
+    }
+
     fn get_queue(&self, qd: &QDesc) -> Result<SharedCatloopQueue, Fail> {
         Ok(self.runtime.get_qtable().get::<SharedCatloopQueue>(qd)?.clone())
     }
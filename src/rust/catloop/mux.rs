@@ -0,0 +1,614 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    catmem::SharedCatmemLibOS,
+    runtime::{
+        fail::Fail,
+        memory::{
+            DemiBuffer,
+            DemiBufferChain,
+        },
+        scheduler::{
+            Yielder,
+            YielderHandle,
+        },
+        OperationResult,
+        QDesc,
+        SharedDemiRuntime,
+        SharedObject,
+    },
+};
+use ::std::{
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    net::SocketAddrV4,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Size of the header of a frame: identifier of the logical connection (8 bytes), frame type (1 byte) and length of
+/// the payload (4 bytes).
+const FRAME_HEADER_SIZE: usize = 13;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Identifier of a logical connection. It is chosen by the end that opens the connection and is unique within a trunk.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct ConnectionId(u64);
+
+/// Types of frames that are sent over a trunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum FrameType {
+    /// Opens a logical connection.
+    Open = 1,
+    /// Carries data of a logical connection.
+    Data = 2,
+    /// Closes a logical connection.
+    Close = 3,
+}
+
+/// Role of a Catloop queue in connection multiplexing.
+#[derive(Clone)]
+pub enum Multiplexing {
+    /// Connections get a shared memory pipe of their own.
+    Off,
+    /// Connections are multiplexed over trunks, once the queue listens or connects.
+    On,
+    /// Listening queue whose connections are multiplexed over the trunks that remotes open to it.
+    Listener(SharedAcceptQueue),
+    /// Logical connection that is carried over a trunk.
+    Connection(SharedTrunk, ConnectionId),
+}
+
+/// Data that was received on a logical connection, but not popped yet.
+#[derive(Default)]
+struct Inbox {
+    /// Data that was received, in order of arrival.
+    ready: VecDeque<DemiBuffer>,
+    /// Did the remote close the connection?
+    closed: bool,
+    /// Yielders of the coroutines that wait for data on the connection.
+    waiters: Vec<YielderHandle>,
+}
+
+/// Logical connections that were opened by remotes, but not accepted yet.
+#[derive(Default)]
+pub struct AcceptQueue {
+    /// Connections that are waiting to be accepted, in order of arrival.
+    ready: VecDeque<(SharedTrunk, ConnectionId)>,
+    /// Was the listening queue closed?
+    closed: bool,
+    /// Yielder of the coroutine that accepts trunks, which is woken up when the listening queue is closed.
+    acceptor: Option<YielderHandle>,
+}
+
+/// Accept queue that is shared by a listening queue and the trunks that remotes opened to it.
+#[derive(Clone)]
+pub struct SharedAcceptQueue(SharedObject<AcceptQueue>);
+
+/// Frame that is waiting to be pushed on a trunk.
+struct Frame {
+    /// Header of the frame.
+    header: DemiBuffer,
+    /// Payload of the frame, if any.
+    payload: Option<DemiBuffer>,
+    /// Yielder of the coroutine that sent the frame, which is woken up with the result of the push.
+    sender: Option<YielderHandle>,
+}
+
+/// A trunk is a shared memory pipe that carries many logical connections. Each message on the trunk is a frame that is
+/// tagged with the identifier of its logical connection.
+pub struct Trunk {
+    /// Underlying shared runtime.
+    runtime: SharedDemiRuntime,
+    /// Underlying Catmem LibOS.
+    catmem: SharedCatmemLibOS,
+    /// Underlying shared memory pipe.
+    catmem_qd: QDesc,
+    /// Address of the remote end of the trunk.
+    remote: SocketAddrV4,
+    /// Received data of the logical connections on this trunk.
+    inboxes: HashMap<ConnectionId, Inbox>,
+    /// Accept queue for the logical connections that are opened by the remote, if this end is the server.
+    listener: Option<SharedAcceptQueue>,
+    /// Identifier of the next logical connection that is opened by this end.
+    next_id: u64,
+    /// Frames that are waiting to be pushed, in order of sending.
+    outbox: VecDeque<Frame>,
+    /// Is the coroutine that pushes frames running? Frames span several messages on the shared memory pipe, so they
+    /// are pushed by this coroutine alone, one at a time and in full, even if their senders are cancelled.
+    writing: bool,
+    /// Was the trunk closed by the remote?
+    closed: bool,
+    /// Failure of the shared memory pipe, if any.
+    error: Option<Fail>,
+}
+
+/// Trunk that is shared by its logical connections and the coroutine that demultiplexes incoming frames.
+#[derive(Clone)]
+pub struct SharedTrunk(SharedObject<Trunk>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SharedAcceptQueue {
+    /// Takes the oldest logical connection that is waiting to be accepted, if any.
+    pub fn pop(&mut self) -> Option<(SharedTrunk, ConnectionId)> {
+        self.0.ready.pop_front()
+    }
+
+    /// Registers `acceptor` as the yielder of the coroutine that accepts trunks for the target queue.
+    pub fn set_acceptor(&mut self, acceptor: YielderHandle) {
+        self.0.acceptor = Some(acceptor);
+    }
+
+    /// Checks if the listening queue was closed.
+    pub fn is_closed(&self) -> bool {
+        self.0.closed
+    }
+
+    /// Stops accepting logical connections. Connections that are waiting to be accepted are closed, and so is the
+    /// coroutine that accepts trunks.
+    pub fn close(&mut self) {
+        self.0.closed = true;
+        if let Some(mut acceptor) = self.0.acceptor.take() {
+            acceptor.wake_with(Err(Fail::new(libc::ECANCELED, "listening queue was closed")));
+        }
+        while let Some((mut trunk, id)) = self.0.ready.pop_front() {
+            trunk.close(id);
+        }
+    }
+}
+
+impl SharedTrunk {
+    /// Creates a trunk on top of the shared memory pipe `catmem_qd`, whose remote end is `remote`. If `listener` is
+    /// set, logical connections that are opened by the remote are queued there.
+    pub fn new(
+        runtime: SharedDemiRuntime,
+        catmem: SharedCatmemLibOS,
+        catmem_qd: QDesc,
+        remote: SocketAddrV4,
+        listener: Option<SharedAcceptQueue>,
+    ) -> Self {
+        Self(SharedObject::new(Trunk {
+            runtime,
+            catmem,
+            catmem_qd,
+            remote,
+            inboxes: HashMap::new(),
+            listener,
+            next_id: 0,
+            outbox: VecDeque::new(),
+            writing: false,
+            closed: false,
+            error: None,
+        }))
+    }
+
+    /// Returns the address of the remote end of the target trunk.
+    pub fn remote(&self) -> SocketAddrV4 {
+        self.0.remote
+    }
+
+    /// Returns the shared memory pipe that backs the target trunk.
+    pub fn catmem_qd(&self) -> QDesc {
+        self.0.catmem_qd
+    }
+
+    /// Checks if the target trunk can no longer carry logical connections.
+    pub fn is_closed(&self) -> bool {
+        self.0.closed || self.0.error.is_some()
+    }
+
+    /// Opens a new logical connection on the target trunk.
+    pub async fn open(&mut self, yielder: &Yielder) -> Result<ConnectionId, Fail> {
+        let id: ConnectionId = ConnectionId(self.0.next_id);
+        self.0.next_id += 1;
+        self.0.inboxes.insert(id, Inbox::default());
+        if let Err(e) = self.send(id, FrameType::Open, None, yielder).await {
+            self.0.inboxes.remove(&id);
+            return Err(e);
+        }
+        Ok(id)
+    }
+
+    /// Pushes `buf` on logical connection `id`.
+    pub async fn push(&mut self, id: ConnectionId, buf: DemiBuffer, yielder: &Yielder) -> Result<(), Fail> {
+        self.send(id, FrameType::Data, Some(buf), yielder).await
+    }
+
+    /// Pops up to `size` bytes from logical connection `id`. Bytes in excess stay in the inbox for the next pop. A
+    /// zero-length buffer indicates that the remote closed the connection.
+    pub async fn pop(&mut self, id: ConnectionId, size: Option<usize>, yielder: &Yielder) -> Result<DemiBuffer, Fail> {
+        loop {
+            let trunk: &mut Trunk = &mut self.0;
+            let inbox: &mut Inbox = match trunk.inboxes.get_mut(&id) {
+                Some(inbox) => inbox,
+                None => {
+                    let cause: String = format!("logical connection is closed (id={:?})", id);
+                    error!("pop(): {}", &cause);
+                    return Err(Fail::new(libc::EBADF, &cause));
+                },
+            };
+            if let Some(mut buf) = inbox.ready.pop_front() {
+                if let Some(size) = size {
+                    if buf.len() > size {
+                        let rest: DemiBuffer = buf.split_back(size)?;
+                        inbox.ready.push_front(rest);
+                    }
+                }
+                return Ok(buf);
+            }
+            if inbox.closed {
+                return Ok(DemiBuffer::new(0));
+            }
+            if let Some(e) = &trunk.error {
+                return Err(e.clone());
+            }
+            inbox.waiters.push(yielder.get_handle());
+            yielder.yield_until_wake().await?;
+        }
+    }
+
+    /// Closes logical connection `id`. The remote is notified in the background.
+    pub fn close(&mut self, id: ConnectionId) {
+        if let Some(mut inbox) = self.0.inboxes.remove(&id) {
+            inbox.wake();
+        }
+        if self.is_closed() {
+            return;
+        }
+        if let Err(e) = self.enqueue(id, FrameType::Close, None, None) {
+            warn!("close(): failed to notify remote (id={:?}, error={:?})", id, e);
+        }
+    }
+
+    /// Demultiplexes frames that arrive on the target trunk into the inboxes of their logical connections, until the
+    /// remote closes the trunk or it fails.
+    pub async fn poll(mut self) {
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let yielder: Yielder = Yielder::new();
+            let buf: DemiBuffer = match self
+                .0
+                .catmem
+                .clone()
+                .pop_coroutine(self.0.catmem_qd, None, yielder)
+                .await
+            {
+                (_, OperationResult::Pop(_, buf)) => buf,
+                (_, OperationResult::Failed(e)) => {
+                    warn!("poll(): trunk failed (remote={:?}, error={:?})", self.0.remote, e);
+                    self.fail(e);
+                    return;
+                },
+                _ => unreachable!("should not return anything other than pop or error"),
+            };
+
+            // The remote closed the trunk, thus so are all logical connections on it.
+            if buf.len() == 0 {
+                self.0.closed = true;
+                for inbox in self.0.inboxes.values_mut() {
+                    inbox.closed = true;
+                    inbox.wake();
+                }
+                let catmem_qd: QDesc = self.0.catmem_qd;
+                if let Err(e) = self.0.catmem.close(catmem_qd) {
+                    warn!(
+                        "poll(): failed to close trunk (remote={:?}, error={:?})",
+                        self.0.remote, e
+                    );
+                }
+                return;
+            }
+
+            pending.extend_from_slice(&buf[..]);
+            let mut offset: usize = 0;
+            while let Some((id, frame_type, len)) = parse_frame_header(&pending[offset..]) {
+                let start: usize = offset + FRAME_HEADER_SIZE;
+                if pending.len() < start + len {
+                    break;
+                }
+                if let Err(e) = self.dispatch(id, frame_type, &pending[start..(start + len)]) {
+                    warn!("poll(): dropping frame (id={:?}, error={:?})", id, e);
+                }
+                offset = start + len;
+            }
+            pending.drain(..offset);
+        }
+    }
+
+    /// Handles a frame of type `frame_type` that carries `payload` on logical connection `id`.
+    fn dispatch(&mut self, id: ConnectionId, frame_type: u8, payload: &[u8]) -> Result<(), Fail> {
+        match FrameType::try_from(frame_type)? {
+            FrameType::Open => match self.0.listener.clone() {
+                Some(mut listener) if !listener.0.closed => {
+                    self.0.inboxes.insert(id, Inbox::default());
+                    listener.0.ready.push_back((self.clone(), id));
+                    Ok(())
+                },
+                // Refuse connections while not listening.
+                _ => {
+                    self.close(id);
+                    Ok(())
+                },
+            },
+            FrameType::Data => match self.0.inboxes.get_mut(&id) {
+                Some(inbox) => {
+                    inbox.ready.push_back(DemiBuffer::from_slice(payload)?);
+                    inbox.wake();
+                    Ok(())
+                },
+                None => Err(Fail::new(libc::ENOTCONN, "logical connection is not open")),
+            },
+            FrameType::Close => {
+                if let Some(inbox) = self.0.inboxes.get_mut(&id) {
+                    inbox.closed = true;
+                    inbox.wake();
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// Sends a frame of type `frame_type` that carries `payload` on logical connection `id`, and waits for it to be
+    /// pushed. If the send is cancelled, the frame is still pushed in full.
+    async fn send(
+        &mut self,
+        id: ConnectionId,
+        frame_type: FrameType,
+        payload: Option<DemiBuffer>,
+        yielder: &Yielder,
+    ) -> Result<(), Fail> {
+        self.enqueue(id, frame_type, payload, Some(yielder.get_handle()))?;
+        yielder.yield_until_wake().await
+    }
+
+    /// Queues a frame of type `frame_type` that carries `payload` on logical connection `id` in the outbox of the
+    /// target trunk. If `sender` is set, it is woken up with the result of the push.
+    fn enqueue(
+        &mut self,
+        id: ConnectionId,
+        frame_type: FrameType,
+        payload: Option<DemiBuffer>,
+        sender: Option<YielderHandle>,
+    ) -> Result<(), Fail> {
+        if let Some(e) = &self.0.error {
+            return Err(e.clone());
+        }
+        if !self.0.writing {
+            let task_name: String = format!("Catloop::mux::write for remote={:?}", self.0.remote);
+            let future = self.clone().write();
+            self.0
+                .runtime
+                .insert_background_coroutine(&task_name, Box::pin(future))?;
+            self.0.writing = true;
+        }
+
+        let len: usize = payload.as_ref().map_or(0, |buf| buf.len());
+        let mut header: DemiBuffer = DemiBuffer::new(FRAME_HEADER_SIZE as u16);
+        header[0..8].copy_from_slice(&id.0.to_le_bytes());
+        header[8] = frame_type as u8;
+        header[9..FRAME_HEADER_SIZE].copy_from_slice(&(len as u32).to_le_bytes());
+        self.0.outbox.push_back(Frame {
+            header,
+            payload,
+            sender,
+        });
+        Ok(())
+    }
+
+    /// Pushes the frames in the outbox of the target trunk, until it is empty. A frame that fails halfway would leave
+    /// the trunk corrupt, thus any failure fails the trunk.
+    async fn write(mut self) {
+        while let Some(frame) = self.0.outbox.pop_front() {
+            let mut result: Result<(), Fail> = match &self.0.error {
+                Some(e) => Err(e.clone()),
+                None => self.send_buf(frame.header).await,
+            };
+            if let (Ok(()), Some(payload)) = (&result, frame.payload) {
+                result = self.send_buf(payload).await;
+            }
+            if let Err(e) = &result {
+                if self.0.error.is_none() {
+                    warn!("write(): trunk failed (remote={:?}, error={:?})", self.0.remote, e);
+                    self.fail(e.clone());
+                }
+            }
+            if let Some(mut sender) = frame.sender {
+                sender.wake_with(result);
+            }
+        }
+        self.0.writing = false;
+    }
+
+    /// Pushes `buf` to the shared memory pipe that backs the target trunk.
+    async fn send_buf(&self, buf: DemiBuffer) -> Result<(), Fail> {
+        let catmem_qd: QDesc = self.0.catmem_qd;
+        let yielder: Yielder = Yielder::new();
+        match self
+            .0
+            .catmem
+            .clone()
+            .push_coroutine(catmem_qd, DemiBufferChain::from(buf), yielder)
+            .await
+        {
+            (_, OperationResult::Push) => Ok(()),
+            (_, OperationResult::Failed(e)) => Err(e),
+            _ => unreachable!("should not return anything other than push or error"),
+        }
+    }
+
+    /// Fails the target trunk with `e`, and wakes up the coroutines that wait for data on it.
+    fn fail(&mut self, e: Fail) {
+        self.0.error = Some(e);
+        for inbox in self.0.inboxes.values_mut() {
+            inbox.wake();
+        }
+    }
+}
+
+impl Inbox {
+    /// Wakes up the coroutines that wait for data on the target connection.
+    fn wake(&mut self) {
+        for mut waiter in self.waiters.drain(..) {
+            waiter.wake_with(Ok(()));
+        }
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Parses the header of the frame at the beginning of `buf`. Returns the identifier of the logical connection, the
+/// type of the frame and the length of its payload, or `None` if the header was not received in full yet.
+fn parse_frame_header(buf: &[u8]) -> Option<(ConnectionId, u8, usize)> {
+    if buf.len() < FRAME_HEADER_SIZE {
+        return None;
+    }
+    let id: u64 = u64::from_le_bytes(buf[0..8].try_into().expect("slice should have 8 bytes"));
+    let len: u32 = u32::from_le_bytes(buf[9..FRAME_HEADER_SIZE].try_into().expect("slice should have 4 bytes"));
+    Some((ConnectionId(id), buf[8], len as usize))
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Default for SharedAcceptQueue {
+    fn default() -> Self {
+        Self(SharedObject::new(AcceptQueue::default()))
+    }
+}
+
+impl TryFrom<u8> for FrameType {
+    type Error = Fail;
+
+    fn try_from(value: u8) -> Result<Self, Fail> {
+        match value {
+            1 => Ok(FrameType::Open),
+            2 => Ok(FrameType::Data),
+            3 => Ok(FrameType::Close),
+            _ => Err(Fail::new(libc::EBADMSG, "invalid frame type")),
+        }
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ConnectionId,
+        SharedAcceptQueue,
+        SharedTrunk,
+    };
+    use crate::{
+        catmem::SharedCatmemLibOS,
+        demikernel::config::Config,
+        ensure_eq,
+        runtime::{
+            memory::DemiBuffer,
+            scheduler::Yielder,
+            QDesc,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::futures::task::noop_waker_ref;
+    use ::std::{
+        future::Future,
+        net::{
+            Ipv4Addr,
+            SocketAddrV4,
+        },
+        pin::Pin,
+        process,
+        task::{
+            Context,
+            Poll,
+        },
+    };
+    use ::yaml_rust::Yaml;
+
+    /// Drives `future` and the coroutines of `runtime` until `future` completes.
+    fn run<F: Future>(runtime: &mut SharedDemiRuntime, future: F) -> Result<F::Output> {
+        let mut ctx: Context = Context::from_waker(noop_waker_ref());
+        futures::pin_mut!(future);
+        for _ in 0..1000 {
+            if let Poll::Ready(output) = Future::poll(Pin::new(&mut future), &mut ctx) {
+                return Ok(output);
+            }
+            runtime.poll();
+        }
+        anyhow::bail!("future did not complete")
+    }
+
+    /// Tests that a frame whose send is cancelled halfway is still pushed in full, ahead of the frames that are sent
+    /// after it.
+    #[test]
+    fn cancelled_send_pushes_whole_frame() -> Result<()> {
+        const PAYLOAD_SIZE: u16 = 32768;
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut catmem: SharedCatmemLibOS = SharedCatmemLibOS::new(&Config(Yaml::Null), runtime.clone());
+        let name: String = format!("catloop-test-cancelled-send-{}", process::id());
+        let server_qd: QDesc = catmem.create_pipe(&name)?;
+        let client_qd: QDesc = catmem.open_pipe(&name)?;
+        let remote: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 12345);
+        let mut accept_queue: SharedAcceptQueue = SharedAcceptQueue::default();
+        let server: SharedTrunk = SharedTrunk::new(
+            runtime.clone(),
+            catmem.clone(),
+            server_qd,
+            remote,
+            Some(accept_queue.clone()),
+        );
+        runtime.insert_background_coroutine("server", Box::pin(server.poll()))?;
+        let mut client: SharedTrunk = SharedTrunk::new(runtime.clone(), catmem, client_qd, remote, None);
+        let yielder: Yielder = Yielder::new();
+        let id: ConnectionId = run(&mut runtime, client.clone().open(&yielder))??;
+
+        // Start pushing a frame, then cancel the push once its header is out.
+        {
+            let mut other: SharedTrunk = client.clone();
+            let yielder: Yielder = Yielder::new();
+            let send = other.push(id, DemiBuffer::new(PAYLOAD_SIZE), &yielder);
+            futures::pin_mut!(send);
+            let mut ctx: Context = Context::from_waker(noop_waker_ref());
+            ensure_eq!(Future::poll(Pin::new(&mut send), &mut ctx).is_pending(), true);
+            runtime.poll();
+        }
+
+        // Frames that are sent later go out after it.
+        let buf: DemiBuffer = DemiBuffer::from_slice(b"hello")?;
+        run(&mut runtime, client.push(id, buf, &yielder))??;
+
+        // The remote receives both frames in full.
+        let (mut trunk, id) = match accept_queue.pop() {
+            Some(connection) => connection,
+            None => anyhow::bail!("connection should be opened"),
+        };
+        let buf: DemiBuffer = run(&mut runtime, trunk.clone().pop(id, None, &yielder))??;
+        ensure_eq!(buf.len(), PAYLOAD_SIZE as usize);
+        let buf: DemiBuffer = run(&mut runtime, trunk.pop(id, None, &yielder))??;
+        ensure_eq!(&buf[..], b"hello");
+
+        Ok(())
+    }
+}
@@ -6,12 +6,20 @@
 //======================================================================================================================
 
 use crate::{
-    catloop::socket::Socket,
+    catloop::{
+        mux::{
+            ConnectionId,
+            Multiplexing,
+            SharedTrunk,
+        },
+        socket::Socket,
+    },
     catmem::SharedCatmemLibOS,
     pal,
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
+        network::socket::state::SocketState,
         queue::{
             IoQueue,
             NetworkQueue,
@@ -49,6 +57,8 @@ use ::std::{
 pub struct CatloopQueue {
     qtype: QType,
     socket: Socket,
+    /// Role of this queue in connection multiplexing.
+    mux: Multiplexing,
 }
 
 /// A shared Catloop queue. This queue is concurrently accessed by multiple coroutines, so each time a coroutine yields, it implicitly gives up ownership and then regains it when it resumes.
@@ -65,12 +75,17 @@ impl CatloopQueue {
         Ok(Self {
             qtype,
             socket: Socket::new(runtime, catmem)?,
+            mux: Multiplexing::Off,
         })
     }
 
     /// Allocates a new Catloop queue.
     pub fn alloc(qtype: QType, socket: Socket) -> Self {
-        Self { qtype, socket }
+        Self {
+            qtype,
+            socket,
+            mux: Multiplexing::Off,
+        }
     }
 }
 
@@ -80,17 +95,40 @@ impl SharedCatloopQueue {
         Ok(Self(SharedObject::new(CatloopQueue::new(qtype, runtime, catmem)?)))
     }
 
+    /// Allocates a new shared Catloop queue for logical connection `id`, which is multiplexed over `trunk`.
+    pub fn alloc_multiplexed(qtype: QType, socket: Socket, trunk: SharedTrunk, id: ConnectionId) -> Self {
+        Self(SharedObject::new(CatloopQueue {
+            qtype,
+            socket,
+            mux: Multiplexing::Connection(trunk, id),
+        }))
+    }
+
+    /// Returns the role of the target queue in connection multiplexing.
+    pub fn get_multiplexing(&self) -> Multiplexing {
+        self.mux.clone()
+    }
+
+    /// Sets the role of the target queue in connection multiplexing.
+    pub fn set_multiplexing(&mut self, mux: Multiplexing) {
+        self.mux = mux;
+    }
+
     /// Returns the local address to which the target queue is bound.
     pub fn local(&self) -> Option<SocketAddrV4> {
         self.socket.local()
     }
 
-    #[allow(unused)]
     /// Returns the remote address to which the target queue is connected to.
     pub fn remote(&self) -> Option<SocketAddrV4> {
         self.socket.remote()
     }
 
+    /// Returns the current connection state of the target queue.
+    pub fn get_state(&self) -> SocketState {
+        self.socket.get_state()
+    }
+
     /// Binds the target queue to `local` address.
     pub fn bind(&mut self, local: SocketAddrV4) -> Result<(), Fail> {
         self.socket.bind(local)
@@ -129,6 +167,16 @@ impl SharedCatloopQueue {
         }
     }
 
+    /// Asynchronously accepts a new trunk on the queue. On success, returns the shared memory pipe that backs the trunk.
+    pub async fn do_accept_trunk(&mut self, new_port: u16, yielder: &Yielder) -> Result<QDesc, Fail> {
+        // It is safe to unwrap here because we ensured that the socket is bound, thus it is assigned a local address.
+        let ipv4: Ipv4Addr = self.local().unwrap().ip().clone();
+        let trunk_socket: Socket = self.socket.do_accept(ipv4, new_port, yielder).await?;
+        Ok(trunk_socket
+            .catmem_qd()
+            .expect("accepted socket should have a shared memory pipe"))
+    }
+
     /// Start an asynchronous coroutine to start connecting this queue. This function contains all of the single-queue,
     /// asynchronous code necessary to connect to a remote endpoint and any single-queue functionality after the
     /// connect completes.
@@ -146,9 +194,25 @@ impl SharedCatloopQueue {
         self.socket.do_connect(remote, yielder).await
     }
 
+    /// Completes a connect on a logical connection `id` over `trunk`, or fails it if `result` is an error.
+    pub fn complete_multiplexed_connect(
+        &mut self,
+        result: Result<(SharedTrunk, ConnectionId), Fail>,
+    ) -> Result<(), Fail> {
+        match result {
+            Ok((trunk, id)) => {
+                self.socket.complete_multiplexed_connect(Ok(trunk.remote()))?;
+                self.mux = Multiplexing::Connection(trunk, id);
+                Ok(())
+            },
+            Err(e) => self.socket.complete_multiplexed_connect(Err(e)),
+        }
+    }
+
     /// Close this queue. This function contains all the single-queue functionality to synchronously close a queue.
     pub fn close(&mut self) -> Result<(), Fail> {
         self.socket.close()?;
+        self.close_multiplexing();
         Ok(())
     }
 
@@ -164,6 +228,9 @@ impl SharedCatloopQueue {
     /// Close this queue. This function contains all the single-queue functionality to synchronously close a queue.
     pub async fn do_close(&mut self, yielder: Yielder) -> Result<(QDesc, OperationResult), Fail> {
         let result: (QDesc, OperationResult) = self.socket.do_close(yielder).await?;
+        if let (_, OperationResult::Close) = result {
+            self.close_multiplexing();
+        }
         Ok(result)
     }
 
@@ -178,7 +245,13 @@ impl SharedCatloopQueue {
     }
 
     pub async fn do_push(&mut self, buf: DemiBuffer, yielder: Yielder) -> Result<(QDesc, OperationResult), Fail> {
-        self.socket.do_push(buf, yielder).await
+        match self.mux.clone() {
+            Multiplexing::Connection(mut trunk, id) => match trunk.push(id, buf, &yielder).await {
+                Ok(()) => Ok((trunk.catmem_qd(), OperationResult::Push)),
+                Err(e) => Ok((trunk.catmem_qd(), OperationResult::Failed(e))),
+            },
+            _ => self.socket.do_push(buf, yielder).await,
+        }
     }
 
     /// Schedule a coroutine to pop from this queue. This function contains all of the single-queue,
@@ -192,7 +265,24 @@ impl SharedCatloopQueue {
     }
 
     pub async fn do_pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<(QDesc, OperationResult), Fail> {
-        self.socket.do_pop(size, yielder).await
+        match self.mux.clone() {
+            Multiplexing::Connection(mut trunk, id) => match trunk.pop(id, size, &yielder).await {
+                Ok(buf) => Ok((trunk.catmem_qd(), OperationResult::Pop(self.remote(), buf))),
+                Err(e) => Ok((trunk.catmem_qd(), OperationResult::Failed(e))),
+            },
+            _ => self.socket.do_pop(size, yielder).await,
+        }
+    }
+
+    /// Releases the multiplexing resources of the target queue: a logical connection is closed on its trunk, and a
+    /// listening queue stops accepting logical connections.
+    fn close_multiplexing(&mut self) {
+        match &mut self.mux {
+            Multiplexing::Connection(trunk, id) => trunk.close(*id),
+            Multiplexing::Listener(accept_queue) => accept_queue.close(),
+            Multiplexing::Off | Multiplexing::On => (),
+        }
+        self.mux = Multiplexing::Off;
     }
 }
 
@@ -12,11 +12,15 @@ use crate::{
         fail::Fail,
         memory::{
             DemiBuffer,
+            DemiBufferChain,
             MemoryRuntime,
         },
         network::socket::{
             operation::SocketOp,
-            state::SocketStateMachine,
+            state::{
+                SocketState,
+                SocketStateMachine,
+            },
         },
         queue::{
             QDesc,
@@ -114,11 +118,12 @@ impl Socket {
         })
     }
 
-    /// Allocates a new socket that is bound to [local].
-    fn alloc(
+    /// Allocates a new socket that is bound to [local]. Sockets of logical connections that are multiplexed over a
+    /// trunk have no shared memory pipe of their own.
+    pub fn alloc(
         runtime: SharedDemiRuntime,
         catmem: SharedCatmemLibOS,
-        catmem_qd: QDesc,
+        catmem_qd: Option<QDesc>,
         local: Option<SocketAddrV4>,
         remote: Option<SocketAddrV4>,
     ) -> Self {
@@ -126,7 +131,7 @@ impl Socket {
             state: SocketStateMachine::new_established(),
             runtime,
             catmem,
-            catmem_qd: Some(catmem_qd),
+            catmem_qd,
             local,
             remote,
             backlog: 1,
@@ -224,7 +229,7 @@ impl Socket {
             let new_socket: Self = Self::alloc(
                 self.runtime.clone(),
                 self.catmem.clone(),
-                new_qd,
+                Some(new_qd),
                 None,
                 Some(SocketAddrV4::new(ipv4, new_port)),
             );
@@ -312,6 +317,33 @@ impl Socket {
         }
     }
 
+    /// Opens a shared memory pipe to [remote] that is used as a trunk for many logical connections. This socket is
+    /// consumed, as the pipe is handed over to the trunk.
+    pub async fn do_open_trunk(mut self, remote: SocketAddrV4, yielder: &Yielder) -> Result<QDesc, Fail> {
+        self.state.prepare(SocketOp::Connect)?;
+        self.state.commit();
+        self.do_connect(remote, yielder).await?;
+        Ok(self.catmem_qd.expect("socket should be connected"))
+    }
+
+    /// Completes a connect on a logical connection that is multiplexed over a trunk. On success, this socket is
+    /// connected to [remote].
+    pub fn complete_multiplexed_connect(&mut self, result: Result<SocketAddrV4, Fail>) -> Result<(), Fail> {
+        match result {
+            Ok(remote) => {
+                self.state.prepare(SocketOp::Established)?;
+                self.state.commit();
+                self.remote = Some(remote);
+                Ok(())
+            },
+            Err(e) => {
+                self.state.prepare(SocketOp::Closed)?;
+                self.state.commit();
+                Err(e)
+            },
+        }
+    }
+
     /// Closes this socket.
     pub fn close(&mut self) -> Result<(), Fail> {
         self.state.prepare(SocketOp::Close)?;
@@ -381,7 +413,7 @@ impl Socket {
         // and by construction it should be connected. If not, the socket state machine
         // was not correctly driven.
         let qd: QDesc = self.catmem_qd.expect("socket should be connected");
-        Ok(self.catmem.clone().push_coroutine(qd, buf, yielder).await)
+        Ok(self.catmem.clone().push_coroutine(qd, DemiBufferChain::from(buf), yielder).await)
     }
 
     /// Schedule a coroutine to pop from the underlying Catmem queue. This function contains all of the single-queue,
@@ -407,6 +439,11 @@ impl Socket {
         }
     }
 
+    /// Returns the shared memory pipe that backs [self], if any.
+    pub fn catmem_qd(&self) -> Option<QDesc> {
+        self.catmem_qd
+    }
+
     /// Returns the `local` address to which [self] is bound.
     pub fn local(&self) -> Option<SocketAddrV4> {
         self.local
@@ -417,6 +454,11 @@ impl Socket {
         self.remote
     }
 
+    /// Returns the current connection state of [self].
+    pub fn get_state(&self) -> SocketState {
+        self.state.get_state()
+    }
+
     /// Generic function for spawning a control-path coroutine on [self].
     fn do_generic_sync_control_path_call<F>(&mut self, coroutine_constructor: F) -> Result<TaskHandle, Fail>
     where
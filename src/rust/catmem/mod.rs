@@ -16,9 +16,13 @@ use crate::{
         limits,
         memory::{
             DemiBuffer,
+            DemiBufferChain,
             MemoryRuntime,
         },
-        queue::downcast_queue,
+        queue::{
+            downcast_queue,
+            QueueInfo,
+        },
         scheduler::{
             TaskHandle,
             Yielder,
@@ -34,6 +38,8 @@ use crate::{
         OperationTask,
         QDesc,
         QToken,
+        QType,
+        Readiness,
         SharedDemiRuntime,
         SharedObject,
     },
@@ -45,6 +51,10 @@ use ::std::{
         DerefMut,
     },
     pin::Pin,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 #[cfg(feature = "profiler")]
@@ -85,7 +95,7 @@ impl SharedCatmemLibOS {
         trace!("create_pipe() name={:?}", name);
         let qd: QDesc = self
             .runtime
-            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::create(name)?);
+            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::create(name)?)?;
 
         Ok(qd)
     }
@@ -96,11 +106,63 @@ impl SharedCatmemLibOS {
 
         let qd: QDesc = self
             .runtime
-            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::open(name)?);
+            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::open(name)?)?;
+
+        Ok(qd)
+    }
+
+    /// Creates a new memory queue that carries messages, rather than a byte stream. Each pop hands out (part of) a
+    /// single message, and messages are never merged. Both ends of the queue must be framed.
+    pub fn create_pipe_framed(&mut self, name: &str) -> Result<QDesc, Fail> {
+        trace!("create_pipe_framed() name={:?}", name);
+        let qd: QDesc = self
+            .runtime
+            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::create_framed(name)?)?;
+
+        Ok(qd)
+    }
+
+    /// Opens a memory queue that carries messages, rather than a byte stream.
+    pub fn open_pipe_framed(&mut self, name: &str) -> Result<QDesc, Fail> {
+        trace!("open_pipe_framed() name={:?}", name);
+
+        let qd: QDesc = self
+            .runtime
+            .alloc_queue::<SharedCatmemQueue>(SharedCatmemQueue::open_framed(name)?)?;
 
         Ok(qd)
     }
 
+    /// Grows the shared memory ring of a memory queue to `new_size` bytes. This is only allowed while the queue is empty
+    /// in both directions, so it is best done right after the queue is set up, before any data flows. The other end
+    /// picks up the new size on its next pop.
+    pub fn resize_pipe(&mut self, qd: QDesc, new_size: usize) -> Result<(), Fail> {
+        trace!("resize_pipe() qd={:?}, new_size={:?}", qd, new_size);
+        self.get_queue(&qd)?.resize(new_size)
+    }
+
+    /// Sets the low-water mark of memory queue `qd`, so that pops do not complete until at least `bytes` bytes are
+    /// available, unless the other end closes the queue first.
+    pub fn set_rcvlowat(&mut self, qd: QDesc, bytes: usize) -> Result<(), Fail> {
+        trace!("set_rcvlowat() qd={:?}, bytes={:?}", qd, bytes);
+        self.get_queue(&qd)?.set_rcvlowat(bytes);
+        Ok(())
+    }
+
+    /// Returns how long the oldest data that waits to be popped from memory queue `qd` has been waiting, so that a
+    /// consumer that falls behind can be told apart. Returns `None` if no data is waiting, or if its age is unknown.
+    pub fn oldest_unread_age(&self, qd: QDesc) -> Result<Option<Duration>, Fail> {
+        trace!("oldest_unread_age() qd={:?}", qd);
+        Ok(self.get_queue(&qd)?.oldest_unread_age())
+    }
+
+    /// Checks which operations would make progress right away on a memory queue, from the state of its shared memory
+    /// ring.
+    pub fn readiness(&self, qd: QDesc) -> Result<Readiness, Fail> {
+        trace!("readiness() qd={:?}", qd);
+        Ok(self.get_queue(&qd)?.readiness())
+    }
+
     /// Shutdown a consumer/pop-only queue. Currently, this is basically a no-op but it does cancel pending operations
     /// and free the queue from the IoQueueTable.
     pub fn shutdown(&mut self, qd: QDesc) -> Result<(), Fail> {
@@ -157,7 +219,7 @@ impl SharedCatmemLibOS {
         }
     }
 
-    /// Pushes a scatter-gather array to a Push ring. If not a Push ring, then fail.
+    /// Pushes a scatter-gather array to a memory queue. Either end of a memory queue may push.
     pub fn push(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
         trace!("push() qd={:?}", qd);
 
@@ -169,30 +231,97 @@ impl SharedCatmemLibOS {
             return Err(Fail::new(libc::EINVAL, &cause));
         }
 
+        self.do_push_chain(qd, DemiBufferChain::from(buf))
+    }
+
+    /// Pushes a chain of buffers to a memory queue. The other end pops the concatenation of the buffers, which are
+    /// copied into the queue one after the other, without being merged first.
+    pub fn push_chain(&mut self, qd: QDesc, chain: DemiBufferChain) -> Result<QToken, Fail> {
+        trace!("push_chain() qd={:?}, len={:?}", qd, chain.len());
+
+        if chain.is_empty() {
+            let cause: String = format!("zero-length buffer chain (qd={:?})", qd);
+            error!("push_chain(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        self.do_push_chain(qd, chain)
+    }
+
+    /// Schedules a coroutine that pushes the buffers of [chain] to a memory queue.
+    fn do_push_chain(&mut self, qd: QDesc, chain: DemiBufferChain) -> Result<QToken, Fail> {
         let mut queue: SharedCatmemQueue = self.get_queue(&qd)?;
         // Issue pop operation.
         let coroutine = |yielder: Yielder| -> Result<TaskHandle, Fail> {
-            let coroutine: Pin<Box<Operation>> = Box::pin(self.clone().push_coroutine(qd, buf, yielder));
+            let coroutine: Pin<Box<Operation>> = Box::pin(self.clone().push_coroutine(qd, chain, yielder));
             let task_name: String = format!("Catmem::push for qd={:?}", qd);
             self.runtime.insert_coroutine(&task_name, coroutine)
         };
         queue.push(coroutine)
     }
 
-    pub async fn push_coroutine(self, qd: QDesc, buf: DemiBuffer, yielder: Yielder) -> (QDesc, OperationResult) {
+    pub async fn push_coroutine(self, qd: QDesc, chain: DemiBufferChain, yielder: Yielder) -> (QDesc, OperationResult) {
         // Make sure the queue still exists.
         let mut queue: SharedCatmemQueue = match self.get_queue(&qd) {
             Ok(queue) => queue,
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
         // Handle result.
-        match queue.do_push(buf, yielder).await {
+        match queue.do_push_chain(chain, &yielder).await {
             Ok(()) => (qd, OperationResult::Push),
             Err(e) => (qd, OperationResult::Failed(e)),
         }
     }
 
-    /// Pops data from a Pop ring. If not a Pop ring, then return an error.
+    /// Pushes a scatter-gather array to a memory queue, failing with `ETIMEDOUT` if the queue does not accept it
+    /// within `timeout`. A buffer that has been partially written when the timeout expires is pushed to completion,
+    /// so the stream is never left with a truncated buffer.
+    pub fn push_timeout(&mut self, qd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        trace!("push_timeout() qd={:?}, timeout={:?}", qd, timeout);
+
+        let buf: DemiBuffer = self.runtime.clone_sgarray(sga)?;
+
+        if buf.len() == 0 {
+            let cause: String = format!("zero-length buffer (qd={:?})", qd);
+            error!("push_timeout(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        let deadline: Instant = self.runtime.get_now() + timeout;
+        let mut queue: SharedCatmemQueue = self.get_queue(&qd)?;
+        // Issue push operation.
+        let coroutine = |yielder: Yielder| -> Result<TaskHandle, Fail> {
+            let coroutine: Pin<Box<Operation>> =
+                Box::pin(self.clone().push_timeout_coroutine(qd, buf, deadline, yielder));
+            let task_name: String = format!("Catmem::push_timeout for qd={:?}", qd);
+            self.runtime.insert_coroutine(&task_name, coroutine)
+        };
+        queue.push(coroutine)
+    }
+
+    pub async fn push_timeout_coroutine(
+        self,
+        qd: QDesc,
+        buf: DemiBuffer,
+        deadline: Instant,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        // Make sure the queue still exists.
+        let mut queue: SharedCatmemQueue = match self.get_queue(&qd) {
+            Ok(queue) => queue,
+            Err(e) => return (qd, OperationResult::Failed(e)),
+        };
+        // Handle result.
+        match queue
+            .do_push_until(buf, Some((self.runtime.get_timer(), deadline)), &yielder)
+            .await
+        {
+            Ok(()) => (qd, OperationResult::Push),
+            Err(e) => (qd, OperationResult::Failed(e)),
+        }
+    }
+
+    /// Pops data from a memory queue. Either end of a memory queue may pop.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         trace!("pop() qd={:?}, size={:?}", qd, size);
 
@@ -217,28 +346,172 @@ impl SharedCatmemLibOS {
         };
 
         // Wait for pop to complete.
-        let (buf, _) = match queue.do_pop(size, yielder).await {
+        let (buf, _) = match queue.do_pop(size, &yielder).await {
             Ok(result) => result,
             Err(e) => return (qd, OperationResult::Failed(e)),
         };
         (qd, OperationResult::Pop(None, buf))
     }
 
-    /// Takes out the [OperationResult] associated with the target [TaskHandle].
-    fn take_result(&mut self, handle: TaskHandle) -> (QDesc, OperationResult) {
-        let task: OperationTask = self.runtime.remove_coroutine(&handle);
+    /// Peeks at the next message in memory queue `qd`, without removing it. Fails with `EAGAIN` if there is no message
+    /// yet. Peeking again returns the same message, until it is removed with [commit_pop].
+    pub fn peek_message(&mut self, qd: QDesc, size: Option<usize>) -> Result<demi_sgarray_t, Fail> {
+        trace!("peek_message() qd={:?}, size={:?}", qd, size);
+
+        // We just assert 'size' here, because it was previously checked at PDPIX layer.
+        debug_assert!(size.is_none() || ((size.unwrap() > 0) && (size.unwrap() <= limits::POP_SIZE_MAX)));
+
+        let buf: DemiBuffer = self.get_queue(&qd)?.peek_message(size)?;
+        self.runtime.into_sgarray(buf)
+    }
+
+    /// Removes the message that was last peeked at from memory queue `qd`.
+    pub fn commit_pop(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("commit_pop() qd={:?}", qd);
+        self.get_queue(&qd)?.commit_pop()
+    }
+
+    /// Streams data from memory queue `qd` into `f`, one chunk at a time, until `f` returns false or `qd` reaches end of
+    /// file. Each chunk is released as soon as `f` returns, so memory usage stays bounded regardless of the size of the
+    /// stream.
+    pub fn pop_stream<F: FnMut(&[u8]) -> bool + 'static>(&mut self, qd: QDesc, f: F) -> Result<QToken, Fail> {
+        trace!("pop_stream() qd={:?}", qd);
+
+        let mut queue: SharedCatmemQueue = self.get_queue(&qd)?;
+        let coroutine = |yielder: Yielder| -> Result<TaskHandle, Fail> {
+            let coroutine: Pin<Box<Operation>> = Box::pin(self.clone().pop_stream_coroutine(qd, f, yielder));
+            let task_name: String = format!("Catmem::pop_stream for qd={:?}", qd);
+            self.runtime.insert_coroutine(&task_name, coroutine)
+        };
+        queue.pop(coroutine)
+    }
+
+    pub async fn pop_stream_coroutine<F: FnMut(&[u8]) -> bool>(
+        self,
+        qd: QDesc,
+        mut f: F,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        // Make sure the queue still exists.
+        let mut queue: SharedCatmemQueue = match self.get_queue(&qd) {
+            Ok(queue) => queue,
+            Err(e) => return (qd, OperationResult::Failed(e)),
+        };
+
+        let mut nbytes: usize = 0;
+        loop {
+            let (buf, eof): (DemiBuffer, bool) = match queue.do_pop(None, &yielder).await {
+                Ok(result) => result,
+                Err(e) => return (qd, OperationResult::Failed(e)),
+            };
+            if eof {
+                break;
+            }
+            nbytes += buf.len();
+            if !f(&buf[..]) {
+                break;
+            }
+        }
+
+        trace!("data streamed ({:?} bytes)", nbytes);
+        (qd, OperationResult::PopStream(nbytes))
+    }
+
+    /// Moves data from memory queue `src_qd` to memory queue `dst_qd` until `size` bytes have been moved or `src_qd`
+    /// reaches end of file. Popped buffers are pushed as is, so data is never copied into application buffers.
+    pub fn splice(&mut self, src_qd: QDesc, dst_qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        trace!("splice() src_qd={:?}, dst_qd={:?}, size={:?}", src_qd, dst_qd, size);
+
+        if src_qd == dst_qd {
+            let cause: String = format!("cannot splice a queue into itself (qd={:?})", src_qd);
+            error!("splice(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if size == Some(0) {
+            let cause: String = format!("zero-length splice (src_qd={:?})", src_qd);
+            error!("splice(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        // Make sure that the destination queue exists. The operation is tracked by the source queue.
+        self.get_queue(&dst_qd)?;
+        let mut queue: SharedCatmemQueue = self.get_queue(&src_qd)?;
+        let coroutine = |yielder: Yielder| -> Result<TaskHandle, Fail> {
+            let coroutine: Pin<Box<Operation>> = Box::pin(self.clone().splice_coroutine(src_qd, dst_qd, size, yielder));
+            let task_name: String = format!("Catmem::splice for src_qd={:?} dst_qd={:?}", src_qd, dst_qd);
+            self.runtime.insert_coroutine(&task_name, coroutine)
+        };
+        queue.pop(coroutine)
+    }
+
+    pub async fn splice_coroutine(
+        self,
+        src_qd: QDesc,
+        dst_qd: QDesc,
+        size: Option<usize>,
+        yielder: Yielder,
+    ) -> (QDesc, OperationResult) {
+        // Make sure both queues still exist.
+        let mut src_queue: SharedCatmemQueue = match self.get_queue(&src_qd) {
+            Ok(queue) => queue,
+            Err(e) => return (src_qd, OperationResult::Failed(e)),
+        };
+        let mut dst_queue: SharedCatmemQueue = match self.get_queue(&dst_qd) {
+            Ok(queue) => queue,
+            Err(e) => return (src_qd, OperationResult::Failed(e)),
+        };
+
+        let mut nbytes: usize = 0;
+        loop {
+            // Pop at most what is left to move.
+            let pop_size: usize = match size {
+                Some(size) if size == nbytes => break,
+                Some(size) => (size - nbytes).min(limits::POP_SIZE_MAX),
+                None => limits::POP_SIZE_MAX,
+            };
+            let (buf, eof): (DemiBuffer, bool) = match src_queue.do_pop(Some(pop_size), &yielder).await {
+                Ok(result) => result,
+                Err(e) => return (src_qd, OperationResult::Failed(e)),
+            };
+            if eof {
+                break;
+            }
+
+            let len: usize = buf.len();
+            if let Err(e) = dst_queue.do_push(buf, &yielder).await {
+                return (src_qd, OperationResult::Failed(e));
+            }
+            nbytes += len;
+        }
+
+        trace!("data spliced ({:?} bytes)", nbytes);
+        (src_qd, OperationResult::Splice(nbytes))
+    }
+
+    /// Takes out the [OperationResult] associated with the target [TaskHandle], if the coroutine has completed. The
+    /// coroutine is removed from the scheduler only then, so `None` is returned and nothing changes if it is still
+    /// running or if its result was already taken out.
+    pub fn try_get_result(&mut self, handle: &TaskHandle) -> Option<(QDesc, OperationResult)> {
+        let task: OperationTask = self.runtime.try_remove_coroutine(handle)?;
         let (qd, result): (QDesc, OperationResult) = task.get_result().expect("The coroutine has not finished");
 
         match self.get_queue(&qd) {
-            Ok(mut queue) => queue.remove_pending_op(&handle),
-            Err(_) => debug!("take_result(): this queue was closed (qd={:?})", qd),
+            Ok(mut queue) => queue.remove_pending_op(handle),
+            Err(_) => debug!("try_get_result(): this queue was closed (qd={:?})", qd),
         }
 
-        (qd, result)
+        Some((qd, result))
     }
 
     pub fn pack_result(&mut self, handle: TaskHandle, qt: QToken) -> Result<demi_qresult_t, Fail> {
-        let (qd, result): (QDesc, OperationResult) = self.take_result(handle);
+        let (qd, result): (QDesc, OperationResult) = match self.try_get_result(&handle) {
+            Some(result) => result,
+            None => {
+                let cause: String = format!("operation has not completed (qt={:?})", qt);
+                error!("pack_result(): {}", cause);
+                return Err(Fail::new(libc::EAGAIN, &cause));
+            },
+        };
         let qr = match result {
             OperationResult::Push => demi_qresult_t {
                 qr_opcode: demi_opcode_t::DEMI_OPC_PUSH,
@@ -276,6 +549,20 @@ impl SharedCatmemLibOS {
                 qr_ret: 0,
                 qr_value: unsafe { mem::zeroed() },
             },
+            OperationResult::Splice(nbytes) => demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_SPLICE,
+                qr_qd: qd.into(),
+                qr_qt: qt.into(),
+                qr_ret: nbytes as i64,
+                qr_value: unsafe { mem::zeroed() },
+            },
+            OperationResult::PopStream(nbytes) => demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_POP_STREAM,
+                qr_qd: qd.into(),
+                qr_qt: qt.into(),
+                qr_ret: nbytes as i64,
+                qr_value: unsafe { mem::zeroed() },
+            },
             OperationResult::Failed(e) => {
                 warn!("Operation Failed: {:?}", e);
                 demi_qresult_t {
@@ -291,6 +578,24 @@ impl SharedCatmemLibOS {
         Ok(qr)
     }
 
+    /// Lists all memory queues that are currently open in Catmem. This function is read-only and intended for
+    /// diagnostics.
+    pub fn list_queues(&self) -> Vec<QueueInfo> {
+        let mut queues: Vec<QueueInfo> = Vec::new();
+        for qd in self.runtime.get_qtable().get_qds() {
+            if let Ok(QType::MemoryQueue) = self.runtime.get_queue_type(&qd) {
+                queues.push(QueueInfo {
+                    qd,
+                    qtype: QType::MemoryQueue,
+                    local: None,
+                    remote: None,
+                    state: None,
+                });
+            }
+        }
+        queues
+    }
+
     pub fn get_queue(&self, qd: &QDesc) -> Result<SharedCatmemQueue, Fail> {
         Ok(self.runtime.get_qtable().get::<SharedCatmemQueue>(qd)?.clone())
     }
@@ -327,3 +632,284 @@ impl Drop for CatmemLibOS {
         }
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CatmemLibOS,
+        SharedCatmemLibOS,
+    };
+    use crate::{
+        ensure_eq,
+        runtime::{
+            memory::{
+                DemiBuffer,
+                DemiBufferChain,
+                MemoryRuntime,
+            },
+            scheduler::TaskHandle,
+            types::demi_sgarray_t,
+            OperationResult,
+            QDesc,
+            QToken,
+            SharedDemiRuntime,
+            SharedObject,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        ptr::NonNull,
+        sync::{
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+            Arc,
+        },
+    };
+
+    /// Tests that the result of an operation is only taken out once the operation completes, and only once.
+    #[test]
+    fn try_get_result_waits_for_completion() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedCatmemLibOS = SharedCatmemLibOS(SharedObject::new(CatmemLibOS::new(runtime.clone())));
+        let server_qd: QDesc = libos.create_pipe("catmem-test-try-get-result")?;
+        let client_qd: QDesc = libos.open_pipe("catmem-test-try-get-result")?;
+
+        // The pop stays pending, as there is nothing to pop yet.
+        let qt: QToken = libos.pop(server_qd, None)?;
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        runtime.poll();
+        ensure_eq!(libos.try_get_result(&handle).is_none(), true);
+        ensure_eq!(runtime.from_task_id(qt).is_ok(), true);
+
+        // Once data arrives, the result is taken out and the coroutine is removed.
+        let sga: demi_sgarray_t = libos.runtime.into_sgarray(DemiBuffer::from_slice(&[1, 2, 3])?)?;
+        let push_qt: QToken = libos.push(client_qd, &sga)?;
+        libos.runtime.sgafree(sga)?;
+        runtime.poll();
+        runtime.poll();
+        match libos.try_get_result(&handle) {
+            Some((qd, OperationResult::Pop(_, buf))) => {
+                ensure_eq!(qd, server_qd);
+                ensure_eq!(&buf[..], &[1, 2, 3]);
+            },
+            _ => anyhow::bail!("pop should have completed"),
+        }
+        ensure_eq!(runtime.from_task_id(qt).is_err(), true);
+
+        // Taking out the result again does not panic.
+        ensure_eq!(libos.try_get_result(&handle).is_none(), true);
+
+        let push_handle: TaskHandle = runtime.from_task_id(push_qt)?;
+        ensure_eq!(libos.try_get_result(&push_handle).is_some(), true);
+
+        Ok(())
+    }
+
+    /// Tests that pushing a buffer that wraps external memory leaves that memory to its owner.
+    #[test]
+    fn push_external_buffer() -> Result<()> {
+        static DATA: [u8; 3] = [4, 5, 6];
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedCatmemLibOS = SharedCatmemLibOS(SharedObject::new(CatmemLibOS::new(runtime.clone())));
+        let server_qd: QDesc = libos.create_pipe("catmem-test-push-external-buffer")?;
+        let client_qd: QDesc = libos.open_pipe("catmem-test-push-external-buffer")?;
+
+        let drops: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let counter: Arc<AtomicUsize> = drops.clone();
+        // Safety: This is safe, as DATA lives forever and is only read through the buffer.
+        let buf: DemiBuffer = unsafe {
+            DemiBuffer::from_external(NonNull::from(&DATA).cast::<u8>(), DATA.len() as u16, move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+        let sga: demi_sgarray_t = libos.runtime.into_sgarray(buf)?;
+        let push_qt: QToken = libos.push(client_qd, &sga)?;
+        let pop_qt: QToken = libos.pop(server_qd, None)?;
+        runtime.poll();
+        runtime.poll();
+        ensure_eq!(drops.load(Ordering::Relaxed), 0);
+        libos.runtime.sgafree(sga)?;
+
+        // The push holds a reference on the memory until it is reaped.
+        let push_handle: TaskHandle = runtime.from_task_id(push_qt)?;
+        ensure_eq!(libos.try_get_result(&push_handle).is_some(), true);
+        let pop_handle: TaskHandle = runtime.from_task_id(pop_qt)?;
+        match libos.try_get_result(&pop_handle) {
+            Some((qd, OperationResult::Pop(_, buf))) => {
+                ensure_eq!(qd, server_qd);
+                ensure_eq!(&buf[..], &DATA[..]);
+            },
+            _ => anyhow::bail!("pop should have completed"),
+        }
+        ensure_eq!(drops.load(Ordering::Relaxed), 1);
+
+        Ok(())
+    }
+
+    /// Tests that pushing a chain of buffers delivers their concatenation, and that the chain is pushed from the very
+    /// buffers that it was built from.
+    #[test]
+    fn push_chain_concatenates_buffers() -> Result<()> {
+        static HEADER: [u8; 2] = [7, 8];
+        static BODY: [u8; 4] = [9, 10, 11, 12];
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedCatmemLibOS = SharedCatmemLibOS(SharedObject::new(CatmemLibOS::new(runtime.clone())));
+        let server_qd: QDesc = libos.create_pipe("catmem-test-push-chain")?;
+        let client_qd: QDesc = libos.open_pipe("catmem-test-push-chain")?;
+
+        // Wrap external memory, so that the chain can only be pushed by reading it in place.
+        let drops: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let mut chain: DemiBufferChain = DemiBufferChain::new();
+        for data in [&HEADER[..], &BODY[..]] {
+            let counter: Arc<AtomicUsize> = drops.clone();
+            // Safety: This is safe, as the data lives forever and is only read through the buffer.
+            chain.push(unsafe {
+                DemiBuffer::from_external(NonNull::from(data).cast::<u8>(), data.len() as u16, move || {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                })
+            });
+        }
+        ensure_eq!(
+            chain.iter().map(|buf| buf.as_ptr()).collect::<Vec<*const u8>>(),
+            vec![HEADER.as_ptr(), BODY.as_ptr()]
+        );
+
+        // Empty chains are rejected.
+        ensure_eq!(libos.push_chain(client_qd, DemiBufferChain::new()).is_err(), true);
+
+        let push_qt: QToken = libos.push_chain(client_qd, chain)?;
+        libos.set_rcvlowat(server_qd, HEADER.len() + BODY.len())?;
+        let pop_qt: QToken = libos.pop(server_qd, None)?;
+        for _ in 0..3 {
+            runtime.poll();
+        }
+
+        let push_handle: TaskHandle = runtime.from_task_id(push_qt)?;
+        match libos.try_get_result(&push_handle) {
+            Some((_, OperationResult::Push)) => {},
+            _ => anyhow::bail!("push should have completed"),
+        }
+        ensure_eq!(drops.load(Ordering::Relaxed), 2);
+        let pop_handle: TaskHandle = runtime.from_task_id(pop_qt)?;
+        match libos.try_get_result(&pop_handle) {
+            Some((qd, OperationResult::Pop(_, buf))) => {
+                ensure_eq!(qd, server_qd);
+                ensure_eq!(&buf[..], &[7, 8, 9, 10, 11, 12]);
+            },
+            _ => anyhow::bail!("pop should have completed"),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that pops wait for the low-water mark to be reached, unless the other end closes the queue first.
+    #[test]
+    fn set_rcvlowat_waits_for_data() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedCatmemLibOS = SharedCatmemLibOS(SharedObject::new(CatmemLibOS::new(runtime.clone())));
+        let server_qd: QDesc = libos.create_pipe("catmem-test-set-rcvlowat")?;
+        let client_qd: QDesc = libos.open_pipe("catmem-test-set-rcvlowat")?;
+        libos.set_rcvlowat(server_qd, 8)?;
+
+        // The pop waits while there are fewer bytes than the low-water mark, and then gathers them.
+        let qt: QToken = libos.pop(server_qd, None)?;
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        for data in [&[1, 1, 1, 1][..], &[2, 2, 2, 2, 2, 2][..]] {
+            ensure_eq!(libos.try_get_result(&handle).is_none(), true);
+            let sga: demi_sgarray_t = libos.runtime.into_sgarray(DemiBuffer::from_slice(data)?)?;
+            libos.push(client_qd, &sga)?;
+            libos.runtime.sgafree(sga)?;
+            for _ in 0..3 {
+                runtime.poll();
+            }
+        }
+        match libos.try_get_result(&handle) {
+            Some((_, OperationResult::Pop(_, buf))) => ensure_eq!(&buf[..], &[1, 1, 1, 1, 2, 2, 2, 2, 2, 2]),
+            _ => anyhow::bail!("pop should have completed"),
+        }
+
+        // Fewer bytes are handed out once the other end closes the queue, and then comes EoF.
+        let sga: demi_sgarray_t = libos.runtime.into_sgarray(DemiBuffer::from_slice(&[3, 3])?)?;
+        libos.push(client_qd, &sga)?;
+        libos.runtime.sgafree(sga)?;
+        let qt: QToken = libos.pop(server_qd, None)?;
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        for _ in 0..3 {
+            runtime.poll();
+        }
+        ensure_eq!(libos.try_get_result(&handle).is_none(), true);
+        libos.close(client_qd)?;
+        for _ in 0..3 {
+            runtime.poll();
+        }
+        match libos.try_get_result(&handle) {
+            Some((_, OperationResult::Pop(_, buf))) => ensure_eq!(&buf[..], &[3, 3]),
+            _ => anyhow::bail!("pop should have completed"),
+        }
+        let qt: QToken = libos.pop(server_qd, None)?;
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        runtime.poll();
+        match libos.try_get_result(&handle) {
+            Some((_, OperationResult::Pop(_, buf))) => ensure_eq!(buf.len(), 0),
+            _ => anyhow::bail!("pop should have reported EoF"),
+        }
+
+        Ok(())
+    }
+
+    /// Tests that framed queues hand out one message per pop, even with a low-water mark, and split only messages that
+    /// do not fit in a pop.
+    #[test]
+    fn framed_pipe_preserves_boundaries() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: SharedCatmemLibOS = SharedCatmemLibOS(SharedObject::new(CatmemLibOS::new(runtime.clone())));
+        let server_qd: QDesc = libos.create_pipe_framed("catmem-test-framed-pipe")?;
+        let client_qd: QDesc = libos.open_pipe_framed("catmem-test-framed-pipe")?;
+        libos.set_rcvlowat(server_qd, 8)?;
+
+        for data in [&[1][..], &[2, 2][..], &[3, 3, 3, 3, 3][..]] {
+            let sga: demi_sgarray_t = libos.runtime.into_sgarray(DemiBuffer::from_slice(data)?)?;
+            libos.push(client_qd, &sga)?;
+            libos.runtime.sgafree(sga)?;
+        }
+        for _ in 0..3 {
+            runtime.poll();
+        }
+
+        // Peeking would expose the framing, so it is not supported.
+        ensure_eq!(libos.peek_message(server_qd, None).is_err(), true);
+
+        for (size, expected) in [
+            (None, &[1][..]),
+            (None, &[2, 2][..]),
+            (Some(3), &[3, 3, 3][..]),
+            (None, &[3, 3][..]),
+        ] {
+            let qt: QToken = libos.pop(server_qd, size)?;
+            let handle: TaskHandle = runtime.from_task_id(qt)?;
+            runtime.poll();
+            match libos.try_get_result(&handle) {
+                Some((_, OperationResult::Pop(_, buf))) => ensure_eq!(&buf[..], expected),
+                _ => anyhow::bail!("pop should have completed"),
+            }
+        }
+
+        // EoF comes once the other end closes the queue.
+        libos.close(client_qd)?;
+        let qt: QToken = libos.pop(server_qd, None)?;
+        let handle: TaskHandle = runtime.from_task_id(qt)?;
+        runtime.poll();
+        match libos.try_get_result(&handle) {
+            Some((_, OperationResult::Pop(_, buf))) => ensure_eq!(buf.len(), 0),
+            _ => anyhow::bail!("pop should have reported EoF"),
+        }
+
+        Ok(())
+    }
+}
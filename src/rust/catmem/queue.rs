@@ -13,38 +13,58 @@ use crate::{
     runtime::{
         fail::Fail,
         limits,
-        memory::DemiBuffer,
+        memory::{
+            DemiBuffer,
+            DemiBufferChain,
+        },
         queue::IoQueue,
         scheduler::{
             TaskHandle,
             Yielder,
             YielderHandle,
         },
+        timer::SharedTimer,
         DemiRuntime,
         QToken,
         QType,
+        Readiness,
         SharedObject,
     },
 };
 use ::std::{
     any::Any,
+    cmp,
     collections::HashMap,
     ops::{
         Deref,
         DerefMut,
     },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Size of the largest message that may be popped from a shared memory ring at once.
+const MESSAGE_SIZE_MAX: usize = u16::MAX as usize;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
 
 /// This structure contains code for manipulating a single, Catmem-specific Demikernel queue. Queue state is kept in
 /// the [ring] structure, while [pending_ops] holds the map of TaskHandles and YielderHandle for currently active async
-/// functions.
+/// functions. Data of a message that did not fit in the buffer of a pop is kept in [leftover], and is handed out by the
+/// next pops before anything else that is in the ring. Pops wait for at least [low_water] bytes, unless EoF comes first.
 pub struct CatmemQueue {
     ring: Ring,
     pending_ops: HashMap<TaskHandle, YielderHandle>,
+    leftover: Option<DemiBuffer>,
+    low_water: usize,
 }
 
 #[derive(Clone)]
@@ -55,20 +75,34 @@ pub struct SharedCatmemQueue(SharedObject<CatmemQueue>);
 //======================================================================================================================
 
 impl CatmemQueue {
+    /// Creates a new [CatmemQueue] on top of [ring].
+    fn new(ring: Ring) -> Self {
+        Self {
+            ring,
+            pending_ops: HashMap::<TaskHandle, YielderHandle>::new(),
+            leftover: None,
+            low_water: 1,
+        }
+    }
+
     /// Creates a new [CatmemQueue] and a new shared ring buffer.
     pub fn create(name: &str) -> Result<Self, Fail> {
-        Ok(Self {
-            ring: Ring::create(name)?,
-            pending_ops: HashMap::<TaskHandle, YielderHandle>::new(),
-        })
+        Ok(Self::new(Ring::create(name)?))
     }
 
     /// Creates a new [CatmemQueue] and attaches it to an existing share ring buffer.
     pub fn open(name: &str) -> Result<Self, Fail> {
-        Ok(Self {
-            ring: Ring::open(name)?,
-            pending_ops: HashMap::<TaskHandle, YielderHandle>::new(),
-        })
+        Ok(Self::new(Ring::open(name)?))
+    }
+
+    /// Creates a new [CatmemQueue] and a new shared ring buffer that carries messages, rather than a byte stream.
+    pub fn create_framed(name: &str) -> Result<Self, Fail> {
+        Ok(Self::new(Ring::create_framed(name)?))
+    }
+
+    /// Creates a new [CatmemQueue] and attaches it to an existing shared ring buffer that carries messages.
+    pub fn open_framed(name: &str) -> Result<Self, Fail> {
+        Ok(Self::new(Ring::open_framed(name)?))
     }
 }
 
@@ -81,6 +115,14 @@ impl SharedCatmemQueue {
         Ok(Self(SharedObject::new(CatmemQueue::open(name)?)))
     }
 
+    pub fn create_framed(name: &str) -> Result<Self, Fail> {
+        Ok(Self(SharedObject::new(CatmemQueue::create_framed(name)?)))
+    }
+
+    pub fn open_framed(name: &str) -> Result<Self, Fail> {
+        Ok(Self(SharedObject::new(CatmemQueue::open_framed(name)?)))
+    }
+
     pub fn shutdown(&mut self) -> Result<(), Fail> {
         {
             self.ring.prepare_close()?;
@@ -162,24 +204,89 @@ impl SharedCatmemQueue {
         self.do_generic_sync_data_path_call(coroutine_constructor)
     }
 
-    /// This function pops a buffer of optional [size] from the queue. If the queue is connected to the push end of a
-    /// shared memory ring, this function returns an error.
-    pub async fn do_pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<(DemiBuffer, bool), Fail> {
+    /// This function pops a buffer of optional [size] from the queue. Data is read from the direction of the shared
+    /// memory ring that the other end pushes to. Messages that are larger than [size] are handed out across several
+    /// pops, and EoF is only reported once all data that was pushed before it has been handed out. Messages are
+    /// gathered until there are at least as many bytes as the low-water mark, unless EoF comes first. Framed queues
+    /// never merge messages, so each pop hands out (part of) a single message regardless of the low-water mark.
+    pub async fn do_pop(&mut self, size: Option<usize>, yielder: &Yielder) -> Result<(DemiBuffer, bool), Fail> {
         let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
+        let (mut buf, eof): (DemiBuffer, bool) = self.do_pop_once(size, yielder).await?;
+
+        let low_water: usize = if self.ring.is_framed() {
+            0
+        } else {
+            cmp::min(self.low_water, size)
+        };
+        while !eof && buf.len() < low_water {
+            let (more, more_eof): (DemiBuffer, bool) = match self.do_pop_once(size - buf.len(), yielder).await {
+                Ok(result) => result,
+                Err(e) => {
+                    // Keep what was gathered so far for the next pops.
+                    self.leftover = Some(buf);
+                    return Err(e);
+                },
+            };
+            if more_eof {
+                // Report EoF on the next pop, after handing out what was gathered so far.
+                self.leftover = Some(more);
+                break;
+            }
+            let mut gathered: DemiBuffer = DemiBuffer::new((buf.len() + more.len()) as u16);
+            gathered[..buf.len()].copy_from_slice(&buf[..]);
+            gathered[buf.len()..].copy_from_slice(&more[..]);
+            buf = gathered;
+        }
+
+        Ok((buf, eof))
+    }
+
+    /// Pops the next message of at most [size] bytes from the queue, without regard to the low-water mark.
+    async fn do_pop_once(&mut self, size: usize, yielder: &Yielder) -> Result<(DemiBuffer, bool), Fail> {
+        // Hand out what is left of the last message first, as it was pushed before anything that is still in the ring.
+        // An empty leftover stands for an EoF that was reached while gathering data for an earlier pop.
+        if let Some(mut leftover) = self.leftover.take() {
+            if leftover.len() > size {
+                let buf: DemiBuffer = leftover.split_front(size)?;
+                self.leftover = Some(leftover);
+                return Ok((buf, false));
+            }
+            let eof: bool = leftover.len() == 0;
+            trace!("data read ({:?}/{:?} bytes, eof={:?})", leftover.len(), size, eof);
+            return Ok((leftover, eof));
+        }
+
+        if self.ring.is_framed() {
+            return self.do_pop_message(size, yielder).await;
+        }
+
         let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
         let eof: bool = loop {
+            let capacity: usize = buf.len();
             match self.ring.try_pop(&mut buf) {
                 Ok((len, eof)) => {
                     if eof {
                         self.ring.prepare_close()?;
                         self.ring.commit();
-                        buf.trim(size).expect("should be able to trim to a zero-length buffer");
+                        buf.trim(capacity)
+                            .expect("should be able to trim to a zero-length buffer");
                     } else {
-                        buf.trim(size - len)
+                        buf.trim(capacity - len)
                             .expect("should be able to trim down to only read bytes");
+                        // Keep the part of the message that does not fit for the next pops.
+                        if len > size {
+                            let mut leftover: DemiBuffer = buf;
+                            buf = leftover.split_front(size)?;
+                            self.leftover = Some(leftover);
+                        }
                     }
                     break eof;
                 },
+                // The next message does not fit, so pop it into a buffer that is large enough for any message.
+                Err(e) if e.errno == libc::EINVAL && capacity < MESSAGE_SIZE_MAX => {
+                    buf = DemiBuffer::new(MESSAGE_SIZE_MAX as u16);
+                    continue;
+                },
                 Err(e) if DemiRuntime::should_retry(e.errno) => {
                     // Operation in progress. Check if cancelled.
                     match yielder.yield_once().await {
@@ -195,6 +302,78 @@ impl SharedCatmemQueue {
         Ok((buf, eof))
     }
 
+    /// Pops the next message from a framed queue. The part of the message that does not fit in [size] bytes is
+    /// handed out by the next pops, before any other message.
+    async fn do_pop_message(&mut self, size: usize, yielder: &Yielder) -> Result<(DemiBuffer, bool), Fail> {
+        let (msg, eof): (Vec<u8>, bool) = loop {
+            match self.ring.pop_message() {
+                Ok(result) => break result,
+                Err(e) if DemiRuntime::should_retry(e.errno) => {
+                    // Operation in progress. Check if cancelled.
+                    match yielder.yield_once().await {
+                        Ok(()) => continue,
+                        Err(cause) => return Err(cause),
+                    }
+                },
+                Err(e) => return Err(e),
+            }
+        };
+
+        if eof {
+            self.ring.prepare_close()?;
+            self.ring.commit();
+            trace!("message read (0/{:?} bytes, eof={:?})", size, eof);
+            return Ok((DemiBuffer::new(0), true));
+        }
+
+        let mut buf: DemiBuffer = DemiBuffer::from_slice(&msg)?;
+        if buf.len() > size {
+            let mut leftover: DemiBuffer = buf;
+            buf = leftover.split_front(size)?;
+            self.leftover = Some(leftover);
+        }
+        trace!("message read ({:?}/{:?} bytes, eof={:?})", buf.len(), size, eof);
+        Ok((buf, false))
+    }
+
+    /// Peeks at the next message of optional [size] in this queue, without removing it. Peeking again returns the same
+    /// message, until it is removed with [commit_pop]. This gives at-least-once semantics to consumers that may fail to
+    /// process a message. A zero-length buffer indicates end of file.
+    pub fn peek_message(&mut self, size: Option<usize>) -> Result<DemiBuffer, Fail> {
+        if self.ring.is_framed() {
+            let cause: &str = "cannot peek at a framed queue";
+            error!("peek_message(): {}", cause);
+            return Err(Fail::new(libc::ENOTSUP, cause));
+        }
+        if self.leftover.is_some() {
+            let cause: &str = "the rest of a popped message must be popped first";
+            error!("peek_message(): {}", cause);
+            return Err(Fail::new(libc::EBUSY, cause));
+        }
+        let size: usize = size.unwrap_or(limits::RECVBUF_SIZE_MAX);
+        let mut buf: DemiBuffer = DemiBuffer::new(size as u16);
+        let (len, eof): (usize, bool) = self.ring.try_peek(&mut buf)?;
+        buf.trim(size - len)
+            .expect("should be able to trim down to only read bytes");
+        trace!("data peeked ({:?}/{:?} bytes, eof={:?})", buf.len(), size, eof);
+        Ok(buf)
+    }
+
+    /// Sets the low-water mark of this queue, so that pops wait for at least [bytes] bytes unless EoF comes first. A
+    /// low-water mark of zero is treated as one.
+    pub fn set_rcvlowat(&mut self, bytes: usize) {
+        self.low_water = cmp::max(bytes, 1);
+    }
+
+    /// Removes the message that was last peeked at from this queue.
+    pub fn commit_pop(&mut self) -> Result<(), Fail> {
+        if self.ring.commit_pop()? {
+            self.ring.prepare_close()?;
+            self.ring.commit();
+        }
+        Ok(())
+    }
+
     /// Schedule a coroutine to push to this queue. This function contains all of the single-queue,
     /// asynchronous code necessary to run push a buffer and any single-queue functionality after the push completes.
     pub fn push<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
@@ -204,17 +383,48 @@ impl SharedCatmemQueue {
         self.do_generic_sync_data_path_call(coroutine_constructor)
     }
 
-    /// This function tries to push [buf] to the shared memory ring. If the queue is connected to the pop end, then
-    /// this function returns an error.
-    pub async fn do_push(&mut self, mut buf: DemiBuffer, yielder: Yielder) -> Result<(), Fail> {
+    /// This function tries to push [buf] to the shared memory ring. Data is written to the direction of the shared
+    /// memory ring that the other end pops from.
+    pub async fn do_push(&mut self, buf: DemiBuffer, yielder: &Yielder) -> Result<(), Fail> {
+        self.do_push_until(buf, None, yielder).await
+    }
+
+    /// Pushes the buffers of [chain] to the shared memory ring, one after the other, so that the other end pops their
+    /// concatenation. The buffers are written as they are, without being merged into a single buffer first. On a
+    /// framed queue, each buffer is pushed as a message of its own.
+    pub async fn do_push_chain(&mut self, chain: DemiBufferChain, yielder: &Yielder) -> Result<(), Fail> {
+        for buf in chain {
+            self.do_push_until(buf, None, yielder).await?;
+        }
+        Ok(())
+    }
+
+    /// Same as [do_push], but gives up with `ETIMEDOUT` if the shared memory ring does not take any data from [buf]
+    /// before [deadline] expires. Once some data has been written, the remainder is always pushed, so that the other
+    /// end never observes a truncated buffer.
+    pub async fn do_push_until(
+        &mut self,
+        mut buf: DemiBuffer,
+        deadline: Option<(SharedTimer, Instant)>,
+        yielder: &Yielder,
+    ) -> Result<(), Fail> {
+        let framed: bool = self.ring.is_framed();
+        let mut written: bool = false;
         loop {
-            match self.ring.try_push(&buf) {
+            // Framed queues push the whole buffer as a single message, or nothing at all.
+            let result: Result<usize, Fail> = if framed {
+                self.ring.push_message(&buf).map(|()| buf.len())
+            } else {
+                self.ring.try_push(&buf)
+            };
+            match result {
                 Ok(len) if len == buf.len() => {
                     trace!("data written ({:?}/{:?} bytes)", buf.len(), buf.len());
                     return Ok(());
                 },
                 Ok(len) if len < buf.len() => {
                     buf.adjust(len).expect("should be able to split remaining bytes");
+                    written = written || len > 0;
                     continue;
                 },
                 Ok(len) => unreachable!(
@@ -222,7 +432,15 @@ impl SharedCatmemQueue {
                     len
                 ),
                 Err(e) if DemiRuntime::should_retry(e.errno) => {
-                    // Operation not completed. Check if it was cancelled.
+                    // Operation not completed. Check if it timed out.
+                    if let Some((timer, expiry)) = deadline.as_ref() {
+                        if !written && timer.now() >= *expiry {
+                            let cause: String = format!("push timed out ({:?} bytes pending)", buf.len());
+                            error!("do_push_until(): {}", cause);
+                            return Err(Fail::new(libc::ETIMEDOUT, &cause));
+                        }
+                    }
+                    // Check if it was cancelled.
                     match yielder.yield_once().await {
                         Ok(()) => continue,
                         Err(cause) => return Err(cause),
@@ -276,6 +494,24 @@ impl SharedCatmemQueue {
         Ok(task_handle.get_task_id().into())
     }
 
+    /// Grows the shared memory ring of this queue to `new_size` bytes. The ring must be empty.
+    pub fn resize(&mut self, new_size: usize) -> Result<(), Fail> {
+        self.ring.resize(new_size)
+    }
+
+    /// Returns how long the oldest data that was pushed to this queue, but not popped from its ring yet, has been
+    /// waiting.
+    pub fn oldest_unread_age(&self) -> Option<Duration> {
+        self.ring.oldest_unread_age()
+    }
+
+    /// Checks which operations would make progress right away on this queue.
+    pub fn readiness(&self) -> Readiness {
+        let mut readiness: Readiness = self.ring.readiness();
+        readiness.readable = readiness.readable || (self.leftover.is_some() && !readiness.hangup);
+        readiness
+    }
+
     /// Adds a new operation to the list of pending operations on this queue.
     pub fn add_pending_op(&mut self, handle: &TaskHandle, yielder_handle: &YielderHandle) {
         self.pending_ops.insert(handle.clone(), yielder_handle.clone());
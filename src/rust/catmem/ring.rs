@@ -7,7 +7,10 @@
 
 use crate::{
     collections::{
-        concurrent_ring::ConcurrentRingBuffer,
+        concurrent_ring::{
+            ConcurrentRingBuffer,
+            PeekedMessage,
+        },
         shared_ring::SharedRingBuffer,
     },
     runtime::{
@@ -16,9 +19,14 @@ use crate::{
             operation::RingControlOperation,
             state::RingStateMachine,
         },
+        Readiness,
     },
 };
-use ::std::ptr::copy;
+use ::std::{
+    mem,
+    ptr::copy,
+    time::Duration,
+};
 
 //======================================================================================================================
 // Constants
@@ -33,6 +41,24 @@ const EOF_MESSAGE_HEADER: [u8; HEADER_SIZE] = [0xD, 0xE, 0xA, 0xD];
 /// Header for regular messages.
 const REGULAR_MESSAGE_HEADER: [u8; HEADER_SIZE] = [0xB, 0xE, 0xE, 0xF];
 
+/// Header for resize messages. A resize message is the last message sent in a direction before moving over to a new,
+/// larger, pair of shared memory regions.
+const RESIZE_MESSAGE_HEADER: [u8; HEADER_SIZE] = [0x5, 0x1, 0x2, 0xE];
+
+/// Header for timestamp messages. A timestamp message carries the time at which the data that follows it was pushed, so
+/// that the peer can tell how long data has been waiting in the ring.
+const TIMESTAMP_MESSAGE_HEADER: [u8; HEADER_SIZE] = [0x7, 0x1, 0x3, 0xE];
+
+/// Size of the time carried by timestamp messages, in bytes.
+const TIMESTAMP_SIZE: usize = 8;
+
+/// Minimum time between two timestamp messages. Data that is pushed in between shares the timestamp of the data before
+/// it, so this bounds how much the age of unread data may be overestimated.
+const TIMESTAMP_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Size of the length prefix of messages in framed rings.
+const LENGTH_PREFIX_SIZE: usize = 4;
+
 /// Capacity of the ring buffer, in bytes.
 /// This does not correspond to the effective number of bytes that may be stored in the ring buffer due to layout and
 /// padding. Still, this is intentionally set so as the effective capacity is large enough to hold 16 KB of data.
@@ -46,11 +72,51 @@ pub const MAX_RETRIES_PUSH_EOF: u32 = 16;
 //======================================================================================================================
 
 /// An endpoint for a unidirectional queue built on a shared ring buffer
+///
+/// A ring may be resized by either endpoint while it is empty, using the following protocol:
+/// 1. The initiator creates a new pair of shared memory regions of the requested size, sends a resize message through
+///    the old sending buffer, and sends through the new one from then on. It keeps receiving from the old receiving
+///    buffer until it gets a resize message back.
+/// 2. When the peer receives the resize message, it opens the new pair of shared memory regions and receives from the
+///    new one from then on. It then sends a resize message back through its old sending buffer, and sends through the
+///    new one from then on.
+///
+/// Since each direction switches over right after a resize message, no data is lost or reordered, even if the peer
+/// sends data while the resize is in progress. Only one resize may be in progress at a time: the new shared memory
+/// regions are named after the number of resizes so far, so concurrent resizes from both endpoints collide and all but
+/// one of them fail.
+///
+/// By default, a ring carries a byte stream. A framed ring instead carries discrete messages, each of which is pushed
+/// with [Ring::push_message] along with a length prefix and popped whole with [Ring::pop_message].
 pub struct Ring {
+    /// Name of the ring.
+    name: String,
+    /// Indicates whether this endpoint created the ring or opened it.
+    is_creator: bool,
+    /// Number of resizes so far.
+    generation: u32,
+    /// Size of the shared memory regions, in bytes.
+    size: usize,
     /// Underlying buffer used for sending data.
     push_buf: SharedRingBuffer<ConcurrentRingBuffer>,
     /// Underlying buffer used for receiving data.
     pop_buf: SharedRingBuffer<ConcurrentRingBuffer>,
+    /// Buffer used for sending data once the resize message of an ongoing resize is sent back to the peer.
+    next_push_buf: Option<SharedRingBuffer<ConcurrentRingBuffer>>,
+    /// Buffer used for receiving data once the peer acknowledges an ongoing resize.
+    next_pop_buf: Option<SharedRingBuffer<ConcurrentRingBuffer>>,
+    /// Message that was peeked at but not popped yet, and whether it is an EoF message.
+    peeked: Option<(PeekedMessage, bool)>,
+    /// Indicates whether the ring carries length-prefixed messages instead of a byte stream.
+    framed: bool,
+    /// Time at which we last pushed a timestamp message, if ever.
+    last_push_timestamp: Option<Duration>,
+    /// Time carried by the last timestamp message that we popped, if any. Data that is not popped yet was pushed after
+    /// that time.
+    last_pop_timestamp: Option<Duration>,
+    /// Buffer that framed messages are popped into before their length prefix is checked. It is reused across pops, so
+    /// that popping a message does not require a buffer as large as the ring.
+    pop_scratch: Vec<u8>,
     /// Indicates whether the ring is open or closed.
     state_machine: RingStateMachine,
 }
@@ -67,8 +133,19 @@ impl Ring {
             return Err(Fail::new(libc::EINVAL, "name of shared memory region cannot be empty"));
         }
         Ok(Self {
+            name: name.to_string(),
+            is_creator: true,
+            generation: 0,
+            size: RING_BUFFER_CAPACITY,
             push_buf: SharedRingBuffer::create(&format!("{}:tx", name), RING_BUFFER_CAPACITY)?,
             pop_buf: SharedRingBuffer::create(&format!("{}:rx", name), RING_BUFFER_CAPACITY)?,
+            next_push_buf: None,
+            next_pop_buf: None,
+            peeked: None,
+            framed: false,
+            last_push_timestamp: None,
+            last_pop_timestamp: None,
+            pop_scratch: Vec::new(),
             state_machine: RingStateMachine::new(),
         })
     }
@@ -80,21 +157,185 @@ impl Ring {
             return Err(Fail::new(libc::EINVAL, "name of shared memory region cannot be empty"));
         }
         Ok(Self {
+            name: name.to_string(),
+            is_creator: false,
+            generation: 0,
+            size: RING_BUFFER_CAPACITY,
             push_buf: SharedRingBuffer::open(&format!("{}:rx", name), RING_BUFFER_CAPACITY)?,
             pop_buf: SharedRingBuffer::open(&format!("{}:tx", name), RING_BUFFER_CAPACITY)?,
+            next_push_buf: None,
+            next_pop_buf: None,
+            peeked: None,
+            framed: false,
+            last_push_timestamp: None,
+            last_pop_timestamp: None,
+            pop_scratch: Vec::new(),
             state_machine: RingStateMachine::new(),
         })
     }
 
+    /// Creates a new shared memory ring that carries length-prefixed messages. Both endpoints must be framed.
+    pub fn create_framed(name: &str) -> Result<Self, Fail> {
+        let mut ring: Self = Self::create(name)?;
+        ring.framed = true;
+        Ok(ring)
+    }
+
+    /// Opens an existing shared memory ring that carries length-prefixed messages. Both endpoints must be framed.
+    pub fn open_framed(name: &str) -> Result<Self, Fail> {
+        let mut ring: Self = Self::open(name)?;
+        ring.framed = true;
+        Ok(ring)
+    }
+
+    /// Grows the shared memory regions of the ring to `new_size` bytes each. This is only allowed while the ring is
+    /// empty in both directions and no other resize is in progress. The peer switches over to the new regions when it
+    /// next pops from the ring.
+    pub fn resize(&mut self, new_size: usize) -> Result<(), Fail> {
+        self.state_machine.may_push()?;
+
+        // Check arguments.
+        if new_size <= self.size {
+            let cause: String = format!("ring can only grow (size={:?}, new_size={:?})", self.size, new_size);
+            error!("resize(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if self.next_push_buf.is_some() || self.next_pop_buf.is_some() {
+            let cause: String = format!("a resize is already in progress (name={:?})", self.name);
+            error!("resize(): {}", cause);
+            return Err(Fail::new(libc::EBUSY, &cause));
+        }
+        if !self.push_buf.is_empty() || !self.pop_buf.is_empty() {
+            let cause: String = format!("ring is not empty (name={:?})", self.name);
+            error!("resize(): {}", cause);
+            return Err(Fail::new(libc::EBUSY, &cause));
+        }
+
+        // Create the new shared memory regions. This fails if the peer is resizing the ring too.
+        let generation: u32 = self.generation + 1;
+        let (push_name, pop_name): (String, String) = self.region_names(generation);
+        let push_buf: SharedRingBuffer<ConcurrentRingBuffer> = SharedRingBuffer::create(&push_name, new_size)?;
+        let pop_buf: SharedRingBuffer<ConcurrentRingBuffer> = SharedRingBuffer::create(&pop_name, new_size)?;
+
+        // Let the peer know, and send through the new buffer from now on.
+        if let Err(e) = self.push_buf.try_push(&RESIZE_MESSAGE_HEADER) {
+            let cause: String = format!("failed to push resize message (name={:?}, err={:?})", self.name, e);
+            error!("resize(): {}", cause);
+            return Err(Fail::new(libc::EAGAIN, &cause));
+        }
+        self.push_buf = push_buf;
+        self.next_pop_buf = Some(pop_buf);
+        self.generation = generation;
+        self.size = new_size;
+
+        Ok(())
+    }
+
+    /// Checks which operations would make progress right away on the shared memory ring. A pending EoF counts as
+    /// readable, as popping it completes right away.
+    pub fn readiness(&self) -> Readiness {
+        let closed: bool = self.state_machine.may_push().is_err();
+        Readiness {
+            readable: !closed && (self.peeked.is_some() || !self.pop_buf.is_empty()),
+            writable: !closed && !self.push_buf.is_full(),
+            error: false,
+            hangup: closed,
+        }
+    }
+
     /// Try to pop a byte from the shared memory ring. If successful, return the byte and whether the eof flag is set,
     /// otherwise return None for a retry.
     pub fn try_pop(&mut self, buf: &mut [u8]) -> Result<(usize, bool), Fail> {
         self.state_machine.may_pop()?;
+        // A peeked message locks the ring from further pops, until it is popped itself.
+        if self.peeked.is_some() {
+            let cause: String = format!("a peeked message must be popped first (name={:?})", self.name);
+            error!("try_pop(): {}", cause);
+            return Err(Fail::new(libc::EBUSY, &cause));
+        }
+        self.pop_timestamps();
 
         let mut msg: Vec<u8> = vec![0; buf.len() + HEADER_SIZE];
         // Read data from the ring buffer.
-        let msg_len: usize = self.pop_buf.try_pop(&mut msg)? - HEADER_SIZE;
+        let msg_len: usize = match self.pop_buf.try_pop(&mut msg) {
+            Ok(len) => len - HEADER_SIZE,
+            // The message may not fit because it is a timestamp that was pushed in the meantime.
+            Err(e) if e.errno == libc::EINVAL && self.pop_timestamps() => {
+                return Err(Fail::new(libc::EAGAIN, "timestamp was popped"))
+            },
+            Err(e) => return Err(e),
+        };
+        if self.record_timestamp(&msg[..(HEADER_SIZE + msg_len)]) {
+            return Err(Fail::new(libc::EAGAIN, "timestamp was popped"));
+        }
+
+        // Switch over to the new buffers if the peer resized the ring, or acknowledged our resize.
+        if msg_len == 0 && RESIZE_MESSAGE_HEADER == msg[0..HEADER_SIZE] {
+            self.handle_resize()?;
+            return Err(Fail::new(libc::EAGAIN, "ring was resized"));
+        }
+
+        Ok(Self::read_message(&msg, msg_len, buf))
+    }
 
+    /// Try to peek at the next message in the shared memory ring, without removing it. If successful, return the length
+    /// of the message and whether the eof flag is set. The message stays in the ring, and is returned again by further
+    /// peeks, until it is removed with [commit_pop].
+    pub fn try_peek(&mut self, buf: &mut [u8]) -> Result<(usize, bool), Fail> {
+        self.state_machine.may_pop()?;
+
+        let mut msg: Vec<u8> = vec![0; buf.len() + HEADER_SIZE];
+        // Peek at the same message again, if there is one already.
+        if let Some((peeked, _)) = &self.peeked {
+            let msg_len: usize = self.pop_buf.copy_peeked(peeked, &mut msg)? - HEADER_SIZE;
+            return Ok(Self::read_message(&msg, msg_len, buf));
+        }
+        self.pop_timestamps();
+
+        let (peeked, len): (PeekedMessage, usize) = match self.pop_buf.try_peek(&mut msg) {
+            Ok(result) => result,
+            // The message may not fit because it is a timestamp that was pushed in the meantime.
+            Err(e) if e.errno == libc::EINVAL && self.pop_timestamps() => {
+                return Err(Fail::new(libc::EAGAIN, "timestamp was popped"))
+            },
+            Err(e) => return Err(e),
+        };
+        if self.record_timestamp(&msg[..len]) {
+            self.pop_buf.commit_pop(peeked);
+            return Err(Fail::new(libc::EAGAIN, "timestamp was popped"));
+        }
+        let msg_len: usize = len - HEADER_SIZE;
+
+        // Resize messages are not visible to the caller, so remove them right away.
+        if msg_len == 0 && RESIZE_MESSAGE_HEADER == msg[0..HEADER_SIZE] {
+            self.pop_buf.commit_pop(peeked);
+            self.handle_resize()?;
+            return Err(Fail::new(libc::EAGAIN, "ring was resized"));
+        }
+
+        let (len, eof): (usize, bool) = Self::read_message(&msg, msg_len, buf);
+        self.peeked = Some((peeked, eof));
+        Ok((len, eof))
+    }
+
+    /// Removes the message that was last peeked at from the shared memory ring. Returns whether it was an EoF message.
+    pub fn commit_pop(&mut self) -> Result<bool, Fail> {
+        match self.peeked.take() {
+            Some((peeked, eof)) => {
+                self.pop_buf.commit_pop(peeked);
+                Ok(eof)
+            },
+            None => {
+                let cause: String = format!("no message was peeked (name={:?})", self.name);
+                error!("commit_pop(): {}", cause);
+                Err(Fail::new(libc::EINVAL, &cause))
+            },
+        }
+    }
+
+    /// Copies a message read from the shared memory ring into [buf]. Returns the length of the message and whether the
+    /// eof flag is set.
+    fn read_message(msg: &[u8], msg_len: usize, buf: &mut [u8]) -> (usize, bool) {
         // Check how many bytes were read.
         if msg_len > 0 {
             // We read some bytes. This should be a regular message,
@@ -110,14 +351,14 @@ impl Ring {
                 copy(msg_ptr.add(HEADER_SIZE), buf_ptr, msg_len);
             };
 
-            Ok((msg_len, false))
+            (msg_len, false)
         } else {
             // We read no bytes. This should be an EoF message.
 
             // Ensure that the message header is what we expect.
             debug_assert_eq!(EOF_MESSAGE_HEADER, msg[0..HEADER_SIZE]);
 
-            Ok((0, true))
+            (0, true)
         }
     }
 
@@ -125,6 +366,8 @@ impl Ring {
     /// ring, return [false], otherwise, return [true] if successfully enqueued.
     pub fn try_push(&mut self, buf: &[u8]) -> Result<usize, Fail> {
         self.state_machine.may_push()?;
+        self.try_acknowledge_resize();
+        self.push_timestamp(HEADER_SIZE + buf.len());
         // Write the header.
         let mut msg: Vec<u8> = REGULAR_MESSAGE_HEADER.to_vec();
         msg.append(&mut buf.to_vec());
@@ -133,6 +376,111 @@ impl Ring {
         Ok(self.push_buf.try_push(&msg)? - HEADER_SIZE)
     }
 
+    /// Checks if the ring carries length-prefixed messages, rather than a byte stream.
+    pub fn is_framed(&self) -> bool {
+        self.framed
+    }
+
+    /// Returns the size of the largest message that may be pushed with [push_message], in bytes.
+    pub fn max_message_size(&self) -> usize {
+        self.push_buf.max_message_size() - HEADER_SIZE - LENGTH_PREFIX_SIZE
+    }
+
+    /// Try to send [msg] as a single message through a framed shared memory ring. Messages that are larger than the
+    /// ring are rejected, as they could never be pushed.
+    pub fn push_message(&mut self, msg: &[u8]) -> Result<(), Fail> {
+        self.state_machine.may_push()?;
+        self.check_framed("push_message")?;
+        self.try_acknowledge_resize();
+        if msg.len() > self.max_message_size() {
+            let cause: String = format!(
+                "message is larger than the ring (len={:?}, max={:?})",
+                msg.len(),
+                self.max_message_size()
+            );
+            error!("push_message(): {}", cause);
+            return Err(Fail::new(libc::EMSGSIZE, &cause));
+        }
+        self.push_timestamp(HEADER_SIZE + LENGTH_PREFIX_SIZE + msg.len());
+
+        // Write the header and the length prefix.
+        let mut record: Vec<u8> = Vec::with_capacity(HEADER_SIZE + LENGTH_PREFIX_SIZE + msg.len());
+        record.extend_from_slice(&REGULAR_MESSAGE_HEADER);
+        record.extend_from_slice(&(msg.len() as u32).to_le_bytes());
+        record.extend_from_slice(msg);
+
+        // Write the whole record at once, so that the peer never observes a partial message.
+        self.push_buf.try_push(&record)?;
+        Ok(())
+    }
+
+    /// Try to pop exactly one message from a framed shared memory ring. If successful, return the message and whether
+    /// the eof flag is set.
+    pub fn pop_message(&mut self) -> Result<(Vec<u8>, bool), Fail> {
+        self.state_machine.may_pop()?;
+        self.check_framed("pop_message")?;
+        self.pop_timestamps();
+
+        // The ring may have been resized since the last pop, so make sure that the largest message fits.
+        let max_message_size: usize = self.pop_buf.max_message_size();
+        if self.pop_scratch.len() < max_message_size {
+            self.pop_scratch.resize(max_message_size, 0);
+        }
+        let mut scratch: Vec<u8> = mem::take(&mut self.pop_scratch);
+        let result: Result<(Vec<u8>, bool), Fail> = self.parse_message(&mut scratch);
+        self.pop_scratch = scratch;
+        result
+    }
+
+    /// Pops one record from a framed shared memory ring into `scratch`, and parses it into a message.
+    fn parse_message(&mut self, scratch: &mut [u8]) -> Result<(Vec<u8>, bool), Fail> {
+        let record_len: usize = self.pop_buf.try_pop(scratch)?;
+        let record: &[u8] = &scratch[..record_len];
+        if self.record_timestamp(record) {
+            return Err(Fail::new(libc::EAGAIN, "timestamp was popped"));
+        }
+
+        // Switch over to the new buffers if the peer resized the ring, or acknowledged our resize.
+        if record == RESIZE_MESSAGE_HEADER {
+            self.handle_resize()?;
+            return Err(Fail::new(libc::EAGAIN, "ring was resized"));
+        }
+        if record == EOF_MESSAGE_HEADER {
+            return Ok((Vec::new(), true));
+        }
+
+        // Ensure that the length prefix matches the message.
+        debug_assert_eq!(REGULAR_MESSAGE_HEADER, record[0..HEADER_SIZE]);
+        let msg_len: usize = match record.get(HEADER_SIZE..(HEADER_SIZE + LENGTH_PREFIX_SIZE)) {
+            Some(prefix) => u32::from_le_bytes(prefix.try_into().expect("prefix should be 4 bytes long")) as usize,
+            None => 0,
+        };
+        if record_len != HEADER_SIZE + LENGTH_PREFIX_SIZE + msg_len {
+            let cause: String = format!("malformed message (name={:?}, len={:?})", self.name, record_len);
+            error!("pop_message(): {}", cause);
+            return Err(Fail::new(libc::EBADMSG, &cause));
+        }
+
+        Ok((record[(HEADER_SIZE + LENGTH_PREFIX_SIZE)..].to_vec(), false))
+    }
+
+    /// Returns how long the oldest data that was pushed to us, but not popped yet, has been waiting in the ring. This is
+    /// exact up to [TIMESTAMP_INTERVAL], and it may only be overestimated. Returns `None` if there is no such data, or
+    /// if it was pushed without a timestamp.
+    pub fn oldest_unread_age(&self) -> Option<Duration> {
+        if self.peeked.is_none() && self.pop_buf.is_empty() {
+            return None;
+        }
+
+        // A timestamp that is next in line tells exactly when the data that follows it was pushed. Otherwise, the data
+        // was pushed after the last timestamp that we popped.
+        let pushed_at: Duration = match self.peek_timestamp() {
+            Some(timestamp) => timestamp,
+            None => self.last_pop_timestamp?,
+        };
+        Some(monotonic_now().saturating_sub(pushed_at))
+    }
+
     /// Closes the target ring.
     pub fn close(&mut self) -> Result<(), Fail> {
         // Attempt to push EoF.
@@ -183,4 +531,384 @@ impl Ring {
     pub fn abort(&mut self) {
         self.state_machine.abort();
     }
+
+    /// Checks if the ring is framed, before calling message-oriented function [fn_name].
+    fn check_framed(&self, fn_name: &str) -> Result<(), Fail> {
+        if !self.framed {
+            let cause: String = format!("ring is not framed (name={:?})", self.name);
+            error!("{}(): {}", fn_name, cause);
+            return Err(Fail::new(libc::ENOTSUP, &cause));
+        }
+        Ok(())
+    }
+
+    /// Pushes a timestamp message ahead of a message of `len` bytes, unless one was pushed recently. The timestamp is only
+    /// pushed if the message fits in the ring along with it, so that it is never left behind on its own.
+    fn push_timestamp(&mut self, len: usize) {
+        let now: Duration = monotonic_now();
+        if let Some(last) = self.last_push_timestamp {
+            if now.saturating_sub(last) < TIMESTAMP_INTERVAL {
+                return;
+            }
+        }
+        let needed: usize =
+            ConcurrentRingBuffer::footprint(HEADER_SIZE + TIMESTAMP_SIZE) + ConcurrentRingBuffer::footprint(len);
+        if self.push_buf.remaining_capacity() < needed {
+            return;
+        }
+
+        let mut msg: Vec<u8> = TIMESTAMP_MESSAGE_HEADER.to_vec();
+        msg.extend_from_slice(&(now.as_nanos() as u64).to_le_bytes());
+        if self.push_buf.try_push(&msg).is_ok() {
+            self.last_push_timestamp = Some(now);
+        }
+    }
+
+    /// Pops the timestamp messages that are next in line in the ring, if any, and records the last time that they carry.
+    /// Returns whether any timestamp message was popped.
+    fn pop_timestamps(&mut self) -> bool {
+        let mut popped: bool = false;
+        let mut msg: [u8; HEADER_SIZE + TIMESTAMP_SIZE] = [0; HEADER_SIZE + TIMESTAMP_SIZE];
+        while let Ok((peeked, len)) = self.pop_buf.try_peek(&mut msg) {
+            if !self.record_timestamp(&msg[..len]) {
+                self.pop_buf.abort_peek(peeked);
+                break;
+            }
+            self.pop_buf.commit_pop(peeked);
+            popped = true;
+        }
+        popped
+    }
+
+    /// Records the time carried by [msg], if it is a timestamp message. Returns whether it is one.
+    fn record_timestamp(&mut self, msg: &[u8]) -> bool {
+        match Self::parse_timestamp(msg) {
+            Some(timestamp) => {
+                self.last_pop_timestamp = Some(timestamp);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Gets the time carried by the timestamp message that is next in line in the ring, if any, without popping it.
+    fn peek_timestamp(&self) -> Option<Duration> {
+        // A peeked message is next in line, and it is no timestamp.
+        if self.peeked.is_some() {
+            return None;
+        }
+        let mut msg: [u8; HEADER_SIZE + TIMESTAMP_SIZE] = [0; HEADER_SIZE + TIMESTAMP_SIZE];
+        let (peeked, len): (PeekedMessage, usize) = self.pop_buf.try_peek(&mut msg).ok()?;
+        self.pop_buf.abort_peek(peeked);
+        Self::parse_timestamp(&msg[..len])
+    }
+
+    /// Parses the time carried by timestamp message [msg]. Returns `None` if [msg] is not a timestamp message.
+    fn parse_timestamp(msg: &[u8]) -> Option<Duration> {
+        if msg.len() != HEADER_SIZE + TIMESTAMP_SIZE || msg[0..HEADER_SIZE] != TIMESTAMP_MESSAGE_HEADER {
+            return None;
+        }
+        let nanos: [u8; TIMESTAMP_SIZE] = msg[HEADER_SIZE..].try_into().expect("timestamp should be 8 bytes long");
+        Some(Duration::from_nanos(u64::from_le_bytes(nanos)))
+    }
+
+    /// Handles a resize message received from the peer.
+    fn handle_resize(&mut self) -> Result<(), Fail> {
+        // The peer acknowledged our resize, so it sends through the new buffer from now on.
+        if let Some(pop_buf) = self.next_pop_buf.take() {
+            self.pop_buf = pop_buf;
+            return Ok(());
+        }
+
+        // The peer resized the ring. It created the new shared memory regions before letting us know.
+        let generation: u32 = self.generation + 1;
+        let (push_name, pop_name): (String, String) = self.region_names(generation);
+        let push_buf: SharedRingBuffer<ConcurrentRingBuffer> = SharedRingBuffer::open_whole(&push_name)?;
+        let pop_buf: SharedRingBuffer<ConcurrentRingBuffer> = SharedRingBuffer::open_whole(&pop_name)?;
+        self.size = push_buf.region_size();
+        self.pop_buf = pop_buf;
+        self.next_push_buf = Some(push_buf);
+        self.generation = generation;
+        self.try_acknowledge_resize();
+
+        Ok(())
+    }
+
+    /// Sends back the resize message of an ongoing resize, if the old sending buffer has room for it, and sends through
+    /// the new buffer from then on. Until then, data keeps going through the old buffer, which the peer still reads.
+    fn try_acknowledge_resize(&mut self) {
+        if self.next_push_buf.is_some() && self.push_buf.try_push(&RESIZE_MESSAGE_HEADER).is_ok() {
+            self.push_buf = self.next_push_buf.take().expect("a resize should be in progress");
+        }
+    }
+
+    /// Builds the names of the shared memory regions used for sending and receiving data after `generation` resizes.
+    fn region_names(&self, generation: u32) -> (String, String) {
+        let tx: String = format!("{}:tx.{}", self.name, generation);
+        let rx: String = format!("{}:rx.{}", self.name, generation);
+        if self.is_creator {
+            (tx, rx)
+        } else {
+            (rx, tx)
+        }
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Reads the monotonic clock of the system. Unlike an [std::time::Instant], a reading may be handed over to another
+/// process on the same host, which is how both ends of a ring agree on when data was pushed.
+fn monotonic_now() -> Duration {
+    let mut ts: libc::timespec = unsafe { mem::zeroed() };
+    // Safety: The clock is always available on Linux, and it writes to a valid timespec.
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Ring,
+        RING_BUFFER_CAPACITY,
+    };
+    use crate::runtime::Readiness;
+    use ::anyhow::Result;
+    use ::std::{
+        ffi::CString,
+        process,
+        thread,
+        time::Duration,
+    };
+
+    /// Size of the ring after resizing it.
+    const NEW_SIZE: usize = 4 * RING_BUFFER_CAPACITY;
+
+    /// Size of messages of which only one fits in a ring that was not resized.
+    const MESSAGE_SIZE: usize = RING_BUFFER_CAPACITY / 2;
+
+    /// Number of messages that are sent at once through a resized ring.
+    const NUM_MESSAGES: usize = 3;
+
+    /// Unlinks the shared memory regions of a ring when dropped, including those of resizes, so that a failed test does
+    /// not leave them behind.
+    struct RegionGuard(String);
+
+    impl Drop for RegionGuard {
+        fn drop(&mut self) {
+            for suffix in [":tx", ":rx", ":tx.1", ":rx.1", ":tx.2", ":rx.2"] {
+                let name: CString =
+                    CString::new(format!("demikernel-{}{}", self.0, suffix)).expect("name should be valid");
+                // Regions that were already unlinked by their creator are not there anymore, which is fine.
+                unsafe { libc::shm_unlink(name.as_ptr()) };
+            }
+        }
+    }
+
+    // Pops a message, going through resize messages on the way.
+    fn pop(ring: &mut Ring, buf: &mut [u8]) -> Result<usize> {
+        loop {
+            match ring.try_pop(buf) {
+                Ok((len, eof)) => {
+                    crate::ensure_eq!(eof, false);
+                    return Ok(len);
+                },
+                Err(e) if e.errno == libc::EAGAIN => continue,
+                Err(e) => anyhow::bail!("pop should succeed: {:?}", e),
+            }
+        }
+    }
+
+    /// Tests if we succeed to resize an empty ring and then transfer data at the new capacity in both directions.
+    #[test]
+    fn resize_empty_ring() -> Result<()> {
+        // Use a name of our own, so that regions left behind by another run do not get in the way.
+        let guard: RegionGuard = RegionGuard(format!("catmem-test-ring-resize-{}", process::id()));
+        let mut creator: Ring = Ring::create(&guard.0)?;
+        let mut opener: Ring = Ring::open(&guard.0)?;
+        let message: Vec<u8> = (0..MESSAGE_SIZE).map(|i| (i & 255) as u8).collect();
+        let mut buf: Vec<u8> = vec![0; MESSAGE_SIZE];
+
+        // Only one message fits in the ring at first.
+        crate::ensure_eq!(creator.try_push(&message)?, MESSAGE_SIZE);
+        crate::ensure_eq!(creator.try_push(&message).is_err(), true);
+        crate::ensure_eq!(pop(&mut opener, &mut buf)?, MESSAGE_SIZE);
+
+        // Shrinking is not allowed.
+        crate::ensure_eq!(creator.resize(RING_BUFFER_CAPACITY).is_err(), true);
+
+        // Resize the ring. A second resize has to wait for the first one to complete.
+        creator.resize(NEW_SIZE)?;
+        crate::ensure_eq!(creator.resize(2 * NEW_SIZE).is_err(), true);
+
+        // Send data from the creator to the opener, which switches over to the new ring on the way.
+        for _ in 0..NUM_MESSAGES {
+            crate::ensure_eq!(creator.try_push(&message)?, MESSAGE_SIZE);
+        }
+        for _ in 0..NUM_MESSAGES {
+            buf.fill(0);
+            crate::ensure_eq!(pop(&mut opener, &mut buf)?, MESSAGE_SIZE);
+            crate::ensure_eq!(buf, message);
+        }
+
+        // Send data from the opener to the creator, which switches over to the new ring on the way.
+        for _ in 0..NUM_MESSAGES {
+            crate::ensure_eq!(opener.try_push(&message)?, MESSAGE_SIZE);
+        }
+        for _ in 0..NUM_MESSAGES {
+            buf.fill(0);
+            crate::ensure_eq!(pop(&mut creator, &mut buf)?, MESSAGE_SIZE);
+            crate::ensure_eq!(buf, message);
+        }
+
+        // The resize is now complete, so the ring may be resized again.
+        creator.resize(2 * NEW_SIZE)?;
+
+        Ok(())
+    }
+
+    /// Tests if a peeked message stays in the ring until it is committed, so that it can be peeked again after a failure.
+    #[test]
+    fn peek_then_commit() -> Result<()> {
+        let guard: RegionGuard = RegionGuard(format!("catmem-test-ring-peek-{}", process::id()));
+        let mut creator: Ring = Ring::create(&guard.0)?;
+        let mut opener: Ring = Ring::open(&guard.0)?;
+        let mut buf: Vec<u8> = vec![0; 16];
+
+        // Nothing to peek at or commit yet.
+        crate::ensure_eq!(opener.try_peek(&mut buf).unwrap_err().errno, libc::EAGAIN);
+        crate::ensure_eq!(opener.commit_pop().is_err(), true);
+
+        creator.try_push(b"first")?;
+        creator.try_push(b"second")?;
+
+        // Peek at the first message, and fail to process it.
+        crate::ensure_eq!(opener.try_peek(&mut buf)?, (5, false));
+        crate::ensure_eq!(&buf[..5], b"first");
+        buf.fill(0);
+
+        // The message is still there, and it blocks further pops.
+        crate::ensure_eq!(opener.try_peek(&mut buf)?, (5, false));
+        crate::ensure_eq!(&buf[..5], b"first");
+        crate::ensure_eq!(opener.try_pop(&mut buf).unwrap_err().errno, libc::EBUSY);
+
+        // Commit the message, and move on to the next one.
+        crate::ensure_eq!(opener.commit_pop()?, false);
+        crate::ensure_eq!(opener.try_peek(&mut buf)?, (6, false));
+        crate::ensure_eq!(&buf[..6], b"second");
+        crate::ensure_eq!(opener.commit_pop()?, false);
+
+        // The ring is now empty.
+        crate::ensure_eq!(opener.try_pop(&mut buf).unwrap_err().errno, libc::EAGAIN);
+
+        Ok(())
+    }
+
+    /// Tests if each pop from a framed ring returns exactly one message, with its boundaries intact.
+    #[test]
+    fn framed_ring_preserves_boundaries() -> Result<()> {
+        let guard: RegionGuard = RegionGuard(format!("catmem-test-ring-framed-{}", process::id()));
+        let mut creator: Ring = Ring::create_framed(&guard.0)?;
+        let mut opener: Ring = Ring::open_framed(&guard.0)?;
+        let messages: Vec<Vec<u8>> = vec![b"a".to_vec(), vec![0xab; 100], vec![0xcd; 1000], b"last".to_vec()];
+
+        for message in &messages {
+            creator.push_message(message)?;
+        }
+        for message in &messages {
+            crate::ensure_eq!(opener.pop_message()?, (message.clone(), false));
+        }
+        crate::ensure_eq!(opener.pop_message().unwrap_err().errno, libc::EAGAIN);
+
+        // Messages that are larger than the ring are rejected.
+        let message: Vec<u8> = vec![0; creator.max_message_size() + 1];
+        crate::ensure_eq!(creator.push_message(&message).unwrap_err().errno, libc::EMSGSIZE);
+
+        // The largest message still fits.
+        creator.push_message(&message[1..])?;
+        crate::ensure_eq!(opener.pop_message()?.0.len(), message.len() - 1);
+
+        // An end of file is popped as such.
+        creator.close()?;
+        crate::ensure_eq!(opener.pop_message()?, (Vec::new(), true));
+
+        Ok(())
+    }
+
+    /// Tests if rings carry a byte stream unless they are framed.
+    #[test]
+    fn stream_ring_is_not_framed() -> Result<()> {
+        let guard: RegionGuard = RegionGuard(format!("catmem-test-ring-stream-{}", process::id()));
+        let mut creator: Ring = Ring::create(&guard.0)?;
+        crate::ensure_eq!(creator.push_message(b"data").unwrap_err().errno, libc::ENOTSUP);
+        crate::ensure_eq!(creator.pop_message().unwrap_err().errno, libc::ENOTSUP);
+
+        Ok(())
+    }
+
+    /// Tests if a ring is writable right away, and only becomes readable once data arrives.
+    #[test]
+    fn readiness_follows_data() -> Result<()> {
+        let guard: RegionGuard = RegionGuard(format!("catmem-test-ring-readiness-{}", process::id()));
+        let mut creator: Ring = Ring::create(&guard.0)?;
+        let mut opener: Ring = Ring::open(&guard.0)?;
+        let mut buf: Vec<u8> = vec![0; 16];
+
+        // Nothing to pop yet, but there is room to push.
+        let readiness: Readiness = opener.readiness();
+        crate::ensure_eq!(readiness.writable, true);
+        crate::ensure_eq!(readiness.readable, false);
+        crate::ensure_eq!(readiness.hangup, false);
+
+        // Data arrives.
+        creator.try_push(b"data")?;
+        crate::ensure_eq!(opener.readiness().readable, true);
+
+        // Pop it, and the ring is drained again.
+        crate::ensure_eq!(pop(&mut opener, &mut buf)?, 4);
+        crate::ensure_eq!(opener.readiness().readable, false);
+
+        Ok(())
+    }
+
+    /// Tests if the age of the oldest unread data is reported while there is some, both before and after its timestamp
+    /// is popped.
+    #[test]
+    fn oldest_unread_age_tracks_data() -> Result<()> {
+        let guard: RegionGuard = RegionGuard(format!("catmem-test-ring-age-{}", process::id()));
+        let mut creator: Ring = Ring::create(&guard.0)?;
+        let mut opener: Ring = Ring::open(&guard.0)?;
+        let mut buf: Vec<u8> = vec![0; 16];
+        let wait: Duration = Duration::from_millis(20);
+
+        // Nothing to report yet.
+        crate::ensure_eq!(opener.oldest_unread_age(), None);
+
+        // Data waits in the ring.
+        creator.try_push(b"first")?;
+        creator.try_push(b"second")?;
+        thread::sleep(wait);
+        let age: Duration = opener
+            .oldest_unread_age()
+            .ok_or(anyhow::anyhow!("age should be known"))?;
+        crate::ensure_eq!(age >= wait && age < Duration::from_secs(10), true);
+
+        // Data that is still waiting after a pop shares the timestamp of the data before it.
+        crate::ensure_eq!(pop(&mut opener, &mut buf)?, 5);
+        let age: Duration = opener
+            .oldest_unread_age()
+            .ok_or(anyhow::anyhow!("age should be known"))?;
+        crate::ensure_eq!(age >= wait && age < Duration::from_secs(10), true);
+
+        // Nothing to report once the ring is drained.
+        crate::ensure_eq!(pop(&mut opener, &mut buf)?, 6);
+        crate::ensure_eq!(opener.oldest_unread_age(), None);
+
+        Ok(())
+    }
 }
@@ -30,8 +30,10 @@ use crate::{
         },
         queue::{
             downcast_queue,
+            IoQueue,
             Operation,
             OperationResult,
+            QueueInfo,
         },
         scheduler::{
             TaskHandle,
@@ -123,7 +125,7 @@ impl SharedCatnapLibOS {
 
         // Create underlying queue.
         let queue: SharedCatnapQueue = SharedCatnapQueue::new(domain, typ, self.transport.clone())?;
-        let qd: QDesc = self.runtime.alloc_queue(queue);
+        let qd: QDesc = self.runtime.alloc_queue(queue)?;
         Ok(qd)
     }
 
@@ -215,7 +217,13 @@ impl SharedCatnapLibOS {
                 let addr: SocketAddr = new_queue
                     .remote()
                     .expect("An accepted socket must have a remote address");
-                let new_qd: QDesc = self.runtime.alloc_queue(new_queue);
+                let new_qd: QDesc = match self.runtime.alloc_queue(new_queue) {
+                    Ok(new_qd) => new_qd,
+                    Err(e) => {
+                        warn!("accept() listening_qd={:?}: {:?}", qd, &e);
+                        return (qd, OperationResult::Failed(e));
+                    },
+                };
                 // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
                 (
                     qd,
@@ -491,6 +499,24 @@ impl SharedCatnapLibOS {
         }
     }
 
+    /// Lists all I/O queues that are currently open in Catnap. This function is read-only and intended for diagnostics.
+    pub fn list_queues(&self) -> Vec<QueueInfo> {
+        let mut queues: Vec<QueueInfo> = Vec::new();
+        for qd in self.runtime.get_qtable().get_qds() {
+            if let Ok(queue) = self.get_shared_queue(&qd) {
+                // FIXME: add IPv6 support; https://github.com/microsoft/demikernel/issues/935
+                queues.push(QueueInfo {
+                    qd,
+                    qtype: queue.get_qtype(),
+                    local: queue.local().and_then(|addr| unwrap_socketaddr(addr).ok()),
+                    remote: queue.remote().and_then(|addr| unwrap_socketaddr(addr).ok()),
+                    state: Some(queue.get_state()),
+                });
+            }
+        }
+        queues
+    }
+
     /// This function gets a shared queue reference out of the I/O queue table. The type if a ref counted pointer to the
     /// queue itself.
     fn get_shared_queue(&self, qd: &QDesc) -> Result<SharedCatnapQueue, Fail> {
@@ -16,7 +16,10 @@ use crate::{
         memory::DemiBuffer,
         network::socket::{
             operation::SocketOp,
-            state::SocketStateMachine,
+            state::{
+                SocketState,
+                SocketStateMachine,
+            },
         },
         queue::{
             IoQueue,
@@ -342,6 +345,11 @@ impl SharedCatnapQueue {
     pub fn remote(&self) -> Option<SocketAddr> {
         self.remote
     }
+
+    /// Gets the current connection state of the target queue.
+    pub fn get_state(&self) -> SocketState {
+        self.state_machine.get_state()
+    }
 }
 
 //======================================================================================================================
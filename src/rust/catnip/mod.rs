@@ -15,7 +15,10 @@ use crate::{
     runtime::{
         fail::Fail,
         libdpdk::load_mlx_driver,
-        memory::MemoryRuntime,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+        },
         network::{
             config::{
                 ArpConfig,
@@ -122,6 +125,16 @@ impl CatnipLibOS {
         }
     }
 
+    /// Create a push request that sends the data in `sga` as urgent data on the TCP connection represented by `qd`.
+    pub fn push_urgent(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        trace!("push_urgent(): qd={:?}", qd);
+        let buf: DemiBuffer = self.transport.clone_sgarray(sga)?;
+        if buf.len() == 0 {
+            return Err(Fail::new(libc::EINVAL, "zero-length buffer"));
+        }
+        self.do_push_urgent(qd, buf)
+    }
+
     pub fn pushto(&mut self, qd: QDesc, sga: &demi_sgarray_t, to: SocketAddr) -> Result<QToken, Fail> {
         trace!("pushto2(): qd={:?}", qd);
         match self.transport.clone_sgarray(sga) {
@@ -154,7 +154,12 @@ impl SharedDPDKRuntime {
             Some(tcp_checksum_offload),
         );
 
-        let udp_config = UdpConfig::new(Some(udp_checksum_offload), Some(udp_checksum_offload));
+        let udp_config = UdpConfig::new(
+            Some(udp_checksum_offload),
+            Some(udp_checksum_offload),
+            None,
+            Some(mtu as usize),
+        );
 
         Self(SharedObject::<DPDKRuntime>::new(DPDKRuntime {
             mm,
@@ -99,6 +99,12 @@ impl<T> AsyncQueue<T> {
         self.queue.pop_front()
     }
 
+    /// Remove the first item in the queue that matches a predicate.
+    pub fn remove_first<F: Fn(&T) -> bool>(&mut self, predicate: F) -> Option<T> {
+        let index: usize = self.queue.iter().position(|item| predicate(item))?;
+        self.queue.remove(index)
+    }
+
     /// Get the length of the queue.
     pub fn len(&self) -> usize {
         self.queue.len()
@@ -78,6 +78,16 @@ pub struct ConcurrentRingBuffer {
     is_managed: bool,
 }
 
+/// A message that was peeked at but not popped yet. The header of the message is zeroed, so it stays locked from other
+/// readers until it is popped with [ConcurrentRingBuffer::commit_pop].
+#[derive(Debug)]
+pub struct PeekedMessage {
+    /// Offset of the header of the message.
+    offset: usize,
+    /// Length of the payload of the message.
+    len: usize,
+}
+
 //======================================================================================================================
 // Associated Functions
 //======================================================================================================================
@@ -123,7 +133,12 @@ impl ConcurrentRingBuffer {
         self.buffer.capacity()
     }
 
-    #[allow(unused)]
+    /// Returns the size of the largest message that fits in the target ring buffer when it is empty, in bytes.
+    pub fn max_message_size(&self) -> usize {
+        // One header goes with the message, and another one is always left free to tell a full ring from an empty one.
+        (self.capacity() - 2 * HEADER_SIZE).min(u16::MAX as usize)
+    }
+
     pub fn remaining_capacity(&self) -> usize {
         #[cfg(feature = "profiler")]
         timer!("collections::concurrent_ring::remaining_capacity");
@@ -220,13 +235,9 @@ impl ConcurrentRingBuffer {
             },
         };
 
-        // Pop first part of buffer.
+        // Pop first part of buffer. Only the message is copied, regardless of how large the buffer is.
         let first_offset: usize = pop_offset + HEADER_SIZE;
-        let first_len: usize = if pop_offset + pop_len + HEADER_SIZE > self.capacity() {
-            self.capacity() - first_offset
-        } else {
-            len
-        };
+        let first_len: usize = pop_len.min(self.capacity() - first_offset);
         let buf_ptr: *mut u8 = buf.as_mut_ptr();
         let ring_ptr: *const u8 = unsafe { self.buffer.get().as_ptr() };
         // Copy the data into the ring buffer.
@@ -234,10 +245,10 @@ impl ConcurrentRingBuffer {
             copy(ring_ptr.add(first_offset), buf_ptr, first_len);
         }
         // If there is remaining data in the buffer, wrap around.
-        if len > first_len {
+        if pop_len > first_len {
             // Copy the data into the ring buffer.
             unsafe {
-                copy(ring_ptr, buf_ptr.add(first_len), len - first_len);
+                copy(ring_ptr, buf_ptr.add(first_len), pop_len - first_len);
             }
         }
 
@@ -252,6 +263,81 @@ impl ConcurrentRingBuffer {
         Ok(pop_len)
     }
 
+    /// Attempts to lock the next message in the ring buffer and copy it into [buf], without removing it. Returns a
+    /// handle to the message along with its length. The message is removed only once [commit_pop] is called on the
+    /// handle. This function does not block.
+    pub fn try_peek(&self, buf: &mut [u8]) -> Result<(PeekedMessage, usize), Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("collections::concurrent_ring::try_peek");
+        let len: usize = buf.len();
+        if len == 0 {
+            return Err(Fail::new(libc::EINVAL, "Buffer must be non-zero length"));
+        }
+
+        // Lock the next message, just like a pop would.
+        let pop_offset: usize = peek(self.pop_offset);
+        let pop_len: usize = match self.write_header(pop_offset, 0) {
+            0 => return Err(Fail::new(libc::EAGAIN, "No messages in the ring buffer")),
+            bytes if bytes <= len => bytes,
+            bytes => {
+                // Buffer is not big enough so put the message back in the queue.
+                let old_len: usize = self.write_header(pop_offset, bytes);
+                debug_assert_eq!(old_len, 0);
+                return Err(Fail::new(libc::EINVAL, "Buffer is too small to hold next message"));
+            },
+        };
+
+        let msg: PeekedMessage = PeekedMessage {
+            offset: pop_offset,
+            len: pop_len,
+        };
+        let len: usize = self.copy_peeked(&msg, buf)?;
+        Ok((msg, len))
+    }
+
+    /// Copies the payload of a message that was peeked at with [try_peek] into [buf] again.
+    pub fn copy_peeked(&self, msg: &PeekedMessage, buf: &mut [u8]) -> Result<usize, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("collections::concurrent_ring::copy_peeked");
+        if buf.len() < msg.len {
+            return Err(Fail::new(libc::EINVAL, "Buffer is too small to hold peeked message"));
+        }
+
+        // Copy first part of the message. If longer than the capacity of the ring, wrap around.
+        let first_offset: usize = msg.offset + HEADER_SIZE;
+        let first_len: usize = msg.len.min(self.capacity() - first_offset);
+        let buf_ptr: *mut u8 = buf.as_mut_ptr();
+        let ring_ptr: *const u8 = unsafe { self.buffer.get().as_ptr() };
+        unsafe {
+            copy(ring_ptr.add(first_offset), buf_ptr, first_len);
+            copy(ring_ptr, buf_ptr.add(first_len), msg.len - first_len);
+        }
+
+        Ok(msg.len)
+    }
+
+    /// Puts back a message that was peeked at with [try_peek], without removing it, so that it can be popped or peeked
+    /// at again.
+    pub fn abort_peek(&self, msg: PeekedMessage) {
+        #[cfg(feature = "profiler")]
+        timer!("collections::concurrent_ring::abort_peek");
+        let old_len: usize = self.write_header(msg.offset, msg.len);
+        debug_assert_eq!(old_len, 0);
+    }
+
+    /// Returns the number of bytes that a message of [len] bytes takes up in a ring buffer, including its header and
+    /// padding.
+    pub fn footprint(len: usize) -> usize {
+        align_header(len + HEADER_SIZE)
+    }
+
+    /// Removes a message that was peeked at with [try_peek] from the ring buffer.
+    pub fn commit_pop(&self, msg: PeekedMessage) {
+        #[cfg(feature = "profiler")]
+        timer!("collections::concurrent_ring::commit_pop");
+        self.release_space(msg.offset, msg.len);
+    }
+
     /// Removes the next message from the ring buffer up to [len] bytes and copies into [buf]. This function may block
     /// (spin).
     #[allow(unused)]
@@ -416,8 +502,10 @@ impl Ring for ConcurrentRingBuffer {
             buffer: raw_array::RawArray::<u8>::from_raw_parts(buffer_ptr, capacity - size_of_ring)?,
             is_managed: false,
         };
-        // Intialize the header to 0.
-        me.write_header(0, 0);
+        // Intialize the header to 0. A ring that is opened may already hold messages, so leave it as it is.
+        if init {
+            me.write_header(0, 0);
+        }
         Ok(me)
     }
 }
@@ -25,7 +25,6 @@ use ::std::ops::{
 /// This structure resides on a shared memory region and it is lock-free.
 /// This abstraction ensures the correct concurrent access by a single writer and a single reader.
 pub struct SharedRingBuffer<T: Ring> {
-    #[allow(unused)]
     shm: SharedMemory,
     ring: T,
 }
@@ -49,6 +48,18 @@ impl<T: Ring> SharedRingBuffer<T> {
         let ring: T = T::from_raw_parts(false, shm.as_mut_ptr(), shm.len())?;
         Ok(SharedRingBuffer { shm, ring })
     }
+
+    /// Opens an existing shared ring buffer, with whatever capacity it was created with.
+    pub fn open_whole(name: &str) -> Result<Self, Fail> {
+        let mut shm: SharedMemory = SharedMemory::open_whole(&name)?;
+        let ring: T = T::from_raw_parts(false, shm.as_mut_ptr(), shm.len())?;
+        Ok(SharedRingBuffer { shm, ring })
+    }
+
+    /// Gets the size of the underlying shared memory region, in bytes.
+    pub fn region_size(&self) -> usize {
+        self.shm.size()
+    }
 }
 
 //======================================================================================================================
@@ -5,9 +5,21 @@
 // Imports
 //======================================================================================================================
 
+use crate::runtime::{
+    fail::Fail,
+    limits,
+    network::{
+        ephemeral::{
+            FIRST_PRIVATE_PORT,
+            LAST_PRIVATE_PORT,
+        },
+        types::MacAddress,
+    },
+};
 use ::std::{
     fs::File,
     io::Read,
+    net::Ipv4Addr,
 };
 use ::yaml_rust::{
     Yaml,
@@ -42,6 +54,194 @@ impl Config {
         Self { 0: config_obj.clone() }
     }
 
+    /// Reads the maximum number of I/O queues parameter from the underlying configuration file. If it is not set, a
+    /// generous default is used.
+    pub fn max_queues(&self) -> usize {
+        // FIXME: this function should return a result.
+        match self.0["demikernel"]["max_queues"].as_i64() {
+            Some(max_queues) if max_queues > 0 => max_queues as usize,
+            Some(max_queues) => panic!("Invalid max_queues (max_queues={:?})", max_queues),
+            None => limits::QUEUES_MAX,
+        }
+    }
+
+    /// Reads the parameter that tells whether queue descriptors are tagged with a generation from the underlying
+    /// configuration file. If it is not set, queue descriptors are not tagged.
+    pub fn tag_qds(&self) -> bool {
+        // FIXME: this function should return a result.
+        match self.0["demikernel"]["tag_qds"].as_bool() {
+            Some(tag_qds) => tag_qds,
+            None => false,
+        }
+    }
+
+    /// Reads the range of ports that ephemeral ports are allocated from (first and last port, inclusive) from the
+    /// underlying configuration file. If it is not set, the private port range of RFC 6335 is used.
+    pub fn ephemeral_port_range(&self) -> (u16, u16) {
+        // FIXME: this function should return a result.
+        let range: &Yaml = &self.0["demikernel"]["ephemeral_port_range"];
+        if range.is_badvalue() {
+            return (FIRST_PRIVATE_PORT, LAST_PRIVATE_PORT);
+        }
+        match Self::parse_port_range(range) {
+            Some(range) => range,
+            None => panic!("Invalid ephemeral_port_range (ephemeral_port_range={:?})", range),
+        }
+    }
+
+    /// Parses a port range, given as a `[first, last]` pair of non-zero ports.
+    fn parse_port_range(value: &Yaml) -> Option<(u16, u16)> {
+        let port = |v: &Yaml| {
+            v.as_i64()
+                .and_then(|port| u16::try_from(port).ok())
+                .filter(|&port| port > 0)
+        };
+        match value.as_vec()?.as_slice() {
+            [first, last] => match (port(first)?, port(last)?) {
+                (first, last) if first <= last => Some((first, last)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Checks the underlying configuration file for malformed parameters. Unlike the accessors of this structure,
+    /// which stop at the first problem, this function reports all problems at once.
+    pub fn validate(&self) -> Result<(), Vec<Fail>> {
+        let mut errors: Vec<Fail> = Vec::new();
+
+        Self::check(
+            &mut errors,
+            "demikernel.max_queues",
+            &self.0["demikernel"]["max_queues"],
+            |v| v.as_i64().map_or(false, |max_queues| max_queues > 0),
+        );
+        Self::check(
+            &mut errors,
+            "demikernel.tag_qds",
+            &self.0["demikernel"]["tag_qds"],
+            |v| v.as_bool().is_some(),
+        );
+        Self::check(
+            &mut errors,
+            "demikernel.ephemeral_port_range",
+            &self.0["demikernel"]["ephemeral_port_range"],
+            |v| Self::parse_port_range(v).is_some(),
+        );
+
+        // Parameters that are required by the libOSes that have their own network stack.
+        #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos", feature = "catloop-libos"))]
+        Self::require(&mut errors, "catnip.my_ipv4_addr", &self.0["catnip"]["my_ipv4_addr"]);
+        #[cfg(feature = "catpowder-libos")]
+        {
+            Self::require(&mut errors, "catnip.my_link_addr", &self.0["catnip"]["my_link_addr"]);
+            Self::require(
+                &mut errors,
+                "catnip.my_interface_name",
+                &self.0["catnip"]["my_interface_name"],
+            );
+        }
+        #[cfg(feature = "catnip-libos")]
+        Self::require(&mut errors, "dpdk.eal_init", &self.0["dpdk"]["eal_init"]);
+
+        Self::check(
+            &mut errors,
+            "catnip.my_ipv4_addr",
+            &self.0["catnip"]["my_ipv4_addr"],
+            |v| {
+                v.as_str()
+                    .and_then(|s| s.parse::<Ipv4Addr>().ok())
+                    .map_or(false, |addr| !addr.is_unspecified() && !addr.is_broadcast())
+            },
+        );
+        Self::check(
+            &mut errors,
+            "catnip.my_link_addr",
+            &self.0["catnip"]["my_link_addr"],
+            |v| v.as_str().map_or(false, |s| MacAddress::parse_str(s).is_ok()),
+        );
+        Self::check(
+            &mut errors,
+            "catnip.my_interface_name",
+            &self.0["catnip"]["my_interface_name"],
+            |v| v.as_str().map_or(false, |s| !s.is_empty()),
+        );
+        Self::check(
+            &mut errors,
+            "catnip.disable_arp",
+            &self.0["catnip"]["disable_arp"],
+            |v| v.as_bool().is_some(),
+        );
+        if let Some(arp_table) = self.0["catnip"]["arp_table"].as_hash() {
+            for (link_addr, ipv4_addr) in arp_table {
+                Self::check(&mut errors, "catnip.arp_table", link_addr, |v| {
+                    v.as_str().map_or(false, |s| MacAddress::parse_str(s).is_ok())
+                });
+                Self::check(&mut errors, "catnip.arp_table", ipv4_addr, |v| {
+                    v.as_str().map_or(false, |s| s.parse::<Ipv4Addr>().is_ok())
+                });
+            }
+        } else {
+            Self::check(&mut errors, "catnip.arp_table", &self.0["catnip"]["arp_table"], |_| {
+                false
+            });
+        }
+        Self::check(&mut errors, "dpdk.eal_init", &self.0["dpdk"]["eal_init"], |v| {
+            v.as_vec()
+                .map_or(false, |args| args.iter().all(|arg| arg.as_str().is_some()))
+        });
+
+        // MTU and MSS are read from environment variables.
+        #[cfg(feature = "catnip-libos")]
+        {
+            use crate::runtime::network::consts::{
+                MAX_MSS,
+                MIN_MSS,
+            };
+            const TCP_IPV4_HEADERS_SIZE: usize = 40;
+
+            let mtu: Option<Result<u16, _>> = ::std::env::var("MTU").ok().map(|mtu| mtu.parse::<u16>());
+            let mss: Option<Result<usize, _>> = ::std::env::var("MSS").ok().map(|mss| mss.parse::<usize>());
+            if let Some(Err(_)) = mtu {
+                errors.push(Fail::new(libc::EINVAL, "invalid value for MTU environment variable"));
+            }
+            match mss {
+                Some(Ok(mss)) if mss < MIN_MSS || mss > MAX_MSS => {
+                    errors.push(Fail::new(libc::EINVAL, "MSS environment variable is out of range"))
+                },
+                Some(Err(_)) => errors.push(Fail::new(libc::EINVAL, "invalid value for MSS environment variable")),
+                _ => {},
+            }
+            if let (Some(Ok(mtu)), Some(Ok(mss))) = (mtu, mss) {
+                if mss + TCP_IPV4_HEADERS_SIZE > mtu as usize {
+                    errors.push(Fail::new(libc::EINVAL, "MSS does not fit in MTU"));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Records an error in `errors` if the parameter `key` is not set.
+    fn require(errors: &mut Vec<Fail>, key: &str, value: &Yaml) {
+        if value.is_badvalue() {
+            let cause: String = format!("missing value for {}", key);
+            errors.push(Fail::new(libc::EINVAL, &cause));
+        }
+    }
+
+    /// Records an error in `errors` if the parameter `key` is set but its value is not valid.
+    fn check<F: FnOnce(&Yaml) -> bool>(errors: &mut Vec<Fail>, key: &str, value: &Yaml, is_valid: F) {
+        if !value.is_badvalue() && !is_valid(value) {
+            let cause: String = format!("invalid value for {} (value={:?})", key, value);
+            errors.push(Fail::new(libc::EINVAL, &cause));
+        }
+    }
+
     /// Reads the local IPv4 address parameter from the underlying configuration file.
     #[cfg(any(feature = "catnip-libos", feature = "catpowder-libos", feature = "catloop-libos"))]
     pub fn local_ipv4_addr(&self) -> ::std::net::Ipv4Addr {
@@ -61,3 +261,78 @@ impl Config {
         local_ipv4_addr
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::runtime::fail::Fail;
+    use ::anyhow::Result;
+    use ::yaml_rust::YamlLoader;
+
+    fn parse(config_s: &str) -> Config {
+        Config(YamlLoader::load_from_str(config_s).unwrap().remove(0))
+    }
+
+    #[test]
+    fn validate_accepts_good_config() -> Result<()> {
+        let config: Config = parse(
+            r#"
+demikernel:
+  max_queues: 1024
+  tag_qds: true
+  ephemeral_port_range: [50000, 50100]
+catnip:
+  my_ipv4_addr: "192.168.1.1"
+  my_link_addr: "12:23:45:67:89:ab"
+  my_interface_name: "eth0"
+  arp_table:
+    "12:23:45:67:89:ac": "192.168.1.2"
+dpdk:
+  eal_init: ["", "-c", "0xff"]
+"#,
+        );
+        crate::ensure_eq!(config.validate().is_ok(), true);
+        crate::ensure_eq!(config.ephemeral_port_range(), (50000, 50100));
+        Ok(())
+    }
+
+    #[test]
+    fn validate_reports_all_errors() -> Result<()> {
+        let config: Config = parse(
+            r#"
+demikernel:
+  max_queues: -1
+  ephemeral_port_range: [50100, 50000]
+catnip:
+  my_ipv4_addr: "0.0.0.0"
+  my_link_addr: "not a mac"
+  my_interface_name: "eth0"
+  arp_table:
+    "12:23:45:67:89:ac": "192.168.1.256"
+dpdk:
+  eal_init: ["", 3]
+"#,
+        );
+        let errors: Vec<Fail> = match config.validate() {
+            Ok(()) => anyhow::bail!("validation should fail"),
+            Err(errors) => errors,
+        };
+        crate::ensure_eq!(errors.len(), 6);
+        for (error, key) in errors.iter().zip([
+            "demikernel.max_queues",
+            "demikernel.ephemeral_port_range",
+            "catnip.my_ipv4_addr",
+            "catnip.my_link_addr",
+            "catnip.arp_table",
+            "dpdk.eal_init",
+        ]) {
+            crate::ensure_eq!(error.errno, libc::EINVAL);
+            crate::ensure_eq!(error.cause.contains(key), true);
+        }
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@
 
 use crate::runtime::{
     fail::Fail,
+    memory::DemiBufferChain,
     scheduler::TaskHandle,
     types::{
         demi_qresult_t,
@@ -14,7 +15,10 @@ use crate::runtime::{
     },
     QDesc,
     QToken,
+    QueueInfo,
+    Readiness,
 };
+use ::std::time::Duration;
 
 #[cfg(feature = "catmem-libos")]
 use crate::{
@@ -62,6 +66,26 @@ impl MemoryLibOS {
         }
     }
 
+    /// Grows the shared memory ring of a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn resize_pipe(&mut self, memqd: QDesc, new_size: usize) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.resize_pipe(memqd, new_size),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Sets the low-water mark of a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn set_rcvlowat(&mut self, memqd: QDesc, bytes: usize) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.set_rcvlowat(memqd, bytes),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     /// Closes a memory queue.
     #[allow(unreachable_patterns, unused_variables)]
     pub fn close(&mut self, memqd: QDesc) -> Result<(), Fail> {
@@ -92,6 +116,26 @@ impl MemoryLibOS {
         }
     }
 
+    /// Pushes a chain of buffers to a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn push_chain(&mut self, memqd: QDesc, chain: DemiBufferChain) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.push_chain(memqd, chain),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Pushes a scatter-gather array to a memory queue, giving up if it is not accepted within a timeout.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn push_timeout(&mut self, memqd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.push_timeout(memqd, sga, timeout),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     /// Pops data from a memory queue.
     #[allow(unreachable_patterns, unused_variables)]
     pub fn pop(&mut self, memqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
@@ -102,6 +146,46 @@ impl MemoryLibOS {
         }
     }
 
+    /// Peeks at the next message in a memory queue without removing it.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn peek_message(&mut self, memqd: QDesc, size: Option<usize>) -> Result<demi_sgarray_t, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.peek_message(memqd, size),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Removes the message that was last peeked at from a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn commit_pop(&mut self, memqd: QDesc) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.commit_pop(memqd),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Streams data from a memory queue into a callback.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn pop_stream<F: FnMut(&[u8]) -> bool + 'static>(&mut self, memqd: QDesc, f: F) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.pop_stream(memqd, f),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Moves data from one memory queue to another.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn splice(&mut self, src_memqd: QDesc, dst_memqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.splice(src_memqd, dst_memqd, size),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     /// Allocates a scatter-gather array.
     #[allow(unreachable_patterns, unused_variables)]
     pub fn sgaalloc(&self, size: usize) -> Result<demi_sgarray_t, Fail> {
@@ -122,6 +206,26 @@ impl MemoryLibOS {
         }
     }
 
+    /// Checks which operations would make progress right away on a memory queue.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn readiness(&self, memqd: QDesc) -> Result<Readiness, Fail> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.readiness(memqd),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Lists all memory queues that are currently open.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn list_queues(&self) -> Vec<QueueInfo> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime: _, libos } => libos.list_queues(),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     /// Waits for any operation in an I/O queue.
     #[allow(unreachable_patterns, unused_variables)]
     pub fn from_task_id(&mut self, qt: QToken) -> Result<TaskHandle, Fail> {
@@ -141,12 +245,43 @@ impl MemoryLibOS {
         }
     }
 
+    /// Sets the correlation ID that is attached to the task IDs of the operations that are issued from now on, and
+    /// returns the previous one.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn set_trace_context(&mut self, trace_context: Option<&str>) -> Option<String> {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.set_trace_context(trace_context),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Enables or disables the manual clock of the runtime.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn set_manual_clock(&mut self, enabled: bool) {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.set_manual_clock(enabled),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
+    /// Moves the clock of the runtime forward by `duration`.
+    #[allow(unreachable_patterns, unused_variables)]
+    pub fn advance_clock_by(&mut self, duration: Duration) {
+        match self {
+            #[cfg(feature = "catmem-libos")]
+            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.advance_clock_by(duration),
+            _ => unreachable!("unknown memory libos"),
+        }
+    }
+
     /// Waits for any operation in an I/O queue.
     #[allow(unreachable_patterns, unused_variables)]
     pub fn poll(&mut self) {
         match self {
             #[cfg(feature = "catmem-libos")]
-            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.poll(),
+            MemoryLibOS::Catmem { runtime, libos: _ } => runtime.poll_and_advance_clock(),
             _ => unreachable!("unknown memory libos"),
         }
     }
@@ -20,6 +20,7 @@ use crate::{
         fail::Fail,
         limits,
         logging,
+        memory::DemiBufferChain,
         scheduler::TaskHandle,
         types::{
             demi_qresult_t,
@@ -27,12 +28,17 @@ use crate::{
         },
         QDesc,
         QToken,
+        QueueInfo,
+        Readiness,
         SharedDemiRuntime,
     },
 };
 use ::std::{
     env,
-    net::SocketAddr,
+    net::{
+        SocketAddr,
+        SocketAddrV4,
+    },
     time::{
         Duration,
         Instant,
@@ -92,7 +98,18 @@ impl LibOS {
             },
         };
         let config: Config = Config::new(config_path);
-        let runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        if let Err(errors) = config.validate() {
+            for e in errors.iter() {
+                error!("new(): {:?}", e);
+            }
+            let cause: String = format!("invalid configuration file ({} errors)", errors.len());
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        runtime.set_max_queues(config.max_queues());
+        runtime.set_tag_qds(config.tag_qds());
+        let (first_port, last_port): (u16, u16) = config.ephemeral_port_range();
+        runtime.set_ephemeral_port_range(first_port, last_port)?;
         // Instantiate LibOS.
         #[allow(unreachable_patterns)]
         let libos: LibOS = match libos_name {
@@ -104,7 +121,7 @@ impl LibOS {
             #[cfg(feature = "catcollar-libos")]
             LibOSName::Catcollar => Self::NetworkLibOS(NetworkLibOS::Catcollar {
                 runtime: runtime.clone(),
-                libos: CatcollarLibOS::new(&config, runtime.clone()),
+                libos: CatcollarLibOS::new(&config, runtime.clone())?,
             }),
             #[cfg(feature = "catpowder-libos")]
             LibOSName::Catpowder => Self::NetworkLibOS(NetworkLibOS::Catpowder {
@@ -170,6 +187,26 @@ impl LibOS {
         result
     }
 
+    /// Grows the shared memory ring of a memory queue to `new_size` bytes. The queue must be empty in both directions,
+    /// so this should be done before any data flows.
+    pub fn resize_pipe(&mut self, memqd: QDesc, new_size: usize) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::resize_pipe");
+            match self {
+                LibOS::NetworkLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "resize_pipe() is not supported on network liboses",
+                )),
+                LibOS::MemoryLibOS(libos) => libos.resize_pipe(memqd, new_size),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Creates a socket.
     pub fn socket(
         &mut self,
@@ -207,6 +244,27 @@ impl LibOS {
         result
     }
 
+    /// Creates a socket and binds it to a local address. If the socket cannot be bound, it is closed again.
+    pub fn bound_socket(
+        &mut self,
+        domain: libc::c_int,
+        socket_type: libc::c_int,
+        protocol: libc::c_int,
+        local: SocketAddr,
+    ) -> Result<QDesc, Fail> {
+        let sockqd: QDesc = self.socket(domain, socket_type, protocol)?;
+        if let Err(e) = self.bind(sockqd, local) {
+            if let Err(close_e) = self.close(sockqd) {
+                warn!(
+                    "bound_socket(): failed to close socket (qd={:?}): {:?}",
+                    sockqd, close_e
+                );
+            }
+            return Err(e);
+        }
+        Ok(sockqd)
+    }
+
     /// Marks a socket as a passive one.
     pub fn listen(&mut self, sockqd: QDesc, backlog: usize) -> Result<(), Fail> {
         let result: Result<(), Fail> = {
@@ -223,14 +281,96 @@ impl LibOS {
         result
     }
 
-    /// Accepts an incoming connection on a TCP socket.
-    pub fn accept(&mut self, sockqd: QDesc) -> Result<QToken, Fail> {
-        let result: Result<QToken, Fail> = {
+    /// Changes the backlog of a passive socket.
+    pub fn set_backlog(&mut self, sockqd: QDesc, backlog: usize) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_backlog");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_backlog(sockqd, backlog),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_backlog() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Turns quick ACK mode on or off for a TCP socket. While it is on, incoming data is acknowledged right away
+    /// instead of being delayed, which avoids stalls on request/response traffic.
+    pub fn set_quickack(&mut self, sockqd: QDesc, enable: bool) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_quickack");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_quickack(sockqd, enable),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_quickack() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Corks or uncorks a TCP socket. While it is corked, small pushes are coalesced and held back, so that they go out
+    /// in as few segments as possible once it is uncorked.
+    pub fn cork(&mut self, sockqd: QDesc, enabled: bool) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::cork");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.cork(sockqd, enabled),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "cork() is not supported on memory liboses")),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Registers a group of `count` receive buffers of `size` bytes each, which the kernel picks from when data arrives.
+    /// Received data is then handed over without a copy, and each buffer is reused once its scatter-gather array is
+    /// freed.
+    pub fn register_recv_buffer_group(&mut self, count: u16, size: u16) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::register_recv_buffer_group");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.register_recv_buffer_group(count, size),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "register_recv_buffer_group() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Sets up a pool of `count` receive chunks of `chunk_size` bytes each, which pops draw their buffers from instead of
+    /// allocating a maximum-sized buffer for each of them. Buffers go back to the pool once their scatter-gather array is
+    /// freed, and are allocated off of the heap when the pool runs out of them.
+    pub fn set_recv_buffer_pool(&mut self, chunk_size: u16, count: usize) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
             #[cfg(feature = "profiler")]
-            timer!("demikernel::accept");
+            timer!("demikernel::set_recv_buffer_pool");
             match self {
-                LibOS::NetworkLibOS(libos) => libos.accept(sockqd),
-                LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "accept() is not supported on memory liboses")),
+                LibOS::NetworkLibOS(libos) => libos.set_recv_buffer_pool(chunk_size, count),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_recv_buffer_pool() is not supported on memory liboses",
+                )),
             }
         };
 
@@ -239,6 +379,259 @@ impl LibOS {
         result
     }
 
+    /// Caps the send rate of a queue to `bytes_per_sec`. Pushes on the queue are held back until the rate limit lets
+    /// them through. A zero rate removes the limit.
+    pub fn set_rate_limit(&mut self, qd: QDesc, bytes_per_sec: u64) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_rate_limit");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_rate_limit(qd, bytes_per_sec),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_rate_limit() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Sets a socket to multiplex its connections over shared transport channels, instead of setting up a channel for
+    /// each of them. This must be done on both ends, before they listen or connect. It is only supported on catloop,
+    /// where logical connections to the same remote share a single pair of shared memory rings.
+    pub fn set_multiplexed(&mut self, qd: QDesc) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_multiplexed");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_multiplexed(qd),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_multiplexed() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Turns on continuous receive mode on a queue. A receive operation is then kept armed on the queue at all times,
+    /// so that data is buffered in a backlog of up to `count` receive buffers as soon as it arrives, and pops return
+    /// from that backlog. This prevents data from being dropped between pops.
+    pub fn set_continuous_recv(&mut self, qd: QDesc, count: usize) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_continuous_recv");
+
+            // Check if backlog size is valid.
+            if !((count > 0) && (count <= limits::RECV_BACKLOG_MAX)) {
+                let cause: String = format!("invalid backlog size (count={:?})", count);
+                error!("set_continuous_recv(): {:?}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_continuous_recv(qd, count),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_continuous_recv() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Sets the receive low-water mark (SO_RCVLOWAT) of a socket or memory queue, so that pops do not complete until at
+    /// least `bytes` bytes are available, unless the other end closes first.
+    pub fn set_rcvlowat(&mut self, qd: QDesc, bytes: usize) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_rcvlowat");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_rcvlowat(qd, bytes),
+                LibOS::MemoryLibOS(libos) => libos.set_rcvlowat(qd, bytes),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Sets the priority (SO_PRIORITY) of the packets sent on a socket, so that Linux queueing disciplines can shape
+    /// its traffic. Priorities range from 0 to 6.
+    pub fn set_priority(&mut self, sockqd: QDesc, priority: u32) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_priority");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_priority(sockqd, priority),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_priority() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Sets the key that signs the segments that a socket exchanges with `remote` with the TCP MD5 signature option (see
+    /// RFC 2385), as required by peers that authenticate sessions with it (e.g., BGP routers). Only the address of
+    /// `remote` is taken into account, keys are at most 80 bytes long, and an empty key stops signing segments.
+    pub fn set_tcp_md5sig(&mut self, sockqd: QDesc, remote: SocketAddrV4, key: &[u8]) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::set_tcp_md5sig");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.set_tcp_md5sig(sockqd, remote, key),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "set_tcp_md5sig() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Gets the priority (SO_PRIORITY) of the packets sent on a socket.
+    pub fn get_priority(&self, sockqd: QDesc) -> Result<u32, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::get_priority");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.get_priority(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "get_priority() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Binds a socket to the network interface named `ifname` (SO_BINDTODEVICE), so that it only sends and receives
+    /// packets through that interface. Fails with `ENODEV` if there is no such interface. An empty name removes the
+    /// binding.
+    pub fn bind_to_device(&mut self, sockqd: QDesc, ifname: &str) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::bind_to_device");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.bind_to_device(sockqd, ifname),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "bind_to_device() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Gets the local address of a socket. Once a socket that was not bound is connected, this reports the ephemeral
+    /// port that was picked for it.
+    pub fn getsockname(&self, sockqd: QDesc) -> Result<SocketAddrV4, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::getsockname");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.getsockname(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "getsockname() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Gets the maximum segment size of the connection of a TCP socket. Pushing multiples of it avoids partial segments.
+    pub fn connection_mss(&self, sockqd: QDesc) -> Result<usize, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::connection_mss");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.connection_mss(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "connection_mss() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Gets the name of the network interface that a socket is bound to (SO_BINDTODEVICE), or an empty name if it is
+    /// not bound to any.
+    pub fn get_bound_device(&self, sockqd: QDesc) -> Result<String, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::get_bound_device");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.get_bound_device(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(
+                libc::ENOTSUP,
+                "get_bound_device() is not supported on memory liboses",
+            )),
+        }
+    }
+
+    /// Retrieves the oldest asynchronous error reported on a UDP socket, such as `ECONNREFUSED` when a datagram was
+    /// sent to a closed port. If no error is pending, `None` is returned.
+    pub fn poll_error(&mut self, sockqd: QDesc) -> Result<Option<Fail>, Fail> {
+        let result: Result<Option<Fail>, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::poll_error");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.poll_error(sockqd),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "poll_error() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Accepts an incoming connection on a TCP socket.
+    pub fn accept(&mut self, sockqd: QDesc) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = self.do_accept(sockqd);
+
+        self.poll();
+
+        result
+    }
+
+    /// Accepts an incoming connection on a TCP socket, like [Self::accept], and tags the operation with the
+    /// correlation ID `trace_context` (see [Self::set_trace_context]).
+    pub fn accept_traced(&mut self, sockqd: QDesc, trace_context: &str) -> Result<QToken, Fail> {
+        let previous: Option<String> = self.set_trace_context(Some(trace_context));
+        let result: Result<QToken, Fail> = self.do_accept(sockqd);
+        self.set_trace_context(previous.as_deref());
+
+        self.poll();
+
+        result
+    }
+
+    fn do_accept(&mut self, sockqd: QDesc) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::accept");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.accept(sockqd),
+            LibOS::MemoryLibOS(_) => Err(Fail::new(libc::ENOTSUP, "accept() is not supported on memory liboses")),
+        }
+    }
+
     /// Initiates a connection with a remote TCP socket.
     pub fn connect(&mut self, sockqd: QDesc, remote: SocketAddr) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = {
@@ -286,14 +679,82 @@ impl LibOS {
         result
     }
 
+    /// Asynchronously closes a socket, lingering until the peer acknowledges that the connection is closed or until
+    /// `timeout` expires, in which case the connection is reset.
+    pub fn async_close_with_linger(&mut self, qd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::async_close_with_linger");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.async_close_with_linger(qd, timeout),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "async_close_with_linger() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Pushes a scatter-gather array to an I/O queue.
     pub fn push(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = self.do_push(qd, sga);
+
+        self.poll();
+
+        result
+    }
+
+    /// Pushes a scatter-gather array to an I/O queue, like [Self::push], and tags the operation with the correlation
+    /// ID `trace_context` (see [Self::set_trace_context]).
+    pub fn push_traced(&mut self, qd: QDesc, sga: &demi_sgarray_t, trace_context: &str) -> Result<QToken, Fail> {
+        let previous: Option<String> = self.set_trace_context(Some(trace_context));
+        let result: Result<QToken, Fail> = self.do_push(qd, sga);
+        self.set_trace_context(previous.as_deref());
+
+        self.poll();
+
+        result
+    }
+
+    fn do_push(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::push");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.push(qd, sga),
+            LibOS::MemoryLibOS(libos) => libos.push(qd, sga),
+        }
+    }
+
+    /// Pushes a chain of buffers to an I/O queue. The data that is pushed is the concatenation of the buffers, which
+    /// are not merged into a single buffer along the way.
+    pub fn push_chain(&mut self, qd: QDesc, chain: DemiBufferChain) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = {
             #[cfg(feature = "profiler")]
-            timer!("demikernel::push");
+            timer!("demikernel::push_chain");
             match self {
-                LibOS::NetworkLibOS(libos) => libos.push(qd, sga),
-                LibOS::MemoryLibOS(libos) => libos.push(qd, sga),
+                LibOS::NetworkLibOS(libos) => libos.push_chain(qd, chain),
+                LibOS::MemoryLibOS(libos) => libos.push_chain(qd, chain),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Pushes a scatter-gather array to an I/O queue, failing with `ETIMEDOUT` if the data cannot be sent within
+    /// `timeout`.
+    pub fn push_timeout(&mut self, qd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::push_timeout");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.push_timeout(qd, sga, timeout),
+                LibOS::MemoryLibOS(libos) => libos.push_timeout(qd, sga, timeout),
             }
         };
 
@@ -318,25 +779,101 @@ impl LibOS {
         result
     }
 
+    /// Pushes a batch of datagrams, each to its own destination, through a UDP socket. One queue token is returned for
+    /// each datagram, in the same order, so partial sends can be told apart by waiting on them individually.
+    pub fn pushto_batch(&mut self, qd: QDesc, items: &[(demi_sgarray_t, SocketAddr)]) -> Result<Vec<QToken>, Fail> {
+        let result: Result<Vec<QToken>, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::pushto_batch");
+
+            // Check if batch size is valid.
+            if items.is_empty() || (items.len() > limits::PUSH_BATCH_MAX) {
+                let cause: String = format!("invalid batch size (count={:?})", items.len());
+                error!("pushto_batch(): {:?}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.pushto_batch(qd, items),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "pushto_batch() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
     /// Pops data from a an I/O queue.
     pub fn pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        Self::check_pop_size(size)?;
+        let result: Result<QToken, Fail> = self.do_pop(qd, size);
+
+        self.poll();
+
+        result
+    }
+
+    /// Pops data from an I/O queue, like [Self::pop], and tags the operation with the correlation ID `trace_context`
+    /// (see [Self::set_trace_context]).
+    pub fn pop_traced(&mut self, qd: QDesc, size: Option<usize>, trace_context: &str) -> Result<QToken, Fail> {
+        Self::check_pop_size(size)?;
+        let previous: Option<String> = self.set_trace_context(Some(trace_context));
+        let result: Result<QToken, Fail> = self.do_pop(qd, size);
+        self.set_trace_context(previous.as_deref());
+
+        self.poll();
+
+        result
+    }
+
+    /// Checks the size of a fixed-size pop, if that is one.
+    fn check_pop_size(size: Option<usize>) -> Result<(), Fail> {
+        if let Some(size) = size {
+            if !((size > 0) && (size <= limits::POP_SIZE_MAX)) {
+                let cause: String = format!("invalid pop size (size={:?})", size);
+                error!("pop(): {:?}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+        }
+        Ok(())
+    }
+
+    fn do_pop(&mut self, qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::pop");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.pop(qd, size),
+            LibOS::MemoryLibOS(libos) => libos.pop(qd, size),
+        }
+    }
+
+    /// Pops data from an I/O queue, failing with `ETIMEDOUT` if no data arrives before the clock of the runtime reaches
+    /// `deadline`. The deadline is carried by the operation itself, so there is no need to wrap the wait in a timeout.
+    pub fn pop_with_deadline(&mut self, qd: QDesc, size: Option<usize>, deadline: Instant) -> Result<QToken, Fail> {
         let result: Result<QToken, Fail> = {
             #[cfg(feature = "profiler")]
-            timer!("demikernel::pop");
+            timer!("demikernel::pop_with_deadline");
 
             // Check if this is a fixed-size pop.
             if let Some(size) = size {
                 // Check if size is valid.
                 if !((size > 0) && (size <= limits::POP_SIZE_MAX)) {
                     let cause: String = format!("invalid pop size (size={:?})", size);
-                    error!("pop(): {:?}", &cause);
+                    error!("pop_with_deadline(): {:?}", &cause);
                     return Err(Fail::new(libc::EINVAL, &cause));
                 }
             }
 
             match self {
-                LibOS::NetworkLibOS(libos) => libos.pop(qd, size),
-                LibOS::MemoryLibOS(libos) => libos.pop(qd, size),
+                LibOS::NetworkLibOS(libos) => libos.pop_with_deadline(qd, size, deadline),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "pop_with_deadline() is not supported on memory liboses",
+                )),
             }
         };
 
@@ -345,6 +882,157 @@ impl LibOS {
         result
     }
 
+    /// Peeks at the next message in memory queue `memqd` without removing it, failing with `EAGAIN` if there is none
+    /// yet. Peeking again returns the same message until it is removed with [commit_pop], so a message that fails to be
+    /// processed is not lost. A zero-length scatter-gather array indicates end of file.
+    pub fn peek_message(&mut self, memqd: QDesc, size: Option<usize>) -> Result<demi_sgarray_t, Fail> {
+        let result: Result<demi_sgarray_t, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::peek_message");
+
+            // Check if this is a fixed-size peek.
+            if let Some(size) = size {
+                // Check if size is valid.
+                if !((size > 0) && (size <= limits::POP_SIZE_MAX)) {
+                    let cause: String = format!("invalid peek size (size={:?})", size);
+                    error!("peek_message(): {:?}", &cause);
+                    return Err(Fail::new(libc::EINVAL, &cause));
+                }
+            }
+
+            match self {
+                LibOS::NetworkLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "peek_message() is not supported on network liboses",
+                )),
+                LibOS::MemoryLibOS(libos) => libos.peek_message(memqd, size),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Removes the message that was last peeked at from memory queue `memqd`.
+    pub fn commit_pop(&mut self, memqd: QDesc) -> Result<(), Fail> {
+        let result: Result<(), Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::commit_pop");
+            match self {
+                LibOS::NetworkLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "commit_pop() is not supported on network liboses",
+                )),
+                LibOS::MemoryLibOS(libos) => libos.commit_pop(memqd),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Pops up to `max` datagrams from the UDP socket `sockqd` at once. The operation completes as soon as a datagram
+    /// arrives, along with any others that are already queued by then. The result carries the number of datagrams in
+    /// `qr_ret`, and a scatter-gather array whose single segment holds an array of that many scatter-gather arrays, one
+    /// for each datagram along with its source address. All of these scatter-gather arrays must be released.
+    pub fn pop_batch(&mut self, sockqd: QDesc, max: usize) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::pop_batch");
+
+            // Check if batch size is valid.
+            if !((max > 0) && (max <= limits::POP_BATCH_MAX)) {
+                let cause: String = format!("invalid batch size (max={:?})", max);
+                error!("pop_batch(): {:?}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            }
+
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.pop_batch(sockqd, max),
+                LibOS::MemoryLibOS(_) => Err(Fail::new(
+                    libc::ENOTSUP,
+                    "pop_batch() is not supported on memory liboses",
+                )),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Streams data from `qd` into `f`, one chunk at a time, until `f` returns false or end of file is reached. Chunks
+    /// are released as soon as `f` returns, so memory usage stays bounded for arbitrarily large transfers. The number of
+    /// bytes streamed is reported in the result of the operation.
+    pub fn pop_stream<F: FnMut(&[u8]) -> bool + 'static>(&mut self, qd: QDesc, f: F) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::pop_stream");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.pop_stream(qd, f),
+                LibOS::MemoryLibOS(libos) => libos.pop_stream(qd, f),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Moves data from queue `src_qd` to queue `dst_qd`, until `size` bytes have been moved or `src_qd` reaches end of
+    /// file. Both queues are either memory queues or TCP sockets. The number of bytes moved is reported in the result of
+    /// the operation.
+    pub fn splice(&mut self, src_qd: QDesc, dst_qd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        let result: Result<QToken, Fail> = {
+            #[cfg(feature = "profiler")]
+            timer!("demikernel::splice");
+            match self {
+                LibOS::NetworkLibOS(libos) => libos.splice(src_qd, dst_qd, size),
+                LibOS::MemoryLibOS(libos) => libos.splice(src_qd, dst_qd, size),
+            }
+        };
+
+        self.poll();
+
+        result
+    }
+
+    /// Sets the correlation ID that is attached to the task IDs of the operations that are issued from now on, so that
+    /// their completions can be tied back to an application request in logs and failures. Passing `None` clears it.
+    /// Background coroutines are never tagged. This returns the previous correlation ID, so that it can be restored.
+    pub fn set_trace_context(&mut self, trace_context: Option<&str>) -> Option<String> {
+        trace!("set_trace_context(): trace_context={:?}", trace_context);
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.set_trace_context(trace_context),
+            LibOS::MemoryLibOS(libos) => libos.set_trace_context(trace_context),
+        }
+    }
+
+    /// Enables or disables the manual clock. While it is enabled, the clock of the runtime stands still when polling and
+    /// only moves forward through [Self::advance_clock_by], so that tests drive timers deterministically. Timeouts of
+    /// waits still follow wall time.
+    pub fn set_manual_clock(&mut self, enabled: bool) {
+        trace!("set_manual_clock(): enabled={:?}", enabled);
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.set_manual_clock(enabled),
+            LibOS::MemoryLibOS(libos) => libos.set_manual_clock(enabled),
+        }
+    }
+
+    /// Moves the clock of the runtime forward by `duration`, and runs the coroutines whose timers expired along the
+    /// way.
+    pub fn advance_clock_by(&mut self, duration: Duration) {
+        trace!("advance_clock_by(): duration={:?}", duration);
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.advance_clock_by(duration),
+            LibOS::MemoryLibOS(libos) => libos.advance_clock_by(duration),
+        }
+
+        self.poll();
+    }
+
     /// Waits for a pending I/O operation to complete or a timeout to expire.
     /// This is just a single-token convenience wrapper for wait_any().
     pub fn wait(&mut self, qt: QToken, timeout: Option<Duration>) -> Result<demi_qresult_t, Fail> {
@@ -378,9 +1066,37 @@ impl LibOS {
             if abstime.is_none() || SystemTime::now() >= abstime.unwrap() {
                 return Err(Fail::new(libc::ETIMEDOUT, "timer expired"));
             }
+
+            // Nothing completed, so sleep until something may have.
+            let remaining: Option<Duration> =
+                abstime.and_then(|abstime| abstime.duration_since(SystemTime::now()).ok());
+            self.idle(remaining);
         }
     }
 
+    /// Waits for a pending I/O operation to complete by busy-polling the scheduler at most `max_spins` times. This skips
+    /// the timeout bookkeeping of [wait], so it is meant for latency-sensitive paths where the operation is expected to
+    /// complete right away. Returns `None` if the operation is still pending afterwards, in which case it may be waited
+    /// on again.
+    pub fn wait_spin(&mut self, qt: QToken, max_spins: usize) -> Result<Option<demi_qresult_t>, Fail> {
+        trace!("wait_spin(): qt={:?}, max_spins={:?}", qt, max_spins);
+
+        // Retrieve associated schedule handle.
+        let handle: TaskHandle = self.schedule(qt)?;
+
+        // The operation may have completed already, in which case there is no need to poll at all.
+        for spin in 0..=max_spins {
+            if handle.has_completed() {
+                return Ok(Some(self.pack_result(handle, qt)?));
+            }
+            if spin < max_spins {
+                self.poll();
+            }
+        }
+
+        Ok(None)
+    }
+
     /// Waits for any of the given pending I/O operations to complete or a timeout to expire.
     pub fn wait_any(&mut self, qts: &[QToken], timeout: Option<Duration>) -> Result<(usize, demi_qresult_t), Fail> {
         trace!("wait_any(): qts={:?}, timeout={:?}", qts, timeout);
@@ -411,6 +1127,13 @@ impl LibOS {
             {
                 return Err(Fail::new(libc::ETIMEDOUT, "timer expired"));
             }
+
+            // Nothing completed, so sleep until something may have.
+            let remaining: Option<Duration> = match (start, timeout) {
+                (Some(start), Some(timeout)) => Some(timeout.saturating_sub(start.elapsed())),
+                _ => None,
+            };
+            self.idle(remaining);
         }
     }
 
@@ -446,6 +1169,28 @@ impl LibOS {
         result
     }
 
+    /// Checks which operations would make progress right away on an I/O queue: whether it is readable, writable, or has
+    /// an error or hangup pending. This lets an external event loop decide which operations to issue. This function is
+    /// read-only.
+    pub fn readiness(&self, qd: QDesc) -> Result<Readiness, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::readiness");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.readiness(qd),
+            LibOS::MemoryLibOS(libos) => libos.readiness(qd),
+        }
+    }
+
+    /// Lists all I/O queues that are currently open. This function is read-only and intended for diagnostics.
+    pub fn list_queues(&self) -> Vec<QueueInfo> {
+        #[cfg(feature = "profiler")]
+        timer!("demikernel::list_queues");
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.list_queues(),
+            LibOS::MemoryLibOS(libos) => libos.list_queues(),
+        }
+    }
+
     /// Waits for any operation in an I/O queue.
     fn schedule(&mut self, qt: QToken) -> Result<TaskHandle, Fail> {
         match self {
@@ -464,6 +1209,15 @@ impl LibOS {
         }
     }
 
+    /// Sleeps for up to `timeout`, or for as long as the LibOS sees fit if no timeout is given, when no coroutine is
+    /// ready to run.
+    fn idle(&mut self, timeout: Option<Duration>) {
+        match self {
+            LibOS::NetworkLibOS(libos) => libos.idle(timeout),
+            LibOS::MemoryLibOS(_) => (),
+        }
+    }
+
     fn poll(&mut self) {
         #[cfg(feature = "profiler")]
         timer!("demikernel::poll");
@@ -473,3 +1227,111 @@ impl LibOS {
         }
     }
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(all(test, feature = "catmem-libos"))]
+mod tests {
+    use super::{
+        memory::MemoryLibOS,
+        LibOS,
+    };
+    use crate::{
+        catmem::SharedCatmemLibOS,
+        demikernel::config::Config,
+        ensure_eq,
+        runtime::{
+            scheduler::{
+                Task,
+                TaskHandle,
+            },
+            types::demi_sgarray_t,
+            QDesc,
+            QToken,
+            SharedDemiRuntime,
+        },
+    };
+    use ::anyhow::Result;
+    use ::yaml_rust::YamlLoader;
+
+    /// Tests that a traced operation is tagged with its own trace context, and that the trace context that was set
+    /// beforehand applies again to the operations that are issued afterwards.
+    #[test]
+    fn traced_operations_restore_trace_context() -> Result<()> {
+        let config: Config = Config(YamlLoader::load_from_str("{}")?.remove(0));
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: LibOS = LibOS::MemoryLibOS(MemoryLibOS::Catmem {
+            runtime: runtime.clone(),
+            libos: SharedCatmemLibOS::new(&config, runtime.clone()),
+        });
+        let server_qd: QDesc = libos.create_pipe("libos-test-traced-operations")?;
+        let client_qd: QDesc = libos.open_pipe("libos-test-traced-operations")?;
+
+        // Only the traced pop is tagged with its trace context, and the push that follows it gets the outer one.
+        ensure_eq!(libos.set_trace_context(Some("outer")), None);
+        let pop_qt: QToken = libos.pop_traced(server_qd, None, "req-1")?;
+        let sga: demi_sgarray_t = libos.sgaalloc(3)?;
+        let push_qt: QToken = libos.push(client_qd, &sga)?;
+        libos.sgafree(sga)?;
+        ensure_eq!(libos.set_trace_context(None), Some("outer".to_string()));
+
+        // Without an outer trace context, none is left behind either.
+        let sga: demi_sgarray_t = libos.sgaalloc(3)?;
+        let traced_push_qt: QToken = libos.push_traced(client_qd, &sga, "req-2")?;
+        libos.sgafree(sga)?;
+        ensure_eq!(libos.set_trace_context(None), None);
+
+        for (qt, suffix) in [
+            (pop_qt, " trace=req-1"),
+            (push_qt, " trace=outer"),
+            (traced_push_qt, " trace=req-2"),
+        ] {
+            let handle: TaskHandle = runtime.from_task_id(qt)?;
+            let name: String = runtime.remove_coroutine(&handle).get_name();
+            ensure_eq!(name.ends_with(suffix), true);
+        }
+
+        Ok(())
+    }
+
+    /// Tests if a socket may be created and bound in one go, and that no queue descriptor is leaked if binding fails.
+    #[cfg(feature = "catnap-libos")]
+    #[test]
+    fn bound_socket_closes_socket_on_failure() -> Result<()> {
+        use super::network::NetworkLibOS;
+        use crate::{
+            catnap::SharedCatnapLibOS,
+            runtime::QueueInfo,
+        };
+        use ::std::net::{
+            Ipv4Addr,
+            SocketAddr,
+            SocketAddrV4,
+        };
+
+        let config: Config = Config(YamlLoader::load_from_str("{}")?.remove(0));
+        let runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let mut libos: LibOS = LibOS::NetworkLibOS(NetworkLibOS::Catnap {
+            runtime: runtime.clone(),
+            libos: SharedCatnapLibOS::new(&config, runtime.clone()),
+        });
+
+        let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 23461);
+        let sockqd: QDesc = libos.bound_socket(libc::AF_INET, libc::SOCK_STREAM, 0, SocketAddr::V4(local))?;
+        let queues: Vec<QueueInfo> = libos.list_queues();
+        ensure_eq!(queues.len(), 1);
+        ensure_eq!(queues[0].qd, sockqd);
+        ensure_eq!(queues[0].local, Some(local));
+
+        // Can't bind an address twice, and the socket that could not be bound is gone.
+        match libos.bound_socket(libc::AF_INET, libc::SOCK_STREAM, 0, SocketAddr::V4(local)) {
+            Err(e) if e.errno == libc::EADDRINUSE => (),
+            _ => anyhow::bail!("bound_socket() to an address in use should fail with EADDRINUSE"),
+        };
+        ensure_eq!(libos.list_queues().len(), 1);
+
+        Ok(())
+    }
+}
@@ -9,7 +9,10 @@ use crate::{
     pal::constants::SOMAXCONN,
     runtime::{
         fail::Fail,
-        memory::MemoryRuntime,
+        memory::{
+            DemiBufferChain,
+            MemoryRuntime,
+        },
         scheduler::TaskHandle,
         types::{
             demi_qresult_t,
@@ -17,10 +20,21 @@ use crate::{
         },
         QDesc,
         QToken,
+        QueueInfo,
+        Readiness,
         SharedDemiRuntime,
     },
 };
-use ::std::net::SocketAddr;
+use ::std::{
+    net::{
+        SocketAddr,
+        SocketAddrV4,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
 
 #[cfg(feature = "catcollar-libos")]
 use crate::catcollar::CatcollarLibOS;
@@ -112,21 +126,8 @@ impl NetworkLibOS {
     }
 
     /// Marks a socket as a passive one.
-    pub fn listen(&mut self, sockqd: QDesc, mut backlog: usize) -> Result<(), Fail> {
-        // Truncate backlog length.
-        if backlog > SOMAXCONN as usize {
-            let cause: String = format!(
-                "backlog length is too large, truncating (qd={:?}, backlog={:?})",
-                sockqd, backlog
-            );
-            debug!("listen(): {}", &cause);
-            backlog = SOMAXCONN as usize;
-        }
-
-        // Round up backlog length.
-        if backlog == 0 {
-            backlog = 1;
-        }
+    pub fn listen(&mut self, sockqd: QDesc, backlog: usize) -> Result<(), Fail> {
+        let backlog: usize = Self::clamp_backlog(sockqd, backlog);
 
         match self {
             #[cfg(feature = "catpowder-libos")]
@@ -205,6 +206,555 @@ impl NetworkLibOS {
         }
     }
 
+    /// Asynchronously closes a socket, lingering until the peer acknowledges our FIN or until `timeout` expires.
+    #[allow(unused_variables)]
+    pub fn async_close_with_linger(&mut self, sockqd: QDesc, timeout: Duration) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "async_close_with_linger() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "async_close_with_linger() is not supported on catnap",
+            )),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.async_close_with_linger(sockqd, timeout),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "async_close_with_linger() is not supported on catnip",
+            )),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "async_close_with_linger() is not supported on catloop",
+            )),
+        }
+    }
+
+    /// Changes the backlog of a passive socket.
+    pub fn set_backlog(&mut self, sockqd: QDesc, backlog: usize) -> Result<(), Fail> {
+        let backlog: usize = Self::clamp_backlog(sockqd, backlog);
+
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_backlog(sockqd, backlog),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_backlog() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.set_backlog(sockqd, backlog),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_backlog(sockqd, backlog),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_backlog() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Turns quick ACK mode on or off for a TCP socket.
+    pub fn set_quickack(&mut self, sockqd: QDesc, enable: bool) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_quickack(sockqd, enable),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_quickack() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.set_quickack(sockqd, enable),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_quickack(sockqd, enable),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_quickack() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Corks or uncorks a TCP socket.
+    pub fn cork(&mut self, sockqd: QDesc, enabled: bool) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.cork(sockqd, enabled),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "cork() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.cork(sockqd, enabled),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.cork(sockqd, enabled),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "cork() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Registers a group of receive buffers that the kernel picks from when data arrives.
+    #[allow(unused_variables)]
+    pub fn register_recv_buffer_group(&mut self, count: u16, size: u16) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "register_recv_buffer_group() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "register_recv_buffer_group() is not supported on catnap",
+            )),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.register_recv_buffer_group(count, size),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "register_recv_buffer_group() is not supported on catnip",
+            )),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "register_recv_buffer_group() is not supported on catloop",
+            )),
+        }
+    }
+
+    /// Sets up a pool of receive chunks that pops draw their buffers from.
+    #[allow(unused_variables)]
+    pub fn set_recv_buffer_pool(&mut self, chunk_size: u16, count: usize) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_recv_buffer_pool() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_recv_buffer_pool() is not supported on catnap",
+            )),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.set_recv_buffer_pool(chunk_size, count),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_recv_buffer_pool() is not supported on catnip",
+            )),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_recv_buffer_pool() is not supported on catloop",
+            )),
+        }
+    }
+
+    /// Caps the send rate of a queue. A zero rate removes the limit.
+    #[allow(unused_variables)]
+    pub fn set_rate_limit(&mut self, qd: QDesc, bytes_per_sec: u64) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_rate_limit() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_rate_limit() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.set_rate_limit(qd, bytes_per_sec),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_rate_limit() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_rate_limit() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Turns on continuous receive mode on a queue, with a backlog of up to `count` receive buffers.
+    #[allow(unused_variables)]
+    pub fn set_continuous_recv(&mut self, qd: QDesc, count: usize) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_continuous_recv() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_continuous_recv() is not supported on catnap",
+            )),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.set_continuous_recv(qd, count),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_continuous_recv() is not supported on catnip",
+            )),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_continuous_recv() is not supported on catloop",
+            )),
+        }
+    }
+
+    /// Sets a socket to multiplex its connections over shared transport channels.
+    #[allow(unused_variables)]
+    pub fn set_multiplexed(&mut self, qd: QDesc) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_multiplexed() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_multiplexed() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_multiplexed() is not supported on catcollar",
+            )),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_multiplexed() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos } => libos.set_multiplexed(qd),
+        }
+    }
+
+    /// Sets the receive low-water mark of a socket.
+    #[allow(unused_variables)]
+    pub fn set_rcvlowat(&mut self, sockqd: QDesc, bytes: usize) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_rcvlowat(sockqd, bytes),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_rcvlowat() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.set_rcvlowat(sockqd, bytes),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_rcvlowat(sockqd, bytes),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_rcvlowat() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Sets the priority of the packets sent on a socket.
+    #[allow(unused_variables)]
+    pub fn set_priority(&mut self, sockqd: QDesc, priority: u32) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_priority() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_priority() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.set_priority(sockqd, priority),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_priority() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_priority() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Gets the priority of the packets sent on a socket.
+    #[allow(unused_variables)]
+    pub fn get_priority(&self, sockqd: QDesc) -> Result<u32, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "get_priority() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "get_priority() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.get_priority(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "get_priority() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "get_priority() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Sets the key that signs the segments that a socket exchanges with a remote address (TCP MD5 signature option).
+    #[allow(unused_variables)]
+    pub fn set_tcp_md5sig(&mut self, sockqd: QDesc, remote: SocketAddrV4, key: &[u8]) -> Result<(), Fail> {
+        match self {
+            #[cfg(all(feature = "catpowder-libos", feature = "tcp-md5"))]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.set_tcp_md5sig(sockqd, remote, key),
+            #[cfg(all(feature = "catpowder-libos", not(feature = "tcp-md5")))]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_tcp_md5sig() requires the tcp-md5 feature on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_tcp_md5sig() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.set_tcp_md5sig(sockqd, remote, key),
+            #[cfg(all(feature = "catnip-libos", feature = "tcp-md5"))]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.set_tcp_md5sig(sockqd, remote, key),
+            #[cfg(all(feature = "catnip-libos", not(feature = "tcp-md5")))]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "set_tcp_md5sig() requires the tcp-md5 feature on catnip",
+            )),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "set_tcp_md5sig() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Binds a socket to a network interface.
+    #[allow(unused_variables)]
+    pub fn bind_to_device(&mut self, sockqd: QDesc, ifname: &str) -> Result<(), Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "bind_to_device() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "bind_to_device() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.bind_to_device(sockqd, ifname),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "bind_to_device() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "bind_to_device() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Gets the maximum segment size of the connection of a TCP socket.
+    #[allow(unused_variables)]
+    pub fn connection_mss(&self, sockqd: QDesc) -> Result<usize, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.connection_mss(sockqd),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "connection_mss() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.connection_mss(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.connection_mss(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "connection_mss() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Gets the local address of a socket.
+    #[allow(unused_variables)]
+    pub fn getsockname(&self, sockqd: QDesc) -> Result<SocketAddrV4, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "getsockname() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "getsockname() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.getsockname(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "getsockname() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "getsockname() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Gets the name of the network interface that a socket is bound to.
+    #[allow(unused_variables)]
+    pub fn get_bound_device(&self, sockqd: QDesc) -> Result<String, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "get_bound_device() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "get_bound_device() is not supported on catnap",
+            )),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.get_bound_device(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "get_bound_device() is not supported on catnip",
+            )),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "get_bound_device() is not supported on catloop",
+            )),
+        }
+    }
+
+    /// Pops a batch of datagrams from a UDP socket.
+    #[allow(unused_variables)]
+    pub fn pop_batch(&mut self, sockqd: QDesc, max: usize) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pop_batch() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pop_batch() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.pop_batch(sockqd, max),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pop_batch() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pop_batch() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Moves data from one TCP socket to another.
+    #[allow(unused_variables)]
+    pub fn splice(&mut self, src_sockqd: QDesc, dst_sockqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "splice() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "splice() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.splice(src_sockqd, dst_sockqd, size),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "splice() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "splice() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Streams data from a socket into a callback.
+    #[allow(unused_variables)]
+    pub fn pop_stream<F: FnMut(&[u8]) -> bool + 'static>(&mut self, sockqd: QDesc, f: F) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pop_stream() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pop_stream() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.pop_stream(sockqd, f),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pop_stream() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pop_stream() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Retrieves the oldest asynchronous error reported on a UDP socket.
+    pub fn poll_error(&mut self, sockqd: QDesc) -> Result<Option<Fail>, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.poll_error(sockqd),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "poll_error() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.poll_error(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.poll_error(sockqd),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "poll_error() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Truncates a backlog length to the range accepted by listen().
+    fn clamp_backlog(sockqd: QDesc, mut backlog: usize) -> usize {
+        // Truncate backlog length.
+        if backlog > SOMAXCONN as usize {
+            let cause: String = format!(
+                "backlog length is too large, truncating (qd={:?}, backlog={:?})",
+                sockqd, backlog
+            );
+            debug!("listen(): {}", &cause);
+            backlog = SOMAXCONN as usize;
+        }
+
+        // Round up backlog length.
+        if backlog == 0 {
+            backlog = 1;
+        }
+
+        backlog
+    }
+
     /// Pushes a scatter-gather array to a TCP socket.
     pub fn push(&mut self, sockqd: QDesc, sga: &demi_sgarray_t) -> Result<QToken, Fail> {
         match self {
@@ -221,6 +771,56 @@ impl NetworkLibOS {
         }
     }
 
+    /// Pushes a chain of buffers to a TCP socket.
+    #[allow(unused_variables)]
+    pub fn push_chain(&mut self, sockqd: QDesc, chain: DemiBufferChain) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "push_chain() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "push_chain() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.push_chain(sockqd, chain),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "push_chain() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "push_chain() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Pushes a scatter-gather array to a TCP socket, giving up if it cannot be sent within a timeout.
+    #[allow(unused_variables)]
+    pub fn push_timeout(&mut self, sockqd: QDesc, sga: &demi_sgarray_t, timeout: Duration) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "push_timeout() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "push_timeout() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.push_timeout(sockqd, sga, timeout),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "push_timeout() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "push_timeout() is not supported on catloop"))
+            },
+        }
+    }
+
     /// Pushes a scatter-gather array to a UDP socket.
     pub fn pushto(&mut self, sockqd: QDesc, sga: &demi_sgarray_t, to: SocketAddr) -> Result<QToken, Fail> {
         match self {
@@ -237,6 +837,31 @@ impl NetworkLibOS {
         }
     }
 
+    /// Pushes a batch of datagrams, each to its own destination, through a UDP socket.
+    #[allow(unused_variables)]
+    pub fn pushto_batch(&mut self, sockqd: QDesc, items: &[(demi_sgarray_t, SocketAddr)]) -> Result<Vec<QToken>, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pushto_batch() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pushto_batch() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.pushto_batch(sockqd, items),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pushto_batch() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "pushto_batch() is not supported on catloop"))
+            },
+        }
+    }
+
     /// Pops data from a socket.
     pub fn pop(&mut self, sockqd: QDesc, size: Option<usize>) -> Result<QToken, Fail> {
         match self {
@@ -245,7 +870,7 @@ impl NetworkLibOS {
             #[cfg(all(feature = "catnap-libos"))]
             NetworkLibOS::Catnap { runtime: _, libos } => libos.pop(sockqd, size),
             #[cfg(feature = "catcollar-libos")]
-            NetworkLibOS::Catcollar { runtime: _, libos } => libos.pop(sockqd, size),
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.pop(sockqd, size, None),
             #[cfg(feature = "catnip-libos")]
             NetworkLibOS::Catnip { runtime: _, libos } => libos.pop(sockqd, size),
             #[cfg(feature = "catloop-libos")]
@@ -253,6 +878,125 @@ impl NetworkLibOS {
         }
     }
 
+    /// Pops data from a socket, giving up once the clock of the runtime reaches a deadline.
+    #[allow(unused_variables)]
+    pub fn pop_with_deadline(&mut self, sockqd: QDesc, size: Option<usize>, deadline: Instant) -> Result<QToken, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "pop_with_deadline() is not supported on catpowder",
+            )),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "pop_with_deadline() is not supported on catnap",
+            )),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.pop(sockqd, size, Some(deadline)),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "pop_with_deadline() is not supported on catnip",
+            )),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => Err(Fail::new(
+                libc::ENOTSUP,
+                "pop_with_deadline() is not supported on catloop",
+            )),
+        }
+    }
+
+    /// Checks which operations would make progress right away on a socket.
+    #[allow(unused_variables)]
+    pub fn readiness(&self, sockqd: QDesc) -> Result<Readiness, Fail> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "readiness() is not supported on catpowder"))
+            },
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "readiness() is not supported on catnap"))
+            },
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.readiness(sockqd),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "readiness() is not supported on catnip"))
+            },
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => {
+                Err(Fail::new(libc::ENOTSUP, "readiness() is not supported on catloop"))
+            },
+        }
+    }
+
+    /// Lists all sockets that are currently open.
+    pub fn list_queues(&self) -> Vec<QueueInfo> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos } => libos.list_queues(),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos } => libos.list_queues(),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.list_queues(),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos } => libos.list_queues(),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos } => libos.list_queues(),
+        }
+    }
+
+    /// Sets the correlation ID that is attached to the task IDs of the operations that are issued from now on, and
+    /// returns the previous one.
+    pub fn set_trace_context(&mut self, trace_context: Option<&str>) -> Option<String> {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime, libos: _ } => runtime.set_trace_context(trace_context),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime, libos: _ } => runtime.set_trace_context(trace_context),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.set_trace_context(trace_context),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime, libos: _ } => runtime.set_trace_context(trace_context),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime, libos: _ } => runtime.set_trace_context(trace_context),
+        }
+    }
+
+    /// Enables or disables the manual clock of the runtime.
+    pub fn set_manual_clock(&mut self, enabled: bool) {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime, libos: _ } => runtime.set_manual_clock(enabled),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime, libos: _ } => runtime.set_manual_clock(enabled),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.set_manual_clock(enabled),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime, libos: _ } => runtime.set_manual_clock(enabled),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime, libos: _ } => runtime.set_manual_clock(enabled),
+        }
+    }
+
+    /// Moves the clock of the runtime forward by `duration`.
+    pub fn advance_clock_by(&mut self, duration: Duration) {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime, libos: _ } => runtime.advance_clock_by(duration),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime, libos: _ } => runtime.advance_clock_by(duration),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.advance_clock_by(duration),
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime, libos: _ } => runtime.advance_clock_by(duration),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime, libos: _ } => runtime.advance_clock_by(duration),
+        }
+    }
+
     /// Waits for any operation in an I/O queue.
     pub fn poll(&mut self) {
         match self {
@@ -261,7 +1005,10 @@ impl NetworkLibOS {
             #[cfg(all(feature = "catnap-libos"))]
             NetworkLibOS::Catnap { runtime, libos: _ } => runtime.poll_and_advance_clock(),
             #[cfg(feature = "catcollar-libos")]
-            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.poll_and_advance_clock(),
+            NetworkLibOS::Catcollar { runtime, libos } => {
+                libos.reap_completions();
+                runtime.poll_and_advance_clock()
+            },
             #[cfg(feature = "catnip-libos")]
             NetworkLibOS::Catnip { runtime, libos: _ } => runtime.poll_and_advance_clock(),
             #[cfg(feature = "catloop-libos")]
@@ -269,6 +1016,28 @@ impl NetworkLibOS {
         }
     }
 
+    /// Sleeps for up to `timeout` if no coroutine is ready to run, until some operation may make progress. LibOSes that
+    /// cannot sleep on their I/O return right away.
+    #[allow(unused_variables)]
+    pub fn idle(&mut self, timeout: Option<Duration>) {
+        match self {
+            #[cfg(feature = "catpowder-libos")]
+            NetworkLibOS::Catpowder { runtime: _, libos: _ } => (),
+            #[cfg(all(feature = "catnap-libos"))]
+            NetworkLibOS::Catnap { runtime: _, libos: _ } => (),
+            #[cfg(feature = "catcollar-libos")]
+            NetworkLibOS::Catcollar { runtime, libos } => {
+                if !runtime.has_ready_coroutines() {
+                    libos.wait_completions(timeout);
+                }
+            },
+            #[cfg(feature = "catnip-libos")]
+            NetworkLibOS::Catnip { runtime: _, libos: _ } => (),
+            #[cfg(feature = "catloop-libos")]
+            NetworkLibOS::Catloop { runtime: _, libos: _ } => (),
+        }
+    }
+
     /// Waits for any operation in an I/O queue.
     pub fn from_task_id(&mut self, qt: QToken) -> Result<TaskHandle, Fail> {
         match self {
@@ -314,7 +1083,7 @@ impl NetworkLibOS {
             #[cfg(all(feature = "catnap-libos"))]
             NetworkLibOS::Catnap { runtime, libos: _ } => runtime.sgaalloc(size),
             #[cfg(feature = "catcollar-libos")]
-            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.sgaalloc(size),
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.sgaalloc(size),
             #[cfg(feature = "catnip-libos")]
             // TODO: Move this over to the transport once we set that up.
             // FIXME: https://github.com/microsoft/demikernel/issues/1057
@@ -332,7 +1101,7 @@ impl NetworkLibOS {
             #[cfg(all(feature = "catnap-libos"))]
             NetworkLibOS::Catnap { runtime, libos: _ } => runtime.sgafree(sga),
             #[cfg(feature = "catcollar-libos")]
-            NetworkLibOS::Catcollar { runtime, libos: _ } => runtime.sgafree(sga),
+            NetworkLibOS::Catcollar { runtime: _, libos } => libos.sgafree(sga),
             #[cfg(feature = "catnip-libos")]
             NetworkLibOS::Catnip { runtime, libos: _ } => runtime.sgafree(sga),
             #[cfg(feature = "catloop-libos")]
@@ -12,6 +12,13 @@ use crate::{
             EtherType2,
             Ethernet2Header,
         },
+        tcp::{
+            queue::SharedTcpQueue,
+            segment::TcpSegment,
+            ConnEvent,
+            ConnMetrics,
+            ListenerStats,
+        },
         udp::queue::SharedUdpQueue,
         Peer,
     },
@@ -24,23 +31,32 @@ use crate::{
         fail::Fail,
         limits,
         memory::DemiBuffer,
+        metrics::MetricsText,
         network::{
             config::{
                 ArpConfig,
                 TcpConfig,
                 UdpConfig,
             },
+            socket::state::SocketState,
+            tap::{
+                Direction,
+                SharedPacketTap,
+                TapNetworkRuntime,
+            },
             types::MacAddress,
             unwrap_socketaddr,
             NetworkRuntime,
         },
         queue::{
+            NetworkQueue,
             Operation,
             OperationResult,
             OperationTask,
             QDesc,
             QToken,
             QType,
+            QueueInfo,
         },
         scheduler::{
             TaskHandle,
@@ -63,11 +79,15 @@ use ::std::{
         DerefMut,
     },
     pin::Pin,
+    time::Duration,
 };
 
 #[cfg(feature = "profiler")]
 use crate::timer;
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::TCP_MD5_MAX_KEY_LEN;
+
 //======================================================================================================================
 // Exports
 //======================================================================================================================
@@ -94,6 +114,7 @@ pub struct InetStack<const N: usize> {
     ipv4: Peer<N>,
     runtime: SharedDemiRuntime,
     transport: SharedBox<dyn NetworkRuntime<N>>,
+    tap: SharedPacketTap,
     local_link_addr: MacAddress,
 }
 
@@ -115,6 +136,9 @@ impl<const N: usize> SharedInetStack<N> {
         rng_seed: [u8; 32],
         arp_config: ArpConfig,
     ) -> Result<Self, Fail> {
+        let tap: SharedPacketTap = SharedPacketTap::default();
+        let transport: SharedBox<dyn NetworkRuntime<N>> =
+            SharedBox::<dyn NetworkRuntime<N>>::new(Box::new(TapNetworkRuntime::new(transport, tap.clone())));
         let arp: SharedArpPeer<N> = SharedArpPeer::new(
             runtime.clone(),
             transport.clone(),
@@ -137,6 +161,7 @@ impl<const N: usize> SharedInetStack<N> {
             ipv4,
             runtime: runtime.clone(),
             transport,
+            tap,
             local_link_addr,
         }));
         let yielder: Yielder = Yielder::new();
@@ -235,6 +260,96 @@ impl<const N: usize> SharedInetStack<N> {
         }
     }
 
+    ///
+    /// **Brief**
+    ///
+    /// Changes the maximum length of the queue of pending connections of the
+    /// listening socket referred to by `qd`, without tearing it down.
+    /// Connections that are already pending are kept even if the backlog
+    /// shrinks.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn set_backlog(&mut self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
+        trace!("set_backlog() qd={:?}, backlog={:?}", qd, backlog);
+
+        if backlog == 0 {
+            return Err(Fail::new(libc::EINVAL, "invalid backlog length"));
+        }
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_backlog(qd, backlog),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Sets the maximum time that connections may wait to be accepted on the
+    /// listening socket referred to by `qd`. Connections that wait for longer
+    /// are reset and dropped. `None` lets connections wait for as long as it
+    /// takes.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn set_ready_ttl(&mut self, qd: QDesc, ttl: Option<Duration>) -> Result<(), Fail> {
+        trace!("set_ready_ttl() qd={:?} ttl={:?}", qd, ttl);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.set_ready_ttl(qd, ttl),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Pauses accepting connections on the listening socket referred to by
+    /// `qd`, without closing it. Connections that are already pending stay
+    /// queued and new connection requests are dropped, so that peers retry
+    /// them once accepting is resumed.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn pause_accept(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("pause_accept() qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.pause_accept(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Resumes accepting connections on the listening socket referred to by
+    /// `qd`.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn resume_accept(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("resume_accept() qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.resume_accept(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
     ///
     /// **Brief**
     ///
@@ -282,6 +397,46 @@ impl<const N: usize> SharedInetStack<N> {
         }
     }
 
+    ///
+    /// **Brief**
+    ///
+    /// Waits for the connection of the socket referred to by `qd` to be
+    /// established, that is, for the three-way handshake to complete.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, a queue token is returned. This token
+    /// completes with a connect result once the connection is established, or
+    /// with a failure if the handshake fails. Upon failure, `Fail` is returned
+    /// instead.
+    ///
+    pub fn wait_established(&mut self, qd: QDesc) -> Result<QToken, Fail> {
+        trace!("wait_established(): qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.wait_established(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Resolves the link address of `ip` ahead of time, so that a subsequent
+    /// connect to that address does not have to wait for an ARP round trip.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, a queue token is returned. This token
+    /// completes with a connect result once the link address is known. The
+    /// queue descriptor in that result is not meaningful. Upon failure, `Fail`
+    /// is returned instead.
+    ///
+    pub fn resolve_arp(&mut self, ip: Ipv4Addr) -> Result<QToken, Fail> {
+        trace!("resolve_arp(): ip={:?}", ip);
+        self.arp.resolve(ip)
+    }
+
     ///
     /// **Brief**
     ///
@@ -347,6 +502,14 @@ impl<const N: usize> SharedInetStack<N> {
         }
     }
 
+    /// Pushes a buffer of urgent data to a TCP socket.
+    pub fn do_push_urgent(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.push_urgent(qd, buf),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
     /// Pushes raw data to a TCP socket.
     /// TODO: Move this function to demikernel repo once we have a common buffer representation across all libOSes.
     pub fn push2(&mut self, qd: QDesc, data: &[u8]) -> Result<QToken, Fail> {
@@ -417,6 +580,32 @@ impl<const N: usize> SharedInetStack<N> {
         }
     }
 
+    /// Reads data from the TCP connection represented by `qd` in place. The next buffer of received data is handed to
+    /// `f` as a borrowed slice and it is released right after `f` returns, so no copy or scatter-gather array is
+    /// involved. This function does not block: if no data is ready, it fails with `EAGAIN`.
+    ///
+    /// Upon success, the number of bytes handed to `f` is returned. Zero bytes means that the peer closed the
+    /// connection.
+    pub fn pop_with<F: FnOnce(&[u8])>(&mut self, qd: QDesc, f: F) -> Result<usize, Fail> {
+        trace!("pop_with() qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.pop_with(qd, f),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Create a pop request to read urgent data from the TCP connection represented by `qd`. Urgent data is delivered
+    /// separately from, and never appears in, the in-band stream read by [pop](Self::pop).
+    pub fn pop_urgent(&mut self, qd: QDesc) -> Result<QToken, Fail> {
+        trace!("pop_urgent() qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.pop_urgent(qd),
+            _ => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
     /// Waits for an operation to complete.
     /// This function is deprecated, do not use.
     /// FIXME: https://github.com/microsoft/demikernel/issues/889
@@ -463,6 +652,195 @@ impl<const N: usize> SharedInetStack<N> {
         }
     }
 
+    /// Lists all I/O queues that are currently open in the network stack. This function is read-only and intended for
+    /// diagnostics.
+    pub fn list_queues(&self) -> Vec<QueueInfo> {
+        let mut queues: Vec<QueueInfo> = Vec::new();
+        for qd in self.runtime.get_qtable().get_qds() {
+            match self.runtime.get_queue_type(&qd) {
+                Ok(QType::TcpSocket) => {
+                    if let Ok(queue) = self.runtime.get_shared_queue::<SharedTcpQueue<N>>(&qd) {
+                        queues.push(QueueInfo {
+                            qd,
+                            qtype: QType::TcpSocket,
+                            local: queue.local(),
+                            remote: queue.remote(),
+                            state: Some(queue.get_state()),
+                        });
+                    }
+                },
+                Ok(QType::UdpSocket) => {
+                    if let Ok(queue) = self.runtime.get_shared_queue::<SharedUdpQueue<N>>(&qd) {
+                        let state: SocketState = if queue.is_bound() {
+                            SocketState::Bound
+                        } else {
+                            SocketState::Unbound
+                        };
+                        queues.push(QueueInfo {
+                            qd,
+                            qtype: QType::UdpSocket,
+                            local: queue.local(),
+                            remote: queue.remote(),
+                            state: Some(state),
+                        });
+                    }
+                },
+                _ => continue,
+            }
+        }
+        queues
+    }
+
+    /// Renders the metrics that are currently tracked, in the Prometheus text exposition format: the depth of the
+    /// scheduler, the memory held by the buffer pool, and the state of every listening TCP socket and established TCP
+    /// connection. This function is read-only and intended for scraping by standard monitoring tools.
+    pub fn metrics_text(&self) -> String {
+        let mut metrics: MetricsText = MetricsText::default();
+        self.runtime.write_metrics(&mut metrics);
+        self.ipv4.tcp.write_metrics(&mut metrics);
+        metrics.render()
+    }
+
+    /// Returns a snapshot of the listening TCP socket associated with `qd`: how many connections it accepted, how many
+    /// connection requests it refused because its backlog was full, and how many connections are pending acceptance.
+    /// This function is read-only and intended for tuning the size of the backlog.
+    pub fn listener_stats(&self, qd: QDesc) -> Result<ListenerStats, Fail> {
+        match self.runtime.get_queue_type(&qd)? {
+            QType::TcpSocket => self.ipv4.tcp.listener_stats(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Returns a snapshot of the round-trip time and congestion control state of the established TCP connection
+    /// associated with `qd`. This function is read-only and intended for diagnostics.
+    pub fn connection_metrics(&self, qd: QDesc) -> Result<ConnMetrics, Fail> {
+        self.ipv4.tcp.connection_metrics(qd)
+    }
+
+    /// Returns the maximum segment size that the established TCP connection associated with `qd` sends, as negotiated
+    /// with the peer and less the room taken by options. Pushing multiples of it avoids partial segments. This function
+    /// is read-only.
+    pub fn connection_mss(&self, qd: QDesc) -> Result<usize, Fail> {
+        self.ipv4.tcp.remote_mss(qd)
+    }
+
+    /// Formats the full state of the established TCP connection associated with `qd` into a human-readable report:
+    /// sequence numbers, windows, timers, and the unacknowledged, unsent and out-of-order queues. This function is
+    /// read-only and intended for troubleshooting wedged connections.
+    pub fn dump_tcb(&self, qd: QDesc) -> Result<String, Fail> {
+        trace!("dump_tcb() qd={:?}", qd);
+        self.ipv4.tcp.dump_tcb(qd)
+    }
+
+    /// Returns the number of bytes pushed to the established TCP connection associated with `qd` that are waiting to be
+    /// sent or acknowledged by the peer. This function is read-only.
+    pub fn send_queue_len(&self, qd: QDesc) -> Result<usize, Fail> {
+        self.ipv4.tcp.send_queue_len(qd)
+    }
+
+    /// Turns quick ACK mode on or off for the established TCP connection associated with `qd`. While it is on, incoming
+    /// data is acknowledged right away instead of being delayed, which avoids stalls on request/response traffic.
+    pub fn set_quickack(&mut self, qd: QDesc, enable: bool) -> Result<(), Fail> {
+        trace!("set_quickack() qd={:?}, enable={:?}", qd, enable);
+        self.ipv4.tcp.set_quickack(qd, enable)
+    }
+
+    /// Acknowledges all data received so far on the established TCP connection associated with `qd` right away,
+    /// instead of waiting for the delayed ACK timer. This opens the window of the peer sooner, which helps when it is
+    /// window-limited.
+    pub fn flush_ack(&mut self, qd: QDesc) -> Result<(), Fail> {
+        trace!("flush_ack() qd={:?}", qd);
+        self.ipv4.tcp.flush_ack(qd)
+    }
+
+    /// Sets the receive low-water mark (SO_RCVLOWAT) of the established TCP connection associated with `qd`, so that pops
+    /// do not complete until at least `bytes` bytes are available, unless the peer closes the connection first.
+    pub fn set_rcvlowat(&mut self, qd: QDesc, bytes: usize) -> Result<(), Fail> {
+        trace!("set_rcvlowat() qd={:?}, bytes={:?}", qd, bytes);
+        self.ipv4.tcp.set_rcvlowat(qd, bytes)
+    }
+
+    /// Overrides the congestion window of the established TCP connection associated with `qd` with `cwnd` bytes. The
+    /// next flight of data is limited by this value, and the congestion control algorithm carries on from it. Algorithms
+    /// that never adjust the congestion window (e.g. [crate::runtime::network::config::CongestionControlType::None])
+    /// keep the override for good. This is meant for reproducing congestion scenarios in experiments, not for
+    /// production use.
+    #[cfg(feature = "experimental")]
+    pub fn set_cwnd(&mut self, qd: QDesc, cwnd: usize) -> Result<(), Fail> {
+        trace!("set_cwnd() qd={:?}, cwnd={:?}", qd, cwnd);
+        let cwnd: u32 = match u32::try_from(cwnd) {
+            Ok(cwnd) if cwnd > 0 => cwnd,
+            _ => {
+                let cause: String = format!("invalid congestion window (cwnd={:?})", cwnd);
+                error!("set_cwnd(): {}", &cause);
+                return Err(Fail::new(libc::EINVAL, &cause));
+            },
+        };
+        self.ipv4.tcp.set_cwnd(qd, cwnd)
+    }
+
+    /// Returns the congestion window of the established TCP connection associated with `qd` (in bytes).
+    #[cfg(feature = "experimental")]
+    pub fn get_cwnd(&self, qd: QDesc) -> Result<usize, Fail> {
+        trace!("get_cwnd() qd={:?}", qd);
+        Ok(self.ipv4.tcp.get_cwnd(qd)? as usize)
+    }
+
+    /// Sets the key that signs segments that the TCP socket associated with `qd` exchanges with `remote`, with the MD5
+    /// signature option (see RFC 2385). Only the address of `remote` is taken into account, and an empty key stops
+    /// signing segments. Keys must be set before the socket connects or listens.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_tcp_md5sig(&mut self, qd: QDesc, remote: SocketAddrV4, key: &[u8]) -> Result<(), Fail> {
+        trace!(
+            "set_tcp_md5sig() qd={:?}, remote={:?}, key_len={:?}",
+            qd,
+            remote,
+            key.len()
+        );
+        if key.len() > TCP_MD5_MAX_KEY_LEN {
+            let cause: String = format!("key is too long (key_len={:?})", key.len());
+            error!("set_tcp_md5sig(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        self.ipv4.tcp.set_md5_key(qd, *remote.ip(), key)
+    }
+
+    /// Corks or uncorks the established TCP connection associated with `qd`. While it is corked, small pushes are
+    /// coalesced and held back, and they are sent in as few segments as possible once it is uncorked.
+    pub fn cork(&mut self, qd: QDesc, enabled: bool) -> Result<(), Fail> {
+        trace!("cork() qd={:?}, enabled={:?}", qd, enabled);
+        self.ipv4.tcp.set_cork(qd, enabled)
+    }
+
+    /// Retrieves the oldest asynchronous error reported on the UDP socket represented by `qd`, such as a port or host
+    /// unreachable message sent back by the network. If no error is pending, `None` is returned.
+    pub fn poll_error(&mut self, qd: QDesc) -> Result<Option<Fail>, Fail> {
+        trace!("poll_error() qd={:?}", qd);
+
+        match self.runtime.get_queue_type(&qd)? {
+            QType::UdpSocket => self.ipv4.udp.poll_error(qd),
+            _ => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+        }
+    }
+
+    /// Installs `f` as the observer of TCP connection events. The observer is invoked when a connection is established
+    /// and when it is closed or reset.
+    pub fn set_connection_observer(&mut self, f: Box<dyn Fn(ConnEvent)>) {
+        self.ipv4.tcp.set_connection_observer(f)
+    }
+
+    /// Installs `f` as the packet tap of the stack. The tap is shown a copy of every Ethernet frame that is transmitted or
+    /// received, which comes in handy to capture traffic for debugging without any external tools.
+    pub fn set_packet_tap(&mut self, f: Box<dyn FnMut(Direction, &[u8])>) {
+        self.tap.set(f)
+    }
+
+    /// Installs `f` as the transmit filter of the stack. The filter may rewrite every outgoing TCP segment (e.g., its
+    /// addresses, for transparent proxying) right before it is transmitted. Checksums are computed afterwards.
+    pub fn set_transmit_filter(&mut self, f: Box<dyn FnMut(&mut TcpSegment)>) {
+        self.ipv4.tcp.set_transmit_filter(f)
+    }
+
     /// Given a handle representing a task in our scheduler. Return the results of this future
     /// and the file descriptor for this connection.
     ///
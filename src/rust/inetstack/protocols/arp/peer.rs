@@ -23,8 +23,15 @@ use crate::{
             types::MacAddress,
             NetworkRuntime,
         },
-        scheduler::Yielder,
+        scheduler::{
+            TaskHandle,
+            Yielder,
+        },
         timer::UtilityMethods,
+        Operation,
+        OperationResult,
+        QDesc,
+        QToken,
         SharedBox,
         SharedDemiRuntime,
         SharedObject,
@@ -50,6 +57,7 @@ use ::std::{
         Deref,
         DerefMut,
     },
+    pin::Pin,
     time::Duration,
 };
 
@@ -303,6 +311,24 @@ impl<const N: usize> SharedArpPeer<N> {
         result
     }
 
+    /// Issues an ARP query for `ipv4_addr` in the background, so that the link address is already cached by the time
+    /// it is needed (e.g. by a subsequent connect). The returned queue token completes once the link address is known.
+    pub fn resolve(&mut self, ipv4_addr: Ipv4Addr) -> Result<QToken, Fail> {
+        let task_name: String = format!("Inetstack::arp::resolve for {:?}", ipv4_addr);
+        let mut peer: SharedArpPeer<N> = self.clone();
+        let coroutine: Pin<Box<Operation>> = Box::pin(async move {
+            // This operation is not associated with any queue.
+            let qd: QDesc = QDesc::from(QDesc::MAX);
+            let yielder: Yielder = Yielder::new();
+            match peer.query(ipv4_addr, &yielder).await {
+                Ok(_) => (qd, OperationResult::Connect),
+                Err(e) => (qd, OperationResult::Failed(e)),
+            }
+        });
+        let handle: TaskHandle = self.runtime.insert_coroutine(&task_name, coroutine)?;
+        Ok(handle.get_task_id().into())
+    }
+
     #[cfg(test)]
     pub fn export_cache(&self) -> HashMap<Ipv4Addr, MacAddress> {
         self.cache.export()
@@ -7,15 +7,23 @@ use super::packet::{
 };
 use crate::{
     inetstack::{
-        protocols::ethernet2::Ethernet2Header,
+        protocols::ethernet2::{
+            EtherType2,
+            Ethernet2Header,
+        },
         test_helpers::{
             self,
             SharedEngine,
         },
     },
-    runtime::network::{
-        consts::RECEIVE_BATCH_SIZE,
-        types::MacAddress,
+    runtime::{
+        network::{
+            consts::RECEIVE_BATCH_SIZE,
+            types::MacAddress,
+        },
+        OperationResult,
+        QDesc,
+        QToken,
     },
 };
 use ::anyhow::Result;
@@ -29,6 +37,7 @@ use ::futures::{
 use ::libc::ETIMEDOUT;
 use ::std::{
     future::Future,
+    net::SocketAddrV4,
     task::Poll,
     time::{
         Duration,
@@ -210,3 +219,45 @@ fn no_reply() -> Result<()> {
         _ => anyhow::bail!("poll should have succeeded"),
     }
 }
+
+/// Tests that resolving an address ahead of time spares a subsequent connect from issuing an ARP query.
+#[test]
+fn resolve_before_connect() -> Result<()> {
+    let now = Instant::now();
+    let mut alice: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice(now);
+    let mut carrie: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_carrie(now);
+
+    // Resolve carrie's link address.
+    let qt: QToken = alice.arp_resolve(test_helpers::CARRIE_IPV4)?;
+    alice.get_test_rig().poll_scheduler();
+    let request = alice.get_test_rig().pop_frame();
+    carrie.receive(request)?;
+    carrie.advance_clock(now);
+    let reply = carrie.get_test_rig().pop_frame();
+    alice.receive(reply)?;
+    alice.get_test_rig().poll_scheduler();
+    match alice
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Connect)) => {},
+        result => anyhow::bail!("resolve should have completed, instead returned: {:?}", result),
+    };
+    let cache = alice.export_arp_cache();
+    crate::ensure_eq!(cache.get(&test_helpers::CARRIE_IPV4), Some(&test_helpers::CARRIE_MAC));
+
+    // The connect should go straight to sending a SYN.
+    let qd: QDesc = alice.tcp_socket()?;
+    let _qt: QToken = alice.tcp_connect(qd, SocketAddrV4::new(test_helpers::CARRIE_IPV4, 80))?;
+    alice.get_test_rig().poll_scheduler();
+    let frames = alice.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    for frame in frames {
+        let (header, _) = Ethernet2Header::parse(frame)?;
+        crate::ensure_eq!(header.ether_type(), EtherType2::Ipv4);
+    }
+
+    Ok(())
+}
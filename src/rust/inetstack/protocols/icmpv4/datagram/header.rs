@@ -88,4 +88,9 @@ impl Icmpv4Header {
     pub fn get_protocol(&self) -> Icmpv4Type2 {
         self.protocol
     }
+
+    /// Returns the code of the target ICMPv4 header.
+    pub fn get_code(&self) -> u8 {
+        self.code
+    }
 }
@@ -5,7 +5,13 @@ mod header;
 mod message;
 mod protocol;
 
-pub use self::protocol::ICMPV4_ECHO_REQUEST_MESSAGE_SIZE;
+pub use self::protocol::{
+    ICMPV4_ECHO_REQUEST_MESSAGE_SIZE,
+    ICMPV4_FRAGMENTATION_NEEDED,
+    ICMPV4_NET_UNREACHABLE,
+    ICMPV4_PORT_UNREACHABLE,
+    ICMPV4_PROTOCOL_UNREACHABLE,
+};
 pub use header::Icmpv4Header;
 pub use message::Icmpv4Message;
 pub use protocol::Icmpv4Type2;
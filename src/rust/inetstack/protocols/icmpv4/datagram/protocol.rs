@@ -11,6 +11,18 @@ use ::libc::EBADMSG;
 /// Size of ICMPv4 Echo Request Message (in bytes)
 pub const ICMPV4_ECHO_REQUEST_MESSAGE_SIZE: u16 = 56;
 
+/// Destination Unreachable Code: Network Unreachable
+pub const ICMPV4_NET_UNREACHABLE: u8 = 0;
+
+/// Destination Unreachable Code: Protocol Unreachable
+pub const ICMPV4_PROTOCOL_UNREACHABLE: u8 = 2;
+
+/// Destination Unreachable Code: Port Unreachable
+pub const ICMPV4_PORT_UNREACHABLE: u8 = 3;
+
+/// Destination Unreachable Code: Fragmentation Needed
+pub const ICMPV4_FRAGMENTATION_NEEDED: u8 = 4;
+
 //==============================================================================
 // Icmpv4Type2
 //==============================================================================
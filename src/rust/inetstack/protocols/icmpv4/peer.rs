@@ -13,9 +13,17 @@ use crate::{
             Icmpv4Header,
             Icmpv4Message,
             Icmpv4Type2,
+            ICMPV4_FRAGMENTATION_NEEDED,
+            ICMPV4_NET_UNREACHABLE,
+            ICMPV4_PORT_UNREACHABLE,
+            ICMPV4_PROTOCOL_UNREACHABLE,
         },
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            IPV4_HEADER_MIN_SIZE,
+        },
+        udp::SharedUdpPeer,
     },
     runtime::{
         fail::Fail,
@@ -52,8 +60,12 @@ use ::rand::{
     SeedableRng,
 };
 use ::std::{
+    cmp,
     collections::HashMap,
-    net::Ipv4Addr,
+    net::{
+        Ipv4Addr,
+        SocketAddrV4,
+    },
     num::Wrapping,
     ops::{
         Deref,
@@ -116,8 +128,11 @@ pub struct Icmpv4Peer<const N: usize> {
     /// Underlying ARP Peer
     arp: SharedArpPeer<N>,
 
+    /// UDP Peer, to which destination unreachable errors are reported.
+    udp: SharedUdpPeer<N>,
+
     /// Transmitter
-    tx: mpsc::UnboundedSender<(Ipv4Addr, u16, u16, DemiBuffer)>,
+    tx: mpsc::UnboundedSender<(Ipv4Addr, Icmpv4Header, DemiBuffer)>,
 
     /// Queue of Requests
     requests: ReqQueue,
@@ -137,11 +152,12 @@ impl<const N: usize> SharedIcmpv4Peer<N> {
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         arp: SharedArpPeer<N>,
+        udp: SharedUdpPeer<N>,
         rng_seed: [u8; 32],
     ) -> Result<Self, Fail> {
         let (tx, rx): (
-            mpsc::UnboundedSender<(Ipv4Addr, u16, u16, DemiBuffer)>,
-            mpsc::UnboundedReceiver<(Ipv4Addr, u16, u16, DemiBuffer)>,
+            mpsc::UnboundedSender<(Ipv4Addr, Icmpv4Header, DemiBuffer)>,
+            mpsc::UnboundedReceiver<(Ipv4Addr, Icmpv4Header, DemiBuffer)>,
         ) = mpsc::unbounded();
         runtime.insert_background_coroutine(
             "Inetstack::ICMP::background",
@@ -161,6 +177,7 @@ impl<const N: usize> SharedIcmpv4Peer<N> {
             local_link_addr,
             local_ipv4_addr,
             arp: arp.clone(),
+            udp,
             tx,
             requests,
             seq: Wrapping(0),
@@ -174,25 +191,25 @@ impl<const N: usize> SharedIcmpv4Peer<N> {
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         mut arp: SharedArpPeer<N>,
-        mut rx: mpsc::UnboundedReceiver<(Ipv4Addr, u16, u16, DemiBuffer)>,
+        mut rx: mpsc::UnboundedReceiver<(Ipv4Addr, Icmpv4Header, DemiBuffer)>,
     ) {
-        // Reply requests.
-        while let Some((dst_ipv4_addr, id, seq_num, data)) = rx.next().await {
+        // Reply requests and send error messages.
+        while let Some((dst_ipv4_addr, icmpv4_hdr, data)) = rx.next().await {
             debug!("initiating ARP query");
             let dst_link_addr: MacAddress = match arp.query(dst_ipv4_addr, &Yielder::new()).await {
                 Ok(dst_link_addr) => dst_link_addr,
                 Err(e) => {
-                    warn!("reply_to_ping({}, {}, {}) failed: {:?}", dst_ipv4_addr, id, seq_num, e);
+                    warn!("reply({}, {:?}) failed: {:?}", dst_ipv4_addr, icmpv4_hdr, e);
                     continue;
                 },
             };
             debug!("ARP query complete ({} -> {})", dst_ipv4_addr, dst_link_addr);
-            debug!("reply ping ({}, {}, {})", dst_ipv4_addr, id, seq_num);
+            debug!("reply ({}, {:?})", dst_ipv4_addr, icmpv4_hdr);
             // Send reply message.
             transport.transmit(Box::new(Icmpv4Message::new(
                 Ethernet2Header::new(dst_link_addr, local_link_addr, EtherType2::Ipv4),
                 Ipv4Header::new(local_ipv4_addr, dst_ipv4_addr, IpProtocol::ICMPv4),
-                Icmpv4Header::new(Icmpv4Type2::EchoReply { id, seq_num }, 0),
+                icmpv4_hdr,
                 data,
             )));
         }
@@ -204,8 +221,9 @@ impl<const N: usize> SharedIcmpv4Peer<N> {
         debug!("ICMPv4 received {:?}", icmpv4_hdr);
         match icmpv4_hdr.get_protocol() {
             Icmpv4Type2::EchoRequest { id, seq_num } => {
+                let reply: Icmpv4Header = Icmpv4Header::new(Icmpv4Type2::EchoReply { id, seq_num }, 0);
                 self.tx
-                    .unbounded_send((ipv4_header.get_src_addr(), id, seq_num, data))
+                    .unbounded_send((ipv4_header.get_src_addr(), reply, data))
                     .unwrap();
             },
             Icmpv4Type2::EchoReply { id, seq_num } => {
@@ -213,6 +231,7 @@ impl<const N: usize> SharedIcmpv4Peer<N> {
                     let _ = tx.send(());
                 }
             },
+            Icmpv4Type2::DestinationUnreachable => self.handle_destination_unreachable(icmpv4_hdr.get_code(), &data),
             _ => {
                 warn!("Unsupported ICMPv4 message: {:?}", icmpv4_hdr);
             },
@@ -220,6 +239,74 @@ impl<const N: usize> SharedIcmpv4Peer<N> {
         Ok(())
     }
 
+    /// Sends a port unreachable message in response to a datagram that was addressed to a closed port.
+    /// `leading_bytes` holds the first bytes of the payload of the offending datagram.
+    pub fn send_port_unreachable(
+        &mut self,
+        ipv4_header: &Ipv4Header,
+        payload_len: usize,
+        leading_bytes: &[u8],
+    ) -> Result<(), Fail> {
+        // Never send error messages in response to broadcasts (RFC 1122 Section 3.2.2).
+        if ipv4_header.get_dest_addr().is_broadcast() {
+            return Ok(());
+        }
+        // The message carries the offending IPv4 header and the first 8 bytes of its payload.
+        let len: usize = cmp::min(leading_bytes.len(), 8);
+        let mut data: DemiBuffer = DemiBuffer::new((IPV4_HEADER_MIN_SIZE as usize + len) as u16);
+        ipv4_header.serialize(&mut data[..IPV4_HEADER_MIN_SIZE as usize], payload_len);
+        data[IPV4_HEADER_MIN_SIZE as usize..].copy_from_slice(&leading_bytes[..len]);
+        let icmpv4_hdr: Icmpv4Header = Icmpv4Header::new(Icmpv4Type2::DestinationUnreachable, ICMPV4_PORT_UNREACHABLE);
+        self.tx
+            .unbounded_send((ipv4_header.get_src_addr(), icmpv4_hdr, data))
+            .unwrap();
+        Ok(())
+    }
+
+    /// Reports a destination unreachable message to the socket that sent the offending datagram.
+    fn handle_destination_unreachable(&mut self, code: u8, data: &[u8]) {
+        // Parse the offending IPv4 header. It is usually truncated, so we cannot rely on Ipv4Header::parse().
+        if data.len() < IPV4_HEADER_MIN_SIZE as usize {
+            warn!("destination unreachable message is too small");
+            return;
+        }
+        let ihl: usize = ((data[0] & 0xf) as usize) << 2;
+        if ihl < IPV4_HEADER_MIN_SIZE as usize || data.len() < ihl + 4 {
+            warn!("destination unreachable message is too small");
+            return;
+        }
+        let src_addr: Ipv4Addr = Ipv4Addr::new(data[12], data[13], data[14], data[15]);
+        let dst_addr: Ipv4Addr = Ipv4Addr::new(data[16], data[17], data[18], data[19]);
+        if src_addr != self.local_ipv4_addr {
+            warn!("destination unreachable message for foreign datagram");
+            return;
+        }
+        let src_port: u16 = u16::from_be_bytes([data[ihl], data[ihl + 1]]);
+        let dst_port: u16 = u16::from_be_bytes([data[ihl + 2], data[ihl + 3]]);
+
+        let errno: libc::c_int = match code {
+            ICMPV4_NET_UNREACHABLE => libc::ENETUNREACH,
+            ICMPV4_PROTOCOL_UNREACHABLE => libc::ENOPROTOOPT,
+            ICMPV4_PORT_UNREACHABLE => libc::ECONNREFUSED,
+            ICMPV4_FRAGMENTATION_NEEDED => libc::EMSGSIZE,
+            _ => libc::EHOSTUNREACH,
+        };
+        let local: SocketAddrV4 = SocketAddrV4::new(src_addr, src_port);
+        let remote: SocketAddrV4 = SocketAddrV4::new(dst_addr, dst_port);
+        debug!(
+            "destination unreachable (local={:?}, remote={:?}, code={:?})",
+            local, remote, code
+        );
+
+        match data[9] {
+            protocol if protocol == IpProtocol::UDP as u8 => {
+                let cause: String = format!("destination unreachable (code={:?})", code);
+                self.udp.receive_error(local, remote, Fail::new(errno, &cause));
+            },
+            protocol => warn!("destination unreachable not supported for protocol {:?}", protocol),
+        }
+    }
+
     /// Computes the identifier for an ICMP message.
     fn make_id(&mut self) -> u16 {
         let mut state: u32 = 0xFFFF;
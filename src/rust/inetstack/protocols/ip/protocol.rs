@@ -14,7 +14,7 @@ use ::std::convert::TryFrom;
 
 /// Ipv4 Protocol
 #[repr(u8)]
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum IpProtocol {
     /// Internet Control Message Protocol
     ICMPv4 = 0x01,
@@ -55,6 +55,12 @@ const IPV4_CTRL_FLAG_DF: u8 = 0x2;
 /// IPv4 Control Flag: More Fragments.
 const IPV4_CTRL_FLAG_MF: u8 = 0x1;
 
+/// ECN codepoint: ECN-Capable Transport (ECT(0)).
+pub const IPV4_ECN_ECT0: u8 = 0b10;
+
+/// ECN codepoint: Congestion Experienced (CE).
+pub const IPV4_ECN_CE: u8 = 0b11;
+
 //==============================================================================
 // Structures
 //==============================================================================
@@ -154,9 +160,6 @@ impl Ipv4Header {
 
         // Explicit congestion notification.
         let ecn: u8 = hdr_buf[1] & 3;
-        if ecn != 0 {
-            warn!("ignoring ecn field (ecn={:?})", ecn);
-        }
 
         // Total length.
         let total_length: u16 = u16::from_be_bytes([hdr_buf[2], hdr_buf[3]]);
@@ -188,19 +191,8 @@ impl Ipv4Header {
             return Err(Fail::new(EBADMSG, "ipv4 datagram is marked as evil"));
         }
 
-        // TODO: drop this check once we support fragmentation.
-        if flags & IPV4_CTRL_FLAG_MF != 0 {
-            warn!("fragmentation is not supported flags={:?}", flags);
-            return Err(Fail::new(ENOTSUP, "ipv4 fragmentation is not supported"));
-        }
-
         // Fragment offset.
         let fragment_offset: u16 = u16::from_be_bytes([hdr_buf[6], hdr_buf[7]]) & 0x1fff;
-        // TODO: drop this check once we support fragmentation.
-        if fragment_offset != 0 {
-            warn!("fragmentation is not supported offset={:?}", fragment_offset);
-            return Err(Fail::new(ENOTSUP, "ipv4 fragmentation is not supported"));
-        }
 
         // Time to live.
         let time_to_live: u8 = hdr_buf[8];
@@ -305,6 +297,46 @@ impl Ipv4Header {
         self.protocol
     }
 
+    /// Returns the identification field stored in the target IPv4 header.
+    pub fn get_identification(&self) -> u16 {
+        self.identification
+    }
+
+    /// Returns the offset (in bytes) of the payload of the target IPv4 header within the datagram that it belongs to.
+    pub fn get_fragment_offset(&self) -> usize {
+        (self.fragment_offset as usize) << 3
+    }
+
+    /// Checks if the More Fragments flag is set in the target IPv4 header.
+    pub fn has_more_fragments(&self) -> bool {
+        self.flags & IPV4_CTRL_FLAG_MF != 0
+    }
+
+    /// Checks if the target IPv4 header belongs to a fragment of a larger datagram.
+    pub fn is_fragment(&self) -> bool {
+        self.has_more_fragments() || self.fragment_offset != 0
+    }
+
+    /// Turns the target IPv4 header into the header of a fragment that starts at `offset` bytes into the datagram
+    /// identified by `identification`. The offset must be a multiple of eight. A fragment with offset zero and no more
+    /// fragments is a whole datagram.
+    pub fn set_fragment(&mut self, identification: u16, offset: usize, more_fragments: bool) {
+        debug_assert_eq!(offset & 7, 0);
+        self.identification = identification;
+        self.flags = if more_fragments { IPV4_CTRL_FLAG_MF } else { 0 };
+        self.fragment_offset = (offset >> 3) as u16;
+    }
+
+    /// Returns the explicit congestion notification field stored in the target IPv4 header.
+    pub fn get_ecn(&self) -> u8 {
+        self.ecn
+    }
+
+    /// Sets the explicit congestion notification field in the target IPv4 header.
+    pub fn set_ecn(&mut self, ecn: u8) {
+        self.ecn = ecn & 3;
+    }
+
     /// Computes the checksum of the target IPv4 header.
     pub fn compute_checksum(buf: &[u8]) -> u16 {
         let mut state: u32 = 0xffff;
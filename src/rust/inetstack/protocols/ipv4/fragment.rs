@@ -0,0 +1,146 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use super::datagram::{
+    Ipv4Header,
+    IPV4_HEADER_MIN_SIZE,
+};
+use crate::{
+    inetstack::protocols::ethernet2::Ethernet2Header,
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::PacketBuf,
+        SharedObject,
+    },
+};
+use ::libc::EMSGSIZE;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Maximum size of the payload of an IPv4 datagram (in bytes).
+pub const IPV4_PAYLOAD_MAX_SIZE: usize = u16::MAX as usize - IPV4_HEADER_MIN_SIZE as usize;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Fragment of an IPv4 datagram whose payload did not fit in a single packet.
+#[derive(Debug)]
+pub struct Ipv4Fragment {
+    /// Ethernet header.
+    ethernet2_hdr: Ethernet2Header,
+    /// IPv4 header.
+    ipv4_hdr: Ipv4Header,
+    /// Slice of the payload of the datagram that is carried by this fragment.
+    data: DemiBuffer,
+}
+
+/// Generator of identification values for outgoing IPv4 datagrams. It is shared by all sockets of a peer, so that
+/// fragments of different datagrams to the same destination are never mixed up.
+#[derive(Clone)]
+pub struct SharedIpv4IdGenerator(SharedObject<u16>);
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+/// Associated Functions for IPv4 Identification Generators
+impl SharedIpv4IdGenerator {
+    /// Returns the identification value for the next outgoing datagram.
+    pub fn next(&mut self) -> u16 {
+        let identification: u16 = *self.0;
+        *self.0 = identification.wrapping_add(1);
+        identification
+    }
+}
+
+//==============================================================================
+// Standalone Functions
+//==============================================================================
+
+/// Splits `payload` into fragments of a datagram with headers `ethernet2_hdr` and `ipv4_hdr`, each of which fits in a
+/// packet of `mtu` bytes (not including the Ethernet header).
+pub fn fragment(
+    ethernet2_hdr: Ethernet2Header,
+    ipv4_hdr: Ipv4Header,
+    identification: u16,
+    mut payload: DemiBuffer,
+    mtu: usize,
+) -> Result<Vec<Ipv4Fragment>, Fail> {
+    if payload.len() > IPV4_PAYLOAD_MAX_SIZE {
+        let cause: String = format!("datagram is too big (len={:?})", payload.len());
+        error!("fragment(): {}", &cause);
+        return Err(Fail::new(EMSGSIZE, &cause));
+    }
+
+    // All fragments but the last one carry a multiple of eight bytes, because that is the unit of fragment offsets.
+    let max_fragment_size: usize = (mtu - ipv4_hdr.compute_size()) & !7;
+    let mut fragments: Vec<Ipv4Fragment> = Vec::new();
+    let mut offset: usize = 0;
+    loop {
+        let more_fragments: bool = payload.len() > max_fragment_size;
+        let data: DemiBuffer = if more_fragments {
+            payload.split_front(max_fragment_size)?
+        } else {
+            payload.clone()
+        };
+        let mut ipv4_hdr: Ipv4Header = ipv4_hdr;
+        ipv4_hdr.set_fragment(identification, offset, more_fragments);
+        offset += data.len();
+        fragments.push(Ipv4Fragment {
+            ethernet2_hdr: ethernet2_hdr.clone(),
+            ipv4_hdr,
+            data,
+        });
+        if !more_fragments {
+            break;
+        }
+    }
+
+    Ok(fragments)
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Default Trait Implementation for IPv4 Identification Generators
+impl Default for SharedIpv4IdGenerator {
+    fn default() -> Self {
+        Self(SharedObject::new(0))
+    }
+}
+
+/// Packet Buffer Trait Implementation for IPv4 Fragments
+impl PacketBuf for Ipv4Fragment {
+    /// Computes the header size of the target IPv4 fragment.
+    fn header_size(&self) -> usize {
+        self.ethernet2_hdr.compute_size() + self.ipv4_hdr.compute_size()
+    }
+
+    /// Computes the payload size of the target IPv4 fragment.
+    fn body_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Serializes the header of the target IPv4 fragment.
+    fn write_header(&self, buf: &mut [u8]) {
+        let eth_hdr_size: usize = self.ethernet2_hdr.compute_size();
+        let ipv4_hdr_size: usize = self.ipv4_hdr.compute_size();
+        self.ethernet2_hdr.serialize(&mut buf[..eth_hdr_size]);
+        self.ipv4_hdr
+            .serialize(&mut buf[eth_hdr_size..(eth_hdr_size + ipv4_hdr_size)], self.data.len());
+    }
+
+    /// Returns the payload of the target IPv4 fragment.
+    fn take_body(&self) -> Option<DemiBuffer> {
+        Some(self.data.clone())
+    }
+}
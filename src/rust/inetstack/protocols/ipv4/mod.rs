@@ -2,6 +2,8 @@
 // Licensed under the MIT license.
 
 mod datagram;
+mod fragment;
+mod reassembly;
 
 #[cfg(test)]
 mod tests;
@@ -10,8 +12,23 @@ mod tests;
 // Exports
 //==============================================================================
 
-pub use self::datagram::{
-    Ipv4Header,
-    IPV4_HEADER_MIN_SIZE,
-    IPV4_HEADER_MAX_SIZE,
+pub use self::{
+    datagram::{
+        Ipv4Header,
+        IPV4_ECN_CE,
+        IPV4_ECN_ECT0,
+        IPV4_HEADER_MAX_SIZE,
+        IPV4_HEADER_MIN_SIZE,
+    },
+    fragment::{
+        fragment,
+        Ipv4Fragment,
+        SharedIpv4IdGenerator,
+        IPV4_PAYLOAD_MAX_SIZE,
+    },
+    reassembly::{
+        SharedReassemblyTable,
+        IPV4_REASSEMBLY_MAX_DATAGRAMS,
+        IPV4_REASSEMBLY_TIMEOUT,
+    },
 };
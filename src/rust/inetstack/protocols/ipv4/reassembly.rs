@@ -0,0 +1,261 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use super::{
+    datagram::Ipv4Header,
+    fragment::IPV4_PAYLOAD_MAX_SIZE,
+};
+use crate::{
+    inetstack::protocols::ip::IpProtocol,
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        scheduler::Yielder,
+        SharedDemiRuntime,
+        SharedObject,
+    },
+};
+use ::libc::{
+    EBADMSG,
+    ENOBUFS,
+    ETIMEDOUT,
+};
+use ::std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    net::Ipv4Addr,
+    ops::{
+        Deref,
+        DerefMut,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Time to wait for the missing fragments of a datagram before dropping the ones that were received. This follows the
+/// default of Linux.
+pub const IPV4_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of datagrams that are reassembled at the same time.
+pub const IPV4_REASSEMBLY_MAX_DATAGRAMS: usize = 64;
+
+/// Interval at which datagrams whose fragments did not all arrive in time are dropped.
+const IPV4_REASSEMBLY_CLEANUP_INTERVAL: Duration = Duration::from_secs(1);
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Fragments that belong to the same datagram share source, destination, identification and protocol (see RFC 791).
+type ReassemblyKey = (Ipv4Addr, Ipv4Addr, u16, IpProtocol);
+
+/// Datagram whose fragments are being reassembled.
+struct PartialDatagram {
+    /// Header of the first fragment, once it was received.
+    header: Option<Ipv4Header>,
+    /// Payloads of the fragments that were received, indexed by their offset.
+    fragments: BTreeMap<usize, DemiBuffer>,
+    /// Size of the payload of the datagram, once the last fragment was received.
+    total_len: Option<usize>,
+    /// Number of payload bytes that were received.
+    received_len: usize,
+    /// Time at which the fragments that were received are dropped.
+    deadline: Instant,
+}
+
+/// Table of datagrams whose fragments are being reassembled.
+#[derive(Default)]
+pub struct ReassemblyTable {
+    datagrams: HashMap<ReassemblyKey, PartialDatagram>,
+}
+
+#[derive(Clone)]
+pub struct SharedReassemblyTable(SharedObject<ReassemblyTable>);
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+/// Associated Functions for Shared Reassembly Tables
+impl SharedReassemblyTable {
+    /// Creates an empty reassembly table whose expired datagrams are dropped by a background coroutine.
+    pub fn new(mut runtime: SharedDemiRuntime) -> Result<Self, Fail> {
+        let table: SharedReassemblyTable = Self(SharedObject::new(ReassemblyTable::default()));
+        runtime.insert_background_coroutine(
+            "Inetstack::ipv4::reassembly",
+            Box::pin(table.clone().poll(runtime.clone())),
+        )?;
+        Ok(table)
+    }
+
+    /// Periodically drops the datagrams whose fragments did not all arrive in time, so that they do not hold on to
+    /// memory when no further fragments are received.
+    async fn poll(mut self, runtime: SharedDemiRuntime) {
+        loop {
+            let yielder: Yielder = Yielder::new();
+            match runtime
+                .get_timer()
+                .wait(IPV4_REASSEMBLY_CLEANUP_INTERVAL, &yielder)
+                .await
+            {
+                Ok(()) => {},
+                Err(Fail { errno, cause: _ }) if errno == ETIMEDOUT => {},
+                Err(_) => break,
+            }
+            self.expire(runtime.get_now());
+        }
+    }
+}
+
+/// Associated Functions for Reassembly Tables
+impl ReassemblyTable {
+    /// Drops the datagrams whose fragments did not all arrive before `now`.
+    pub fn expire(&mut self, now: Instant) {
+        self.datagrams.retain(|_, datagram| datagram.deadline > now);
+    }
+
+    /// Returns the number of datagrams that are being reassembled.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.datagrams.len()
+    }
+
+    /// Adds a fragment with `header` and `payload` that was received at `now` to the target table. If it was the last
+    /// missing fragment, the whole datagram is returned. Otherwise, `None` is returned.
+    pub fn insert(
+        &mut self,
+        header: Ipv4Header,
+        payload: DemiBuffer,
+        now: Instant,
+    ) -> Result<Option<(Ipv4Header, DemiBuffer)>, Fail> {
+        let offset: usize = header.get_fragment_offset();
+        let end: usize = offset + payload.len();
+        let more_fragments: bool = header.has_more_fragments();
+        if end > IPV4_PAYLOAD_MAX_SIZE {
+            return Err(Fail::new(EBADMSG, "ipv4 fragment exceeds maximum datagram size"));
+        }
+        if more_fragments && payload.len() & 7 != 0 {
+            return Err(Fail::new(EBADMSG, "ipv4 fragment size is not a multiple of eight"));
+        }
+
+        let key: ReassemblyKey = (
+            header.get_src_addr(),
+            header.get_dest_addr(),
+            header.get_identification(),
+            header.get_protocol(),
+        );
+        // Start over if the fragments of this datagram did not all arrive in time, even if the expired ones were not
+        // dropped yet.
+        if self
+            .datagrams
+            .get(&key)
+            .map_or(false, |datagram| datagram.deadline <= now)
+        {
+            self.datagrams.remove(&key);
+        }
+        if !self.datagrams.contains_key(&key) && self.datagrams.len() >= IPV4_REASSEMBLY_MAX_DATAGRAMS {
+            let cause: String = format!(
+                "too many datagrams being reassembled (max={:?})",
+                IPV4_REASSEMBLY_MAX_DATAGRAMS
+            );
+            warn!("insert(): {}", &cause);
+            return Err(Fail::new(ENOBUFS, &cause));
+        }
+        let datagram: &mut PartialDatagram = self.datagrams.entry(key).or_insert_with(|| PartialDatagram {
+            header: None,
+            fragments: BTreeMap::new(),
+            total_len: None,
+            received_len: 0,
+            deadline: now + IPV4_REASSEMBLY_TIMEOUT,
+        });
+
+        // Drop the whole datagram if fragments disagree on its size or overlap, as they cannot be trusted.
+        let bad_size: bool = match datagram.total_len {
+            Some(total_len) => end > total_len || (!more_fragments && end != total_len),
+            None => !more_fragments && datagram.fragments.iter().any(|(o, buf)| o + buf.len() > end),
+        };
+        let mut duplicate: bool = false;
+        let mut overlap: bool = false;
+        for (o, buf) in datagram.fragments.range(..end) {
+            if offset < o + buf.len() {
+                if *o == offset && buf.len() == payload.len() {
+                    duplicate = true;
+                } else {
+                    overlap = true;
+                }
+            }
+        }
+        if bad_size || overlap {
+            self.datagrams.remove(&key);
+            let cause: String = format!(
+                "inconsistent ipv4 fragment (offset={:?}, len={:?})",
+                offset,
+                payload.len()
+            );
+            warn!("insert(): {}", &cause);
+            return Err(Fail::new(EBADMSG, &cause));
+        }
+        if duplicate {
+            return Ok(None);
+        }
+
+        if offset == 0 {
+            datagram.header = Some(header);
+        }
+        if !more_fragments {
+            datagram.total_len = Some(end);
+        }
+        datagram.received_len += payload.len();
+        datagram.fragments.insert(offset, payload);
+        if datagram.total_len != Some(datagram.received_len) {
+            return Ok(None);
+        }
+
+        // All fragments arrived, so put the datagram back together.
+        let datagram: PartialDatagram = self
+            .datagrams
+            .remove(&key)
+            .expect("datagram should be in the reassembly table");
+        let mut header: Ipv4Header = match datagram.header {
+            Some(header) => header,
+            None => return Err(Fail::new(EBADMSG, "missing first ipv4 fragment")),
+        };
+        header.set_fragment(header.get_identification(), 0, false);
+        let mut buf: DemiBuffer = DemiBuffer::new(datagram.received_len as u16);
+        for (offset, fragment) in datagram.fragments {
+            buf[offset..(offset + fragment.len())].copy_from_slice(&fragment[..]);
+        }
+
+        Ok(Some((header, buf)))
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+impl Deref for SharedReassemblyTable {
+    type Target = ReassemblyTable;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.deref()
+    }
+}
+
+impl DerefMut for SharedReassemblyTable {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.deref_mut()
+    }
+}
@@ -511,17 +511,14 @@ fn test_ipv4_header_parse_unsupported_ecn() -> Result<()> {
     Ok(())
 }
 
-/// Parses a malformed IPv4 header with unsupported fragmentation fields.
-///
-/// TODO: Drop this test once we support fragmentation.
+/// Parses IPv4 headers of fragments.
 #[test]
-fn test_ipv4_header_parse_unsupported_fragmentation() -> Result<()> {
+fn test_ipv4_header_parse_fragment() -> Result<()> {
     const HEADER_SIZE: usize = 20;
     const PAYLOAD_SIZE: usize = 0;
     const DATAGRAM_SIZE: usize = HEADER_SIZE + PAYLOAD_SIZE;
     let mut buf: [u8; DATAGRAM_SIZE] = [0; DATAGRAM_SIZE];
 
-    // Fragmented packets are unsupported.
     // Fragments are detected by having either the MF bit set in Flags or a non-zero Fragment Offset field.
     let flags: u8 = 0x1; // Set MF bit.
     build_ipv4_header(
@@ -548,12 +545,16 @@ fn test_ipv4_header_parse_unsupported_fragmentation() -> Result<()> {
     };
 
     match Ipv4Header::parse(buf_bytes) {
-        Ok(_) => anyhow::bail!("parsed ipv4 header with Flags={:?}. Do we support it now?", flags,),
-        Err(_) => {},
+        Ok((header, _)) => {
+            crate::ensure_eq!(header.is_fragment(), true);
+            crate::ensure_eq!(header.has_more_fragments(), true);
+            crate::ensure_eq!(header.get_identification(), 0x1d);
+            crate::ensure_eq!(header.get_fragment_offset(), 0);
+        },
+        Err(e) => anyhow::bail!("could not parse ipv4 header with Flags={:?}: {:?}", flags, e),
     };
 
-    // Fragmented packets are unsupported.
-    // Fragments are detected by having either the MF bit set in Flags or a non-zero Fragment Offset field.
+    // The fragment offset is expressed in units of eight bytes.
     let fragment_offset: u16 = 1;
     build_ipv4_header(
         &mut buf,
@@ -563,7 +564,7 @@ fn test_ipv4_header_parse_unsupported_fragmentation() -> Result<()> {
         0,
         DATAGRAM_SIZE as u16,
         0x1d,
-        0x2,
+        0x0,
         fragment_offset,
         1,
         IpProtocol::UDP as u8,
@@ -579,11 +580,17 @@ fn test_ipv4_header_parse_unsupported_fragmentation() -> Result<()> {
     };
 
     match Ipv4Header::parse(buf_bytes) {
-        Ok(_) => anyhow::bail!(
-            "parsed ipv4 header with fragment_offset={:?}. Do we support it now?",
+        Ok((header, _)) => {
+            crate::ensure_eq!(header.is_fragment(), true);
+            crate::ensure_eq!(header.has_more_fragments(), false);
+            crate::ensure_eq!(header.get_fragment_offset(), 8);
+            Ok(())
+        },
+        Err(e) => anyhow::bail!(
+            "could not parse ipv4 header with fragment_offset={:?}: {:?}",
             fragment_offset,
+            e
         ),
-        Err(_) => Ok(()),
     }
 }
 
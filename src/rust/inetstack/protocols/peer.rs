@@ -6,7 +6,10 @@ use crate::{
         arp::SharedArpPeer,
         icmpv4::SharedIcmpv4Peer,
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            SharedReassemblyTable,
+        },
         tcp::SharedTcpPeer,
         udp::SharedUdpPeer,
     },
@@ -27,6 +30,7 @@ use crate::{
 };
 use ::libc::ENOTCONN;
 use ::std::{
+    cmp,
     net::Ipv4Addr,
     time::Duration,
 };
@@ -35,7 +39,9 @@ use ::std::{
 use crate::runtime::QDesc;
 
 pub struct Peer<const N: usize> {
+    runtime: SharedDemiRuntime,
     local_ipv4_addr: Ipv4Addr,
+    reassembly: SharedReassemblyTable,
     icmpv4: SharedIcmpv4Peer<N>,
     pub tcp: SharedTcpPeer<N>,
     pub udp: SharedUdpPeer<N>,
@@ -59,6 +65,8 @@ impl<const N: usize> Peer<N> {
             local_link_addr,
             local_ipv4_addr,
             udp_offload_checksum,
+            udp_config.get_checksum(),
+            udp_config.get_mtu(),
             arp.clone(),
         )?;
         let icmpv4: SharedIcmpv4Peer<N> = SharedIcmpv4Peer::new(
@@ -67,6 +75,7 @@ impl<const N: usize> Peer<N> {
             local_link_addr,
             local_ipv4_addr,
             arp.clone(),
+            udp.clone(),
             rng_seed,
         )?;
         let tcp: SharedTcpPeer<N> = SharedTcpPeer::new(
@@ -79,8 +88,12 @@ impl<const N: usize> Peer<N> {
             rng_seed,
         )?;
 
+        let reassembly: SharedReassemblyTable = SharedReassemblyTable::new(runtime.clone())?;
+
         Ok(Peer {
+            runtime,
             local_ipv4_addr,
+            reassembly,
             icmpv4,
             tcp,
             udp,
@@ -93,10 +106,33 @@ impl<const N: usize> Peer<N> {
         if header.get_dest_addr() != self.local_ipv4_addr && !header.get_dest_addr().is_broadcast() {
             return Err(Fail::new(ENOTCONN, "invalid destination address"));
         }
+        let (header, payload): (Ipv4Header, DemiBuffer) = if header.is_fragment() {
+            match self.reassembly.insert(header, payload, self.runtime.get_now())? {
+                Some(datagram) => datagram,
+                // Wait for the remaining fragments.
+                None => return Ok(()),
+            }
+        } else {
+            (header, payload)
+        };
         match header.get_protocol() {
             IpProtocol::ICMPv4 => self.icmpv4.receive(&header, payload),
             IpProtocol::TCP => self.tcp.receive(&header, payload),
-            IpProtocol::UDP => self.udp.receive(&header, payload),
+            IpProtocol::UDP => {
+                // Keep the leading bytes of the datagram around, in case we have to report that the port is closed.
+                let payload_len: usize = payload.len();
+                let mut leading_bytes: [u8; 8] = [0; 8];
+                let n: usize = cmp::min(payload_len, leading_bytes.len());
+                leading_bytes[..n].copy_from_slice(&payload[..n]);
+                match self.udp.receive(&header, payload) {
+                    Err(e) if e.errno == ENOTCONN => {
+                        self.icmpv4
+                            .send_port_unreachable(&header, payload_len, &leading_bytes[..n])?;
+                        Err(e)
+                    },
+                    result => result,
+                }
+            },
         }
     }
 
@@ -114,4 +150,8 @@ impl<const N: usize> Peer<N> {
     pub fn tcp_rto(&self, fd: QDesc) -> Result<Duration, Fail> {
         self.tcp.current_rto(fd)
     }
+
+    pub fn reassembly_len(&self) -> usize {
+        self.reassembly.len()
+    }
 }
@@ -18,17 +18,20 @@ use crate::{
         tcp::{
             constants::FALLBACK_MSS,
             established::{
-                congestion_control::{
-                    self,
-                    CongestionControl,
-                },
+                congestion_control,
                 EstablishedSocket,
             },
+            observer::{
+                ConnEventKind,
+                SharedConnectionObserver,
+            },
             segment::{
+                self,
                 TcpHeader,
                 TcpOptions2,
                 TcpSegment,
             },
+            timestamps::Timestamps,
             SeqNumber,
         },
     },
@@ -58,8 +61,12 @@ use ::std::{
         Deref,
         DerefMut,
     },
+    time::Instant,
 };
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::md5;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -74,7 +81,12 @@ pub struct ActiveOpenSocket<const N: usize> {
     tcp_config: TcpConfig,
     arp: SharedArpPeer<N>,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+    observer: SharedConnectionObserver,
     recv_queue: AsyncQueue<TcpHeader>,
+    // SYN received from our peer while we were waiting for a SYN+ACK, in case of a simultaneous open.
+    remote_syn: Option<TcpHeader>,
+    // Time at which our timestamp clock started, in case the timestamp option is negotiated.
+    timestamp_base: Instant,
 }
 
 #[derive(Clone)]
@@ -95,6 +107,7 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
         local_link_addr: MacAddress,
         arp: SharedArpPeer<N>,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+        observer: SharedConnectionObserver,
     ) -> Result<Self, Fail> {
         // TODO: Add fast path here when remote is already in the ARP cache (and subtract one retry).
 
@@ -108,7 +121,10 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
             tcp_config,
             arp,
             dead_socket_tx,
+            observer,
             recv_queue: AsyncQueue::<TcpHeader>::default(),
+            remote_syn: None,
+            timestamp_base: runtime.get_now(),
         })))
     }
 
@@ -120,6 +136,14 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
     fn process_ack(&mut self, header: TcpHeader) -> Result<EstablishedSocket<N>, Fail> {
         let expected_seq: SeqNumber = self.local_isn + SeqNumber::from(1);
 
+        // Check if our peer is connecting to us at the same time (see RFC 793 Section 3.4). If so, we move to
+        // SYN_RCVD, and acknowledge its SYN from now on when we (re)send ours.
+        if header.syn && !header.ack && !header.rst {
+            debug!("Received SYN in SYN_SENT state (simultaneous open): {:?}", header);
+            self.remote_syn = Some(header);
+            return Err(Fail::new(libc::EAGAIN, "simultaneous open"));
+        }
+
         // Bail if we didn't receive a ACK packet with the right sequence number.
         if !(header.ack && header.ack_num == expected_seq) {
             let cause: String = format!(
@@ -137,54 +161,114 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
             return Err(Fail::new(libc::ECONNREFUSED, &cause));
         }
 
-        // Bail if we didn't receive a SYN packet.
-        if !header.syn {
-            let cause: String = format!("is not a syn packet");
-            error!("process_ack(): {}", cause);
-            return Err(Fail::new(libc::EAGAIN, &cause));
-        }
+        // Parse the SYN of our peer. In SYN_RCVD, we have already received it, so a plain ACK completes the handshake.
+        let (remote_isn, remote_window_scale, mss, ecn): (SeqNumber, Option<u8>, usize, bool) =
+            match (header.syn, self.remote_syn.as_ref()) {
+                // Our peer agrees to use ECN by setting ECE but not CWR on its SYN+ACK (see RFC 3168 Section 6.1.1).
+                (true, None) => {
+                    let (remote_window_scale, mss): (Option<u8>, usize) = Self::parse_syn_options(&header);
+                    (header.seq_num, remote_window_scale, mss, header.ece && !header.cwr)
+                },
+                // In a simultaneous open, ECN was requested in the SYN of our peer.
+                (true, Some(remote_syn)) => {
+                    let (remote_window_scale, mss): (Option<u8>, usize) = Self::parse_syn_options(&header);
+                    (
+                        header.seq_num,
+                        remote_window_scale,
+                        mss,
+                        remote_syn.ece && remote_syn.cwr,
+                    )
+                },
+                (false, Some(remote_syn)) => {
+                    let (remote_window_scale, mss): (Option<u8>, usize) = Self::parse_syn_options(remote_syn);
+                    (
+                        remote_syn.seq_num,
+                        remote_window_scale,
+                        mss,
+                        remote_syn.ece && remote_syn.cwr,
+                    )
+                },
+                // Bail if we didn't receive a SYN packet.
+                (false, None) => {
+                    let cause: String = format!("is not a syn packet");
+                    error!("process_ack(): {}", cause);
+                    return Err(Fail::new(libc::EAGAIN, &cause));
+                },
+            };
 
         debug!("Received SYN+ACK: {:?}", header);
 
-        // Acknowledge the SYN+ACK segment.
-        let remote_link_addr = match self.arp.try_query(self.remote.ip().clone()) {
-            Some(r) => r,
-            None => panic!("TODO: Clean up ARP query control flow"),
+        let remote_seq_num = remote_isn + SeqNumber::from(1);
+
+        // Timestamps are only used if both sides send the option on their SYN (see RFC 7323 Section 3.2).
+        let syn_timestamp: Option<(u32, u32)> = match self.remote_syn.as_ref() {
+            Some(remote_syn) if !header.syn => remote_syn.get_timestamp(),
+            _ => header.get_timestamp(),
         };
-        let remote_seq_num = header.seq_num + SeqNumber::from(1);
-
-        let mut tcp_hdr = TcpHeader::new(self.local.port(), self.remote.port());
-        tcp_hdr.ack = true;
-        tcp_hdr.ack_num = remote_seq_num;
-        tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
-        tcp_hdr.seq_num = self.local_isn + SeqNumber::from(1);
-        debug!("Sending ACK: {:?}", tcp_hdr);
-
-        let segment = TcpSegment {
-            ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
-            ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP),
-            tcp_hdr,
-            data: None,
-            tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
+        let timestamps: Option<Timestamps> = match syn_timestamp {
+            Some((value, _)) if self.tcp_config.get_enable_timestamps() => {
+                let recent: u32 = header.get_timestamp().map_or(value, |(value, _)| value);
+                Some(Timestamps::new(self.timestamp_base, recent))
+            },
+            _ => None,
         };
-        self.transport.transmit(Box::new(segment));
+        info!("Timestamps: {}", timestamps.is_some());
 
-        let mut remote_window_scale = None;
-        let mut mss = FALLBACK_MSS;
-        for option in header.iter_options() {
-            match option {
-                TcpOptions2::WindowScale(w) => {
-                    info!("Received window scale: {}", w);
-                    remote_window_scale = Some(*w);
-                },
-                TcpOptions2::MaximumSegmentSize(m) => {
-                    info!("Received advertised MSS: {}", m);
-                    mss = *m as usize;
-                },
-                _ => continue,
+        // Our peer hands out a connection token on its SYN+ACK if we asked for one on our SYN.
+        let connection_token: Option<u32> = match self.remote_syn {
+            None if cfg!(feature = "tcp-migration") => header.get_connection_token().filter(|token| *token != 0),
+            _ => None,
+        };
+
+        // Acknowledge the SYN+ACK segment. A plain ACK does not need to be acknowledged.
+        if header.syn {
+            let remote_link_addr = match self.arp.try_query(self.remote.ip().clone()) {
+                Some(r) => r,
+                None => panic!("TODO: Clean up ARP query control flow"),
+            };
+
+            let mut tcp_hdr = TcpHeader::new(self.local.port(), self.remote.port());
+            tcp_hdr.ack = true;
+            tcp_hdr.ack_num = remote_seq_num;
+            tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
+            tcp_hdr.seq_num = self.local_isn + SeqNumber::from(1);
+            if let Some(ref timestamps) = timestamps {
+                tcp_hdr.push_option(TcpOptions2::Timestamp {
+                    sender_timestamp: timestamps.value(self.runtime.get_now()),
+                    echo_timestamp: timestamps.echo(),
+                });
             }
+            if let Some(token) = connection_token {
+                tcp_hdr.push_option(TcpOptions2::ConnectionToken(token));
+            }
+            debug!("Sending ACK: {:?}", tcp_hdr);
+
+            let segment = TcpSegment {
+                ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
+                ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP),
+                tcp_hdr,
+                data: None,
+                tx_checksum_offload: self.tcp_config.get_tx_checksum_offload(),
+            };
+            #[cfg(feature = "tcp-md5")]
+            let segment: TcpSegment = md5::sign(segment, &self.tcp_config);
+            self.transport.transmit(Box::new(segment));
         }
 
+        // Segments are signed if we share a key with our peer.
+        #[cfg(feature = "tcp-md5")]
+        let md5_signature: bool = self.tcp_config.get_md5_key(self.remote.ip()).is_some();
+        #[cfg(not(feature = "tcp-md5"))]
+        let md5_signature: bool = false;
+
+        let mss: usize = segment::compute_mss(
+            mss,
+            self.tcp_config.get_effective_mss(),
+            timestamps.is_some(),
+            connection_token.is_some(),
+            md5_signature,
+        );
+
         let (local_window_scale, remote_window_scale) = match remote_window_scale {
             Some(w) => (self.tcp_config.get_window_scale() as u32, w),
             None => (0, 0),
@@ -193,10 +277,9 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
         // TODO(RFC1323): Clamp the scale to 14 instead of panicking.
         assert!(local_window_scale <= 14 && remote_window_scale <= 14);
 
-        let rx_window_size: u32 = (self.tcp_config.get_receive_window_size())
+        // Widen the window before scaling it, as it may not fit in the window field of the header once scaled.
+        let rx_window_size: u32 = (self.tcp_config.get_receive_window_size() as u32)
             .checked_shl(local_window_scale as u32)
-            .expect("TODO: Window size overflow")
-            .try_into()
             .expect("TODO: Window size overflow");
 
         let tx_window_size: u32 = (header.window_size)
@@ -205,6 +288,9 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
             .try_into()
             .expect("TODO: Window size overflow");
 
+        let ecn: bool = self.tcp_config.get_enable_ecn() && ecn;
+        info!("ECN: {}", ecn);
+
         info!("Window sizes: local {}, remote {}", rx_window_size, tx_window_size);
         info!(
             "Window scale: local {}, remote {}",
@@ -226,12 +312,36 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
             tx_window_size,
             remote_window_scale,
             mss,
-            congestion_control::None::new,
-            None,
+            congestion_control::constructor(&self.tcp_config),
+            congestion_control::initial_window_options(&self.tcp_config, mss),
+            ecn,
+            timestamps,
+            connection_token,
             self.dead_socket_tx.clone(),
+            self.observer.clone(),
         )?)
     }
 
+    /// Parses the window scale and maximum segment size options of a SYN segment.
+    fn parse_syn_options(header: &TcpHeader) -> (Option<u8>, usize) {
+        let mut remote_window_scale = None;
+        let mut mss = FALLBACK_MSS;
+        for option in header.iter_options() {
+            match option {
+                TcpOptions2::WindowScale(w) => {
+                    info!("Received window scale: {}", w);
+                    remote_window_scale = Some(*w);
+                },
+                TcpOptions2::MaximumSegmentSize(m) => {
+                    info!("Received advertised MSS: {}", m);
+                    mss = *m as usize;
+                },
+                _ => continue,
+            }
+        }
+        (remote_window_scale, mss)
+    }
+
     pub async fn connect(mut self, yielder: Yielder) -> Result<EstablishedSocket<N>, Fail> {
         // Start connection handshake.
         let handshake_retries: usize = self.tcp_config.get_handshake_retries();
@@ -253,6 +363,25 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
             tcp_hdr.seq_num = self.local_isn;
             tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
 
+            match self.remote_syn {
+                // In a simultaneous open, our SYN also acknowledges the one of our peer. Agree to use ECN if it was
+                // requested, by setting ECE but not CWR.
+                Some(ref remote_syn) => {
+                    tcp_hdr.ack = true;
+                    tcp_hdr.ack_num = remote_syn.seq_num + SeqNumber::from(1);
+                    if self.tcp_config.get_enable_ecn() && remote_syn.ece && remote_syn.cwr {
+                        tcp_hdr.ece = true;
+                    }
+                },
+                // Request ECN by setting both ECE and CWR on our SYN (see RFC 3168 Section 6.1.1).
+                None => {
+                    if self.tcp_config.get_enable_ecn() {
+                        tcp_hdr.ece = true;
+                        tcp_hdr.cwr = true;
+                    }
+                },
+            }
+
             let mss = self.tcp_config.get_advertised_mss() as u16;
             tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
             info!("Advertising MSS: {}", mss);
@@ -260,14 +389,33 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
             tcp_hdr.push_option(TcpOptions2::WindowScale(self.tcp_config.get_window_scale()));
             info!("Advertising window scale: {}", self.tcp_config.get_window_scale());
 
+            // Offer timestamps, echoing the one of our peer in a simultaneous open.
+            if self.tcp_config.get_enable_timestamps() {
+                let echo_timestamp: u32 = match self.remote_syn {
+                    Some(ref remote_syn) => remote_syn.get_timestamp().map_or(0, |(value, _)| value),
+                    None => 0,
+                };
+                tcp_hdr.push_option(TcpOptions2::Timestamp {
+                    sender_timestamp: Timestamps::clock(self.timestamp_base, self.runtime.get_now()),
+                    echo_timestamp,
+                });
+            }
+
+            // Ask our peer for a connection token, so that the connection survives changes of our address.
+            if cfg!(feature = "tcp-migration") && self.remote_syn.is_none() {
+                tcp_hdr.push_option(TcpOptions2::ConnectionToken(0));
+            }
+
             debug!("Sending SYN {:?}", tcp_hdr);
             let segment = TcpSegment {
                 ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
                 ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP),
                 tcp_hdr,
                 data: None,
-                tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
+                tx_checksum_offload: self.tcp_config.get_tx_checksum_offload(),
             };
+            #[cfg(feature = "tcp-md5")]
+            let segment: TcpSegment = md5::sign(segment, &self.tcp_config);
             // Send SYN.
             self.transport.transmit(Box::new(segment));
 
@@ -283,7 +431,10 @@ impl<const N: usize> SharedActiveOpenSocket<N> {
                 // again.
                 result = ack_future => match result {
                     Ok(header) => match self.process_ack(header) {
-                        Ok(socket) => return Ok(socket),
+                        Ok(socket) => {
+                            self.observer.notify(self.local, self.remote, ConnEventKind::Established);
+                            return Ok(socket);
+                        },
                         Err(Fail{errno, cause:_}) if errno == libc::EAGAIN => continue,
                         Err(e) => return Err(e),
                     },
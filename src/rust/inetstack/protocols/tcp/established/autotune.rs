@@ -0,0 +1,117 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use crate::inetstack::protocols::tcp::SeqNumber;
+use ::std::time::{
+    Duration,
+    Instant,
+};
+
+// TCP Receive Window Auto-Tuning.
+// The receive buffer is sized after the bandwidth-delay product of the path, as estimated from the rate at which data
+// is delivered to us and the round-trip time.  This lets the window keep up with high-BDP paths without every
+// connection paying for a large buffer up front.
+//
+// The round-trip time is estimated on the receiving side, as a connection that only receives data never gets
+// acknowledgements to time.  With timestamps, it is the age of the timestamps that our peer echoes back.  Without them,
+// it is the time it takes to receive one window of data, which is an upper bound, so the smallest such sample is kept
+// (this is what Linux does, see tcp_rcv_rtt_update()).
+
+#[derive(Debug)]
+pub struct ReceiveWindowTuner {
+    // Lower bound for the receive buffer size.
+    min: u32,
+
+    // Upper bound for the receive buffer size.
+    max: u32,
+
+    // Size that the receive buffer is tuned to.
+    target: u32,
+
+    // Time at which the current measurement started.
+    start: Instant,
+
+    // RCV.NXT when the current measurement started.
+    start_seq: SeqNumber,
+
+    // Round-trip time estimate, once there is one.
+    rtt: Option<Duration>,
+
+    // Time at which the current window-based round-trip time measurement started, and the sequence number that ends it.
+    rtt_measurement: Option<(Instant, SeqNumber)>,
+}
+
+impl ReceiveWindowTuner {
+    /// Initializes a tuner that keeps the receive buffer size within `[min, max]`, starting at `min`.
+    pub fn new(min: u32, max: u32, now: Instant, receive_next: SeqNumber) -> Self {
+        debug_assert!(min <= max);
+        Self {
+            min,
+            max,
+            target: min,
+            start: now,
+            start_seq: receive_next,
+            rtt: None,
+            rtt_measurement: None,
+        }
+    }
+
+    /// Samples the round-trip time from the age `rtt` of a timestamp that our peer echoed back.
+    pub fn sample_rtt(&mut self, rtt: Duration) {
+        // Timestamps have a millisecond granularity, so anything faster than that carries no information.
+        if rtt.is_zero() {
+            return;
+        }
+        self.rtt = Some(match self.rtt {
+            Some(srtt) => (srtt * 7 + rtt) / 8,
+            None => rtt,
+        });
+    }
+
+    /// Samples the round-trip time from the time it takes RCV.NXT to move past the end of the receive window, as it was
+    /// when the measurement started.  `receive_next` and `window` are RCV.NXT and RCV.WND at `now`.
+    pub fn sample_rtt_from_window(&mut self, now: Instant, receive_next: SeqNumber, window: u32) {
+        match self.rtt_measurement {
+            Some((start, end)) if receive_next >= end => {
+                let rtt: Duration = now.saturating_duration_since(start);
+                if !rtt.is_zero() {
+                    self.rtt = Some(self.rtt.map_or(rtt, |min_rtt| min_rtt.min(rtt)));
+                }
+                self.rtt_measurement = Some((now, receive_next + SeqNumber::from(window)));
+            },
+            Some(_) => (),
+            None => self.rtt_measurement = Some((now, receive_next + SeqNumber::from(window))),
+        }
+    }
+
+    /// Records that RCV.NXT reached `receive_next` at `now`.  Once per round-trip time, this retargets the receive
+    /// buffer at twice the bandwidth-delay product, so that the sender has room to grow, and returns true.  Nothing is
+    /// retargeted until the round-trip time has been sampled.
+    pub fn update(&mut self, now: Instant, receive_next: SeqNumber) -> bool {
+        let rtt: Duration = match self.rtt {
+            Some(rtt) => rtt,
+            None => {
+                self.start = now;
+                self.start_seq = receive_next;
+                return false;
+            },
+        };
+        let elapsed: Duration = now.saturating_duration_since(self.start);
+        if elapsed.is_zero() || elapsed < rtt {
+            return false;
+        }
+
+        let delivered: u32 = (receive_next - self.start_seq).into();
+        let delivery_rate: f64 = delivered as f64 / elapsed.as_secs_f64();
+        let bdp: f64 = delivery_rate * rtt.as_secs_f64();
+        self.target = ((2.0 * bdp) as u64).clamp(self.min as u64, self.max as u64) as u32;
+        self.start = now;
+        self.start_seq = receive_next;
+        true
+    }
+
+    /// Gets the size that the receive buffer is tuned to.
+    pub fn target(&self) -> u32 {
+        self.target
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use super::SharedControlBlock;
+use crate::{
+    inetstack::protocols::tcp::established::ctrlblk::State,
+    runtime::{
+        fail::Fail,
+        scheduler::Yielder,
+        timer::SharedTimer,
+    },
+};
+use ::futures::future;
+use ::std::time::{
+    Duration,
+    Instant,
+};
+
+/// Closes the connection once neither side has sent anything for the configured idle timeout. Unlike keepalive, this
+/// does not probe the peer: it simply initiates an orderly close.
+pub async fn idle_timer<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yielder) -> Result<!, Fail> {
+    let idle_timeout: Duration = match cb.get_idle_timeout() {
+        Some(idle_timeout) => idle_timeout,
+        None => future::pending().await,
+    };
+
+    loop {
+        // Activity only ever moves the deadline forward, so we sleep until the current deadline and re-check.
+        let deadline: Instant = cb.get_last_activity() + idle_timeout;
+        let clock_ref: SharedTimer = cb.get_timer();
+        clock_ref.wait_until(deadline, &yielder).await?;
+        if cb.get_last_activity() + idle_timeout > cb.get_now() {
+            continue;
+        }
+
+        // Only initiate a close if nobody else has started one.
+        if cb.user_is_done_sending || !matches!(cb.get_state(), State::Established | State::CloseWait) {
+            return future::pending().await;
+        }
+        info!("Closing idle connection (timeout={:?})", idle_timeout);
+        cb.close()?;
+        return future::pending().await;
+    }
+}
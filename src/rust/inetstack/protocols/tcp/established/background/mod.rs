@@ -2,13 +2,17 @@
 // Licensed under the MIT license.
 
 mod acknowledger;
+mod idle;
 mod retransmitter;
 mod sender;
+mod teardown;
 
 use self::{
     acknowledger::acknowledger,
+    idle::idle_timer,
     retransmitter::retransmitter,
     sender::sender,
+    teardown::teardown_timer,
 };
 use crate::{
     inetstack::protocols::tcp::established::ctrlblk::SharedControlBlock,
@@ -35,10 +39,20 @@ pub async fn background<const N: usize>(cb: SharedControlBlock<N>, _dead_socket_
     let sender = sender(cb.clone(), yielder_sender).fuse();
     futures::pin_mut!(sender);
 
+    let yielder_idle_timer: Yielder = Yielder::new();
+    let idle_timer = idle_timer(cb.clone(), yielder_idle_timer).fuse();
+    futures::pin_mut!(idle_timer);
+
+    let yielder_teardown_timer: Yielder = Yielder::new();
+    let teardown_timer = teardown_timer(cb.clone(), yielder_teardown_timer).fuse();
+    futures::pin_mut!(teardown_timer);
+
     let r = futures::select_biased! {
         r = acknowledger => r,
         r = retransmitter => r,
         r = sender => r,
+        r = idle_timer => r,
+        r = teardown_timer => r,
     };
     error!("Connection terminated: {:?}", r);
 
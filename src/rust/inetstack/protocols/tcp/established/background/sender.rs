@@ -21,7 +21,10 @@ use crate::{
 use ::futures::FutureExt;
 use ::std::{
     cmp,
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 pub async fn sender<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yielder) -> Result<!, Fail> {
@@ -49,6 +52,29 @@ pub async fn sender<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yiel
             }
         }
 
+        // Hold back unsent data until the connection is uncorked.
+        let mut corked_watched: SharedWatchedValue<bool> = cb.get_corked();
+        if corked_watched.get() {
+            let corked_yielder: Yielder = Yielder::new();
+            corked_watched.watch(corked_yielder).await?;
+            continue 'top;
+        }
+
+        // Hold back small pushes until they are flushed, or until the deadline for coalescing them expires.
+        let mut coalesce_deadline_watched: SharedWatchedValue<Option<Instant>> = cb.get_coalesce_deadline();
+        if let Some(deadline) = coalesce_deadline_watched.get() {
+            let coalesce_yielder: Yielder = Yielder::new();
+            let coalesce_deadline_changed = coalesce_deadline_watched.watch(coalesce_yielder).fuse();
+            futures::pin_mut!(coalesce_deadline_changed);
+            let clock_ref: SharedTimer = cb.get_timer();
+            futures::select_biased! {
+                _ = coalesce_deadline_changed => continue 'top,
+                result = clock_ref.wait_until(deadline, &yielder).fuse() => result?,
+            }
+            cb.flush_coalesced();
+            continue 'top;
+        }
+
         // Okay, we know we have some unsent data past this point. Next, check to see that the
         // remote side has available window.
         let mut win_sz_watched: SharedWatchedValue<u32> = cb.get_send_window();
@@ -58,8 +84,16 @@ pub async fn sender<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yiel
         futures::pin_mut!(win_sz_changed);
 
         // If we don't have any window size at all, we need to transition to PERSIST mode and
-        // repeatedly send window probes until window opens up.
+        // repeatedly send window probes until window opens up. The first probe goes out once the persist timer fires,
+        // so that a window update from our peer has a chance to arrive first.
         if win_sz == 0 {
+            let timeout: Duration = cb.persist_start();
+            let clock_ref: SharedTimer = cb.get_timer();
+            futures::select_biased! {
+                _ = win_sz_changed => continue 'top,
+                result = clock_ref.wait(timeout, &yielder).fuse() => result?,
+            }
+
             // Send a window probe (this is a one-byte packet designed to elicit a window update from our peer).
             let arp_yielder: Yielder = Yielder::new();
             let remote_link_addr = cb.arp().query(cb.get_remote().ip().clone(), &arp_yielder).await?;
@@ -83,16 +117,12 @@ pub async fn sender<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yiel
             cb4.emit(header, Some(buf.clone()), remote_link_addr);
 
             // Note that we loop here *forever*, exponentially backing off.
-            // TODO: Use the correct PERSIST mode timer here.
-            let mut timeout: Duration = Duration::from_secs(1);
             loop {
+                let timeout: Duration = cb.persist_back_off();
                 let clock_ref: SharedTimer = cb.get_timer();
-
                 futures::select_biased! {
                     _ = win_sz_changed => continue 'top,
-                    _ = clock_ref.wait(timeout, &yielder).fuse() => {
-                        timeout *= 2;
-                    }
+                    result = clock_ref.wait(timeout, &yielder).fuse() => result?,
                 }
                 // Retransmit our window probe.
                 let mut header: TcpHeader = cb.tcp_header();
@@ -103,6 +133,7 @@ pub async fn sender<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yiel
         }
 
         // The remote window is nonzero, but there still may not be room.
+        cb.persist_stop();
         let mut send_unacked_watched: SharedWatchedValue<SeqNumber> = cb.get_send_unacked();
         let send_unacked: SeqNumber = send_unacked_watched.get();
         let send_unacked_yielder: Yielder = Yielder::new();
@@ -141,6 +172,13 @@ pub async fn sender<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yiel
             }
         }
 
+        // If send pacing is enabled, wait until we are allowed to release the next segment.
+        if let Some(release_time) = cb.pacing_get_release_time() {
+            let clock_ref: SharedTimer = cb.get_timer();
+            clock_ref.wait_until(release_time, &yielder).await?;
+            continue 'top;
+        }
+
         // Past this point we have data to send and it's valid to send it!
 
         // TODO: Nagle's algorithm - We need to coalese small buffers together to send MSS sized packets.
@@ -177,6 +215,7 @@ pub async fn sender<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yiel
         }
         let mut cb4 = cb.clone();
         cb4.emit(header, Some(segment_data.clone()), remote_link_addr);
+        cb4.pacing_on_send(segment_data_len as usize);
 
         // Update SND.NXT.
         cb.modify_send_next(|s| s + SeqNumber::from(segment_data_len));
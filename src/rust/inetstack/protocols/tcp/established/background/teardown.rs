@@ -0,0 +1,44 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use super::SharedControlBlock;
+use crate::runtime::{
+    fail::Fail,
+    scheduler::Yielder,
+    timer::SharedTimer,
+    watched::SharedWatchedValue,
+};
+use ::futures::future::{
+    self,
+    Either,
+    FutureExt,
+};
+use ::std::time::Instant;
+
+/// Cleans up the connection once it has waited for long enough in FIN-WAIT-2 or TIME-WAIT.
+pub async fn teardown_timer<const N: usize>(mut cb: SharedControlBlock<N>, yielder: Yielder) -> Result<!, Fail> {
+    loop {
+        let mut close_deadline_watched: SharedWatchedValue<Option<Instant>> = cb.watch_close_deadline();
+        let close_yielder: Yielder = Yielder::new();
+        let close_deadline: Option<Instant> = close_deadline_watched.get();
+        let close_deadline_changed = close_deadline_watched.watch(close_yielder).fuse();
+        futures::pin_mut!(close_deadline_changed);
+        let clock_ref: SharedTimer = cb.get_timer();
+        let close_future = match close_deadline {
+            Some(t) => Either::Left(clock_ref.wait_until(t, &yielder).fuse()),
+            None => Either::Right(future::pending()),
+        };
+        futures::pin_mut!(close_future);
+
+        futures::select_biased! {
+            _ = close_deadline_changed => continue,
+            _ = close_future => {
+                match cb.get_close_deadline() {
+                    Some(deadline) if deadline > cb.get_now() => continue,
+                    None => continue,
+                    _ => cb.on_close_timeout(),
+                }
+            },
+        }
+    }
+}
@@ -62,16 +62,21 @@ pub struct Cubic {
 impl CongestionControl for Cubic {
     fn new(mss: usize, seq_no: SeqNumber, options: Option<Options>) -> Box<dyn CongestionControl> {
         let mss: u32 = mss.try_into().unwrap();
-        // The initial value of cwnd is set according to RFC5681, section 3.1, page 7.
-        let initial_cwnd: u32 = match mss {
-            0..=1095 => 4 * mss,
-            1096..=2190 => 3 * mss,
-            _ => 2 * mss,
-        };
-
         let options: Options = options.unwrap_or_default();
         let fast_convergence: bool = options.get_bool("fast_convergence").unwrap_or(true);
 
+        // Unless configured otherwise, the initial value of cwnd is set according to RFC5681, section 3.1, page 7.
+        let initial_cwnd: u32 = match options.get_int("initial_cwnd") {
+            Some(initial_cwnd) => initial_cwnd as u32,
+            None => match mss {
+                0..=1095 => 4 * mss,
+                1096..=2190 => 3 * mss,
+                _ => 2 * mss,
+            },
+        };
+        // According to RFC5681 ssthresh should be initialised 'arbitrarily high'.
+        let initial_ssthresh: u32 = options.get_int("initial_ssthresh").map_or(u32::MAX, |s| s as u32);
+
         Box::new(Self {
             mss,
             // Slow Start / Congestion Avoidance State
@@ -82,8 +87,8 @@ impl CongestionControl for Cubic {
             last_send_time: Cell::new(Instant::now()),
             retransmitted_packets_in_flight: Cell::new(0),
             rtt_at_last_send: Cell::new(Duration::new(1, 0)), // The default RTT is 1 sec.
-            ssthresh: Cell::new(u32::MAX), // According to RFC5681 ssthresh should be initialised 'arbitrarily high'.
-            w_max: Cell::new(0), // Because ssthresh is u32::MAX, this will be set appropriately during the 1st congestion event.
+            ssthresh: Cell::new(initial_ssthresh),
+            w_max: Cell::new(0), // This will be set appropriately during the 1st congestion event.
             last_congestion_was_rto: Cell::new(false),
 
             in_fast_recovery: Cell::new(false),
@@ -276,6 +281,10 @@ impl SlowStartCongestionAvoidance for Cubic {
         self.cwnd.clone()
     }
 
+    fn get_ssthresh(&self) -> u32 {
+        self.ssthresh.get()
+    }
+
     fn on_cwnd_check_before_send(&mut self) {
         let long_time_since_send: bool =
             Instant::now().duration_since(self.last_send_time.get()) > self.rtt_at_last_send.get();
@@ -321,6 +330,23 @@ impl SlowStartCongestionAvoidance for Cubic {
         self.on_rto_ss_ca();
         self.on_rto_fast_recovery(send_unacked);
     }
+
+    fn on_ecn_echo(&mut self) {
+        // Respond to congestion as we would to a loss detected by duplicate ACKs, but without retransmitting.
+        let cwnd: u32 = self.cwnd.get();
+        let reduced_cwnd: u32 = (cwnd as f32 * Self::BETA_CUBIC) as u32;
+
+        if self.fast_convergence {
+            self.fast_convergence();
+        } else {
+            self.w_max.set(cwnd);
+        }
+        self.ssthresh.set(max(reduced_cwnd, 2 * self.mss));
+        self.cwnd.set(max(reduced_cwnd, 2 * self.mss));
+        // Record the time we go back into congestion avoidance.
+        self.ca_start.set(Instant::now());
+        self.last_congestion_was_rto.set(false);
+    }
 }
 
 impl FastRetransmitRecovery for Cubic {
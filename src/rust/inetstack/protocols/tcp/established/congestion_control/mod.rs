@@ -7,7 +7,13 @@ mod options;
 
 use crate::{
     inetstack::protocols::tcp::SeqNumber,
-    runtime::watched::SharedWatchedValue,
+    runtime::{
+        network::config::{
+            CongestionControlType,
+            TcpConfig,
+        },
+        watched::SharedWatchedValue,
+    },
 };
 use ::std::{
     fmt::Debug,
@@ -26,6 +32,11 @@ pub use self::{
 pub trait SlowStartCongestionAvoidance {
     fn get_cwnd(&self) -> SharedWatchedValue<u32>;
 
+    // Slow start threshold.  Congestion control algorithms without one never leave slow start.
+    fn get_ssthresh(&self) -> u32 {
+        u32::MAX
+    }
+
     // Called immediately before the cwnd check is performed before data is sent.
     fn on_cwnd_check_before_send(&mut self) {}
 
@@ -43,6 +54,9 @@ pub trait SlowStartCongestionAvoidance {
 
     // Called immediately before a segment is sent for the 1st time.
     fn on_send(&mut self, _rto: Duration, _num_sent_bytes: u32) {}
+
+    // Called when the peer echoes explicit congestion notification, at most once per window of data.
+    fn on_ecn_echo(&mut self) {}
 }
 
 pub trait FastRetransmitRecovery
@@ -72,3 +86,38 @@ pub trait CongestionControl: SlowStartCongestionAvoidance + FastRetransmitRecove
 }
 
 pub type CongestionControlConstructor = fn(usize, SeqNumber, Option<options::Options>) -> Box<dyn CongestionControl>;
+
+/// Returns the constructor of the congestion control algorithm that `tcp_config` selects.
+pub fn constructor(tcp_config: &TcpConfig) -> CongestionControlConstructor {
+    match tcp_config.get_congestion_control() {
+        CongestionControlType::None => None::new,
+        CongestionControlType::Cubic => Cubic::new,
+    }
+}
+
+/// Builds the congestion control options that carry the initial window settings of `tcp_config` for a connection
+/// whose negotiated MSS is `mss`.
+pub fn initial_window_options(tcp_config: &TcpConfig, mss: usize) -> Option<Options> {
+    if tcp_config.get_initial_cwnd().is_none() && tcp_config.get_initial_ssthresh().is_none() {
+        return Option::None;
+    }
+
+    let mut options: Options = Options::default();
+    if let Some(initial_cwnd) = tcp_config.get_initial_cwnd() {
+        // The initial window must allow at least one full-sized segment to be sent.
+        let initial_cwnd: u32 = if (initial_cwnd as usize) < mss {
+            warn!(
+                "initial cwnd is smaller than the MSS, using the MSS instead (initial_cwnd={}, mss={})",
+                initial_cwnd, mss
+            );
+            mss as u32
+        } else {
+            initial_cwnd
+        };
+        options.insert_int("initial_cwnd".to_string(), initial_cwnd as i64);
+    }
+    if let Some(initial_ssthresh) = tcp_config.get_initial_ssthresh() {
+        options.insert_int("initial_ssthresh".to_string(), initial_ssthresh as i64);
+    }
+    Some(options)
+}
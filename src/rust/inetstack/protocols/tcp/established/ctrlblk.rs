@@ -2,6 +2,7 @@
 // Licensed under the MIT license.
 
 use super::{
+    autotune::ReceiveWindowTuner,
     congestion_control::{
         self,
         CongestionControlConstructor,
@@ -24,12 +25,22 @@ use crate::{
             Ethernet2Header,
         },
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            IPV4_ECN_ECT0,
+        },
         tcp::{
+            metrics::ConnMetrics,
+            observer::{
+                ConnEventKind,
+                SharedConnectionObserver,
+            },
             segment::{
                 TcpHeader,
+                TcpOptions2,
                 TcpSegment,
             },
+            timestamps::Timestamps,
             SeqNumber,
         },
     },
@@ -50,8 +61,13 @@ use crate::{
     },
 };
 use ::std::{
+    cmp,
     collections::VecDeque,
     convert::TryInto,
+    fmt::{
+        self,
+        Write,
+    },
     net::SocketAddrV4,
     ops::{
         Deref,
@@ -63,6 +79,9 @@ use ::std::{
     },
 };
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::md5;
+
 // TODO: Review this value (and its purpose).  It (2048 segments) of 8 KB jumbo packets would limit the unread data to
 // just 16 MB.  If we don't want to lie, that is also about the max window size we should ever advertise.  Whereas TCP
 // with the window scale option allows for window sizes of up to 1 GB.  This value appears to exist more because of the
@@ -113,6 +132,16 @@ struct Receiver {
 
     // Receive queue.  Contains in-order received (and acknowledged) data ready for the application to read.
     recv_queue: AsyncQueue<DemiBuffer>,
+
+    // Sequence number that follows the last byte of urgent data announced by our peer, if any.
+    pub urgent_end: Option<SeqNumber>,
+
+    // Urgent queue.  Contains urgent data that was taken out of the in-band stream, ready for the application to read.
+    urgent_queue: AsyncQueue<DemiBuffer>,
+
+    // Receive low-water mark (akin to SO_RCVLOWAT).  Pops wait for at least this many bytes, unless the end of the
+    // stream comes first.
+    low_water: usize,
 }
 
 impl Receiver {
@@ -121,11 +150,16 @@ impl Receiver {
             reader_next,
             receive_next,
             recv_queue: AsyncQueue::with_capacity(RECV_QUEUE_SZ),
+            urgent_end: None,
+            urgent_queue: AsyncQueue::default(),
+            low_water: 1,
         }
     }
 
     pub async fn pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<DemiBuffer, Fail> {
-        let buf: DemiBuffer = if let Some(size) = size {
+        let buf: DemiBuffer = if self.low_water > 1 {
+            self.pop_low_water(size.unwrap_or(usize::MAX), &yielder).await?
+        } else if let Some(size) = size {
             let mut buf: DemiBuffer = self.recv_queue.pop(&yielder).await?;
             // Split the buffer if it's too big.
             if buf.len() > size {
@@ -142,8 +176,96 @@ impl Receiver {
         Ok(buf)
     }
 
-    pub fn push(&mut self, buf: DemiBuffer) {
+    // Pops at least as many bytes as the low-water mark (but no more than `size`), gathering them from as many buffers
+    // as it takes.  Fewer bytes are only returned if the end of the stream comes first.
+    async fn pop_low_water(&mut self, size: usize, yielder: &Yielder) -> Result<DemiBuffer, Fail> {
+        let max_len: usize = cmp::min(size, u16::MAX as usize);
+        let target: usize = cmp::min(self.low_water, max_len);
+        let mut bufs: Vec<DemiBuffer> = Vec::new();
+        let mut len: usize = 0;
+        while len < target {
+            let buf: DemiBuffer = match self.recv_queue.pop(yielder).await {
+                Ok(buf) => buf,
+                Err(e) => {
+                    // Put back what was gathered so far, so that it is not lost.
+                    for buf in bufs.into_iter().rev() {
+                        self.recv_queue.push_front(buf);
+                    }
+                    return Err(e);
+                },
+            };
+            // An empty buffer marks the end of the stream.  Leave it for the next pop, unless there is nothing else to
+            // hand out.
+            if buf.len() == 0 {
+                if bufs.is_empty() {
+                    return Ok(buf);
+                }
+                self.recv_queue.push_front(buf);
+                break;
+            }
+            len += buf.len();
+            bufs.push(buf);
+        }
+
+        // Put back what does not fit.  Only the last buffer may hold more than that, as we stopped gathering as soon as
+        // we had enough.
+        let mut last: DemiBuffer = bufs.pop().expect("should have gathered at least one buffer");
+        let front_len: usize = len - last.len();
+        let len: usize = cmp::min(len, max_len);
+        if front_len + last.len() > len {
+            let front: DemiBuffer = last.split_front(len - front_len)?;
+            self.recv_queue.push_front(last);
+            last = front;
+        }
+        if bufs.is_empty() {
+            return Ok(last);
+        }
+
+        // Gather the data into a single buffer.
+        bufs.push(last);
+        let mut buf: DemiBuffer = DemiBuffer::new(len as u16);
+        let mut offset: usize = 0;
+        for gathered in bufs {
+            buf[offset..(offset + gathered.len())].copy_from_slice(&gathered[..]);
+            offset += gathered.len();
+        }
+        Ok(buf)
+    }
+
+    pub fn try_pop(&mut self) -> Option<DemiBuffer> {
+        let buf: DemiBuffer = self.recv_queue.try_pop()?;
+        self.reader_next = self.reader_next + SeqNumber::from(buf.len() as u32);
+        Some(buf)
+    }
+
+    pub async fn pop_urgent(&mut self, yielder: Yielder) -> Result<DemiBuffer, Fail> {
+        let buf: DemiBuffer = self.urgent_queue.pop(&yielder).await?;
+        self.reader_next = self.reader_next + SeqNumber::from(buf.len() as u32);
+        Ok(buf)
+    }
+
+    pub fn push(&mut self, mut buf: DemiBuffer) {
         let buf_len: u32 = buf.len() as u32;
+
+        // Take urgent data out of the in-band stream, so that it can only be read through the urgent queue.  Note that
+        // the end-of-stream marker (i.e. an empty buffer) always goes to the receive queue.
+        if let Some(urgent_end) = self.urgent_end.filter(|_| buf_len > 0) {
+            if urgent_end > self.receive_next {
+                let urgent_len: u32 = u32::from(urgent_end - self.receive_next).min(buf_len);
+                if urgent_len == buf_len {
+                    self.urgent_queue.push(buf);
+                    self.receive_next = self.receive_next + SeqNumber::from(buf_len);
+                    return;
+                }
+                let urgent: DemiBuffer = buf
+                    .split_front(urgent_len as usize)
+                    .expect("urgent data should be shorter than the buffer");
+                self.urgent_queue.push(urgent);
+            } else {
+                self.urgent_end = None;
+            }
+        }
+
         self.recv_queue.push(buf);
         self.receive_next = self.receive_next + SeqNumber::from(buf_len as u32);
     }
@@ -179,6 +301,10 @@ pub struct ControlBlock<const N: usize> {
     // Note: The maximum possible advertised window is 1 GiB with window scaling and 64 KiB without.
     receive_buffer_size: u32,
 
+    // Receive window auto-tuning state, if enabled.  The tuner sets the size that the receive buffer should have, which
+    // the receive buffer grows to right away but only shrinks to as the user reads data.
+    receive_window_tuner: Option<ReceiveWindowTuner>,
+
     // TODO: Review how this is used.  We could have separate window scale factors, so there should be one for the
     // receiver and one for the sender.
     // This is the receive-side window scale factor.
@@ -213,8 +339,56 @@ pub struct ControlBlock<const N: usize> {
     // Retransmission Timeout (RTO) calculator.
     rto_calculator: RtoCalculator,
 
+    // Current interval of the persist timer, which runs while our peer advertises a zero window.  It starts at the RTO
+    // and doubles after each window probe, up to the maximum RTO.  The timer is off when this is None.
+    persist_timeout: Option<Duration>,
+
+    // Time at which the connection is cleaned up while it waits in FIN-WAIT-2 or TIME-WAIT.
+    close_deadline: SharedWatchedValue<Option<Instant>>,
+
+    // Earliest time at which the next data segment may be sent, when send pacing is enabled.
+    pacing_release_time: Option<Instant>,
+
+    // Whether explicit congestion notification was negotiated for this connection (see RFC 3168).
+    ecn: bool,
+
+    // Whether we have received a CE-marked segment and should set ECE on our ACKs until our peer sends CWR.
+    ecn_echo: bool,
+
+    // Whether we have reacted to an ECE and should set CWR on the next data segment we send.
+    ecn_cwr: bool,
+
+    // We react to ECE at most once per window of data. This is the value of SND.NXT when we last reacted.
+    ecn_recover: SeqNumber,
+
+    // State of the timestamp option (see RFC 7323), if it was negotiated.
+    timestamps: Option<Timestamps>,
+
+    // Token that identifies this connection across changes of the address of our peer, if one was negotiated.
+    connection_token: Option<u32>,
+
+    // Time at which we last sent or received a segment that occupies sequence space.  Used to close idle connections.
+    last_activity: Instant,
+
+    // Sequence number that follows the last byte of urgent data queued by the user, if any.  Segments carrying data
+    // before this point are sent with the URG flag set.
+    send_urgent_end: Option<SeqNumber>,
+
+    // Whether incoming data is acknowledged right away, instead of waiting for a piggyback opportunity.  This avoids
+    // the latency caused by delayed ACKs on request/response traffic.
+    quickack: bool,
+
+    // Number of duplicate ACKs received, i.e. ACKs that acknowledge nothing new while data is outstanding.
+    duplicate_acks: u64,
+
+    // Number of fast retransmissions triggered by duplicate ACKs.
+    fast_retransmits: u64,
+
     // Result of current operation. For now, this is just used for closing.
     result: AsyncValue<Result<(), Fail>>,
+
+    // Observer notified when this connection is torn down.
+    observer: SharedConnectionObserver,
 }
 
 #[derive(Clone)]
@@ -240,8 +414,31 @@ impl<const N: usize> SharedControlBlock<N> {
         sender_mss: usize,
         cc_constructor: CongestionControlConstructor,
         congestion_control_options: Option<congestion_control::Options>,
+        ecn: bool,
+        timestamps: Option<Timestamps>,
+        connection_token: Option<u32>,
+        observer: SharedConnectionObserver,
     ) -> Self {
-        let sender: Sender<N> = Sender::new(sender_seq_no, sender_window_size, sender_window_scale, sender_mss);
+        let sender: Sender<N> = Sender::new(
+            sender_seq_no,
+            sender_window_size,
+            sender_window_scale,
+            sender_mss,
+            tcp_config.get_coalesce_timeout(),
+        );
+        let now: Instant = runtime.get_now();
+        let rto_calculator: RtoCalculator = RtoCalculator::new(tcp_config.get_rto_min(), tcp_config.get_rto_max());
+        let receive_window_tuner: Option<ReceiveWindowTuner> = if tcp_config.get_enable_window_autotune() {
+            // The window cannot grow past what the window field of the header can carry at our window scale.
+            let max: u32 = cmp::min(
+                tcp_config.get_max_receive_window_size(),
+                (u16::MAX as u32) << receiver_window_scale,
+            )
+            .max(receiver_window_size);
+            Some(ReceiveWindowTuner::new(receiver_window_size, max, now, receiver_seq_no))
+        } else {
+            None
+        };
         Self(SharedObject::<ControlBlock<N>>::new(ControlBlock::<N> {
             local,
             remote,
@@ -255,6 +452,7 @@ impl<const N: usize> SharedControlBlock<N> {
             ack_delay_timeout,
             ack_deadline: SharedWatchedValue::new(None),
             receive_buffer_size: receiver_window_size,
+            receive_window_tuner,
             window_scale: receiver_window_scale,
             out_of_order: VecDeque::new(),
             out_of_order_fin: Option::None,
@@ -262,8 +460,23 @@ impl<const N: usize> SharedControlBlock<N> {
             user_is_done_sending: false,
             cc: cc_constructor(sender_mss, sender_seq_no, congestion_control_options),
             retransmit_deadline: SharedWatchedValue::new(None),
-            rto_calculator: RtoCalculator::new(),
+            rto_calculator,
+            persist_timeout: None,
+            close_deadline: SharedWatchedValue::new(None),
+            pacing_release_time: None,
+            ecn,
+            ecn_echo: false,
+            ecn_cwr: false,
+            ecn_recover: sender_seq_no,
+            timestamps,
+            connection_token,
+            last_activity: now,
+            send_urgent_end: None,
+            quickack: false,
+            duplicate_acks: 0,
+            fast_retransmits: 0,
             result: AsyncValue::default(),
+            observer,
         }))
     }
 
@@ -285,6 +498,17 @@ impl<const N: usize> SharedControlBlock<N> {
         self.sender.send(buf, self_)
     }
 
+    pub fn send_urgent(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
+        let previous_urgent_end: Option<SeqNumber> = self.send_urgent_end;
+        let urgent_end: SeqNumber = self.sender.get_unsent_seq_no().get() + SeqNumber::from(buf.len() as u32);
+        self.send_urgent_end = Some(urgent_end);
+        if let Err(e) = self.send(buf) {
+            self.send_urgent_end = previous_urgent_end;
+            return Err(e);
+        }
+        Ok(())
+    }
+
     pub fn retransmit(&self) {
         self.sender.retransmit(self.clone())
     }
@@ -294,6 +518,7 @@ impl<const N: usize> SharedControlBlock<N> {
     }
 
     pub fn congestion_control_on_fast_retransmit(&mut self) {
+        self.fast_retransmits += 1;
         self.cc.on_fast_retransmit()
     }
 
@@ -317,6 +542,38 @@ impl<const N: usize> SharedControlBlock<N> {
         self.cc.get_limited_transmit_cwnd_increase()
     }
 
+    /// Returns the time at which the next data segment may be sent, if send pacing currently holds it back.
+    pub fn pacing_get_release_time(&self) -> Option<Instant> {
+        match self.pacing_release_time {
+            Some(release_time) if release_time > self.get_now() => Some(release_time),
+            _ => None,
+        }
+    }
+
+    /// Accounts for `num_sent_bytes` being sent, pushing back the time at which the next data segment may be sent.
+    pub fn pacing_on_send(&mut self, num_sent_bytes: usize) {
+        if !self.tcp_config.get_enable_pacing() {
+            return;
+        }
+
+        // Use the configured rate, if any. Otherwise, spread one congestion window worth of data across one RTT.
+        let rate: f64 = match self.tcp_config.get_pacing_rate() {
+            0 => self.cc.get_cwnd().get() as f64 / self.rto_calculator.srtt().as_secs_f64(),
+            rate => rate as f64,
+        };
+        if !rate.is_normal() {
+            return;
+        }
+
+        // Do not accumulate credit while the connection is idle.
+        let now: Instant = self.get_now();
+        let start: Instant = match self.pacing_release_time {
+            Some(release_time) if release_time > now => release_time,
+            _ => now,
+        };
+        self.pacing_release_time = Some(start + Duration::from_secs_f64(num_sent_bytes as f64 / rate));
+    }
+
     pub fn get_mss(&self) -> usize {
         self.sender.get_mss()
     }
@@ -333,10 +590,29 @@ impl<const N: usize> SharedControlBlock<N> {
         self.sender.get_unsent_seq_no()
     }
 
+    pub fn get_corked(&self) -> SharedWatchedValue<bool> {
+        self.sender.get_corked()
+    }
+
+    /// Corks or uncorks the connection. While it is corked, small pushes are coalesced and held back until it is
+    /// uncorked.
+    pub fn set_cork(&mut self, enable: bool) {
+        self.sender.set_cork(enable);
+    }
+
     pub fn get_send_next(&self) -> SharedWatchedValue<SeqNumber> {
         self.sender.get_send_next()
     }
 
+    pub fn get_coalesce_deadline(&self) -> SharedWatchedValue<Option<Instant>> {
+        self.sender.get_coalesce_deadline()
+    }
+
+    /// Lets the background sender flush the small pushes that are held back for coalescing.
+    pub fn flush_coalesced(&mut self) {
+        self.sender.flush_coalesced();
+    }
+
     pub fn modify_send_next(&mut self, f: impl FnOnce(SeqNumber) -> SeqNumber) {
         self.sender.modify_send_next(f)
     }
@@ -353,10 +629,49 @@ impl<const N: usize> SharedControlBlock<N> {
         self.retransmit_deadline.clone()
     }
 
+    pub fn get_close_deadline(&self) -> Option<Instant> {
+        self.close_deadline.get()
+    }
+
+    pub fn watch_close_deadline(&self) -> SharedWatchedValue<Option<Instant>> {
+        self.close_deadline.clone()
+    }
+
+    /// Enters TIME-WAIT, (re)starting the time-wait timer and turning off the other timers.
+    fn enter_time_wait(&mut self, now: Instant) {
+        self.state = State::TimeWait;
+        self.retransmit_deadline.set(None);
+        let deadline: Instant = now + self.tcp_config.get_time_wait_duration();
+        self.close_deadline.set(Some(deadline));
+    }
+
+    /// Cleans up a connection that waited in FIN-WAIT-2 or TIME-WAIT for long enough.
+    pub fn on_close_timeout(&mut self) {
+        match self.state {
+            State::FinWait2 => {
+                // Our peer never sent its FIN, so give up on it.
+                info!("Connection timed out in FIN-WAIT-2");
+                self.observer.notify(self.local, self.remote, ConnEventKind::Closed);
+            },
+            // The teardown was already reported when we entered TIME-WAIT.
+            State::TimeWait => (),
+            // The connection left the waiting states in the meantime.
+            _ => return,
+        }
+        self.state = State::Closed;
+        self.close_deadline.set(None);
+        self.result.set(Ok(()));
+    }
+
     pub fn push_unacked_segment(&self, segment: UnackedSegment) {
         self.sender.push_unacked_segment(segment)
     }
 
+    /// Checks if the timestamp option was negotiated, in which case round-trip times are sampled from it.
+    pub fn has_timestamps(&self) -> bool {
+        self.timestamps.is_some()
+    }
+
     pub fn rto_add_sample(&mut self, rtt: Duration) {
         self.rto_calculator.add_sample(rtt)
     }
@@ -369,6 +684,119 @@ impl<const N: usize> SharedControlBlock<N> {
         self.rto_calculator.back_off()
     }
 
+    /// Starts the persist timer, unless it is already running, and returns its current interval.
+    pub fn persist_start(&mut self) -> Duration {
+        let rto: Duration = self.rto_calculator.rto();
+        *self.persist_timeout.get_or_insert(rto)
+    }
+
+    /// Doubles the interval of the persist timer after a window probe was sent, and returns it. The interval is capped
+    /// at the maximum RTO.
+    pub fn persist_back_off(&mut self) -> Duration {
+        let timeout: Duration = cmp::min(self.persist_start() * 2, self.tcp_config.get_rto_max());
+        self.persist_timeout = Some(timeout);
+        timeout
+    }
+
+    /// Stops the persist timer, as the send window opened up.
+    pub fn persist_stop(&mut self) {
+        self.persist_timeout = None;
+    }
+
+    /// Takes a snapshot of the round-trip time and congestion control state of this connection.
+    pub fn metrics(&self) -> ConnMetrics {
+        let send_unacknowledged: SeqNumber = self.sender.get_send_unacked().get();
+        let send_next: SeqNumber = self.sender.get_send_next().get();
+        ConnMetrics {
+            srtt: self.rto_calculator.srtt(),
+            rttvar: self.rto_calculator.rttvar(),
+            cwnd: self.cc.get_cwnd().get(),
+            ssthresh: self.cc.get_ssthresh(),
+            bytes_in_flight: (send_next - send_unacknowledged).into(),
+            duplicate_acks: self.duplicate_acks,
+            fast_retransmits: self.fast_retransmits,
+        }
+    }
+
+    /// Formats the full state of this connection into a human-readable report, for troubleshooting wedged connections.
+    /// This function is read-only.
+    pub fn dump(&self) -> String {
+        let mut out: String = String::new();
+        if let Err(e) = self.write_dump(&mut out) {
+            warn!("dump(): cannot format control block (error={:?})", e);
+        }
+        out
+    }
+
+    /// Writes the report of [Self::dump] into `out`.
+    fn write_dump(&self, out: &mut String) -> fmt::Result {
+        let now: Instant = self.get_now();
+        let deadline = |deadline: Option<Instant>| -> String {
+            match deadline {
+                Some(deadline) => format!("in {:?}", deadline.saturating_duration_since(now)),
+                None => String::from("off"),
+            }
+        };
+
+        writeln!(
+            out,
+            "tcb: local={} remote={} state={:?}",
+            self.local, self.remote, self.state
+        )?;
+        self.sender.dump(out, now)?;
+        writeln!(
+            out,
+            "recv: nxt={} reader_nxt={} wnd={} buffer_size={} wnd_scale={} unread_buffers={} urgent_end={:?} \
+             urgent_buffers={}",
+            self.receiver.receive_next,
+            self.receiver.reader_next,
+            self.get_receive_window_size(),
+            self.receive_buffer_size,
+            self.window_scale,
+            self.receiver.recv_queue.len(),
+            self.receiver.urgent_end,
+            self.receiver.urgent_queue.len()
+        )?;
+        writeln!(
+            out,
+            "out_of_order: segments={} fin={:?}",
+            self.out_of_order.len(),
+            self.out_of_order_fin
+        )?;
+        for (seq_num, buf) in self.out_of_order.iter() {
+            writeln!(out, "  seq={} len={}", seq_num, buf.len())?;
+        }
+        writeln!(
+            out,
+            "timers: retransmit={} ack={} close={} persist={:?} rto={:?} srtt={:?} rttvar={:?}",
+            deadline(self.retransmit_deadline.get()),
+            deadline(self.ack_deadline.get()),
+            deadline(self.close_deadline.get()),
+            self.persist_timeout,
+            self.rto_calculator.rto(),
+            self.rto_calculator.srtt(),
+            self.rto_calculator.rttvar()
+        )?;
+        writeln!(
+            out,
+            "congestion: cwnd={} ssthresh={} duplicate_acks={} fast_retransmits={} ecn={} ecn_echo={} ecn_cwr={}",
+            self.cc.get_cwnd().get(),
+            self.cc.get_ssthresh(),
+            self.duplicate_acks,
+            self.fast_retransmits,
+            self.ecn,
+            self.ecn_echo,
+            self.ecn_cwr
+        )?;
+        writeln!(
+            out,
+            "flags: user_is_done_sending={} quickack={} idle_for={:?}",
+            self.user_is_done_sending,
+            self.quickack,
+            now.saturating_duration_since(self.last_activity)
+        )
+    }
+
     pub fn unsent_top_size(&self) -> Option<usize> {
         self.sender.top_size_unsent()
     }
@@ -391,7 +819,7 @@ impl<const N: usize> SharedControlBlock<N> {
 
     // This is the main TCP receive routine.
     //
-    pub fn receive(&mut self, mut header: TcpHeader, mut data: DemiBuffer) {
+    pub fn receive(&mut self, mut header: TcpHeader, mut data: DemiBuffer, congestion_experienced: bool) {
         debug!(
             "{:?} Connection Receiving {} bytes + {:?}",
             self.state,
@@ -405,6 +833,16 @@ impl<const N: usize> SharedControlBlock<N> {
         // the packet.  However, this is wasteful if we don't take a path below that actually uses it.  Review this.
         let now: Instant = self.get_timer().now();
 
+        // Reject old duplicate segments, whose timestamp is older than the most recent one of our peer (PAWS, see RFC
+        // 7323 Section 5.3).  ACK (if not RST) and drop.
+        let timestamp: Option<(u32, u32)> = header.get_timestamp();
+        if let (Some(timestamps), Some((value, _))) = (self.timestamps, timestamp) {
+            if timestamps.is_old(value) && !header.rst {
+                self.send_ack();
+                return;
+            }
+        }
+
         // Check to see if the segment is acceptable sequence-wise (i.e. contains some data that fits within the receive
         // window, or is a non-data segment with a sequence number that falls within the window).  Unacceptable segments
         // should be ACK'd (unless they are RSTs), and then dropped.
@@ -517,7 +955,21 @@ impl<const N: usize> SharedControlBlock<N> {
         // this point, and only proceed onwards if seg_start == receive_next.  But we process any RSTs, SYNs, or ACKs
         // we receive (as long as they're in the window) as we receive them, even if they're out-of-order.  It's only
         // when we get to processing the data (and FIN) that we store aside any out-of-order segments for later.
-        debug_assert!(receive_next <= seg_start && seg_end < after_receive_window);
+        // A segment that occupies no sequence space (e.g. a pure ACK) is acceptable even when the window is closed.
+        debug_assert!(receive_next <= seg_start && (seg_len == 0 || seg_end < after_receive_window));
+
+        // Remember the timestamp of our peer to echo it back, unless the segment starts past what we expected (see RFC
+        // 7323 Section 4.3). We check against RCV.NXT in lieu of the last acknowledgement number we sent.
+        if let (Some(timestamps), Some((value, _))) = (self.timestamps.as_mut(), timestamp) {
+            if header.seq_num <= receive_next {
+                timestamps.update(value);
+            }
+        }
+
+        // Anything that occupies sequence space counts as activity on this connection.
+        if seg_len > 0 {
+            self.last_activity = now;
+        }
 
         // Check the RST bit.
         if header.rst {
@@ -534,6 +986,7 @@ impl<const N: usize> SharedControlBlock<N> {
 
                     // Enter Closed state.
                     self.state = State::Closed;
+                    self.observer.notify(self.local, self.remote, ConnEventKind::Reset);
 
                     // TODO: Delete the ControlBlock.
                     return;
@@ -541,6 +994,11 @@ impl<const N: usize> SharedControlBlock<N> {
 
                 // Closing states.
                 State::Closing | State::LastAck | State::TimeWait => {
+                    // The teardown was already reported when we entered TIME-WAIT.
+                    if self.state != State::TimeWait {
+                        self.observer.notify(self.local, self.remote, ConnEventKind::Reset);
+                    }
+
                     // Enter Closed state.
                     self.state = State::Closed;
 
@@ -570,6 +1028,7 @@ impl<const N: usize> SharedControlBlock<N> {
 
             // Enter Closed state.
             self.state = State::Closed;
+            self.observer.notify(self.local, self.remote, ConnEventKind::Reset);
 
             // TODO: Delete the ControlBlock.
             return;
@@ -585,6 +1044,11 @@ impl<const N: usize> SharedControlBlock<N> {
         // TODO: RFC 5961 "Blind Data Injection Attack" prevention would have us perform additional ACK validation
         // checks here.
 
+        // Process explicit congestion notification signals (see RFC 3168).
+        if self.ecn {
+            self.process_ecn(&header, congestion_experienced);
+        }
+
         // Process the ACK.
         // Note: We process valid ACKs while in any synchronized state, even though there shouldn't be anything to do
         // in some states (e.g. TIME-WAIT) as it is more wasteful to always check that we're not in TIME-WAIT.
@@ -595,6 +1059,11 @@ impl<const N: usize> SharedControlBlock<N> {
         let send_unacknowledged: SeqNumber = self.sender.get_send_unacked().get();
         let send_next: SeqNumber = self.sender.get_send_next().get();
 
+        // Keep track of duplicate ACKs (see RFC 5681 Section 2) for diagnostics.
+        if header.ack_num == send_unacknowledged && send_unacknowledged != send_next && data.is_empty() {
+            self.duplicate_acks += 1;
+        }
+
         // TODO: Restructure this call into congestion control to either integrate it directly or make it more fine-
         // grained.  It currently duplicates the new/duplicate ack check itself internally, which is inefficient.
         // We should either make separate calls for each case or integrate those cases directly.
@@ -607,6 +1076,12 @@ impl<const N: usize> SharedControlBlock<N> {
                 // This segment acknowledges new data (possibly and/or FIN).
                 let bytes_acknowledged: u32 = (header.ack_num - send_unacknowledged).into();
 
+                // Sample the round-trip time from the timestamp echoed by our peer. Unlike transmission times, it is
+                // not ambiguous for retransmitted segments (see RFC 7323 Section 4.1).
+                if let (Some(timestamps), Some((_, echo))) = (self.timestamps, timestamp) {
+                    self.rto_calculator.add_sample(timestamps.rtt(echo, now));
+                }
+
                 // Remove the now acknowledged data from the unacknowledged queue.
                 self.sender
                     .remove_acknowledged_data(self.clone(), bytes_acknowledged, now);
@@ -627,12 +1102,15 @@ impl<const N: usize> SharedControlBlock<N> {
                     match self.state {
                         State::Established => (), // Common case.  Nothing more to do.
                         State::FinWait1 => {
-                            // Our FIN is now ACK'd, so enter FIN-WAIT-2.
+                            // Our FIN is now ACK'd, so enter FIN-WAIT-2 and wait for our peer's FIN for a while.
                             self.state = State::FinWait2;
+                            let deadline: Instant = now + self.tcp_config.get_fin_wait_timeout();
+                            self.close_deadline.set(Some(deadline));
                         },
                         State::Closing => {
                             // Our FIN is now ACK'd, so enter TIME-WAIT.
-                            self.state = State::TimeWait;
+                            self.enter_time_wait(now);
+                            self.observer.notify(self.local, self.remote, ConnEventKind::Closed);
                         },
                         State::LastAck => {
                             // Our FIN is now ACK'd, so this connection can be safely closed.  In LAST-ACK state we
@@ -641,8 +1119,9 @@ impl<const N: usize> SharedControlBlock<N> {
                             // but we had already sent everything we're ever going to send (incl. FIN) at least once).
                             self.state = State::Closed;
                             self.result.set(Ok(()));
+                            self.observer.notify(self.local, self.remote, ConnEventKind::Closed);
                         },
-                        // TODO: Handle TimeWait to Closed transition.
+                        // The time-wait timer handles the TIME-WAIT to CLOSED transition.
                         _ => (),
                     }
                 } else {
@@ -664,9 +1143,18 @@ impl<const N: usize> SharedControlBlock<N> {
             // TODO: Implement fast-retransmit.  In which case, we'd increment our dup-ack counter here.
         }
 
-        // TODO: Check the URG bit.  If we decide to support this, how should we do it?
-        if header.urg {
-            warn!("Got packet with URG bit set!");
+        // Remember where urgent data ends, so that it gets diverted from the in-band stream when it is received.
+        // Note: We follow the interpretation of RFC 6093, in which the urgent pointer refers to the byte that follows
+        // the urgent data.
+        if header.urg && header.urgent_pointer > 0 {
+            let urgent_end: SeqNumber = header.seq_num + SeqNumber::from(header.urgent_pointer as u32);
+            let is_newer: bool = match self.receiver.urgent_end {
+                Some(end) => urgent_end > end,
+                None => true,
+            };
+            if is_newer && urgent_end > receive_next {
+                self.receiver.urgent_end = Some(urgent_end);
+            }
         }
 
         // We can only process in-order data (or FIN).  Check for out-of-order segment.
@@ -704,6 +1192,7 @@ impl<const N: usize> SharedControlBlock<N> {
                 State::Established | State::FinWait1 | State::FinWait2 => {
                     // We can only legitimately receive data in ESTABLISHED, FIN-WAIT-1, and FIN-WAIT-2.
                     header.fin |= self.receive_data(seg_start, data);
+                    self.tune_receive_window(now, timestamp);
                     should_schedule_ack = true;
                 },
                 state => warn!("Ignoring data received after FIN (in state {:?}).", state),
@@ -729,12 +1218,13 @@ impl<const N: usize> SharedControlBlock<N> {
                 },
                 State::FinWait2 => {
                     // Enter TIME-WAIT.
-                    self.state = State::TimeWait;
-                    // TODO: Start the time-wait timer and turn off the other timers.
+                    self.enter_time_wait(now);
+                    self.observer.notify(self.local, self.remote, ConnEventKind::Closed);
                 },
                 State::CloseWait | State::Closing | State::LastAck => (), // Remain in current state.
                 State::TimeWait => {
-                    // TODO: Remain in TIME-WAIT.  Restart the 2 MSL time-wait timeout.
+                    // Remain in TIME-WAIT, as our peer retransmitted its FIN.  Restart the time-wait timer.
+                    self.enter_time_wait(now);
                 },
                 state => panic!("Bad TCP state {:?}", state), // Should never happen.
             }
@@ -754,7 +1244,11 @@ impl<const N: usize> SharedControlBlock<N> {
         if should_schedule_ack {
             // We should ACK this segment, preferably via piggybacking on a response.
             // TODO: Consider replacing the delayed ACK timer with a simple flag.
-            if self.ack_deadline.get().is_none() {
+            if self.quickack {
+                // Quick ACK mode is on, so don't wait for a piggyback opportunity.
+                self.ack_deadline.set(None);
+                self.send_ack();
+            } else if self.ack_deadline.get().is_none() {
                 // Start the delayed ACK timer to ensure an ACK gets sent soon even if no piggyback opportunity occurs.
                 let timeout: Duration = self.ack_delay_timeout;
                 self.ack_deadline.set(Some(now + timeout));
@@ -787,6 +1281,9 @@ impl<const N: usize> SharedControlBlock<N> {
         // The emit function updates the state.
         debug_assert!((self.state == State::Established) || (self.state == State::CloseWait));
 
+        // Flush any data that was held back, as nothing else may be pushed from now on.
+        self.sender.set_cork(false);
+
         // Send a FIN.
         let fin_buf: DemiBuffer = DemiBuffer::new(0);
         self.send(fin_buf).expect("send failed");
@@ -811,10 +1308,39 @@ impl<const N: usize> SharedControlBlock<N> {
         header.ack = true;
         header.ack_num = self.receiver.receive_next;
 
+        // Keep echoing congestion back to our peer until it tells us that it has reacted.
+        header.ece = self.ecn_echo;
+
+        // Stamp the segment and echo the most recent timestamp of our peer.
+        if let Some(ref timestamps) = self.timestamps {
+            header.push_option(TcpOptions2::Timestamp {
+                sender_timestamp: timestamps.value(self.get_now()),
+                echo_timestamp: timestamps.echo(),
+            });
+        }
+
+        // Carry the connection token, so that our peer still finds this connection if our address changes.
+        if let Some(token) = self.connection_token {
+            header.push_option(TcpOptions2::ConnectionToken(token));
+        }
+
         // Return this header.
         header
     }
 
+    /// Resets the connection, so that our peer gives up on it right away. A RST is sent and the connection is closed.
+    pub fn abort(&mut self) {
+        let mut header: TcpHeader = self.tcp_header();
+        header.seq_num = self.get_send_next().get();
+        header.rst = true;
+        if let Some(remote_link_addr) = self.arp().try_query(self.remote.ip().clone()) {
+            self.emit(header, None, remote_link_addr);
+        }
+        self.state = State::Closed;
+        self.retransmit_deadline.set(None);
+        self.observer.notify(self.local, self.remote, ConnEventKind::Reset);
+    }
+
     /// Send an ACK to our peer, reflecting our current state.
     pub fn send_ack(&mut self) {
         let mut header: TcpHeader = self.tcp_header();
@@ -832,7 +1358,7 @@ impl<const N: usize> SharedControlBlock<N> {
 
     /// Transmit this message to our connected peer.
     ///
-    pub fn emit(&mut self, header: TcpHeader, body: Option<DemiBuffer>, remote_link_addr: MacAddress) {
+    pub fn emit(&mut self, mut header: TcpHeader, body: Option<DemiBuffer>, remote_link_addr: MacAddress) {
         // Only perform this debug print in debug builds.  debug_assertions is compiler set in non-optimized builds.
         #[cfg(debug_assertions)]
         if body.is_some() {
@@ -846,15 +1372,44 @@ impl<const N: usize> SharedControlBlock<N> {
 
         let sent_fin: bool = header.fin;
 
+        // Anything that occupies sequence space counts as activity on this connection.
+        if sent_fin || body.as_ref().map_or(false, |body| !body.is_empty()) {
+            self.last_activity = self.get_now();
+        }
+
+        let mut ipv4_hdr: Ipv4Header =
+            Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP);
+
+        // Flag data segments that carry urgent data, until all of it has been acknowledged.
+        if let Some(urgent_end) = self.send_urgent_end {
+            if self.sender.get_send_unacked().get() >= urgent_end {
+                self.send_urgent_end = None;
+            } else if header.seq_num < urgent_end && body.as_ref().map_or(false, |body| !body.is_empty()) {
+                header.urg = true;
+                header.urgent_pointer = u32::from(urgent_end - header.seq_num).min(u16::MAX as u32) as u16;
+            }
+        }
+
+        // Only data segments are marked as ECN-capable (see RFC 3168 Section 6.1.4).
+        if self.ecn && body.as_ref().map_or(false, |body| !body.is_empty()) {
+            ipv4_hdr.set_ecn(IPV4_ECN_ECT0);
+            if self.ecn_cwr {
+                header.cwr = true;
+                self.ecn_cwr = false;
+            }
+        }
+
         // Prepare description of TCP segment to send.
         // TODO: Change this to call lower levels to fill in their header information, handle routing, ARPing, etc.
         let segment = TcpSegment {
             ethernet2_hdr: Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
-            ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), self.remote.ip().clone(), IpProtocol::TCP),
+            ipv4_hdr,
             tcp_hdr: header,
             data: body,
             tx_checksum_offload: self.tcp_config.get_tx_checksum_offload(),
         };
+        #[cfg(feature = "tcp-md5")]
+        let segment: TcpSegment = md5::sign(segment, &self.tcp_config);
 
         // Call the runtime to send the segment.
         self.transport.transmit(Box::new(segment));
@@ -880,10 +1435,139 @@ impl<const N: usize> SharedControlBlock<N> {
         }
     }
 
+    // This routine processes the ECN-related signals carried by an incoming segment on an ECN-capable connection.
+    //
+    fn process_ecn(&mut self, header: &TcpHeader, congestion_experienced: bool) {
+        // Receiver side: our peer has reacted to the congestion we echoed, so stop echoing it.  A CE mark on this same
+        // segment signals new congestion, so echo that.
+        if header.cwr {
+            self.ecn_echo = false;
+        }
+        if congestion_experienced {
+            self.ecn_echo = true;
+        }
+
+        // Sender side: our peer saw congestion along the path.  React as we would to a loss, but at most once per
+        // window of data.
+        if header.ece && header.ack_num > self.ecn_recover {
+            info!("Received ECE");
+            self.ecn_recover = self.sender.get_send_next().get();
+            self.cc.on_ecn_echo();
+            self.ecn_cwr = true;
+        }
+    }
+
     pub fn remote_mss(&self) -> usize {
         self.sender.remote_mss()
     }
 
+    /// Returns the number of bytes that were pushed by the user but not yet acknowledged by our peer, including the
+    /// ones that were not sent at all.
+    pub fn send_queue_len(&self) -> usize {
+        let send_unacked: SeqNumber = self.sender.get_send_unacked().get();
+        let unsent_seq_no: SeqNumber = self.sender.get_unsent_seq_no().get();
+        u32::from(unsent_seq_no - send_unacked) as usize
+    }
+
+    pub fn get_state(&self) -> State {
+        self.state
+    }
+
+    pub fn get_idle_timeout(&self) -> Option<Duration> {
+        self.tcp_config.get_idle_timeout()
+    }
+
+    /// Turns quick ACK mode on or off. While it is on, incoming data is acknowledged as soon as it arrives.
+    pub fn set_quickack(&mut self, enable: bool) {
+        self.quickack = enable;
+    }
+
+    /// Sets the receive low-water mark, so that pops wait for at least `bytes` bytes unless the end of the stream comes
+    /// first. A low-water mark of zero is treated as one. It cannot exceed the receive buffer, as it would never be
+    /// reached.
+    pub fn set_rcvlowat(&mut self, bytes: usize) -> Result<(), Fail> {
+        if bytes > self.receive_buffer_size as usize {
+            let cause: String = format!("low-water mark exceeds the receive buffer (bytes={:?})", bytes);
+            error!("set_rcvlowat(): {}", cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        self.receiver.low_water = cmp::max(bytes, 1);
+        Ok(())
+    }
+
+    /// Acknowledges all data received so far right away, instead of waiting for the delayed ACK timer.
+    pub fn flush_ack(&mut self) {
+        self.ack_deadline.set(None);
+        self.send_ack();
+    }
+
+    /// Overrides the congestion window. The congestion control algorithm carries on from this value, if it ever adjusts
+    /// the congestion window.
+    #[cfg(feature = "experimental")]
+    pub fn set_cwnd(&mut self, cwnd: u32) {
+        self.cc.get_cwnd().set(cwnd);
+    }
+
+    /// Returns the token that identifies this connection across changes of the address of our peer, if one was
+    /// negotiated.
+    #[cfg(feature = "tcp-migration")]
+    pub fn get_connection_token(&self) -> Option<u32> {
+        self.connection_token
+    }
+
+    /// Moves this connection over to `remote`, which is the new address of our peer, if `header` could have been sent
+    /// by our peer. The byte stream carries on as if nothing happened. Stale or forged segments are refused, so that
+    /// they cannot take the connection over.
+    #[cfg(feature = "tcp-migration")]
+    pub fn rebind(&mut self, remote: SocketAddrV4, header: &TcpHeader, data_len: usize) -> Result<(), Fail> {
+        if !self.is_acceptable(header, data_len) {
+            let cause: String = format!("segment does not belong to this connection (remote={:?})", remote);
+            warn!("rebind(): {}", &cause);
+            return Err(Fail::new(libc::EBADMSG, &cause));
+        }
+        info!("Connection migrated: {:?} -> {:?}", self.remote, remote);
+        self.remote = remote;
+        Ok(())
+    }
+
+    /// Checks whether `header` could have been sent by our peer, i.e. whether it is not an old duplicate, it falls
+    /// within the receive window (see RFC 793 Section 3.3), and it acknowledges data that we sent but that was not
+    /// acknowledged yet.
+    #[cfg(feature = "tcp-migration")]
+    fn is_acceptable(&self, header: &TcpHeader, data_len: usize) -> bool {
+        if header.syn || header.rst || !header.ack {
+            return false;
+        }
+
+        // Old duplicates are never acceptable (PAWS, see RFC 7323 Section 5.3).
+        if let (Some(timestamps), Some((value, _))) = (self.timestamps, header.get_timestamp()) {
+            if timestamps.is_old(value) {
+                return false;
+            }
+        }
+
+        // SND.UNA =< SEG.ACK =< SND.NXT
+        if header.ack_num < self.sender.get_send_unacked().get() || header.ack_num > self.sender.get_send_next().get() {
+            return false;
+        }
+
+        let receive_next: SeqNumber = self.receiver.receive_next;
+        let receive_window: u32 = self.get_receive_window_size();
+        let after_receive_window: SeqNumber = receive_next + SeqNumber::from(receive_window);
+        let in_window = |seq_no: SeqNumber| -> bool { seq_no >= receive_next && seq_no < after_receive_window };
+        let seg_len: u32 = data_len as u32 + if header.fin { 1 } else { 0 };
+        match (seg_len, receive_window) {
+            (0, 0) => header.seq_num == receive_next,
+            (0, _) => in_window(header.seq_num),
+            (_, 0) => false,
+            (_, _) => in_window(header.seq_num) || in_window(header.seq_num + SeqNumber::from(seg_len - 1)),
+        }
+    }
+
+    pub fn get_last_activity(&self) -> Instant {
+        self.last_activity
+    }
+
     pub fn get_ack_deadline(&self) -> SharedWatchedValue<Option<Instant>> {
         self.ack_deadline.clone()
     }
@@ -919,7 +1603,61 @@ impl<const N: usize> SharedControlBlock<N> {
         //  if self.receiver.reader_next.get() == self.receiver.receive_next.get() {
         // But that will think data is available to be read once we've received a FIN, because FINs consume sequence
         // number space.  Now we call is_empty() on the receive queue instead.
-        self.receiver.pop(size, yielder).await
+        let buf: DemiBuffer = self.receiver.pop(size, yielder).await?;
+        self.shrink_receive_buffer(buf.len());
+        Ok(buf)
+    }
+
+    pub async fn pop_urgent(&mut self, yielder: Yielder) -> Result<DemiBuffer, Fail> {
+        let buf: DemiBuffer = self.receiver.pop_urgent(yielder).await?;
+        self.shrink_receive_buffer(buf.len());
+        Ok(buf)
+    }
+
+    /// Takes the next buffer of in-order data out of the receive queue, if any, without waiting for it.
+    pub fn try_pop(&mut self) -> Option<DemiBuffer> {
+        let buf: DemiBuffer = self.receiver.try_pop()?;
+        self.shrink_receive_buffer(buf.len());
+        Some(buf)
+    }
+
+    /// Feeds the receive window tuner, if any, with a segment of data that carried `timestamp`, and grows the receive
+    /// buffer if the tuner asks for a bigger one.  The round-trip time is sampled on the receiving side, as our own
+    /// estimate does not move on a connection that only receives data.
+    fn tune_receive_window(&mut self, now: Instant, timestamp: Option<(u32, u32)>) {
+        let receive_next: SeqNumber = self.receiver.receive_next;
+        let receive_buffer_size: u32 = self.receive_buffer_size;
+        let timestamps: Option<Timestamps> = self.timestamps;
+        let target: u32 = match self.receive_window_tuner.as_mut() {
+            Some(tuner) => {
+                match (timestamps, timestamp) {
+                    // An echo of zero means that our peer has nothing to echo yet (see RFC 7323 Section 3.2).
+                    (Some(timestamps), Some((_, echo))) if echo != 0 => tuner.sample_rtt(timestamps.rtt(echo, now)),
+                    (Some(_), _) => (),
+                    (None, _) => tuner.sample_rtt_from_window(now, receive_next, receive_buffer_size),
+                }
+                if !tuner.update(now, receive_next) {
+                    return;
+                }
+                tuner.target()
+            },
+            None => return,
+        };
+        if target > receive_buffer_size {
+            debug!("Receive buffer grows: {} -> {}", receive_buffer_size, target);
+            self.receive_buffer_size = target;
+        }
+    }
+
+    /// Shrinks the receive buffer towards the size set by the receive window tuner, after the user read `len` bytes.
+    /// The right edge of the receive window is RCV.NXT plus the window, that is, the sequence number of the next byte
+    /// to read plus the receive buffer size.  Shrinking by no more than what was read keeps that edge from moving back,
+    /// as RFC 9293 asks.
+    fn shrink_receive_buffer(&mut self, len: usize) {
+        if let Some(target) = self.receive_window_tuner.as_ref().map(|tuner| tuner.target()) {
+            let excess: u32 = self.receive_buffer_size.saturating_sub(target);
+            self.receive_buffer_size -= cmp::min(excess, len as u32);
+        }
     }
 
     // This routine remembers that we have received an out-of-order FIN.
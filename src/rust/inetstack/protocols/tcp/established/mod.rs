@@ -1,6 +1,7 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod autotune;
 mod background;
 pub mod congestion_control;
 mod ctrlblk;
@@ -12,7 +13,10 @@ use crate::{
         protocols::tcp::{
             congestion_control::CongestionControlConstructor,
             established::ctrlblk::SharedControlBlock,
+            metrics::ConnMetrics,
+            observer::SharedConnectionObserver,
             segment::TcpHeader,
+            timestamps::Timestamps,
             SeqNumber,
         },
         MacAddress,
@@ -69,7 +73,11 @@ impl<const N: usize> EstablishedSocket<N> {
         sender_mss: usize,
         cc_constructor: CongestionControlConstructor,
         congestion_control_options: Option<congestion_control::Options>,
+        ecn: bool,
+        timestamps: Option<Timestamps>,
+        connection_token: Option<u32>,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+        observer: SharedConnectionObserver,
     ) -> Result<Self, Fail> {
         // TODO: Maybe add the queue descriptor here.
         let cb = SharedControlBlock::new(
@@ -90,6 +98,10 @@ impl<const N: usize> EstablishedSocket<N> {
             sender_mss,
             cc_constructor,
             congestion_control_options,
+            ecn,
+            timestamps,
+            connection_token,
+            observer,
         );
         let handle: TaskHandle = runtime.insert_background_coroutine(
             "Inetstack::TCP::established::background",
@@ -102,22 +114,42 @@ impl<const N: usize> EstablishedSocket<N> {
         })
     }
 
-    pub fn receive(&mut self, header: TcpHeader, data: DemiBuffer) {
-        self.cb.receive(header, data)
+    pub fn receive(&mut self, header: TcpHeader, data: DemiBuffer, congestion_experienced: bool) {
+        self.cb.receive(header, data, congestion_experienced)
     }
 
     pub fn send(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
         self.cb.send(buf)
     }
 
+    pub fn send_urgent(&mut self, buf: DemiBuffer) -> Result<(), Fail> {
+        self.cb.send_urgent(buf)
+    }
+
     pub async fn pop(&mut self, size: Option<usize>, yielder: Yielder) -> Result<DemiBuffer, Fail> {
         self.cb.pop(size, yielder).await
     }
 
+    pub fn try_pop(&mut self) -> Option<DemiBuffer> {
+        self.cb.try_pop()
+    }
+
+    pub async fn pop_urgent(&mut self, yielder: Yielder) -> Result<DemiBuffer, Fail> {
+        self.cb.pop_urgent(yielder).await
+    }
+
     pub fn close(&mut self) -> Result<(), Fail> {
         self.cb.close()
     }
 
+    /// Resets the connection and stops its background coroutine.
+    pub fn abort(&mut self) {
+        self.cb.abort();
+        if let Err(e) = self.runtime.remove_background_coroutine(&self.background) {
+            warn!("abort(): failed to remove background coroutine (error={:?})", e);
+        }
+    }
+
     pub async fn async_close(&mut self, yielder: Yielder) -> Result<(), Fail> {
         self.cb.async_close(yielder).await
     }
@@ -126,10 +158,58 @@ impl<const N: usize> EstablishedSocket<N> {
         self.cb.remote_mss()
     }
 
+    pub fn send_queue_len(&self) -> usize {
+        self.cb.send_queue_len()
+    }
+
+    pub fn set_quickack(&mut self, enable: bool) {
+        self.cb.set_quickack(enable)
+    }
+
+    pub fn flush_ack(&mut self) {
+        self.cb.flush_ack()
+    }
+
+    pub fn set_rcvlowat(&mut self, bytes: usize) -> Result<(), Fail> {
+        self.cb.set_rcvlowat(bytes)
+    }
+
+    #[cfg(feature = "experimental")]
+    pub fn set_cwnd(&mut self, cwnd: u32) {
+        self.cb.set_cwnd(cwnd)
+    }
+
+    #[cfg(feature = "experimental")]
+    pub fn get_cwnd(&self) -> u32 {
+        self.cb.congestion_control_get_cwnd().get()
+    }
+
+    #[cfg(feature = "tcp-migration")]
+    pub fn connection_token(&self) -> Option<u32> {
+        self.cb.get_connection_token()
+    }
+
+    #[cfg(feature = "tcp-migration")]
+    pub fn rebind(&mut self, remote: SocketAddrV4, header: &TcpHeader, data_len: usize) -> Result<(), Fail> {
+        self.cb.rebind(remote, header, data_len)
+    }
+
+    pub fn set_cork(&mut self, enable: bool) {
+        self.cb.set_cork(enable)
+    }
+
     pub fn current_rto(&self) -> Duration {
         self.cb.rto()
     }
 
+    pub fn connection_metrics(&self) -> ConnMetrics {
+        self.cb.metrics()
+    }
+
+    pub fn dump(&self) -> String {
+        self.cb.dump()
+    }
+
     pub fn endpoints(&self) -> (SocketAddrV4, SocketAddrV4) {
         (self.cb.get_local(), self.cb.get_remote())
     }
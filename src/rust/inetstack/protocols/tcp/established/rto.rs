@@ -21,19 +21,30 @@ pub struct RtoCalculator {
 
     // Whether a RTT (round-trip-time) sample has been received yet.
     received_sample: bool,
+
+    // Lower bound for the retransmission timeout.
+    lower_bound: f64,
+
+    // Upper bound for the retransmission timeout.
+    upper_bound: f64,
 }
 
 impl RtoCalculator {
-    /// Initializes an RTO Calculator.
-    pub fn new() -> Self {
+    /// Initializes an RTO Calculator that keeps the RTO within `[min, max]`.
+    pub fn new(min: Duration, max: Duration) -> Self {
+        debug_assert!(min <= max);
         // RFC 6298 recommends an initial value of 1 second for RTO (See also RFC 6298 Appendix A).  The initial values
         // for SRTT and RTTVAR are arbitrary as they aren't used until after the first sample has been received.
-        Self {
+        let mut calculator: Self = Self {
             srtt: 1.0,
             rttvar: 0.0,
             rto: 1.0,
             received_sample: false,
-        }
+            lower_bound: min.as_secs_f64(),
+            upper_bound: max.as_secs_f64(),
+        };
+        calculator.update_rto(1.0);
+        calculator
     }
 
     /// Adds an RTT sample to the calculator.
@@ -65,17 +76,12 @@ impl RtoCalculator {
         self.update_rto(rto);
     }
 
-    /// Updates the stored RTO value while keeping it within the prescribed bounds (RFC 6298 Section 2.4)
+    /// Updates the stored RTO value while keeping it within the configured bounds (RFC 6298 Section 2.4).
     fn update_rto(&mut self, new_rto: f64) {
-        // RFC 6298's suggested value for the lower bound is 1 second.  Note this currently uses 1/10 of a second.
-        const LOWER_BOUND_SEC: f64 = 0.100f64;
-        // RFC 6298's suggested value for the upper bound is >= 60 seconds.
-        const UPPER_BOUND_SEC: f64 = 60.0f64;
-
         // Note: We use clamp() below as it is clearer in intent than a min/max combination.  However, if we were
         // concerned that new_rto could be NaN here (we're not) we wouldn't want to use clamp() as it would pass NaN
-        // through.  We'd use "self.rto = f64::min(new_rto.max(LOWER_BOUND_SEC), UPPER_BOUND_SEC);" below instead.
-        self.rto = new_rto.clamp(LOWER_BOUND_SEC, UPPER_BOUND_SEC);
+        // through.  We'd use "self.rto = f64::min(new_rto.max(self.lower_bound), self.upper_bound);" below instead.
+        self.rto = new_rto.clamp(self.lower_bound, self.upper_bound);
     }
 
     /// Performs an exponential "back off" of the RTO (doubles the current timeout).
@@ -87,4 +93,14 @@ impl RtoCalculator {
     pub fn rto(&self) -> Duration {
         Duration::from_secs_f64(self.rto)
     }
+
+    /// Gets the current smoothed RTT value.
+    pub fn srtt(&self) -> Duration {
+        Duration::from_secs_f64(self.srtt)
+    }
+
+    /// Gets the current RTT variation value.
+    pub fn rttvar(&self) -> Duration {
+        Duration::from_secs_f64(self.rttvar)
+    }
 }
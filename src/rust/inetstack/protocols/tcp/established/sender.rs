@@ -22,9 +22,13 @@ use ::std::{
         Cell,
         RefCell,
     },
+    cmp,
     collections::VecDeque,
     convert::TryInto,
-    fmt,
+    fmt::{
+        self,
+        Write,
+    },
     time::{
         Duration,
         Instant,
@@ -88,6 +92,17 @@ pub struct Sender<const N: usize> {
     // Maximum Segment Size currently in use for this connection.
     // TODO: Revisit this once we support path MTU discovery.
     mss: usize,
+
+    // Whether the connection is corked. While it is, user data is held back on the unsent queue and small buffers are
+    // coalesced together, so that they go out in as few segments as possible once the connection is uncorked.
+    corked: SharedWatchedValue<bool>,
+
+    // Longest time that small pushes are held back so that they are coalesced into a single segment, if coalescing is
+    // enabled. Unlike Nagle's algorithm, this does not wait for outstanding data to be acknowledged.
+    coalesce_timeout: Option<Duration>,
+
+    // Time at which small pushes that are held back for coalescing are flushed, if any are.
+    coalesce_deadline: SharedWatchedValue<Option<Instant>>,
 }
 
 impl<const N: usize> fmt::Debug for Sender<N> {
@@ -99,12 +114,21 @@ impl<const N: usize> fmt::Debug for Sender<N> {
             .field("send_window", &self.send_window)
             .field("window_scale", &self.window_scale)
             .field("mss", &self.mss)
+            .field("corked", &self.corked)
+            .field("coalesce_timeout", &self.coalesce_timeout)
+            .field("coalesce_deadline", &self.coalesce_deadline)
             .finish()
     }
 }
 
 impl<const N: usize> Sender<N> {
-    pub fn new(seq_no: SeqNumber, send_window: u32, window_scale: u8, mss: usize) -> Self {
+    pub fn new(
+        seq_no: SeqNumber,
+        send_window: u32,
+        window_scale: u8,
+        mss: usize,
+        coalesce_timeout: Option<Duration>,
+    ) -> Self {
         Self {
             send_unacked: SharedWatchedValue::new(seq_no),
             unacked_queue: RefCell::new(VecDeque::new()),
@@ -118,6 +142,9 @@ impl<const N: usize> Sender<N> {
 
             window_scale,
             mss,
+            corked: SharedWatchedValue::new(false),
+            coalesce_timeout,
+            coalesce_deadline: SharedWatchedValue::new(None),
         }
     }
 
@@ -145,13 +172,31 @@ impl<const N: usize> Sender<N> {
         self.unsent_seq_no.clone()
     }
 
+    pub fn get_corked(&self) -> SharedWatchedValue<bool> {
+        self.corked.clone()
+    }
+
+    /// Corks or uncorks the connection. Uncorking lets the background sender flush the data that was held back.
+    pub fn set_cork(&mut self, enable: bool) {
+        self.corked.set(enable);
+    }
+
+    pub fn get_coalesce_deadline(&self) -> SharedWatchedValue<Option<Instant>> {
+        self.coalesce_deadline.clone()
+    }
+
+    /// Lets the background sender flush the small pushes that are held back for coalescing.
+    pub fn flush_coalesced(&mut self) {
+        self.coalesce_deadline.set(None);
+    }
+
     pub fn push_unacked_segment(&self, segment: UnackedSegment) {
         self.unacked_queue.borrow_mut().push_back(segment)
     }
 
     // This is the main TCP send routine.
     //
-    pub fn send(&mut self, buf: DemiBuffer, mut cb: SharedControlBlock<N>) -> Result<(), Fail> {
+    pub fn send(&mut self, mut buf: DemiBuffer, mut cb: SharedControlBlock<N>) -> Result<(), Fail> {
         // If the user is done sending (i.e. has called close on this connection), then they shouldn't be sending.
         //
         if cb.user_is_done_sending {
@@ -186,8 +231,17 @@ impl<const N: usize> Sender<N> {
         // it on the unsent queue and that's it.
         //
 
-        // Check for unsent data.
-        if self.unsent_queue.borrow().is_empty() {
+        // Small pushes are held back for a while if coalescing is enabled, so that the ones that follow go out in the
+        // same segment. The first of them starts the clock, so that none of them waits longer than the timeout.
+        let coalesce: bool = self.coalesce_timeout.is_some() && buf_len > 0 && (buf_len as usize) < self.mss;
+        if coalesce && self.unsent_queue.borrow().is_empty() && self.coalesce_deadline.get().is_none() {
+            let timeout: Duration = self.coalesce_timeout.expect("coalescing should be enabled");
+            self.coalesce_deadline.set(Some(cb.get_timer().now() + timeout));
+        }
+
+        // Check for unsent data. Data is never sent right away while the connection is corked or coalescing, nor if it
+        // does not fit in a single segment, in which case the background sender segments it.
+        if self.unsent_queue.borrow().is_empty() && !self.corked.get() && !coalesce && buf_len as usize <= self.mss {
             // No unsent data queued up, so we can try to send this new buffer immediately.
 
             // Calculate amount of data in flight (SND.NXT - SND.UNA).
@@ -195,7 +249,6 @@ impl<const N: usize> Sender<N> {
             let send_next: SeqNumber = self.send_next.get();
             let sent_data: u32 = (send_next - send_unacknowledged).into();
 
-            // TODO: What limits buffer len to MSS?
             let in_flight_after_send: u32 = sent_data + buf_len;
 
             // Before we get cwnd for the check, we prompt it to shrink it if the connection has been idle.
@@ -207,7 +260,10 @@ impl<const N: usize> Sender<N> {
 
             let win_sz: u32 = self.send_window.get();
 
-            if win_sz > 0 && win_sz >= in_flight_after_send && effective_cwnd >= in_flight_after_send {
+            // Do not send right away if pacing holds back the next segment.
+            let paced: bool = cb.pacing_get_release_time().is_some();
+
+            if win_sz > 0 && win_sz >= in_flight_after_send && effective_cwnd >= in_flight_after_send && !paced {
                 if let Some(remote_link_addr) = cb.arp().try_query(cb.get_remote().ip().clone()) {
                     // This hook is primarily intended to record the last time we sent data, so we can later tell if
                     // the connection has been idle.
@@ -227,6 +283,7 @@ impl<const N: usize> Sender<N> {
                     }
                     trace!("Send immediate");
                     cb.emit(header, Some(buf.clone()), remote_link_addr);
+                    cb.pacing_on_send(buf_len as usize);
 
                     // Update SND.NXT.
                     self.send_next.modify(|s| s + SeqNumber::from(buf_len));
@@ -260,6 +317,40 @@ impl<const N: usize> Sender<N> {
             return Err(Fail::new(EBUSY, "too many packets to send"));
         }
 
+        // While the connection is corked or coalescing, append buffers to the last unsent one, until it fills a segment.
+        // The rest of a buffer that does not fit is queued on its own.
+        if (self.corked.get() || self.coalesce_timeout.is_some()) && buf_len > 0 {
+            let mut unsent_queue = self.unsent_queue.borrow_mut();
+            if let Some(last_buf) = unsent_queue.back_mut() {
+                let last_len: usize = last_buf.len();
+                // Do not coalesce with the end-of-send marker.
+                if last_len > 0 && last_len < self.mss {
+                    let len: usize = cmp::min(buf.len(), self.mss - last_len);
+                    // Append to the last buffer in place if it has room. Otherwise, move its data into a buffer that
+                    // can hold a full segment, so that the pushes that follow are appended without reallocating.
+                    if last_buf.append(&buf[..len]).is_err() {
+                        let mut coalesced_buf: DemiBuffer = DemiBuffer::new(self.mss as u16);
+                        coalesced_buf.trim(self.mss)?;
+                        coalesced_buf.append(&last_buf[..])?;
+                        coalesced_buf.append(&buf[..len])?;
+                        *last_buf = coalesced_buf;
+                    }
+                    self.unsent_seq_no.modify(|s| s + SeqNumber::from(len as u32));
+                    trace!("Coalescing Send");
+                    if len == buf.len() {
+                        return Ok(());
+                    }
+                    buf.adjust(len)?;
+                    buf_len -= len as u32;
+                }
+            }
+        }
+
+        // A full segment is ready, or the user is done sending, so there is no point in holding back data any longer.
+        if self.coalesce_deadline.get().is_some() && (!coalesce || !self.unsent_queue.borrow().is_empty()) {
+            self.coalesce_deadline.set(None);
+        }
+
         // Slow path: Delegating sending the data to background processing.
         trace!("Queueing Send for background processing");
         self.unsent_queue.borrow_mut().push_back(buf);
@@ -303,14 +394,17 @@ impl<const N: usize> Sender<N> {
     //
     pub fn remove_acknowledged_data(&self, mut cb: SharedControlBlock<N>, bytes_acknowledged: u32, now: Instant) {
         let mut bytes_remaining: usize = bytes_acknowledged as usize;
+        let timestamps: bool = cb.has_timestamps();
 
         while bytes_remaining != 0 {
             if let Some(segment) = self.unacked_queue.borrow_mut().front_mut() {
                 // Add sample for RTO if we have an initial transmit time.
                 // Note that in the case of repacketization, an ack for the first byte is enough for the time sample.
-                // TODO: TCP timestamp support.
-                if let Some(initial_tx) = segment.initial_tx {
-                    cb.rto_add_sample(now - initial_tx);
+                // If timestamps were negotiated, samples are taken from them instead.
+                if !timestamps {
+                    if let Some(initial_tx) = segment.initial_tx {
+                        cb.rto_add_sample(now - initial_tx);
+                    }
                 }
 
                 if segment.bytes.len() > bytes_remaining {
@@ -414,4 +508,47 @@ impl<const N: usize> Sender<N> {
     pub fn remote_mss(&self) -> usize {
         self.mss
     }
+
+    /// Writes a human-readable report of the send-side state into `out`. Queues that are borrowed elsewhere are
+    /// reported as busy instead of being inspected.
+    pub fn dump(&self, out: &mut String, now: Instant) -> fmt::Result {
+        writeln!(
+            out,
+            "send: una={} nxt={} unsent_seq={} wnd={} wnd_scale={} wl1={} wl2={} mss={} corked={}",
+            self.send_unacked.get(),
+            self.send_next.get(),
+            self.unsent_seq_no.get(),
+            self.send_window.get(),
+            self.window_scale,
+            self.send_window_last_update_seq.get(),
+            self.send_window_last_update_ack.get(),
+            self.mss,
+            self.corked.get()
+        )?;
+
+        match self.unacked_queue.try_borrow() {
+            Ok(unacked_queue) => {
+                let len: usize = unacked_queue.iter().map(|segment| segment.bytes.len()).sum();
+                writeln!(out, "unacked: segments={} bytes={}", unacked_queue.len(), len)?;
+                let mut seq_num: SeqNumber = self.send_unacked.get();
+                for segment in unacked_queue.iter() {
+                    let sent: String = match segment.initial_tx {
+                        Some(initial_tx) => format!("{:?} ago", now.saturating_duration_since(initial_tx)),
+                        None => String::from("retransmitted"),
+                    };
+                    writeln!(out, "  seq={} len={} sent={}", seq_num, segment.bytes.len(), sent)?;
+                    seq_num = seq_num + SeqNumber::from(segment.bytes.len() as u32);
+                }
+            },
+            Err(_) => writeln!(out, "unacked: busy")?,
+        }
+
+        match self.unsent_queue.try_borrow() {
+            Ok(unsent_queue) => {
+                let len: usize = unsent_queue.iter().map(|buf| buf.len()).sum();
+                writeln!(out, "unsent: buffers={} bytes={}", unsent_queue.len(), len)
+            },
+            Err(_) => writeln!(out, "unsent: busy"),
+        }
+    }
 }
@@ -0,0 +1,87 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::tcp::segment::TcpSegment,
+    runtime::{
+        memory::DemiBuffer,
+        network::{
+            NetworkRuntime,
+            PacketBuf,
+        },
+        SharedBox,
+        SharedObject,
+    },
+};
+use ::arrayvec::ArrayVec;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Shared slot holding the user-supplied transmit filter.
+#[derive(Clone)]
+pub struct SharedTransmitFilter(SharedObject<Option<Box<dyn FnMut(&mut TcpSegment)>>>);
+
+/// Network runtime that wraps another network runtime and runs every outgoing TCP segment through a transmit filter
+/// before handing it over. Since segments are serialized afterwards, checksums always reflect the rewritten headers.
+pub struct FilterNetworkRuntime<const N: usize> {
+    inner: SharedBox<dyn NetworkRuntime<N>>,
+    filter: SharedTransmitFilter,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SharedTransmitFilter {
+    pub fn new() -> Self {
+        Self(SharedObject::<Option<Box<dyn FnMut(&mut TcpSegment)>>>::new(None))
+    }
+
+    /// Installs `f` as the transmit filter, replacing any previous one.
+    pub fn set(&mut self, f: Box<dyn FnMut(&mut TcpSegment)>) {
+        *self.0 = Some(f);
+    }
+
+    /// Shows `segment` to the transmit filter, if any, which may rewrite it.
+    pub fn apply(&mut self, segment: &mut TcpSegment) {
+        if let Some(f) = &mut *self.0 {
+            f(segment);
+        }
+    }
+}
+
+impl<const N: usize> FilterNetworkRuntime<N> {
+    /// Wraps `inner` into a network runtime that runs all outgoing TCP segments through `filter`.
+    pub fn new(inner: SharedBox<dyn NetworkRuntime<N>>, filter: SharedTransmitFilter) -> Self {
+        Self { inner, filter }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Default for SharedTransmitFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> NetworkRuntime<N> for FilterNetworkRuntime<N> {
+    fn transmit(&mut self, mut pkt: Box<dyn PacketBuf>) {
+        if let Some(segment) = pkt.as_any_mut().and_then(|pkt| pkt.downcast_mut::<TcpSegment>()) {
+            self.filter.apply(segment);
+        }
+        self.inner.transmit(pkt)
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, N> {
+        self.inner.receive()
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    inetstack::protocols::{
+        ip::IpProtocol,
+        ipv4::Ipv4Header,
+        tcp::segment::{
+            TcpHeader,
+            TcpOptions2,
+            TcpSegment,
+            MAX_TCP_HEADER_SIZE,
+            MAX_TCP_OPTIONS,
+            MIN_TCP_HEADER_SIZE,
+        },
+    },
+    runtime::{
+        fail::Fail,
+        network::config::TcpConfig,
+    },
+};
+use ::md5::{
+    Digest,
+    Md5,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Maximum length of a key (in bytes). This is what Linux accepts, so that keys can be shared with it.
+pub const TCP_MD5_MAX_KEY_LEN: usize = 80;
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Signs `segment` with the MD5 signature option, if a key is configured in `tcp_config` for its destination. Segments
+/// whose header has no room left for the option are sent unsigned, and our peer drops them.
+pub fn sign(mut segment: TcpSegment, tcp_config: &TcpConfig) -> TcpSegment {
+    let key: &[u8] = match tcp_config.get_md5_key(&segment.ipv4_hdr.get_dest_addr()) {
+        Some(key) => key,
+        None => return segment,
+    };
+    if segment.tcp_hdr.num_options >= MAX_TCP_OPTIONS {
+        warn!("sign(): no room for the TCP MD5 signature option");
+        return segment;
+    }
+    segment.tcp_hdr.push_option(TcpOptions2::Md5Signature([0; 16]));
+    let header_size: usize = segment.tcp_hdr.compute_size();
+    if header_size > MAX_TCP_HEADER_SIZE {
+        warn!("sign(): no room for the TCP MD5 signature option");
+        segment.tcp_hdr.num_options -= 1;
+        return segment;
+    }
+
+    // The digest covers the data offset, so the header is serialized with the option already in place.
+    let data: &[u8] = match &segment.data {
+        Some(buf) => &buf[..],
+        None => &[],
+    };
+    let mut header: [u8; MAX_TCP_HEADER_SIZE] = [0; MAX_TCP_HEADER_SIZE];
+    segment
+        .tcp_hdr
+        .serialize(&mut header[..header_size], &segment.ipv4_hdr, data, true);
+    let digest: [u8; 16] = digest(&segment.ipv4_hdr, &header, header_size + data.len(), data, key);
+    segment.tcp_hdr.option_list[segment.tcp_hdr.num_options - 1] = TcpOptions2::Md5Signature(digest);
+    segment
+}
+
+/// Checks the MD5 signature option of a segment with header `tcp_hdr`, if a key is configured in `tcp_config` for its
+/// source. `buf` is the segment as it was received, since the digest covers the header as it went on the wire.
+pub fn verify(ipv4_hdr: &Ipv4Header, tcp_hdr: &TcpHeader, buf: &[u8], tcp_config: &TcpConfig) -> Result<(), Fail> {
+    let key: &[u8] = match tcp_config.get_md5_key(&ipv4_hdr.get_src_addr()) {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+    let signature: [u8; 16] = match tcp_hdr.get_md5_signature() {
+        Some(signature) => signature,
+        None => {
+            let cause: String = format!("missing TCP MD5 signature (remote={})", ipv4_hdr.get_src_addr());
+            error!("verify(): {}", &cause);
+            return Err(Fail::new(libc::EBADMSG, &cause));
+        },
+    };
+
+    // The header was parsed already, so its data offset is known to be valid.
+    let data_offset: usize = (buf[12] >> 4) as usize * 4;
+    let digest: [u8; 16] = digest(ipv4_hdr, buf, buf.len(), &buf[data_offset..], key);
+    // Compare in constant time, so that timing does not tell how much of a forged signature is right.
+    let mismatch: u8 = digest.iter().zip(signature.iter()).fold(0, |acc, (a, b)| acc | (a ^ b));
+    if mismatch != 0 {
+        let cause: String = format!("TCP MD5 signature mismatch (remote={})", ipv4_hdr.get_src_addr());
+        error!("verify(): {}", &cause);
+        return Err(Fail::new(libc::EBADMSG, &cause));
+    }
+    Ok(())
+}
+
+/// Computes the digest of a segment of `segment_len` bytes, whose header starts with `header` and whose payload is
+/// `data`. It covers the pseudo-header, the fixed part of the header with a zero checksum, the payload, and `key`, in
+/// this order (see RFC 2385 Section 2.0).
+fn digest(ipv4_hdr: &Ipv4Header, header: &[u8], segment_len: usize, data: &[u8], key: &[u8]) -> [u8; 16] {
+    let mut hasher: Md5 = Md5::new();
+    hasher.update(ipv4_hdr.get_src_addr().octets());
+    hasher.update(ipv4_hdr.get_dest_addr().octets());
+    hasher.update([0, IpProtocol::TCP as u8]);
+    hasher.update((segment_len as u16).to_be_bytes());
+    hasher.update(&header[..16]);
+    hasher.update([0, 0]);
+    hasher.update(&header[18..MIN_TCP_HEADER_SIZE]);
+    hasher.update(data);
+    hasher.update(key);
+    hasher.finalize().into()
+}
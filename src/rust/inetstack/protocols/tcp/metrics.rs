@@ -0,0 +1,44 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::std::time::Duration;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Read-only snapshot of the transmission state of an established TCP connection, used for diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConnMetrics {
+    /// Smoothed round-trip time.
+    pub srtt: Duration,
+    /// Round-trip time variation.
+    pub rttvar: Duration,
+    /// Congestion window (in bytes).
+    pub cwnd: u32,
+    /// Slow start threshold (in bytes).
+    pub ssthresh: u32,
+    /// Number of bytes sent but not yet acknowledged.
+    pub bytes_in_flight: u32,
+    /// Number of duplicate ACKs received.
+    pub duplicate_acks: u64,
+    /// Number of fast retransmissions triggered by duplicate ACKs.
+    pub fast_retransmits: u64,
+}
+
+/// Read-only snapshot of the state of a listening TCP socket, used for tuning the size of its backlog.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListenerStats {
+    /// Number of connections that were accepted.
+    pub accepted: u64,
+    /// Number of connection requests that were refused because the backlog was full.
+    pub refused: u64,
+    /// Number of connections that are being established.
+    pub inflight: usize,
+    /// Number of connections that are established and waiting to be accepted.
+    pub ready: usize,
+}
@@ -4,18 +4,34 @@
 mod active_open;
 pub mod constants;
 mod established;
+mod filter;
 mod isn_generator;
+#[cfg(feature = "tcp-md5")]
+mod md5;
+mod metrics;
+mod observer;
 mod passive_open;
 pub mod peer;
 pub mod queue;
 pub mod segment;
 mod sequence_number;
+mod timestamps;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::{
     established::congestion_control,
+    isn_generator::IsnGeneratorState,
+    metrics::{
+        ConnMetrics,
+        ListenerStats,
+    },
+    observer::{
+        ConnEvent,
+        ConnEventKind,
+        SharedConnectionObserver,
+    },
     peer::SharedTcpPeer,
     segment::{
         MAX_TCP_HEADER_SIZE,
@@ -23,3 +39,6 @@ pub use self::{
     },
     sequence_number::SeqNumber,
 };
+
+#[cfg(feature = "tcp-md5")]
+pub use self::md5::TCP_MD5_MAX_KEY_LEN;
@@ -0,0 +1,81 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::SharedObject;
+use ::std::{
+    net::SocketAddrV4,
+    rc::Rc,
+};
+
+//======================================================================================================================
+// Enumerations
+//======================================================================================================================
+
+/// Kinds of TCP connection events reported to a connection observer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnEventKind {
+    /// The three-way handshake completed and the connection is established.
+    Established,
+    /// The connection was gracefully torn down.
+    Closed,
+    /// The connection was aborted by the remote peer.
+    Reset,
+}
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// A TCP connection event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConnEvent {
+    /// Local endpoint of the connection.
+    pub local: SocketAddrV4,
+    /// Remote endpoint of the connection.
+    pub remote: SocketAddrV4,
+    /// What happened to the connection.
+    pub kind: ConnEventKind,
+}
+
+/// Shared slot holding the user-supplied connection observer.
+#[derive(Clone)]
+pub struct SharedConnectionObserver(SharedObject<Option<Rc<dyn Fn(ConnEvent)>>>);
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl SharedConnectionObserver {
+    pub fn new() -> Self {
+        Self(SharedObject::<Option<Rc<dyn Fn(ConnEvent)>>>::new(None))
+    }
+
+    /// Installs `f` as the connection observer, replacing any previous one.
+    pub fn set(&mut self, f: Box<dyn Fn(ConnEvent)>) {
+        *self.0 = Some(Rc::from(f));
+    }
+
+    /// Reports a connection event to the observer, if any.
+    pub fn notify(&self, local: SocketAddrV4, remote: SocketAddrV4, kind: ConnEventKind) {
+        // Clone the observer out of the slot so that no borrow is held while it runs. This allows the observer to
+        // re-enter the stack (e.g., to replace itself).
+        let observer: Option<Rc<dyn Fn(ConnEvent)>> = (*self.0).clone();
+        if let Some(observer) = observer {
+            observer(ConnEvent { local, remote, kind });
+        }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+impl Default for SharedConnectionObserver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -14,20 +14,29 @@ use crate::{
             Ethernet2Header,
         },
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            IPV4_ECN_CE,
+        },
         tcp::{
             constants::FALLBACK_MSS,
             established::{
                 congestion_control,
-                congestion_control::CongestionControl,
                 EstablishedSocket,
             },
             isn_generator::IsnGenerator,
+            metrics::ListenerStats,
+            observer::{
+                ConnEventKind,
+                SharedConnectionObserver,
+            },
             segment::{
+                self,
                 TcpHeader,
                 TcpOptions2,
                 TcpSegment,
             },
+            timestamps::Timestamps,
             SeqNumber,
         },
     },
@@ -65,9 +74,24 @@ use ::std::{
         Deref,
         DerefMut,
     },
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::md5;
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Time that accepting backs off for after the first failure to hand out a connection for lack of queue descriptors.
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(10);
+/// Upper bound for the time that accepting backs off for. Back-off periods double on consecutive failures.
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -78,15 +102,35 @@ struct InflightAccept {
     header_window_size: u16,
     remote_window_scale: Option<u8>,
     mss: usize,
+    ecn: bool,
+    timestamps: Option<Timestamps>,
+    connection_token: Option<u32>,
     handle: TaskHandle,
     yielder_handle: YielderHandle,
 }
 
 pub struct PassiveSocket<const N: usize> {
     inflight: HashMap<SocketAddrV4, InflightAccept>,
-    ready: AsyncQueue<Result<EstablishedSocket<N>, Fail>>,
+    /// Connections that are pending acceptance, along with the time at which they became ready.
+    ready: AsyncQueue<(Instant, Result<EstablishedSocket<N>, Fail>)>,
+    /// Maximum time that a connection may be pending acceptance before it is reset, if any.
+    ready_ttl: Option<Duration>,
     max_backlog: usize,
+    paused: bool,
+    /// Accept operations that wait for accepting to be resumed.
+    resume_waiters: Vec<YielderHandle>,
+    /// Time that accepting backs off for on the next failure to hand out a connection.
+    accept_backoff: Duration,
+    /// Time until which accepting backs off, if it does.
+    accept_backoff_until: Option<Instant>,
+    /// Number of connections that were accepted.
+    accepted: u64,
+    /// Number of connection requests that were refused because the backlog was full.
+    refused: u64,
     isn_generator: IsnGenerator,
+    /// Initial sequence number to use instead of a generated one (for testing only).
+    #[cfg(test)]
+    fixed_isn: Option<SeqNumber>,
     local: SocketAddrV4,
     runtime: SharedDemiRuntime,
     transport: SharedBox<dyn NetworkRuntime<N>>,
@@ -94,6 +138,7 @@ pub struct PassiveSocket<const N: usize> {
     local_link_addr: MacAddress,
     arp: SharedArpPeer<N>,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+    observer: SharedConnectionObserver,
 }
 
 #[derive(Clone)]
@@ -113,13 +158,23 @@ impl<const N: usize> SharedPassiveSocket<N> {
         local_link_addr: MacAddress,
         arp: SharedArpPeer<N>,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+        observer: SharedConnectionObserver,
         nonce: u32,
     ) -> Self {
         Self(SharedObject::<PassiveSocket<N>>::new(PassiveSocket::<N> {
             inflight: HashMap::new(),
-            ready: AsyncQueue::<Result<EstablishedSocket<N>, Fail>>::default(),
+            ready: AsyncQueue::<(Instant, Result<EstablishedSocket<N>, Fail>)>::default(),
+            ready_ttl: None,
             max_backlog,
-            isn_generator: IsnGenerator::new(nonce),
+            paused: false,
+            resume_waiters: Vec::new(),
+            accept_backoff: ACCEPT_BACKOFF_MIN,
+            accept_backoff_until: None,
+            accepted: 0,
+            refused: 0,
+            isn_generator: IsnGenerator::with_seed(nonce),
+            #[cfg(test)]
+            fixed_isn: None,
             local,
             local_link_addr,
             runtime,
@@ -127,6 +182,7 @@ impl<const N: usize> SharedPassiveSocket<N> {
             tcp_config,
             arp,
             dead_socket_tx,
+            observer,
         }))
     }
 
@@ -135,31 +191,60 @@ impl<const N: usize> SharedPassiveSocket<N> {
         self.local
     }
 
-    /// Accept a new connection by fetching one from the queue of requests, blocking if there are no new requests.
+    /// Accept a new connection by fetching one from the queue of requests, blocking if there are no new requests, if
+    /// accepting is paused, or if it backs off. Connections that have been pending for too long are reset instead of
+    /// being handed out.
     pub async fn do_accept(&mut self, yielder: Yielder) -> Result<EstablishedSocket<N>, Fail> {
-        self.ready.pop(&yielder).await?
+        loop {
+            self.expire_ready();
+            if self.paused {
+                // Sleep until accepting is resumed, instead of spinning.
+                self.resume_waiters.push(yielder.get_handle());
+                yielder.yield_until_wake().await?;
+                continue;
+            }
+            if let Some(until) = self.accept_backoff_until {
+                if self.runtime.get_now() < until {
+                    let clock_ref: SharedTimer = self.runtime.get_timer();
+                    clock_ref.wait_until(until, &yielder).await?;
+                    continue;
+                }
+                self.accept_backoff_until = None;
+            }
+            let (since, result): (Instant, Result<EstablishedSocket<N>, Fail>) = self.ready.pop(&yielder).await?;
+            if !self.paused {
+                if result.is_ok() {
+                    self.accepted += 1;
+                }
+                return result;
+            }
+            // Accepting was paused while we were waiting, so leave the connection pending.
+            self.ready.push_front((since, result));
+        }
     }
 
     /// Receive and direct new connection requests and ACKs.
     pub fn receive(&mut self, ip_header: &Ipv4Header, header: TcpHeader, buf: DemiBuffer) -> Result<(), Fail> {
+        self.expire_ready();
         let remote = SocketAddrV4::new(ip_header.get_src_addr(), header.src_port);
+        let congestion_experienced: bool = ip_header.get_ecn() == IPV4_ECN_CE;
         // If the packet is for an inflight connection, route it there.
         if let Some(inflight) = self.inflight.remove(&remote) {
             // If ack for inflight connection request, remove from inflight table and handle. If error, we do not put
             // the inflight request back but drop it.
             // FIXME: https://github.com/microsoft/demikernel/issues/1054
             if header.ack {
-                return self.handle_ack(inflight, remote, header, buf);
+                return self.handle_ack(inflight, remote, header, buf, congestion_experienced);
             } else {
                 return Err(Fail::new(EBADMSG, "expecting ACK"));
             }
         } else {
             // Check whether this packet is for a connection that we have finished accepting.
-            for result in self.ready.get_mut_values() {
+            for (_, result) in self.ready.get_mut_values() {
                 match result {
                     // We've finished establishing the connection, so just deliver the packet.
                     Ok(ref mut socket) if socket.endpoints().1 == remote => {
-                        socket.receive(header, buf);
+                        socket.receive(header, buf, congestion_experienced);
                         return Ok(());
                     },
                     _ => continue,
@@ -175,10 +260,87 @@ impl<const N: usize> SharedPassiveSocket<N> {
         self.handle_syn(remote, header)
     }
 
+    /// Changes the maximum number of connections that may be pending acceptance. Connections that are already pending
+    /// are kept even if they exceed the new backlog.
+    pub fn set_backlog(&mut self, max_backlog: usize) {
+        self.max_backlog = max_backlog;
+    }
+
+    /// Changes the maximum time that a connection may be pending acceptance. Connections that are pending for longer
+    /// are reset, so that their peers fail fast instead of waiting on an application that accepts slowly. `None` lets
+    /// connections be pending for as long as it takes.
+    pub fn set_ready_ttl(&mut self, ttl: Option<Duration>) {
+        self.ready_ttl = ttl;
+    }
+
+    /// Pins the initial sequence number of the connections that are accepted from now on to `isn`. `None` goes back to
+    /// generating them.
+    #[cfg(test)]
+    pub fn set_fixed_isn(&mut self, isn: Option<SeqNumber>) {
+        self.fixed_isn = isn;
+    }
+
+    /// Pauses or resumes accepting connections. While paused, connections that are already pending stay queued and new
+    /// connection requests are dropped without an answer, so that peers retry them later.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if !paused {
+            for mut yielder_handle in self.resume_waiters.drain(..) {
+                yielder_handle.wake_with(Ok(()));
+            }
+        }
+    }
+
+    /// Backs off accepting connections, or stops backing off. Backing off is meant for when accepted connections cannot
+    /// be handed out, e.g. because we ran out of queue descriptors, so that accepting does not spin on new connections
+    /// until the situation clears. Consecutive back-off periods double, up to [ACCEPT_BACKOFF_MAX].
+    pub fn set_backoff(&mut self, backoff: bool) {
+        if backoff {
+            self.accept_backoff_until = Some(self.runtime.get_now() + self.accept_backoff);
+            self.accept_backoff = (self.accept_backoff * 2).min(ACCEPT_BACKOFF_MAX);
+        } else {
+            self.accept_backoff = ACCEPT_BACKOFF_MIN;
+            self.accept_backoff_until = None;
+        }
+    }
+
+    /// Returns a snapshot of the connections that went through the socket and of those that are pending acceptance.
+    pub fn listener_stats(&self) -> ListenerStats {
+        ListenerStats {
+            accepted: self.accepted,
+            refused: self.refused,
+            inflight: self.inflight.len(),
+            ready: self.ready.len(),
+        }
+    }
+
+    /// Resets and drops the connections that have been pending acceptance for longer than the ready queue TTL.
+    fn expire_ready(&mut self) {
+        let ttl: Duration = match self.ready_ttl {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        let now: Instant = self.runtime.get_now();
+        // Connections become ready in order, so the stale ones are at the front of the queue.
+        while let Some((since, _)) = self.ready.get_values().next() {
+            if now.saturating_duration_since(*since) <= ttl {
+                break;
+            }
+            if let Some((_, Ok(mut socket))) = self.ready.try_pop() {
+                warn!(
+                    "expire_ready(): resetting stale connection (remote={:?})",
+                    socket.endpoints().1
+                );
+                socket.abort();
+            }
+        }
+    }
+
     fn handle_syn(&mut self, remote: SocketAddrV4, header: TcpHeader) -> Result<(), Fail> {
         debug!("Received SYN: {:?}", header);
         let inflight_len: usize = self.inflight.len();
         if inflight_len + self.ready.len() >= self.max_backlog {
+            self.refused += 1;
             let cause: String = format!(
                 "backlog full (inflight={}, ready={}, backlog={})",
                 inflight_len,
@@ -188,21 +350,27 @@ impl<const N: usize> SharedPassiveSocket<N> {
             error!("receive(): {:?}", &cause);
             return Err(Fail::new(libc::ECONNREFUSED, &cause));
         }
+        if self.paused {
+            let cause: &str = "accepting connections is paused";
+            debug!("receive(): {}", cause);
+            return Err(Fail::new(libc::EAGAIN, cause));
+        }
 
-        // Send SYN+ACK.
+        // Pick sequence numbers.
         let local: SocketAddrV4 = self.local.clone();
-        let local_isn = self.isn_generator.generate(&local, &remote);
+        #[cfg(test)]
+        let local_isn: SeqNumber = match self.fixed_isn {
+            Some(isn) => isn,
+            None => self.isn_generator.generate(&local, &remote),
+        };
+        #[cfg(not(test))]
+        let local_isn: SeqNumber = self.isn_generator.generate(&local, &remote);
         let remote_isn = header.seq_num;
 
-        // Allocate a new coroutine to send the SYN+ACK and retry if necessary.
-        let yielder: Yielder = Yielder::new();
-        let yielder_handle: YielderHandle = yielder.get_handle();
-        let future = self.clone().send_syn_ack(remote, remote_isn, local_isn, yielder);
-        let handle: TaskHandle = self
-            .runtime
-            .insert_background_coroutine("Inetstack::TCP::passiveopen::background", Box::pin(future))?;
+        // Our peer requests ECN by setting both ECE and CWR on its SYN (see RFC 3168 Section 6.1.1).
+        let ecn: bool = self.tcp_config.get_enable_ecn() && header.ece && header.cwr;
 
-        // Set up new inflight accept connection.
+        // Parse options of the SYN.
         let mut remote_window_scale = None;
         let mut mss = FALLBACK_MSS;
         for option in header.iter_options() {
@@ -218,12 +386,66 @@ impl<const N: usize> SharedPassiveSocket<N> {
                 _ => continue,
             }
         }
+
+        // Our peer offers timestamps by sending the option on its SYN (see RFC 7323 Section 3.2). Our timestamp clock
+        // starts now.
+        let timestamps: Option<Timestamps> = match header.get_timestamp() {
+            Some((value, _)) if self.tcp_config.get_enable_timestamps() => {
+                Some(Timestamps::new(self.runtime.get_now(), value))
+            },
+            _ => None,
+        };
+
+        // Our peer asks for a connection token by sending the option on its SYN. The token is never zero, as a zero
+        // token is a request for one.
+        let connection_token: Option<u32> = match header.get_connection_token() {
+            Some(_) if cfg!(feature = "tcp-migration") => Some(::rand::random::<u32>().max(1)),
+            _ => None,
+        };
+
+        // Segments are signed if we share a key with our peer.
+        #[cfg(feature = "tcp-md5")]
+        let md5_signature: bool = self.tcp_config.get_md5_key(remote.ip()).is_some();
+        #[cfg(not(feature = "tcp-md5"))]
+        let md5_signature: bool = false;
+
+        let mss: usize = segment::compute_mss(
+            mss,
+            self.tcp_config.get_effective_mss(),
+            timestamps.is_some(),
+            connection_token.is_some(),
+            md5_signature,
+        );
+
+        // Allocate a new coroutine to send the SYN+ACK and retry if necessary. Window scaling is only used if both
+        // sides send the option (see RFC 7323 Section 2.2), so we only offer it back if our peer did.
+        let window_scale: bool = remote_window_scale.is_some();
+        let yielder: Yielder = Yielder::new();
+        let yielder_handle: YielderHandle = yielder.get_handle();
+        let future = self.clone().send_syn_ack(
+            remote,
+            remote_isn,
+            local_isn,
+            ecn,
+            window_scale,
+            timestamps,
+            connection_token,
+            yielder,
+        );
+        let handle: TaskHandle = self
+            .runtime
+            .insert_background_coroutine("Inetstack::TCP::passiveopen::background", Box::pin(future))?;
+
+        // Set up new inflight accept connection.
         let accept = InflightAccept {
             local_isn,
             remote_isn,
             header_window_size: header.window_size,
             remote_window_scale,
             mss,
+            ecn,
+            timestamps,
+            connection_token,
             handle,
             yielder_handle,
         };
@@ -237,6 +459,7 @@ impl<const N: usize> SharedPassiveSocket<N> {
         remote: SocketAddrV4,
         header: TcpHeader,
         buf: DemiBuffer,
+        congestion_experienced: bool,
     ) -> Result<(), Fail> {
         debug!("Received ACK: {:?}", header);
         // Grab values from inflight accept.
@@ -246,6 +469,9 @@ impl<const N: usize> SharedPassiveSocket<N> {
             header_window_size,
             remote_window_scale,
             mss,
+            ecn,
+            mut timestamps,
+            connection_token,
             ..
         } = inflight;
 
@@ -254,6 +480,11 @@ impl<const N: usize> SharedPassiveSocket<N> {
             return Err(Fail::new(EBADMSG, "invalid SYN+ACK seq num"));
         }
 
+        // Echo the most recent timestamp of our peer from now on.
+        if let (Some(timestamps), Some((value, _))) = (timestamps.as_mut(), header.get_timestamp()) {
+            timestamps.update(value);
+        }
+
         let (local_window_scale, remote_window_scale) = match remote_window_scale {
             Some(w) => (self.tcp_config.get_window_scale() as u32, w),
             None => (0, 0),
@@ -291,14 +522,18 @@ impl<const N: usize> SharedPassiveSocket<N> {
             remote_window_size,
             remote_window_scale,
             mss,
-            congestion_control::None::new,
-            None,
+            congestion_control::constructor(&self.tcp_config),
+            congestion_control::initial_window_options(&self.tcp_config, mss),
+            ecn,
+            timestamps,
+            connection_token,
             self.dead_socket_tx.clone(),
+            self.observer.clone(),
         )?;
 
         // If there is data with the SYN+ACK, deliver it.
         if !buf.is_empty() {
-            new_socket.receive(header, buf);
+            new_socket.receive(header, buf, congestion_experienced);
         }
 
         // Remove SYN+ACK coroutine. Setting the yielder handle will keep it from being woken in the future.
@@ -307,7 +542,9 @@ impl<const N: usize> SharedPassiveSocket<N> {
             panic!("Failed to remove inflight accept (error={:?})", e);
         }
 
-        self.ready.push(Ok(new_socket));
+        let now: Instant = self.runtime.get_now();
+        self.ready.push((now, Ok(new_socket)));
+        self.observer.notify(self.local, remote, ConnEventKind::Established);
         Ok(())
     }
 
@@ -316,6 +553,10 @@ impl<const N: usize> SharedPassiveSocket<N> {
         remote: SocketAddrV4,
         remote_isn: SeqNumber,
         local_isn: SeqNumber,
+        ecn: bool,
+        window_scale: bool,
+        timestamps: Option<Timestamps>,
+        connection_token: Option<u32>,
         yielder: Yielder,
     ) {
         let handshake_retries: usize = self.tcp_config.get_handshake_retries();
@@ -336,12 +577,28 @@ impl<const N: usize> SharedPassiveSocket<N> {
             tcp_hdr.ack_num = remote_isn + SeqNumber::from(1);
             tcp_hdr.window_size = self.tcp_config.get_receive_window_size();
 
+            // Agree to use ECN by setting ECE but not CWR on our SYN+ACK (see RFC 3168 Section 6.1.1).
+            tcp_hdr.ece = ecn;
+
             let mss = self.tcp_config.get_advertised_mss() as u16;
             tcp_hdr.push_option(TcpOptions2::MaximumSegmentSize(mss));
             info!("Advertising MSS: {}", mss);
 
-            tcp_hdr.push_option(TcpOptions2::WindowScale(self.tcp_config.get_window_scale()));
-            info!("Advertising window scale: {}", self.tcp_config.get_window_scale());
+            if window_scale {
+                tcp_hdr.push_option(TcpOptions2::WindowScale(self.tcp_config.get_window_scale()));
+                info!("Advertising window scale: {}", self.tcp_config.get_window_scale());
+            }
+
+            if let Some(ref timestamps) = timestamps {
+                tcp_hdr.push_option(TcpOptions2::Timestamp {
+                    sender_timestamp: timestamps.value(self.runtime.get_now()),
+                    echo_timestamp: timestamps.echo(),
+                });
+            }
+
+            if let Some(token) = connection_token {
+                tcp_hdr.push_option(TcpOptions2::ConnectionToken(token));
+            }
 
             debug!("Sending SYN+ACK: {:?}", tcp_hdr);
             let segment = TcpSegment {
@@ -349,16 +606,20 @@ impl<const N: usize> SharedPassiveSocket<N> {
                 ipv4_hdr: Ipv4Header::new(self.local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
                 tcp_hdr,
                 data: None,
-                tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
+                tx_checksum_offload: self.tcp_config.get_tx_checksum_offload(),
             };
+            #[cfg(feature = "tcp-md5")]
+            let segment: TcpSegment = md5::sign(segment, &self.tcp_config);
             self.transport.transmit(Box::new(segment));
             let clock_ref: SharedTimer = self.runtime.get_timer();
             if let Err(e) = clock_ref.wait(handshake_timeout, &yielder).await {
-                self.ready.push(Err(e));
+                let now: Instant = self.runtime.get_now();
+                self.ready.push((now, Err(e)));
                 return;
             }
         }
-        self.ready.push(Err(Fail::new(ETIMEDOUT, "handshake timeout")));
+        let now: Instant = self.runtime.get_now();
+        self.ready.push((now, Err(Fail::new(ETIMEDOUT, "handshake timeout"))));
     }
 }
 
@@ -8,17 +8,41 @@
 use crate::{
     inetstack::protocols::{
         arp::SharedArpPeer,
+        ethernet2::{
+            EtherType2,
+            Ethernet2Header,
+        },
+        ip::IpProtocol,
         ipv4::Ipv4Header,
         tcp::{
-            isn_generator::IsnGenerator,
+            filter::{
+                FilterNetworkRuntime,
+                SharedTransmitFilter,
+            },
+            isn_generator::{
+                IsnGenerator,
+                IsnGeneratorState,
+            },
+            metrics::{
+                ConnMetrics,
+                ListenerStats,
+            },
+            observer::{
+                ConnEvent,
+                SharedConnectionObserver,
+            },
             queue::SharedTcpQueue,
-            segment::TcpHeader,
+            segment::{
+                TcpHeader,
+                TcpSegment,
+            },
             SeqNumber,
         },
     },
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
+        metrics::MetricsText,
         network::{
             config::TcpConfig,
             socket::SocketId,
@@ -35,6 +59,7 @@ use crate::{
         OperationResult,
         QDesc,
         QToken,
+        QType,
         SharedBox,
         SharedDemiRuntime,
         SharedObject,
@@ -63,6 +88,9 @@ use ::std::{
 #[cfg(feature = "profiler")]
 use crate::timer;
 
+#[cfg(feature = "tcp-migration")]
+use ::std::collections::HashMap;
+
 //======================================================================================================================
 // Structures
 //======================================================================================================================
@@ -77,6 +105,11 @@ pub struct TcpPeer<const N: usize> {
     arp: SharedArpPeer<N>,
     rng: SmallRng,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+    observer: SharedConnectionObserver,
+    filter: SharedTransmitFilter,
+    /// Connections that negotiated a connection token, so that they can be found when our peer moves to a new address.
+    #[cfg(feature = "tcp-migration")]
+    connection_tokens: HashMap<u32, QDesc>,
 }
 
 #[derive(Clone)]
@@ -99,8 +132,12 @@ impl<const N: usize> SharedTcpPeer<N> {
         let mut rng: SmallRng = SmallRng::from_seed(rng_seed);
         let nonce: u32 = rng.gen();
         let (tx, _) = mpsc::unbounded();
+        // Run all segments that we send through the transmit filter.
+        let filter: SharedTransmitFilter = SharedTransmitFilter::new();
+        let transport: SharedBox<dyn NetworkRuntime<N>> =
+            SharedBox::<dyn NetworkRuntime<N>>::new(Box::new(FilterNetworkRuntime::new(transport, filter.clone())));
         Ok(Self(SharedObject::<TcpPeer<N>>::new(TcpPeer::<N> {
-            isn_generator: IsnGenerator::new(nonce),
+            isn_generator: IsnGenerator::with_seed(nonce),
             runtime,
             transport,
             local_link_addr,
@@ -109,6 +146,10 @@ impl<const N: usize> SharedTcpPeer<N> {
             arp,
             rng,
             dead_socket_tx: tx,
+            observer: SharedConnectionObserver::new(),
+            filter,
+            #[cfg(feature = "tcp-migration")]
+            connection_tokens: HashMap::new(),
         })))
     }
 
@@ -121,8 +162,9 @@ impl<const N: usize> SharedTcpPeer<N> {
             self.tcp_config.clone(),
             self.arp.clone(),
             self.dead_socket_tx.clone(),
+            self.observer.clone(),
         );
-        let new_qd: QDesc = self.runtime.alloc_queue::<SharedTcpQueue<N>>(new_queue);
+        let new_qd: QDesc = self.runtime.alloc_queue::<SharedTcpQueue<N>>(new_queue)?;
         Ok(new_qd)
     }
 
@@ -158,7 +200,7 @@ impl<const N: usize> SharedTcpPeer<N> {
         }
 
         // Check if this is an ephemeral port.
-        if SharedDemiRuntime::is_private_ephemeral_port(local.port()) {
+        if self.runtime.is_private_ephemeral_port(local.port()) {
             // Allocate ephemeral port from the pool, to leave  ephemeral port allocator in a consistent state.
             self.runtime.reserve_ephemeral_port(local.port())?
         }
@@ -174,7 +216,7 @@ impl<const N: usize> SharedTcpPeer<N> {
             },
             Err(e) => {
                 // Rollback ephemeral port allocation.
-                if SharedDemiRuntime::is_private_ephemeral_port(local.port()) {
+                if self.runtime.is_private_ephemeral_port(local.port()) {
                     if self.runtime.free_ephemeral_port(local.port()).is_err() {
                         warn!("bind(): leaking ephemeral port (port={})", local.port());
                     }
@@ -205,6 +247,49 @@ impl<const N: usize> SharedTcpPeer<N> {
         }
     }
 
+    // Changes the backlog of a passive socket.
+    pub fn set_backlog(&mut self, qd: QDesc, backlog: usize) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_backlog(backlog)
+    }
+
+    // Changes the maximum time that connections of a passive socket may be pending acceptance.
+    pub fn set_ready_ttl(&mut self, qd: QDesc, ttl: Option<Duration>) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_ready_ttl(ttl)
+    }
+
+    // Stops handing out connections of a passive socket, without closing it.
+    pub fn pause_accept(&mut self, qd: QDesc) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_accept_paused(true)
+    }
+
+    // Resumes handing out connections of a passive socket.
+    pub fn resume_accept(&mut self, qd: QDesc) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_accept_paused(false)
+    }
+
+    // Pins the initial sequence number of the connections of a socket, overriding the ISN generator (for testing only).
+    #[cfg(test)]
+    pub fn set_fixed_isn(&mut self, qd: QDesc, isn: Option<SeqNumber>) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_fixed_isn(isn);
+        Ok(())
+    }
+
+    // Takes a snapshot of the generator of the initial sequence numbers of the connections that we open.
+    pub fn isn_generator_state(&self) -> IsnGeneratorState {
+        self.isn_generator.state()
+    }
+
+    // Restores the generator of the initial sequence numbers of the connections that we open from a snapshot, so that
+    // they follow the same sequence as when the snapshot was taken.
+    pub fn restore_isn_generator(&mut self, state: IsnGeneratorState) {
+        self.isn_generator = IsnGenerator::from_state(state);
+    }
+
+    // Returns a snapshot of the state of a passive socket.
+    pub fn listener_stats(&self, qd: QDesc) -> Result<ListenerStats, Fail> {
+        self.get_shared_queue(&qd)?.listener_stats()
+    }
+
     /// Sets up the coroutine for accepting a new connection.
     pub fn accept(&mut self, qd: QDesc) -> Result<QToken, Fail> {
         trace!("accept(): qd={:?}", qd);
@@ -233,13 +318,32 @@ impl<const N: usize> SharedTcpPeer<N> {
         };
         // Wait for accept to complete.
         match queue.accept_coroutine(yielder).await {
-            Ok(new_queue) => {
+            Ok(mut new_queue) => {
                 // Handle result: If successful, allocate a new queue.
                 let endpoints: (SocketAddrV4, SocketAddrV4) = match new_queue.endpoints() {
                     Ok(endpoints) => endpoints,
                     Err(e) => return (qd, OperationResult::Failed(e)),
                 };
-                let new_qd: QDesc = self.runtime.alloc_queue::<SharedTcpQueue<N>>(new_queue.clone());
+                let new_qd: QDesc = match self.runtime.alloc_queue::<SharedTcpQueue<N>>(new_queue.clone()) {
+                    Ok(new_qd) => {
+                        let _ = queue.set_accept_backoff(false);
+                        new_qd
+                    },
+                    Err(e) => {
+                        // The connection cannot be handed out, so reset it rather than leaving our peer hanging. If we
+                        // ran out of queue descriptors, also back off accepting, so that we do not go through every
+                        // pending connection the same way until descriptors are released.
+                        new_queue.abort();
+                        if e.errno == libc::EMFILE || e.errno == libc::ENFILE {
+                            warn!(
+                                "accept_coroutine(): out of queue descriptors, backing off (qd={:?})",
+                                qd
+                            );
+                            let _ = queue.set_accept_backoff(true);
+                        }
+                        return (qd, OperationResult::Failed(e));
+                    },
+                };
                 if let Some(existing_qd) = self
                     .runtime
                     .insert_socket_id_to_qd(SocketId::Active(endpoints.0, endpoints.1), new_qd)
@@ -251,6 +355,8 @@ impl<const N: usize> SharedTcpPeer<N> {
                         existing_qd
                     );
                 }
+                #[cfg(feature = "tcp-migration")]
+                self.register_connection_token(new_qd, &new_queue);
                 (qd, OperationResult::Accept((new_qd, endpoints.1)))
             },
             Err(e) => (qd, OperationResult::Failed(e)),
@@ -284,6 +390,12 @@ impl<const N: usize> SharedTcpPeer<N> {
                 existing_qd
             );
         }
+        #[cfg(test)]
+        let local_isn: SeqNumber = match queue.fixed_isn() {
+            Some(isn) => isn,
+            None => self.isn_generator.generate(&local, &remote),
+        };
+        #[cfg(not(test))]
         let local_isn: SeqNumber = self.isn_generator.generate(&local, &remote);
         let coroutine_constructor = || -> Result<TaskHandle, Fail> {
             let task_name: String = format!("inetstack::tcp::connect for qd={:?}", qd);
@@ -311,7 +423,11 @@ impl<const N: usize> SharedTcpPeer<N> {
             .expect("We should have allocated endpoints when we allocated the coroutine");
         // Wait for connect to complete.
         match queue.connect_coroutine(yielder).await {
-            Ok(()) => (qd, OperationResult::Connect),
+            Ok(()) => {
+                #[cfg(feature = "tcp-migration")]
+                self.register_connection_token(qd, &queue);
+                (qd, OperationResult::Connect)
+            },
             Err(e) => {
                 self.runtime.remove_socket_id_to_qd(&SocketId::Active(local, remote));
                 (qd, OperationResult::Failed(e))
@@ -319,6 +435,35 @@ impl<const N: usize> SharedTcpPeer<N> {
         }
     }
 
+    /// Sets up the coroutine for waiting until the connection of the socket is established.
+    pub fn wait_established(&mut self, qd: QDesc) -> Result<QToken, Fail> {
+        trace!("wait_established(): qd={:?}", qd);
+        let mut queue: SharedTcpQueue<N> = self.get_shared_queue(&qd)?;
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("inetstack::tcp::wait_established for qd={:?}", qd);
+            let yielder: Yielder = Yielder::new();
+            let yielder_handle: YielderHandle = yielder.get_handle();
+            let coroutine: Pin<Box<Operation>> = Box::pin(self.clone().wait_established_coroutine(qd, yielder));
+            self.runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine, yielder_handle, qd)
+        };
+
+        queue.wait_established(coroutine_constructor)
+    }
+
+    /// Runs until the three-way handshake of the socket completes.
+    async fn wait_established_coroutine(self, qd: QDesc, yielder: Yielder) -> (QDesc, OperationResult) {
+        // Grab the queue, make sure it hasn't been closed in the meantime.
+        let queue: SharedTcpQueue<N> = match self.runtime.get_shared_queue(&qd) {
+            Ok(queue) => queue,
+            Err(e) => return (qd, OperationResult::Failed(e)),
+        };
+        match queue.wait_established_coroutine(yielder).await {
+            Ok(()) => (qd, OperationResult::Connect),
+            Err(e) => (qd, OperationResult::Failed(e)),
+        }
+    }
+
     /// Pushes immediately to the socket and returns the result asynchronously.
     pub fn push(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
         let mut queue: SharedTcpQueue<N> = self.get_shared_queue(&qd)?;
@@ -334,6 +479,21 @@ impl<const N: usize> SharedTcpPeer<N> {
         queue.push(buf, coroutine_constructor)
     }
 
+    /// Pushes urgent data immediately to the socket and returns the result asynchronously.
+    pub fn push_urgent(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
+        let mut queue: SharedTcpQueue<N> = self.get_shared_queue(&qd)?;
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("inetstack::tcp::push_urgent for qd={:?}", qd);
+            let yielder: Yielder = Yielder::new();
+            let yielder_handle: YielderHandle = yielder.get_handle();
+            let coroutine: Pin<Box<Operation>> = Box::pin(self.clone().push_coroutine(qd, yielder));
+            self.runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine, yielder_handle, qd)
+        };
+
+        queue.push_urgent(buf, coroutine_constructor)
+    }
+
     async fn push_coroutine(self, qd: QDesc, yielder: Yielder) -> (QDesc, OperationResult) {
         // Grab the queue, make sure it hasn't been closed in the meantime.
         // This will bump the Rc refcount so the coroutine can have it's own reference to the shared queue data
@@ -383,6 +543,39 @@ impl<const N: usize> SharedTcpPeer<N> {
         }
     }
 
+    /// Hands the data that is ready to be read from the socket to `f`, without setting up a coroutine.
+    pub fn pop_with<F: FnOnce(&[u8])>(&mut self, qd: QDesc, f: F) -> Result<usize, Fail> {
+        self.get_shared_queue(&qd)?.pop_with(f)
+    }
+
+    /// Sets up a coroutine for popping urgent data from the socket.
+    pub fn pop_urgent(&mut self, qd: QDesc) -> Result<QToken, Fail> {
+        let mut queue: SharedTcpQueue<N> = self.get_shared_queue(&qd)?;
+        let coroutine_constructor = || -> Result<TaskHandle, Fail> {
+            let task_name: String = format!("inetstack::tcp::pop_urgent for qd={:?}", qd);
+            let yielder: Yielder = Yielder::new();
+            let yielder_handle: YielderHandle = yielder.get_handle();
+            let coroutine: Pin<Box<Operation>> = Box::pin(self.clone().pop_urgent_coroutine(qd, yielder));
+            self.runtime
+                .insert_coroutine_with_tracking(&task_name, coroutine, yielder_handle, qd)
+        };
+
+        queue.pop(coroutine_constructor)
+    }
+
+    async fn pop_urgent_coroutine(self, qd: QDesc, yielder: Yielder) -> (QDesc, OperationResult) {
+        // Grab the queue, make sure it hasn't been closed in the meantime.
+        let mut queue: SharedTcpQueue<N> = match self.get_shared_queue(&qd) {
+            Ok(queue) => queue,
+            Err(e) => return (qd, OperationResult::Failed(e)),
+        };
+        // Wait for pop to complete.
+        match queue.pop_urgent_coroutine(yielder).await {
+            Ok(buf) => (qd, OperationResult::Pop(None, buf)),
+            Err(e) => (qd, OperationResult::Failed(e)),
+        }
+    }
+
     /// Closes a TCP socket.
     pub fn close(&mut self, qd: QDesc) -> Result<(), Fail> {
         trace!("Closing socket: qd={:?}", qd);
@@ -398,6 +591,8 @@ impl<const N: usize> SharedTcpPeer<N> {
                 _ => return Err(Fail::new(libc::EINVAL, "socket id did not map to this qd!")),
             };
         }
+        #[cfg(feature = "tcp-migration")]
+        self.connection_tokens.retain(|_, token_qd| *token_qd != qd);
         Ok(())
     }
 
@@ -441,6 +636,8 @@ impl<const N: usize> SharedTcpPeer<N> {
                         },
                     }
                 }
+                #[cfg(feature = "tcp-migration")]
+                self.connection_tokens.retain(|_, token_qd| *token_qd != qd);
                 // Free the queue.
                 self.runtime
                     .free_queue::<SharedTcpQueue<N>>(&qd)
@@ -456,20 +653,216 @@ impl<const N: usize> SharedTcpPeer<N> {
         self.get_shared_queue(&qd)?.remote_mss()
     }
 
+    pub fn send_queue_len(&self, qd: QDesc) -> Result<usize, Fail> {
+        self.get_shared_queue(&qd)?.send_queue_len()
+    }
+
+    pub fn set_quickack(&mut self, qd: QDesc, enable: bool) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_quickack(enable)
+    }
+
+    pub fn set_rcvlowat(&mut self, qd: QDesc, bytes: usize) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_rcvlowat(bytes)
+    }
+
+    pub fn flush_ack(&mut self, qd: QDesc) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.flush_ack()
+    }
+
+    #[cfg(feature = "experimental")]
+    pub fn set_cwnd(&mut self, qd: QDesc, cwnd: u32) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_cwnd(cwnd)
+    }
+
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, qd: QDesc, remote: Ipv4Addr, key: &[u8]) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_md5_key(remote, key)
+    }
+
+    #[cfg(feature = "experimental")]
+    pub fn get_cwnd(&self, qd: QDesc) -> Result<u32, Fail> {
+        self.get_shared_queue(&qd)?.get_cwnd()
+    }
+
+    pub fn set_cork(&mut self, qd: QDesc, enable: bool) -> Result<(), Fail> {
+        self.get_shared_queue(&qd)?.set_cork(enable)
+    }
+
     pub fn current_rto(&self, qd: QDesc) -> Result<Duration, Fail> {
         self.get_shared_queue(&qd)?.current_rto()
     }
 
+    pub fn connection_metrics(&self, qd: QDesc) -> Result<ConnMetrics, Fail> {
+        self.get_shared_queue(&qd)?.connection_metrics()
+    }
+
+    pub fn dump_tcb(&self, qd: QDesc) -> Result<String, Fail> {
+        self.get_shared_queue(&qd)?.dump_tcb()
+    }
+
     pub fn endpoints(&self, qd: QDesc) -> Result<(SocketAddrV4, SocketAddrV4), Fail> {
         self.get_shared_queue(&qd)?.endpoints()
     }
 
+    /// Adds the metrics of every listening socket and established connection to `metrics`.
+    pub fn write_metrics(&self, metrics: &mut MetricsText) {
+        for qd in self.runtime.get_qtable().get_qds() {
+            if !matches!(self.runtime.get_queue_type(&qd), Ok(QType::TcpSocket)) {
+                continue;
+            }
+            let queue: SharedTcpQueue<N> = match self.get_shared_queue(&qd) {
+                Ok(queue) => queue,
+                Err(_) => continue,
+            };
+
+            if let Ok(stats) = queue.listener_stats() {
+                let labels: [(&str, String); 2] = [
+                    ("qd", u32::from(qd).to_string()),
+                    (
+                        "local",
+                        queue.local().map(|local| local.to_string()).unwrap_or_default(),
+                    ),
+                ];
+                metrics.counter(
+                    "demikernel_tcp_listener_accepted_total",
+                    "Number of connections that were accepted.",
+                    &labels,
+                    stats.accepted as f64,
+                );
+                metrics.counter(
+                    "demikernel_tcp_listener_refused_total",
+                    "Number of connection requests that were refused because the backlog was full.",
+                    &labels,
+                    stats.refused as f64,
+                );
+                metrics.gauge(
+                    "demikernel_tcp_listener_inflight",
+                    "Number of connections that are being established.",
+                    &labels,
+                    stats.inflight as f64,
+                );
+                metrics.gauge(
+                    "demikernel_tcp_listener_ready",
+                    "Number of connections that are established and waiting to be accepted.",
+                    &labels,
+                    stats.ready as f64,
+                );
+            }
+
+            if let (Ok(conn), Ok((local, remote))) = (queue.connection_metrics(), queue.endpoints()) {
+                let labels: [(&str, String); 3] = [
+                    ("qd", u32::from(qd).to_string()),
+                    ("local", local.to_string()),
+                    ("remote", remote.to_string()),
+                ];
+                metrics.gauge(
+                    "demikernel_tcp_srtt_seconds",
+                    "Smoothed round-trip time.",
+                    &labels,
+                    conn.srtt.as_secs_f64(),
+                );
+                metrics.gauge(
+                    "demikernel_tcp_rttvar_seconds",
+                    "Round-trip time variation.",
+                    &labels,
+                    conn.rttvar.as_secs_f64(),
+                );
+                metrics.gauge(
+                    "demikernel_tcp_cwnd_bytes",
+                    "Congestion window (in bytes).",
+                    &labels,
+                    conn.cwnd as f64,
+                );
+                metrics.gauge(
+                    "demikernel_tcp_ssthresh_bytes",
+                    "Slow start threshold (in bytes).",
+                    &labels,
+                    conn.ssthresh as f64,
+                );
+                metrics.gauge(
+                    "demikernel_tcp_bytes_in_flight",
+                    "Number of bytes sent but not yet acknowledged.",
+                    &labels,
+                    conn.bytes_in_flight as f64,
+                );
+                metrics.counter(
+                    "demikernel_tcp_duplicate_acks_total",
+                    "Number of duplicate ACKs received.",
+                    &labels,
+                    conn.duplicate_acks as f64,
+                );
+                metrics.counter(
+                    "demikernel_tcp_fast_retransmits_total",
+                    "Number of fast retransmissions triggered by duplicate ACKs.",
+                    &labels,
+                    conn.fast_retransmits as f64,
+                );
+            }
+        }
+    }
+
+    /// Installs `f` as the observer of connection establishment and teardown events, replacing any previous one.
+    pub fn set_connection_observer(&mut self, f: Box<dyn Fn(ConnEvent)>) {
+        self.observer.set(f)
+    }
+
+    /// Installs `f` as the transmit filter, replacing any previous one. The filter is shown every outgoing segment right
+    /// before it is transmitted, and may rewrite its headers. Checksums are computed after the filter runs.
+    pub fn set_transmit_filter(&mut self, f: Box<dyn FnMut(&mut TcpSegment)>) {
+        self.filter.set(f)
+    }
+
     fn get_shared_queue(&self, qd: &QDesc) -> Result<SharedTcpQueue<N>, Fail> {
         self.runtime.get_shared_queue::<SharedTcpQueue<N>>(qd)
     }
 
+    /// Remembers the connection token of the connection on `qd`, if it negotiated one.
+    #[cfg(feature = "tcp-migration")]
+    fn register_connection_token(&mut self, qd: QDesc, queue: &SharedTcpQueue<N>) {
+        if let Some(token) = queue.connection_token() {
+            self.connection_tokens.insert(token, qd);
+        }
+    }
+
+    /// Looks up the connection that `tcp_hdr` belongs to by the connection token that it carries, for a segment that
+    /// came from an address that matches no connection.
+    #[cfg(feature = "tcp-migration")]
+    fn find_migrated(&self, local: SocketAddrV4, tcp_hdr: &TcpHeader) -> Option<QDesc> {
+        let token: u32 = tcp_hdr.get_connection_token()?;
+        let qd: QDesc = *self.connection_tokens.get(&token)?;
+        let (old_local, _): (SocketAddrV4, SocketAddrV4) = self.get_shared_queue(&qd).ok()?.endpoints().ok()?;
+        // Only our peer may move, segments for another local address do not belong to this connection.
+        if old_local != local {
+            return None;
+        }
+        Some(qd)
+    }
+
+    /// Rebinds the connection on `qd` to `remote`, which is the new address of our peer, if `tcp_hdr` is acceptable to
+    /// it.
+    #[cfg(feature = "tcp-migration")]
+    fn migrate(
+        &mut self,
+        qd: QDesc,
+        local: SocketAddrV4,
+        remote: SocketAddrV4,
+        tcp_hdr: &TcpHeader,
+        data_len: usize,
+    ) -> Result<(), Fail> {
+        let mut queue: SharedTcpQueue<N> = self.get_shared_queue(&qd)?;
+        let (_, old_remote): (SocketAddrV4, SocketAddrV4) = queue.endpoints()?;
+        queue.rebind(remote, tcp_hdr, data_len)?;
+        self.runtime
+            .remove_socket_id_to_qd(&SocketId::Active(local, old_remote));
+        self.runtime.insert_socket_id_to_qd(SocketId::Active(local, remote), qd);
+        Ok(())
+    }
+
     /// Processes an incoming TCP segment.
     pub fn receive(&mut self, ip_hdr: &Ipv4Header, buf: DemiBuffer) -> Result<(), Fail> {
+        // Signatures cover the header as it was received, so hold on to it.
+        #[cfg(feature = "tcp-md5")]
+        let segment: DemiBuffer = buf.clone();
         let (tcp_hdr, data): (TcpHeader, DemiBuffer) =
             TcpHeader::parse(ip_hdr, buf, self.tcp_config.get_rx_checksum_offload())?;
         debug!("TCP received {:?}", tcp_hdr);
@@ -483,22 +876,74 @@ impl<const N: usize> SharedTcpPeer<N> {
         }
 
         // Retrieve the queue descriptor based on the incoming segment.
-        let qd: QDesc = match self.runtime.get_qd_from_socket_id(&SocketId::Active(local, remote)) {
+        let qd: Option<QDesc> = self.runtime.get_qd_from_socket_id(&SocketId::Active(local, remote));
+        // Segments from an unknown address may belong to a connection whose peer moved.
+        #[cfg(feature = "tcp-migration")]
+        let migrated: Option<QDesc> = match qd {
+            Some(_) => None,
+            None => self.find_migrated(local, &tcp_hdr),
+        };
+        #[cfg(feature = "tcp-migration")]
+        let qd: Option<QDesc> = qd.or(migrated);
+        let qd: QDesc = match qd {
             Some(qdesc) => qdesc,
             None => match self.runtime.get_qd_from_socket_id(&SocketId::Passive(local)) {
                 Some(qdesc) => qdesc,
-                None => {
-                    let cause: String = format!("no queue descriptor for remote address (remote={})", remote.ip());
-                    error!("receive(): {}", &cause);
-                    return Err(Fail::new(libc::EBADF, &cause));
+                // No socket is bound to the port, so reset the connection, unless the segment is a reset itself.
+                None => match tcp_hdr.new_rst_reply(data.len()) {
+                    Some(rst_hdr) => {
+                        debug!(
+                            "receive(): sending RST for closed port (local={:?}, remote={:?})",
+                            local, remote
+                        );
+                        return self.send_rst(local, remote, rst_hdr);
+                    },
+                    None => {
+                        let cause: String = format!("no queue descriptor for remote address (remote={})", remote.ip());
+                        error!("receive(): {}", &cause);
+                        return Err(Fail::new(libc::EBADF, &cause));
+                    },
                 },
             },
         };
 
+        // Drop segments that are not signed with the key that we share with our peer, if any.
+        #[cfg(feature = "tcp-md5")]
+        self.get_shared_queue(&qd)?.verify_md5(ip_hdr, &tcp_hdr, &segment[..])?;
+
+        // Only move the connection once the segment is known to come from our peer.
+        #[cfg(feature = "tcp-migration")]
+        if let Some(qd) = migrated {
+            self.migrate(qd, local, remote, &tcp_hdr, data.len())?;
+        }
+
         // Dispatch to further processing depending on the socket state.
         self.get_shared_queue(&qd)?
             .receive(ip_hdr, tcp_hdr, local, remote, data)
     }
+
+    /// Sends a RST segment with header `tcp_hdr` from `local` to `remote`, for a segment that matched no socket.
+    fn send_rst(&mut self, local: SocketAddrV4, remote: SocketAddrV4, tcp_hdr: TcpHeader) -> Result<(), Fail> {
+        // Query link address for destination. Do not wait for an ARP reply, as the segment is not worth it.
+        let dst_link_addr: MacAddress = match self.arp.try_query(remote.ip().clone()) {
+            Some(link_addr) => link_addr,
+            None => {
+                let cause: String = format!("missing ARP entry (remote={})", remote.ip());
+                error!("send_rst(): {}", &cause);
+                return Err(Fail::new(libc::EHOSTUNREACH, &cause));
+            },
+        };
+
+        let segment: TcpSegment = TcpSegment {
+            ethernet2_hdr: Ethernet2Header::new(dst_link_addr, self.local_link_addr, EtherType2::Ipv4),
+            ipv4_hdr: Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
+            tcp_hdr,
+            data: None,
+            tx_checksum_offload: self.tcp_config.get_tx_checksum_offload(),
+        };
+        self.transport.transmit(Box::new(segment));
+        Ok(())
+    }
 }
 
 //======================================================================================================================
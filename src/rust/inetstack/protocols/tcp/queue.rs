@@ -13,10 +13,18 @@ use crate::{
                 Ethernet2Header,
             },
             ip::IpProtocol,
-            ipv4::Ipv4Header,
+            ipv4::{
+                Ipv4Header,
+                IPV4_ECN_CE,
+            },
             tcp::{
                 active_open::SharedActiveOpenSocket,
                 established::EstablishedSocket,
+                metrics::{
+                    ConnMetrics,
+                    ListenerStats,
+                },
+                observer::SharedConnectionObserver,
                 passive_open::SharedPassiveSocket,
                 segment::{
                     TcpHeader,
@@ -35,7 +43,10 @@ use crate::{
         network::{
             socket::{
                 operation::SocketOp,
-                state::SocketStateMachine,
+                state::{
+                    SocketState,
+                    SocketStateMachine,
+                },
                 SocketId,
             },
             NetworkRuntime,
@@ -68,6 +79,11 @@ use ::std::{
     time::Duration,
 };
 
+#[cfg(feature = "tcp-md5")]
+use crate::inetstack::protocols::tcp::md5;
+#[cfg(feature = "tcp-md5")]
+use ::std::net::Ipv4Addr;
+
 //======================================================================================================================
 // Enumerations
 //======================================================================================================================
@@ -95,6 +111,11 @@ pub struct TcpQueue<const N: usize> {
     tcp_config: TcpConfig,
     arp: SharedArpPeer<N>,
     dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+    observer: SharedConnectionObserver,
+    /// Initial sequence number to use instead of a generated one, so that tests can make exact assertions on the
+    /// handshake. This is never compiled into non-test builds, as predictable ISNs open the door to spoofing attacks.
+    #[cfg(test)]
+    fixed_isn: Option<SeqNumber>,
 }
 
 #[derive(Clone)]
@@ -113,6 +134,7 @@ impl<const N: usize> SharedTcpQueue<N> {
         tcp_config: TcpConfig,
         arp: SharedArpPeer<N>,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+        observer: SharedConnectionObserver,
     ) -> Self {
         Self(SharedObject::<TcpQueue<N>>::new(TcpQueue {
             state_machine: SocketStateMachine::new_unbound(Type::STREAM),
@@ -123,6 +145,9 @@ impl<const N: usize> SharedTcpQueue<N> {
             tcp_config,
             arp,
             dead_socket_tx,
+            observer,
+            #[cfg(test)]
+            fixed_isn: None,
         }))
     }
 
@@ -134,6 +159,7 @@ impl<const N: usize> SharedTcpQueue<N> {
         tcp_config: TcpConfig,
         arp: SharedArpPeer<N>,
         dead_socket_tx: mpsc::UnboundedSender<QDesc>,
+        observer: SharedConnectionObserver,
     ) -> Self {
         Self(SharedObject::<TcpQueue<N>>::new(TcpQueue {
             state_machine: SocketStateMachine::new_established(),
@@ -144,6 +170,9 @@ impl<const N: usize> SharedTcpQueue<N> {
             tcp_config,
             arp,
             dead_socket_tx,
+            observer,
+            #[cfg(test)]
+            fixed_isn: None,
         }))
     }
 
@@ -158,7 +187,8 @@ impl<const N: usize> SharedTcpQueue<N> {
     /// Sets the target queue to listen for incoming connections.
     pub fn listen(&mut self, backlog: usize, nonce: u32) -> Result<(), Fail> {
         self.state_machine.prepare(SocketOp::Listen)?;
-        self.socket = Socket::Listening(SharedPassiveSocket::new(
+        #[allow(unused_mut)]
+        let mut socket: SharedPassiveSocket<N> = SharedPassiveSocket::new(
             self.local()
                 .expect("If we were able to prepare, then the socket must be bound"),
             backlog,
@@ -168,12 +198,89 @@ impl<const N: usize> SharedTcpQueue<N> {
             self.local_link_addr,
             self.arp.clone(),
             self.dead_socket_tx.clone(),
+            self.observer.clone(),
             nonce,
-        ));
+        );
+        #[cfg(test)]
+        socket.set_fixed_isn(self.fixed_isn);
+        self.socket = Socket::Listening(socket);
         self.state_machine.commit();
         Ok(())
     }
 
+    /// Pins the initial sequence number of the connections that the target queue opens or accepts from now on to `isn`.
+    /// `None` goes back to generating them.
+    #[cfg(test)]
+    pub fn set_fixed_isn(&mut self, isn: Option<SeqNumber>) {
+        self.fixed_isn = isn;
+    }
+
+    /// Gets the initial sequence number that connections of the target queue are pinned to, if any.
+    #[cfg(test)]
+    pub fn fixed_isn(&self) -> Option<SeqNumber> {
+        self.fixed_isn
+    }
+
+    /// Changes the backlog of the target queue, which must be listening for incoming connections.
+    pub fn set_backlog(&mut self, backlog: usize) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Listening(ref mut socket) => {
+                socket.set_backlog(backlog);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
+    /// Changes the ready queue TTL of the target queue, which must be listening for incoming connections.
+    pub fn set_ready_ttl(&mut self, ttl: Option<Duration>) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Listening(ref mut socket) => {
+                socket.set_ready_ttl(ttl);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
+    /// Pauses or resumes accepting connections on the target queue, which must be listening for incoming connections.
+    pub fn set_accept_paused(&mut self, paused: bool) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Listening(ref mut socket) => {
+                socket.set_paused(paused);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
+    /// Backs off accepting connections on the target queue, or stops backing off. The target queue must be listening
+    /// for incoming connections.
+    pub fn set_accept_backoff(&mut self, backoff: bool) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Listening(ref mut socket) => {
+                socket.set_backoff(backoff);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
+    /// Resets the connection of the target queue, if it is established, without going through the closing handshake.
+    pub fn abort(&mut self) {
+        if let Socket::Established(ref mut socket) = self.socket {
+            socket.abort();
+        }
+    }
+
+    /// Returns a snapshot of the state of the target queue, which must be listening for incoming connections.
+    pub fn listener_stats(&self) -> Result<ListenerStats, Fail> {
+        match self.socket {
+            Socket::Listening(ref socket) => Ok(socket.listener_stats()),
+            _ => Err(Fail::new(libc::EINVAL, "socket is not listening")),
+        }
+    }
+
     pub fn accept<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
     where
         F: FnOnce() -> Result<TaskHandle, Fail>,
@@ -201,6 +308,7 @@ impl<const N: usize> SharedTcpQueue<N> {
             self.tcp_config.clone(),
             self.arp.clone(),
             self.dead_socket_tx.clone(),
+            self.observer.clone(),
         );
         Ok(new_queue)
     }
@@ -228,6 +336,7 @@ impl<const N: usize> SharedTcpQueue<N> {
             self.local_link_addr,
             self.arp.clone(),
             self.dead_socket_tx.clone(),
+            self.observer.clone(),
         )?);
 
         Ok(self
@@ -257,6 +366,32 @@ impl<const N: usize> SharedTcpQueue<N> {
         }
     }
 
+    /// Sets up a coroutine that waits for the target queue to be connected to its remote. The target queue must be
+    /// connecting or connected.
+    pub fn wait_established<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
+    where
+        F: FnOnce() -> Result<TaskHandle, Fail>,
+    {
+        match self.socket {
+            Socket::Connecting(_) | Socket::Established(_) => Ok(coroutine_constructor()?.get_task_id().into()),
+            _ => Err(Fail::new(libc::ENOTCONN, "socket is not connecting")),
+        }
+    }
+
+    /// Waits until the three-way handshake of the target queue completes and the connection is established. This fails
+    /// if the handshake fails or the connection goes away.
+    pub async fn wait_established_coroutine(&self, yielder: Yielder) -> Result<(), Fail> {
+        loop {
+            match self.socket {
+                Socket::Established(_) => return Ok(()),
+                Socket::Connecting(_) if self.state_machine.get_state() == SocketState::ActiveConnecting => {
+                    yielder.yield_once().await?
+                },
+                _ => return Err(Fail::new(libc::ENOTCONN, "socket is not connected")),
+            }
+        }
+    }
+
     pub fn push<F>(&mut self, buf: DemiBuffer, coroutine_constructor: F) -> Result<QToken, Fail>
     where
         F: FnOnce() -> Result<TaskHandle, Fail>,
@@ -273,6 +408,22 @@ impl<const N: usize> SharedTcpQueue<N> {
             .into())
     }
 
+    pub fn push_urgent<F>(&mut self, buf: DemiBuffer, coroutine_constructor: F) -> Result<QToken, Fail>
+    where
+        F: FnOnce() -> Result<TaskHandle, Fail>,
+    {
+        self.state_machine.may_push()?;
+        // Send synchronously.
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.send_urgent(buf)?,
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        };
+        Ok(self
+            .do_generic_sync_data_path_call(coroutine_constructor)?
+            .get_task_id()
+            .into())
+    }
+
     pub async fn push_coroutine(&mut self, _yielder: Yielder) -> Result<(), Fail> {
         Ok(())
     }
@@ -296,6 +447,27 @@ impl<const N: usize> SharedTcpQueue<N> {
         }
     }
 
+    pub fn pop_with<F: FnOnce(&[u8])>(&mut self, f: F) -> Result<usize, Fail> {
+        self.state_machine.may_pop()?;
+        let buf: DemiBuffer = match self.socket {
+            Socket::Established(ref mut socket) => match socket.try_pop() {
+                Some(buf) => buf,
+                None => return Err(Fail::new(libc::EAGAIN, "no data available")),
+            },
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        };
+        f(&buf[..]);
+        Ok(buf.len())
+    }
+
+    pub async fn pop_urgent_coroutine(&mut self, yielder: Yielder) -> Result<DemiBuffer, Fail> {
+        self.state_machine.may_pop()?;
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.pop_urgent(yielder).await,
+            _ => unreachable!("State machine check should ensure that this socket is connected"),
+        }
+    }
+
     pub fn async_close<F>(&mut self, coroutine_constructor: F) -> Result<QToken, Fail>
     where
         F: FnOnce() -> Result<TaskHandle, Fail>,
@@ -394,6 +566,11 @@ impl<const N: usize> SharedTcpQueue<N> {
         }
     }
 
+    /// Gets the current connection state of the target queue.
+    pub fn get_state(&self) -> SocketState {
+        self.state_machine.get_state()
+    }
+
     pub fn remote_mss(&self) -> Result<usize, Fail> {
         match self.socket {
             Socket::Established(ref socket) => Ok(socket.remote_mss()),
@@ -401,6 +578,108 @@ impl<const N: usize> SharedTcpQueue<N> {
         }
     }
 
+    pub fn send_queue_len(&self) -> Result<usize, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) => Ok(socket.send_queue_len()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn set_quickack(&mut self, enable: bool) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_quickack(enable);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn set_rcvlowat(&mut self, bytes: usize) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.set_rcvlowat(bytes),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn flush_ack(&mut self) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.flush_ack();
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    #[cfg(feature = "experimental")]
+    pub fn set_cwnd(&mut self, cwnd: u32) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_cwnd(cwnd);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    #[cfg(feature = "experimental")]
+    pub fn get_cwnd(&self) -> Result<u32, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) => Ok(socket.get_cwnd()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    /// Sets the key that signs segments exchanged with `remote`. Keys only apply to the connections that the target
+    /// queue opens or accepts afterwards, so they must be set before it connects or listens.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, remote: Ipv4Addr, key: &[u8]) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Unbound | Socket::Bound(_) => {
+                self.tcp_config.set_md5_key(remote, key);
+                Ok(())
+            },
+            _ => Err(Fail::new(
+                libc::EINVAL,
+                "keys must be set before connecting or listening",
+            )),
+        }
+    }
+
+    /// Checks the MD5 signature of a segment with header `tcp_hdr` that is bound to the target queue. `buf` is the
+    /// segment as it was received.
+    #[cfg(feature = "tcp-md5")]
+    pub fn verify_md5(&self, ip_hdr: &Ipv4Header, tcp_hdr: &TcpHeader, buf: &[u8]) -> Result<(), Fail> {
+        md5::verify(ip_hdr, tcp_hdr, buf, &self.tcp_config)
+    }
+
+    #[cfg(feature = "tcp-migration")]
+    pub fn connection_token(&self) -> Option<u32> {
+        match self.socket {
+            Socket::Established(ref socket) => socket.connection_token(),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "tcp-migration")]
+    pub fn rebind(&mut self, remote: SocketAddrV4, header: &TcpHeader, data_len: usize) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => socket.rebind(remote, header, data_len),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn set_cork(&mut self, enable: bool) -> Result<(), Fail> {
+        match self.socket {
+            Socket::Established(ref mut socket) => {
+                socket.set_cork(enable);
+                Ok(())
+            },
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
     pub fn current_rto(&self) -> Result<Duration, Fail> {
         match self.socket {
             Socket::Established(ref socket) => Ok(socket.current_rto()),
@@ -408,6 +687,20 @@ impl<const N: usize> SharedTcpQueue<N> {
         }
     }
 
+    pub fn connection_metrics(&self) -> Result<ConnMetrics, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) => Ok(socket.connection_metrics()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
+    pub fn dump_tcb(&self) -> Result<String, Fail> {
+        match self.socket {
+            Socket::Established(ref socket) => Ok(socket.dump()),
+            _ => Err(Fail::new(libc::ENOTCONN, "connection not established")),
+        }
+    }
+
     pub fn endpoints(&self) -> Result<(SocketAddrV4, SocketAddrV4), Fail> {
         match self.socket {
             Socket::Established(ref socket) => Ok(socket.endpoints()),
@@ -424,26 +717,14 @@ impl<const N: usize> SharedTcpQueue<N> {
         remote: SocketAddrV4,
         buf: DemiBuffer,
     ) -> Result<(), Fail> {
-        // Generate the RST segment accordingly to the ACK field.
-        // If the incoming segment has an ACK field, the reset takes its
-        // sequence number from the ACK field of the segment, otherwise the
-        // reset has sequence number zero and the ACK field is set to the sum
-        // of the sequence number and segment length of the incoming segment.
-        // Reference: https://datatracker.ietf.org/doc/html/rfc793#section-3.4
-        let (seq_num, ack_num): (SeqNumber, Option<SeqNumber>) = if tcp_hdr.ack {
-            (tcp_hdr.ack_num, None)
-        } else {
-            (
-                SeqNumber::from(0),
-                Some(tcp_hdr.seq_num + SeqNumber::from(tcp_hdr.compute_size() as u32)),
-            )
-        };
+        // Generate the RST segment that is sent back if the segment is not accepted.
+        let rst_hdr: Option<TcpHeader> = tcp_hdr.new_rst_reply(buf.len());
 
         // Route the TCP packet to the socket.
         match self.socket {
             Socket::Established(ref mut socket) => {
                 debug!("Routing to established connection: {:?}", socket.endpoints());
-                socket.receive(tcp_hdr, buf);
+                socket.receive(tcp_hdr, buf, ip_hdr.get_ecn() == IPV4_ECN_CE);
                 return Ok(());
             },
             Socket::Connecting(ref mut socket) => {
@@ -472,24 +753,23 @@ impl<const N: usize> SharedTcpQueue<N> {
             // Fall through and send a RST segment back.
             Socket::Closing(ref mut socket) => {
                 debug!("Routing to closing connection: {:?}", socket.endpoints());
-                socket.receive(tcp_hdr, buf);
+                socket.receive(tcp_hdr, buf, ip_hdr.get_ecn() == IPV4_ECN_CE);
                 return Ok(());
             },
         }
 
-        debug!("receive(): sending RST (local={:?}, remote={:?})", local, remote);
-        self.send_rst(&local, &remote, seq_num, ack_num)?;
-        Ok(())
+        match rst_hdr {
+            Some(rst_hdr) => {
+                debug!("receive(): sending RST (local={:?}, remote={:?})", local, remote);
+                self.send_rst(&local, &remote, rst_hdr)
+            },
+            // Never answer a RST segment.
+            None => Ok(()),
+        }
     }
 
-    /// Sends a RST segment from `local` to `remote`.
-    pub fn send_rst(
-        &mut self,
-        local: &SocketAddrV4,
-        remote: &SocketAddrV4,
-        seq_num: SeqNumber,
-        ack_num: Option<SeqNumber>,
-    ) -> Result<(), Fail> {
+    /// Sends a RST segment with header `tcp_hdr` from `local` to `remote`.
+    pub fn send_rst(&mut self, local: &SocketAddrV4, remote: &SocketAddrV4, tcp_hdr: TcpHeader) -> Result<(), Fail> {
         // Query link address for destination.
         let dst_link_addr: MacAddress = match self.arp.try_query(remote.ip().clone()) {
             Some(link_addr) => link_addr,
@@ -503,22 +783,15 @@ impl<const N: usize> SharedTcpQueue<N> {
         };
 
         // Create a RST segment.
-        let segment: TcpSegment = {
-            let mut tcp_hdr: TcpHeader = TcpHeader::new(local.port(), remote.port());
-            tcp_hdr.rst = true;
-            tcp_hdr.seq_num = seq_num;
-            if let Some(ack_num) = ack_num {
-                tcp_hdr.ack = true;
-                tcp_hdr.ack_num = ack_num;
-            }
-            TcpSegment {
-                ethernet2_hdr: Ethernet2Header::new(dst_link_addr, self.local_link_addr, EtherType2::Ipv4),
-                ipv4_hdr: Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
-                tcp_hdr,
-                data: None,
-                tx_checksum_offload: self.tcp_config.get_rx_checksum_offload(),
-            }
+        let segment: TcpSegment = TcpSegment {
+            ethernet2_hdr: Ethernet2Header::new(dst_link_addr, self.local_link_addr, EtherType2::Ipv4),
+            ipv4_hdr: Ipv4Header::new(local.ip().clone(), remote.ip().clone(), IpProtocol::TCP),
+            tcp_hdr,
+            data: None,
+            tx_checksum_offload: self.tcp_config.get_tx_checksum_offload(),
         };
+        #[cfg(feature = "tcp-md5")]
+        let segment: TcpSegment = md5::sign(segment, &self.tcp_config);
 
         // Send it.
         let pkt: Box<TcpSegment> = Box::new(segment);
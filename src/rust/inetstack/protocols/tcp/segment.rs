@@ -11,11 +11,15 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
-        network::PacketBuf,
+        network::{
+            consts::MIN_MSS,
+            PacketBuf,
+        },
     },
 };
 use ::libc::EBADMSG;
 use ::std::{
+    any::Any,
     convert::TryInto,
     io::{
         Cursor,
@@ -28,6 +32,19 @@ pub const MIN_TCP_HEADER_SIZE: usize = 20;
 pub const MAX_TCP_HEADER_SIZE: usize = 60;
 pub const MAX_TCP_OPTIONS: usize = 5;
 
+/// Kind of the TCP options that are shared by experiments, which tell themselves apart by an experiment identifier
+/// (see RFC 6994).
+const TCP_OPTION_EXPERIMENTAL: u8 = 253;
+
+/// Experiment identifier of the connection token option.
+const TCP_CONNECTION_TOKEN_EXID: u16 = 0x4d47;
+
+/// Kind of the TCP MD5 signature option (see RFC 2385).
+pub const TCP_OPTION_MD5_SIGNATURE: u8 = 19;
+
+/// Size of the TCP MD5 signature option (in bytes).
+pub const TCP_OPTION_MD5_SIGNATURE_SIZE: usize = 18;
+
 pub struct TcpSegment {
     pub ethernet2_hdr: Ethernet2Header,
     pub ipv4_hdr: Ipv4Header,
@@ -81,6 +98,10 @@ impl PacketBuf for TcpSegment {
             None => None,
         }
     }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -104,6 +125,11 @@ pub enum TcpOptions2 {
         sender_timestamp: u32,
         echo_timestamp: u32,
     },
+    /// Opaque token that identifies a connection across changes of the address of either end. A zero token on a SYN
+    /// asks our peer to hand one out.
+    ConnectionToken(u32),
+    /// MD5 digest of a segment and of a key that both ends share, which authenticates the segment (see RFC 2385).
+    Md5Signature([u8; 16]),
 }
 
 impl TcpOptions2 {
@@ -117,6 +143,8 @@ impl TcpOptions2 {
             SelectiveAcknowlegementPermitted => 2,
             SelectiveAcknowlegement { num_sacks, .. } => 2 + 8 * num_sacks,
             Timestamp { .. } => 10,
+            ConnectionToken(..) => 8,
+            Md5Signature(..) => TCP_OPTION_MD5_SIGNATURE_SIZE,
         }
     }
 
@@ -164,6 +192,19 @@ impl TcpOptions2 {
                 buf[6..10].copy_from_slice(&echo_timestamp.to_be_bytes());
                 10
             },
+            ConnectionToken(token) => {
+                buf[0] = TCP_OPTION_EXPERIMENTAL;
+                buf[1] = 8;
+                buf[2..4].copy_from_slice(&TCP_CONNECTION_TOKEN_EXID.to_be_bytes());
+                buf[4..8].copy_from_slice(&token.to_be_bytes());
+                8
+            },
+            Md5Signature(digest) => {
+                buf[0] = TCP_OPTION_MD5_SIGNATURE;
+                buf[1] = TCP_OPTION_MD5_SIGNATURE_SIZE as u8;
+                buf[2..TCP_OPTION_MD5_SIGNATURE_SIZE].copy_from_slice(digest);
+                TCP_OPTION_MD5_SIGNATURE_SIZE
+            },
         }
     }
 }
@@ -225,6 +266,27 @@ impl TcpHeader {
         }
     }
 
+    /// Creates the header of a RST segment in reply to a segment with this header and `data_len` bytes of payload. If
+    /// that segment has an ACK field, the reset takes its sequence number from it. Otherwise, the reset has sequence
+    /// number zero and acknowledges the whole segment (see RFC 793 Section 3.4). A RST segment is never answered with
+    /// another one, so that peers cannot keep resetting each other, thus `None` is returned for those.
+    pub fn new_rst_reply(&self, data_len: usize) -> Option<Self> {
+        if self.rst {
+            return None;
+        }
+        let mut tcp_hdr: TcpHeader = TcpHeader::new(self.dst_port, self.src_port);
+        tcp_hdr.rst = true;
+        if self.ack {
+            tcp_hdr.seq_num = self.ack_num;
+        } else {
+            // SYN and FIN each take up a sequence number.
+            let seg_len: u32 = data_len as u32 + self.syn as u32 + self.fin as u32;
+            tcp_hdr.ack = true;
+            tcp_hdr.ack_num = self.seq_num + SeqNumber::from(seg_len);
+        }
+        Some(tcp_hdr)
+    }
+
     pub fn parse(
         ipv4_header: &Ipv4Header,
         mut buf: DemiBuffer,
@@ -356,6 +418,39 @@ impl TcpHeader {
                             echo_timestamp,
                         }
                     },
+                    TCP_OPTION_EXPERIMENTAL => {
+                        let mut temp: [u8; 1] = [0; 1];
+                        option_rdr.read_exact(&mut temp)?;
+                        let option_length: u8 = temp[0];
+                        if option_length < 4 {
+                            return Err(Fail::new(EBADMSG, "experimental TCP option size was too small"));
+                        }
+                        let mut temp: [u8; 2] = [0; 2];
+                        option_rdr.read_exact(&mut temp)?;
+                        let exid: u16 = u16::from_be_bytes(temp);
+                        if exid != TCP_CONNECTION_TOKEN_EXID {
+                            // Skip experiments that we do not take part in.
+                            option_rdr.set_position(option_rdr.position() + option_length as u64 - 4);
+                            continue;
+                        }
+                        if option_length != 8 {
+                            return Err(Fail::new(EBADMSG, "TCP connection token size was not 8"));
+                        }
+                        let mut temp: [u8; 4] = [0; 4];
+                        option_rdr.read_exact(&mut temp)?;
+                        TcpOptions2::ConnectionToken(u32::from_be_bytes(temp))
+                    },
+                    TCP_OPTION_MD5_SIGNATURE => {
+                        let mut temp: [u8; 1] = [0; 1];
+                        option_rdr.read_exact(&mut temp)?;
+                        let option_length: u8 = temp[0];
+                        if option_length as usize != TCP_OPTION_MD5_SIGNATURE_SIZE {
+                            return Err(Fail::new(EBADMSG, "TCP MD5 signature size was not 18"));
+                        }
+                        let mut digest: [u8; 16] = [0; 16];
+                        option_rdr.read_exact(&mut digest)?;
+                        TcpOptions2::Md5Signature(digest)
+                    },
                     _ => return Err(Fail::new(EBADMSG, "invalid TCP option")),
                 };
                 if num_options >= option_list.len() {
@@ -482,6 +577,66 @@ impl TcpHeader {
         self.option_list[self.num_options] = option;
         self.num_options += 1;
     }
+
+    /// Returns the timestamp value and echo reply carried by the timestamp option, if present.
+    pub fn get_timestamp(&self) -> Option<(u32, u32)> {
+        self.iter_options().find_map(|option| match option {
+            TcpOptions2::Timestamp {
+                sender_timestamp,
+                echo_timestamp,
+            } => Some((*sender_timestamp, *echo_timestamp)),
+            _ => None,
+        })
+    }
+
+    /// Returns the digest carried by the MD5 signature option, if present.
+    pub fn get_md5_signature(&self) -> Option<[u8; 16]> {
+        self.iter_options().find_map(|option| match option {
+            TcpOptions2::Md5Signature(digest) => Some(*digest),
+            _ => None,
+        })
+    }
+
+    /// Returns the token carried by the connection token option, if present.
+    pub fn get_connection_token(&self) -> Option<u32> {
+        self.iter_options().find_map(|option| match option {
+            TcpOptions2::ConnectionToken(token) => Some(*token),
+            _ => None,
+        })
+    }
+}
+
+/// Computes the space taken in the header of every segment of a connection by the options that it negotiated or that
+/// sign it, including the padding that aligns them (in bytes).
+pub fn compute_persistent_options_size(timestamps: bool, connection_token: bool, md5_signature: bool) -> usize {
+    let mut header: TcpHeader = TcpHeader::new(0, 0);
+    if timestamps {
+        header.push_option(TcpOptions2::Timestamp {
+            sender_timestamp: 0,
+            echo_timestamp: 0,
+        });
+    }
+    if connection_token {
+        header.push_option(TcpOptions2::ConnectionToken(0));
+    }
+    if md5_signature {
+        header.push_option(TcpOptions2::Md5Signature([0; 16]));
+    }
+    header.compute_size() - MIN_TCP_HEADER_SIZE
+}
+
+/// Computes the size of the payload of the segments of a connection (in bytes). Segments are sized at the smaller of
+/// what our peer advertised and what we are configured to use, leaving room for the options that every segment carries.
+/// Our peer is not trusted to advertise less than the minimum MSS, so that these options always fit.
+pub fn compute_mss(
+    remote_mss: usize,
+    effective_mss: usize,
+    timestamps: bool,
+    connection_token: bool,
+    md5_signature: bool,
+) -> usize {
+    remote_mss.max(MIN_MSS).min(effective_mss)
+        - compute_persistent_options_size(timestamps, connection_token, md5_signature)
 }
 
 fn tcp_checksum(ipv4_header: &Ipv4Header, header: &[u8], data: &[u8]) -> u16 {
@@ -7,16 +7,40 @@
 
 use crate::{
     inetstack::{
-        protocols::tcp::{
-            tests::{
-                check_packet_data,
-                check_packet_pure_ack,
-                setup::{
-                    advance_clock,
-                    connection_setup,
+        protocols::{
+            ethernet2::{
+                EtherType2,
+                Ethernet2Header,
+                ETHERNET2_HEADER_SIZE,
+            },
+            ipv4::{
+                Ipv4Header,
+                IPV4_ECN_CE,
+                IPV4_ECN_ECT0,
+            },
+            tcp::{
+                congestion_control::{
+                    self,
+                    CongestionControl,
+                },
+                segment::{
+                    self,
+                    TcpHeader,
+                    TcpSegment,
+                },
+                tests::{
+                    check_packet_data,
+                    check_packet_pure_ack,
+                    setup::{
+                        advance_clock,
+                        connection_setup,
+                    },
                 },
+                ConnEvent,
+                ConnEventKind,
+                ConnMetrics,
+                SeqNumber,
             },
-            SeqNumber,
         },
         test_helpers::{
             self,
@@ -25,18 +49,34 @@ use crate::{
     },
     runtime::{
         memory::DemiBuffer,
-        network::consts::RECEIVE_BATCH_SIZE,
+        network::{
+            config::{
+                CongestionControlType,
+                TcpConfig,
+            },
+            consts::{
+                DEFAULT_MSS,
+                RECEIVE_BATCH_SIZE,
+            },
+            tap::Direction,
+        },
         OperationResult,
         QDesc,
         QToken,
+        SharedDemiRuntime,
     },
 };
 use ::anyhow::Result;
 use ::rand;
 use ::std::{
+    cell::RefCell,
     collections::VecDeque,
     net::SocketAddrV4,
-    time::Instant,
+    rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 //======================================================================================================================
@@ -53,6 +93,14 @@ fn cook_buffer(size: usize, stamp: Option<u8>) -> DemiBuffer {
     buf
 }
 
+/// Parses the IPv4 and TCP headers of a frame.
+fn parse_ipv4_tcp(bytes: DemiBuffer) -> Result<(Ipv4Header, TcpHeader)> {
+    let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(bytes)?;
+    let (ipv4_header, ipv4_payload): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+    let (tcp_header, _): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+    Ok((ipv4_header, tcp_header))
+}
+
 /// This function pushes a DemiBuffer to the test engine and returns the emitted packets.
 fn send_data<const N: usize>(
     now: &mut Instant,
@@ -530,3 +578,1508 @@ fn test_connect_disconnect() -> Result<()> {
 
     Ok(())
 }
+
+/// Tests that the connection observer is notified when a connection is accepted and when it is closed.
+#[test]
+fn test_connection_observer() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // Register observer on the server.
+    let events: Rc<RefCell<Vec<ConnEvent>>> = Rc::new(RefCell::new(Vec::new()));
+    let events_: Rc<RefCell<Vec<ConnEvent>>> = events.clone();
+    server.tcp_set_connection_observer(Box::new(move |event: ConnEvent| events_.borrow_mut().push(event)));
+
+    let ((server_qd, addr), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(events.borrow().len(), 1);
+    crate::ensure_eq!(events.borrow()[0].kind, ConnEventKind::Established);
+    crate::ensure_eq!(events.borrow()[0].local, listen_addr);
+    crate::ensure_eq!(events.borrow()[0].remote, addr);
+
+    connection_hangup(&mut now, &mut server, &mut client, server_qd, client_qd)?;
+    crate::ensure_eq!(events.borrow().len(), 2);
+    crate::ensure_eq!(events.borrow()[1].kind, ConnEventKind::Closed);
+    crate::ensure_eq!(events.borrow()[1].remote, addr);
+
+    Ok(())
+}
+
+/// Tests that send pacing spaces out the segments emitted by the sender.
+#[test]
+fn test_send_pacing() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Pace the client at 1 MB/s, so that 1000-byte segments are released 1 ms apart.
+    let bufsize: usize = 1000;
+    let gap: Duration = Duration::from_millis(1);
+    let tcp_config: TcpConfig = TcpConfig::default().set_enable_pacing(true).set_pacing_rate(1_000_000);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((_, addr), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Push a burst of data. Only the first segment should go out right away.
+    for _ in 0..3 {
+        client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    }
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 1);
+
+    // The remaining segments should be released one per gap.
+    for _ in 0..2 {
+        now += gap / 2;
+        client.advance_clock(now);
+        client.get_test_rig().poll_scheduler();
+        crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+        now += gap / 2;
+        client.advance_clock(now);
+        client.get_test_rig().poll_scheduler();
+        crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 1);
+    }
+
+    Ok(())
+}
+
+/// Tests that a congestion mark on an incoming segment is echoed back to the sender, and that the sender reacts.
+#[test]
+fn test_ecn_echo() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 1000;
+    let tcp_config: TcpConfig = TcpConfig::default()
+        .set_enable_ecn(true)
+        .set_congestion_control(CongestionControlType::Cubic);
+    let ack_delay_timeout: Duration = tcp_config.get_ack_delay_timeout();
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2_with_tcp_config(now, tcp_config.clone());
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((_, addr), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Data segments should be marked as ECN-capable.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let mut frame: DemiBuffer = frames.pop_front().unwrap();
+    let (mut ipv4_header, _): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frame.clone())?;
+    crate::ensure_eq!(ipv4_header.get_ecn(), IPV4_ECN_ECT0);
+    let cwnd: u32 = client.tcp_connection_metrics(client_qd)?.cwnd;
+
+    // Mark congestion along the path and deliver the segment to the server.
+    ipv4_header.set_ecn(IPV4_ECN_CE);
+    let payload_len: usize = frame.len() - ETHERNET2_HEADER_SIZE - ipv4_header.compute_size();
+    let ipv4_header_end: usize = ETHERNET2_HEADER_SIZE + ipv4_header.compute_size();
+    ipv4_header.serialize(&mut frame[ETHERNET2_HEADER_SIZE..ipv4_header_end], payload_len);
+    server.receive(frame)?;
+    server.get_test_rig().poll_scheduler();
+
+    // The server should echo the congestion mark on its ACK.
+    now += ack_delay_timeout;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let frame: DemiBuffer = frames.pop_front().unwrap();
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frame.clone())?;
+    crate::ensure_eq!(tcp_header.ece, true);
+
+    // The client should shrink its congestion window, and signal so on its next data segment.
+    client.receive(frame)?;
+    let metrics: ConnMetrics = client.tcp_connection_metrics(client_qd)?;
+    crate::ensure_eq!(metrics.cwnd < cwnd, true);
+    crate::ensure_eq!(metrics.ssthresh < u32::MAX, true);
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames.pop_front().unwrap())?;
+    crate::ensure_eq!(tcp_header.cwr, true);
+
+    Ok(())
+}
+
+/// Tests that connection metrics reflect the data in flight and the round-trip time observed by the sender.
+#[test]
+fn test_connection_metrics() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 1000;
+    let delay: Duration = Duration::from_millis(10);
+    let ack_delay_timeout: Duration = TcpConfig::default().get_ack_delay_timeout();
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((_, addr), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Send some data. It should show up as in flight.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let metrics: ConnMetrics = client.tcp_connection_metrics(client_qd)?;
+    crate::ensure_eq!(metrics.bytes_in_flight, bufsize as u32);
+    crate::ensure_neq!(metrics.cwnd, 0);
+    crate::ensure_neq!(metrics.ssthresh, 0);
+
+    // Deliver the data to the server after some delay and let it acknowledge it.
+    now += delay;
+    server.advance_clock(now);
+    server.receive(frames.pop_front().unwrap())?;
+    server.get_test_rig().poll_scheduler();
+    now += ack_delay_timeout;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+
+    // Deliver the ACK to the client. Nothing should be in flight, and the RTT estimates should reflect the sample.
+    client.advance_clock(now);
+    client.receive(frames.pop_front().unwrap())?;
+    let rtt: Duration = delay + ack_delay_timeout;
+    let metrics: ConnMetrics = client.tcp_connection_metrics(client_qd)?;
+    crate::ensure_eq!(metrics.bytes_in_flight, 0);
+    crate::ensure_eq!((metrics.srtt.as_secs_f64() - rtt.as_secs_f64()).abs() < 1e-6, true);
+    crate::ensure_eq!(
+        (metrics.rttvar.as_secs_f64() - rtt.as_secs_f64() / 2.0).abs() < 1e-6,
+        true
+    );
+
+    Ok(())
+}
+
+/// Tests that a configured initial congestion window is honored and that the first flight can fill it.
+#[test]
+fn test_initial_congestion_window() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mss: usize = DEFAULT_MSS;
+    let tcp_config: TcpConfig = TcpConfig::default()
+        .set_congestion_control(CongestionControlType::Cubic)
+        .set_initial_cwnd(10 * mss as u32)
+        .set_initial_ssthresh(20 * mss as u32);
+
+    // The congestion window should start at ten segments.
+    let cc: Box<dyn CongestionControl> = congestion_control::Cubic::new(
+        mss,
+        SeqNumber::from(0),
+        congestion_control::initial_window_options(&tcp_config, mss),
+    );
+    crate::ensure_eq!(cc.get_cwnd().get(), 10 * mss as u32);
+    crate::ensure_eq!(cc.get_ssthresh(), 20 * mss as u32);
+
+    // An initial window smaller than one segment should be raised to the MSS.
+    let small_config: TcpConfig = TcpConfig::default().set_initial_cwnd(1);
+    let cc: Box<dyn CongestionControl> = congestion_control::Cubic::new(
+        mss,
+        SeqNumber::from(0),
+        congestion_control::initial_window_options(&small_config, mss),
+    );
+    crate::ensure_eq!(cc.get_cwnd().get(), mss as u32);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((_, addr), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // The live connection should use the configured window.
+    let metrics: ConnMetrics = client.tcp_connection_metrics(client_qd)?;
+    crate::ensure_eq!(metrics.cwnd, 10 * mss as u32);
+    crate::ensure_eq!(metrics.ssthresh, 20 * mss as u32);
+
+    // The first flight should carry ten full-sized segments, and no more. Segments leave room for the options that
+    // each of them carries.
+    let segment_size: usize = client.tcp_mss(client_qd)?;
+    for _ in 0..11 {
+        client.tcp_push(client_qd, cook_buffer(segment_size, None))?;
+    }
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 10);
+
+    Ok(())
+}
+
+/// Tests that a connection with no traffic is closed once the idle timeout elapses.
+#[test]
+fn test_idle_timeout() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    // Connection setup advances the clock by a second per step, so the timeout must be longer than that.
+    let idle_timeout: Duration = Duration::from_secs(10);
+    let tcp_config: TcpConfig = TcpConfig::default().set_idle_timeout(idle_timeout);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((_, addr), _): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // Nothing should happen before the idle timeout elapses.
+    now += idle_timeout / 2;
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // Once it elapses, the client should initiate a close.
+    now += idle_timeout;
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames.pop_front().unwrap())?;
+    crate::ensure_eq!(tcp_header.fin, true);
+
+    Ok(())
+}
+
+/// Tests that segmentation uses the effective MSS, independently of the MSS advertised in the handshake.
+#[test]
+fn test_effective_mss() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let effective_mss: usize = 1000;
+    let tcp_config: TcpConfig = TcpConfig::default().set_effective_mss(effective_mss);
+    crate::ensure_eq!(tcp_config.get_advertised_mss(), DEFAULT_MSS);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((server_qd, addr), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // The client should have advertised the default MSS, but segment at the effective one. Both sides leave room for
+    // the connection token that every segment carries if migration is enabled.
+    let options_size: usize = segment::compute_persistent_options_size(false, cfg!(feature = "tcp-migration"), false);
+    crate::ensure_eq!(server.tcp_mss(server_qd)?, DEFAULT_MSS - options_size);
+    crate::ensure_eq!(client.tcp_mss(client_qd)?, effective_mss - options_size);
+
+    client.tcp_push(client_qd, cook_buffer(2 * (effective_mss - options_size), None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 2);
+    for frame in frames {
+        let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+        let (_, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        crate::ensure_eq!(data.len(), effective_mss - options_size);
+    }
+
+    Ok(())
+}
+
+/// Tests that urgent data is flagged on the wire and delivered separately from the in-band stream.
+#[test]
+fn test_urgent_data() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send urgent data followed by in-band data.
+    let urgent_len: usize = 8;
+    let inband_len: usize = 32;
+    client.tcp_push_urgent(client_qd, cook_buffer(urgent_len, Some(0xff)))?;
+    client.tcp_push(client_qd, cook_buffer(inband_len, Some(0x11)))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 2);
+
+    // Only the first segment carries urgent data.
+    let (_, urgent_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    crate::ensure_eq!(urgent_header.urg, true);
+    crate::ensure_eq!(urgent_header.urgent_pointer as usize, urgent_len);
+    let (_, inband_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[1].clone())?;
+    crate::ensure_eq!(inband_header.urg, false);
+
+    // Deliver both segments to the server.
+    let urgent_qt: QToken = server.tcp_pop_urgent(server_qd)?;
+    let inband_qt: QToken = server.tcp_pop(server_qd)?;
+    for frame in frames {
+        server.receive(frame)?;
+    }
+    server.get_test_rig().poll_scheduler();
+
+    // Urgent data must not show up in the in-band stream.
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(urgent_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => {
+            crate::ensure_eq!(buf.len(), urgent_len);
+            crate::ensure_eq!(buf[..].iter().all(|b| *b == 0xff), true);
+        },
+        Some((_, result)) => anyhow::bail!("urgent pop did not complete successfully: {:?}", result),
+        None => anyhow::bail!("urgent pop should have completed"),
+    }
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(inband_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => {
+            crate::ensure_eq!(buf.len(), inband_len);
+            crate::ensure_eq!(buf[..].iter().all(|b| *b == 0x11), true);
+        },
+        Some((_, result)) => anyhow::bail!("pop did not complete successfully: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+
+    Ok(())
+}
+
+/// Tests that the send queue length accounts for data that was not acknowledged by a stalled peer.
+#[test]
+fn test_send_queue_len() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(client.tcp_send_queue_len(client_qd)?, 0);
+
+    // Push data without ever delivering it to the server, so that nothing gets acknowledged.
+    let bufsize: usize = 64;
+    for i in 1..=4 {
+        client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+        client.get_test_rig().poll_scheduler();
+        client.get_test_rig().pop_all_frames();
+        crate::ensure_eq!(client.tcp_send_queue_len(client_qd)?, i * bufsize);
+    }
+
+    Ok(())
+}
+
+/// Tests that data read in place is handed over without being copied out of the received frame.
+#[test]
+fn test_pop_with() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Nothing to read yet.
+    match server.tcp_pop_with(server_qd, |_| {}) {
+        Err(e) if e.errno == libc::EAGAIN => {},
+        result => anyhow::bail!("pop_with() should fail with EAGAIN: {:?}", result.map(|_| ())),
+    }
+
+    for round in 0..4 {
+        let bufsize: usize = 32;
+        client.tcp_push(client_qd, cook_buffer(bufsize, Some(round)))?;
+        client.get_test_rig().poll_scheduler();
+        let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+        crate::ensure_eq!(frames.len(), 1);
+        let frame: DemiBuffer = frames[0].clone();
+        server.receive(frames[0].clone())?;
+
+        // The closure must see the payload in place, within the frame that was received.
+        let frame_start: usize = frame.as_ptr() as usize;
+        let frame_end: usize = frame_start + frame.len();
+        let mut seen: Option<(usize, bool)> = None;
+        let nbytes: usize = server.tcp_pop_with(server_qd, |bytes| {
+            seen = Some((bytes.as_ptr() as usize, bytes.iter().all(|b| *b == round)));
+        })?;
+        crate::ensure_eq!(nbytes, bufsize);
+        let (ptr, correct): (usize, bool) = seen.expect("closure should have been called");
+        crate::ensure_eq!(correct, true);
+        crate::ensure_eq!(ptr >= frame_start && ptr + bufsize <= frame_end, true);
+    }
+
+    Ok(())
+}
+
+/// Tests that the retransmission timeout is kept within the configured bounds.
+#[test]
+fn test_rto_bounds() -> Result<()> {
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 1000;
+    let rto_min: Duration = Duration::from_millis(200);
+    let rto_max: Duration = Duration::from_secs(2);
+    let ack_delay_timeout: Duration = TcpConfig::default().get_ack_delay_timeout();
+
+    // A fast round trip computes an RTO below the lower bound, and a slow one an RTO above the upper bound. The slow
+    // round trip is still shorter than the initial RTO, so that nothing gets retransmitted.
+    for (delay, expected_rto) in [
+        (Duration::from_millis(10), rto_min),
+        (Duration::from_millis(800), rto_max),
+    ] {
+        let mut now = Instant::now();
+        let tcp_config: TcpConfig = TcpConfig::default().set_rto_min(rto_min).set_rto_max(rto_max);
+
+        // Setup peers.
+        let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+        let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+        let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+            connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+        client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+        client.get_test_rig().poll_scheduler();
+        let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+        crate::ensure_eq!(frames.len(), 1);
+
+        // Deliver the data to the server after some delay and let it acknowledge it.
+        now += delay;
+        server.advance_clock(now);
+        server.receive(frames.pop_front().unwrap())?;
+        server.get_test_rig().poll_scheduler();
+        now += ack_delay_timeout;
+        server.advance_clock(now);
+        server.get_test_rig().poll_scheduler();
+        let mut frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+        crate::ensure_eq!(frames.len(), 1);
+
+        // Deliver the ACK to the client.
+        client.advance_clock(now);
+        client.receive(frames.pop_front().unwrap())?;
+        crate::ensure_eq!(client.tcp_rto(client_qd)?, expected_rto);
+    }
+
+    Ok(())
+}
+
+/// Tests that incoming data is acknowledged right away once quick ACK mode is on.
+#[test]
+fn test_quickack() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let ack_delay_timeout: Duration = TcpConfig::default().get_ack_delay_timeout();
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // By default, the ACK is held back until the delayed ACK timer fires.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    server.receive(frames.pop_front().unwrap())?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.get_test_rig().pop_all_frames().len(), 0);
+    now += ack_delay_timeout;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    client.receive(frames.pop_front().unwrap())?;
+
+    // With quick ACK mode on, the ACK goes out as soon as the data arrives.
+    server.tcp_set_quickack(server_qd, true)?;
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    server.receive(frames.pop_front().unwrap())?;
+    server.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    crate::ensure_eq!(tcp_header.ack, true);
+
+    Ok(())
+}
+
+/// Tests that overriding the congestion window limits the next flight of data.
+#[cfg(feature = "experimental")]
+#[test]
+fn test_set_cwnd() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((_, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Let only two buffers in flight.
+    client.tcp_set_cwnd(client_qd, 2 * bufsize as u32)?;
+    crate::ensure_eq!(client.tcp_get_cwnd(client_qd)?, 2 * bufsize as u32);
+
+    // Push more data than fits in the congestion window.
+    for _ in 0..4 {
+        client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    }
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 2);
+    let (_, first_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    let (_, second_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[1].clone())?;
+    crate::ensure_eq!(
+        second_header.seq_num,
+        first_header.seq_num + SeqNumber::from(bufsize as u32)
+    );
+
+    Ok(())
+}
+
+/// Tests that flushing the ACK acknowledges received data right away, instead of when the delayed ACK timer fires.
+#[test]
+fn test_flush_ack() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let ack_delay_timeout: Duration = TcpConfig::default().get_ack_delay_timeout();
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Receive data, which does not get acknowledged right away.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let data: DemiBuffer = frames.pop_front().unwrap();
+    let (_, data_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(data.clone())?;
+    server.receive(data)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.get_test_rig().pop_all_frames().len(), 0);
+
+    // Flushing the ACK sends it out right away.
+    server.tcp_flush_ack(server_qd)?;
+    let frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    crate::ensure_eq!(tcp_header.ack, true);
+    crate::ensure_eq!(
+        tcp_header.ack_num,
+        data_header.seq_num + SeqNumber::from(bufsize as u32)
+    );
+    crate::ensure_eq!(tcp_header.window_size > 0, true);
+
+    // The delayed ACK timer was stopped, so no other ACK goes out.
+    now += ack_delay_timeout;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.get_test_rig().pop_all_frames().len(), 0);
+
+    Ok(())
+}
+
+/// Tests that duplicate ACKs are counted in the connection metrics and that three of them trigger a fast retransmit.
+#[test]
+fn test_duplicate_acks() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let mss: usize = DEFAULT_MSS;
+    let tcp_config: TcpConfig = TcpConfig::default()
+        .set_congestion_control(CongestionControlType::Cubic)
+        .set_initial_cwnd(10 * mss as u32);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((_, addr), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    crate::ensure_eq!(addr.ip(), &test_helpers::ALICE_IPV4);
+
+    // Send four full segments and lose the first one. Segments leave room for the options that each of them carries.
+    let segment_size: usize = client.tcp_mss(client_qd)?;
+    for _ in 0..4 {
+        client.tcp_push(client_qd, cook_buffer(segment_size, None))?;
+    }
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 4);
+    let (_, lost_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames.pop_front().unwrap())?;
+
+    // Each out-of-order segment should be acknowledged right away with a duplicate ACK.
+    for frame in frames {
+        server.receive(frame)?;
+    }
+    server.get_test_rig().poll_scheduler();
+    let acks: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(acks.len(), 3);
+    for (i, ack) in acks.into_iter().enumerate() {
+        client.receive(ack)?;
+        client.get_test_rig().poll_scheduler();
+        let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+
+        // Only the third duplicate ACK should trigger a fast retransmit.
+        if i < 2 {
+            crate::ensure_eq!(frames.len(), 0);
+            crate::ensure_eq!(client.tcp_connection_metrics(client_qd)?.fast_retransmits, 0);
+        } else {
+            crate::ensure_eq!(frames.len(), 1);
+            let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+            crate::ensure_eq!(tcp_header.seq_num, lost_header.seq_num);
+        }
+    }
+    let metrics: ConnMetrics = client.tcp_connection_metrics(client_qd)?;
+    crate::ensure_eq!(metrics.duplicate_acks, 3);
+    crate::ensure_eq!(metrics.fast_retransmits, 1);
+    crate::ensure_eq!(metrics.bytes_in_flight, 4 * segment_size as u32);
+
+    Ok(())
+}
+
+/// Tests that small pushes to a corked connection are held back and coalesced into a single segment on uncork.
+#[test]
+fn test_cork() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 16;
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Nothing goes out while the connection is corked.
+    client.tcp_cork(client_qd, true)?;
+    for stamp in 1..=3 {
+        client.tcp_push(client_qd, cook_buffer(bufsize, Some(stamp)))?;
+    }
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // Uncorking sends all pushes in a single segment.
+    client.tcp_cork(client_qd, false)?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(frames[0].clone())?;
+    let (ipv4_header, ipv4_payload): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+    let (tcp_header, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+    crate::ensure_eq!(tcp_header.psh, true);
+    crate::ensure_eq!(data.len(), 3 * bufsize);
+    for (i, byte) in data.iter().enumerate() {
+        crate::ensure_eq!(*byte, (i / bufsize + 1) as u8);
+    }
+
+    Ok(())
+}
+
+/// Tests that back-to-back small pushes are coalesced into a single segment, which goes out once the coalescing timeout
+/// expires, and that a push that fills a segment flushes the pending ones right away.
+#[test]
+fn test_coalesce_small_pushes() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 16;
+    let coalesce_timeout: Duration = Duration::from_millis(10);
+    let tcp_config: TcpConfig = TcpConfig::default().set_coalesce_timeout(coalesce_timeout);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Small pushes are held back until the timeout expires.
+    for stamp in 1..=3 {
+        client.tcp_push(client_qd, cook_buffer(bufsize, Some(stamp)))?;
+    }
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+    now += coalesce_timeout / 2;
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // Then they all go out in a single segment.
+    now += coalesce_timeout / 2;
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(frames[0].clone())?;
+    let (ipv4_header, ipv4_payload): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+    let (_, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+    crate::ensure_eq!(data.len(), 3 * bufsize);
+    for (i, byte) in data.iter().enumerate() {
+        crate::ensure_eq!(*byte, (i / bufsize + 1) as u8);
+    }
+
+    // A push that does not fit in the same segment tops up the pending one, which goes out without waiting for the
+    // timeout, and the rest of it follows in a second segment.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.tcp_push(client_qd, cook_buffer(DEFAULT_MSS, None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 2);
+    let mut len: usize = 0;
+    for frame in frames {
+        let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(frame)?;
+        let (ipv4_header, ipv4_payload): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+        let (_, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        len += data.len();
+    }
+    crate::ensure_eq!(len, bufsize + DEFAULT_MSS);
+
+    Ok(())
+}
+
+/// Tests that the packet tap is shown every frame that is transmitted.
+#[test]
+fn test_packet_tap() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 16;
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    let tapped: Rc<RefCell<Vec<(Direction, Vec<u8>)>>> = Rc::new(RefCell::new(Vec::new()));
+    let tapped_clone: Rc<RefCell<Vec<(Direction, Vec<u8>)>>> = tapped.clone();
+    client.set_packet_tap(Box::new(move |direction: Direction, frame: &[u8]| {
+        tapped_clone.borrow_mut().push((direction, frame.to_vec()))
+    }));
+
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+
+    // The tap saw exactly the frame that went out, and it carries an IPv4 packet.
+    let tapped: Vec<(Direction, Vec<u8>)> = tapped.take();
+    crate::ensure_eq!(tapped.len(), 1);
+    let (direction, frame): &(Direction, Vec<u8>) = &tapped[0];
+    crate::ensure_eq!(*direction, Direction::Outgoing);
+    crate::ensure_eq!(&frame[..], &frames[0][..]);
+    let (eth2_header, _): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(frames[0].clone())?;
+    crate::ensure_eq!(eth2_header.ether_type(), EtherType2::Ipv4);
+
+    Ok(())
+}
+
+/// Tests that the transmit filter may rewrite outgoing segments, and that checksums reflect the rewritten headers.
+#[test]
+fn test_transmit_filter() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 16;
+    let proxy_port: u16 = 8080;
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    client.tcp_set_transmit_filter(Box::new(move |segment: &mut TcpSegment| {
+        segment.tcp_hdr.src_port = proxy_port
+    }));
+
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+
+    // The segment went out from the rewritten port. Parsing it checks that its checksum is still valid.
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    crate::ensure_eq!(tcp_header.src_port, proxy_port);
+    crate::ensure_eq!(tcp_header.dst_port, listen_port);
+
+    Ok(())
+}
+
+/// Tests that a connection carries on when the client moves to a new port, as its segments carry the connection token
+/// that was handed out during setup.
+#[cfg(feature = "tcp-migration")]
+#[test]
+fn test_connection_migration() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 16;
+    let new_port: u16 = 8080;
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send data from the original port. Every segment carries the token.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    crate::ensure_eq!(tcp_header.get_connection_token().is_some(), true);
+    recv_data(&mut server, &mut client, server_qd, frames[0].clone())?;
+    server.get_test_rig().pop_all_frames();
+
+    // Move the client over to a new port. A retransmission of data that the server already got carries the token too,
+    // but it is stale, so it should not move the connection.
+    client.tcp_set_transmit_filter(Box::new(move |segment: &mut TcpSegment| {
+        segment.tcp_hdr.src_port = new_port
+    }));
+    now += client.tcp_rto(client_qd)?;
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    // The segment does not belong to any connection, so the server may refuse it.
+    let _ = server.receive(frames[0].clone());
+    server.get_test_rig().poll_scheduler();
+    server.get_test_rig().pop_all_frames();
+    server.tcp_flush_ack(server_qd)?;
+    let frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_neq!(frames.len(), 0);
+    for frame in frames {
+        let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frame)?;
+        crate::ensure_neq!(tcp_header.dst_port, new_port);
+    }
+
+    // New data from the new port keeps the byte stream going.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    recv_data(&mut server, &mut client, server_qd, frames[0].clone())?;
+
+    // The server now sends to the new port.
+    server.tcp_flush_ack(server_qd)?;
+    let frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_neq!(frames.len(), 0);
+    for frame in frames {
+        let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frame)?;
+        crate::ensure_eq!(tcp_header.dst_port, new_port);
+    }
+
+    Ok(())
+}
+
+/// Tests that a connection is cleaned up once it has spent the configured duration in TIME-WAIT.
+#[test]
+fn test_time_wait_duration() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let time_wait_duration: Duration = Duration::from_secs(10);
+    let tcp_config: TcpConfig = TcpConfig::default().set_time_wait_duration(time_wait_duration);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // The client closed first, so it is now in TIME-WAIT.
+    connection_hangup(&mut now, &mut server, &mut client, server_qd, client_qd)?;
+    match client.tcp_send_queue_len(client_qd) {
+        Err(e) if e.errno == libc::ENOTCONN => (),
+        result => anyhow::bail!("connection should be closing (result={:?})", result),
+    }
+
+    // Once TIME-WAIT is over, the connection goes away. The timer fires on the first poll, and the close completes on
+    // the second one.
+    now += time_wait_duration;
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    match client.tcp_send_queue_len(client_qd) {
+        Err(e) if e.errno == libc::EBADF => (),
+        result => anyhow::bail!("connection should be cleaned up (result={:?})", result),
+    }
+
+    Ok(())
+}
+
+/// Tests that window probes are sent at exponentially increasing intervals while the peer advertises a zero window,
+/// even if the update that reopens the window is lost.
+#[test]
+fn test_persist_timer() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let ack_delay_timeout: Duration = TcpConfig::default().get_ack_delay_timeout();
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send some data and have the server acknowledge it with a zero window.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    server.receive(frames.pop_front().unwrap())?;
+    now += ack_delay_timeout;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let mut frame: DemiBuffer = frames.pop_front().unwrap();
+    let (ipv4_header, mut tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frame.clone())?;
+    tcp_header.window_size = 0;
+    let tcp_header_start: usize = ETHERNET2_HEADER_SIZE + ipv4_header.compute_size();
+    let tcp_header_end: usize = tcp_header_start + tcp_header.compute_size();
+    tcp_header.serialize(&mut frame[tcp_header_start..tcp_header_end], &ipv4_header, &[], false);
+    client.advance_clock(now);
+    client.receive(frame)?;
+
+    // Nothing goes out while the window is closed.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // The update that reopens the window is lost, so the client sends a one-byte probe once the persist timer fires,
+    // and then again at twice the interval.
+    let rto: Duration = client.tcp_rto(client_qd)?;
+    let mut probe_seq_num: Option<SeqNumber> = None;
+    for interval in [rto, 2 * rto] {
+        now += interval - Duration::from_millis(1);
+        client.advance_clock(now);
+        client.get_test_rig().poll_scheduler();
+        crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+        now += Duration::from_millis(1);
+        client.advance_clock(now);
+        client.get_test_rig().poll_scheduler();
+        let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+        crate::ensure_eq!(frames.len(), 1);
+        let (_, eth2_payload): (Ethernet2Header, DemiBuffer) = Ethernet2Header::parse(frames[0].clone())?;
+        let (ipv4_header, ipv4_payload): (Ipv4Header, DemiBuffer) = Ipv4Header::parse(eth2_payload)?;
+        let (tcp_header, data): (TcpHeader, DemiBuffer) = TcpHeader::parse(&ipv4_header, ipv4_payload, false)?;
+        crate::ensure_eq!(data.len(), 1);
+        crate::ensure_eq!(*probe_seq_num.get_or_insert(tcp_header.seq_num), tcp_header.seq_num);
+    }
+
+    Ok(())
+}
+
+/// Tests that the control block dump reports the state of an established connection, and that sockets without one
+/// are rejected.
+#[test]
+fn test_dump_tcb() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 1000;
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Leave some data unacknowledged, so that it shows up in the dump along with the retransmission timer.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 1);
+
+    let dump: String = client.tcp_dump_tcb(client_qd)?;
+    for expected in [
+        "state=Established",
+        "send: una=",
+        "unacked: segments=1 bytes=1000",
+        "unsent: buffers=0 bytes=0",
+        "recv: nxt=",
+        "out_of_order: segments=0",
+        "timers: retransmit=in",
+        "congestion: cwnd=",
+    ] {
+        crate::ensure_eq!(dump.contains(expected), true);
+    }
+
+    // A socket that is not connected has no control block to dump.
+    let qd: QDesc = client.tcp_socket()?;
+    match client.tcp_dump_tcb(qd) {
+        Err(e) if e.errno == libc::ENOTCONN => (),
+        result => anyhow::bail!("dump_tcb() should fail with ENOTCONN: {:?}", result),
+    }
+
+    Ok(())
+}
+
+/// Tests that negotiated timestamps are echoed back, and that round-trip times are sampled from them even for
+/// retransmitted segments.
+#[test]
+fn test_timestamps() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 64;
+    let delay: Duration = Duration::from_millis(10);
+    let ack_delay_timeout: Duration = TcpConfig::default().get_ack_delay_timeout();
+    let tcp_config: TcpConfig = TcpConfig::default().set_enable_timestamps(true);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2_with_tcp_config(now, tcp_config.clone());
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Send some data and lose it. The data segment should carry a timestamp.
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    let first_timestamp: u32 = match tcp_header.get_timestamp() {
+        Some((value, _)) => value,
+        None => anyhow::bail!("data segment should carry a timestamp"),
+    };
+
+    // Let the client retransmit the data, and deliver it to the server after some delay.
+    now += client.tcp_rto(client_qd)?;
+    client.advance_clock(now);
+    client.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    let timestamp: u32 = match tcp_header.get_timestamp() {
+        Some((value, _)) => value,
+        None => anyhow::bail!("retransmitted segment should carry a timestamp"),
+    };
+    crate::ensure_eq!(timestamp > first_timestamp, true);
+    now += delay;
+    server.advance_clock(now);
+    server.receive(frames.pop_front().unwrap())?;
+
+    // The acknowledgement of the server should echo the timestamp of the retransmission.
+    now += ack_delay_timeout;
+    server.advance_clock(now);
+    server.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = server.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+    let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frames[0].clone())?;
+    crate::ensure_eq!(tcp_header.get_timestamp().map(|(_, echo)| echo), Some(timestamp));
+
+    // Deliver the ACK to the client. The RTT estimates should reflect the time since the retransmission, which would
+    // not be sampled without timestamps.
+    client.advance_clock(now);
+    client.receive(frames.pop_front().unwrap())?;
+    let rtt: Duration = delay + ack_delay_timeout;
+    let metrics: ConnMetrics = client.tcp_connection_metrics(client_qd)?;
+    crate::ensure_eq!(metrics.bytes_in_flight, 0);
+    crate::ensure_eq!((metrics.srtt.as_secs_f64() - rtt.as_secs_f64()).abs() < 1e-6, true);
+    crate::ensure_eq!(
+        (metrics.rttvar.as_secs_f64() - rtt.as_secs_f64() / 2.0).abs() < 1e-6,
+        true
+    );
+
+    Ok(())
+}
+
+/// Tests that the maximum segment size reported for a connection is the one that was negotiated, less the room taken
+/// by the options that every segment carries, and that pushing that much data fills exactly one segment.
+#[test]
+fn test_connection_mss() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let tcp_config: TcpConfig = TcpConfig::default().set_enable_timestamps(true);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2_with_tcp_config(now, tcp_config.clone());
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Both sides negotiated timestamps, so both leave room for the option, as well as for the connection token if
+    // migration is enabled.
+    let mss: usize =
+        DEFAULT_MSS - segment::compute_persistent_options_size(true, cfg!(feature = "tcp-migration"), false);
+    crate::ensure_eq!(server.tcp_mss(server_qd)?, mss);
+    crate::ensure_eq!(client.tcp_mss(client_qd)?, mss);
+
+    client.tcp_push(client_qd, cook_buffer(mss, None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+
+    Ok(())
+}
+
+/// Tests that pops wait for the receive low-water mark to be reached, unless the peer closes the connection first.
+#[test]
+fn test_rcvlowat() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let low_water: usize = 8;
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    server.tcp_set_rcvlowat(server_qd, low_water)?;
+
+    // The pop waits while less data than the low-water mark has arrived, and then gathers everything that arrived from
+    // several segments.
+    let qt: QToken = server.tcp_pop(server_qd)?;
+    for (len, stamp) in [(4, 1), (6, 2)] {
+        crate::ensure_eq!(
+            server.get_test_rig().get_runtime().from_task_id(qt)?.has_completed(),
+            false
+        );
+        client.tcp_push(client_qd, cook_buffer(len, Some(stamp)))?;
+        client.get_test_rig().poll_scheduler();
+        for frame in client.get_test_rig().pop_all_frames() {
+            server.receive(frame)?;
+        }
+        server.get_test_rig().poll_scheduler();
+    }
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => crate::ensure_eq!(&buf[..], &[1, 1, 1, 1, 2, 2, 2, 2, 2, 2]),
+        Some((_, result)) => anyhow::bail!("pop did not complete successfully: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+
+    // Data short of the low-water mark is handed out once the peer closes the connection.
+    let qt: QToken = server.tcp_pop(server_qd)?;
+    client.tcp_push(client_qd, cook_buffer(2, Some(3)))?;
+    client.get_test_rig().poll_scheduler();
+    for frame in client.get_test_rig().pop_all_frames() {
+        server.receive(frame)?;
+    }
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(
+        server.get_test_rig().get_runtime().from_task_id(qt)?.has_completed(),
+        false
+    );
+    client.tcp_async_close(client_qd)?;
+    client.get_test_rig().poll_scheduler();
+    for frame in client.get_test_rig().pop_all_frames() {
+        server.receive(frame)?;
+    }
+    server.get_test_rig().poll_scheduler();
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => crate::ensure_eq!(&buf[..], &[3, 3]),
+        Some((_, result)) => anyhow::bail!("pop did not complete successfully: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+
+    // Then comes the end of the stream.
+    let qt: QToken = server.tcp_pop(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Pop(_, buf))) => crate::ensure_eq!(buf.len(), 0),
+        Some((_, result)) => anyhow::bail!("pop did not complete successfully: {:?}", result),
+        None => anyhow::bail!("pop should have completed"),
+    }
+
+    Ok(())
+}
+
+/// Tests that the advertised receive window tracks the bandwidth-delay product of the path when receive window
+/// auto-tuning is enabled, as estimated from the round-trip time seen by the receiver, and never goes past the
+/// configured maximum.
+#[test]
+fn test_receive_window_autotune() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let window_scale: u8 = 4;
+    let initial_window_size: u32 = 0x1000 << window_scale;
+    let max_window_size: u32 = 1 << 20;
+    let tcp_config: TcpConfig = TcpConfig::new(None, None, None, Some(0x1000), Some(window_scale), None, None, None)
+        .set_enable_window_autotune(true)
+        .set_enable_timestamps(true)
+        .set_max_receive_window_size(max_window_size);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2_with_tcp_config(now, tcp_config.clone());
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, tcp_config);
+
+    let ((server_qd, _), client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    // Stream 48 KB per round trip over a path with a round-trip time of 100 ms, reading data as soon as it arrives, and
+    // keep track of the window that the receiver advertises. The receiver only sends ACKs, so it can only learn the
+    // round-trip time from the timestamps that the sender echoes.
+    let rtt: Duration = Duration::from_millis(100);
+    let bdp: u32 = 48 * 1024;
+    let mut window_sizes: Vec<u32> = Vec::new();
+    for _ in 0..20 {
+        client.tcp_push(client_qd, cook_buffer(bdp as usize, None))?;
+        client.get_test_rig().poll_scheduler();
+        for frame in client.get_test_rig().pop_all_frames() {
+            server.receive(frame)?;
+        }
+        while server.tcp_pop_with(server_qd, |_| {}).is_ok() {}
+        server.tcp_flush_ack(server_qd)?;
+        for frame in server.get_test_rig().pop_all_frames() {
+            let (_, tcp_header): (Ipv4Header, TcpHeader) = parse_ipv4_tcp(frame.clone())?;
+            window_sizes.push((tcp_header.window_size as u32) << window_scale);
+            client.receive(frame)?;
+        }
+        now += rtt;
+        server.advance_clock(now);
+        client.advance_clock(now);
+    }
+
+    // The window starts at its initial size and settles at twice the bandwidth-delay product, give or take the
+    // granularity of the window scale. Had the receiver used a round-trip time other than 100 ms, the window would
+    // settle somewhere else.
+    crate::ensure_eq!(window_sizes.first().copied(), Some(initial_window_size));
+    let last_window_size: u32 = *window_sizes.last().expect("receiver should have advertised a window");
+    crate::ensure_eq!(last_window_size.abs_diff(2 * bdp) <= 1 << window_scale, true);
+    crate::ensure_eq!(last_window_size <= max_window_size, true);
+
+    Ok(())
+}
+
+/// Tests that metrics render as valid Prometheus text, covering the scheduler, the buffer pool, listening sockets and
+/// established connections.
+#[test]
+fn test_metrics_text() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let ((server_qd, _), _): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+    let text: String = server.metrics_text();
+
+    // Every line is either a comment that describes a family or a sample of the family that was last described.
+    let mut family: Option<String> = None;
+    let mut names: Vec<String> = Vec::new();
+    for line in text.lines() {
+        if let Some(help) = line.strip_prefix("# HELP ") {
+            let name: &str = help.split(' ').next().unwrap_or_default();
+            crate::ensure_eq!(names.iter().any(|n| n == name), false);
+            names.push(name.to_string());
+            family = Some(name.to_string());
+        } else if let Some(typ) = line.strip_prefix("# TYPE ") {
+            let (name, typ): (&str, &str) = typ.split_once(' ').unwrap_or_default();
+            crate::ensure_eq!(Some(name.to_string()), family);
+            crate::ensure_eq!(typ == "counter" || typ == "gauge", true);
+        } else {
+            let (sample, value): (&str, &str) = line.rsplit_once(' ').unwrap_or_default();
+            let name: &str = sample.split('{').next().unwrap_or_default();
+            crate::ensure_eq!(Some(name.to_string()), family);
+            crate::ensure_eq!(value.parse::<f64>().is_ok(), true);
+            if let Some(labels) = sample.strip_prefix(name).filter(|labels| !labels.is_empty()) {
+                crate::ensure_eq!(labels.starts_with('{') && labels.ends_with('}'), true);
+            }
+        }
+    }
+
+    // The metrics cover the scheduler, the buffer pool, and the connection.
+    for name in [
+        "demikernel_scheduler_tasks",
+        "demikernel_buffer_pool_free_bytes",
+        "demikernel_tcp_srtt_seconds",
+        "demikernel_tcp_cwnd_bytes",
+    ] {
+        crate::ensure_eq!(names.iter().any(|n| n == name), true);
+    }
+    let qd: u32 = server_qd.into();
+    crate::ensure_eq!(
+        text.contains(&format!("demikernel_tcp_cwnd_bytes{{qd=\"{}\",", qd)),
+        true
+    );
+
+    Ok(())
+}
+
+/// Tests that, with the manual clock, polling does not move time forward and a segment is retransmitted exactly once
+/// the retransmission timeout has elapsed.
+#[test]
+fn test_manual_clock_retransmit() -> Result<()> {
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let bufsize: usize = 1000;
+
+    // Setup peers.
+    let mut now = Instant::now();
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    let (_, client_qd): ((QDesc, SocketAddrV4), QDesc) =
+        connection_setup(&mut now, &mut server, &mut client, listen_port, listen_addr)?;
+
+    let mut runtime: SharedDemiRuntime = client.get_test_rig().get_runtime();
+    runtime.set_manual_clock(true);
+
+    client.tcp_push(client_qd, cook_buffer(bufsize, None))?;
+    client.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = client.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 1);
+
+    // Polling for longer than the timer resolution must leave the clock alone.
+    let start: Instant = runtime.get_now();
+    for _ in 0..1024 {
+        runtime.poll_and_advance_clock();
+    }
+    crate::ensure_eq!(runtime.get_now(), start);
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // Nothing is retransmitted right before the timeout expires.
+    let rto: Duration = client.tcp_rto(client_qd)?;
+    runtime.advance_clock_by(rto - Duration::from_nanos(1));
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 0);
+
+    // The segment is retransmitted as soon as the timeout expires.
+    runtime.advance_clock_by(Duration::from_nanos(1));
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(client.get_test_rig().pop_all_frames().len(), 1);
+    crate::ensure_eq!(runtime.get_now(), start + rto);
+
+    Ok(())
+}
@@ -11,13 +11,21 @@ use crate::{
             ethernet2::{
                 EtherType2,
                 Ethernet2Header,
+                ETHERNET2_HEADER_SIZE,
+            },
+            ipv4::{
+                Ipv4Header,
+                IPV4_HEADER_MIN_SIZE,
             },
-            ipv4::Ipv4Header,
             tcp::{
                 segment::{
+                    self,
                     TcpHeader,
+                    TcpOptions2,
                     TcpSegment,
                 },
+                IsnGeneratorState,
+                ListenerStats,
                 SeqNumber,
             },
         },
@@ -30,7 +38,11 @@ use crate::{
         fail::Fail,
         memory::DemiBuffer,
         network::{
-            consts::RECEIVE_BATCH_SIZE,
+            config::TcpConfig,
+            consts::{
+                MIN_MSS,
+                RECEIVE_BATCH_SIZE,
+            },
             types::MacAddress,
             PacketBuf,
         },
@@ -40,7 +52,12 @@ use crate::{
     },
 };
 use ::anyhow::Result;
-use ::libc::EBADMSG;
+use ::libc::{
+    EADDRNOTAVAIL,
+    EAGAIN,
+    EBADMSG,
+    EMFILE,
+};
 use ::std::{
     net::{
         Ipv4Addr,
@@ -307,6 +324,121 @@ fn test_refuse_connection_missing_syn() -> Result<()> {
     }
 }
 
+/// Tests that the SYN+ACK only carries the window scale option if the SYN did.
+#[test]
+fn test_syn_ack_window_scale_option() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // Server: LISTEN state.
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_listen(server_qd, 4)?;
+
+    for peer_window_scale in [true, false] {
+        // Client: SYN_SENT state, with the window scale option removed if asked to.
+        let (_, _, bytes): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+        let (eth2_header, ipv4_header, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes)?;
+        let mut syn: TcpHeader = TcpHeader::new(tcp_header.src_port, tcp_header.dst_port);
+        syn.syn = true;
+        syn.seq_num = tcp_header.seq_num;
+        syn.window_size = tcp_header.window_size;
+        for option in tcp_header.iter_options() {
+            match option {
+                TcpOptions2::WindowScale(_) if !peer_window_scale => continue,
+                _ => syn.push_option(*option),
+            }
+        }
+        let buf: DemiBuffer = serialize_segment(TcpSegment {
+            ethernet2_hdr: eth2_header,
+            ipv4_hdr: ipv4_header,
+            tcp_hdr: syn,
+            data: None,
+            tx_checksum_offload: false,
+        })?;
+
+        // Server: SYN_RCVD state.
+        let (_, _, syn_ack): (Ethernet2Header, Ipv4Header, TcpHeader) =
+            extract_headers(connection_setup_listen_syn_rcvd(&mut server, buf)?)?;
+        crate::ensure_eq!(syn_ack.syn && syn_ack.ack, true);
+        let local_window_scale: bool = syn_ack
+            .iter_options()
+            .any(|option| matches!(option, TcpOptions2::WindowScale(_)));
+        crate::ensure_eq!(local_window_scale, peer_window_scale);
+    }
+
+    Ok(())
+}
+
+/// Tests that a connection does not segment below the minimum MSS, even if its peer advertises a smaller one.
+#[test]
+fn test_tiny_remote_mss() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // Server: LISTEN state.
+    let accept_qt: QToken = connection_setup_closed_listen(&mut server, listen_addr)?;
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+
+    // Client: SYN_SENT state, advertising an MSS that is smaller than the options that every segment carries.
+    let (_, _, bytes): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let (eth2_header, ipv4_header, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes)?;
+    let mut syn: TcpHeader = TcpHeader::new(tcp_header.src_port, tcp_header.dst_port);
+    syn.syn = true;
+    syn.seq_num = tcp_header.seq_num;
+    syn.window_size = tcp_header.window_size;
+    for option in tcp_header.iter_options() {
+        match option {
+            TcpOptions2::MaximumSegmentSize(_) => syn.push_option(TcpOptions2::MaximumSegmentSize(8)),
+            _ => syn.push_option(*option),
+        }
+    }
+    let bytes: DemiBuffer = serialize_segment(TcpSegment {
+        ethernet2_hdr: eth2_header,
+        ipv4_hdr: ipv4_header,
+        tcp_hdr: syn,
+        data: None,
+        tx_checksum_offload: false,
+    })?;
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+
+    // Complete the handshake.
+    let bytes: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, bytes)?;
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    let bytes: DemiBuffer = connection_setup_syn_sent_established(&mut client, bytes)?;
+    advance_clock(Some(&mut server), Some(&mut client), &mut now);
+    connection_setup_sync_rcvd_established(&mut server, bytes)?;
+    let server_qd: QDesc = match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Accept((server_qd, _)))) => server_qd,
+        _ => anyhow::bail!("accept should have completed"),
+    };
+
+    // The server segments at the minimum MSS instead.
+    let options_size: usize = segment::compute_persistent_options_size(false, cfg!(feature = "tcp-migration"), false);
+    crate::ensure_eq!(server.tcp_mss(server_qd)?, MIN_MSS - options_size);
+
+    Ok(())
+}
+
 /// Tests basic 3-way connection setup.
 #[test]
 fn test_good_connect() -> Result<()> {
@@ -326,6 +458,793 @@ fn test_good_connect() -> Result<()> {
     Ok(())
 }
 
+/// Tests that two peers connecting to each other at the same time both reach the established state.
+#[test]
+fn test_simultaneous_open() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, 5000);
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 6000);
+
+    // Setup peers.
+    let mut alice: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let mut bob: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+
+    // Both peers send a SYN to each other.
+    let alice_qd: QDesc = alice.tcp_socket()?;
+    alice.tcp_bind(alice_qd, alice_addr)?;
+    let alice_qt: QToken = alice.tcp_connect(alice_qd, bob_addr)?;
+    let bob_qd: QDesc = bob.tcp_socket()?;
+    bob.tcp_bind(bob_qd, bob_addr)?;
+    let bob_qt: QToken = bob.tcp_connect(bob_qd, alice_addr)?;
+    alice.get_test_rig().poll_scheduler();
+    bob.get_test_rig().poll_scheduler();
+    let alice_syn: DemiBuffer = alice.get_test_rig().pop_frame();
+    let bob_syn: DemiBuffer = bob.get_test_rig().pop_frame();
+
+    // Each peer answers the SYN of the other with a SYN+ACK.
+    alice.receive(bob_syn)?;
+    alice.get_test_rig().poll_scheduler();
+    bob.receive(alice_syn)?;
+    bob.get_test_rig().poll_scheduler();
+    let alice_syn_ack: DemiBuffer = alice.get_test_rig().pop_frame();
+    let bob_syn_ack: DemiBuffer = bob.get_test_rig().pop_frame();
+    for bytes in [&alice_syn_ack, &bob_syn_ack] {
+        let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(bytes.clone())?;
+        crate::ensure_eq!(tcp_header.syn && tcp_header.ack, true);
+    }
+
+    // Both connections complete once the SYN+ACKs are exchanged.
+    alice.receive(bob_syn_ack)?;
+    alice.get_test_rig().poll_scheduler();
+    bob.receive(alice_syn_ack)?;
+    bob.get_test_rig().poll_scheduler();
+    for (engine, qt) in [(&mut alice, alice_qt), (&mut bob, bob_qt)] {
+        match engine
+            .get_test_rig()
+            .get_runtime()
+            .remove_coroutine_with_qtoken(qt)
+            .get_result()
+        {
+            Some((_, OperationResult::Connect)) => {},
+            Some((_, result)) => anyhow::bail!("connect did not complete successfully: {:?}", result),
+            None => anyhow::bail!("connect should have completed"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that TCP checksums are only verified in software when receive checksum offload is disabled.
+#[test]
+fn test_rx_checksum_offload() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    for rx_checksum_offload in [false, true] {
+        let tcp_config: TcpConfig = TcpConfig::new(None, None, None, None, None, None, Some(rx_checksum_offload), None);
+
+        // Setup peers.
+        let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2_with_tcp_config(now, tcp_config);
+        let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+        connection_setup_closed_listen(&mut server, listen_addr)?;
+        let (_, _, mut bytes): (QDesc, QToken, DemiBuffer) =
+            connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+
+        // Corrupt the checksum of the SYN segment.
+        let checksum_offset: usize = ETHERNET2_HEADER_SIZE + IPV4_HEADER_MIN_SIZE as usize + 16;
+        bytes[checksum_offset] ^= 0xff;
+
+        match server.receive(bytes) {
+            Ok(()) if rx_checksum_offload => {},
+            Err(e) if !rx_checksum_offload && e.errno == EBADMSG => {},
+            result => anyhow::bail!(
+                "unexpected result with rx_checksum_offload={}: {:?}",
+                rx_checksum_offload,
+                result
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that control segments only carry a checksum computed in software when transmit checksum offload is disabled.
+#[test]
+fn test_tx_checksum_offload() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let closed_port: u16 = 80;
+    let closed_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, closed_port);
+    // Headers are not parsed, as that verifies their checksum.
+    const TCP_FLAG_RST: u8 = 0x04;
+    let tcp_offset: usize = ETHERNET2_HEADER_SIZE + IPV4_HEADER_MIN_SIZE as usize;
+    let checksum_offset: usize = tcp_offset + 16;
+
+    for checksum_offload in [false, true] {
+        // Receive checksum offload is set apart from transmit checksum offload, so that mixing them up shows. The server
+        // always offloads it, so that it takes segments that carry no checksum.
+        let client_config: TcpConfig = TcpConfig::new(
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(!checksum_offload),
+            Some(checksum_offload),
+        );
+        let server_config: TcpConfig =
+            TcpConfig::new(None, None, None, None, None, None, Some(true), Some(checksum_offload));
+
+        // Setup peers.
+        let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2_with_tcp_config(now, server_config);
+        let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_tcp_config(now, client_config);
+
+        // The client sends a SYN segment.
+        let (_, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, closed_addr)?;
+        let checksum: u16 = u16::from_be_bytes([syn[checksum_offset], syn[checksum_offset + 1]]);
+        crate::ensure_eq!(checksum != 0, !checksum_offload);
+
+        // The server answers it with a RST segment, as no socket is bound to the port.
+        server.receive(syn)?;
+        server.get_test_rig().poll_scheduler();
+        let rst: DemiBuffer = server.get_test_rig().pop_frame();
+        crate::ensure_eq!(rst[tcp_offset + 13] & TCP_FLAG_RST, TCP_FLAG_RST);
+        let checksum: u16 = u16::from_be_bytes([rst[checksum_offset], rst[checksum_offset + 1]]);
+        crate::ensure_eq!(checksum != 0, !checksum_offload);
+    }
+
+    Ok(())
+}
+
+/// Tests that sockets cannot be created beyond the configured maximum number of queues.
+#[test]
+fn test_max_queues() -> Result<()> {
+    let now = Instant::now();
+    let max_queues: usize = 4;
+
+    // Setup peer.
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    client.get_test_rig().get_runtime().set_max_queues(max_queues);
+
+    for _ in 0..max_queues {
+        client.tcp_socket()?;
+    }
+    match client.tcp_socket() {
+        Err(error) if error.errno == EMFILE => Ok(()),
+        result => anyhow::bail!("socket should have failed with EMFILE, instead returned: {:?}", result),
+    }
+}
+
+/// Tests that outbound connections take their local port from the configured ephemeral port range, until it runs out.
+#[test]
+fn test_ephemeral_port_range() -> Result<()> {
+    let now = Instant::now();
+    let first_port: u16 = 50000;
+    let last_port: u16 = 50001;
+    let remote: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+
+    // Setup peer.
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    client
+        .get_test_rig()
+        .get_runtime()
+        .set_ephemeral_port_range(first_port, last_port)?;
+
+    // Every port of the range may be used once.
+    for _ in first_port..=last_port {
+        let qd: QDesc = client.tcp_socket()?;
+        client.tcp_connect(qd, remote)?;
+    }
+
+    // The range is now exhausted.
+    let qd: QDesc = client.tcp_socket()?;
+    match client.tcp_connect(qd, remote) {
+        Err(error) if error.errno == EADDRNOTAVAIL => Ok(()),
+        result => anyhow::bail!(
+            "connect should have failed with EADDRNOTAVAIL, instead returned: {:?}",
+            result
+        ),
+    }
+}
+
+/// Tests that growing the backlog of a listening socket lets more connections be pending acceptance.
+#[test]
+fn test_set_backlog() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // Listen with room for a single pending connection.
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_listen(server_qd, 1)?;
+
+    // The first connection attempt fills the backlog, so the second one is refused.
+    for expect_syn_ack in [true, false] {
+        let (_, _, bytes): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+        server.receive(bytes)?;
+        server.get_test_rig().poll_scheduler();
+        let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+            extract_headers(server.get_test_rig().pop_frame())?;
+        crate::ensure_eq!(tcp_header.syn && tcp_header.ack, expect_syn_ack);
+        crate::ensure_eq!(tcp_header.rst, !expect_syn_ack);
+    }
+
+    // After growing the backlog, another connection attempt goes through.
+    server.tcp_set_backlog(server_qd, 2)?;
+    let (_, _, bytes): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    server.receive(bytes)?;
+    server.get_test_rig().poll_scheduler();
+    let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(server.get_test_rig().pop_frame())?;
+    crate::ensure_eq!(tcp_header.syn && tcp_header.ack, true);
+
+    Ok(())
+}
+
+/// Tests that a listening socket counts the connection requests that it refuses because its backlog is full, as well as
+/// the connections that it accepts.
+#[test]
+fn test_listener_stats() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // Only listening sockets have listener stats.
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    crate::ensure_eq!(server.tcp_listener_stats(server_qd).is_err(), true);
+
+    // Listen with room for a single pending connection.
+    server.tcp_listen(server_qd, 1)?;
+    let stats: ListenerStats = server.tcp_listener_stats(server_qd)?;
+    crate::ensure_eq!(
+        (stats.accepted, stats.refused, stats.inflight, stats.ready),
+        (0, 0, 0, 0)
+    );
+
+    // Establish a connection, which fills the backlog until it is accepted.
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    crate::ensure_eq!(server.tcp_listener_stats(server_qd)?.inflight, 1);
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    server.receive(ack)?;
+    server.get_test_rig().poll_scheduler();
+    let stats: ListenerStats = server.tcp_listener_stats(server_qd)?;
+    crate::ensure_eq!((stats.inflight, stats.ready), (0, 1));
+
+    // Further connection requests are refused, and counted as such.
+    for refused in 1..=2 {
+        let (_, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+        server.receive(syn)?;
+        server.get_test_rig().poll_scheduler();
+        let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+            extract_headers(server.get_test_rig().pop_frame())?;
+        crate::ensure_eq!(tcp_header.rst, true);
+        crate::ensure_eq!(server.tcp_listener_stats(server_qd)?.refused, refused);
+    }
+
+    // Accepting the connection makes room in the backlog.
+    let accept_qt: QToken = server.tcp_accept(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(accept_completed(&server, accept_qt)?, true);
+    let stats: ListenerStats = server.tcp_listener_stats(server_qd)?;
+    crate::ensure_eq!(
+        (stats.accepted, stats.refused, stats.inflight, stats.ready),
+        (1, 2, 0, 0)
+    );
+
+    Ok(())
+}
+
+/// Tests that segments for a port with no socket are answered with a RST segment, unless they are RST segments themselves.
+#[test]
+fn test_rst_for_closed_port() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let closed_port: u16 = 80;
+    let closed_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, closed_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // A connection request is reset, and the reset acknowledges the SYN.
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, closed_addr)?;
+    let (eth2_header, ipv4_header, syn_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(syn.clone())?;
+    server.receive(syn)?;
+    server.get_test_rig().poll_scheduler();
+    let (rst_eth2_header, rst_ipv4_header, rst_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(server.get_test_rig().pop_frame())?;
+    crate::ensure_eq!(rst_eth2_header.dst_addr(), test_helpers::ALICE_MAC);
+    crate::ensure_eq!(rst_ipv4_header.get_dest_addr(), test_helpers::ALICE_IPV4);
+    crate::ensure_eq!(rst_header.src_port, closed_port);
+    crate::ensure_eq!(rst_header.dst_port, syn_header.src_port);
+    crate::ensure_eq!(rst_header.rst, true);
+    crate::ensure_eq!(rst_header.syn, false);
+    crate::ensure_eq!(rst_header.seq_num, SeqNumber::from(0));
+    crate::ensure_eq!(rst_header.ack, true);
+    crate::ensure_eq!(rst_header.ack_num, syn_header.seq_num + SeqNumber::from(1));
+
+    // A segment that acknowledges data is reset with the sequence number that it acknowledges.
+    let mut tcp_hdr: TcpHeader = TcpHeader::new(syn_header.src_port, closed_port);
+    tcp_hdr.seq_num = syn_header.seq_num + SeqNumber::from(1);
+    tcp_hdr.ack = true;
+    tcp_hdr.ack_num = SeqNumber::from(1234);
+    let segment: TcpSegment = TcpSegment {
+        ethernet2_hdr: eth2_header.clone(),
+        ipv4_hdr: ipv4_header,
+        tcp_hdr,
+        data: Some(DemiBuffer::from_slice(&[0; 8])?),
+        tx_checksum_offload: false,
+    };
+    server.receive(serialize_segment(segment)?)?;
+    let (_, _, rst_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(server.get_test_rig().pop_frame())?;
+    crate::ensure_eq!(rst_header.rst, true);
+    crate::ensure_eq!(rst_header.seq_num, SeqNumber::from(1234));
+    crate::ensure_eq!(rst_header.ack, false);
+
+    // A RST segment is never answered, so that peers cannot keep resetting each other.
+    let mut tcp_hdr: TcpHeader = TcpHeader::new(syn_header.src_port, closed_port);
+    tcp_hdr.rst = true;
+    let segment: TcpSegment = TcpSegment {
+        ethernet2_hdr: eth2_header,
+        ipv4_hdr: ipv4_header,
+        tcp_hdr,
+        data: None,
+        tx_checksum_offload: false,
+    };
+    crate::ensure_eq!(server.receive(serialize_segment(segment)?).is_err(), true);
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.get_test_rig().pop_frame_unchecked().is_none(), true);
+
+    Ok(())
+}
+
+/// Tests that connections do not complete while accepting is paused on a listening socket, and that they do once it is
+/// resumed.
+#[test]
+fn test_pause_accept() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // Listen and pause accepting right away.
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_listen(server_qd, 4)?;
+    let accept_qt: QToken = server.tcp_accept(server_qd)?;
+    server.tcp_pause_accept(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+
+    // The connection request is dropped without an answer.
+    let (_, connect_qt, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    match server.receive(syn.clone()) {
+        Err(e) if e.errno == EAGAIN => {},
+        _ => anyhow::bail!("SYN should have been dropped"),
+    }
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(server.get_test_rig().pop_frame_unchecked().is_none(), true);
+    crate::ensure_eq!(accept_completed(&server, accept_qt)?, false);
+    // The accept operation sleeps until accepting is resumed, instead of spinning.
+    crate::ensure_eq!(server.get_test_rig().get_runtime().has_ready_coroutines(), false);
+
+    // Once accepting is resumed, the retransmitted connection request goes through.
+    server.tcp_resume_accept(server_qd)?;
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    connection_setup_sync_rcvd_established(&mut server, ack)?;
+    crate::ensure_eq!(accept_completed(&server, accept_qt)?, true);
+    match client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(connect_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Connect)) => {},
+        _ => anyhow::bail!("connect should have completed"),
+    };
+
+    Ok(())
+}
+
+/// Tests that connections that wait too long to be accepted are reset instead of being accepted.
+#[test]
+fn test_ready_ttl() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // Listen with a ready queue TTL of one second.
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_listen(server_qd, 4)?;
+    server.tcp_set_ready_ttl(server_qd, Some(Duration::from_secs(1)))?;
+
+    // Establish a connection, without accepting it.
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let (_, _, syn_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(syn.clone())?;
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    connection_setup_sync_rcvd_established(&mut server, ack)?;
+    crate::ensure_eq!(server.tcp_listener_stats(server_qd)?.ready, 1);
+
+    // Once the connection is stale, accepting resets it instead of handing it out.
+    advance_clock(Some(&mut server), None, &mut now);
+    advance_clock(Some(&mut server), None, &mut now);
+    let accept_qt: QToken = server.tcp_accept(server_qd)?;
+    server.get_test_rig().poll_scheduler();
+    let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(server.get_test_rig().pop_frame())?;
+    crate::ensure_eq!(tcp_header.rst, true);
+    crate::ensure_eq!(tcp_header.dst_port, syn_header.src_port);
+    crate::ensure_eq!(server.tcp_listener_stats(server_qd)?.ready, 0);
+    crate::ensure_eq!(accept_completed(&server, accept_qt)?, false);
+
+    // Fresh connections are still accepted.
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    connection_setup_sync_rcvd_established(&mut server, ack)?;
+    crate::ensure_eq!(accept_completed(&server, accept_qt)?, true);
+
+    Ok(())
+}
+
+/// Tests that waiting for a connection to be established completes only once the SYN+ACK is processed.
+#[test]
+fn test_wait_established() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_listen(server_qd, 1)?;
+
+    // Sockets that are not connecting have nothing to wait for.
+    let client_qd: QDesc = client.tcp_socket()?;
+    match client.tcp_wait_established(client_qd) {
+        Err(e) if e.errno == libc::ENOTCONN => {},
+        _ => anyhow::bail!("wait_established() should have failed"),
+    }
+
+    // The wait is pending while the handshake is in progress.
+    let (client_qd, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let wait_qt: QToken = client.tcp_wait_established(client_qd)?;
+    client.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(accept_completed(&client, wait_qt)?, false);
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    crate::ensure_eq!(accept_completed(&client, wait_qt)?, false);
+
+    // The wait completes once the SYN+ACK is processed.
+    connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    client.get_test_rig().poll_scheduler();
+    match client
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(wait_qt)
+        .get_result()
+    {
+        Some((qd, OperationResult::Connect)) if qd == client_qd => {},
+        _ => anyhow::bail!("wait_established() should have completed"),
+    };
+
+    Ok(())
+}
+
+/// Tests that connections whose initial sequence number is pinned carry it on the handshake.
+#[test]
+fn test_fixed_isn() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let client_isn: SeqNumber = SeqNumber::from(0xdeadbeef);
+    let server_isn: SeqNumber = SeqNumber::from(0x12345678);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_set_fixed_isn(server_qd, Some(server_isn))?;
+    server.tcp_listen(server_qd, 1)?;
+    server.tcp_accept(server_qd)?;
+
+    // The SYN carries the ISN of the client.
+    let client_qd: QDesc = client.tcp_socket()?;
+    client.tcp_set_fixed_isn(client_qd, Some(client_isn))?;
+    client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    let syn: DemiBuffer = client.get_test_rig().pop_frame();
+    let (_, _, syn_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(syn.clone())?;
+    crate::ensure_eq!(syn_header.syn, true);
+    crate::ensure_eq!(syn_header.seq_num, client_isn);
+
+    // The SYN+ACK carries the ISN of the server.
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    let (_, _, syn_ack_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(syn_ack.clone())?;
+    crate::ensure_eq!(syn_ack_header.seq_num, server_isn);
+    crate::ensure_eq!(syn_ack_header.ack_num, client_isn + SeqNumber::from(1));
+
+    // The ACK acknowledges it.
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    let (_, _, ack_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(ack)?;
+    crate::ensure_eq!(ack_header.seq_num, client_isn + SeqNumber::from(1));
+    crate::ensure_eq!(ack_header.ack_num, server_isn + SeqNumber::from(1));
+
+    Ok(())
+}
+
+/// Tests that the ISN generator of a peer can be snapshotted and restored, so that it hands out the same sequence of
+/// ISNs as when the snapshot was taken.
+#[test]
+fn test_isn_generator_restore() -> Result<()> {
+    let now = Instant::now();
+    let state: IsnGeneratorState = IsnGeneratorState {
+        nonce: 0xdeadbeef,
+        counter: 42,
+    };
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    crate::ensure_neq!(server.tcp_isn_generator_state(), state);
+
+    // Once restored, the generator of the server is in the restored state, and the one of the client is left alone.
+    let client_state: IsnGeneratorState = client.tcp_isn_generator_state();
+    server.tcp_restore_isn_generator(state);
+    crate::ensure_eq!(server.tcp_isn_generator_state(), state);
+    crate::ensure_eq!(client.tcp_isn_generator_state(), client_state);
+
+    Ok(())
+}
+
+/// Tests that segments are signed with the MD5 signature option when a key is configured, and that segments that are
+/// not signed with the right key are dropped.
+#[cfg(feature = "tcp-md5")]
+#[test]
+fn test_md5_signature() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let key: &[u8] = b"bgp-session-key";
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_set_md5_key(server_qd, test_helpers::ALICE_IPV4, key)?;
+    server.tcp_listen(server_qd, 1)?;
+    let accept_qt: QToken = server.tcp_accept(server_qd)?;
+
+    // Segments that are not signed, or that are signed with another key, are dropped.
+    for client_key in [None, Some(&b"another-key"[..])] {
+        let client_qd: QDesc = client.tcp_socket()?;
+        if let Some(client_key) = client_key {
+            client.tcp_set_md5_key(client_qd, test_helpers::BOB_IPV4, client_key)?;
+        }
+        client.tcp_connect(client_qd, listen_addr)?;
+        client.get_test_rig().poll_scheduler();
+        client.get_test_rig().poll_scheduler();
+        let syn: DemiBuffer = client.get_test_rig().pop_frame();
+        match server.receive(syn) {
+            Err(e) if e.errno == EBADMSG => {},
+            result => anyhow::bail!("SYN should have been dropped: {:?}", result),
+        }
+    }
+
+    // All segments of the handshake carry a signature that our peer accepts.
+    let client_qd: QDesc = client.tcp_socket()?;
+    client.tcp_set_md5_key(client_qd, test_helpers::BOB_IPV4, key)?;
+    client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    let syn: DemiBuffer = client.get_test_rig().pop_frame();
+    let (_, _, syn_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(syn.clone())?;
+    crate::ensure_eq!(syn_header.get_md5_signature().is_some(), true);
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    let (_, _, syn_ack_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(syn_ack.clone())?;
+    crate::ensure_eq!(syn_ack_header.get_md5_signature().is_some(), true);
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    let (_, _, ack_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(ack.clone())?;
+    crate::ensure_eq!(ack_header.get_md5_signature().is_some(), true);
+    connection_setup_sync_rcvd_established(&mut server, ack)?;
+    crate::ensure_eq!(accept_completed(&server, accept_qt)?, true);
+
+    // Keys cannot be changed once the socket is connected.
+    match client.tcp_set_md5_key(client_qd, test_helpers::BOB_IPV4, key) {
+        Err(e) if e.errno == libc::EINVAL => {},
+        result => anyhow::bail!("setting a key on a connected socket should fail: {:?}", result),
+    }
+
+    Ok(())
+}
+
+/// Tests that a segment that carries the connection token, but that is not signed, does not move the connection to
+/// the address that it came from.
+#[cfg(all(feature = "tcp-md5", feature = "tcp-migration"))]
+#[test]
+fn test_md5_signature_before_migration() -> Result<()> {
+    let now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+    let key: &[u8] = b"bgp-session-key";
+    let new_port: u16 = 8080;
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let listen_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(listen_qd, listen_addr)?;
+    server.tcp_set_md5_key(listen_qd, test_helpers::ALICE_IPV4, key)?;
+    server.tcp_listen(listen_qd, 1)?;
+    let accept_qt: QToken = server.tcp_accept(listen_qd)?;
+
+    // Set up a signed connection.
+    let client_qd: QDesc = client.tcp_socket()?;
+    client.tcp_set_md5_key(client_qd, test_helpers::BOB_IPV4, key)?;
+    client.tcp_connect(client_qd, listen_addr)?;
+    client.get_test_rig().poll_scheduler();
+    client.get_test_rig().poll_scheduler();
+    let syn: DemiBuffer = client.get_test_rig().pop_frame();
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    let (eth2_header, ipv4_header, ack_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(ack.clone())?;
+    connection_setup_sync_rcvd_established(&mut server, ack)?;
+    let server_qd: QDesc = match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Accept((server_qd, _)))) => server_qd,
+        _ => anyhow::bail!("accept should have completed"),
+    };
+
+    // Replay the ACK from a new port without its signature. It carries the connection token, but it is dropped.
+    let mut tcp_hdr: TcpHeader = TcpHeader::new(new_port, ack_header.dst_port);
+    tcp_hdr.seq_num = ack_header.seq_num;
+    tcp_hdr.ack = true;
+    tcp_hdr.ack_num = ack_header.ack_num;
+    tcp_hdr.window_size = ack_header.window_size;
+    for option in ack_header.iter_options() {
+        match option {
+            TcpOptions2::Md5Signature(_) => continue,
+            _ => tcp_hdr.push_option(*option),
+        }
+    }
+    crate::ensure_eq!(tcp_hdr.get_connection_token().is_some(), true);
+    let segment: TcpSegment = TcpSegment {
+        ethernet2_hdr: eth2_header,
+        ipv4_hdr: ipv4_header,
+        tcp_hdr,
+        data: None,
+        tx_checksum_offload: false,
+    };
+    match server.receive(serialize_segment(segment)?) {
+        Err(e) if e.errno == EBADMSG => {},
+        result => anyhow::bail!("unsigned segment should have been dropped: {:?}", result),
+    }
+
+    // The connection still sends to the original port.
+    server.tcp_push(server_qd, DemiBuffer::from_slice(&[0; 8])?)?;
+    server.get_test_rig().poll_scheduler();
+    let (_, _, data_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(server.get_test_rig().pop_frame())?;
+    crate::ensure_eq!(data_header.dst_port, ack_header.src_port);
+
+    Ok(())
+}
+
+/// Tests that accepting backs off, instead of spinning, when accepted connections cannot get a queue descriptor, and
+/// that those connections are reset.
+#[test]
+fn test_accept_emfile_backoff() -> Result<()> {
+    let mut now = Instant::now();
+
+    // Connection parameters
+    let listen_port: u16 = 80;
+    let listen_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, listen_port);
+
+    // Setup peers.
+    let mut server: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let mut client: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+
+    // Listen, with no room left for the queue descriptors of accepted connections.
+    let server_qd: QDesc = server.tcp_socket()?;
+    server.tcp_bind(server_qd, listen_addr)?;
+    server.tcp_listen(server_qd, 4)?;
+    server.get_test_rig().get_runtime().set_max_queues(1);
+
+    // The accepted connection cannot be handed out, so it is reset.
+    let accept_qt: QToken = server.tcp_accept(server_qd)?;
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let (_, _, syn_header): (Ethernet2Header, Ipv4Header, TcpHeader) = extract_headers(syn.clone())?;
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    connection_setup_sync_rcvd_established(&mut server, ack)?;
+    match server
+        .get_test_rig()
+        .get_runtime()
+        .remove_coroutine_with_qtoken(accept_qt)
+        .get_result()
+    {
+        Some((_, OperationResult::Failed(e))) if e.errno == EMFILE => {},
+        _ => anyhow::bail!("accept should have failed with EMFILE"),
+    };
+    let (_, _, tcp_header): (Ethernet2Header, Ipv4Header, TcpHeader) =
+        extract_headers(server.get_test_rig().pop_frame())?;
+    crate::ensure_eq!(tcp_header.rst, true);
+    crate::ensure_eq!(tcp_header.dst_port, syn_header.src_port);
+
+    // Accepting backs off, even though a queue descriptor is now available and a connection is pending.
+    server.get_test_rig().get_runtime().set_max_queues(2);
+    let accept_qt: QToken = server.tcp_accept(server_qd)?;
+    let (_, _, syn): (QDesc, QToken, DemiBuffer) = connection_setup_listen_syn_sent(&mut client, listen_addr)?;
+    let syn_ack: DemiBuffer = connection_setup_listen_syn_rcvd(&mut server, syn)?;
+    let ack: DemiBuffer = connection_setup_syn_sent_established(&mut client, syn_ack)?;
+    connection_setup_sync_rcvd_established(&mut server, ack)?;
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(accept_completed(&server, accept_qt)?, false);
+    crate::ensure_eq!(server.tcp_listener_stats(server_qd)?.ready, 1);
+
+    // Accepting resumes once the back-off period is over.
+    advance_clock(Some(&mut server), None, &mut now);
+    server.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(accept_completed(&server, accept_qt)?, true);
+
+    Ok(())
+}
+
 //======================================================================================================================
 // Standalone Functions
 //======================================================================================================================
@@ -339,6 +1258,14 @@ fn extract_headers(bytes: DemiBuffer) -> Result<(Ethernet2Header, Ipv4Header, Tc
     return Ok((eth2_header, ipv4_header, tcp_header));
 }
 
+/// Checks if an accept operation has completed.
+fn accept_completed<const N: usize>(server: &SharedEngine<N>, accept_qt: QToken) -> Result<bool> {
+    match server.get_test_rig().get_runtime().from_task_id(accept_qt) {
+        Ok(task_handle) => Ok(task_handle.has_completed()),
+        Err(e) => anyhow::bail!("{:?}", e),
+    }
+}
+
 /// Serializes a TCP segment.
 fn serialize_segment(pkt: TcpSegment) -> Result<DemiBuffer> {
     let header_size: usize = pkt.header_size();
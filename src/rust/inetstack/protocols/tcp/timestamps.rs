@@ -0,0 +1,70 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::std::time::{
+    Duration,
+    Instant,
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// State of the timestamp option (see RFC 7323) on a connection that negotiated it.
+#[derive(Clone, Copy, Debug)]
+pub struct Timestamps {
+    /// Time at which our timestamp clock started. It starts with the handshake, so that every connection has its own
+    /// clock and timestamps cannot be correlated across connections.
+    base: Instant,
+    /// Most recent timestamp value received from our peer (TS.Recent), which is echoed back to it.
+    recent: u32,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl Timestamps {
+    /// Creates the timestamp state of a connection whose clock started at `base` and whose peer last sent `recent`.
+    pub fn new(base: Instant, recent: u32) -> Self {
+        Self { base, recent }
+    }
+
+    /// Returns the value of our timestamp clock at `now`. The clock ticks once per millisecond and starts at one, so
+    /// that a zero echo reply can never be mistaken for a valid one.
+    pub fn clock(base: Instant, now: Instant) -> u32 {
+        (now.saturating_duration_since(base).as_millis() as u32).wrapping_add(1)
+    }
+
+    /// Returns the timestamp value (TSval) to send at `now`.
+    pub fn value(&self, now: Instant) -> u32 {
+        Self::clock(self.base, now)
+    }
+
+    /// Returns the timestamp echo reply (TSecr) to send.
+    pub fn echo(&self) -> u32 {
+        self.recent
+    }
+
+    /// Checks if `value` was sent before the most recent timestamp value received from our peer. Such segments are old
+    /// duplicates and are rejected by PAWS (see RFC 7323 Section 5.3).
+    pub fn is_old(&self, value: u32) -> bool {
+        (value.wrapping_sub(self.recent) as i32) < 0
+    }
+
+    /// Records `value` as the most recent timestamp value received from our peer.
+    pub fn update(&mut self, value: u32) {
+        if !self.is_old(value) {
+            self.recent = value;
+        }
+    }
+
+    /// Returns the round-trip time measured at `now` by a segment that echoed `echo` (see RFC 7323 Section 4.1).
+    pub fn rtt(&self, echo: u32, now: Instant) -> Duration {
+        Duration::from_millis(self.value(now).wrapping_sub(echo) as u64)
+    }
+}
@@ -91,8 +91,13 @@ impl UdpHeader {
             let checksum: u16 = u16::from_be_bytes([hdr_buf[6], hdr_buf[7]]);
             // Check if we should skip checksum verification.
             if checksum != 0 {
-                // No, so check if checksum value matches what we expect.
-                if checksum != Self::checksum(&ipv4_hdr, hdr_buf, payload_buf) {
+                // No, so check if checksum value matches what we expect. A computed checksum of zero is sent as all
+                // ones, so we should map it back before comparing.
+                let expected: u16 = match Self::checksum(&ipv4_hdr, hdr_buf, payload_buf) {
+                    0 => 0xffff,
+                    expected => expected,
+                };
+                if checksum != expected {
                     return Err(Fail::new(EBADMSG, "UDP checksum mismatch"));
                 }
             }
@@ -124,10 +129,14 @@ impl UdpHeader {
         fixed_buf[4..6].copy_from_slice(&((UDP_HEADER_SIZE + data.len()) as u16).to_be_bytes());
 
         // Write checksum.
+        // A computed checksum of zero is sent as all ones, as zero means that there is no checksum (see RFC 768).
         let checksum: u16 = if checksum_offload {
             0
         } else {
-            Self::checksum(ipv4_hdr, &fixed_buf[..], data)
+            match Self::checksum(ipv4_hdr, &fixed_buf[..], data) {
+                0 => 0xffff,
+                checksum => checksum,
+            }
         };
         fixed_buf[6..8].copy_from_slice(&checksum.to_be_bytes());
     }
@@ -269,4 +278,42 @@ mod test {
 
         Ok(())
     }
+
+    /// Tests that a datagram whose checksum computes to zero survives a round trip.
+    #[test]
+    fn test_udp_zero_checksum_round_trip() -> Result<()> {
+        // Build fake IPv4 header.
+        let ipv4_hdr: Ipv4Header = ipv4_header();
+
+        // Build fake UDP header.
+        let src_port: u16 = 0x32;
+        let dest_port: u16 = 0x45;
+        let checksum_offload: bool = false;
+        let udp_hdr: UdpHeader = UdpHeader::new(src_port, dest_port);
+
+        // Pick a payload word that cancels out everything else, so that the checksum computes to zero.
+        let mut hdr: [u8; 8] = [0x0, 0x32, 0x0, 0x45, 0x0, 0xa, 0x0, 0x0];
+        let partial: u16 = UdpHeader::checksum(&ipv4_hdr, &hdr, &[0x0, 0x0]);
+        let data: [u8; 2] = partial.to_be_bytes();
+        crate::ensure_eq!(UdpHeader::checksum(&ipv4_hdr, &hdr, &data), 0);
+
+        // Zero is sent as all ones.
+        udp_hdr.serialize(&mut hdr, &ipv4_hdr, &data, checksum_offload);
+        crate::ensure_eq!(hdr[6..8], [0xff, 0xff]);
+
+        // Input buffer.
+        let buf: Vec<u8> = [&hdr[..], &data[..]].concat();
+
+        // Do it.
+        match UdpHeader::parse_from_slice(&ipv4_hdr, &buf, checksum_offload) {
+            Ok((udp_hdr, buf)) => {
+                crate::ensure_eq!(udp_hdr.src_port(), src_port);
+                crate::ensure_eq!(udp_hdr.dest_port(), dest_port);
+                crate::ensure_eq!(buf, &data[..]);
+            },
+            Err(e) => anyhow::bail!("could not parse: {:?}", e),
+        };
+
+        Ok(())
+    }
 }
@@ -22,7 +22,10 @@ use crate::{
 // Exports
 //==============================================================================
 
-pub use header::UdpHeader;
+pub use header::{
+    UdpHeader,
+    UDP_HEADER_SIZE,
+};
 
 //==============================================================================
 // Structures
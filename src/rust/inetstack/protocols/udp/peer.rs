@@ -12,7 +12,10 @@ use super::{
 use crate::{
     inetstack::protocols::{
         arp::SharedArpPeer,
-        ipv4::Ipv4Header,
+        ipv4::{
+            Ipv4Header,
+            SharedIpv4IdGenerator,
+        },
     },
     runtime::{
         fail::Fail,
@@ -68,6 +71,12 @@ pub struct UdpPeer<const N: usize> {
     local_ipv4_addr: Ipv4Addr,
     /// Offload checksum to hardware?
     checksum_offload: bool,
+    /// Compute checksums of outgoing datagrams?
+    checksum: bool,
+    /// Maximum transmission unit of the link.
+    mtu: usize,
+    /// Identification values for datagrams that have to be fragmented, shared by all sockets.
+    ip_ids: SharedIpv4IdGenerator,
 }
 
 #[derive(Clone)]
@@ -86,6 +95,8 @@ impl<const N: usize> SharedUdpPeer<N> {
         local_link_addr: MacAddress,
         local_ipv4_addr: Ipv4Addr,
         offload_checksum: bool,
+        checksum: bool,
+        mtu: usize,
         arp: SharedArpPeer<N>,
     ) -> Result<Self, Fail> {
         Ok(Self(SharedObject::<UdpPeer<N>>::new(UdpPeer {
@@ -95,6 +106,9 @@ impl<const N: usize> SharedUdpPeer<N> {
             local_link_addr,
             local_ipv4_addr,
             checksum_offload: offload_checksum,
+            checksum,
+            mtu,
+            ip_ids: SharedIpv4IdGenerator::default(),
         })))
     }
 
@@ -106,8 +120,11 @@ impl<const N: usize> SharedUdpPeer<N> {
             self.transport.clone(),
             self.arp.clone(),
             self.checksum_offload,
+            self.checksum,
+            self.mtu,
+            self.ip_ids.clone(),
         )?;
-        let new_qd: QDesc = self.runtime.alloc_queue::<SharedUdpQueue<N>>(new_queue);
+        let new_qd: QDesc = self.runtime.alloc_queue::<SharedUdpQueue<N>>(new_queue)?;
         trace!("socket(): qd={:?}", new_qd);
         Ok(new_qd)
     }
@@ -133,7 +150,7 @@ impl<const N: usize> SharedUdpPeer<N> {
         }
 
         // Check if this is an ephemeral port or a wildcard one.
-        if SharedDemiRuntime::is_private_ephemeral_port(addr.port()) {
+        if self.runtime.is_private_ephemeral_port(addr.port()) {
             // Allocate ephemeral port from the pool, to leave  ephemeral port allocator in a consistent state.
             self.runtime.reserve_ephemeral_port(addr.port())?
         } else if addr.port() == 0 {
@@ -199,21 +216,41 @@ impl<const N: usize> SharedUdpPeer<N> {
         let local: SocketAddrV4 = SocketAddrV4::new(ipv4_hdr.get_dest_addr(), hdr.dest_port());
         let remote: SocketAddrV4 = SocketAddrV4::new(ipv4_hdr.get_src_addr(), hdr.src_port());
 
-        let mut queue: SharedUdpQueue<N> = match self.get_queue_from_addr(&local) {
+        let mut queue: SharedUdpQueue<N> = match self.get_bound_queue(&local) {
             Some(queue) => queue,
-            None => {
-                // Handle wildcard address.
-                let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, hdr.dest_port());
-                match self.get_queue_from_addr(&local) {
-                    Some(queue) => queue,
-                    None => return Err(Fail::new(libc::ENOTCONN, "port not bound")),
-                }
-            },
+            None => return Err(Fail::new(libc::ENOTCONN, "port not bound")),
         };
         // TODO: Drop this packet if local address/port pair is not bound.
         queue.receive(remote, data)
     }
 
+    /// Reports an asynchronous error for a datagram sent from `local` to `remote`.
+    pub fn receive_error(&mut self, local: SocketAddrV4, remote: SocketAddrV4, error: Fail) {
+        match self.get_bound_queue(&local) {
+            Some(mut queue) => queue.receive_error(remote, error),
+            None => warn!("receive_error(): no socket bound to {:?}", local),
+        }
+    }
+
+    /// Retrieves the oldest asynchronous error reported on a UDP socket, if any.
+    pub fn poll_error(&mut self, qd: QDesc) -> Result<Option<Fail>, Fail> {
+        trace!("poll_error(): qd={:?}", qd);
+        let mut queue: SharedUdpQueue<N> = self.get_shared_queue(&qd)?;
+        Ok(queue.poll_error())
+    }
+
+    /// Looks up the queue bound to a local address, falling back to a queue bound to the wildcard address.
+    fn get_bound_queue(&self, local: &SocketAddrV4) -> Option<SharedUdpQueue<N>> {
+        match self.get_queue_from_addr(local) {
+            Some(queue) => Some(queue),
+            None => {
+                // Handle wildcard address.
+                let local: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, local.port());
+                self.get_queue_from_addr(&local)
+            },
+        }
+    }
+
     fn get_queue_from_addr(&self, local: &SocketAddrV4) -> Option<SharedUdpQueue<N>> {
         for (_, boxed_queue) in self.runtime.get_qtable().get_values() {
             match downcast_queue_ptr::<SharedUdpQueue<N>>(boxed_queue) {
@@ -14,9 +14,19 @@ use crate::{
             Ethernet2Header,
         },
         ip::IpProtocol,
-        ipv4::Ipv4Header,
+        ipv4::{
+            fragment,
+            Ipv4Fragment,
+            Ipv4Header,
+            SharedIpv4IdGenerator,
+            IPV4_HEADER_MIN_SIZE,
+            IPV4_PAYLOAD_MAX_SIZE,
+        },
         udp::{
-            datagram::UdpDatagram,
+            datagram::{
+                UdpDatagram,
+                UDP_HEADER_SIZE,
+            },
             UdpHeader,
         },
     },
@@ -72,10 +82,17 @@ pub struct UdpQueue<const N: usize> {
     bound: Option<SocketAddrV4>,
     local_link_addr: MacAddress,
     transport: SharedBox<dyn NetworkRuntime<N>>,
-    // A queue of incoming packets as remote address and data buffer pairs.
-    recv_queue: AsyncQueue<(SocketAddrV4, DemiBuffer)>,
+    // A queue of incoming packets as remote address and data buffer pairs, interleaved with asynchronous errors
+    // reported by the network (e.g. ICMP destination unreachable messages).
+    recv_queue: AsyncQueue<Result<(SocketAddrV4, DemiBuffer), Fail>>,
     arp: SharedArpPeer<N>,
     checksum_offload: bool,
+    // Compute checksums of outgoing datagrams? If not, they are sent with a zero checksum, which is legal over IPv4.
+    checksum: bool,
+    // Maximum transmission unit of the link. Larger datagrams are sent in fragments.
+    mtu: usize,
+    // Identification values for datagrams that have to be fragmented.
+    ip_ids: SharedIpv4IdGenerator,
 }
 #[derive(Clone)]
 pub struct SharedUdpQueue<const N: usize>(SharedObject<UdpQueue<N>>);
@@ -91,15 +108,21 @@ impl<const N: usize> SharedUdpQueue<N> {
         transport: SharedBox<dyn NetworkRuntime<N>>,
         arp: SharedArpPeer<N>,
         checksum_offload: bool,
+        checksum: bool,
+        mtu: usize,
+        ip_ids: SharedIpv4IdGenerator,
     ) -> Result<Self, Fail> {
         Ok(Self(SharedObject::new(UdpQueue {
             local_ipv4_addr,
             bound: None,
             local_link_addr,
             transport,
-            recv_queue: AsyncQueue::<(SocketAddrV4, DemiBuffer)>::default(),
+            recv_queue: AsyncQueue::<Result<(SocketAddrV4, DemiBuffer), Fail>>::default(),
             arp,
             checksum_offload,
+            checksum,
+            mtu,
+            ip_ids,
         })))
     }
 
@@ -125,13 +148,25 @@ impl<const N: usize> SharedUdpQueue<N> {
         let remote_link_addr: MacAddress = self.arp.query(remote.ip().clone(), &yielder).await?;
         let udp_header: UdpHeader = UdpHeader::new(port, remote.port());
         debug!("UDP send {:?}", udp_header);
-        let datagram = UdpDatagram::new(
-            Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4),
-            Ipv4Header::new(self.local_ipv4_addr, remote.ip().clone(), IpProtocol::UDP),
-            udp_header,
-            buf,
-            self.checksum_offload,
-        );
+        let ethernet2_hdr: Ethernet2Header =
+            Ethernet2Header::new(remote_link_addr, self.local_link_addr, EtherType2::Ipv4);
+        let ipv4_hdr: Ipv4Header = Ipv4Header::new(self.local_ipv4_addr, remote.ip().clone(), IpProtocol::UDP);
+
+        // Send datagrams that do not fit in a single packet in fragments.
+        let mtu: usize = self.mtu;
+        if UDP_HEADER_SIZE + buf.len() > mtu - IPV4_HEADER_MIN_SIZE as usize {
+            let payload: DemiBuffer = self.serialize_datagram(&udp_header, &ipv4_hdr, &buf)?;
+            let identification: u16 = self.ip_ids.next();
+            let fragments: Vec<Ipv4Fragment> = fragment(ethernet2_hdr, ipv4_hdr, identification, payload, mtu)?;
+            for pkt in fragments {
+                self.transport.transmit(Box::new(pkt));
+            }
+            return Ok(());
+        }
+
+        // Leave the checksum zero if hardware computes it or if it is disabled.
+        let skip_checksum: bool = self.checksum_offload || !self.checksum;
+        let datagram = UdpDatagram::new(ethernet2_hdr, ipv4_hdr, udp_header, buf, skip_checksum);
         self.transport.transmit(Box::new(datagram));
         Ok(())
     }
@@ -142,7 +177,7 @@ impl<const N: usize> SharedUdpQueue<N> {
 
         loop {
             match self.recv_queue.pop(&yielder).await {
-                Ok(msg) => {
+                Ok(Ok(msg)) => {
                     let remote: SocketAddrV4 = msg.0;
                     let mut buf: DemiBuffer = msg.1;
                     // We got more bytes than expected, so we trim the buffer.
@@ -151,6 +186,7 @@ impl<const N: usize> SharedUdpQueue<N> {
                     };
                     return Ok((remote, buf));
                 },
+                Ok(Err(e)) => return Err(e),
                 Err(e) => return Err(e),
             }
         }
@@ -159,13 +195,48 @@ impl<const N: usize> SharedUdpQueue<N> {
     pub fn receive(&mut self, remote: SocketAddrV4, buf: DemiBuffer) -> Result<(), Fail> {
         // Push data to the receiver-side shared queue. This will cause the
         // associated pool operation to be ready.
-        self.recv_queue.push((remote, buf));
+        self.recv_queue.push(Ok((remote, buf)));
         Ok(())
     }
 
+    /// Reports an asynchronous error on this queue. The error is surfaced by the next pop, unless it is retrieved
+    /// beforehand with [Self::poll_error].
+    pub fn receive_error(&mut self, remote: SocketAddrV4, error: Fail) {
+        debug!("receive_error(): remote={:?} error={:?}", remote, error);
+        self.recv_queue.push(Err(error));
+    }
+
+    /// Retrieves the oldest asynchronous error reported on this queue, if any.
+    pub fn poll_error(&mut self) -> Option<Fail> {
+        match self.recv_queue.remove_first(|item| item.is_err()) {
+            Some(Err(e)) => Some(e),
+            _ => None,
+        }
+    }
+
     pub fn is_bound(&self) -> bool {
         self.bound.is_some()
     }
+
+    /// Serializes a whole UDP datagram into a single buffer, so that it can be split into IPv4 fragments. The checksum
+    /// is computed in software unless it is disabled, because hardware only sees one fragment at a time.
+    fn serialize_datagram(
+        &self,
+        udp_hdr: &UdpHeader,
+        ipv4_hdr: &Ipv4Header,
+        data: &DemiBuffer,
+    ) -> Result<DemiBuffer, Fail> {
+        let len: usize = UDP_HEADER_SIZE + data.len();
+        if len > IPV4_PAYLOAD_MAX_SIZE {
+            let cause: String = format!("datagram is too big (len={:?})", data.len());
+            error!("pushto(): {}", &cause);
+            return Err(Fail::new(libc::EMSGSIZE, &cause));
+        }
+        let mut buf: DemiBuffer = DemiBuffer::new(len as u16);
+        udp_hdr.serialize(&mut buf[..UDP_HEADER_SIZE], ipv4_hdr, &data[..], !self.checksum);
+        buf[UDP_HEADER_SIZE..].copy_from_slice(&data[..]);
+        Ok(buf)
+    }
 }
 
 //======================================================================================================================
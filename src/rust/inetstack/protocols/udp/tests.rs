@@ -2,13 +2,28 @@
 // // Licensed under the MIT license.
 
 use crate::{
-    inetstack::test_helpers::{
-        self,
-        SharedEngine,
+    inetstack::{
+        protocols::{
+            ethernet2::ETHERNET2_HEADER_SIZE,
+            ipv4::{
+                IPV4_HEADER_MIN_SIZE,
+                IPV4_REASSEMBLY_TIMEOUT,
+            },
+        },
+        test_helpers::{
+            self,
+            SharedEngine,
+        },
     },
     runtime::{
         memory::DemiBuffer,
-        network::consts::RECEIVE_BATCH_SIZE,
+        network::{
+            config::UdpConfig,
+            consts::{
+                DEFAULT_MTU,
+                RECEIVE_BATCH_SIZE,
+            },
+        },
         queue::{
             Operation,
             OperationResult,
@@ -24,9 +39,11 @@ use ::futures::task::{
 use ::libc::{
     EADDRINUSE,
     EBADF,
+    ECONNREFUSED,
     ENOTCONN,
 };
 use ::std::{
+    collections::VecDeque,
     convert::TryFrom,
     future::Future,
     net::{
@@ -130,6 +147,223 @@ fn udp_push_pop() -> Result<()> {
     Ok(())
 }
 
+/// Tests that a datagram that does not fit in a single packet is sent in fragments and reassembled on receive, even if
+/// fragments arrive out of order.
+#[test]
+fn udp_push_pop_fragmented() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let alice_port: u16 = 80;
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let bob_port: u16 = 80;
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Send a 4 KB datagram to Bob.
+    let data: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+    let buf: DemiBuffer = DemiBuffer::from_slice(&data[..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push)) => {},
+        _ => anyhow::bail!("push should have completed"),
+    };
+    alice.get_test_rig().poll_scheduler();
+
+    // It should have been split into packets that fit in the MTU.
+    let frames: VecDeque<DemiBuffer> = alice.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 3);
+    for frame in &frames {
+        crate::ensure_eq!(frame.len() <= ETHERNET2_HEADER_SIZE + DEFAULT_MTU, true);
+    }
+
+    // Deliver fragments to Bob in reverse order.
+    for frame in frames.into_iter().rev() {
+        bob.receive(frame)?;
+    }
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    let (remote_addr, received_buf): (Option<SocketAddrV4>, DemiBuffer) =
+        match Future::poll(coroutine.as_mut(), &mut ctx) {
+            Poll::Ready((_, OperationResult::Pop(addr, buf))) => (addr, buf),
+            _ => anyhow::bail!("pop should have completed"),
+        };
+    crate::ensure_eq!(remote_addr, Some(alice_addr));
+    crate::ensure_eq!(received_buf[..], buf[..]);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that datagrams are only fragmented if they do not fit in the configured MTU.
+#[test]
+fn udp_push_configured_mtu() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice with jumbo frames.
+    let mtu: usize = 9000;
+    let udp_config: UdpConfig = UdpConfig::new(None, None, None, Some(mtu));
+    let mut alice: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_udp_config(now, udp_config);
+    let alice_port: u16 = 80;
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, 80);
+
+    // A 4 KB datagram fits in a single packet.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 4096][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push)) => {},
+        _ => anyhow::bail!("push should have completed"),
+    };
+    alice.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(alice.get_test_rig().pop_all_frames().len(), 1);
+
+    // A 16 KB datagram is split into packets that fit in the configured MTU.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 16384][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push)) => {},
+        _ => anyhow::bail!("push should have completed"),
+    };
+    alice.get_test_rig().poll_scheduler();
+    let frames: VecDeque<DemiBuffer> = alice.get_test_rig().pop_all_frames();
+    crate::ensure_eq!(frames.len(), 2);
+    for frame in &frames {
+        crate::ensure_eq!(frame.len() <= ETHERNET2_HEADER_SIZE + mtu, true);
+    }
+
+    alice.udp_close(alice_fd)?;
+
+    Ok(())
+}
+
+/// Tests that datagrams are sent with a zero checksum when checksums are disabled, and that they are still accepted.
+#[test]
+fn udp_push_pop_without_checksum() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let udp_config: UdpConfig = UdpConfig::new(None, None, Some(false), None);
+    let mut alice: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2_with_udp_config(now, udp_config);
+    let alice_port: u16 = 80;
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let bob_port: u16 = 80;
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Send data to Bob.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf.clone(), bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push)) => {},
+        _ => anyhow::bail!("push should have completed"),
+    };
+    alice.get_test_rig().poll_scheduler();
+
+    // The checksum of the datagram should be zero.
+    let frame: DemiBuffer = alice.get_test_rig().pop_frame();
+    let checksum_offset: usize = ETHERNET2_HEADER_SIZE + IPV4_HEADER_MIN_SIZE as usize + 6;
+    crate::ensure_eq!(&frame[checksum_offset..(checksum_offset + 2)], &[0, 0]);
+
+    // Bob skips verification of the checksum.
+    bob.receive(frame)?;
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    let (remote_addr, received_buf): (Option<SocketAddrV4>, DemiBuffer) =
+        match Future::poll(coroutine.as_mut(), &mut ctx) {
+            Poll::Ready((_, OperationResult::Pop(addr, buf))) => (addr, buf),
+            _ => anyhow::bail!("pop should have completed"),
+        };
+    crate::ensure_eq!(remote_addr, Some(alice_addr));
+    crate::ensure_eq!(received_buf[..], buf[..]);
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
+/// Tests that fragments of a datagram are dropped in the background if the remaining ones do not arrive in time.
+#[test]
+fn udp_push_pop_fragmented_timeout() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let mut now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let alice_port: u16 = 80;
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let bob_port: u16 = 80;
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
+    let bob_fd: QDesc = bob.udp_socket()?;
+    bob.udp_bind(bob_fd, bob_addr)?;
+
+    // Send a 4 KB datagram to Bob.
+    let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 4096][..]).expect("slice should fit in DemiBuffer");
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Push)) => {},
+        _ => anyhow::bail!("push should have completed"),
+    };
+    alice.get_test_rig().poll_scheduler();
+    let mut frames: VecDeque<DemiBuffer> = alice.get_test_rig().pop_all_frames();
+
+    // Deliver all fragments but the last one.
+    bob.get_test_rig().poll_scheduler();
+    let last: DemiBuffer = frames.pop_back().expect("datagram should have been fragmented");
+    for frame in frames {
+        bob.receive(frame)?;
+    }
+    crate::ensure_eq!(bob.ipv4_reassembly_len(), 1);
+
+    // The fragments should be dropped once they expire, even though no further fragment arrives.
+    now += IPV4_REASSEMBLY_TIMEOUT + Duration::from_secs(1);
+    bob.advance_clock(now);
+    bob.get_test_rig().poll_scheduler();
+    crate::ensure_eq!(bob.ipv4_reassembly_len(), 0);
+
+    // Deliver the last fragment too late.
+    bob.receive(last)?;
+
+    // The datagram should never be delivered.
+    let mut coroutine: Pin<Box<Operation>> = bob.udp_pop(bob_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Pending => {},
+        _ => anyhow::bail!("pop should not have completed"),
+    };
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+    bob.udp_close(bob_fd)?;
+
+    Ok(())
+}
+
 //==============================================================================
 // Push & Pop
 //==============================================================================
@@ -576,6 +810,66 @@ fn udp_pop_not_bound() -> Result<()> {
     Ok(())
 }
 
+//==============================================================================
+// Port Unreachable
+//==============================================================================
+
+#[test]
+fn udp_push_port_unreachable() -> Result<()> {
+    let mut ctx: Context = Context::from_waker(noop_waker_ref());
+    let now: Instant = Instant::now();
+
+    // Setup Alice.
+    let mut alice: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_alice2(now);
+    let alice_port: u16 = 80;
+    let alice_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::ALICE_IPV4, alice_port);
+    let alice_fd: QDesc = alice.udp_socket()?;
+    alice.udp_bind(alice_fd, alice_addr)?;
+
+    // Setup Bob.
+    let mut bob: SharedEngine<RECEIVE_BATCH_SIZE> = test_helpers::new_bob2(now);
+    let bob_port: u16 = 80;
+    let bob_addr: SocketAddrV4 = SocketAddrV4::new(test_helpers::BOB_IPV4, bob_port);
+    // Bob does not create a socket.
+
+    for _ in 0..2 {
+        // Send data to Bob.
+        let buf: DemiBuffer = DemiBuffer::from_slice(&vec![0x5a; 32][..]).expect("slice should fit in DemiBuffer");
+        let mut coroutine: Pin<Box<Operation>> = alice.udp_pushto(alice_fd, buf, bob_addr)?;
+        match Future::poll(coroutine.as_mut(), &mut ctx) {
+            Poll::Ready((_, OperationResult::Push)) => {},
+            _ => unreachable!("Push failed"),
+        };
+
+        // Bob rejects the datagram and sends back a port unreachable message.
+        match bob.receive(alice.get_test_rig().pop_frame()) {
+            Err(e) if e.errno == ENOTCONN => {},
+            _ => anyhow::bail!("receive should have failed"),
+        };
+        bob.get_test_rig().poll_scheduler();
+        alice.receive(bob.get_test_rig().pop_frame())?;
+    }
+
+    // The first error is retrieved by polling.
+    match alice.udp_poll_error(alice_fd)? {
+        Some(e) if e.errno == ECONNREFUSED => {},
+        e => anyhow::bail!("poll_error should have reported ECONNREFUSED, got {:?}", e),
+    };
+
+    // The second error is reported by the next pop.
+    let mut coroutine: Pin<Box<Operation>> = alice.udp_pop(alice_fd)?;
+    match Future::poll(coroutine.as_mut(), &mut ctx) {
+        Poll::Ready((_, OperationResult::Failed(e))) if e.errno == ECONNREFUSED => {},
+        _ => anyhow::bail!("pop should have failed with ECONNREFUSED"),
+    };
+    assert!(alice.udp_poll_error(alice_fd)?.is_none());
+
+    // Close peers.
+    alice.udp_close(alice_fd)?;
+
+    Ok(())
+}
+
 //==============================================================================
 // Bad Push
 //==============================================================================
@@ -9,6 +9,14 @@ use crate::{
                 EtherType2,
                 Ethernet2Header,
             },
+            tcp::{
+                segment::TcpSegment,
+                ConnEvent,
+                ConnMetrics,
+                IsnGeneratorState,
+                ListenerStats,
+                SeqNumber,
+            },
             udp::SharedUdpPeer,
             Peer,
         },
@@ -19,7 +27,13 @@ use crate::{
     runtime::{
         fail::Fail,
         memory::DemiBuffer,
+        metrics::MetricsText,
         network::{
+            tap::{
+                Direction,
+                SharedPacketTap,
+                TapNetworkRuntime,
+            },
             types::MacAddress,
             NetworkRuntime,
         },
@@ -55,6 +69,7 @@ pub struct Engine<const N: usize> {
     test_rig: SharedTestRuntime,
     arp: SharedArpPeer<N>,
     ipv4: Peer<N>,
+    tap: SharedPacketTap,
 }
 
 #[derive(Clone)]
@@ -68,7 +83,11 @@ impl<const N: usize> SharedEngine<N> {
         let udp_config: UdpConfig = test_rig.get_udp_config();
         let tcp_config: TcpConfig = test_rig.get_tcp_config();
 
-        let boxed_test_rig: SharedBox<dyn NetworkRuntime<N>> = SharedBox::new(Box::new(test_rig.clone()));
+        let tap: SharedPacketTap = SharedPacketTap::default();
+        let boxed_test_rig: SharedBox<dyn NetworkRuntime<N>> = SharedBox::new(Box::new(TapNetworkRuntime::new(
+            SharedBox::<dyn NetworkRuntime<N>>::new(Box::new(test_rig.clone())),
+            tap.clone(),
+        )));
         let arp = SharedArpPeer::new(
             test_rig.get_runtime(),
             boxed_test_rig.clone(),
@@ -87,7 +106,12 @@ impl<const N: usize> SharedEngine<N> {
             arp.clone(),
             rng_seed,
         )?;
-        Ok(Self(SharedObject::<Engine<N>>::new(Engine { test_rig, arp, ipv4 })))
+        Ok(Self(SharedObject::<Engine<N>>::new(Engine {
+            test_rig,
+            arp,
+            ipv4,
+            tap,
+        })))
     }
 
     pub fn advance_clock(&mut self, now: Instant) {
@@ -139,6 +163,10 @@ impl<const N: usize> SharedEngine<N> {
         self.ipv4.udp.close(socket_fd)
     }
 
+    pub fn udp_poll_error(&mut self, socket_fd: QDesc) -> Result<Option<Fail>, Fail> {
+        self.ipv4.udp.poll_error(socket_fd)
+    }
+
     pub fn tcp_socket(&mut self) -> Result<QDesc, Fail> {
         self.ipv4.tcp.socket()
     }
@@ -147,6 +175,10 @@ impl<const N: usize> SharedEngine<N> {
         self.ipv4.tcp.connect(socket_fd, remote_endpoint)
     }
 
+    pub fn tcp_wait_established(&mut self, socket_fd: QDesc) -> Result<QToken, Fail> {
+        self.ipv4.tcp.wait_established(socket_fd)
+    }
+
     pub fn tcp_bind(&mut self, socket_fd: QDesc, endpoint: SocketAddrV4) -> Result<(), Fail> {
         self.ipv4.tcp.bind(socket_fd, endpoint)
     }
@@ -163,6 +195,18 @@ impl<const N: usize> SharedEngine<N> {
         self.ipv4.tcp.pop(socket_fd, None)
     }
 
+    pub fn tcp_push_urgent(&mut self, socket_fd: QDesc, buf: DemiBuffer) -> Result<QToken, Fail> {
+        self.ipv4.tcp.push_urgent(socket_fd, buf)
+    }
+
+    pub fn tcp_pop_with<F: FnOnce(&[u8])>(&mut self, socket_fd: QDesc, f: F) -> Result<usize, Fail> {
+        self.ipv4.tcp.pop_with(socket_fd, f)
+    }
+
+    pub fn tcp_pop_urgent(&mut self, socket_fd: QDesc) -> Result<QToken, Fail> {
+        self.ipv4.tcp.pop_urgent(socket_fd)
+    }
+
     pub fn tcp_async_close(&mut self, socket_fd: QDesc) -> Result<QToken, Fail> {
         self.ipv4.tcp.async_close(socket_fd)
     }
@@ -171,18 +215,120 @@ impl<const N: usize> SharedEngine<N> {
         self.ipv4.tcp.listen(socket_fd, backlog)
     }
 
+    pub fn tcp_set_backlog(&mut self, socket_fd: QDesc, backlog: usize) -> Result<(), Fail> {
+        self.ipv4.tcp.set_backlog(socket_fd, backlog)
+    }
+
+    pub fn tcp_set_ready_ttl(&mut self, socket_fd: QDesc, ttl: Option<Duration>) -> Result<(), Fail> {
+        self.ipv4.tcp.set_ready_ttl(socket_fd, ttl)
+    }
+
+    pub fn tcp_set_fixed_isn(&mut self, socket_fd: QDesc, isn: Option<SeqNumber>) -> Result<(), Fail> {
+        self.ipv4.tcp.set_fixed_isn(socket_fd, isn)
+    }
+
+    pub fn tcp_isn_generator_state(&self) -> IsnGeneratorState {
+        self.ipv4.tcp.isn_generator_state()
+    }
+
+    pub fn tcp_restore_isn_generator(&mut self, state: IsnGeneratorState) {
+        self.ipv4.tcp.restore_isn_generator(state)
+    }
+
+    #[cfg(feature = "tcp-md5")]
+    pub fn tcp_set_md5_key(&mut self, socket_fd: QDesc, remote: Ipv4Addr, key: &[u8]) -> Result<(), Fail> {
+        self.ipv4.tcp.set_md5_key(socket_fd, remote, key)
+    }
+
+    pub fn tcp_pause_accept(&mut self, socket_fd: QDesc) -> Result<(), Fail> {
+        self.ipv4.tcp.pause_accept(socket_fd)
+    }
+
+    pub fn tcp_resume_accept(&mut self, socket_fd: QDesc) -> Result<(), Fail> {
+        self.ipv4.tcp.resume_accept(socket_fd)
+    }
+
+    pub fn tcp_listener_stats(&self, socket_fd: QDesc) -> Result<ListenerStats, Fail> {
+        self.ipv4.tcp.listener_stats(socket_fd)
+    }
+
+    pub fn tcp_connection_metrics(&self, socket_fd: QDesc) -> Result<ConnMetrics, Fail> {
+        self.ipv4.tcp.connection_metrics(socket_fd)
+    }
+
+    pub fn metrics_text(&self) -> String {
+        let mut metrics: MetricsText = MetricsText::default();
+        self.test_rig.get_runtime().write_metrics(&mut metrics);
+        self.ipv4.tcp.write_metrics(&mut metrics);
+        metrics.render()
+    }
+
+    pub fn tcp_dump_tcb(&self, socket_fd: QDesc) -> Result<String, Fail> {
+        self.ipv4.tcp.dump_tcb(socket_fd)
+    }
+
+    pub fn tcp_set_connection_observer(&mut self, f: Box<dyn Fn(ConnEvent)>) {
+        self.ipv4.tcp.set_connection_observer(f)
+    }
+
+    pub fn set_packet_tap(&mut self, f: Box<dyn FnMut(Direction, &[u8])>) {
+        self.tap.set(f)
+    }
+
+    pub fn tcp_set_transmit_filter(&mut self, f: Box<dyn FnMut(&mut TcpSegment)>) {
+        self.ipv4.tcp.set_transmit_filter(f)
+    }
+
     pub async fn arp_query(&mut self, ipv4_addr: Ipv4Addr) -> Result<MacAddress, Fail> {
         self.arp.query(ipv4_addr, &Yielder::new()).await
     }
 
+    pub fn arp_resolve(&mut self, ipv4_addr: Ipv4Addr) -> Result<QToken, Fail> {
+        self.arp.resolve(ipv4_addr)
+    }
+
     pub fn tcp_mss(&self, handle: QDesc) -> Result<usize, Fail> {
         self.ipv4.tcp_mss(handle)
     }
 
+    pub fn tcp_send_queue_len(&self, handle: QDesc) -> Result<usize, Fail> {
+        self.ipv4.tcp.send_queue_len(handle)
+    }
+
+    pub fn tcp_set_quickack(&mut self, handle: QDesc, enable: bool) -> Result<(), Fail> {
+        self.ipv4.tcp.set_quickack(handle, enable)
+    }
+
+    pub fn tcp_set_rcvlowat(&mut self, handle: QDesc, bytes: usize) -> Result<(), Fail> {
+        self.ipv4.tcp.set_rcvlowat(handle, bytes)
+    }
+
+    pub fn tcp_flush_ack(&mut self, handle: QDesc) -> Result<(), Fail> {
+        self.ipv4.tcp.flush_ack(handle)
+    }
+
+    #[cfg(feature = "experimental")]
+    pub fn tcp_set_cwnd(&mut self, handle: QDesc, cwnd: u32) -> Result<(), Fail> {
+        self.ipv4.tcp.set_cwnd(handle, cwnd)
+    }
+
+    #[cfg(feature = "experimental")]
+    pub fn tcp_get_cwnd(&self, handle: QDesc) -> Result<u32, Fail> {
+        self.ipv4.tcp.get_cwnd(handle)
+    }
+
+    pub fn tcp_cork(&mut self, handle: QDesc, enable: bool) -> Result<(), Fail> {
+        self.ipv4.tcp.set_cork(handle, enable)
+    }
+
     pub fn tcp_rto(&self, handle: QDesc) -> Result<Duration, Fail> {
         self.ipv4.tcp_rto(handle)
     }
 
+    pub fn ipv4_reassembly_len(&self) -> usize {
+        self.ipv4.reassembly_len()
+    }
+
     pub fn export_arp_cache(&self) -> HashMap<Ipv4Addr, MacAddress> {
         self.arp.export_cache()
     }
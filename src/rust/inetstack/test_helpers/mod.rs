@@ -71,6 +71,22 @@ pub fn new_bob<const N: usize>(now: Instant) -> SharedEngine<N> {
 }
 
 pub fn new_alice2<const N: usize>(now: Instant) -> SharedEngine<N> {
+    new_alice2_with_tcp_config(now, TcpConfig::default())
+}
+
+pub fn new_alice2_with_tcp_config<const N: usize>(now: Instant, tcp_config: TcpConfig) -> SharedEngine<N> {
+    new_alice2_with_config(now, UdpConfig::default(), tcp_config)
+}
+
+pub fn new_alice2_with_udp_config<const N: usize>(now: Instant, udp_config: UdpConfig) -> SharedEngine<N> {
+    new_alice2_with_config(now, udp_config, TcpConfig::default())
+}
+
+fn new_alice2_with_config<const N: usize>(
+    now: Instant,
+    udp_config: UdpConfig,
+    tcp_config: TcpConfig,
+) -> SharedEngine<N> {
     let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::<Ipv4Addr, MacAddress>::new();
     arp.insert(ALICE_IPV4, ALICE_MAC);
     arp.insert(BOB_IPV4, BOB_MAC);
@@ -81,13 +97,15 @@ pub fn new_alice2<const N: usize>(now: Instant) -> SharedEngine<N> {
         Some(arp),
         Some(false),
     );
-    let udp_config = UdpConfig::default();
-    let tcp_config = TcpConfig::default();
     let test_rig = SharedTestRuntime::new(now, arp_config, udp_config, tcp_config, ALICE_MAC, ALICE_IPV4);
     SharedEngine::new(test_rig).unwrap()
 }
 
 pub fn new_bob2<const N: usize>(now: Instant) -> SharedEngine<N> {
+    new_bob2_with_tcp_config(now, TcpConfig::default())
+}
+
+pub fn new_bob2_with_tcp_config<const N: usize>(now: Instant, tcp_config: TcpConfig) -> SharedEngine<N> {
     let mut arp: HashMap<Ipv4Addr, MacAddress> = HashMap::<Ipv4Addr, MacAddress>::new();
     arp.insert(BOB_IPV4, BOB_MAC);
     arp.insert(ALICE_IPV4, ALICE_MAC);
@@ -99,7 +117,6 @@ pub fn new_bob2<const N: usize>(now: Instant) -> SharedEngine<N> {
         Some(false),
     );
     let udp_config = UdpConfig::default();
-    let tcp_config = TcpConfig::default();
     let test_rig = SharedTestRuntime::new(now, arp_config, udp_config, tcp_config, BOB_MAC, BOB_IPV4);
     SharedEngine::new(test_rig).unwrap()
 }
@@ -77,9 +77,10 @@ impl SharedTestRuntime {
         }))
     }
 
-    /// Remove a fixed number of frames from the runtime's outgoing queue.
+    /// Remove a fixed number of frames from the runtime's outgoing queue. The queue should hold at least that many.
     pub fn pop_frames(&mut self, num_frames: usize) -> VecDeque<DemiBuffer> {
         let length: usize = self.outgoing.len();
+        assert!(num_frames <= length, "should be at least {} frames (got {})", num_frames, length);
         self.outgoing.split_off(length - num_frames)
     }
 
@@ -94,7 +95,7 @@ impl SharedTestRuntime {
 
     /// Remove a single frame from the runtime's outgoing queue if it is not empty.
     pub fn pop_frame_unchecked(&mut self) -> Option<DemiBuffer> {
-        self.pop_frames(1).pop_front()
+        self.outgoing.pop_back()
     }
 
     /// Add a frame to the runtime's incoming queue.
@@ -65,6 +65,8 @@ pub use crate::runtime::{
     QDesc,
     QToken,
     QType,
+    QueueInfo,
+    Readiness,
 };
 
 pub mod demikernel;
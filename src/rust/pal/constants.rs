@@ -47,3 +47,15 @@ pub const SOCK_DGRAM: i32 = libc::SOCK_DGRAM;
 
 #[cfg(target_os = "linux")]
 pub const SOMAXCONN: i32 = libc::SOMAXCONN;
+
+/// TCP connection state in which our FIN was acknowledged but the peer has not closed yet (see `tcp_states.h`).
+#[cfg(target_os = "linux")]
+pub const TCP_STATE_FIN_WAIT2: i32 = 5;
+
+/// TCP connection state in which both sides of the connection were closed (see `tcp_states.h`).
+#[cfg(target_os = "linux")]
+pub const TCP_STATE_TIME_WAIT: i32 = 6;
+
+/// TCP connection state in which the connection is fully closed (see `tcp_states.h`).
+#[cfg(target_os = "linux")]
+pub const TCP_STATE_CLOSE: i32 = 7;
@@ -13,6 +13,7 @@ pub mod shm;
 //======================================================================================================================
 
 use ::std::{
+    fs,
     mem,
     net::SocketAddrV4,
 };
@@ -21,16 +22,41 @@ use ::std::{
 use ::std::{
     net::Ipv4Addr,
     os::unix::prelude::RawFd,
+    ptr,
 };
 
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+#[cfg(feature = "catcollar-libos")]
+/// Maximum length of a key of the TCP_MD5SIG option (TCP_MD5SIG_MAXKEYLEN).
+pub const TCP_MD5SIG_MAXKEYLEN: usize = 80;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+#[cfg(feature = "catcollar-libos")]
+/// Value of the TCP_MD5SIG option (struct tcp_md5sig).
+#[repr(C)]
+struct TcpMd5Sig {
+    tcpm_addr: libc::sockaddr_storage,
+    tcpm_flags: u8,
+    tcpm_prefixlen: u8,
+    tcpm_keylen: u16,
+    tcpm_ifindex: libc::c_int,
+    tcpm_key: [u8; TCP_MD5SIG_MAXKEYLEN],
+}
+
 //======================================================================================================================
 // Standalone Functions
 //======================================================================================================================
 
 #[cfg(feature = "catcollar-libos")]
 /// Sets TCP_NODELAY option in a socket.
-pub unsafe fn set_tcp_nodelay(fd: RawFd) -> i32 {
-    let value: u32 = 1;
+pub unsafe fn set_tcp_nodelay(fd: RawFd, enable: bool) -> i32 {
+    let value: u32 = enable as u32;
     let value_ptr: *const u32 = &value as *const u32;
     let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
     libc::setsockopt(
@@ -42,10 +68,40 @@ pub unsafe fn set_tcp_nodelay(fd: RawFd) -> i32 {
     )
 }
 
+#[cfg(feature = "catcollar-libos")]
+/// Sets TCP_QUICKACK option in a socket.
+pub unsafe fn set_tcp_quickack(fd: RawFd, enable: bool) -> i32 {
+    let value: u32 = enable as u32;
+    let value_ptr: *const u32 = &value as *const u32;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_QUICKACK,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Sets TCP_CORK option in a socket.
+pub unsafe fn set_tcp_cork(fd: RawFd, enable: bool) -> i32 {
+    let value: u32 = enable as u32;
+    let value_ptr: *const u32 = &value as *const u32;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_CORK,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
 #[cfg(feature = "catcollar-libos")]
 /// Sets SO_REUSEPORT option in a socket.
-pub unsafe fn set_so_reuseport(fd: RawFd) -> i32 {
-    let value: u32 = 1;
+pub unsafe fn set_so_reuseport(fd: RawFd, enable: bool) -> i32 {
+    let value: u32 = enable as u32;
     let value_ptr: *const u32 = &value as *const u32;
     let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
     libc::setsockopt(
@@ -72,6 +128,307 @@ pub unsafe fn set_nonblock(fd: RawFd) -> i32 {
     libc::fcntl(fd, libc::F_SETFL, flags, 1)
 }
 
+#[cfg(feature = "catcollar-libos")]
+/// Sets SO_LINGER option in a socket with a zero timeout, so that closing it resets the connection.
+pub unsafe fn set_so_linger_abort(fd: RawFd) -> i32 {
+    let value: libc::linger = libc::linger {
+        l_onoff: 1,
+        l_linger: 0,
+    };
+    let value_ptr: *const libc::linger = &value as *const libc::linger;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_LINGER,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Sets SO_PRIORITY option in a socket.
+pub unsafe fn set_so_priority(fd: RawFd, priority: u32) -> i32 {
+    let value: u32 = priority;
+    let value_ptr: *const u32 = &value as *const u32;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_PRIORITY,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Gets SO_PRIORITY option of a socket.
+pub unsafe fn get_so_priority(fd: RawFd) -> i32 {
+    let mut value: i32 = 0;
+    let value_ptr: *mut i32 = &mut value as *mut i32;
+    let mut option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    match libc::getsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_PRIORITY,
+        value_ptr as *mut libc::c_void,
+        &mut option_len as *mut libc::socklen_t,
+    ) {
+        0 => value,
+        _ => -1,
+    }
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Sets SO_RCVLOWAT option in a socket.
+pub unsafe fn set_so_rcvlowat(fd: RawFd, bytes: i32) -> i32 {
+    let value: i32 = bytes;
+    let value_ptr: *const i32 = &value as *const i32;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_RCVLOWAT,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Sets TCP_MD5SIG option in a socket, so that segments exchanged with [addr] are signed with [key]. Only the address of
+/// [addr] is taken into account, and an empty key removes the signature. [key] is at most [TCP_MD5SIG_MAXKEYLEN] long.
+pub unsafe fn set_tcp_md5sig(fd: RawFd, addr: &SocketAddrV4, key: &[u8]) -> i32 {
+    debug_assert!(key.len() <= TCP_MD5SIG_MAXKEYLEN);
+    let mut value: TcpMd5Sig = mem::zeroed();
+    let sin: libc::sockaddr_in = socketaddrv4_to_sockaddr_in(addr);
+    ptr::write(
+        &mut value.tcpm_addr as *mut libc::sockaddr_storage as *mut libc::sockaddr_in,
+        sin,
+    );
+    value.tcpm_keylen = key.len() as u16;
+    value.tcpm_key[..key.len()].copy_from_slice(key);
+    let value_ptr: *const TcpMd5Sig = &value as *const TcpMd5Sig;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_MD5SIG,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Sets SO_BINDTODEVICE option in a socket. An empty interface name removes the binding.
+pub unsafe fn set_so_bindtodevice(fd: RawFd, ifname: &[u8]) -> i32 {
+    let value_ptr: *const u8 = ifname.as_ptr();
+    let option_len: libc::socklen_t = ifname.len() as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_BINDTODEVICE,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Gets SO_BINDTODEVICE option of a socket, which is the NUL-terminated name of the interface that it is bound to. On
+/// success, the number of bytes written to [ifname] is returned, or zero if the socket is not bound to an interface.
+pub unsafe fn get_so_bindtodevice(fd: RawFd, ifname: &mut [u8; libc::IFNAMSIZ]) -> i32 {
+    let value_ptr: *mut u8 = ifname.as_mut_ptr();
+    let mut option_len: libc::socklen_t = mem::size_of_val(ifname) as libc::socklen_t;
+    match libc::getsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_BINDTODEVICE,
+        value_ptr as *mut libc::c_void,
+        &mut option_len as *mut libc::socklen_t,
+    ) {
+        0 => option_len as i32,
+        _ => -1,
+    }
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Gets SO_ACCEPTCONN option of a socket, which tells whether or not it is listening for connections.
+pub unsafe fn get_so_acceptconn(fd: RawFd) -> i32 {
+    let mut value: i32 = 0;
+    let value_ptr: *mut i32 = &mut value as *mut i32;
+    let mut option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    match libc::getsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_ACCEPTCONN,
+        value_ptr as *mut libc::c_void,
+        &mut option_len as *mut libc::socklen_t,
+    ) {
+        0 => value,
+        _ => -1,
+    }
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Gets TCP_MAXSEG option of a socket, which is the maximum segment size of its connection.
+pub unsafe fn get_tcp_maxseg(fd: RawFd) -> i32 {
+    let mut value: i32 = 0;
+    let value_ptr: *mut i32 = &mut value as *mut i32;
+    let mut option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    match libc::getsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_MAXSEG,
+        value_ptr as *mut libc::c_void,
+        &mut option_len as *mut libc::socklen_t,
+    ) {
+        0 => value,
+        _ => -1,
+    }
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Gets the state of the TCP connection of a socket, as reported by TCP_INFO.
+pub unsafe fn get_tcp_state(fd: RawFd) -> i32 {
+    let mut value: libc::tcp_info = mem::zeroed();
+    let value_ptr: *mut libc::tcp_info = &mut value as *mut libc::tcp_info;
+    let mut option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    match libc::getsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_INFO,
+        value_ptr as *mut libc::c_void,
+        &mut option_len as *mut libc::socklen_t,
+    ) {
+        0 => value.tcpi_state as i32,
+        _ => -1,
+    }
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Sets IP_RECVERR option in a socket.
+pub unsafe fn set_ip_recverr(fd: RawFd, enable: bool) -> i32 {
+    let value: u32 = enable as u32;
+    let value_ptr: *const u32 = &value as *const u32;
+    let option_len: libc::socklen_t = mem::size_of_val(&value) as libc::socklen_t;
+    libc::setsockopt(
+        fd,
+        libc::IPPROTO_IP,
+        libc::IP_RECVERR,
+        value_ptr as *const libc::c_void,
+        option_len,
+    )
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Dequeues the oldest error from the error queue of a socket that has IP_RECVERR set. On success, the error number
+/// that was reported is returned, or zero if the error queue is empty. On failure, -1 is returned.
+pub unsafe fn recv_ip_error(fd: RawFd) -> i32 {
+    let mut control: [u64; 64] = [0; 64];
+    let mut msg: libc::msghdr = mem::zeroed();
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    if libc::recvmsg(
+        fd,
+        &mut msg as *mut libc::msghdr,
+        libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT,
+    ) < 0
+    {
+        return match *libc::__errno_location() {
+            libc::EAGAIN => 0,
+            _ => -1,
+        };
+    }
+
+    let mut cmsg: *mut libc::cmsghdr = libc::CMSG_FIRSTHDR(&msg as *const libc::msghdr);
+    while !cmsg.is_null() {
+        if (*cmsg).cmsg_level == libc::IPPROTO_IP && (*cmsg).cmsg_type == libc::IP_RECVERR {
+            let err: libc::sock_extended_err =
+                ::std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::sock_extended_err);
+            return err.ee_errno as i32;
+        }
+        cmsg = libc::CMSG_NXTHDR(&msg as *const libc::msghdr, cmsg);
+    }
+    0
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Receives up to one datagram per buffer in [bufs] from a socket at once, without blocking (recvmmsg). On success, the
+/// number of datagrams received is returned, and the source address and length of each of them are appended to
+/// [received]. On failure, -1 is returned.
+pub unsafe fn recv_mmsg(fd: RawFd, bufs: &mut [&mut [u8]], received: &mut Vec<(SocketAddrV4, usize)>) -> i32 {
+    let mut iovecs: Vec<libc::iovec> = bufs
+        .iter_mut()
+        .map(|buf| libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        })
+        .collect();
+    let mut addrs: Vec<libc::sockaddr_in> = vec![mem::zeroed(); bufs.len()];
+    let mut msgs: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .zip(addrs.iter_mut())
+        .map(|(iovec, addr)| {
+            let mut msg: libc::mmsghdr = mem::zeroed();
+            msg.msg_hdr.msg_name = addr as *mut libc::sockaddr_in as *mut libc::c_void;
+            msg.msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+            msg.msg_hdr.msg_iov = iovec as *mut libc::iovec;
+            msg.msg_hdr.msg_iovlen = 1;
+            msg
+        })
+        .collect();
+
+    let count: i32 = libc::recvmmsg(
+        fd,
+        msgs.as_mut_ptr(),
+        msgs.len() as libc::c_uint,
+        libc::MSG_DONTWAIT,
+        ::std::ptr::null_mut(),
+    );
+    if count < 0 {
+        return -1;
+    }
+    for (msg, addr) in msgs.iter().zip(addrs.iter()).take(count as usize) {
+        received.push((sockaddr_in_to_socketaddrv4(addr), msg.msg_len as usize));
+    }
+    count
+}
+
+#[cfg(feature = "catcollar-libos")]
+/// Sends each buffer in [items] as a datagram to its paired address at once, without blocking (sendmmsg). On success,
+/// the number of leading datagrams that were sent is returned. On failure, -1 is returned.
+pub unsafe fn send_mmsg(fd: RawFd, items: &[(&[u8], SocketAddrV4)]) -> i32 {
+    let mut iovecs: Vec<libc::iovec> = items
+        .iter()
+        .map(|(buf, _)| libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        })
+        .collect();
+    let mut addrs: Vec<libc::sockaddr_in> = items
+        .iter()
+        .map(|(_, addr)| socketaddrv4_to_sockaddr_in(addr))
+        .collect();
+    let mut msgs: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .zip(addrs.iter_mut())
+        .map(|(iovec, addr)| {
+            let mut msg: libc::mmsghdr = mem::zeroed();
+            msg.msg_hdr.msg_name = addr as *mut libc::sockaddr_in as *mut libc::c_void;
+            msg.msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+            msg.msg_hdr.msg_iov = iovec as *mut libc::iovec;
+            msg.msg_hdr.msg_iovlen = 1;
+            msg
+        })
+        .collect();
+
+    let count: i32 = libc::sendmmsg(fd, msgs.as_mut_ptr(), msgs.len() as libc::c_uint, libc::MSG_DONTWAIT);
+    if count < 0 {
+        return -1;
+    }
+    count
+}
+
 /// Converts a [std::net::SocketAddrV4] to a [libc::sockaddr_in].
 fn socketaddrv4_to_sockaddr_in(addr: &SocketAddrV4) -> libc::sockaddr_in {
     libc::sockaddr_in {
@@ -98,6 +455,33 @@ fn sockaddr_in_to_socketaddrv4(sin: &libc::sockaddr_in) -> SocketAddrV4 {
     )
 }
 
+/// Pins the calling thread to CPU core `core_id`.
+pub unsafe fn set_cpu_affinity(core_id: usize) -> i32 {
+    let mut cpuset: libc::cpu_set_t = mem::zeroed();
+    libc::CPU_SET(core_id, &mut cpuset);
+    libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &cpuset)
+}
+
+/// Gets the set of CPU cores that the calling thread may run on.
+pub unsafe fn get_cpu_affinity(cpuset: &mut libc::cpu_set_t) -> i32 {
+    libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), cpuset)
+}
+
+/// Gets the ids of the online CPU cores, which need not be numbered contiguously. Returns `None` if the list of online
+/// cores cannot be read or parsed.
+pub fn get_online_cpus() -> Option<Vec<usize>> {
+    let online: String = fs::read_to_string("/sys/devices/system/cpu/online").ok()?;
+    let mut core_ids: Vec<usize> = Vec::new();
+    // The list is made of comma-separated core ids and inclusive ranges of core ids (e.g. "0-3,6,8-9").
+    for range in online.trim().split(',') {
+        match range.split_once('-') {
+            Some((first, last)) => core_ids.extend(first.parse::<usize>().ok()?..=last.parse::<usize>().ok()?),
+            None => core_ids.push(range.parse::<usize>().ok()?),
+        }
+    }
+    Some(core_ids)
+}
+
 /// Converts a [std::net::SocketAddrV4] to a [libc::sockaddr].
 pub fn socketaddrv4_to_sockaddr(addr: &SocketAddrV4) -> libc::sockaddr {
     let sin: libc::sockaddr_in = socketaddrv4_to_sockaddr_in(addr);
@@ -110,3 +494,96 @@ pub fn sockaddr_to_socketaddrv4(saddr: &libc::sockaddr) -> SocketAddrV4 {
     let sin: libc::sockaddr_in = unsafe { mem::transmute::<libc::sockaddr, libc::sockaddr_in>(saddr.to_owned()) };
     sockaddr_in_to_socketaddrv4(&sin)
 }
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(all(test, feature = "catcollar-libos"))]
+mod tests {
+    use super::{
+        recv_mmsg,
+        send_mmsg,
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        net::{
+            SocketAddrV4,
+            UdpSocket,
+        },
+        os::unix::prelude::AsRawFd,
+    };
+
+    /// Tests if all datagrams of a burst are received at once.
+    #[test]
+    fn recv_mmsg_burst() -> Result<()> {
+        let receiver: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+        let sender: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+        let sender_addr: SocketAddrV4 = match sender.local_addr()? {
+            ::std::net::SocketAddr::V4(addr) => addr,
+            addr => anyhow::bail!("unexpected address (addr={:?})", addr),
+        };
+        for i in 0..8u8 {
+            sender.send_to(&vec![i; 16 + i as usize], receiver.local_addr()?)?;
+        }
+
+        // Leave room for more datagrams than there are.
+        let mut bufs: Vec<Vec<u8>> = vec![vec![0; 64]; 16];
+        let mut slices: Vec<&mut [u8]> = bufs.iter_mut().map(|buf| &mut buf[..]).collect();
+        let mut received: Vec<(SocketAddrV4, usize)> = Vec::new();
+        let count: i32 = unsafe { recv_mmsg(receiver.as_raw_fd(), &mut slices, &mut received) };
+        crate::ensure_eq!(count, 8);
+        crate::ensure_eq!(received.len(), 8);
+        for (i, (addr, len)) in received.iter().enumerate() {
+            crate::ensure_eq!(*addr, sender_addr);
+            crate::ensure_eq!(*len, 16 + i);
+            crate::ensure_eq!(bufs[i][..*len].iter().all(|b| *b == i as u8), true);
+        }
+
+        // Nothing is left, so we should not block.
+        received.clear();
+        let mut slices: Vec<&mut [u8]> = bufs.iter_mut().map(|buf| &mut buf[..]).collect();
+        crate::ensure_eq!(
+            unsafe { recv_mmsg(receiver.as_raw_fd(), &mut slices, &mut received) },
+            -1
+        );
+        crate::ensure_eq!(received.is_empty(), true);
+
+        Ok(())
+    }
+
+    /// Tests if all datagrams of a batch reach their respective destinations.
+    #[test]
+    fn send_mmsg_batch() -> Result<()> {
+        let sender: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+        let mut receivers: Vec<(UdpSocket, SocketAddrV4)> = Vec::new();
+        for _ in 0..4 {
+            let receiver: UdpSocket = UdpSocket::bind("127.0.0.1:0")?;
+            receiver.set_read_timeout(Some(::std::time::Duration::from_secs(1)))?;
+            let addr: SocketAddrV4 = match receiver.local_addr()? {
+                ::std::net::SocketAddr::V4(addr) => addr,
+                addr => anyhow::bail!("unexpected address (addr={:?})", addr),
+            };
+            receivers.push((receiver, addr));
+        }
+
+        // Send two datagrams to each receiver.
+        let payloads: Vec<Vec<u8>> = (0..8u8).map(|i| vec![i; 16 + i as usize]).collect();
+        let items: Vec<(&[u8], SocketAddrV4)> = payloads
+            .iter()
+            .enumerate()
+            .map(|(i, payload)| (&payload[..], receivers[i % receivers.len()].1))
+            .collect();
+        let count: i32 = unsafe { send_mmsg(sender.as_raw_fd(), &items) };
+        crate::ensure_eq!(count, 8);
+
+        let mut buf: [u8; 64] = [0; 64];
+        for (i, payload) in payloads.iter().enumerate() {
+            let (receiver, _): &(UdpSocket, SocketAddrV4) = &receivers[i % receivers.len()];
+            let len: usize = receiver.recv(&mut buf)?;
+            crate::ensure_eq!(&buf[..len], &payload[..]);
+        }
+
+        Ok(())
+    }
+}
@@ -48,6 +48,21 @@ impl SharedMemory {
 
     /// Opens an existing named shared memory region.
     pub fn open(name: &str, len: usize) -> Result<SharedMemory, Fail> {
+        let mut shm: SharedMemory = Self::do_open(name)?;
+        shm.map(len)?;
+        Ok(shm)
+    }
+
+    /// Opens an existing named shared memory region and maps all of it, whatever its size.
+    pub fn open_whole(name: &str) -> Result<SharedMemory, Fail> {
+        let mut shm: SharedMemory = Self::do_open(name)?;
+        let len: usize = shm.stat_size()?;
+        shm.map(len)?;
+        Ok(shm)
+    }
+
+    /// Opens an existing named shared memory region, without mapping it.
+    fn do_open(name: &str) -> Result<SharedMemory, Fail> {
         let name: ffi::CString = Self::build_name(name)?;
         let fd: libc::c_int = unsafe {
             // Forward request to underlying POSIX OS.
@@ -56,10 +71,7 @@ impl SharedMemory {
             // Check for failure return value.
             if ret == -1 {
                 let errno: libc::c_int = *libc::__errno_location();
-                let cause: String = format!(
-                    "failed to open shared memory region (name={:?}, len={}, errno={})",
-                    name, len, errno
-                );
+                let cause: String = format!("failed to open shared memory region (name={:?}, errno={})", name, errno);
                 error!("open(): {}", cause);
                 return Err(Fail::new(errno, &cause));
             }
@@ -67,17 +79,13 @@ impl SharedMemory {
             ret
         };
 
-        let mut shm: SharedMemory = SharedMemory {
+        Ok(SharedMemory {
             was_created: false,
             fd,
             name,
             size: 0,
             addr: ptr::null_mut(),
-        };
-
-        shm.map(len)?;
-
-        Ok(shm)
+        })
     }
 
     /// Creates a named shared memory region.
@@ -165,6 +173,20 @@ impl SharedMemory {
         Ok(())
     }
 
+    /// Gets the size of the target shared memory region, as set by its creator.
+    fn stat_size(&self) -> Result<usize, Fail> {
+        // Forward request to underlying POSIX OS.
+        let mut stat: libc::stat = unsafe { mem::zeroed() };
+        let ret: libc::c_int = unsafe { libc::fstat(self.fd, &mut stat) };
+
+        // Check for failure return value.
+        if ret == -1 {
+            return Err(Fail::new(libc::EAGAIN, "failed to get size of shared memory region"));
+        }
+
+        Ok(stat.st_size as usize)
+    }
+
     /// Maps the target shared memory region to the address space of the calling process.
     fn map(&mut self, size: usize) -> Result<(), Fail> {
         // Forward request to underlying POSIX OS.
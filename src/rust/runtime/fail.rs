@@ -7,7 +7,13 @@
 
 use ::libc::{
     c_int,
+    EAGAIN,
+    ECONNREFUSED,
+    ECONNRESET,
+    EINTR,
     EIO,
+    EPIPE,
+    EWOULDBLOCK,
 };
 use ::std::{
     error,
@@ -41,6 +47,17 @@ impl Fail {
             cause: cause.to_string(),
         }
     }
+
+    /// Checks if the target failure is transient, that is, if retrying the operation that caused it may succeed.
+    pub fn is_transient(&self) -> bool {
+        self.errno == EAGAIN || self.errno == EWOULDBLOCK || self.errno == EINTR
+    }
+
+    /// Checks if the target failure means that the connection is gone, because the remote end reset it, refused it, or
+    /// can no longer be written to.
+    pub fn is_connection_error(&self) -> bool {
+        self.errno == ECONNRESET || self.errno == EPIPE || self.errno == ECONNREFUSED
+    }
 }
 
 //==============================================================================
@@ -73,3 +90,35 @@ impl From<io::Error> for Fail {
         }
     }
 }
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::Fail;
+    use ::anyhow::Result;
+
+    /// Tests if failures are classified according to their error code.
+    #[test]
+    fn classify_failures() -> Result<()> {
+        for errno in [libc::EAGAIN, libc::EWOULDBLOCK, libc::EINTR] {
+            let fail: Fail = Fail::new(errno, "transient");
+            crate::ensure_eq!(fail.is_transient(), true);
+            crate::ensure_eq!(fail.is_connection_error(), false);
+        }
+        for errno in [libc::ECONNRESET, libc::EPIPE, libc::ECONNREFUSED] {
+            let fail: Fail = Fail::new(errno, "connection error");
+            crate::ensure_eq!(fail.is_transient(), false);
+            crate::ensure_eq!(fail.is_connection_error(), true);
+        }
+        for errno in [libc::EINVAL, libc::EBADF, libc::ETIMEDOUT, libc::EIO] {
+            let fail: Fail = Fail::new(errno, "fatal");
+            crate::ensure_eq!(fail.is_transient(), false);
+            crate::ensure_eq!(fail.is_connection_error(), false);
+        }
+
+        Ok(())
+    }
+}
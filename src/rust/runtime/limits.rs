@@ -8,3 +8,20 @@ pub const RECVBUF_SIZE_MAX: usize = 8192;
 /// Maximum size for a fixed-size pop operation.
 /// This is set to be at most `RECVBUF_SIZE_MAX`.
 pub const POP_SIZE_MAX: usize = RECVBUF_SIZE_MAX;
+
+/// Maximum number of datagrams for a batched pop operation.
+/// This is set to match the largest batch that recvmmsg() takes on Linux.
+pub const POP_BATCH_MAX: usize = 1024;
+
+/// Maximum number of datagrams for a batched push operation.
+/// This is set to match the largest batch that sendmmsg() takes on Linux.
+pub const PUSH_BATCH_MAX: usize = 1024;
+
+/// Maximum number of receive buffers in the backlog of a queue in continuous receive mode.
+/// This caps the memory that the backlog of a single queue may hold on to at 8 MB, that is, 1024 buffers of
+/// `RECVBUF_SIZE_MAX` bytes.
+pub const RECV_BACKLOG_MAX: usize = 1024;
+
+/// Default maximum number of I/O queues that may be open at once.
+/// This is set to be generous enough to not get in the way of any reasonable application.
+pub const QUEUES_MAX: usize = 1 << 20;
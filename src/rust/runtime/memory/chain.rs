@@ -0,0 +1,143 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::memory::DemiBuffer;
+use ::std::{
+    slice,
+    vec,
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Chain of buffers that logically concatenates them. This lets a message be built out of several buffers (e.g., a
+/// header and a body) and be pushed segment by segment, without first copying them into a single buffer.
+#[derive(Clone, Default)]
+pub struct DemiBufferChain {
+    /// Buffers of the chain, in order. None of them is empty.
+    bufs: Vec<DemiBuffer>,
+    /// Total length of the chain (in bytes).
+    len: usize,
+}
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+/// Associated Functions for Buffer Chains
+impl DemiBufferChain {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `buf` to the end of the target chain. Empty buffers are skipped, as they carry no data.
+    pub fn push(&mut self, buf: DemiBuffer) {
+        if buf.len() > 0 {
+            self.len += buf.len();
+            self.bufs.push(buf);
+        }
+    }
+
+    /// Returns the total length of the target chain (in bytes).
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the target chain holds no data.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of buffers in the target chain.
+    pub fn num_segments(&self) -> usize {
+        self.bufs.len()
+    }
+
+    /// Iterates over the buffers of the target chain, in order.
+    pub fn iter(&self) -> slice::Iter<'_, DemiBuffer> {
+        self.bufs.iter()
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// From Trait Implementation for Buffer Chains
+impl From<DemiBuffer> for DemiBufferChain {
+    fn from(buf: DemiBuffer) -> Self {
+        let mut chain: DemiBufferChain = DemiBufferChain::new();
+        chain.push(buf);
+        chain
+    }
+}
+
+/// From Iterator Trait Implementation for Buffer Chains
+impl FromIterator<DemiBuffer> for DemiBufferChain {
+    fn from_iter<I: IntoIterator<Item = DemiBuffer>>(iter: I) -> Self {
+        let mut chain: DemiBufferChain = DemiBufferChain::new();
+        for buf in iter {
+            chain.push(buf);
+        }
+        chain
+    }
+}
+
+/// Into Iterator Trait Implementation for Buffer Chains
+impl IntoIterator for DemiBufferChain {
+    type IntoIter = vec::IntoIter<DemiBuffer>;
+    type Item = DemiBuffer;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.bufs.into_iter()
+    }
+}
+
+/// Into Iterator Trait Implementation for References to Buffer Chains
+impl<'a> IntoIterator for &'a DemiBufferChain {
+    type IntoIter = slice::Iter<'a, DemiBuffer>;
+    type Item = &'a DemiBuffer;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::DemiBufferChain;
+    use crate::{
+        ensure_eq,
+        runtime::memory::DemiBuffer,
+    };
+    use ::anyhow::Result;
+
+    /// Tests that a chain hands out the very buffers that were appended to it, in order.
+    #[test]
+    fn buffer_chain_keeps_buffers() -> Result<()> {
+        let header: DemiBuffer = DemiBuffer::from_slice(&[1, 2])?;
+        let body: DemiBuffer = DemiBuffer::from_slice(&[3, 4, 5])?;
+        let ptrs: Vec<*const u8> = vec![header.as_ptr(), body.as_ptr()];
+
+        let mut chain: DemiBufferChain = DemiBufferChain::from(header);
+        chain.push(DemiBuffer::new(0));
+        chain.push(body);
+        ensure_eq!(chain.len(), 5);
+        ensure_eq!(chain.num_segments(), 2);
+        ensure_eq!(chain.iter().map(|buf| buf.as_ptr()).collect::<Vec<*const u8>>(), ptrs);
+        let data: Vec<u8> = chain.iter().flat_map(|buf| buf[..].to_vec()).collect();
+        ensure_eq!(data, vec![1, 2, 3, 4, 5]);
+
+        Ok(())
+    }
+}
@@ -8,7 +8,9 @@
 //
 // The constructors/destructors, however, are necessarily different.  For DPDK-allocated buffers, a MBuf is expected
 // to be allocated externally and provided to the DemiBuffer's "from_mbuf" constructor.  A MBuf can also be extracted
-// from a DPDK allocated DemiBuffer via the "into_mbuf" routine.
+// from a DPDK allocated DemiBuffer via the "into_mbuf" routine.  Heap-allocated DemiBuffers may also wrap memory that
+// is owned by someone else via the "from_external" constructor, in which case that memory is handed back to its owner
+// (rather than freed) once no DemiBuffer refers to it anymore.
 //
 // Note: if compiled without the "libdpdk" feature defined, the DPDK-specific functionality won't be present.
 
@@ -35,12 +37,18 @@ use crate::{
 #[cfg(feature = "libdpdk")]
 use ::dpdk_rs::{
     rte_mbuf,
+    rte_mbuf_refcnt_read,
     rte_mempool,
     rte_pktmbuf_adj,
     rte_pktmbuf_clone,
     rte_pktmbuf_free,
     rte_pktmbuf_trim,
 };
+#[cfg(feature = "buffer-accounting")]
+use ::std::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+};
 use ::std::{
     alloc::{
         alloc,
@@ -94,7 +102,8 @@ struct MetaData {
     _port: u16,
 
     // Offload features.
-    // Note, despite the "offload" name, the indirect buffer flag (METADATA_F_INDIRECT) lives here.
+    // Note, despite the "offload" name, the indirect and external buffer flags (METADATA_F_INDIRECT and
+    // METADATA_F_EXTERNAL) live here.
     ol_flags: u64,
 
     // L2/L3/L4 and tunnel information.
@@ -149,6 +158,20 @@ const _: () = assert!(std::mem::size_of::<MetaData>() == 2 * arch::CPU_DATA_CACH
 // points to another MetaData's directly attached data.
 const METADATA_F_INDIRECT: u64 = 1 << 62;
 
+// Indicates this MetaData's buf_addr points to data that is owned by someone else, rather than to data that is attached
+// to a MetaData.  The reference count on that data lives in the ExternalSharedInfo struct that _shinfo points to.
+const METADATA_F_EXTERNAL: u64 = 1 << 61;
+
+// Shared info of external data.
+// This is akin to DPDK's rte_mbuf_ext_shared_info.  All MetaData structs attached to the same external data point to
+// it, and it is freed along with the last reference to that data.
+struct ExternalSharedInfo {
+    // Reference counter.
+    refcnt: u16,
+    // Callback that hands the data back to its owner, once it is no longer referenced.
+    free_cb: Box<dyn FnOnce() + Send>,
+}
+
 impl MetaData {
     // Note on Reference Counts:
     // Since we are currently single-threaded, there is no need to use atomic operations for refcnt manipulations.
@@ -172,6 +195,16 @@ impl MetaData {
         self.refcnt
     }
 
+    // Gets the shared info of the external data this MetaData is attached to.
+    // Note: Must only be called on MetaData structs that have the METADATA_F_EXTERNAL flag set.
+    #[inline]
+    fn get_shinfo(&mut self) -> &mut ExternalSharedInfo {
+        debug_assert_ne!(self.ol_flags & METADATA_F_EXTERNAL, 0);
+        // Safety: This is safe, as _shinfo points to a live ExternalSharedInfo struct for as long as this MetaData
+        // holds a reference on the external data.
+        unsafe { &mut *(self._shinfo as *mut ExternalSharedInfo) }
+    }
+
     // Gets the MetaData for the last segment in the buffer chain.
     #[inline]
     fn get_last_segment(&mut self) -> &mut MetaData {
@@ -185,6 +218,27 @@ impl MetaData {
     }
 }
 
+// Heap memory accounting.
+// When compiled with the "buffer-accounting" feature, we keep track of the number of bytes currently allocated off of
+// the heap for DemiBuffers (MetaData included), as well as the highest value this has ever reached.  DPDK-allocated
+// MBufs are not accounted for, as they come from pre-allocated memory pools.
+#[cfg(feature = "buffer-accounting")]
+static BUFFER_MEMORY_USAGE: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "buffer-accounting")]
+static BUFFER_MEMORY_PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of bytes currently allocated off of the heap for `DemiBuffer`s.
+#[cfg(feature = "buffer-accounting")]
+pub fn buffer_memory_usage() -> usize {
+    BUFFER_MEMORY_USAGE.load(Ordering::Relaxed)
+}
+
+/// Returns the highest number of bytes ever allocated off of the heap for `DemiBuffer`s at the same time.
+#[cfg(feature = "buffer-accounting")]
+pub fn buffer_memory_peak() -> usize {
+    BUFFER_MEMORY_PEAK.load(Ordering::Relaxed)
+}
+
 // DemiBuffer type tags.
 // Since our MetaData structure is 64-byte aligned, the lower 6 bits of a pointer to it are guaranteed to be zero.
 // We currently only use the lower 2 of those bits to hold the type tag.
@@ -308,6 +362,55 @@ impl DemiBuffer {
         slice.try_into()
     }
 
+    /// Creates a `DemiBuffer` that wraps `len` bytes of memory at `ptr` that is owned by someone else, without copying
+    /// them. The memory is never freed by the `DemiBuffer`: `on_drop` is invoked instead, exactly once, when the
+    /// `DemiBuffer` and all of its clones are dropped, so that the owner may reclaim it.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that `ptr` is valid for reads of `len` bytes (and for writes, if the data is ever
+    /// mutated through the `DemiBuffer`) until `on_drop` is invoked, and that the memory is not mutated through other
+    /// means in the meantime. Note that `on_drop` runs on whichever thread drops the last reference to the memory.
+    pub unsafe fn from_external(ptr: NonNull<u8>, len: u16, on_drop: impl FnOnce() + Send + 'static) -> Self {
+        // Allocate some memory off the heap for the MetaData only, as the data lives elsewhere.
+        let mut temp: NonNull<MetaData> = allocate_metadata_data(0);
+
+        // Allocate the shared info, which holds the reference count on the external data.
+        let shinfo: Box<ExternalSharedInfo> = Box::new(ExternalSharedInfo {
+            refcnt: 1,
+            free_cb: Box::new(on_drop),
+        });
+
+        // Initialize the MetaData.
+        {
+            // Safety: This is safe, as temp is aligned, dereferenceable, and metadata isn't aliased in this block.
+            let metadata: &mut MetaData = temp.as_mut();
+
+            // Point buf_addr at the external data.
+            metadata.buf_addr = ptr.as_ptr();
+
+            // Set field values as appropriate.
+            metadata.data_off = 0;
+            metadata.refcnt = 1;
+            metadata.nb_segs = 1;
+            metadata.ol_flags = METADATA_F_EXTERNAL;
+            metadata.pkt_len = len as u32;
+            metadata.data_len = len;
+            metadata.buf_len = len;
+            metadata.next = None;
+            metadata._shinfo = Box::into_raw(shinfo) as u64;
+        }
+
+        // Embed the buffer type into the lower bits of the pointer.
+        let tagged: NonNull<MetaData> = temp.with_addr(temp.addr() | Tag::Heap);
+
+        // Return the new DemiBuffer.
+        DemiBuffer {
+            tagged_ptr: tagged,
+            _phantom: PhantomData,
+        }
+    }
+
     /// Creates a `DemiBuffer` from a raw pointer.
     pub unsafe fn from_raw(token: NonNull<u8>) -> Self {
         DemiBuffer {
@@ -353,6 +456,82 @@ impl DemiBuffer {
         self.as_metadata().data_len as usize
     }
 
+    /// Returns `true` if the data of this `DemiBuffer` is also referenced by other `DemiBuffer`s (e.g. its clones), and
+    /// `false` otherwise.
+    // Note that only the first segment of a buffer chain is checked.
+    pub fn is_shared(&self) -> bool {
+        match self.get_tag() {
+            Tag::Heap => {
+                let metadata: &mut MetaData = self.as_metadata();
+                if metadata.ol_flags & METADATA_F_EXTERNAL != 0 {
+                    metadata.get_shinfo().refcnt > 1
+                } else {
+                    metadata.refcnt > 1
+                }
+            },
+            // Safety: This is safe, as the MBuf pointer is known to point to a valid MBuf.
+            #[cfg(feature = "libdpdk")]
+            Tag::Dpdk => unsafe { rte_mbuf_refcnt_read(self.as_mbuf()) > 1 },
+        }
+    }
+
+    /// Restores the full data area of the `DemiBuffer`, so that it can be handed out again as a new buffer. Returns
+    /// `true` on success, and `false` if the `DemiBuffer` cannot be reused this way because it was not allocated off of
+    /// the heap, does not own its data, is shared, is a chain, or its data area does not hold `capacity` bytes.
+    pub fn reset(&mut self, capacity: u16) -> bool {
+        if self.get_tag() != Tag::Heap {
+            return false;
+        }
+        let metadata: &mut MetaData = self.as_metadata();
+        if metadata.refcnt != 1
+            || metadata.ol_flags & (METADATA_F_INDIRECT | METADATA_F_EXTERNAL) != 0
+            || metadata.next.is_some()
+            || metadata.buf_len != capacity
+        {
+            return false;
+        }
+        metadata.data_off = 0;
+        metadata.data_len = capacity;
+        metadata.pkt_len = capacity as u32;
+        true
+    }
+
+    /// Appends `data` to the end of the `DemiBuffer`, in the unused space that follows its data. This mirrors DPDK's
+    /// rte_pktmbuf_append() routine. Fails if the `DemiBuffer` was not allocated off of the heap, does not own its data,
+    /// is shared, is a chain, or does not have enough room left for `data`.
+    pub fn append(&mut self, data: &[u8]) -> Result<(), Fail> {
+        if self.get_tag() != Tag::Heap || self.is_shared() {
+            return Err(Fail::new(
+                libc::EINVAL,
+                "cannot append to a buffer that is not exclusively owned",
+            ));
+        }
+        let offset: usize = {
+            let metadata: &mut MetaData = self.as_metadata();
+            if metadata.ol_flags & (METADATA_F_INDIRECT | METADATA_F_EXTERNAL) != 0 || metadata.next.is_some() {
+                return Err(Fail::new(
+                    libc::EINVAL,
+                    "cannot append to a buffer that is not exclusively owned",
+                ));
+            }
+            let tailroom: usize = metadata.buf_len as usize - (metadata.data_off + metadata.data_len) as usize;
+            if data.len() > tailroom {
+                return Err(Fail::new(
+                    libc::EINVAL,
+                    "tried to append more bytes than there is room for",
+                ));
+            }
+            // The above check against the tailroom also means that the length of data is <= u16::MAX.
+            let offset: usize = metadata.data_len as usize;
+            metadata.data_len += data.len() as u16;
+            metadata.pkt_len += data.len() as u32;
+            offset
+        };
+        self[offset..].copy_from_slice(data);
+
+        Ok(())
+    }
+
     /// Removes `nbytes` bytes from the beginning of the `DemiBuffer` chain.
     // Note: If `nbytes` is greater than the length of the first segment in the chain, then this function will fail and
     // return an error, rather than remove the remaining bytes from subsequent segments in the chain.  This is to match
@@ -472,6 +651,30 @@ impl DemiBuffer {
         self.split(true, offset)
     }
 
+    ///
+    /// **Description**
+    ///
+    /// Splits the target [DemiBuffer] at the given index `at`, leaving bytes `[0, at)` in the target [DemiBuffer] and
+    /// returning a new [DemiBuffer] containing bytes `[at, len)`. This mirrors [Vec::split_off], and is the same as
+    /// [split_back](Self::split_back).
+    ///
+    /// No data is copied: both [DemiBuffer]s reference the same underlying allocation, which is only released once
+    /// both of them are dropped. Either of them may be further adjusted, trimmed, or split independently.
+    ///
+    /// **Return Value**
+    ///
+    /// On successful completion, a new [DemiBuffer] containing the data after the split point is returned.  On failure,
+    /// a [Fail] structure encoding the failure condition is returned instead.
+    ///
+    /// **Notes**
+    ///
+    /// - The target [DemiBuffer] must be a single buffer segment (not a chain).
+    /// - The target [DemiBuffer] should be large enough to hold `at`.
+    ///
+    pub fn split_off(&mut self, at: usize) -> Result<Self, Fail> {
+        self.split_back(at)
+    }
+
     ///
     /// **Description**
     ///
@@ -664,6 +867,12 @@ fn allocate_metadata_data(direct_data_size: u16) -> NonNull<MetaData> {
         handle_alloc_error(layout);
     }
 
+    #[cfg(feature = "buffer-accounting")]
+    {
+        let usage: usize = BUFFER_MEMORY_USAGE.fetch_add(amount, Ordering::Relaxed) + amount;
+        BUFFER_MEMORY_PEAK.fetch_max(usage, Ordering::Relaxed);
+    }
+
     let metadata: *mut MetaData = allocation.cast::<MetaData>();
 
     // Initialize select MetaData fields in debug builds for sanity checking.
@@ -703,6 +912,9 @@ fn free_metadata_data(buffer: NonNull<MetaData>) {
 
     // Safety: this is safe because we're using the same (de)allocator and Layout used for allocation.
     unsafe { dealloc(allocation, layout) };
+
+    #[cfg(feature = "buffer-accounting")]
+    BUFFER_MEMORY_USAGE.fetch_sub(amount, Ordering::Relaxed);
 }
 
 // ---------------------
@@ -758,6 +970,16 @@ impl Clone for DemiBuffer {
                         clone.pkt_len = original.pkt_len;
                         clone.data_len = original.data_len;
 
+                        // Special case for buffers attached to external data.
+                        if original.ol_flags & METADATA_F_EXTERNAL != 0 {
+                            // The clone attaches to the same external data.  Its reference count resides in the shared
+                            // info, rather than in a direct buffer.
+                            clone.ol_flags = original.ol_flags;
+                            clone._shinfo = original._shinfo;
+                            original.get_shinfo().refcnt += 1;
+                            continue;
+                        }
+
                         // Special case for zero-length buffers.
                         if original.buf_len == 0 {
                             debug_assert_eq!(clone.buf_len, 0);
@@ -886,8 +1108,27 @@ impl Drop for DemiBuffer {
 
                     // Decrement the reference count.
                     if metadata.dec_refcnt() == 0 {
-                        // See if the data is directly attached, or indirectly attached.
-                        if metadata.ol_flags & METADATA_F_INDIRECT != 0 {
+                        // See if the data is external, directly attached, or indirectly attached.
+                        if metadata.ol_flags & METADATA_F_EXTERNAL != 0 {
+                            // This buffer is attached to external data.  Drop our reference to it, and hand it back
+                            // to its owner if ours was the last one.
+                            let shinfo: &mut ExternalSharedInfo = metadata.get_shinfo();
+                            debug_assert_ne!(shinfo.refcnt, 0);
+                            shinfo.refcnt -= 1;
+                            if shinfo.refcnt == 0 {
+                                // Safety: This is safe, as the shared info was allocated via Box::into_raw(), and we
+                                // held the last reference to it.
+                                let shinfo: Box<ExternalSharedInfo> =
+                                    unsafe { Box::from_raw(metadata._shinfo as *mut ExternalSharedInfo) };
+                                (shinfo.free_cb)();
+                            }
+
+                            // Restore buf_addr and buf_len to their unattached values.
+                            metadata.buf_addr = null_mut();
+                            metadata.buf_len = 0;
+                            metadata.ol_flags = metadata.ol_flags & !METADATA_F_EXTERNAL;
+                            metadata._shinfo = 0;
+                        } else if metadata.ol_flags & METADATA_F_INDIRECT != 0 {
                             // This is an indirect buffer.  Find the direct buffer that holds the actual data.
                             let offset: isize = -(size_of::<MetaData>() as isize);
                             let direct: &mut MetaData = unsafe {
@@ -999,7 +1240,16 @@ impl TryFrom<&[u8]> for DemiBuffer {
 mod tests {
     use super::DemiBuffer;
     use ::anyhow::Result;
-    use std::ptr::NonNull;
+    use std::{
+        ptr::NonNull,
+        sync::{
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+            Arc,
+        },
+    };
 
     // Test basic allocation, len, adjust, and trim.
     #[test]
@@ -1024,6 +1274,51 @@ mod tests {
         Ok(())
     }
 
+    // Test that data can be appended to the unused space at the end of an exclusively owned buffer.
+    #[test]
+    fn append() -> Result<()> {
+        let mut buf: DemiBuffer = DemiBuffer::new(8);
+        buf.trim(8)?;
+        crate::ensure_eq!(buf.len(), 0);
+
+        // Appends fill the buffer up to its capacity.
+        buf.append(b"hello")?;
+        buf.append(b"abc")?;
+        crate::ensure_eq!(&buf[..], b"helloabc");
+        crate::ensure_eq!(buf.append(b"!").is_err(), true);
+
+        // Shared buffers cannot be appended to, as that would change data that others see.
+        buf.trim(3)?;
+        let clone: DemiBuffer = buf.clone();
+        crate::ensure_eq!(buf.append(b"!").is_err(), true);
+        drop(clone);
+        buf.append(b"!")?;
+        crate::ensure_eq!(&buf[..], b"hello!");
+
+        Ok(())
+    }
+
+    // Test that clones share the data of the original buffer until they are dropped.
+    #[test]
+    fn shared() -> Result<()> {
+        let buf: DemiBuffer = DemiBuffer::new(42);
+        crate::ensure_eq!(buf.is_shared(), false);
+
+        // Clones hold a reference on the data, even if they only expose part of it.
+        let mut clone: DemiBuffer = buf.clone();
+        crate::ensure_eq!(clone.trim(21).is_ok(), true);
+        crate::ensure_eq!(buf.is_shared(), true);
+        let another: DemiBuffer = clone.clone();
+        drop(clone);
+        crate::ensure_eq!(buf.is_shared(), true);
+
+        // Once all clones are gone, the original buffer is the only one left.
+        drop(another);
+        crate::ensure_eq!(buf.is_shared(), false);
+
+        Ok(())
+    }
+
     // Test cloning, raw conversion, and zero-size buffers.
     #[test]
     fn advanced() -> Result<()> {
@@ -1167,4 +1462,85 @@ mod tests {
 
         Ok(())
     }
+
+    // Tests that split_off shares the underlying allocation and that the resulting views can be trimmed independently.
+    #[test]
+    fn split_off() -> Result<()> {
+        // Two length-prefixed messages, as they would be returned by a single pop.
+        let slice: &[u8] = b"\x05hello\x05world";
+        let mut buf: DemiBuffer = match DemiBuffer::from_slice(slice) {
+            Ok(buf) => buf,
+            Err(e) => anyhow::bail!(
+                "DemiBuffer::from_slice should return a DemiBuffer for this slice: {}",
+                e
+            ),
+        };
+        let data_ptr: *const u8 = buf.as_ptr();
+
+        // Split off the second message.
+        // `DemiBuffer::split_off` shouldn't fail, as we passed it a valid offset.
+        let mut tail: DemiBuffer = match buf.split_off(6) {
+            Ok(buf) => buf,
+            Err(e) => anyhow::bail!("DemiBuffer::split_off shouldn't fail for this offset: {}", e),
+        };
+        crate::ensure_eq!(&buf[..], &slice[..6]);
+        crate::ensure_eq!(&tail[..], &slice[6..]);
+
+        // Both views should point into the original allocation.
+        crate::ensure_eq!(buf.as_ptr(), data_ptr);
+        crate::ensure_eq!(tail.as_ptr(), data_ptr.wrapping_add(6));
+
+        // Strip the length prefixes. Each view should be unaffected by changes to the other.
+        buf.adjust(1)?;
+        tail.adjust(1)?;
+        crate::ensure_eq!(&buf[..], b"hello");
+        crate::ensure_eq!(&tail[..], b"world");
+        tail.trim(2)?;
+        crate::ensure_eq!(&buf[..], b"hello");
+        crate::ensure_eq!(&tail[..], b"wor");
+
+        // Splitting past the end should fail.
+        crate::ensure_eq!(buf.split_off(buf.len() + 1).is_err(), true);
+
+        Ok(())
+    }
+
+    // Test that external data is handed back to its owner exactly once, when the last view on it is dropped.
+    #[test]
+    fn external() -> Result<()> {
+        static DATA: [u8; 11] = *b"hello world";
+        let drops: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let counter: Arc<AtomicUsize> = drops.clone();
+        // Safety: This is safe, as DATA lives forever and is only read through the buffer.
+        let mut buf: DemiBuffer = unsafe {
+            DemiBuffer::from_external(NonNull::from(&DATA).cast::<u8>(), DATA.len() as u16, move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+        crate::ensure_eq!(buf.as_ptr(), DATA.as_ptr());
+        crate::ensure_eq!(&buf[..], &DATA[..]);
+        crate::ensure_eq!(buf.is_shared(), false);
+
+        // Clones and splits refer to the same data.
+        let clone: DemiBuffer = buf.clone();
+        crate::ensure_eq!(buf.is_shared(), true);
+        let tail: DemiBuffer = buf.split_off(6)?;
+        crate::ensure_eq!(&buf[..], b"hello ");
+        crate::ensure_eq!(tail.as_ptr(), DATA.as_ptr().wrapping_add(6));
+        let mut tail_clone: DemiBuffer = tail.clone();
+        drop(tail);
+        drop(clone);
+        drop(buf);
+        crate::ensure_eq!(drops.load(Ordering::Relaxed), 0);
+        crate::ensure_eq!(&tail_clone[..], b"world");
+
+        // External data cannot be recycled.
+        crate::ensure_eq!(tail_clone.reset(DATA.len() as u16), false);
+
+        // The owner gets its data back once the last view is dropped.
+        drop(tail_clone);
+        crate::ensure_eq!(drops.load(Ordering::Relaxed), 1);
+
+        Ok(())
+    }
 }
@@ -1,7 +1,9 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+mod chain;
 mod demibuffer;
+mod pool;
 
 //==============================================================================
 // Imports
@@ -12,6 +14,7 @@ use crate::runtime::{
     types::{
         demi_sgarray_t,
         demi_sgaseg_t,
+        DEMI_SGARRAY_MAXLEN,
     },
 };
 use ::libc::c_void;
@@ -21,12 +24,17 @@ use ::std::{
         self,
         NonNull,
     },
+    slice,
 };
 //==============================================================================
 // Exports
 //==============================================================================
 
-pub use self::demibuffer::*;
+pub use self::{
+    chain::*,
+    demibuffer::*,
+    pool::*,
+};
 
 //==============================================================================
 // Traits
@@ -37,6 +45,12 @@ pub use self::demibuffer::*;
 /// a Demibuffer from that allocation. Other libOSes may override these functions to allocate memory
 /// specific kernel-bypass memory (e.g., DPDK mbufs or registered RDMA memory).
 pub trait MemoryRuntime {
+    /// Returns the pool that scatter-gather arrays are drawn from, if the runtime has one. Runtimes without a pool
+    /// allocate and release every scatter-gather array off of the heap.
+    fn get_buffer_pool(&self) -> Option<SharedBufferPool> {
+        None
+    }
+
     /// Converts a buffer into a scatter-gather array.
     fn into_sgarray(&self, buf: DemiBuffer) -> Result<demi_sgarray_t, Fail> {
         // Create a scatter-gather segment to expose the DemiBuffer to the user.
@@ -64,8 +78,11 @@ pub trait MemoryRuntime {
             return Err(Fail::new(libc::EINVAL, "size too large for a single demi_sgaseg_t"));
         }
 
-        // First allocate the underlying DemiBuffer.
-        let buf: DemiBuffer = DemiBuffer::new(size as u16);
+        // First allocate the underlying DemiBuffer, preferably from the buffer pool.
+        let buf: DemiBuffer = match self.get_buffer_pool() {
+            Some(mut pool) => pool.alloc(size as u16),
+            None => DemiBuffer::new(size as u16),
+        };
 
         // Create a scatter-gather segment to expose the DemiBuffer to the user.
         let data: *const u8 = buf.as_ptr();
@@ -95,17 +112,50 @@ pub trait MemoryRuntime {
             return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid DemiBuffer token"));
         }
 
-        // Convert back to a DemiBuffer and drop it.
+        // Convert back to a DemiBuffer and return it to the buffer pool or drop it.
         // Safety: The `NonNull::new_unchecked()` call is safe, as we verified `sga.sga_buf` is not null above.
         let token: NonNull<u8> = unsafe { NonNull::new_unchecked(sga.sga_buf as *mut u8) };
         // Safety: The `DemiBuffer::from_raw()` call *should* be safe, as the `sga_buf` field in the `demi_sgarray_t`
         // contained a valid `DemiBuffer` token when we provided it to the user (and the user shouldn't change it).
         let buf: DemiBuffer = unsafe { DemiBuffer::from_raw(token) };
-        drop(buf);
+        match self.get_buffer_pool() {
+            Some(mut pool) => pool.free(buf),
+            None => drop(buf),
+        }
 
         Ok(())
     }
 
+    /// Exposes each segment of a scatter-gather array as a borrowed slice, so that its data can be processed in place
+    /// without copying it out or handling raw pointers.
+    ///
+    /// # Safety
+    ///
+    /// `sga` must have been handed out by this runtime and not been released yet, and it must stay that way for as long
+    /// as the slices are used. As [demi_sgarray_t] is [Copy], borrowing it does not prevent a copy of it from being
+    /// released with [MemoryRuntime::sgafree].
+    unsafe fn sga_as_slices<'a>(&'a self, sga: &'a demi_sgarray_t) -> Result<Vec<&'a [u8]>, Fail> {
+        // Check arguments.
+        let numsegs: usize = sga.sga_numsegs as usize;
+        if numsegs > DEMI_SGARRAY_MAXLEN {
+            return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid segment count"));
+        }
+
+        let sga_segs: [demi_sgaseg_t; DEMI_SGARRAY_MAXLEN] = sga.sga_segs;
+        let mut slices: Vec<&'a [u8]> = Vec::with_capacity(numsegs);
+        for sga_seg in &sga_segs[..numsegs] {
+            let data: *const u8 = sga_seg.sgaseg_buf as *const u8;
+            if data.is_null() {
+                return Err(Fail::new(libc::EINVAL, "demi_sgarray_t has invalid segment buffer"));
+            }
+            // Safety: The segment describes data of the buffer that backs the scatter-gather array, which the caller
+            // keeps alive for as long as the slices are used.
+            slices.push(slice::from_raw_parts(data, sga_seg.sgaseg_len as usize));
+        }
+
+        Ok(slices)
+    }
+
     /// Clones a scatter-gather array.
     fn clone_sgarray(&self, sga: &demi_sgarray_t) -> Result<DemiBuffer, Fail> {
         // Check arguments.
@@ -165,3 +215,49 @@ pub trait MemoryRuntime {
         Ok(clone)
     }
 }
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryRuntime;
+    use crate::{
+        ensure_eq,
+        runtime::types::demi_sgarray_t,
+    };
+    use ::anyhow::Result;
+    use ::std::slice;
+
+    /// Memory runtime that uses the default implementation of every function.
+    struct DefaultRuntime;
+
+    impl MemoryRuntime for DefaultRuntime {}
+
+    /// Tests that the segments of a scatter-gather array are exposed as slices over the data that was written to them.
+    #[test]
+    fn sga_as_slices() -> Result<()> {
+        let runtime: DefaultRuntime = DefaultRuntime;
+        let sga: demi_sgarray_t = runtime.sgaalloc(64)?;
+        let data: &mut [u8] = unsafe { slice::from_raw_parts_mut(sga.sga_segs[0].sgaseg_buf as *mut u8, 64) };
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        // Safety: The scatter-gather array is released only after we are done with the slices.
+        let slices: Vec<&[u8]> = unsafe { runtime.sga_as_slices(&sga)? };
+        ensure_eq!(slices.len(), 1);
+        ensure_eq!(slices[0].len(), 64);
+        ensure_eq!(slices[0].iter().enumerate().all(|(i, byte)| *byte == i as u8), true);
+
+        // Scatter-gather arrays with more segments than there is room for are rejected.
+        let mut bad_sga: demi_sgarray_t = sga;
+        bad_sga.sga_numsegs = 2;
+        ensure_eq!(unsafe { runtime.sga_as_slices(&bad_sga) }.is_err(), true);
+
+        runtime.sgafree(sga)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::{
+    memory::DemiBuffer,
+    SharedObject,
+};
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Default capacity of the buffers that are kept in a buffer pool (in bytes). Allocations that fit in it are served
+/// from the pool, larger ones go straight to the heap.
+pub const BUFFER_POOL_BUFFER_SIZE: u16 = 2048;
+
+/// Default maximum number of free buffers that are kept in a buffer pool. Buffers that are released while the pool is
+/// full are returned to the heap.
+pub const BUFFER_POOL_MAX_BUFFERS: usize = 1024;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Pool of free heap-allocated buffers, so that buffers are recycled instead of going back and forth to the heap.
+pub struct BufferPool {
+    /// Free buffers, all of which have a capacity of [buffer_size] bytes.
+    free: Vec<DemiBuffer>,
+    /// Capacity of the buffers in the pool (in bytes).
+    buffer_size: u16,
+    /// Maximum number of free buffers in the pool.
+    max_buffers: usize,
+}
+
+/// Buffer pool that is shared by all users of a runtime.
+#[derive(Clone)]
+pub struct SharedBufferPool(SharedObject<BufferPool>);
+
+//==============================================================================
+// Associated Functions
+//==============================================================================
+
+/// Associated Functions for Shared Buffer Pools
+impl SharedBufferPool {
+    /// Creates a buffer pool that keeps up to `max_buffers` free buffers of `buffer_size` bytes each.
+    pub fn new(buffer_size: u16, max_buffers: usize) -> Self {
+        Self(SharedObject::new(BufferPool {
+            free: Vec::new(),
+            buffer_size,
+            max_buffers,
+        }))
+    }
+
+    /// Allocates a buffer of `size` bytes. The buffer is taken from the pool if it fits in a pooled buffer and the pool
+    /// is not empty. Otherwise, it is allocated off of the heap.
+    pub fn alloc(&mut self, size: u16) -> DemiBuffer {
+        let buffer_size: u16 = self.0.buffer_size;
+        if size > buffer_size {
+            return DemiBuffer::new(size);
+        }
+
+        let mut buf: DemiBuffer = match self.0.free.pop() {
+            Some(buf) => buf,
+            None => DemiBuffer::new(buffer_size),
+        };
+        buf.trim((buffer_size - size) as usize)
+            .expect("pooled buffer should hold the requested size");
+        buf
+    }
+
+    /// Releases `buf`. The buffer is returned to the pool if it can be reused and the pool is not full. Otherwise, it is
+    /// dropped.
+    pub fn free(&mut self, mut buf: DemiBuffer) {
+        if self.0.free.len() < self.0.max_buffers && buf.reset(self.0.buffer_size) {
+            self.0.free.push(buf);
+        }
+    }
+
+    /// Returns the capacity of the buffers in the pool (in bytes).
+    pub fn buffer_size(&self) -> u16 {
+        self.0.buffer_size
+    }
+
+    /// Returns the number of free buffers in the pool.
+    pub fn len(&self) -> usize {
+        self.0.free.len()
+    }
+
+    /// Checks if the pool has no free buffers.
+    pub fn is_empty(&self) -> bool {
+        self.0.free.is_empty()
+    }
+}
+
+//==============================================================================
+// Trait Implementations
+//==============================================================================
+
+/// Default Trait Implementation for Shared Buffer Pools
+impl Default for SharedBufferPool {
+    fn default() -> Self {
+        Self::new(BUFFER_POOL_BUFFER_SIZE, BUFFER_POOL_MAX_BUFFERS)
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        SharedBufferPool,
+        BUFFER_POOL_BUFFER_SIZE,
+        BUFFER_POOL_MAX_BUFFERS,
+    };
+    use crate::{
+        ensure_eq,
+        runtime::{
+            memory::{
+                DemiBuffer,
+                MemoryRuntime,
+            },
+            types::demi_sgarray_t,
+        },
+    };
+    use ::anyhow::Result;
+    use ::libc::c_void;
+    use ::test::{
+        black_box,
+        Bencher,
+    };
+
+    /// Memory runtime that does not pool buffers.
+    struct UnpooledRuntime;
+
+    /// Memory runtime that pools buffers.
+    struct PooledRuntime(SharedBufferPool);
+
+    impl MemoryRuntime for UnpooledRuntime {}
+
+    impl MemoryRuntime for PooledRuntime {
+        fn get_buffer_pool(&self) -> Option<SharedBufferPool> {
+            Some(self.0.clone())
+        }
+    }
+
+    /// Tests that buffers round-trip through the pool.
+    #[test]
+    fn buffer_pool_round_trip() -> Result<()> {
+        let mut pool: SharedBufferPool = SharedBufferPool::default();
+
+        let mut buf: DemiBuffer = pool.alloc(100);
+        ensure_eq!(buf.len(), 100);
+        buf[..].fill(0xff);
+        let ptr: *const u8 = buf.as_ptr();
+        pool.free(buf);
+        ensure_eq!(pool.len(), 1);
+
+        // The same buffer should be handed out again, with the requested size.
+        let buf: DemiBuffer = pool.alloc(BUFFER_POOL_BUFFER_SIZE);
+        ensure_eq!(buf.as_ptr(), ptr);
+        ensure_eq!(buf.len(), BUFFER_POOL_BUFFER_SIZE as usize);
+        ensure_eq!(pool.is_empty(), true);
+        pool.free(buf);
+
+        Ok(())
+    }
+
+    /// Tests that buffers that cannot be reused do not end up in the pool.
+    #[test]
+    fn buffer_pool_rejects_buffers() -> Result<()> {
+        let mut pool: SharedBufferPool = SharedBufferPool::default();
+
+        // Buffers that are too big are not pooled.
+        let buf: DemiBuffer = pool.alloc(BUFFER_POOL_BUFFER_SIZE + 1);
+        ensure_eq!(buf.len(), BUFFER_POOL_BUFFER_SIZE as usize + 1);
+        pool.free(buf);
+        ensure_eq!(pool.is_empty(), true);
+
+        // Buffers that are still in use elsewhere are not pooled.
+        let buf: DemiBuffer = pool.alloc(100);
+        let clone: DemiBuffer = buf.clone();
+        pool.free(buf);
+        ensure_eq!(pool.is_empty(), true);
+        ensure_eq!(clone.len(), 100);
+
+        // The pool does not grow past its limit.
+        let bufs: Vec<DemiBuffer> = (0..(BUFFER_POOL_MAX_BUFFERS + 1)).map(|_| pool.alloc(100)).collect();
+        for buf in bufs {
+            pool.free(buf);
+        }
+        ensure_eq!(pool.len(), BUFFER_POOL_MAX_BUFFERS);
+
+        Ok(())
+    }
+
+    /// Tests that scatter-gather arrays round-trip through the pool of a memory runtime.
+    #[test]
+    fn buffer_pool_sga_round_trip() -> Result<()> {
+        let pool: SharedBufferPool = SharedBufferPool::default();
+        let runtime: PooledRuntime = PooledRuntime(pool.clone());
+
+        let sga: demi_sgarray_t = runtime.sgaalloc(64)?;
+        ensure_eq!({ sga.sga_segs[0].sgaseg_len }, 64);
+        let ptr: *mut c_void = sga.sga_segs[0].sgaseg_buf;
+        runtime.sgafree(sga)?;
+        ensure_eq!(pool.len(), 1);
+
+        let sga: demi_sgarray_t = runtime.sgaalloc(128)?;
+        ensure_eq!({ sga.sga_segs[0].sgaseg_buf }, ptr);
+        ensure_eq!({ sga.sga_segs[0].sgaseg_len }, 128);
+        runtime.sgafree(sga)?;
+
+        Ok(())
+    }
+
+    #[bench]
+    fn bench_sgaalloc_sgafree_unpooled(b: &mut Bencher) {
+        let runtime: UnpooledRuntime = UnpooledRuntime;
+        b.iter(|| {
+            let sga: demi_sgarray_t = runtime.sgaalloc(black_box(1024)).expect("sgaalloc() should succeed");
+            runtime.sgafree(black_box(sga)).expect("sgafree() should succeed");
+        });
+    }
+
+    #[bench]
+    fn bench_sgaalloc_sgafree_pooled(b: &mut Bencher) {
+        let runtime: PooledRuntime = PooledRuntime(SharedBufferPool::default());
+        b.iter(|| {
+            let sga: demi_sgarray_t = runtime.sgaalloc(black_box(1024)).expect("sgaalloc() should succeed");
+            runtime.sgafree(black_box(sga)).expect("sgafree() should succeed");
+        });
+    }
+}
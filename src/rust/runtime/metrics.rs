@@ -0,0 +1,144 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::std::fmt::Write;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Type of a metric family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricType {
+    /// Value that only goes up.
+    Counter,
+    /// Value that goes up and down.
+    Gauge,
+}
+
+/// Family of metrics that share a name, and whose samples are told apart by their labels.
+struct MetricFamily {
+    /// Name of the family.
+    name: &'static str,
+    /// Description of the family.
+    help: &'static str,
+    /// Type of the family.
+    typ: MetricType,
+    /// Samples of the family, along with their rendered labels.
+    samples: Vec<(String, f64)>,
+}
+
+/// Collection of metrics that renders in the Prometheus text exposition format. Samples of the same family are grouped
+/// together, in the order in which families were first added.
+#[derive(Default)]
+pub struct MetricsText {
+    families: Vec<MetricFamily>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+/// Associated Functions for Metrics Text
+impl MetricsText {
+    /// Adds a sample with labels `labels` and value `value` to the counter family `name`.
+    pub fn counter(&mut self, name: &'static str, help: &'static str, labels: &[(&str, String)], value: f64) {
+        self.add(name, help, MetricType::Counter, labels, value)
+    }
+
+    /// Adds a sample with labels `labels` and value `value` to the gauge family `name`.
+    pub fn gauge(&mut self, name: &'static str, help: &'static str, labels: &[(&str, String)], value: f64) {
+        self.add(name, help, MetricType::Gauge, labels, value)
+    }
+
+    /// Renders the target metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out: String = String::new();
+        for family in self.families.iter() {
+            let typ: &str = match family.typ {
+                MetricType::Counter => "counter",
+                MetricType::Gauge => "gauge",
+            };
+            // Writing to a string cannot fail.
+            let _ = writeln!(out, "# HELP {} {}", family.name, family.help);
+            let _ = writeln!(out, "# TYPE {} {}", family.name, typ);
+            for (labels, value) in family.samples.iter() {
+                let _ = writeln!(out, "{}{} {}", family.name, labels, value);
+            }
+        }
+        out
+    }
+
+    fn add(&mut self, name: &'static str, help: &'static str, typ: MetricType, labels: &[(&str, String)], value: f64) {
+        let labels: String = render_labels(labels);
+        match self.families.iter_mut().find(|family| family.name == name) {
+            Some(family) => {
+                debug_assert_eq!(family.typ, typ);
+                family.samples.push((labels, value));
+            },
+            None => self.families.push(MetricFamily {
+                name,
+                help,
+                typ,
+                samples: vec![(labels, value)],
+            }),
+        }
+    }
+}
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Renders `labels` as a label set, escaping backslashes, double quotes and line feeds in their values.
+fn render_labels(labels: &[(&str, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value: String = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::MetricsText;
+    use crate::ensure_eq;
+    use ::anyhow::Result;
+
+    /// Tests that samples are grouped by family and that label values are escaped.
+    #[test]
+    fn metrics_text_render() -> Result<()> {
+        let mut metrics: MetricsText = MetricsText::default();
+        metrics.gauge("demikernel_test_gauge", "A gauge.", &[("qd", String::from("1"))], 2.0);
+        metrics.counter("demikernel_test_total", "A counter.", &[], 3.0);
+        metrics.gauge(
+            "demikernel_test_gauge",
+            "A gauge.",
+            &[("qd", String::from("a\"b\\c\nd"))],
+            0.5,
+        );
+
+        ensure_eq!(
+            metrics.render(),
+            "# HELP demikernel_test_gauge A gauge.\n# TYPE demikernel_test_gauge \
+             gauge\ndemikernel_test_gauge{qd=\"1\"} 2\ndemikernel_test_gauge{qd=\"a\\\"b\\\\c\\nd\"} 0.5\n# HELP \
+             demikernel_test_total A counter.\n# TYPE demikernel_test_total counter\ndemikernel_test_total 3\n"
+        );
+
+        Ok(())
+    }
+}
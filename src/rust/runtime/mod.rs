@@ -9,6 +9,7 @@ pub mod fail;
 pub mod limits;
 pub mod logging;
 pub mod memory;
+pub mod metrics;
 pub mod network;
 pub mod queue;
 pub mod scheduler;
@@ -23,6 +24,8 @@ pub use queue::{
     QDesc,
     QToken,
     QType,
+    QueueInfo,
+    Readiness,
 };
 
 #[cfg(feature = "liburing")]
@@ -39,7 +42,12 @@ use crate::{
     pal::data_structures::SockAddr,
     runtime::{
         fail::Fail,
-        memory::MemoryRuntime,
+        memory::{
+            DemiBuffer,
+            MemoryRuntime,
+            SharedBufferPool,
+        },
+        metrics::MetricsText,
         network::{
             ephemeral::EphemeralPorts,
             socket::SocketId,
@@ -50,6 +58,7 @@ use crate::{
             IoQueueTable,
         },
         scheduler::{
+            InsertError,
             Scheduler,
             Task,
             TaskHandle,
@@ -74,7 +83,10 @@ use ::std::{
     },
     pin::Pin,
     rc::Rc,
-    time::Instant,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 #[cfg(target_os = "windows")]
@@ -88,7 +100,10 @@ use windows::Win32::Networking::WinSock::{
 use crate::pal::functions::socketaddrv4_to_sockaddr;
 
 #[cfg(target_os = "linux")]
-use crate::pal::linux::socketaddrv4_to_sockaddr;
+use crate::pal::linux::{
+    self,
+    socketaddrv4_to_sockaddr,
+};
 
 use self::{
     scheduler::YielderHandle,
@@ -96,6 +111,7 @@ use self::{
         demi_accept_result_t,
         demi_qr_value_t,
         demi_qresult_t,
+        demi_sgarray_t,
     },
 };
 
@@ -125,6 +141,12 @@ pub struct DemiRuntime {
     /// Currently running coroutines.
     pending_ops: HashMap<QDesc, HashMap<TaskHandle, YielderHandle>>,
     ts_iters: usize,
+    /// Is the clock only moved forward explicitly, instead of following wall time?
+    manual_clock: bool,
+    /// Correlation ID that is attached to the names of the operation coroutines that are inserted, if any.
+    trace_context: Option<String>,
+    /// Shared pool of buffers for scatter-gather arrays.
+    buffer_pool: SharedBufferPool,
 }
 
 #[derive(Clone)]
@@ -167,23 +189,86 @@ impl SharedDemiRuntime {
             network_table: NetworkQueueTable::default(),
             pending_ops: HashMap::<QDesc, HashMap<TaskHandle, YielderHandle>>::new(),
             ts_iters: 0,
+            manual_clock: false,
+            trace_context: None,
+            buffer_pool: SharedBufferPool::default(),
         }))
     }
 
-    /// Inserts the `coroutine` named `task_name` into the scheduler.
+    /// Inserts the `coroutine` named `task_name` into the scheduler. The current trace context, if any, is appended to
+    /// the name.
     pub fn insert_coroutine(&mut self, task_name: &str, coroutine: Pin<Box<Operation>>) -> Result<TaskHandle, Fail> {
+        let task_name: String = match &self.trace_context {
+            Some(trace_context) => format!("{} trace={}", task_name, trace_context),
+            None => task_name.to_string(),
+        };
         trace!("Inserting coroutine: {:?}", task_name);
-        let task: OperationTask = OperationTask::new(task_name.to_string(), coroutine);
+        let task: OperationTask = OperationTask::new(task_name.clone(), coroutine);
         match self.scheduler.insert(task) {
-            Some(handle) => Ok(handle),
-            None => {
-                let cause: String = format!("cannot schedule coroutine (task_name={:?})", &task_name);
-                error!("insert_coroutine(): {}", cause);
-                Err(Fail::new(libc::EAGAIN, &cause))
+            Ok(handle) => Ok(handle),
+            Err(e) => {
+                let fail: Fail = Self::insert_error_to_fail(e, &task_name);
+                error!("insert_coroutine(): {}", fail.cause);
+                Err(fail)
+            },
+        }
+    }
+
+    /// Converts the reason why the coroutine named `task_name` could not be scheduled into a failure. A full scheduler
+    /// is reported with ENOBUFS, as retrying does not help until coroutines complete or the capacity is raised. Other
+    /// failures are reported with EAGAIN.
+    fn insert_error_to_fail(e: InsertError, task_name: &str) -> Fail {
+        match e {
+            InsertError::Full => {
+                let cause: String = format!("scheduler is full (task_name={:?})", task_name);
+                Fail::new(libc::ENOBUFS, &cause)
+            },
+            InsertError::Transient => {
+                let cause: String = format!("cannot schedule coroutine (task_name={:?})", task_name);
+                Fail::new(libc::EAGAIN, &cause)
             },
         }
     }
 
+    /// Returns the maximum number of coroutines that are scheduled at the same time.
+    pub fn scheduler_capacity(&self) -> usize {
+        self.scheduler.capacity()
+    }
+
+    /// Adds the metrics of the scheduler and of the buffer pool to `metrics`.
+    pub fn write_metrics(&self, metrics: &mut MetricsText) {
+        metrics.gauge(
+            "demikernel_scheduler_tasks",
+            "Number of coroutines that are scheduled.",
+            &[],
+            self.scheduler.num_tasks() as f64,
+        );
+        metrics.gauge(
+            "demikernel_scheduler_capacity",
+            "Maximum number of coroutines that are scheduled at the same time.",
+            &[],
+            self.scheduler.capacity() as f64,
+        );
+        metrics.gauge(
+            "demikernel_buffer_pool_free_buffers",
+            "Number of free buffers in the buffer pool.",
+            &[],
+            self.buffer_pool.len() as f64,
+        );
+        metrics.gauge(
+            "demikernel_buffer_pool_free_bytes",
+            "Memory held by free buffers in the buffer pool (in bytes).",
+            &[],
+            (self.buffer_pool.len() * self.buffer_pool.buffer_size() as usize) as f64,
+        );
+    }
+
+    /// Sets the maximum number of coroutines that are scheduled at the same time.
+    pub fn set_scheduler_capacity(&mut self, capacity: usize) -> Result<(), Fail> {
+        trace!("set_scheduler_capacity(): capacity={:?}", capacity);
+        self.scheduler.set_capacity(capacity)
+    }
+
     /// Inserts the `coroutine` named `task_name` into the scheduler. This function also tracks the qd, coroutine and
     /// it's yielder_handle.
     pub fn insert_coroutine_with_tracking(
@@ -218,6 +303,16 @@ impl SharedDemiRuntime {
         OperationTask::from(boxed_task.as_any())
     }
 
+    /// Removes a coroutine from the underlying scheduler given its associated [TaskHandle] `handle`, but only if it has
+    /// completed. Returns `None` and leaves the coroutine in place if it is still running, or if it was already removed.
+    pub fn try_remove_coroutine(&mut self, handle: &TaskHandle) -> Option<OperationTask> {
+        self.scheduler.from_task_id(handle.get_task_id())?;
+        if !handle.has_completed() {
+            return None;
+        }
+        Some(self.remove_coroutine(handle))
+    }
+
     /// Removes a coroutine from the underlying scheduler given its associated [QToken] `qt`.
     pub fn remove_coroutine_with_qtoken(&mut self, qt: QToken) -> OperationTask {
         self.remove_coroutine(&self.scheduler.from_task_id(qt.into()).expect("coroutine should exist"))
@@ -232,6 +327,28 @@ impl SharedDemiRuntime {
         Ok(self.pack_result(result, qd, qt))
     }
 
+    /// Removes up to `max` coroutines that have completed from the underlying scheduler and packs their results. Each
+    /// coroutine is removed at most once, so its [QToken] is no longer valid afterwards.
+    pub fn remove_completed_coroutines(&mut self, max: usize) -> Vec<(QToken, demi_qresult_t)> {
+        let task_ids: Vec<u64> = self.scheduler.get_completed_task_ids::<OperationTask>(max);
+        let mut results: Vec<(QToken, demi_qresult_t)> = Vec::with_capacity(task_ids.len());
+        for task_id in task_ids {
+            let qt: QToken = QToken::from(task_id);
+            let handle: TaskHandle = self
+                .scheduler
+                .from_task_id(task_id)
+                .expect("completed coroutine should exist");
+            match self.remove_coroutine_and_get_result(&handle, task_id) {
+                Ok(qr) => results.push((qt, qr)),
+                Err(e) => warn!(
+                    "remove_completed_coroutines(): cannot pack result (qt={:?}): {:?}",
+                    qt, e
+                ),
+            }
+        }
+        results
+    }
+
     /// When the queue is closed, we need to cancel all pending ops. When the coroutine is removed, we only need to
     /// cancel the pending op associated with the handle.
     fn cancel_or_remove_pending_ops_as_needed(
@@ -278,11 +395,11 @@ impl SharedDemiRuntime {
         trace!("Inserting background coroutine: {:?}", task_name);
         let task: BackgroundTask = BackgroundTask::new(task_name.to_string(), coroutine);
         match self.scheduler.insert(task) {
-            Some(handle) => Ok(handle),
-            None => {
-                let cause: String = format!("cannot schedule coroutine (task_name={:?})", &task_name);
-                error!("insert_background_coroutine(): {}", cause);
-                Err(Fail::new(libc::EAGAIN, &cause))
+            Ok(handle) => Ok(handle),
+            Err(e) => {
+                let fail: Fail = Self::insert_error_to_fail(e, task_name);
+                error!("insert_background_coroutine(): {}", fail.cause);
+                Err(fail)
             },
         }
     }
@@ -304,8 +421,10 @@ impl SharedDemiRuntime {
     }
 
     pub fn poll_and_advance_clock(&mut self) {
-        if self.ts_iters == 0 {
-            self.advance_clock(Instant::now());
+        if self.ts_iters == 0 && !self.manual_clock {
+            // The clock may be ahead of wall time if it was moved forward manually, and it never goes back.
+            let now: Instant = Instant::now().max(self.get_now());
+            self.advance_clock(now);
         }
         self.ts_iters = (self.ts_iters + 1) % TIMER_RESOLUTION;
         self.poll()
@@ -316,6 +435,59 @@ impl SharedDemiRuntime {
         self.scheduler.poll()
     }
 
+    /// Checks whether any coroutine is ready to run, that is, whether the next poll would make progress.
+    pub fn has_ready_coroutines(&self) -> bool {
+        self.scheduler.has_ready_tasks()
+    }
+
+    /// Performs a single poll on the underlying scheduler, running at most `max_tasks` coroutines. Ready coroutines
+    /// that do not fit in the budget are left for the next call.
+    pub fn poll_with_budget(&mut self, max_tasks: usize) {
+        self.scheduler.poll_with_budget(max_tasks);
+    }
+
+    /// Pins the calling thread to CPU core `core_id`, so that the scheduler and the coroutines that it runs stay on that
+    /// core. This improves cache locality and makes benchmarks more reproducible.
+    #[cfg(target_os = "linux")]
+    pub fn set_affinity(&self, core_id: usize) -> Result<(), Fail> {
+        trace!("set_affinity(): core_id={:?}", core_id);
+        let online: Vec<usize> = match linux::get_online_cpus() {
+            Some(online) => online,
+            None => {
+                let cause: String = format!("cannot read online cpus (core_id={:?})", core_id);
+                error!("set_affinity(): {}", &cause);
+                return Err(Fail::new(libc::EIO, &cause));
+            },
+        };
+        if !online.contains(&core_id) || core_id >= libc::CPU_SETSIZE as usize {
+            let cause: String = format!("invalid core id (core_id={:?}, online={:?})", core_id, online);
+            error!("set_affinity(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        if unsafe { linux::set_cpu_affinity(core_id) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            let cause: String = format!("cannot set cpu affinity (core_id={:?}, errno={:?})", core_id, errno);
+            error!("set_affinity(): {}", &cause);
+            return Err(Fail::new(errno, &cause));
+        }
+        Ok(())
+    }
+
+    /// Returns the CPU cores that the calling thread may run on.
+    #[cfg(target_os = "linux")]
+    pub fn get_affinity(&self) -> Result<Vec<usize>, Fail> {
+        let mut cpuset: libc::cpu_set_t = unsafe { mem::zeroed() };
+        if unsafe { linux::get_cpu_affinity(&mut cpuset) } != 0 {
+            let errno: libc::c_int = unsafe { *libc::__errno_location() };
+            let cause: String = format!("cannot get cpu affinity (errno={:?})", errno);
+            error!("get_affinity(): {}", &cause);
+            return Err(Fail::new(errno, &cause));
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|core_id| unsafe { libc::CPU_ISSET(*core_id, &cpuset) })
+            .collect())
+    }
+
     /// Retrieves the [TaskHandle] associated with the given [QToken] `qt`.
     pub fn from_task_id(&self, qt: QToken) -> Result<TaskHandle, Fail> {
         match self.scheduler.from_task_id(qt.into()) {
@@ -329,10 +501,21 @@ impl SharedDemiRuntime {
     }
 
     /// Allocates a queue of type `T` and returns the associated queue descriptor.
-    pub fn alloc_queue<T: IoQueue>(&mut self, queue: T) -> QDesc {
-        let qd: QDesc = self.qtable.alloc::<T>(queue);
+    pub fn alloc_queue<T: IoQueue>(&mut self, queue: T) -> Result<QDesc, Fail> {
+        let qd: QDesc = self.qtable.alloc::<T>(queue)?;
         trace!("Allocating new queue: qd={:?}", qd);
-        qd
+        Ok(qd)
+    }
+
+    /// Sets the maximum number of queues that may be open at once.
+    pub fn set_max_queues(&mut self, max_queues: usize) {
+        self.qtable.set_max_queues(max_queues)
+    }
+
+    /// Sets whether queue descriptors are tagged with a generation, so that stale ones fail instead of referring to a
+    /// queue that was allocated later on.
+    pub fn set_tag_qds(&mut self, tag_qds: bool) {
+        self.qtable.set_tag_qds(tag_qds)
     }
 
     /// Returns a reference to the I/O queue table.
@@ -406,9 +589,16 @@ impl SharedDemiRuntime {
         }
     }
 
-    /// Checks if a port is private.
-    pub fn is_private_ephemeral_port(port: u16) -> bool {
-        EphemeralPorts::is_private(port)
+    /// Checks if a port is in the ephemeral port range.
+    pub fn is_private_ephemeral_port(&self, port: u16) -> bool {
+        self.ephemeral_ports.is_private(port)
+    }
+
+    /// Sets the range of ports (`first` to `last`, inclusive) that ephemeral ports are allocated from. This should be
+    /// done before any port is allocated, as allocations from the previous range are forgotten.
+    pub fn set_ephemeral_port_range(&mut self, first: u16, last: u16) -> Result<(), Fail> {
+        self.ephemeral_ports = EphemeralPorts::new(first, last)?;
+        Ok(())
     }
 
     /// Returns a reference to the shared timer.
@@ -421,6 +611,29 @@ impl SharedDemiRuntime {
         self.timer.advance_clock(now)
     }
 
+    /// Enables or disables the manual clock. While it is enabled, the clock stands still when polling and only moves
+    /// forward through [Self::advance_clock_by], so that timers fire deterministically. Once it is disabled, the clock
+    /// follows wall time again, though it never goes back.
+    pub fn set_manual_clock(&mut self, enabled: bool) {
+        self.manual_clock = enabled;
+    }
+
+    /// Sets the correlation ID that is attached to the names of the operation coroutines that are inserted from now on,
+    /// so that their completions can be tied back to an application request. Passing `None` clears it. This returns the
+    /// previous correlation ID.
+    pub fn set_trace_context(&mut self, trace_context: Option<&str>) -> Option<String> {
+        mem::replace(
+            &mut self.trace_context,
+            trace_context.map(|trace_context| trace_context.to_string()),
+        )
+    }
+
+    /// Moves the clock forward by `duration`, firing the timers that expire along the way.
+    pub fn advance_clock_by(&mut self, duration: Duration) {
+        let now: Instant = self.get_now() + duration;
+        self.advance_clock(now)
+    }
+
     /// Gets the current time according to our internal timer.
     pub fn get_now(&self) -> Instant {
         self.timer.now()
@@ -525,6 +738,28 @@ impl SharedDemiRuntime {
                     }
                 },
             },
+            OperationResult::PopBatch(datagrams) => match self.into_sgarray_batch(datagrams) {
+                Ok((sga, count)) => {
+                    let qr_value: demi_qr_value_t = demi_qr_value_t { sga };
+                    demi_qresult_t {
+                        qr_opcode: demi_opcode_t::DEMI_OPC_POP_BATCH,
+                        qr_qd: qd.into(),
+                        qr_qt: qt,
+                        qr_ret: count as i64,
+                        qr_value,
+                    }
+                },
+                Err(e) => {
+                    warn!("Operation Failed: {:?}", e);
+                    demi_qresult_t {
+                        qr_opcode: demi_opcode_t::DEMI_OPC_FAILED,
+                        qr_qd: qd.into(),
+                        qr_qt: qt,
+                        qr_ret: e.errno as i64,
+                        qr_value: unsafe { mem::zeroed() },
+                    }
+                },
+            },
             OperationResult::Close => demi_qresult_t {
                 qr_opcode: demi_opcode_t::DEMI_OPC_CLOSE,
                 qr_qd: qd.into(),
@@ -532,6 +767,20 @@ impl SharedDemiRuntime {
                 qr_ret: 0,
                 qr_value: unsafe { mem::zeroed() },
             },
+            OperationResult::Splice(nbytes) => demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_SPLICE,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: nbytes as i64,
+                qr_value: unsafe { mem::zeroed() },
+            },
+            OperationResult::PopStream(nbytes) => demi_qresult_t {
+                qr_opcode: demi_opcode_t::DEMI_OPC_POP_STREAM,
+                qr_qd: qd.into(),
+                qr_qt: qt,
+                qr_ret: nbytes as i64,
+                qr_value: unsafe { mem::zeroed() },
+            },
             OperationResult::Failed(e) => {
                 warn!("Operation Failed: {:?}", e);
                 demi_qresult_t {
@@ -544,6 +793,46 @@ impl SharedDemiRuntime {
             },
         }
     }
+
+    /// Converts a batch of datagrams into a scatter-gather array whose single segment holds an array of scatter-gather
+    /// arrays, one for each datagram along with its source address. Returns the outer scatter-gather array and the
+    /// number of datagrams. The inner scatter-gather arrays and the outer one must all be released on their own.
+    fn into_sgarray_batch(
+        &self,
+        datagrams: Vec<(Option<SocketAddrV4>, DemiBuffer)>,
+    ) -> Result<(demi_sgarray_t, usize), Fail> {
+        let count: usize = datagrams.len();
+        let size: u16 = match (count * mem::size_of::<demi_sgarray_t>()).try_into() {
+            Ok(size) => size,
+            Err(_) => return Err(Fail::new(libc::EINVAL, "too many datagrams for a single batch")),
+        };
+        let mut buf: DemiBuffer = DemiBuffer::new(size);
+        let sgas: *mut demi_sgarray_t = buf.as_mut_ptr() as *mut demi_sgarray_t;
+        if sgas as usize % mem::align_of::<demi_sgarray_t>() != 0 {
+            let cause: String = format!("misaligned buffer for a batch of datagrams (addr={:?})", sgas);
+            error!("into_sgarray_batch(): {}", &cause);
+            return Err(Fail::new(libc::EFAULT, &cause));
+        }
+        for (i, (addr, bytes)) in datagrams.into_iter().enumerate() {
+            let mut sga: demi_sgarray_t = match self.into_sgarray(bytes) {
+                Ok(sga) => sga,
+                Err(e) => {
+                    // Release the datagrams that were already handed over to scatter-gather arrays.
+                    for j in 0..i {
+                        if let Err(e) = self.sgafree(unsafe { sgas.add(j).read() }) {
+                            warn!("into_sgarray_batch(): failed to release datagram (error={:?})", e);
+                        }
+                    }
+                    return Err(e);
+                },
+            };
+            if let Some(addr) = addr {
+                sga.sga_addr = socketaddrv4_to_sockaddr(&addr);
+            }
+            unsafe { sgas.add(i).write(sga) };
+        }
+        Ok((self.into_sgarray(buf)?, count))
+    }
 }
 
 impl<T> SharedObject<T> {
@@ -563,7 +852,11 @@ impl<T: ?Sized> SharedBox<T> {
 //======================================================================================================================
 
 /// Memory Runtime Trait Implementation for POSIX Runtime
-impl MemoryRuntime for SharedDemiRuntime {}
+impl MemoryRuntime for SharedDemiRuntime {
+    fn get_buffer_pool(&self) -> Option<SharedBufferPool> {
+        Some(self.buffer_pool.clone())
+    }
+}
 
 impl Default for SharedDemiRuntime {
     fn default() -> Self {
@@ -657,3 +950,221 @@ impl DerefMut for SharedDemiRuntime {
 
 /// Demikernel Runtime
 pub trait Runtime: Clone + Unpin + 'static {}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::SharedDemiRuntime;
+    use crate::{
+        ensure_eq,
+        runtime::{
+            scheduler::{
+                Task,
+                TaskHandle,
+            },
+            types::demi_qresult_t,
+            OperationResult,
+            QDesc,
+            QToken,
+        },
+    };
+    use ::anyhow::Result;
+    use ::std::{
+        cell::RefCell,
+        collections::HashSet,
+        future,
+        rc::Rc,
+    };
+
+    /// Tests that completed coroutines are collected in one go, each of them exactly once.
+    #[test]
+    fn remove_completed_coroutines_collects_each_once() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+
+        // Operations that complete on their first poll.
+        let mut qts: HashSet<QToken> = HashSet::new();
+        for i in 0..4 {
+            let qd: QDesc = QDesc::from(i);
+            let handle: TaskHandle =
+                runtime.insert_coroutine("complete", Box::pin(async move { (qd, OperationResult::Connect) }))?;
+            qts.insert(QToken::from(handle.get_task_id()));
+        }
+        // An operation that never completes, and a background coroutine that does: neither of them is collected.
+        runtime.insert_coroutine("pending", Box::pin(future::pending::<(QDesc, OperationResult)>()))?;
+        runtime.insert_background_coroutine("background", Box::pin(async {}))?;
+
+        runtime.poll();
+        let results: Vec<(QToken, demi_qresult_t)> = runtime.remove_completed_coroutines(16);
+        ensure_eq!(results.len(), qts.len());
+        for (qt, qr) in results {
+            ensure_eq!(qts.remove(&qt), true);
+            ensure_eq!(qr.qr_qt, u64::from(qt));
+        }
+
+        // Nothing is reported twice.
+        runtime.poll();
+        ensure_eq!(runtime.remove_completed_coroutines(16).len(), 0);
+
+        Ok(())
+    }
+
+    /// Tests that a coroutine that is removed by another one that is polled before it is not polled anymore.
+    #[test]
+    fn coroutine_removed_while_polling_is_skipped() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let victim: Rc<RefCell<Option<TaskHandle>>> = Rc::new(RefCell::new(None));
+
+        let mut runtime_clone: SharedDemiRuntime = runtime.clone();
+        let victim_clone: Rc<RefCell<Option<TaskHandle>>> = victim.clone();
+        runtime.insert_background_coroutine(
+            "remover",
+            Box::pin(async move {
+                if let Some(handle) = victim_clone.borrow_mut().take() {
+                    runtime_clone
+                        .remove_background_coroutine(&handle)
+                        .expect("victim should still be there");
+                }
+            }),
+        )?;
+        let handle: TaskHandle = runtime.insert_background_coroutine("victim", Box::pin(future::pending::<()>()))?;
+        *victim.borrow_mut() = Some(handle);
+
+        // Both coroutines are ready to run, but only the first one gets polled.
+        runtime.poll();
+        ensure_eq!(victim.borrow().is_none(), true);
+
+        Ok(())
+    }
+
+    /// Tests that the trace context is attached to the task IDs of the operations that are issued while it is set.
+    #[test]
+    fn trace_context_is_attached_to_task_ids() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let qd: QDesc = QDesc::from(0);
+
+        runtime.set_trace_context(Some("req-42"));
+        let traced: TaskHandle =
+            runtime.insert_coroutine("pop for qd=0", Box::pin(async move { (qd, OperationResult::Connect) }))?;
+        ensure_eq!(runtime.set_trace_context(None), Some("req-42".to_string()));
+        let untraced: TaskHandle =
+            runtime.insert_coroutine("pop for qd=0", Box::pin(async move { (qd, OperationResult::Connect) }))?;
+
+        runtime.poll();
+        ensure_eq!(
+            runtime.remove_coroutine(&traced).get_name(),
+            "pop for qd=0 trace=req-42"
+        );
+        ensure_eq!(runtime.remove_coroutine(&untraced).get_name(), "pop for qd=0");
+
+        Ok(())
+    }
+
+    /// Tests that no more than the requested number of results is collected at once.
+    #[test]
+    fn remove_completed_coroutines_honors_max() -> Result<()> {
+        let mut runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        for i in 0..4 {
+            let qd: QDesc = QDesc::from(i);
+            runtime.insert_coroutine("complete", Box::pin(async move { (qd, OperationResult::Connect) }))?;
+        }
+
+        runtime.poll();
+        ensure_eq!(runtime.remove_completed_coroutines(3).len(), 3);
+        ensure_eq!(runtime.remove_completed_coroutines(3).len(), 1);
+
+        Ok(())
+    }
+
+    /// Tests that a batch of datagrams is handed out as one scatter-gather array per datagram, each of them along with
+    /// its source address, and that all of them can be released on their own.
+    #[cfg(all(target_os = "linux", feature = "catcollar-libos"))]
+    #[test]
+    fn pack_result_pop_batch() -> Result<()> {
+        use crate::runtime::{
+            memory::{
+                DemiBuffer,
+                MemoryRuntime,
+            },
+            types::{
+                demi_opcode_t,
+                demi_sgarray_t,
+            },
+        };
+        use ::std::{
+            net::SocketAddrV4,
+            slice,
+        };
+
+        let runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+        let addr: SocketAddrV4 = SocketAddrV4::new("127.0.0.1".parse()?, 23460);
+        let datagrams: Vec<(Option<SocketAddrV4>, DemiBuffer)> = (0..4u8)
+            .map(|i| {
+                (
+                    Some(addr),
+                    DemiBuffer::from_slice(&vec![i; 16 + i as usize][..]).unwrap(),
+                )
+            })
+            .collect();
+
+        let qr: demi_qresult_t = runtime.pack_result(OperationResult::PopBatch(datagrams), QDesc::from(0), 1);
+        ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP_BATCH);
+        ensure_eq!(qr.qr_ret, 4);
+        let outer: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        ensure_eq!(
+            outer.sga_segs[0].sgaseg_len as usize,
+            4 * ::std::mem::size_of::<demi_sgarray_t>()
+        );
+        let sgas: &[demi_sgarray_t] =
+            unsafe { slice::from_raw_parts(outer.sga_segs[0].sgaseg_buf as *const demi_sgarray_t, 4) };
+        for (i, sga) in sgas.iter().enumerate() {
+            let sga_addr: libc::sockaddr = sga.sga_addr;
+            ensure_eq!(crate::pal::linux::sockaddr_to_socketaddrv4(&sga_addr), addr);
+            let data: Vec<&[u8]> = unsafe { runtime.sga_as_slices(sga)? };
+            ensure_eq!(data.len(), 1);
+            ensure_eq!(data[0].len(), 16 + i);
+            ensure_eq!(data[0].iter().all(|b| *b == i as u8), true);
+        }
+
+        for sga in sgas {
+            runtime.sgafree(*sga)?;
+        }
+        runtime.sgafree(outer)?;
+
+        Ok(())
+    }
+
+    /// Tests that the calling thread can be pinned to a core and that offline cores are rejected.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn set_affinity_pins_thread() -> Result<()> {
+        let runtime: SharedDemiRuntime = SharedDemiRuntime::default();
+
+        // Pick a core that this thread is allowed to run on, as others may be off limits.
+        let core_id: usize = match runtime.get_affinity()?.last() {
+            Some(core_id) => *core_id,
+            None => anyhow::bail!("thread should be allowed to run on some core"),
+        };
+        runtime.set_affinity(core_id)?;
+        ensure_eq!(runtime.get_affinity()?, vec![core_id]);
+
+        // Cores that are not online are rejected.
+        let online: Vec<usize> = match crate::pal::linux::get_online_cpus() {
+            Some(online) => online,
+            None => anyhow::bail!("online cpus should be readable"),
+        };
+        let offline_core_id: usize = match (0..).find(|core_id| !online.contains(core_id)) {
+            Some(core_id) => core_id,
+            None => anyhow::bail!("some core id should not be online"),
+        };
+        match runtime.set_affinity(offline_core_id) {
+            Err(e) if e.errno == libc::EINVAL => (),
+            result => anyhow::bail!("set_affinity() should fail with EINVAL: {:?}", result),
+        }
+        ensure_eq!(runtime.get_affinity()?, vec![core_id]);
+
+        Ok(())
+    }
+}
@@ -9,18 +9,36 @@ use crate::runtime::network::consts::{
     DEFAULT_MSS,
     MAX_MSS,
     MIN_MSS,
+    MSL,
 };
 use ::std::time::Duration;
 
+#[cfg(feature = "tcp-md5")]
+use ::std::{
+    collections::HashMap,
+    net::Ipv4Addr,
+};
+
 //==============================================================================
 // Structures
 //==============================================================================
 
+/// Congestion Control Algorithms
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CongestionControlType {
+    /// No Congestion Control (the Congestion Window Never Limits Sending)
+    None,
+    /// CUBIC (RFC 8312)
+    Cubic,
+}
+
 /// TCP Configuration Descriptor
 #[derive(Clone, Debug)]
 pub struct TcpConfig {
     /// Advertised Maximum Segment Size
     advertised_mss: usize,
+    /// Maximum Segment Size Used for Segmentation (None Means Same as Advertised)
+    effective_mss: Option<usize>,
     /// Number of Retries for TCP Handshake Algorithm
     handshake_retries: usize,
     /// Timeout for TCP Handshake Algorithm
@@ -35,6 +53,39 @@ pub struct TcpConfig {
     rx_checksum_offload: bool,
     /// Offload Checksum to Hardware When Sending?
     tx_checksum_offload: bool,
+    /// Pace Outgoing Segments?
+    enable_pacing: bool,
+    /// Pacing Rate in Bytes per Second (Zero Means Derive From Congestion Window and RTT)
+    pacing_rate: u64,
+    /// Use Explicit Congestion Notification?
+    enable_ecn: bool,
+    /// Congestion Control Algorithm
+    congestion_control: CongestionControlType,
+    /// Initial Congestion Window in Bytes (None Means Derive From MSS as per RFC 5681)
+    initial_cwnd: Option<u32>,
+    /// Initial Slow Start Threshold in Bytes (None Means Arbitrarily High)
+    initial_ssthresh: Option<u32>,
+    /// Idle Period After Which an Established Connection is Closed (None Means Never)
+    idle_timeout: Option<Duration>,
+    /// Lower Bound for the Retransmission Timeout
+    rto_min: Duration,
+    /// Upper Bound for the Retransmission Timeout
+    rto_max: Duration,
+    /// Time Spent in TIME-WAIT Before a Connection is Cleaned Up
+    time_wait_duration: Duration,
+    /// Time Spent in FIN-WAIT-2 Waiting for the Remote FIN Before a Connection is Cleaned Up
+    fin_wait_timeout: Duration,
+    /// Use the Timestamp Option for RTT Measurement and Protection Against Wrapped Sequence Numbers?
+    enable_timestamps: bool,
+    /// Grow and Shrink the Receive Window With the Bandwidth-Delay Product of the Path?
+    enable_window_autotune: bool,
+    /// Upper Bound for the Receive Window When Auto-Tuning It (in Bytes)
+    max_receive_window_size: u32,
+    /// Longest Time Small Pushes Are Held Back to Be Coalesced Into a Single Segment (None Disables Coalescing)
+    coalesce_timeout: Option<Duration>,
+    /// Keys That Sign Segments Exchanged With Each Remote Address With the MD5 Signature Option
+    #[cfg(feature = "tcp-md5")]
+    md5_keys: HashMap<Ipv4Addr, Vec<u8>>,
 }
 
 //==============================================================================
@@ -43,7 +94,8 @@ pub struct TcpConfig {
 
 /// Associate Functions for TCP Configuration Descriptor
 impl TcpConfig {
-    /// Creates a TCP Configuration Descriptor.
+    /// Creates a TCP Configuration Descriptor. Options that are not listed here are set through the setters of the
+    /// returned [TcpConfig].
     pub fn new(
         advertised_mss: Option<usize>,
         handshake_retries: Option<usize>,
@@ -89,6 +141,12 @@ impl TcpConfig {
         self.advertised_mss
     }
 
+    /// Gets the maximum segment size used for segmentation in the target [TcpConfig]. This is further clamped to the
+    /// maximum segment size advertised by the remote peer.
+    pub fn get_effective_mss(&self) -> usize {
+        self.effective_mss.unwrap_or(self.advertised_mss)
+    }
+
     /// Gets the number of TCP handshake retries in the target [TcpConfig].
     pub fn get_handshake_retries(&self) -> usize {
         self.handshake_retries
@@ -124,47 +182,251 @@ impl TcpConfig {
         self.rx_checksum_offload
     }
 
+    /// Gets the send pacing option in the target [TcpConfig].
+    pub fn get_enable_pacing(&self) -> bool {
+        self.enable_pacing
+    }
+
+    /// Gets the pacing rate (in bytes per second) in the target [TcpConfig]. A value of zero means that the rate is
+    /// derived from the congestion window and the round-trip time.
+    pub fn get_pacing_rate(&self) -> u64 {
+        self.pacing_rate
+    }
+
+    /// Gets the explicit congestion notification option in the target [TcpConfig].
+    pub fn get_enable_ecn(&self) -> bool {
+        self.enable_ecn
+    }
+
+    /// Gets the congestion control algorithm in the target [TcpConfig].
+    pub fn get_congestion_control(&self) -> CongestionControlType {
+        self.congestion_control
+    }
+
+    /// Gets the initial congestion window (in bytes) in the target [TcpConfig], if one was configured. This is only
+    /// honored by congestion control algorithms that limit sending.
+    pub fn get_initial_cwnd(&self) -> Option<u32> {
+        self.initial_cwnd
+    }
+
+    /// Gets the initial slow start threshold (in bytes) in the target [TcpConfig], if one was configured.
+    pub fn get_initial_ssthresh(&self) -> Option<u32> {
+        self.initial_ssthresh
+    }
+
+    /// Gets the idle connection timeout in the target [TcpConfig], if one was configured.
+    pub fn get_idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Gets the lower bound for the retransmission timeout in the target [TcpConfig].
+    pub fn get_rto_min(&self) -> Duration {
+        self.rto_min
+    }
+
+    /// Gets the upper bound for the retransmission timeout in the target [TcpConfig].
+    pub fn get_rto_max(&self) -> Duration {
+        self.rto_max
+    }
+
+    /// Gets the duration of the TIME-WAIT state in the target [TcpConfig].
+    pub fn get_time_wait_duration(&self) -> Duration {
+        self.time_wait_duration
+    }
+
+    /// Gets the timeout of the FIN-WAIT-2 state in the target [TcpConfig].
+    pub fn get_fin_wait_timeout(&self) -> Duration {
+        self.fin_wait_timeout
+    }
+
+    /// Gets the timestamp option in the target [TcpConfig].
+    pub fn get_enable_timestamps(&self) -> bool {
+        self.enable_timestamps
+    }
+
+    /// Gets the receive window auto-tuning option in the target [TcpConfig].
+    pub fn get_enable_window_autotune(&self) -> bool {
+        self.enable_window_autotune
+    }
+
+    /// Gets the upper bound for the receive window (in bytes) when auto-tuning it in the target [TcpConfig]. The
+    /// receive window never grows past what the window scale allows, whatever this bound.
+    pub fn get_max_receive_window_size(&self) -> u32 {
+        self.max_receive_window_size
+    }
+
+    /// Gets the longest time that small pushes are held back to be coalesced into a single segment in the target
+    /// [TcpConfig], if coalescing is enabled.
+    pub fn get_coalesce_timeout(&self) -> Option<Duration> {
+        self.coalesce_timeout
+    }
+
+    /// Gets the key that signs segments exchanged with `remote` in the target [TcpConfig], if one was configured.
+    #[cfg(feature = "tcp-md5")]
+    pub fn get_md5_key(&self, remote: &Ipv4Addr) -> Option<&[u8]> {
+        self.md5_keys.get(remote).map(|key| &key[..])
+    }
+
+    /// Sets the key that signs segments exchanged with `remote` in the target [TcpConfig]. An empty key stops signing
+    /// them.
+    #[cfg(feature = "tcp-md5")]
+    pub fn set_md5_key(&mut self, remote: Ipv4Addr, key: &[u8]) {
+        if key.is_empty() {
+            self.md5_keys.remove(&remote);
+        } else {
+            self.md5_keys.insert(remote, key.to_vec());
+        }
+    }
+
     /// Sets the advertised maximum segment size in the target [TcpConfig].
-    fn set_advertised_mss(mut self, value: usize) -> Self {
+    pub fn set_advertised_mss(mut self, value: usize) -> Self {
         assert!(value >= MIN_MSS);
         assert!(value <= MAX_MSS);
         self.advertised_mss = value;
         self
     }
 
+    /// Sets the maximum segment size used for segmentation in the target [TcpConfig]. Values out of the range that an
+    /// MSS may take are clamped to it.
+    pub fn set_effective_mss(mut self, value: usize) -> Self {
+        self.effective_mss = Some(value.clamp(MIN_MSS, MAX_MSS));
+        self
+    }
+
     /// Sets the number of TCP handshake retries in the target [TcpConfig].
-    fn set_handshake_retries(mut self, value: usize) -> Self {
+    pub fn set_handshake_retries(mut self, value: usize) -> Self {
         assert!(value > 0);
         self.handshake_retries = value;
         self
     }
 
     /// Sets the handshake TCP timeout in the target [TcpConfig].
-    fn set_handshake_timeout(mut self, value: Duration) -> Self {
+    pub fn set_handshake_timeout(mut self, value: Duration) -> Self {
         assert!(value > Duration::new(0, 0));
         self.handshake_timeout = value;
         self
     }
 
     /// Sets the receiver window size in the target [TcpConfig].
-    fn set_receive_window_size(mut self, value: u16) -> Self {
+    pub fn set_receive_window_size(mut self, value: u16) -> Self {
         assert!(value > 0);
         self.receive_window_size = value;
         self
     }
 
     /// Gets the window scale in the target [TcpConfig]
-    fn set_window_scale(mut self, value: u8) -> Self {
+    pub fn set_window_scale(mut self, value: u8) -> Self {
         self.window_scale = value;
         self
     }
 
     /// Sets the acknowledgement delay timeout in the target [TcpConfig].
-    fn set_ack_delay_timeout(mut self, value: Duration) -> Self {
+    pub fn set_ack_delay_timeout(mut self, value: Duration) -> Self {
         assert!(value <= Duration::from_millis(500));
         self.ack_delay_timeout = value;
         self
     }
+
+    /// Sets the send pacing option in the target [TcpConfig].
+    pub fn set_enable_pacing(mut self, value: bool) -> Self {
+        self.enable_pacing = value;
+        self
+    }
+
+    /// Sets the pacing rate (in bytes per second) in the target [TcpConfig].
+    pub fn set_pacing_rate(mut self, value: u64) -> Self {
+        self.pacing_rate = value;
+        self
+    }
+
+    /// Sets the explicit congestion notification option in the target [TcpConfig].
+    pub fn set_enable_ecn(mut self, value: bool) -> Self {
+        self.enable_ecn = value;
+        self
+    }
+
+    /// Sets the congestion control algorithm in the target [TcpConfig].
+    pub fn set_congestion_control(mut self, value: CongestionControlType) -> Self {
+        self.congestion_control = value;
+        self
+    }
+
+    /// Sets the initial congestion window in the target [TcpConfig].
+    pub fn set_initial_cwnd(mut self, value: u32) -> Self {
+        assert!(value > 0);
+        self.initial_cwnd = Some(value);
+        self
+    }
+
+    /// Sets the initial slow start threshold in the target [TcpConfig].
+    pub fn set_initial_ssthresh(mut self, value: u32) -> Self {
+        assert!(value > 0);
+        self.initial_ssthresh = Some(value);
+        self
+    }
+
+    /// Sets the idle connection timeout in the target [TcpConfig].
+    pub fn set_idle_timeout(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.idle_timeout = Some(value);
+        self
+    }
+
+    /// Sets the lower bound for the retransmission timeout in the target [TcpConfig].
+    pub fn set_rto_min(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        assert!(value <= self.rto_max);
+        self.rto_min = value;
+        self
+    }
+
+    /// Sets the upper bound for the retransmission timeout in the target [TcpConfig].
+    pub fn set_rto_max(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        assert!(value >= self.rto_min);
+        self.rto_max = value;
+        self
+    }
+
+    /// Sets the duration of the TIME-WAIT state in the target [TcpConfig].
+    pub fn set_time_wait_duration(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.time_wait_duration = value;
+        self
+    }
+
+    /// Sets the timeout of the FIN-WAIT-2 state in the target [TcpConfig].
+    pub fn set_fin_wait_timeout(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.fin_wait_timeout = value;
+        self
+    }
+
+    /// Sets the timestamp option in the target [TcpConfig].
+    pub fn set_enable_timestamps(mut self, value: bool) -> Self {
+        self.enable_timestamps = value;
+        self
+    }
+
+    /// Sets the receive window auto-tuning option in the target [TcpConfig].
+    pub fn set_enable_window_autotune(mut self, value: bool) -> Self {
+        self.enable_window_autotune = value;
+        self
+    }
+
+    /// Sets the upper bound for the receive window when auto-tuning it in the target [TcpConfig].
+    pub fn set_max_receive_window_size(mut self, value: u32) -> Self {
+        assert!(value > 0);
+        self.max_receive_window_size = value;
+        self
+    }
+
+    /// Sets the longest time that small pushes are held back to be coalesced in the target [TcpConfig].
+    pub fn set_coalesce_timeout(mut self, value: Duration) -> Self {
+        assert!(value > Duration::new(0, 0));
+        self.coalesce_timeout = Some(value);
+        self
+    }
 }
 
 //==============================================================================
@@ -177,6 +439,7 @@ impl Default for TcpConfig {
     fn default() -> Self {
         TcpConfig {
             advertised_mss: DEFAULT_MSS,
+            effective_mss: None,
             handshake_retries: 5,
             handshake_timeout: Duration::from_secs(3),
             receive_window_size: 0xffff,
@@ -184,6 +447,26 @@ impl Default for TcpConfig {
             window_scale: 0,
             rx_checksum_offload: false,
             tx_checksum_offload: false,
+            enable_pacing: false,
+            pacing_rate: 0,
+            enable_ecn: false,
+            congestion_control: CongestionControlType::None,
+            initial_cwnd: None,
+            initial_ssthresh: None,
+            idle_timeout: None,
+            // RFC 6298 suggests a lower bound of 1 second, but we use 1/10 of a second to better suit low-latency
+            // networks.
+            rto_min: Duration::from_millis(100),
+            // RFC 6298 suggests an upper bound of at least 60 seconds.
+            rto_max: Duration::from_secs(60),
+            time_wait_duration: 2 * MSL,
+            fin_wait_timeout: 2 * MSL,
+            enable_timestamps: false,
+            enable_window_autotune: false,
+            max_receive_window_size: 4 * 1024 * 1024,
+            coalesce_timeout: None,
+            #[cfg(feature = "tcp-md5")]
+            md5_keys: HashMap::new(),
         }
     }
 }
@@ -195,8 +478,16 @@ impl Default for TcpConfig {
 #[cfg(test)]
 mod tests {
     use crate::runtime::network::{
-        config::TcpConfig,
-        consts::DEFAULT_MSS,
+        config::{
+            CongestionControlType,
+            TcpConfig,
+        },
+        consts::{
+            DEFAULT_MSS,
+            MAX_MSS,
+            MIN_MSS,
+            MSL,
+        },
     };
     use ::anyhow::Result;
     use ::std::time::Duration;
@@ -206,13 +497,40 @@ mod tests {
     fn test_tcp_config_default() -> Result<()> {
         let config: TcpConfig = TcpConfig::default();
         crate::ensure_eq!(config.get_advertised_mss(), DEFAULT_MSS);
+        crate::ensure_eq!(config.get_effective_mss(), DEFAULT_MSS);
         crate::ensure_eq!(config.get_handshake_retries(), 5);
         crate::ensure_eq!(config.get_handshake_timeout(), Duration::from_secs(3));
         crate::ensure_eq!(config.get_receive_window_size(), 0xffff);
         crate::ensure_eq!(config.get_window_scale(), 0);
         crate::ensure_eq!(config.get_rx_checksum_offload(), false);
         crate::ensure_eq!(config.get_tx_checksum_offload(), false);
+        crate::ensure_eq!(config.get_enable_pacing(), false);
+        crate::ensure_eq!(config.get_pacing_rate(), 0);
+        crate::ensure_eq!(config.get_enable_ecn(), false);
+        crate::ensure_eq!(config.get_congestion_control(), CongestionControlType::None);
+        crate::ensure_eq!(config.get_initial_cwnd(), None);
+        crate::ensure_eq!(config.get_initial_ssthresh(), None);
+        crate::ensure_eq!(config.get_idle_timeout(), None);
+        crate::ensure_eq!(config.get_rto_min(), Duration::from_millis(100));
+        crate::ensure_eq!(config.get_rto_max(), Duration::from_secs(60));
+        crate::ensure_eq!(config.get_time_wait_duration(), 2 * MSL);
+        crate::ensure_eq!(config.get_fin_wait_timeout(), 2 * MSL);
+        crate::ensure_eq!(config.get_enable_timestamps(), false);
+        crate::ensure_eq!(config.get_enable_window_autotune(), false);
+        crate::ensure_eq!(config.get_max_receive_window_size(), 4 * 1024 * 1024);
+
+        Ok(())
+    }
 
+    /// Tests that the effective MSS is clamped to the range that an MSS may take.
+    #[test]
+    fn test_tcp_config_effective_mss_clamped() -> Result<()> {
+        crate::ensure_eq!(TcpConfig::default().set_effective_mss(500).get_effective_mss(), MIN_MSS);
+        crate::ensure_eq!(TcpConfig::default().set_effective_mss(1000).get_effective_mss(), 1000);
+        crate::ensure_eq!(
+            TcpConfig::default().set_effective_mss(MAX_MSS + 1).get_effective_mss(),
+            MAX_MSS
+        );
         Ok(())
     }
 }
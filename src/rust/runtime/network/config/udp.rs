@@ -1,6 +1,12 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::network::consts::DEFAULT_MTU;
+
 //==============================================================================
 // Constants & Structures
 //==============================================================================
@@ -12,6 +18,10 @@ pub struct UdpConfig {
     rx_checksum: bool,
     /// Offload Checksum to Hardware When Sending?
     tx_checksum: bool,
+    /// Compute Checksum When Sending? (Zero Checksums Are Sent Otherwise)
+    checksum: bool,
+    /// Maximum Transmission Unit of the Link (Larger Datagrams Are Sent in Fragments)
+    mtu: usize,
 }
 
 //==============================================================================
@@ -21,7 +31,12 @@ pub struct UdpConfig {
 /// Associate functions for UDP Configuration Descriptor
 impl UdpConfig {
     /// Creates a UDP Configuration Descriptor.
-    pub fn new(rx_checksum: Option<bool>, tx_checksum: Option<bool>) -> Self {
+    pub fn new(
+        rx_checksum: Option<bool>,
+        tx_checksum: Option<bool>,
+        checksum: Option<bool>,
+        mtu: Option<usize>,
+    ) -> Self {
         let mut config = Self::default();
         if let Some(rx_checksum) = rx_checksum {
             config.set_rx_checksum_offload(rx_checksum);
@@ -29,6 +44,12 @@ impl UdpConfig {
         if let Some(tx_checksum) = tx_checksum {
             config.set_tx_checksum_offload(tx_checksum);
         }
+        if let Some(checksum) = checksum {
+            config.set_checksum(checksum);
+        }
+        if let Some(mtu) = mtu {
+            config.set_mtu(mtu);
+        }
         config
     }
 
@@ -42,6 +63,16 @@ impl UdpConfig {
         self.tx_checksum
     }
 
+    /// Gets the checksum option in the target [UdpConfig].
+    pub fn get_checksum(&self) -> bool {
+        self.checksum
+    }
+
+    /// Gets the MTU option in the target [UdpConfig].
+    pub fn get_mtu(&self) -> usize {
+        self.mtu
+    }
+
     /// Sets the RX hardware checksum offload option in the target [UdpConfig].
     fn set_rx_checksum_offload(&mut self, rx_checksum: bool) {
         self.rx_checksum = rx_checksum;
@@ -51,6 +82,16 @@ impl UdpConfig {
     fn set_tx_checksum_offload(&mut self, tx_checksum: bool) {
         self.tx_checksum = tx_checksum;
     }
+
+    /// Sets the checksum option in the target [UdpConfig].
+    fn set_checksum(&mut self, checksum: bool) {
+        self.checksum = checksum;
+    }
+
+    /// Sets the MTU option in the target [UdpConfig].
+    fn set_mtu(&mut self, mtu: usize) {
+        self.mtu = mtu;
+    }
 }
 
 //==============================================================================
@@ -64,6 +105,8 @@ impl Default for UdpConfig {
         UdpConfig {
             rx_checksum: false,
             tx_checksum: false,
+            checksum: true,
+            mtu: DEFAULT_MTU,
         }
     }
 }
@@ -75,6 +118,7 @@ impl Default for UdpConfig {
 #[cfg(test)]
 mod tests {
     use super::UdpConfig;
+    use crate::runtime::network::consts::DEFAULT_MTU;
     use ::anyhow::Result;
 
     /// Tests default instantiation for [UdpConfig].
@@ -83,6 +127,8 @@ mod tests {
         let config: UdpConfig = UdpConfig::default();
         crate::ensure_eq!(config.get_rx_checksum_offload(), false);
         crate::ensure_eq!(config.get_tx_checksum_offload(), false);
+        crate::ensure_eq!(config.get_checksum(), true);
+        crate::ensure_eq!(config.get_mtu(), DEFAULT_MTU);
 
         Ok(())
     }
@@ -90,9 +136,11 @@ mod tests {
     /// Tests custom instantiation for [UdpConfig].
     #[test]
     fn test_udp_config_custom() -> Result<()> {
-        let config: UdpConfig = UdpConfig::new(Some(true), Some(true));
+        let config: UdpConfig = UdpConfig::new(Some(true), Some(true), Some(false), Some(9000));
         crate::ensure_eq!(config.get_rx_checksum_offload(), true);
         crate::ensure_eq!(config.get_tx_checksum_offload(), true);
+        crate::ensure_eq!(config.get_checksum(), false);
+        crate::ensure_eq!(config.get_mtu(), 9000);
 
         Ok(())
     }
@@ -1,6 +1,12 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+//==============================================================================
+// Imports
+//==============================================================================
+
+use ::std::time::Duration;
+
 //==============================================================================
 // Constants
 //==============================================================================
@@ -19,6 +25,16 @@ pub const MAX_MSS: usize = u16::max_value() as usize;
 /// TODO: Auto-Discovery MTU Size
 pub const DEFAULT_MSS: usize = 1450;
 
+/// Default MTU of network links (in bytes, not including the Ethernet header)
+///
+/// TODO: Auto-Discovery MTU Size
+pub const DEFAULT_MTU: usize = 1500;
+
+/// Maximum Segment Lifetime for TCP
+///
+/// RFC 9293 suggests 2 minutes, but we follow Linux, whose 60-second TIME-WAIT amounts to 30 seconds.
+pub const MSL: Duration = Duration::from_secs(30);
+
 /// Length of a [crate::memory::DemiBuffer] batch.
 ///
 /// TODO: This Should be Generic
@@ -12,15 +12,16 @@ use ::rand::prelude::{
     SliceRandom,
     SmallRng,
 };
+use ::std::collections::VecDeque;
 
 //======================================================================================================================
 // Constants
 //======================================================================================================================
 
 /// First private port. See https://datatracker.ietf.org/doc/html/rfc6335 for details.
-const FIRST_PRIVATE_PORT: u16 = 49152;
+pub const FIRST_PRIVATE_PORT: u16 = 49152;
 /// Last private port. See https://datatracker.ietf.org/doc/html/rfc6335 for details.
-const LAST_PRIVATE_PORT: u16 = 65535;
+pub const LAST_PRIVATE_PORT: u16 = 65535;
 /// Seed number for ephemeral port allocator.
 #[cfg(not(debug_assertions))]
 const EPHEMERAL_PORT_SEED: u64 = 12345;
@@ -29,8 +30,15 @@ const EPHEMERAL_PORT_SEED: u64 = 12345;
 // Structures
 //======================================================================================================================
 
+/// Ephemeral port allocator. Ports are handed out in a round-robin fashion, so that a port that was just released is
+/// only reused once all other ports of the range were.
 pub struct EphemeralPorts {
-    ports: Vec<u16>,
+    /// First port of the ephemeral port range.
+    first: u16,
+    /// Last port of the ephemeral port range.
+    last: u16,
+    /// Ports that are free, in the order in which they are allocated.
+    ports: VecDeque<u16>,
 }
 
 //======================================================================================================================
@@ -38,15 +46,35 @@ pub struct EphemeralPorts {
 //======================================================================================================================
 
 impl EphemeralPorts {
+    /// Creates an ephemeral port allocator that manages ports `first` to `last`, inclusive.
+    pub fn new(first: u16, last: u16) -> Result<Self, Fail> {
+        if first == 0 || first > last {
+            let cause: String = format!("invalid ephemeral port range (first={:?}, last={:?})", first, last);
+            error!("new(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+
+        #[cfg(debug_assertions)]
+        let ports: VecDeque<u16> = (first..=last).collect();
+        #[cfg(not(debug_assertions))]
+        let ports: VecDeque<u16> = {
+            let mut ports: Vec<u16> = (first..=last).collect();
+            let mut rng: SmallRng = SmallRng::seed_from_u64(EPHEMERAL_PORT_SEED);
+            ports.shuffle(&mut rng);
+            ports.into()
+        };
+        Ok(Self { first, last, ports })
+    }
+
     /// Asserts wether a port is in the ephemeral port range.
-    pub fn is_private(port: u16) -> bool {
-        port >= FIRST_PRIVATE_PORT
+    pub fn is_private(&self, port: u16) -> bool {
+        port >= self.first && port <= self.last
     }
 
     /// Allocates any ephemeral port from the pool.
     pub fn alloc(&mut self) -> Result<u16, Fail> {
-        self.ports.pop().ok_or(Fail::new(
-            libc::EADDRINUSE,
+        self.ports.pop_front().ok_or(Fail::new(
+            libc::EADDRNOTAVAIL,
             "all port numbers in the ephemeral port range are currently in use",
         ))
     }
@@ -67,7 +95,7 @@ impl EphemeralPorts {
     /// Releases a ephemeral port.
     pub fn free(&mut self, port: u16) -> Result<(), Fail> {
         // Check if port is in the valid range.
-        if !Self::is_private(port) {
+        if !self.is_private(port) {
             let cause: String = format!("port {} is not in the ephemeral port range", port);
             error!("free(): {}", &cause);
             return Err(Fail::new(libc::EINVAL, &cause));
@@ -80,7 +108,7 @@ impl EphemeralPorts {
             return Err(Fail::new(libc::EFAULT, &cause));
         }
 
-        self.ports.push(port);
+        self.ports.push_back(port);
 
         Ok(())
     }
@@ -93,16 +121,7 @@ impl EphemeralPorts {
 impl Default for EphemeralPorts {
     /// Creates a new ephemeral port allocator.
     fn default() -> Self {
-        let mut ports: Vec<u16> = Vec::<u16>::new();
-        for port in (FIRST_PRIVATE_PORT..=LAST_PRIVATE_PORT).rev() {
-            ports.push(port);
-        }
-        #[cfg(not(debug_assertions))]
-        {
-            let mut rng: SmallRng = SmallRng::seed_from_u64(EPHEMERAL_PORT_SEED);
-            ports.shuffle(&mut rng);
-        }
-        Self { ports }
+        Self::new(FIRST_PRIVATE_PORT, LAST_PRIVATE_PORT).expect("private port range should be valid")
     }
 }
 
@@ -223,6 +242,52 @@ mod test {
         Ok(())
     }
 
+    /// Allocates ports from a small range, and checks that allocation goes around the range and fails once it is full.
+    #[test]
+    fn test_alloc_custom_range() -> Result<()> {
+        const FIRST: u16 = 50000;
+        const LAST: u16 = 50002;
+        let mut ports: EphemeralPorts = EphemeralPorts::new(FIRST, LAST)?;
+
+        // Invalid ranges are rejected.
+        crate::ensure_eq!(EphemeralPorts::new(LAST, FIRST).is_err(), true);
+        crate::ensure_eq!(EphemeralPorts::new(0, LAST).is_err(), true);
+
+        // Only ports in the range are managed.
+        crate::ensure_eq!(ports.is_private(FIRST - 1), false);
+        crate::ensure_eq!(ports.is_private(LAST + 1), false);
+
+        // Releasing a port right away does not get it back: allocation goes through every other port first.
+        let first: u16 = ports.alloc()?;
+        let mut seen: Vec<u16> = vec![first];
+        ports.free(first)?;
+        for _ in FIRST..LAST {
+            let port: u16 = ports.alloc()?;
+            crate::ensure_eq!(seen.contains(&port), false);
+            seen.push(port);
+            ports.free(port)?;
+        }
+        seen.sort();
+        crate::ensure_eq!(seen, (FIRST..=LAST).collect::<Vec<u16>>());
+        crate::ensure_eq!(ports.alloc()?, first);
+        ports.free(first)?;
+
+        // Allocate the whole range, and then some.
+        for _ in FIRST..=LAST {
+            let port: u16 = ports.alloc()?;
+            crate::ensure_eq!(ports.is_private(port), true);
+        }
+        match ports.alloc() {
+            Err(e) if e.errno == libc::EADDRNOTAVAIL => {},
+            r => anyhow::bail!(
+                "allocation should fail with EADDRNOTAVAIL once the range is full (result={:?})",
+                r
+            ),
+        }
+
+        Ok(())
+    }
+
     /// Attempts to release a port that is not allocated.
     #[test]
     fn test_free_unallocated_port() -> Result<()> {
@@ -0,0 +1,461 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    memory::DemiBuffer,
+    network::{
+        NetworkRuntime,
+        PacketBuf,
+    },
+    timer::SharedTimer,
+    SharedBox,
+};
+use ::arrayvec::ArrayVec;
+use ::rand::{
+    prelude::SmallRng,
+    Rng,
+    SeedableRng,
+};
+use ::std::{
+    collections::VecDeque,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Fault Injection Configuration Descriptor
+#[derive(Clone, Debug)]
+pub struct FaultConfig {
+    /// Probability of Dropping a Packet
+    drop_rate: f64,
+    /// Probability of Delaying a Packet
+    delay_rate: f64,
+    /// Minimum Delay Applied to a Delayed Packet
+    min_delay: Duration,
+    /// Maximum Delay Applied to a Delayed Packet
+    max_delay: Duration,
+    /// Probability of Swapping a Packet With the One That Follows It
+    reorder_rate: f64,
+    /// Seed for the Random Number Generator
+    seed: u64,
+}
+
+/// Network runtime that wraps another network runtime and injects packet drops, delays, and reorderings into both
+/// directions of traffic. Delays are measured against `timer`, so they follow the same (possibly virtual) clock as the
+/// rest of the stack.
+pub struct FaultyNetworkRuntime<const N: usize> {
+    inner: SharedBox<dyn NetworkRuntime<N>>,
+    timer: SharedTimer,
+    config: FaultConfig,
+    rng: SmallRng,
+    /// Outgoing packets that are being delayed, along with the time at which they are released.
+    tx_delayed: VecDeque<(Instant, Box<dyn PacketBuf>)>,
+    /// Outgoing packet that is being held back to be sent after the next one.
+    tx_held: Option<Box<dyn PacketBuf>>,
+    /// Incoming packets that are being delayed, along with the time at which they are released.
+    rx_delayed: VecDeque<(Instant, DemiBuffer)>,
+    /// Incoming packet that is being held back to be delivered after the next one.
+    rx_held: Option<DemiBuffer>,
+}
+
+/// Fate of a packet that goes through the fault injection layer.
+enum Fate {
+    Drop,
+    Delay(Instant),
+    Reorder,
+    Forward,
+}
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+/// Associate Functions for Fault Injection Configuration Descriptor
+impl FaultConfig {
+    /// Creates a Fault Injection Configuration Descriptor.
+    pub fn new(
+        drop_rate: Option<f64>,
+        delay_rate: Option<f64>,
+        min_delay: Option<Duration>,
+        max_delay: Option<Duration>,
+        reorder_rate: Option<f64>,
+        seed: Option<u64>,
+    ) -> Self {
+        let mut config: Self = Self::default();
+
+        if let Some(value) = drop_rate {
+            assert!((0.0..=1.0).contains(&value));
+            config.drop_rate = value;
+        }
+        if let Some(value) = delay_rate {
+            assert!((0.0..=1.0).contains(&value));
+            config.delay_rate = value;
+        }
+        if let Some(value) = min_delay {
+            config.min_delay = value;
+        }
+        if let Some(value) = max_delay {
+            config.max_delay = value;
+        }
+        assert!(config.min_delay <= config.max_delay);
+        if let Some(value) = reorder_rate {
+            assert!((0.0..=1.0).contains(&value));
+            config.reorder_rate = value;
+        }
+        if let Some(value) = seed {
+            config.seed = value;
+        }
+
+        config
+    }
+
+    /// Gets the drop rate in the target [FaultConfig].
+    pub fn get_drop_rate(&self) -> f64 {
+        self.drop_rate
+    }
+
+    /// Gets the delay rate in the target [FaultConfig].
+    pub fn get_delay_rate(&self) -> f64 {
+        self.delay_rate
+    }
+
+    /// Gets the minimum delay in the target [FaultConfig].
+    pub fn get_min_delay(&self) -> Duration {
+        self.min_delay
+    }
+
+    /// Gets the maximum delay in the target [FaultConfig].
+    pub fn get_max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    /// Gets the reorder rate in the target [FaultConfig].
+    pub fn get_reorder_rate(&self) -> f64 {
+        self.reorder_rate
+    }
+
+    /// Gets the random number generator seed in the target [FaultConfig].
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// Associate Functions for Faulty Network Runtime
+impl<const N: usize> FaultyNetworkRuntime<N> {
+    /// Wraps `inner` into a network runtime that injects faults according to `config`.
+    pub fn new(inner: SharedBox<dyn NetworkRuntime<N>>, timer: SharedTimer, config: FaultConfig) -> Self {
+        let rng: SmallRng = SmallRng::seed_from_u64(config.get_seed());
+        Self {
+            inner,
+            timer,
+            config,
+            rng,
+            tx_delayed: VecDeque::new(),
+            tx_held: None,
+            rx_delayed: VecDeque::new(),
+            rx_held: None,
+        }
+    }
+
+    /// Decides what happens to the next packet.
+    fn roll(&mut self) -> Fate {
+        if self.rng.gen_bool(self.config.get_drop_rate()) {
+            return Fate::Drop;
+        }
+        if self.rng.gen_bool(self.config.get_delay_rate()) {
+            let delay: Duration = if self.config.get_min_delay() < self.config.get_max_delay() {
+                self.rng
+                    .gen_range(self.config.get_min_delay()..=self.config.get_max_delay())
+            } else {
+                self.config.get_min_delay()
+            };
+            return Fate::Delay(self.timer.now() + delay);
+        }
+        if self.rng.gen_bool(self.config.get_reorder_rate()) {
+            return Fate::Reorder;
+        }
+        Fate::Forward
+    }
+
+    /// Transmits outgoing packets whose delay has expired.
+    fn release_tx(&mut self) {
+        let now: Instant = self.timer.now();
+        while let Some((release_time, _)) = self.tx_delayed.front() {
+            if *release_time > now {
+                break;
+            }
+            let (_, pkt): (Instant, Box<dyn PacketBuf>) = self.tx_delayed.pop_front().expect("queue is not empty");
+            self.inner.transmit(pkt);
+        }
+    }
+
+    /// Moves incoming packets whose delay has expired into `batch`, as long as there is room for them.
+    fn release_rx(&mut self, batch: &mut ArrayVec<DemiBuffer, N>) {
+        let now: Instant = self.timer.now();
+        while let Some((release_time, _)) = self.rx_delayed.front() {
+            if *release_time > now || batch.is_full() {
+                break;
+            }
+            let (_, buf): (Instant, DemiBuffer) = self.rx_delayed.pop_front().expect("queue is not empty");
+            batch.push(buf);
+        }
+    }
+
+    /// Holds back incoming packet `buf` until `release_time`.
+    fn delay_rx(&mut self, release_time: Instant, buf: DemiBuffer) {
+        // Keep the queue sorted by release time.
+        let index: usize = self.rx_delayed.partition_point(|(t, _)| *t <= release_time);
+        self.rx_delayed.insert(index, (release_time, buf));
+    }
+
+    /// Delivers `buf` into `batch`, or defers it to the next call if there is no room for it.
+    fn deliver_rx(&mut self, batch: &mut ArrayVec<DemiBuffer, N>, buf: DemiBuffer) {
+        if let Err(e) = batch.try_push(buf) {
+            // Packets that are still delayed may be ahead in the queue, so this one has to go in front of them.
+            let now: Instant = self.timer.now();
+            self.delay_rx(now, e.element());
+        }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+/// Default Trait Implementation for Fault Injection Configuration Descriptor
+impl Default for FaultConfig {
+    /// Creates a Fault Injection Configuration Descriptor that does not inject any faults.
+    fn default() -> Self {
+        FaultConfig {
+            drop_rate: 0.0,
+            delay_rate: 0.0,
+            min_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            reorder_rate: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Network Runtime Trait Implementation for Faulty Network Runtime
+impl<const N: usize> NetworkRuntime<N> for FaultyNetworkRuntime<N> {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        self.release_tx();
+
+        match self.roll() {
+            Fate::Drop => trace!("transmit(): dropping packet"),
+            Fate::Delay(release_time) => {
+                trace!("transmit(): delaying packet");
+                // Keep the queue sorted by release time.
+                let index: usize = self.tx_delayed.partition_point(|(t, _)| *t <= release_time);
+                self.tx_delayed.insert(index, (release_time, pkt));
+            },
+            Fate::Reorder if self.tx_held.is_none() => {
+                trace!("transmit(): reordering packet");
+                self.tx_held = Some(pkt);
+            },
+            Fate::Reorder | Fate::Forward => {
+                self.inner.transmit(pkt);
+                if let Some(held) = self.tx_held.take() {
+                    self.inner.transmit(held);
+                }
+            },
+        }
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, N> {
+        // Do not hold back outgoing packets for longer than one poll of the network.
+        self.release_tx();
+        if let Some(held) = self.tx_held.take() {
+            self.inner.transmit(held);
+        }
+
+        let mut batch: ArrayVec<DemiBuffer, N> = ArrayVec::new();
+        self.release_rx(&mut batch);
+
+        for buf in self.inner.receive() {
+            match self.roll() {
+                Fate::Drop => trace!("receive(): dropping packet"),
+                Fate::Delay(release_time) => {
+                    trace!("receive(): delaying packet");
+                    self.delay_rx(release_time, buf);
+                },
+                Fate::Reorder if self.rx_held.is_none() => {
+                    trace!("receive(): reordering packet");
+                    self.rx_held = Some(buf);
+                },
+                Fate::Reorder | Fate::Forward => {
+                    self.deliver_rx(&mut batch, buf);
+                    if let Some(held) = self.rx_held.take() {
+                        self.deliver_rx(&mut batch, held);
+                    }
+                },
+            }
+        }
+
+        // Nothing followed the held packet in this batch, so deliver it now.
+        if let Some(held) = self.rx_held.take() {
+            self.deliver_rx(&mut batch, held);
+        }
+
+        batch
+    }
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        FaultConfig,
+        FaultyNetworkRuntime,
+    };
+    use crate::runtime::{
+        memory::DemiBuffer,
+        network::{
+            NetworkRuntime,
+            PacketBuf,
+        },
+        timer::SharedTimer,
+        SharedBox,
+        SharedObject,
+    };
+    use ::anyhow::Result;
+    use ::arrayvec::ArrayVec;
+    use ::std::time::{
+        Duration,
+        Instant,
+    };
+
+    const BATCH_SIZE: usize = 4;
+
+    /// Packet with no contents.
+    struct EmptyPacket;
+
+    /// Network runtime that counts transmitted packets and always receives a full batch.
+    #[derive(Clone)]
+    struct CountingRuntime(SharedObject<usize>);
+
+    impl PacketBuf for EmptyPacket {
+        fn header_size(&self) -> usize {
+            0
+        }
+
+        fn write_header(&self, _buf: &mut [u8]) {}
+
+        fn body_size(&self) -> usize {
+            0
+        }
+
+        fn take_body(&self) -> Option<DemiBuffer> {
+            None
+        }
+    }
+
+    impl NetworkRuntime<BATCH_SIZE> for CountingRuntime {
+        fn transmit(&mut self, _pkt: Box<dyn PacketBuf>) {
+            *self.0 += 1;
+        }
+
+        fn receive(&mut self) -> ArrayVec<DemiBuffer, BATCH_SIZE> {
+            let mut batch: ArrayVec<DemiBuffer, BATCH_SIZE> = ArrayVec::new();
+            while !batch.is_full() {
+                batch.push(DemiBuffer::new(1));
+            }
+            batch
+        }
+    }
+
+    /// Wraps a counting runtime into a faulty runtime with the given configuration.
+    fn setup(config: FaultConfig) -> (CountingRuntime, SharedTimer, FaultyNetworkRuntime<BATCH_SIZE>) {
+        let inner: CountingRuntime = CountingRuntime(SharedObject::new(0));
+        let timer: SharedTimer = SharedTimer::new(Instant::now());
+        let faulty: FaultyNetworkRuntime<BATCH_SIZE> = FaultyNetworkRuntime::new(
+            SharedBox::<dyn NetworkRuntime<BATCH_SIZE>>::new(Box::new(inner.clone())),
+            timer.clone(),
+            config,
+        );
+        (inner, timer, faulty)
+    }
+
+    /// Tests that no packets make it through when the drop rate is 100%.
+    #[test]
+    fn drop_all_packets() -> Result<()> {
+        let (inner, _, mut faulty) = setup(FaultConfig::new(Some(1.0), None, None, None, None, None));
+
+        for _ in 0..16 {
+            faulty.transmit(Box::new(EmptyPacket));
+        }
+        crate::ensure_eq!(*inner.0, 0);
+        crate::ensure_eq!(faulty.receive().len(), 0);
+
+        Ok(())
+    }
+
+    /// Tests that all packets make it through when the drop rate is 0%.
+    #[test]
+    fn drop_no_packets() -> Result<()> {
+        let (inner, _, mut faulty) = setup(FaultConfig::new(Some(0.0), None, None, None, None, None));
+
+        for _ in 0..16 {
+            faulty.transmit(Box::new(EmptyPacket));
+        }
+        crate::ensure_eq!(*inner.0, 16);
+        crate::ensure_eq!(faulty.receive().len(), BATCH_SIZE);
+
+        Ok(())
+    }
+
+    /// Tests that delayed packets are held back until their delay expires.
+    #[test]
+    fn delay_packets() -> Result<()> {
+        let delay: Duration = Duration::from_millis(10);
+        let (inner, mut timer, mut faulty) =
+            setup(FaultConfig::new(None, Some(1.0), Some(delay), Some(delay), None, None));
+
+        faulty.transmit(Box::new(EmptyPacket));
+        crate::ensure_eq!(*inner.0, 0);
+        crate::ensure_eq!(faulty.receive().len(), 0);
+
+        let now: Instant = timer.now() + delay;
+        timer.advance_clock(now);
+        crate::ensure_eq!(faulty.receive().len(), BATCH_SIZE);
+        crate::ensure_eq!(*inner.0, 1);
+
+        Ok(())
+    }
+
+    /// Tests that packets that do not fit in a batch are delivered next, ahead of packets that are still delayed.
+    #[test]
+    fn overflow_packets_go_before_delayed_ones() -> Result<()> {
+        let (_, timer, mut faulty) = setup(FaultConfig::default());
+        let now: Instant = timer.now();
+
+        // A packet is still delayed when a batch overflows.
+        faulty.delay_rx(now + Duration::from_millis(10), DemiBuffer::new(2));
+        let mut batch: ArrayVec<DemiBuffer, BATCH_SIZE> = ArrayVec::new();
+        while !batch.is_full() {
+            batch.push(DemiBuffer::new(1));
+        }
+        faulty.deliver_rx(&mut batch, DemiBuffer::new(3));
+
+        // The packet that overflowed comes first in the next batch.
+        let batch: ArrayVec<DemiBuffer, BATCH_SIZE> = faulty.receive();
+        crate::ensure_eq!(batch.len(), BATCH_SIZE);
+        crate::ensure_eq!(batch[0].len(), 3);
+        crate::ensure_eq!(batch.iter().all(|buf| buf.len() != 2), true);
+
+        Ok(())
+    }
+}
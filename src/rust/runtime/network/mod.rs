@@ -8,8 +8,10 @@
 pub mod config;
 pub mod consts;
 pub mod ephemeral;
+pub mod faulty;
 pub mod ring;
 pub mod socket;
+pub mod tap;
 pub mod types;
 
 //======================================================================================================================
@@ -24,6 +26,7 @@ use crate::runtime::{
 };
 use ::arrayvec::ArrayVec;
 use ::std::{
+    any::Any,
     collections::HashMap,
     net::{
         SocketAddr,
@@ -109,6 +112,11 @@ pub trait PacketBuf {
     fn body_size(&self) -> usize;
     /// Consumes and returns the body of the target [PacketBuf].
     fn take_body(&self) -> Option<DemiBuffer>;
+    /// Returns the target [PacketBuf] as [Any], so that it can be inspected and rewritten as its concrete type. Packets
+    /// that do not allow for this return `None`.
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
 }
 
 /// Network Runtime
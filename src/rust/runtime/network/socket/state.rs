@@ -17,7 +17,7 @@ use ::socket2::Type;
 
 /// States of a Socket.
 #[derive(Copy, Clone, Debug, PartialEq)]
-enum SocketState {
+pub enum SocketState {
     /// A socket that is not bound.
     Unbound,
     /// A socket that is bound to a local address.
@@ -67,6 +67,11 @@ impl SocketStateMachine {
         }
     }
 
+    /// Gets the current state of the target socket.
+    pub fn get_state(&self) -> SocketState {
+        self.current
+    }
+
     /// Asserts whether the target may continue accepting connections.
     pub fn may_accept(&self) -> Result<(), Fail> {
         self.ensure_not_closing()?;
@@ -0,0 +1,143 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    memory::DemiBuffer,
+    network::{
+        NetworkRuntime,
+        PacketBuf,
+    },
+    SharedBox,
+    SharedObject,
+};
+use ::arrayvec::ArrayVec;
+use ::std::cell::Cell;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Direction of a packet that goes through a packet tap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Packet was transmitted.
+    Outgoing,
+    /// Packet was received.
+    Incoming,
+}
+
+/// Packet tap that is shared by the network runtime and whoever installs the observer.
+#[derive(Clone)]
+pub struct SharedPacketTap(SharedObject<Option<Box<dyn FnMut(Direction, &[u8])>>>);
+
+/// Network runtime that wraps another network runtime and shows a copy of every Ethernet frame that goes through it, in
+/// either direction, to a packet tap. Frames are only copied while an observer is installed on the tap.
+pub struct TapNetworkRuntime<const N: usize> {
+    inner: SharedBox<dyn NetworkRuntime<N>>,
+    tap: SharedPacketTap,
+}
+
+/// Outgoing packet whose body was already taken out to show it to a packet tap.
+struct TappedPacket {
+    pkt: Box<dyn PacketBuf>,
+    body: Cell<Option<DemiBuffer>>,
+}
+
+//======================================================================================================================
+// Associate Functions
+//======================================================================================================================
+
+/// Associate Functions for Packet Taps
+impl SharedPacketTap {
+    /// Installs `f` as the observer of the target packet tap, replacing any previous one.
+    pub fn set(&mut self, f: Box<dyn FnMut(Direction, &[u8])>) {
+        *self.0 = Some(f);
+    }
+
+    /// Checks if an observer is installed on the target packet tap.
+    pub fn is_set(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Shows `frame` to the observer of the target packet tap, if any.
+    pub fn observe(&mut self, direction: Direction, frame: &[u8]) {
+        if let Some(f) = &mut *self.0 {
+            f(direction, frame);
+        }
+    }
+}
+
+/// Associate Functions for Tap Network Runtime
+impl<const N: usize> TapNetworkRuntime<N> {
+    /// Wraps `inner` into a network runtime that shows all frames to `tap`.
+    pub fn new(inner: SharedBox<dyn NetworkRuntime<N>>, tap: SharedPacketTap) -> Self {
+        Self { inner, tap }
+    }
+}
+
+//======================================================================================================================
+// Trait Implementations
+//======================================================================================================================
+
+/// Default Trait Implementation for Packet Taps
+impl Default for SharedPacketTap {
+    /// Creates a packet tap with no observer.
+    fn default() -> Self {
+        Self(SharedObject::new(None))
+    }
+}
+
+/// Packet Buffer Trait Implementation for Tapped Packets
+impl PacketBuf for TappedPacket {
+    fn header_size(&self) -> usize {
+        self.pkt.header_size()
+    }
+
+    fn write_header(&self, buf: &mut [u8]) {
+        self.pkt.write_header(buf)
+    }
+
+    fn body_size(&self) -> usize {
+        self.pkt.body_size()
+    }
+
+    fn take_body(&self) -> Option<DemiBuffer> {
+        self.body.take()
+    }
+}
+
+/// Network Runtime Trait Implementation for Tap Network Runtime
+impl<const N: usize> NetworkRuntime<N> for TapNetworkRuntime<N> {
+    fn transmit(&mut self, pkt: Box<dyn PacketBuf>) {
+        if !self.tap.is_set() {
+            return self.inner.transmit(pkt);
+        }
+
+        // Assemble the frame, holding on to the body so that it can still be transmitted afterwards.
+        let header_size: usize = pkt.header_size();
+        let mut frame: Vec<u8> = vec![0; header_size + pkt.body_size()];
+        pkt.write_header(&mut frame[..header_size]);
+        let body: Option<DemiBuffer> = pkt.take_body();
+        if let Some(body) = &body {
+            frame[header_size..].copy_from_slice(&body[..]);
+        }
+        self.tap.observe(Direction::Outgoing, &frame);
+
+        self.inner.transmit(Box::new(TappedPacket {
+            pkt,
+            body: Cell::new(body),
+        }));
+    }
+
+    fn receive(&mut self) -> ArrayVec<DemiBuffer, N> {
+        let batch: ArrayVec<DemiBuffer, N> = self.inner.receive();
+        for buf in &batch {
+            self.tap.observe(Direction::Incoming, &buf[..]);
+        }
+        batch
+    }
+}
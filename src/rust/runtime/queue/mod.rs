@@ -5,6 +5,8 @@ mod operation_result;
 mod qdesc;
 mod qtoken;
 mod qtype;
+mod queue_info;
+mod readiness;
 
 //======================================================================================================================
 // Imports
@@ -12,6 +14,7 @@ mod qtype;
 
 use crate::runtime::{
     fail::Fail,
+    limits,
     scheduler::TaskWithResult,
 };
 use ::slab::{
@@ -33,6 +36,8 @@ pub use self::{
     qdesc::QDesc,
     qtoken::QToken,
     qtype::QType,
+    queue_info::QueueInfo,
+    readiness::Readiness,
 };
 
 // Coroutine for running an operation on an I/O Queue.
@@ -59,8 +64,18 @@ pub trait NetworkQueue: IoQueue {
 }
 
 /// I/O queue descriptors table.
+///
+/// Entries of the table are reused once released, and so are the I/O queue descriptors that refer to them by default.
+/// Optionally, I/O queue descriptors may be tagged with the generation of their entry, which is bumped whenever the
+/// entry is released. Stale I/O queue descriptors then fail to refer to a queue that reuses their entry.
 pub struct IoQueueTable {
     table: Slab<Box<dyn IoQueue>>,
+    /// Maximum number of queues that may be allocated at once.
+    max_queues: usize,
+    /// Indicates whether I/O queue descriptors are tagged with the generation of their entry.
+    tag_qds: bool,
+    /// Generation of each entry of the table. Only used if I/O queue descriptors are tagged.
+    generations: Vec<u32>,
 }
 
 //======================================================================================================================
@@ -77,9 +92,44 @@ impl IoQueueTable {
     ///
     /// NOTE: This is intentionally set to be half of FD_SETSIZE (1024) in Linux.
     const BASE_QD: u32 = 500;
+    /// Mask for the generation of a tagged I/O queue descriptor, which is stored above its index. Generations wrap
+    /// around so that I/O queue descriptors remain positive when they are converted to file descriptors.
+    const GENERATION_MASK: u32 = (1 << (31 - Self::INDEX_BITS)) - 1;
+    /// Number of low bits of a tagged I/O queue descriptor that refer to an entry of the table. This is enough to hold
+    /// [limits::QUEUES_MAX] entries past [Self::BASE_QD].
+    const INDEX_BITS: u32 = 21;
+
+    /// Creates an I/O queue descriptors table that holds at most `max_queues` entries.
+    pub fn new(max_queues: usize) -> Self {
+        Self {
+            table: Slab::<Box<dyn IoQueue>>::new(),
+            max_queues,
+            tag_qds: false,
+            generations: Vec::new(),
+        }
+    }
+
+    /// Sets the maximum number of entries in the target I/O queue descriptors table. Entries that are already allocated
+    /// are not affected.
+    pub fn set_max_queues(&mut self, max_queues: usize) {
+        self.max_queues = max_queues;
+    }
+
+    /// Sets whether I/O queue descriptors are tagged with the generation of their entry. This should be set before any
+    /// entry is allocated, because it changes how I/O queue descriptors are resolved.
+    pub fn set_tag_qds(&mut self, tag_qds: bool) {
+        debug_assert!(self.table.is_empty());
+        self.tag_qds = tag_qds;
+    }
 
     /// Allocates a new entry in the target I/O queue descriptors table.
-    pub fn alloc<T: IoQueue>(&mut self, queue: T) -> QDesc {
+    pub fn alloc<T: IoQueue>(&mut self, queue: T) -> Result<QDesc, Fail> {
+        if self.table.len() >= self.max_queues {
+            let cause: String = format!("too many open queues (max_queues={:?})", self.max_queues);
+            error!("alloc(): {}", &cause);
+            return Err(Fail::new(libc::EMFILE, &cause));
+        }
+
         let index: usize = self.table.insert(Box::new(queue));
 
         // Ensure that the allocation would yield to a safe conversion between usize to u32.
@@ -89,7 +139,20 @@ impl IoQueueTable {
             "I/O descriptors table overflow"
         );
 
-        QDesc::from((index as u32) + Self::BASE_QD)
+        if self.tag_qds {
+            // Tagged I/O queue descriptors have fewer bits for the index.
+            if (index as u32) + Self::BASE_QD >= (1 << Self::INDEX_BITS) {
+                self.table.remove(index);
+                let cause: String = format!("too many open queues for tagged queue descriptors (index={:?})", index);
+                error!("alloc(): {}", &cause);
+                return Err(Fail::new(libc::EMFILE, &cause));
+            }
+            if index >= self.generations.len() {
+                self.generations.resize(index + 1, 0);
+            }
+        }
+
+        Ok(self.make_qd(index))
     }
 
     /// Gets the type of the queue.
@@ -162,6 +225,11 @@ impl IoQueueTable {
                 return Err(Fail::new(libc::EBADF, &cause));
             },
         };
+        // Stale I/O queue descriptors for this entry should no longer refer to it.
+        if self.tag_qds {
+            let generation: &mut u32 = &mut self.generations[index as usize];
+            *generation = (*generation + 1) & Self::GENERATION_MASK;
+        }
         Ok(downcast_queue::<T>(self.table.remove(index as usize))?)
     }
 
@@ -170,19 +238,46 @@ impl IoQueueTable {
         self.table.iter()
     }
 
+    /// Gets the I/O queue descriptors of all registered queues.
+    pub fn get_qds(&self) -> Vec<QDesc> {
+        self.table.iter().map(|(index, _)| self.make_qd(index)).collect()
+    }
+
     pub fn drain(&mut self) -> slab::Drain<'_, Box<dyn IoQueue>> {
         self.table.drain()
     }
 
+    /// Builds the I/O queue descriptor that refers to a given index in the I/O queue descriptors table.
+    fn make_qd(&self, index: usize) -> QDesc {
+        let rawqd: u32 = (index as u32) + Self::BASE_QD;
+        if self.tag_qds {
+            QDesc::from((self.generations[index] << Self::INDEX_BITS) | rawqd)
+        } else {
+            QDesc::from(rawqd)
+        }
+    }
+
     /// Gets the index in the I/O queue descriptors table to which a given I/O queue descriptor refers to.
     fn get_index(&self, qd: &QDesc) -> Option<u32> {
-        if Into::<u32>::into(*qd) < Self::BASE_QD {
+        let (qd, generation): (u32, Option<u32>) = if self.tag_qds {
+            let qd: u32 = Into::<u32>::into(*qd);
+            (qd & ((1 << Self::INDEX_BITS) - 1), Some(qd >> Self::INDEX_BITS))
+        } else {
+            (Into::<u32>::into(*qd), None)
+        };
+        if qd < Self::BASE_QD {
             None
         } else {
-            let rawqd: u32 = Into::<u32>::into(*qd) - Self::BASE_QD;
+            let rawqd: u32 = qd - Self::BASE_QD;
             if !self.table.contains(rawqd as usize) {
                 return None;
             }
+            // Reject stale I/O queue descriptors, which refer to a previous generation of the entry.
+            if let Some(generation) = generation {
+                if self.generations[rawqd as usize] != generation {
+                    return None;
+                }
+            }
             Some(rawqd)
         }
     }
@@ -244,9 +339,7 @@ pub fn downcast_queue<T: IoQueue>(boxed_queue: Box<dyn IoQueue>) -> Result<T, Fa
 
 impl Default for IoQueueTable {
     fn default() -> Self {
-        Self {
-            table: Slab::<Box<dyn IoQueue>>::new(),
-        }
+        Self::new(limits::QUEUES_MAX)
     }
 }
 
@@ -264,6 +357,7 @@ mod tests {
         QDesc,
         QType,
     };
+    use ::anyhow::Result;
     use ::std::any::Any;
     use ::test::{
         black_box,
@@ -289,12 +383,34 @@ mod tests {
         }
     }
 
+    /// Tests if a stale queue descriptor fails instead of referring to a queue that reuses its entry.
+    #[test]
+    fn stale_tagged_qd() -> Result<()> {
+        let mut ioqueue_table: IoQueueTable = IoQueueTable::default();
+        ioqueue_table.set_tag_qds(true);
+
+        let stale_qd: QDesc = ioqueue_table.alloc::<TestQueue>(TestQueue {})?;
+        ioqueue_table.free::<TestQueue>(&stale_qd)?;
+        let qd: QDesc = ioqueue_table.alloc::<TestQueue>(TestQueue {})?;
+
+        // The entry is reused, but under a different queue descriptor.
+        crate::ensure_neq!(qd, stale_qd);
+        crate::ensure_eq!(ioqueue_table.get::<TestQueue>(&qd).is_ok(), true);
+        crate::ensure_eq!(ioqueue_table.get::<TestQueue>(&stale_qd).is_err(), true);
+        crate::ensure_eq!(ioqueue_table.get_mut::<TestQueue>(&stale_qd).is_err(), true);
+        crate::ensure_eq!(ioqueue_table.get_qds(), vec![qd]);
+
+        Ok(())
+    }
+
     #[bench]
     fn bench_alloc_free(b: &mut Bencher) {
         let mut ioqueue_table: IoQueueTable = IoQueueTable::default();
 
         b.iter(|| {
-            let qd: QDesc = ioqueue_table.alloc::<TestQueue>(TestQueue {});
+            let qd: QDesc = ioqueue_table
+                .alloc::<TestQueue>(TestQueue {})
+                .expect("must not be full");
             black_box(qd);
             let queue: TestQueue = ioqueue_table.free::<TestQueue>(&qd).expect("must be TestQueue");
             black_box(queue);
@@ -25,7 +25,10 @@ pub enum OperationResult {
     Accept((QDesc, SocketAddrV4)),
     Push,
     Pop(Option<SocketAddrV4>, DemiBuffer),
+    PopBatch(Vec<(Option<SocketAddrV4>, DemiBuffer)>),
     Close,
+    Splice(usize),
+    PopStream(usize),
     Failed(Fail),
 }
 
@@ -40,7 +43,10 @@ impl fmt::Debug for OperationResult {
             OperationResult::Accept(..) => write!(f, "Accept"),
             OperationResult::Push => write!(f, "Push"),
             OperationResult::Pop(..) => write!(f, "Pop"),
+            OperationResult::PopBatch(ref datagrams) => write!(f, "PopBatch({:?})", datagrams.len()),
             OperationResult::Close => write!(f, "Close"),
+            OperationResult::Splice(nbytes) => write!(f, "Splice({:?})", nbytes),
+            OperationResult::PopStream(nbytes) => write!(f, "PopStream({:?})", nbytes),
             OperationResult::Failed(ref e) => write!(f, "Failed({:?})", e),
         }
     }
@@ -0,0 +1,32 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::runtime::{
+    network::socket::state::SocketState,
+    QDesc,
+    QType,
+};
+use ::std::net::SocketAddrV4;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Read-only snapshot of an I/O queue, used for diagnostics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueueInfo {
+    /// I/O queue descriptor.
+    pub qd: QDesc,
+    /// Type of the I/O queue.
+    pub qtype: QType,
+    /// Local address to which the queue is bound, if any.
+    pub local: Option<SocketAddrV4>,
+    /// Remote address to which the queue is connected, if any.
+    pub remote: Option<SocketAddrV4>,
+    /// Connection state of the queue, if tracked by the underlying LibOS.
+    pub state: Option<SocketState>,
+}
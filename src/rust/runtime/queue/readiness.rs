@@ -0,0 +1,19 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Readiness of an I/O queue, so that callers can pick the operations that would make progress right away.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Readiness {
+    /// Would a pop complete without waiting?
+    pub readable: bool,
+    /// Would a push complete without waiting?
+    pub writable: bool,
+    /// Is an error pending on the queue?
+    pub error: bool,
+    /// Was the queue closed by the remote end?
+    pub hangup: bool,
+}
@@ -48,7 +48,10 @@ pub use self::{
         YielderHandle,
     },
     mutex::Mutex,
-    scheduler::Scheduler,
+    scheduler::{
+        InsertError,
+        Scheduler,
+    },
     task::{
         Task,
         TaskWithResult,
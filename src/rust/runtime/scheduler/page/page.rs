@@ -64,6 +64,12 @@ impl WakerPage {
         notified
     }
 
+    /// Queries whether or not any future in the target [WakerPage] that has not completed is notified. Unlike
+    /// [Self::take_notified], this leaves notification flags untouched.
+    pub fn has_notified(&self) -> bool {
+        self.notified.load() & !self.completed.load() != 0
+    }
+
     /// Queries whether or not the completed flag for the `ix` future in the target [WakerPage] is set.
     pub fn has_completed(&self, ix: usize) -> bool {
         debug_assert!(ix < WAKER_BIT_LENGTH);
@@ -13,17 +13,20 @@
 
 use crate::{
     collections::pin_slab::PinSlab,
-    runtime::scheduler::{
-        page::{
-            WakerPageRef,
-            WakerRef,
-        },
-        waker64::{
-            WAKER_BIT_LENGTH,
-            WAKER_BIT_LENGTH_SHIFT,
+    runtime::{
+        fail::Fail,
+        scheduler::{
+            page::{
+                WakerPageRef,
+                WakerRef,
+            },
+            waker64::{
+                WAKER_BIT_LENGTH,
+                WAKER_BIT_LENGTH_SHIFT,
+            },
+            Task,
+            TaskHandle,
         },
-        Task,
-        TaskHandle,
     },
 };
 use ::bit_iter::BitIter;
@@ -33,7 +36,11 @@ use ::rand::{
     SeedableRng,
 };
 use ::std::{
-    collections::HashMap,
+    any::TypeId,
+    collections::{
+        HashMap,
+        HashSet,
+    },
     future::Future,
     pin::Pin,
     ptr::NonNull,
@@ -52,7 +59,11 @@ use ::std::{
 /// This value was chosen arbitrarily.
 #[cfg(debug_assertions)]
 const SCHEDULER_SEED: u64 = 42;
-const MAX_NUM_TASKS: usize = 16000;
+/// Default number of tasks that a scheduler holds at the same time.
+pub const DEFAULT_SCHEDULER_CAPACITY: usize = 16000;
+/// Upper bound for the capacity of a scheduler. Task ids are 16 bits long, and once that address space is half full,
+/// it becomes increasingly difficult to avoid collisions.
+pub const MAX_SCHEDULER_CAPACITY: usize = 1 << 15;
 const MAX_RETRIES_TASK_ID_ALLOC: usize = 500;
 
 //======================================================================================================================
@@ -65,10 +76,29 @@ pub struct Scheduler {
     tasks: PinSlab<Box<dyn Task>>,
     /// Maps between externally meaningful ids and the index of the task in the slab.
     task_ids: HashMap<u64, usize>,
+    /// Maps back from the index of a task in the slab to its id.
+    slab_task_ids: HashMap<usize, u64>,
+    /// Indexes in the slab of the tasks that have completed but were not reported as such yet.
+    completed: HashSet<usize>,
     /// Holds the waker bits for controlling task scheduling.
     waker_page_refs: Vec<WakerPageRef>,
     /// Small random number generator for tokens.
     rng: SmallRng,
+    /// Maximum number of tasks that are held at the same time.
+    capacity: usize,
+    /// Index of the waker page that the next budgeted poll starts from, so that tasks on later pages get their turn
+    /// even if tasks on earlier pages use up the budget every time.
+    next_waker_page_index: usize,
+}
+
+/// Reasons why a task cannot be inserted into a scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertError {
+    /// The scheduler holds as many tasks as its capacity allows. Either tasks complete or the capacity is raised
+    /// before new ones fit.
+    Full,
+    /// No room was found for the task this time, but a later attempt may succeed.
+    Transient,
 }
 
 //======================================================================================================================
@@ -85,6 +115,8 @@ impl Scheduler {
             .task_ids
             .remove(&task_id)
             .expect("Token should be in the token table");
+        self.slab_task_ids.remove(&pin_slab_index);
+        self.completed.remove(&pin_slab_index);
         let (waker_page_ref, waker_page_offset): (&WakerPageRef, usize) = {
             let (waker_page_index, waker_page_offset) = self.get_waker_page_index_and_offset(pin_slab_index);
             (&self.waker_page_refs[waker_page_index], waker_page_offset)
@@ -122,13 +154,18 @@ impl Scheduler {
     }
 
     /// Insert a new task into our scheduler returning a handle corresponding to it.
-    pub fn insert<F: Task>(&mut self, future: F) -> Option<TaskHandle> {
-        self.panic_if_too_many_tasks();
+    pub fn insert<F: Task>(&mut self, future: F) -> Result<TaskHandle, InsertError> {
+        if self.task_ids.len() >= self.capacity {
+            warn!("insert(): scheduler is full (capacity={:?})", self.capacity);
+            return Err(InsertError::Full);
+        }
 
         let task_name: String = future.get_name();
+        let task_id: u64 = self.get_new_task_id().ok_or(InsertError::Transient)?;
         // The pin slab index can be reverse-computed in a page index and an offset within the page.
-        let pin_slab_index: usize = self.tasks.insert(Box::new(future))?;
-        let task_id: u64 = self.get_new_task_id(pin_slab_index);
+        let pin_slab_index: usize = self.tasks.insert(Box::new(future)).ok_or(InsertError::Transient)?;
+        self.task_ids.insert(task_id, pin_slab_index);
+        self.slab_task_ids.insert(pin_slab_index, task_id);
 
         self.add_new_pages_up_to_pin_slab_index(pin_slab_index);
 
@@ -145,30 +182,69 @@ impl Scheduler {
             task_id,
             pin_slab_index
         );
-        Some(TaskHandle::new(task_id, waker_page_ref.clone(), waker_page_offset))
+        Ok(TaskHandle::new(task_id, waker_page_ref.clone(), waker_page_offset))
     }
 
-    /// Generate a new id. If the id is currently in use, keep generating until we find an unused id.
-    fn get_new_task_id(&mut self, pin_slab_index: usize) -> u64 {
-        let new_task_id: u64 = 'get_id: {
-            for _ in 0..MAX_RETRIES_TASK_ID_ALLOC {
-                let new_task_id: u64 = self.rng.next_u64() as u16 as u64;
-                if !self.task_ids.contains_key(&new_task_id) {
-                    self.task_ids.insert(new_task_id, pin_slab_index);
-                    break 'get_id new_task_id;
-                }
+    /// Returns the ids of up to `max` tasks of type `T` that have completed but were not removed from the target
+    /// scheduler yet. Each task is only returned once.
+    pub fn get_completed_task_ids<T: Task>(&mut self, max: usize) -> Vec<u64> {
+        let mut pin_slab_indexes: Vec<usize> = Vec::new();
+        for pin_slab_index in &self.completed {
+            if pin_slab_indexes.len() >= max {
+                break;
             }
-            panic!("Could not find a valid task id");
-        };
-        new_task_id
+            match self.tasks.get(*pin_slab_index) {
+                Some(task) if (**task).type_id() == TypeId::of::<T>() => pin_slab_indexes.push(*pin_slab_index),
+                _ => continue,
+            }
+        }
+        pin_slab_indexes
+            .into_iter()
+            .map(|pin_slab_index| {
+                self.completed.remove(&pin_slab_index);
+                self.slab_task_ids[&pin_slab_index]
+            })
+            .collect()
     }
 
-    /// If the address space for task ids is close to half full, it will become increasingly difficult to avoid
-    /// collisions, so we cap the number of tasks.
-    fn panic_if_too_many_tasks(&self) {
-        if self.task_ids.len() > MAX_NUM_TASKS {
-            panic!("Too many concurrent tasks");
+    /// Returns the number of tasks that the target scheduler currently holds.
+    pub fn num_tasks(&self) -> usize {
+        self.task_ids.len()
+    }
+
+    /// Returns the maximum number of tasks that the target scheduler holds at the same time.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Sets the maximum number of tasks that the target scheduler holds at the same time. The capacity cannot go beyond
+    /// [MAX_SCHEDULER_CAPACITY], nor below the number of tasks that are currently held.
+    pub fn set_capacity(&mut self, capacity: usize) -> Result<(), Fail> {
+        if capacity > MAX_SCHEDULER_CAPACITY || capacity < self.task_ids.len() {
+            let cause: String = format!(
+                "invalid scheduler capacity (capacity={:?}, max={:?}, tasks={:?})",
+                capacity,
+                MAX_SCHEDULER_CAPACITY,
+                self.task_ids.len()
+            );
+            error!("set_capacity(): {}", &cause);
+            return Err(Fail::new(libc::EINVAL, &cause));
+        }
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    /// Generate a new id. If the id is currently in use, keep generating until we find an unused id. Gives up after
+    /// [MAX_RETRIES_TASK_ID_ALLOC] attempts.
+    fn get_new_task_id(&mut self) -> Option<u64> {
+        for _ in 0..MAX_RETRIES_TASK_ID_ALLOC {
+            let new_task_id: u64 = self.rng.next_u64() as u16 as u64;
+            if !self.task_ids.contains_key(&new_task_id) {
+                return Some(new_task_id);
+            }
         }
+        warn!("get_new_task_id(): could not find a free task id");
+        None
     }
 
     /// Computes the page and page offset of a given task based on its total offset.
@@ -197,6 +273,45 @@ impl Scheduler {
         }
     }
 
+    /// Poll at most `max_tasks` futures which are ready to run again, and return the number of futures that were
+    /// polled. Ready futures that are not polled due to the budget remain notified, so they will be polled in a
+    /// subsequent call. Calls take turns on which waker page they start from.
+    pub fn poll_with_budget(&mut self, max_tasks: usize) -> usize {
+        let mut num_polled: usize = 0;
+        let num_waker_pages = self.get_num_waker_pages();
+        for i in 0..num_waker_pages {
+            let waker_page_index: usize = (self.next_waker_page_index + i) % num_waker_pages;
+            if num_polled >= max_tasks {
+                // Start from this page next time, so that tasks on the pages before it do not keep it from running.
+                self.next_waker_page_index = waker_page_index;
+                break;
+            }
+            let notified_offsets: u64 = self.get_offsets_for_ready_tasks(waker_page_index);
+
+            // Split ready tasks into the ones that fit in the remaining budget and the ones that do not.
+            let mut budgeted_offsets: u64 = 0;
+            for waker_page_offset in BitIter::from(notified_offsets) {
+                if num_polled >= max_tasks {
+                    // Re-notify this task, so that we do not lose track of it.
+                    self.waker_page_refs[waker_page_index].notify(waker_page_offset);
+                } else {
+                    budgeted_offsets |= 1 << waker_page_offset;
+                    num_polled += 1;
+                }
+            }
+
+            self.poll_notified_tasks(waker_page_index, budgeted_offsets);
+        }
+        num_polled
+    }
+
+    /// Returns whether or not any task is ready to run again, that is, whether the next poll would have work to do.
+    pub fn has_ready_tasks(&self) -> bool {
+        self.waker_page_refs
+            .iter()
+            .any(|waker_page_ref| waker_page_ref.has_notified())
+    }
+
     fn get_num_waker_pages(&self) -> usize {
         self.waker_page_refs.len()
     }
@@ -209,12 +324,13 @@ impl Scheduler {
     fn poll_notified_tasks(&mut self, waker_page_index: usize, notified_offsets: u64) {
         for waker_page_offset in BitIter::from(notified_offsets) {
             // Get the pinned ref.
+            let pin_slab_index: usize = Scheduler::get_pin_slab_index(waker_page_index, waker_page_offset);
             let pinned_ptr = {
-                let pin_slab_index: usize = Scheduler::get_pin_slab_index(waker_page_index, waker_page_offset);
-                let pinned_ref: Pin<&mut Box<dyn Task>> = self
-                    .tasks
-                    .get_pin_mut(pin_slab_index)
-                    .expect(format!("Invalid offset: {:?}", pin_slab_index).as_str());
+                let pinned_ref: Pin<&mut Box<dyn Task>> = match self.tasks.get_pin_mut(pin_slab_index) {
+                    Some(pinned_ref) => pinned_ref,
+                    // The task was removed by a task that was polled before it in this round.
+                    None => continue,
+                };
                 let pinned_ptr = unsafe { Pin::into_inner_unchecked(pinned_ref) as *mut _ };
                 pinned_ptr
             };
@@ -231,7 +347,8 @@ impl Scheduler {
             // Poll future.
             let poll_result: Poll<()> = Future::poll(pinned_ref, &mut waker_context);
             if let Poll::Ready(()) = poll_result {
-                self.waker_page_refs[waker_page_index].mark_completed(waker_page_offset)
+                self.waker_page_refs[waker_page_index].mark_completed(waker_page_offset);
+                self.completed.insert(pin_slab_index);
             }
         }
     }
@@ -256,11 +373,15 @@ impl Default for Scheduler {
         Self {
             tasks: PinSlab::new(),
             task_ids: HashMap::<u64, usize>::new(),
+            slab_task_ids: HashMap::<usize, u64>::new(),
+            completed: HashSet::<usize>::new(),
             waker_page_refs: vec![],
             #[cfg(debug_assertions)]
             rng: SmallRng::seed_from_u64(SCHEDULER_SEED),
             #[cfg(not(debug_assertions))]
             rng: SmallRng::from_entropy(),
+            capacity: DEFAULT_SCHEDULER_CAPACITY,
+            next_waker_page_index: 0,
         }
     }
 }
@@ -273,14 +394,19 @@ impl Default for Scheduler {
 mod tests {
     use crate::runtime::scheduler::{
         scheduler::{
+            InsertError,
             Scheduler,
             TaskHandle,
         },
         task::TaskWithResult,
+        waker64::WAKER_BIT_LENGTH,
     };
     use ::anyhow::Result;
     use ::std::{
-        future::Future,
+        future::{
+            self,
+            Future,
+        },
         pin::Pin,
         task::{
             Context,
@@ -320,6 +446,18 @@ mod tests {
         }
     }
 
+    /// Coroutine that never completes, and asks to be polled again every time.
+    struct HotCoroutine {}
+
+    impl Future for HotCoroutine {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+            ctx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
     type DummyTask = TaskWithResult<()>;
 
     /// Tests if when inserting multiple tasks into the scheduler at once each, of them gets a unique identifier.
@@ -330,16 +468,16 @@ mod tests {
         // Insert a task and make sure the task id is not a simple counter.
         let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(0)));
         let handle: TaskHandle = match scheduler.insert(task) {
-            Some(handle) => handle,
-            None => anyhow::bail!("insert() failed"),
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
         };
         let task_id: u64 = handle.get_task_id();
 
         // Insert another task and make sure the task id is not sequentially after the previous one.
         let task2: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(0)));
         let handle2: TaskHandle = match scheduler.insert(task2) {
-            Some(handle) => handle,
-            None => anyhow::bail!("insert() failed"),
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
         };
         let task_id2: u64 = handle2.get_task_id();
         crate::ensure_neq!(task_id2, task_id);
@@ -347,6 +485,37 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn insert_into_full_scheduler_succeeds_after_raising_capacity() -> Result<()> {
+        let mut scheduler: Scheduler = Scheduler::default();
+        scheduler.set_capacity(4)?;
+        crate::ensure_eq!(scheduler.capacity(), 4);
+
+        // Saturate the scheduler with tasks that do not complete.
+        let mut handles: Vec<TaskHandle> = Vec::new();
+        for _ in 0..4 {
+            let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(1)));
+            match scheduler.insert(task) {
+                Ok(handle) => handles.push(handle),
+                Err(_) => anyhow::bail!("insert() failed"),
+            }
+        }
+
+        // The scheduler is full, so the next task does not fit.
+        let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(1)));
+        crate::ensure_eq!(scheduler.insert(task).err(), Some(InsertError::Full));
+
+        // The capacity cannot go below the number of tasks that are held.
+        crate::ensure_eq!(scheduler.set_capacity(3).is_err(), true);
+
+        // Once the capacity is raised, the task fits.
+        scheduler.set_capacity(8)?;
+        let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(1)));
+        crate::ensure_eq!(scheduler.insert(task).is_ok(), true);
+
+        Ok(())
+    }
+
     #[test]
     fn poll_once_with_one_small_task_completes_it() -> Result<()> {
         let mut scheduler: Scheduler = Scheduler::default();
@@ -354,8 +523,8 @@ mod tests {
         // Insert a single future in the scheduler. This future shall complete with a single poll operation.
         let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(0)));
         let handle: TaskHandle = match scheduler.insert(task) {
-            Some(handle) => handle,
-            None => anyhow::bail!("insert() failed"),
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
         };
 
         // All futures are inserted in the scheduler with notification flag set.
@@ -375,8 +544,8 @@ mod tests {
         // with two poll operations.
         let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(1)));
         let handle: TaskHandle = match scheduler.insert(task) {
-            Some(handle) => handle,
-            None => anyhow::bail!("insert() failed"),
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
         };
 
         // All futures are inserted in the scheduler with notification flag set.
@@ -393,6 +562,32 @@ mod tests {
         Ok(())
     }
 
+    /// Tests that a task is reported as ready for as long as it has been notified, and not once it completed.
+    #[test]
+    fn has_ready_tasks_tracks_notifications() -> Result<()> {
+        let mut scheduler: Scheduler = Scheduler::default();
+        crate::ensure_eq!(scheduler.has_ready_tasks(), false);
+
+        // Tasks are inserted with their notification flag set.
+        let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(1)));
+        let handle: TaskHandle = match scheduler.insert(task) {
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
+        };
+        crate::ensure_eq!(scheduler.has_ready_tasks(), true);
+
+        // The future wakes itself up when first polled, so it is still ready.
+        scheduler.poll();
+        crate::ensure_eq!(scheduler.has_ready_tasks(), true);
+
+        // Once it completes, nothing is left to run.
+        scheduler.poll();
+        crate::ensure_eq!(handle.has_completed(), true);
+        crate::ensure_eq!(scheduler.has_ready_tasks(), false);
+
+        Ok(())
+    }
+
     /// Tests if consecutive tasks are not assigned the same task id.
     #[test]
     fn insert_consecutive_creates_unique_task_ids() -> Result<()> {
@@ -401,8 +596,8 @@ mod tests {
         // Create and run a task.
         let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(0)));
         let handle: TaskHandle = match scheduler.insert(task) {
-            Some(handle) => handle,
-            None => anyhow::bail!("insert() failed"),
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
         };
         let task_id: u64 = handle.clone().get_task_id();
         scheduler.poll();
@@ -413,8 +608,8 @@ mod tests {
         // Create another task.
         let task2: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(0)));
         let handle2: TaskHandle = match scheduler.insert(task2) {
-            Some(handle) => handle,
-            None => anyhow::bail!("insert() failed"),
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
         };
         let task_id2: u64 = handle2.get_task_id();
 
@@ -424,6 +619,101 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn poll_with_budget_polls_at_most_budgeted_tasks() -> Result<()> {
+        let mut scheduler: Scheduler = Scheduler::default();
+        const NUM_TASKS: usize = 10;
+        const BUDGET: usize = 4;
+        let mut handles: Vec<TaskHandle> = Vec::<TaskHandle>::with_capacity(NUM_TASKS);
+
+        // Insert futures that complete with a single poll operation.
+        for _ in 0..NUM_TASKS {
+            let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(0)));
+            let handle: TaskHandle = match scheduler.insert(task) {
+                Ok(handle) => handle,
+                Err(_) => anyhow::bail!("insert() failed"),
+            };
+            handles.push(handle);
+        }
+
+        // Each poll should only advance as many futures as the budget allows.
+        crate::ensure_eq!(scheduler.poll_with_budget(BUDGET), BUDGET);
+        crate::ensure_eq!(handles.iter().filter(|h| h.has_completed()).count(), BUDGET);
+        crate::ensure_eq!(scheduler.poll_with_budget(BUDGET), BUDGET);
+        crate::ensure_eq!(handles.iter().filter(|h| h.has_completed()).count(), 2 * BUDGET);
+        crate::ensure_eq!(scheduler.poll_with_budget(BUDGET), NUM_TASKS - 2 * BUDGET);
+        crate::ensure_eq!(handles.iter().filter(|h| h.has_completed()).count(), NUM_TASKS);
+
+        // Nothing is left to run.
+        crate::ensure_eq!(scheduler.poll_with_budget(BUDGET), 0);
+
+        Ok(())
+    }
+
+    /// Tests that a task that is ready all the time does not keep a task on a later waker page from running, when
+    /// polls are budgeted.
+    #[test]
+    fn poll_with_budget_takes_turns_on_waker_pages() -> Result<()> {
+        let mut scheduler: Scheduler = Scheduler::default();
+
+        // The first task never completes, and asks to be polled again every time. The last one lands on the second
+        // waker page, and completes with its second poll. Tasks in between never wake up again.
+        let hot: DummyTask = DummyTask::new(String::from("hot"), Box::pin(HotCoroutine {}));
+        if scheduler.insert(hot).is_err() {
+            anyhow::bail!("insert() failed");
+        }
+        for _ in 1..WAKER_BIT_LENGTH {
+            let idle: DummyTask = DummyTask::new(String::from("idle"), Box::pin(future::pending()));
+            if scheduler.insert(idle).is_err() {
+                anyhow::bail!("insert() failed");
+            }
+        }
+        let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(1)));
+        let handle: TaskHandle = match scheduler.insert(task) {
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
+        };
+        scheduler.poll();
+
+        // The hot task and the last task now take turns.
+        crate::ensure_eq!(scheduler.poll_with_budget(1), 1);
+        crate::ensure_eq!(scheduler.poll_with_budget(1), 1);
+        crate::ensure_eq!(handle.has_completed(), true);
+
+        Ok(())
+    }
+
+    /// Tests that completed tasks are reported as soon as they complete, and only once.
+    #[test]
+    fn get_completed_task_ids_reports_each_task_once() -> Result<()> {
+        let mut scheduler: Scheduler = Scheduler::default();
+        let mut handles: Vec<TaskHandle> = Vec::<TaskHandle>::with_capacity(2);
+        for val in 0..2 {
+            let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(val)));
+            let handle: TaskHandle = match scheduler.insert(task) {
+                Ok(handle) => handle,
+                Err(_) => anyhow::bail!("insert() failed"),
+            };
+            handles.push(handle);
+        }
+
+        // Only the first task completes with the first poll.
+        crate::ensure_eq!(scheduler.get_completed_task_ids::<DummyTask>(16).len(), 0);
+        scheduler.poll();
+        crate::ensure_eq!(
+            scheduler.get_completed_task_ids::<DummyTask>(16),
+            vec![handles[0].get_task_id()]
+        );
+        crate::ensure_eq!(scheduler.get_completed_task_ids::<DummyTask>(16).len(), 0);
+
+        // The second task completes with the second poll, but is not reported once removed.
+        scheduler.poll();
+        scheduler.remove(&handles[1]);
+        crate::ensure_eq!(scheduler.get_completed_task_ids::<DummyTask>(16).len(), 0);
+
+        Ok(())
+    }
+
     #[test]
     fn remove_removes_task_id() -> Result<()> {
         let mut scheduler: Scheduler = Scheduler::default();
@@ -436,8 +726,8 @@ mod tests {
         for val in 0..NUM_TASKS {
             let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(val)));
             let handle: TaskHandle = match scheduler.insert(task) {
-                Some(handle) => handle,
-                None => panic!("insert() failed"),
+                Ok(handle) => handle,
+                Err(_) => panic!("insert() failed"),
             };
             handles.push(handle);
         }
@@ -476,8 +766,8 @@ mod tests {
         let mut scheduler: Scheduler = Scheduler::default();
         let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(42)));
         let handle: TaskHandle = match scheduler.insert(task) {
-            Some(handle) => handle,
-            None => anyhow::bail!("insert() failed"),
+            Ok(handle) => handle,
+            Err(_) => anyhow::bail!("insert() failed"),
         };
         let task_id: u64 = handle.get_task_id();
         match scheduler.from_task_id(task_id) {
@@ -508,8 +798,8 @@ mod tests {
         for val in 0..NUM_TASKS {
             let task: DummyTask = DummyTask::new(String::from("testing"), Box::pin(DummyCoroutine::new(val)));
             let handle: TaskHandle = match scheduler.insert(task) {
-                Some(handle) => handle,
-                None => panic!("insert() failed"),
+                Ok(handle) => handle,
+                Err(_) => panic!("insert() failed"),
             };
             handles.push(handle);
         }
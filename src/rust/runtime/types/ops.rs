@@ -30,6 +30,9 @@ pub enum demi_opcode_t {
     DEMI_OPC_CONNECT,
     DEMI_OPC_CLOSE,
     DEMI_OPC_FAILED,
+    DEMI_OPC_SPLICE,
+    DEMI_OPC_POP_STREAM,
+    DEMI_OPC_POP_BATCH,
 }
 
 /// Result for `accept()`
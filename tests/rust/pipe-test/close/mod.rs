@@ -7,9 +7,32 @@
 
 use ::anyhow::Result;
 use ::demikernel::{
+    runtime::types::{
+        demi_opcode_t,
+        demi_qresult_t,
+        demi_sgarray_t,
+    },
     LibOS,
     QDesc,
+    QToken,
 };
+use ::std::{
+    slice,
+    time::Duration,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Sizes of the buffers that are pushed before closing a pipe. Some of them do not fit in a single pop.
+const PUSH_SIZES: [usize; 3] = [100, 10000, 1];
+
+/// Number of bytes in each pop.
+const POP_SIZE: usize = 1000;
+
+/// Timeout for waiting on operations.
+const TIMEOUT: Duration = Duration::from_secs(1);
 
 //======================================================================================================================
 // Standalone Functions
@@ -24,6 +47,10 @@ pub fn run(libos: &mut LibOS, pipe_name: &str) -> Vec<(String, String, Result<()
         result,
         demikernel::run_test!(close_pipe_multiple_times(libos, pipe_name))
     );
+    demikernel::collect_test!(
+        result,
+        demikernel::run_test!(close_after_push_delivers_data_before_eof(libos, pipe_name))
+    );
 
     result
 }
@@ -71,3 +98,53 @@ fn close_pipe_multiple_times(libos: &mut LibOS, pipe_name: &str) -> Result<()> {
         Err(e) => anyhow::bail!("close() failed ({})", e),
     }
 }
+
+/// Pushes data and closes the pipe right away, and checks that every byte is popped before end of file.
+fn close_after_push_delivers_data_before_eof(libos: &mut LibOS, pipe_name: &str) -> Result<()> {
+    let name: String = format!("{}-close-after-push", pipe_name);
+    let pipeqd_tx: QDesc = libos.create_pipe(&name)?;
+    let pipeqd_rx: QDesc = libos.open_pipe(&name)?;
+
+    // Push buffers filled with a running counter, and close without waiting for anything to be popped.
+    let mut expected: Vec<u8> = Vec::new();
+    let mut qts: Vec<QToken> = Vec::new();
+    for size in PUSH_SIZES {
+        let sga: demi_sgarray_t = libos.sgaalloc(size)?;
+        let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
+        let data: &mut [u8] = unsafe { slice::from_raw_parts_mut(ptr, size) };
+        for byte in data.iter_mut() {
+            *byte = expected.len() as u8;
+            expected.push(*byte);
+        }
+        qts.push(libos.push(pipeqd_tx, &sga)?);
+        libos.sgafree(sga)?;
+    }
+    for qt in qts {
+        let qr: demi_qresult_t = libos.wait(qt, Some(TIMEOUT))?;
+        demikernel::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+    }
+    libos.close(pipeqd_tx)?;
+
+    // Pop in small pieces until end of file, which is signaled by a zero-length pop.
+    let mut received: Vec<u8> = Vec::new();
+    loop {
+        let qt: QToken = libos.pop(pipeqd_rx, Some(POP_SIZE))?;
+        let qr: demi_qresult_t = libos.wait(qt, Some(TIMEOUT))?;
+        demikernel::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+        let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        let ptr: *const u8 = sga.sga_segs[0].sgaseg_buf as *const u8;
+        let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+        received.extend_from_slice(unsafe { slice::from_raw_parts(ptr, len) });
+        libos.sgafree(sga)?;
+        if len == 0 {
+            break;
+        }
+        demikernel::ensure_eq!(len <= POP_SIZE, true);
+    }
+    demikernel::ensure_eq!(received.len(), expected.len());
+    demikernel::ensure_eq!(received == expected, true);
+
+    libos.close(pipeqd_rx)?;
+
+    Ok(())
+}
@@ -13,8 +13,11 @@ mod async_close;
 mod close;
 mod create_pipe;
 mod open_pipe;
+mod pop_stream;
 mod pop_wait;
+mod push_timeout;
 mod push_wait;
+mod splice;
 mod wait;
 
 //======================================================================================================================
@@ -51,8 +54,11 @@ fn main() -> Result<()> {
             demikernel::collect_test!(result, create_pipe::run(&mut libos, &args.pipe_name()));
             demikernel::collect_test!(result, open_pipe::run(&mut libos, &args.pipe_name()));
             demikernel::collect_test!(result, close::run(&mut libos, &args.pipe_name()));
-            demikernel::collect_test!(result, wait::run(&mut libos));
+            demikernel::collect_test!(result, wait::run(&mut libos, &args.pipe_name()));
             demikernel::collect_test!(result, async_close::run(&mut libos, &args.pipe_name()));
+            demikernel::collect_test!(result, splice::run(&mut libos, &args.pipe_name()));
+            demikernel::collect_test!(result, push_timeout::run(&mut libos, &args.pipe_name()));
+            demikernel::collect_test!(result, pop_stream::run(&mut libos, &args.pipe_name()));
 
             // Dump results.
             demikernel::dump_test!(result)
@@ -0,0 +1,132 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::anyhow::Result;
+use ::demikernel::{
+    runtime::types::{
+        demi_opcode_t,
+        demi_sgarray_t,
+    },
+    LibOS,
+    QDesc,
+    QToken,
+};
+use ::std::{
+    cell::RefCell,
+    rc::Rc,
+    slice,
+    time::Duration,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of bytes in each push.
+const PUSH_SIZE: usize = 4096;
+
+/// Number of pushes issued while streaming.
+const NUM_PUSHES: usize = 1024;
+
+/// Timeout for waiting on operations.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Drives integration tests for streaming pops on pipe queues.
+pub fn run(libos: &mut LibOS, pipe_name: &str) -> Vec<(String, String, Result<(), anyhow::Error>)> {
+    let mut result: Vec<(String, String, Result<(), anyhow::Error>)> = Vec::new();
+
+    demikernel::collect_test!(result, demikernel::run_test!(pop_stream_until_eof(libos, pipe_name)));
+    demikernel::collect_test!(result, demikernel::run_test!(pop_stream_stop_early(libos, pipe_name)));
+
+    result
+}
+
+/// Pushes a buffer filled with the bytes that follow `offset` in the stream.
+fn push_pattern(libos: &mut LibOS, qd: QDesc, offset: usize) -> Result<()> {
+    let sga: demi_sgarray_t = libos.sgaalloc(PUSH_SIZE)?;
+    let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
+    let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+    let data: &mut [u8] = unsafe { slice::from_raw_parts_mut(ptr, len) };
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = ((offset + i) % 251) as u8;
+    }
+    let qt: QToken = libos.push(qd, &sga)?;
+    libos.sgafree(sga)?;
+    match libos.wait(qt, Some(TIMEOUT)) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_PUSH => Ok(()),
+        Ok(_) => anyhow::bail!("wait() should succeed with push()"),
+        Err(e) => anyhow::bail!("wait() failed ({})", e),
+    }
+}
+
+/// Streams a large amount of data out of a pipe, until the other end closes it, and checks that it is reassembled
+/// correctly.
+fn pop_stream_until_eof(libos: &mut LibOS, pipe_name: &str) -> Result<()> {
+    let name: String = format!("{}-pop-stream", pipe_name);
+    let pipeqd_tx: QDesc = libos.create_pipe(&name)?;
+    let pipeqd_rx: QDesc = libos.open_pipe(&name)?;
+
+    // Start streaming before any data is pushed.
+    let received: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+    let sink: Rc<RefCell<Vec<u8>>> = received.clone();
+    let qt: QToken = libos.pop_stream(pipeqd_rx, move |chunk: &[u8]| {
+        sink.borrow_mut().extend_from_slice(chunk);
+        true
+    })?;
+
+    // Push data and then close the pipe, so that the stream reaches end of file.
+    for i in 0..NUM_PUSHES {
+        push_pattern(libos, pipeqd_tx, i * PUSH_SIZE)?;
+    }
+    libos.close(pipeqd_tx)?;
+
+    match libos.wait(qt, Some(TIMEOUT)) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP_STREAM => {
+            demikernel::ensure_eq!(qr.qr_ret as usize, NUM_PUSHES * PUSH_SIZE);
+        },
+        Ok(_) => anyhow::bail!("wait() should succeed with pop_stream()"),
+        Err(e) => anyhow::bail!("wait() failed ({})", e),
+    }
+
+    let received: Vec<u8> = received.take();
+    demikernel::ensure_eq!(received.len(), NUM_PUSHES * PUSH_SIZE);
+    for (i, byte) in received.iter().enumerate() {
+        demikernel::ensure_eq!(*byte, (i % 251) as u8);
+    }
+
+    libos.close(pipeqd_rx)?;
+
+    Ok(())
+}
+
+/// Stops streaming as soon as the callback returns false.
+fn pop_stream_stop_early(libos: &mut LibOS, pipe_name: &str) -> Result<()> {
+    let name: String = format!("{}-pop-stream-stop", pipe_name);
+    let pipeqd_tx: QDesc = libos.create_pipe(&name)?;
+    let pipeqd_rx: QDesc = libos.open_pipe(&name)?;
+
+    let qt: QToken = libos.pop_stream(pipeqd_rx, |_: &[u8]| false)?;
+    push_pattern(libos, pipeqd_tx, 0)?;
+
+    // The stream completes without waiting for end of file.
+    match libos.wait(qt, Some(TIMEOUT)) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP_STREAM => {
+            demikernel::ensure_eq!(qr.qr_ret as usize, PUSH_SIZE);
+        },
+        Ok(_) => anyhow::bail!("wait() should succeed with pop_stream()"),
+        Err(e) => anyhow::bail!("wait() failed ({})", e),
+    }
+
+    libos.close(pipeqd_tx)?;
+    libos.close(pipeqd_rx)?;
+
+    Ok(())
+}
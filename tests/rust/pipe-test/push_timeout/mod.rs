@@ -0,0 +1,107 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::anyhow::Result;
+use ::demikernel::{
+    runtime::types::{
+        demi_opcode_t,
+        demi_qresult_t,
+        demi_sgarray_t,
+    },
+    LibOS,
+    QDesc,
+    QToken,
+};
+use ::std::{
+    slice,
+    time::Duration,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of bytes in each push.
+const PUSH_SIZE: usize = 1024;
+
+/// Maximum number of pushes issued before the pipe is expected to be full.
+const MAX_PUSHES: usize = 1024;
+
+/// Timeout for a single push.
+const PUSH_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Timeout for waiting on operations.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Drives integration tests for pushing to pipe queues with a timeout.
+pub fn run(libos: &mut LibOS, pipe_name: &str) -> Vec<(String, String, Result<(), anyhow::Error>)> {
+    let mut result: Vec<(String, String, Result<(), anyhow::Error>)> = Vec::new();
+
+    demikernel::collect_test!(
+        result,
+        demikernel::run_test!(push_timeout_on_stalled_pipe(libos, pipe_name))
+    );
+
+    result
+}
+
+/// Fills a pipe that nobody reads from, until a push times out, and checks that the stream is left intact.
+fn push_timeout_on_stalled_pipe(libos: &mut LibOS, pipe_name: &str) -> Result<()> {
+    let name: String = format!("{}-push-timeout", pipe_name);
+    let pipeqd_tx: QDesc = libos.create_pipe(&name)?;
+    let pipeqd_rx: QDesc = libos.open_pipe(&name)?;
+
+    // Push until the pipe is full.
+    let mut npushes: usize = 0;
+    loop {
+        if npushes == MAX_PUSHES {
+            anyhow::bail!("push_timeout() should time out once the pipe is full");
+        }
+
+        let sga: demi_sgarray_t = libos.sgaalloc(PUSH_SIZE)?;
+        let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
+        let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+        let data: &mut [u8] = unsafe { slice::from_raw_parts_mut(ptr, len) };
+        data.fill(npushes as u8);
+        let qt: QToken = libos.push_timeout(pipeqd_tx, &sga, PUSH_TIMEOUT)?;
+        libos.sgafree(sga)?;
+
+        match libos.wait(qt, Some(TIMEOUT)) {
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_PUSH => npushes += 1,
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_FAILED && qr.qr_ret == libc::ETIMEDOUT as i64 => break,
+            Ok(qr) => anyhow::bail!("push_timeout() should fail with ETIMEDOUT (qr_ret={:?})", qr.qr_ret),
+            Err(e) => anyhow::bail!("wait() failed ({})", e),
+        }
+    }
+    demikernel::ensure_neq!(npushes, 0);
+
+    // The buffers that were pushed should come out whole and in order.
+    for i in 0..npushes {
+        let qt: QToken = libos.pop(pipeqd_rx, None)?;
+        let qr: demi_qresult_t = match libos.wait(qt, Some(TIMEOUT)) {
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP => qr,
+            Ok(_) => anyhow::bail!("wait() should succeed with pop()"),
+            Err(e) => anyhow::bail!("wait() failed ({})", e),
+        };
+        let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+        let ptr: *const u8 = sga.sga_segs[0].sgaseg_buf as *const u8;
+        let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+        let data: &[u8] = unsafe { slice::from_raw_parts(ptr, len) };
+        demikernel::ensure_eq!(len, PUSH_SIZE);
+        demikernel::ensure_eq!(data.iter().all(|byte| *byte == i as u8), true);
+        libos.sgafree(sga)?;
+    }
+
+    libos.close(pipeqd_tx)?;
+    libos.close(pipeqd_rx)?;
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::anyhow::Result;
+use ::demikernel::{
+    runtime::types::{
+        demi_opcode_t,
+        demi_qresult_t,
+        demi_sgarray_t,
+    },
+    LibOS,
+    QDesc,
+    QToken,
+};
+use ::std::{
+    slice,
+    time::Duration,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of bytes to splice.
+const SPLICE_SIZE: usize = 64;
+
+/// Timeout for waiting on operations.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Drives integration tests for splicing pipe queues.
+pub fn run(libos: &mut LibOS, pipe_name: &str) -> Vec<(String, String, Result<(), anyhow::Error>)> {
+    let mut result: Vec<(String, String, Result<(), anyhow::Error>)> = Vec::new();
+
+    demikernel::collect_test!(result, demikernel::run_test!(splice_pipe_to_itself(libos, pipe_name)));
+    demikernel::collect_test!(result, demikernel::run_test!(splice_between_pipes(libos, pipe_name)));
+
+    result
+}
+
+/// Attempts to splice a pipe into itself.
+fn splice_pipe_to_itself(libos: &mut LibOS, pipe_name: &str) -> Result<()> {
+    let pipeqd: QDesc = libos.create_pipe(pipe_name)?;
+
+    match libos.splice(pipeqd, pipeqd, None) {
+        Err(e) if e.errno == libc::EINVAL => {},
+        Ok(_) => anyhow::bail!("splice() a pipe into itself should fail"),
+        Err(e) => anyhow::bail!("splice() should fail with EINVAL (error={:?})", e),
+    }
+
+    libos.close(pipeqd)?;
+
+    Ok(())
+}
+
+/// Pushes data into one pipe, splices it into another one, and pops it from there.
+fn splice_between_pipes(libos: &mut LibOS, pipe_name: &str) -> Result<()> {
+    let src_name: String = format!("{}-splice-src", pipe_name);
+    let dst_name: String = format!("{}-splice-dst", pipe_name);
+    let src_tx: QDesc = libos.create_pipe(&src_name)?;
+    let src_rx: QDesc = libos.open_pipe(&src_name)?;
+    let dst_tx: QDesc = libos.create_pipe(&dst_name)?;
+    let dst_rx: QDesc = libos.open_pipe(&dst_name)?;
+
+    // Push data into the source pipe.
+    let sga: demi_sgarray_t = libos.sgaalloc(SPLICE_SIZE)?;
+    let ptr: *mut u8 = sga.sga_segs[0].sgaseg_buf as *mut u8;
+    let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+    let data: &mut [u8] = unsafe { slice::from_raw_parts_mut(ptr, len) };
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let qt: QToken = libos.push(src_tx, &sga)?;
+    libos.sgafree(sga)?;
+    match libos.wait(qt, Some(TIMEOUT)) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_PUSH => {},
+        Ok(_) => anyhow::bail!("wait() should succeed with push()"),
+        Err(e) => anyhow::bail!("wait() failed ({})", e),
+    }
+
+    // Splice it into the destination pipe.
+    let qt: QToken = libos.splice(src_rx, dst_tx, Some(SPLICE_SIZE))?;
+    match libos.wait(qt, Some(TIMEOUT)) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_SPLICE => {
+            demikernel::ensure_eq!(qr.qr_ret as usize, SPLICE_SIZE);
+        },
+        Ok(_) => anyhow::bail!("wait() should succeed with splice()"),
+        Err(e) => anyhow::bail!("wait() failed ({})", e),
+    }
+
+    // Pop it from the destination pipe.
+    let qt: QToken = libos.pop(dst_rx, None)?;
+    let qr: demi_qresult_t = match libos.wait(qt, Some(TIMEOUT)) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP => qr,
+        Ok(_) => anyhow::bail!("wait() should succeed with pop()"),
+        Err(e) => anyhow::bail!("wait() failed ({})", e),
+    };
+    let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+    let ptr: *const u8 = sga.sga_segs[0].sgaseg_buf as *const u8;
+    let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+    let data: &[u8] = unsafe { slice::from_raw_parts(ptr, len) };
+    demikernel::ensure_eq!(len, SPLICE_SIZE);
+    for (i, byte) in data.iter().enumerate() {
+        demikernel::ensure_eq!(*byte, i as u8);
+    }
+    libos.sgafree(sga)?;
+
+    for qd in [src_tx, src_rx, dst_tx, dst_rx] {
+        libos.close(qd)?;
+    }
+
+    Ok(())
+}
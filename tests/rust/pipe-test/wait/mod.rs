@@ -7,20 +7,44 @@
 
 use ::anyhow::Result;
 use ::demikernel::{
+    runtime::types::{
+        demi_opcode_t,
+        demi_qresult_t,
+        demi_sgarray_t,
+    },
     LibOS,
+    QDesc,
     QToken,
 };
-use ::std::time::Duration;
+use ::std::time::{
+    Duration,
+    Instant,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Number of operations that are timed in each latency measurement.
+const LATENCY_ROUNDS: u32 = 1024;
+
+/// Maximum number of times to poll the scheduler when spin-waiting.
+const MAX_SPINS: usize = 64;
+
+/// Timeout for waiting on operations.
+const TIMEOUT: Duration = Duration::from_secs(1);
 
 //======================================================================================================================
 // Standalone Functions
 //======================================================================================================================
 
 /// Drives integration tests for pipe queues.
-pub fn run(libos: &mut LibOS) -> Vec<(String, String, Result<(), anyhow::Error>)> {
+pub fn run(libos: &mut LibOS, pipe_name: &str) -> Vec<(String, String, Result<(), anyhow::Error>)> {
     let mut result: Vec<(String, String, Result<(), anyhow::Error>)> = Vec::new();
 
     demikernel::collect_test!(result, demikernel::run_test!(wait_on_invalid_queue_token(libos)));
+    demikernel::collect_test!(result, demikernel::run_test!(wait_spin_on_invalid_queue_token(libos)));
+    demikernel::collect_test!(result, demikernel::run_test!(wait_spin_latency(libos, pipe_name)));
 
     result
 }
@@ -43,3 +67,68 @@ fn wait_on_invalid_queue_token(libos: &mut LibOS) -> Result<()> {
 
     Ok(())
 }
+
+// Attempts to spin-wait on an invalid queue token.
+fn wait_spin_on_invalid_queue_token(libos: &mut LibOS) -> Result<()> {
+    match libos.wait_spin(QToken::from(u64::MAX), MAX_SPINS) {
+        Err(e) if e.errno == libc::EINVAL => Ok(()),
+        Ok(_) => anyhow::bail!("wait_spin() should not succeed on invalid token"),
+        Err(e) => anyhow::bail!("wait_spin() should fail with EINVAL (error={:?})", e),
+    }
+}
+
+/// Compares the latency of spin-waiting and regular waiting on pushes to a pipe, which complete right away.
+fn wait_spin_latency(libos: &mut LibOS, pipe_name: &str) -> Result<()> {
+    let name: String = format!("{}-wait-spin", pipe_name);
+    let pipeqd_tx: QDesc = libos.create_pipe(&name)?;
+    let pipeqd_rx: QDesc = libos.open_pipe(&name)?;
+
+    let mut wait_time: Duration = Duration::ZERO;
+    let mut wait_spin_time: Duration = Duration::ZERO;
+    for _ in 0..LATENCY_ROUNDS {
+        let qt: QToken = push(libos, pipeqd_tx)?;
+        let start: Instant = Instant::now();
+        let qr: demi_qresult_t = libos.wait(qt, Some(TIMEOUT))?;
+        wait_time += start.elapsed();
+        demikernel::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH);
+        drain(libos, pipeqd_rx)?;
+
+        let qt: QToken = push(libos, pipeqd_tx)?;
+        let start: Instant = Instant::now();
+        let qr: Option<demi_qresult_t> = libos.wait_spin(qt, MAX_SPINS)?;
+        wait_spin_time += start.elapsed();
+        match qr {
+            Some(qr) => demikernel::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_PUSH),
+            None => anyhow::bail!("wait_spin() should succeed on a ready operation"),
+        }
+        drain(libos, pipeqd_rx)?;
+    }
+
+    println!(
+        "wait(): {:?}/op, wait_spin(): {:?}/op",
+        wait_time / LATENCY_ROUNDS,
+        wait_spin_time / LATENCY_ROUNDS
+    );
+
+    libos.close(pipeqd_tx)?;
+    libos.close(pipeqd_rx)?;
+
+    Ok(())
+}
+
+/// Pushes a single byte to a pipe.
+fn push(libos: &mut LibOS, pipeqd: QDesc) -> Result<QToken> {
+    let sga: demi_sgarray_t = libos.sgaalloc(1)?;
+    let qt: QToken = libos.push(pipeqd, &sga)?;
+    libos.sgafree(sga)?;
+    Ok(qt)
+}
+
+/// Pops the byte that was last pushed to a pipe.
+fn drain(libos: &mut LibOS, pipeqd: QDesc) -> Result<()> {
+    let qt: QToken = libos.pop(pipeqd, None)?;
+    let qr: demi_qresult_t = libos.wait(qt, Some(TIMEOUT))?;
+    demikernel::ensure_eq!(qr.qr_opcode, demi_opcode_t::DEMI_OPC_POP);
+    libos.sgafree(unsafe { qr.qr_value.sga })?;
+    Ok(())
+}
@@ -175,3 +175,43 @@ fn test_unit_sga_alloc_free_loop_decoupled_small() -> Result<()> {
 fn test_unit_sga_alloc_free_loop_decoupled_big() -> Result<()> {
     do_test_unit_sga_alloc_free_loop_decoupled(SGA_SIZE_BIG)
 }
+
+//==============================================================================
+// test_unit_buffer_memory_accounting()
+//==============================================================================
+
+/// Tests that allocating and releasing buffers leaves the memory usage counter where it started.
+#[cfg(feature = "buffer-accounting")]
+#[test]
+fn test_unit_buffer_memory_accounting() -> Result<()> {
+    use ::demikernel::runtime::memory::{
+        buffer_memory_peak,
+        buffer_memory_usage,
+        DemiBuffer,
+    };
+
+    let baseline: usize = buffer_memory_usage();
+
+    // Allocate some buffers, including a clone that shares its data with the original one.
+    let mut bufs: Vec<DemiBuffer> = Vec::new();
+    for _ in 0..16 {
+        bufs.push(DemiBuffer::new(SGA_SIZE_BIG as u16));
+    }
+    bufs.push(bufs[0].clone());
+    let usage: usize = buffer_memory_usage();
+    if usage < baseline + 16 * SGA_SIZE_BIG {
+        anyhow::bail!("memory usage did not grow (baseline={:?}, usage={:?})", baseline, usage);
+    }
+    if buffer_memory_peak() < usage {
+        anyhow::bail!("peak memory usage is below current usage");
+    }
+
+    // Release all buffers.
+    bufs.clear();
+    demikernel::ensure_eq!(buffer_memory_usage(), baseline);
+    if buffer_memory_peak() < usage {
+        anyhow::bail!("peak memory usage should not decrease");
+    }
+
+    Ok(())
+}
@@ -48,6 +48,7 @@ pub fn run(libos: &mut LibOS, addr: &SocketAddr) -> Vec<(String, String, Result<
     crate::collect!(result, crate::test!(async_close_unbound_socket(libos)));
     crate::collect!(result, crate::test!(async_close_bound_socket(libos, addr)));
     crate::collect!(result, crate::test!(async_close_listening_socket(libos, addr)));
+    crate::collect!(result, crate::test!(async_close_with_linger_unbound_socket(libos)));
 
     result
 }
@@ -189,3 +190,26 @@ fn async_close_listening_socket(libos: &mut LibOS, local: &SocketAddr) -> Result
         Err(_) => anyhow::bail!("wait() should succeed with async_close()"),
     }
 }
+
+/// Attempts to close a TCP socket that is not connected while lingering, which has nothing to linger on.
+fn async_close_with_linger_unbound_socket(libos: &mut LibOS) -> Result<()> {
+    // Create an unbound socket.
+    let sockqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+
+    // Not every libOS supports lingering closes.
+    let qt: QToken = match libos.async_close_with_linger(sockqd, Duration::from_secs(1)) {
+        Ok(qt) => qt,
+        Err(e) if e.errno == libc::ENOTSUP => {
+            libos.close(sockqd)?;
+            return Ok(());
+        },
+        Err(e) => anyhow::bail!("async_close_with_linger() failed with {}", e),
+    };
+
+    // The close completes right away.
+    match libos.wait(qt, Some(Duration::from_secs(1))) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_CLOSE && qr.qr_ret == 0 => Ok(()),
+        Ok(_) => anyhow::bail!("wait() should succeed with async_close_with_linger()"),
+        Err(_) => anyhow::bail!("wait() should succeed with async_close_with_linger()"),
+    }
+}
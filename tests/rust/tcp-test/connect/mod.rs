@@ -53,6 +53,10 @@ pub fn run(
     crate::collect!(result, crate::test!(connect_to_bad_remote(libos)));
     crate::collect!(result, crate::test!(connect_unbound_socket(libos, remote)));
     crate::collect!(result, crate::test!(connect_bound_socket(libos, local, remote)));
+    crate::collect!(
+        result,
+        crate::test!(connect_unbound_socket_ephemeral_port(libos, local))
+    );
     crate::collect!(result, crate::test!(connect_listening_socket(libos, local, remote)));
     crate::collect!(result, crate::test!(connect_connecting_socket(libos, remote)));
     crate::collect!(result, crate::test!(connect_accepting_socket(libos, local, remote)));
@@ -197,6 +201,48 @@ fn connect_bound_socket(libos: &mut LibOS, local: &SocketAddr, remote: &SocketAd
     Ok(())
 }
 
+/// Connects a TCP socket that is not bound and checks that it got a concrete local port.
+fn connect_unbound_socket_ephemeral_port(libos: &mut LibOS, local: &SocketAddr) -> Result<()> {
+    // Create an unbound socket.
+    let sockqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+
+    // Not every libOS reports the local address of a socket.
+    match libos.getsockname(sockqd) {
+        Err(e) if e.errno == libc::ENOTSUP => {
+            libos.close(sockqd)?;
+            return Ok(());
+        },
+        Err(e) => anyhow::bail!("getsockname() failed with {}", e),
+        Ok(_) => (),
+    }
+
+    // Create a listening socket to connect to.
+    let listenqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+    libos.bind(listenqd, local.to_owned())?;
+    libos.listen(listenqd, 16)?;
+
+    // Succeed to connect socket.
+    let qt: QToken = libos.connect(sockqd, local.to_owned())?;
+    match libos.wait(qt, Some(Duration::from_secs(1))) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_CONNECT && qr.qr_ret == 0 => {},
+        Ok(_) => anyhow::bail!("connect() should succeed"),
+        Err(e) => anyhow::bail!("wait() failed with {}", e),
+    }
+
+    // The kernel should have picked a port for us.
+    match libos.getsockname(sockqd) {
+        Ok(addr) if addr.port() != 0 => {},
+        Ok(addr) => anyhow::bail!("getsockname() returned {:?}, which has no port", addr),
+        Err(e) => anyhow::bail!("getsockname() failed with {}", e),
+    }
+
+    // Succeed to close sockets.
+    libos.close(sockqd)?;
+    libos.close(listenqd)?;
+
+    Ok(())
+}
+
 /// Attempts to connect a TCP socket that is listening.
 fn connect_listening_socket(libos: &mut LibOS, local: &SocketAddr, remote: &SocketAddr) -> Result<()> {
     // Create a listening socket.
@@ -15,6 +15,8 @@ mod bind;
 mod close;
 mod connect;
 mod listen;
+mod multiplex;
+mod pop;
 mod socket;
 mod wait;
 
@@ -94,6 +96,8 @@ fn main() -> Result<()> {
     crate::collect!(result, close::run(&mut libos, &args.local()));
     crate::collect!(result, wait::run(&mut libos, &args.local()));
     crate::collect!(result, async_close::run(&mut libos, &args.local()));
+    crate::collect!(result, pop::run(&mut libos, &args.local()));
+    crate::collect!(result, multiplex::run(&mut libos, &args.local()));
 
     // Dump results.
     for (test_name, test_status, test_result) in result {
@@ -0,0 +1,222 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::anyhow::Result;
+use ::demikernel::{
+    runtime::{
+        fail::Fail,
+        types::{
+            demi_opcode_t,
+            demi_qresult_t,
+            demi_sgarray_t,
+        },
+    },
+    LibOS,
+    QDesc,
+    QToken,
+};
+use ::std::{
+    net::SocketAddr,
+    slice,
+    time::Duration,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+#[cfg(target_os = "windows")]
+pub const AF_INET: i32 = windows::Win32::Networking::WinSock::AF_INET.0 as i32;
+
+#[cfg(target_os = "windows")]
+pub const SOCK_STREAM: i32 = windows::Win32::Networking::WinSock::SOCK_STREAM.0 as i32;
+
+#[cfg(target_os = "linux")]
+pub const AF_INET: i32 = libc::AF_INET;
+
+#[cfg(target_os = "linux")]
+pub const SOCK_STREAM: i32 = libc::SOCK_STREAM;
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Drives integration tests for multiplexed connections on TCP sockets.
+pub fn run(libos: &mut LibOS, local: &SocketAddr) -> Vec<(String, String, Result<(), anyhow::Error>)> {
+    let mut result: Vec<(String, String, Result<(), anyhow::Error>)> = Vec::new();
+
+    crate::collect!(result, crate::test!(multiplex_many_connections(libos, local)));
+    crate::collect!(result, crate::test!(multiplex_listen_after_close(libos, local)));
+
+    result
+}
+
+/// Opens several multiplexed connections to the same remote, and checks that each of them carries its own data.
+fn multiplex_many_connections(libos: &mut LibOS, local: &SocketAddr) -> Result<()> {
+    const NUM_CONNECTIONS: usize = 4;
+    const PUSH_SIZE: usize = 64;
+
+    // Not every libOS supports multiplexed connections.
+    let listenqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+    match libos.set_multiplexed(listenqd) {
+        Err(e) if e.errno == libc::ENOTSUP => {
+            libos.close(listenqd)?;
+            return Ok(());
+        },
+        Err(e) => anyhow::bail!("set_multiplexed() failed with {}", e),
+        Ok(()) => (),
+    }
+    libos.bind(listenqd, local.to_owned())?;
+    libos.listen(listenqd, 16)?;
+
+    // Open all connections at once.
+    let mut clientqds: Vec<QDesc> = Vec::with_capacity(NUM_CONNECTIONS);
+    let mut connect_qts: Vec<QToken> = Vec::with_capacity(NUM_CONNECTIONS);
+    for _ in 0..NUM_CONNECTIONS {
+        let clientqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+        libos.set_multiplexed(clientqd)?;
+        connect_qts.push(libos.connect(clientqd, local.to_owned())?);
+        clientqds.push(clientqd);
+    }
+    let mut serverqds: Vec<QDesc> = Vec::with_capacity(NUM_CONNECTIONS);
+    for _ in 0..NUM_CONNECTIONS {
+        let accept_qt: QToken = libos.accept(listenqd)?;
+        match libos.wait(accept_qt, Some(Duration::from_secs(1))) {
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_ACCEPT && qr.qr_ret == 0 => {
+                serverqds.push(unsafe { qr.qr_value.ares.qd.into() })
+            },
+            Ok(_) => anyhow::bail!("accept() should succeed"),
+            Err(e) => anyhow::bail!("wait() failed with {}", e),
+        }
+    }
+    for qt in connect_qts {
+        match libos.wait(qt, Some(Duration::from_secs(1))) {
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_CONNECT && qr.qr_ret == 0 => {},
+            Ok(_) => anyhow::bail!("connect() should succeed"),
+            Err(e) => anyhow::bail!("wait() failed with {}", e),
+        }
+    }
+
+    // Each client sends its own pattern.
+    for (i, clientqd) in clientqds.iter().enumerate() {
+        push_and_wait(libos, *clientqd, &[i as u8; PUSH_SIZE])?;
+    }
+
+    // Each server end echoes what it got, so every client should get its own pattern back.
+    let mut peers: Vec<Option<QDesc>> = vec![None; NUM_CONNECTIONS];
+    for serverqd in serverqds.iter() {
+        let data: Vec<u8> = pop_and_wait(libos, *serverqd)?;
+        let i: usize = data[0] as usize;
+        if data.len() != PUSH_SIZE || i >= NUM_CONNECTIONS || data.iter().any(|b| *b != data[0]) {
+            anyhow::bail!("popped data does not match any pushed data");
+        }
+        if peers[i].replace(*serverqd).is_some() {
+            anyhow::bail!("connection got data of another connection");
+        }
+        push_and_wait(libos, *serverqd, &data)?;
+    }
+    for (i, clientqd) in clientqds.iter().enumerate() {
+        if pop_and_wait(libos, *clientqd)? != vec![i as u8; PUSH_SIZE] {
+            anyhow::bail!("connection got data of another connection");
+        }
+    }
+
+    // Closing a connection should not affect the others.
+    // It is safe to unwrap here, because every connection got data above.
+    libos.close(clientqds[0])?;
+    if !pop_and_wait(libos, peers[0].unwrap())?.is_empty() {
+        anyhow::bail!("pop() should report that the connection was closed");
+    }
+    push_and_wait(libos, clientqds[1], &[0xff; PUSH_SIZE])?;
+    if pop_and_wait(libos, peers[1].unwrap())? != vec![0xff; PUSH_SIZE] {
+        anyhow::bail!("popped data does not match pushed data");
+    }
+
+    // Succeed to close sockets.
+    for qd in serverqds.into_iter().chain(clientqds.into_iter().skip(1)) {
+        libos.close(qd)?;
+    }
+    libos.close(listenqd)?;
+
+    Ok(())
+}
+
+/// Closes a listening socket whose connections are multiplexed, and checks that the address can be listened on again,
+/// as nothing keeps accepting connections for the closed socket.
+fn multiplex_listen_after_close(libos: &mut LibOS, local: &SocketAddr) -> Result<()> {
+    // Not every libOS supports multiplexed connections.
+    let listenqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+    match libos.set_multiplexed(listenqd) {
+        Err(e) if e.errno == libc::ENOTSUP => {
+            libos.close(listenqd)?;
+            return Ok(());
+        },
+        Err(e) => anyhow::bail!("set_multiplexed() failed with {}", e),
+        Ok(()) => (),
+    }
+    libos.bind(listenqd, local.to_owned())?;
+    libos.listen(listenqd, 16)?;
+    libos.close(listenqd)?;
+
+    // Listen again on the same address, and check that connections go to the new socket.
+    let listenqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+    libos.set_multiplexed(listenqd)?;
+    libos.bind(listenqd, local.to_owned())?;
+    libos.listen(listenqd, 16)?;
+    let clientqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+    libos.set_multiplexed(clientqd)?;
+    let connect_qt: QToken = libos.connect(clientqd, local.to_owned())?;
+    let accept_qt: QToken = libos.accept(listenqd)?;
+    let serverqd: QDesc = match libos.wait(accept_qt, Some(Duration::from_secs(1))) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_ACCEPT && qr.qr_ret == 0 => unsafe {
+            qr.qr_value.ares.qd.into()
+        },
+        Ok(_) => anyhow::bail!("accept() should succeed"),
+        Err(e) => anyhow::bail!("wait() failed with {}", e),
+    };
+    match libos.wait(connect_qt, Some(Duration::from_secs(1))) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_CONNECT && qr.qr_ret == 0 => {},
+        Ok(_) => anyhow::bail!("connect() should succeed"),
+        Err(e) => anyhow::bail!("wait() failed with {}", e),
+    }
+
+    // Succeed to close sockets.
+    libos.close(clientqd)?;
+    libos.close(serverqd)?;
+    libos.close(listenqd)?;
+
+    Ok(())
+}
+
+/// Pushes `data` to `qd` and waits for the push to complete.
+fn push_and_wait(libos: &mut LibOS, qd: QDesc, data: &[u8]) -> Result<()> {
+    let sga: demi_sgarray_t = libos.sgaalloc(data.len())?;
+    let buf: &mut [u8] = unsafe { slice::from_raw_parts_mut(sga.sga_segs[0].sgaseg_buf as *mut u8, data.len()) };
+    buf.copy_from_slice(data);
+    let qt: QToken = libos.push(qd, &sga)?;
+    let result: Result<demi_qresult_t, Fail> = libos.wait(qt, Some(Duration::from_secs(1)));
+    libos.sgafree(sga)?;
+    match result {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_PUSH && qr.qr_ret == 0 => Ok(()),
+        Ok(_) => anyhow::bail!("push() should succeed"),
+        Err(e) => anyhow::bail!("wait() failed with {}", e),
+    }
+}
+
+/// Pops from `qd` and returns the popped data.
+fn pop_and_wait(libos: &mut LibOS, qd: QDesc) -> Result<Vec<u8>> {
+    let qt: QToken = libos.pop(qd, None)?;
+    let sga: demi_sgarray_t = match libos.wait(qt, Some(Duration::from_secs(1))) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP && qr.qr_ret == 0 => unsafe { qr.qr_value.sga },
+        Ok(_) => anyhow::bail!("pop() should succeed"),
+        Err(e) => anyhow::bail!("wait() failed with {}", e),
+    };
+    let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+    let data: Vec<u8> = unsafe { slice::from_raw_parts(sga.sga_segs[0].sgaseg_buf as *const u8, len) }.to_vec();
+    libos.sgafree(sga)?;
+    Ok(data)
+}
@@ -0,0 +1,138 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use ::anyhow::Result;
+use ::demikernel::{
+    runtime::types::{
+        demi_opcode_t,
+        demi_sgarray_t,
+    },
+    LibOS,
+    QDesc,
+    QToken,
+};
+use ::std::{
+    net::SocketAddr,
+    slice,
+    time::Duration,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+#[cfg(target_os = "windows")]
+pub const AF_INET: i32 = windows::Win32::Networking::WinSock::AF_INET.0 as i32;
+
+#[cfg(target_os = "windows")]
+pub const SOCK_STREAM: i32 = windows::Win32::Networking::WinSock::SOCK_STREAM.0 as i32;
+
+#[cfg(target_os = "linux")]
+pub const AF_INET: i32 = libc::AF_INET;
+
+#[cfg(target_os = "linux")]
+pub const SOCK_STREAM: i32 = libc::SOCK_STREAM;
+
+//======================================================================================================================
+// Standalone Functions
+//======================================================================================================================
+
+/// Drives integration tests for pop() on TCP sockets.
+pub fn run(libos: &mut LibOS, local: &SocketAddr) -> Vec<(String, String, Result<(), anyhow::Error>)> {
+    let mut result: Vec<(String, String, Result<(), anyhow::Error>)> = Vec::new();
+
+    crate::collect!(result, crate::test!(pop_continuous_recv_back_to_back(libos, local)));
+
+    result
+}
+
+/// Pushes many buffers back to back into a TCP socket that is in continuous receive mode, and checks that all data is
+/// popped, in order and without gaps.
+fn pop_continuous_recv_back_to_back(libos: &mut LibOS, local: &SocketAddr) -> Result<()> {
+    const NUM_PUSHES: usize = 64;
+    const PUSH_SIZE: usize = 1024;
+    const BACKLOG_SIZE: usize = 8;
+
+    // Set up a connection.
+    let listenqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+    libos.bind(listenqd, local.to_owned())?;
+    libos.listen(listenqd, 16)?;
+    let accept_qt: QToken = libos.accept(listenqd)?;
+    let clientqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+    let connect_qt: QToken = libos.connect(clientqd, local.to_owned())?;
+    match libos.wait(connect_qt, Some(Duration::from_secs(1))) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_CONNECT && qr.qr_ret == 0 => {},
+        Ok(_) => anyhow::bail!("connect() should succeed"),
+        Err(e) => anyhow::bail!("wait() failed with {}", e),
+    }
+    let serverqd: QDesc = match libos.wait(accept_qt, Some(Duration::from_secs(1))) {
+        Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_ACCEPT && qr.qr_ret == 0 => unsafe {
+            qr.qr_value.ares.qd.into()
+        },
+        Ok(_) => anyhow::bail!("accept() should succeed"),
+        Err(e) => anyhow::bail!("wait() failed with {}", e),
+    };
+
+    // Not every libOS supports continuous receive mode.
+    match libos.set_continuous_recv(serverqd, BACKLOG_SIZE) {
+        Err(e) if e.errno == libc::ENOTSUP => {
+            libos.close(serverqd)?;
+            libos.close(clientqd)?;
+            libos.close(listenqd)?;
+            return Ok(());
+        },
+        Err(e) => anyhow::bail!("set_continuous_recv() failed with {}", e),
+        Ok(()) => (),
+    }
+
+    // Issue all pushes at once, then wait for them.
+    let mut expected: Vec<u8> = Vec::with_capacity(NUM_PUSHES * PUSH_SIZE);
+    let mut pushes: Vec<(QToken, demi_sgarray_t)> = Vec::with_capacity(NUM_PUSHES);
+    for i in 0..NUM_PUSHES {
+        let sga: demi_sgarray_t = libos.sgaalloc(PUSH_SIZE)?;
+        let data: &mut [u8] = unsafe { slice::from_raw_parts_mut(sga.sga_segs[0].sgaseg_buf as *mut u8, PUSH_SIZE) };
+        data.fill(i as u8);
+        expected.extend_from_slice(data);
+        pushes.push((libos.push(clientqd, &sga)?, sga));
+    }
+    for (qt, sga) in pushes {
+        match libos.wait(qt, Some(Duration::from_secs(1))) {
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_PUSH && qr.qr_ret == 0 => {},
+            Ok(_) => anyhow::bail!("push() should succeed"),
+            Err(e) => anyhow::bail!("wait() failed with {}", e),
+        }
+        libos.sgafree(sga)?;
+    }
+
+    // Pop everything that was pushed.
+    let mut received: Vec<u8> = Vec::with_capacity(expected.len());
+    while received.len() < expected.len() {
+        let qt: QToken = libos.pop(serverqd, None)?;
+        let sga: demi_sgarray_t = match libos.wait(qt, Some(Duration::from_secs(1))) {
+            Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_POP && qr.qr_ret == 0 => unsafe { qr.qr_value.sga },
+            Ok(_) => anyhow::bail!("pop() should succeed"),
+            Err(e) => anyhow::bail!("wait() failed with {}", e),
+        };
+        let len: usize = sga.sga_segs[0].sgaseg_len as usize;
+        let data: &[u8] = unsafe { slice::from_raw_parts(sga.sga_segs[0].sgaseg_buf as *const u8, len) };
+        received.extend_from_slice(data);
+        libos.sgafree(sga)?;
+        if len == 0 {
+            anyhow::bail!("connection was closed after {} bytes", received.len());
+        }
+    }
+    if received != expected {
+        anyhow::bail!("popped data does not match pushed data");
+    }
+
+    // Succeed to close sockets.
+    libos.close(serverqd)?;
+    libos.close(clientqd)?;
+    libos.close(listenqd)?;
+
+    Ok(())
+}
@@ -6,7 +6,10 @@
 //======================================================================================================================
 
 use anyhow::Result;
-use demikernel::LibOS;
+use demikernel::{
+    LibOS,
+    QDesc,
+};
 
 #[cfg(target_os = "windows")]
 use windows::Win32::Networking::WinSock;
@@ -37,6 +40,8 @@ pub fn run(libos: &mut LibOS) -> Vec<(String, String, Result<(), anyhow::Error>)
 
     crate::collect!(result, crate::test!(create_socket_using_unsupported_domain(libos)));
     crate::collect!(result, crate::test!(create_socket_using_unsupported_type(libos)));
+    crate::collect!(result, crate::test!(set_and_get_priority(libos)));
+    crate::collect!(result, crate::test!(bind_to_device(libos)));
 
     result
 }
@@ -153,3 +158,75 @@ fn create_socket_using_unsupported_type(libos: &mut LibOS) -> Result<()> {
 
     Ok(())
 }
+
+/// Sets the priority of a TCP socket and reads it back.
+fn set_and_get_priority(libos: &mut LibOS) -> Result<()> {
+    let sockqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+
+    match libos.set_priority(sockqd, 5) {
+        // Not every libOS supports socket priorities.
+        Err(e) if e.errno == libc::ENOTSUP => {
+            libos.close(sockqd)?;
+            return Ok(());
+        },
+        Err(e) => anyhow::bail!("set_priority() failed with {}", e),
+        Ok(()) => (),
+    }
+    match libos.get_priority(sockqd) {
+        Ok(priority) if priority == 5 => (),
+        Ok(priority) => anyhow::bail!("get_priority() returned {} instead of 5", priority),
+        Err(e) => anyhow::bail!("get_priority() failed with {}", e),
+    }
+
+    // Priorities out of range are rejected and leave the socket untouched.
+    match libos.set_priority(sockqd, 7) {
+        Err(e) if e.errno == libc::EINVAL => (),
+        Err(e) => anyhow::bail!("set_priority() failed with {}", e),
+        Ok(()) => anyhow::bail!("set_priority() with an out of range priority should fail"),
+    }
+    match libos.get_priority(sockqd) {
+        Ok(priority) if priority == 5 => (),
+        Ok(priority) => anyhow::bail!("get_priority() returned {} instead of 5", priority),
+        Err(e) => anyhow::bail!("get_priority() failed with {}", e),
+    }
+
+    libos.close(sockqd)?;
+
+    Ok(())
+}
+
+/// Binds a TCP socket to the loopback interface and reads the binding back.
+fn bind_to_device(libos: &mut LibOS) -> Result<()> {
+    let sockqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+
+    match libos.bind_to_device(sockqd, "lo") {
+        // Not every libOS supports binding to an interface, and older kernels require CAP_NET_RAW for it.
+        Err(e) if e.errno == libc::ENOTSUP || e.errno == libc::EPERM => {
+            libos.close(sockqd)?;
+            return Ok(());
+        },
+        Err(e) => anyhow::bail!("bind_to_device() failed with {}", e),
+        Ok(()) => (),
+    }
+    match libos.get_bound_device(sockqd) {
+        Ok(ifname) if ifname == "lo" => (),
+        Ok(ifname) => anyhow::bail!("get_bound_device() returned {:?} instead of \"lo\"", ifname),
+        Err(e) => anyhow::bail!("get_bound_device() failed with {}", e),
+    }
+
+    // Unknown interfaces and names that are too long are rejected.
+    match libos.bind_to_device(sockqd, "nosuchdevice0") {
+        Err(e) if e.errno == libc::ENODEV => (),
+        Err(e) => anyhow::bail!("bind_to_device() failed with {}", e),
+        Ok(()) => anyhow::bail!("bind_to_device() with an unknown interface should fail"),
+    }
+    match libos.bind_to_device(sockqd, "averyverylongifname") {
+        Err(e) if e.errno == libc::EINVAL => (),
+        Err(e) => anyhow::bail!("bind_to_device() failed with {}", e),
+        Ok(()) => anyhow::bail!("bind_to_device() with a name that is too long should fail"),
+    }
+
+    libos.close(sockqd)?;
+
+    Ok(())
+}
@@ -12,9 +12,12 @@ use ::demikernel::{
     inetstack::SharedInetStack,
     runtime::{
         memory::DemiBuffer,
+        network::socket::state::SocketState,
         OperationResult,
         QDesc,
         QToken,
+        QType,
+        QueueInfo,
     },
 };
 use common::{
@@ -41,18 +44,25 @@ pub const AF_INET: i32 = windows::Win32::Networking::WinSock::AF_INET.0 as i32;
 #[cfg(target_os = "windows")]
 pub const SOCK_STREAM: i32 = windows::Win32::Networking::WinSock::SOCK_STREAM.0 as i32;
 
+#[cfg(target_os = "windows")]
+pub const SOCK_DGRAM: i32 = windows::Win32::Networking::WinSock::SOCK_DGRAM.0 as i32;
+
 #[cfg(target_os = "linux")]
 pub const AF_INET: i32 = libc::AF_INET;
 
 #[cfg(target_os = "linux")]
 pub const SOCK_STREAM: i32 = libc::SOCK_STREAM;
 
+#[cfg(target_os = "linux")]
+pub const SOCK_DGRAM: i32 = libc::SOCK_DGRAM;
+
 use std::{
     net::{
         IpAddr,
         Ipv4Addr,
         Ipv6Addr,
         SocketAddr,
+        SocketAddrV4,
         SocketAddrV6,
     },
     thread::{
@@ -102,6 +112,55 @@ fn tcp_connection_setup() -> Result<()> {
     Ok(())
 }
 
+//======================================================================================================================
+// List Queues
+//======================================================================================================================
+
+/// Tests if open sockets are reported when listing queues.
+#[test]
+fn tcp_list_queues() -> Result<()> {
+    let (tx, rx): (Sender<DemiBuffer>, Receiver<DemiBuffer>) = crossbeam_channel::unbounded();
+    let mut libos: SharedInetStack<RECEIVE_BATCH_SIZE> = DummyLibOS::new(ALICE_MAC, ALICE_IPV4, tx, rx, arp())?;
+
+    // Open a passive TCP socket.
+    let tcp_local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE);
+    let tcp_qd: QDesc = safe_socket(&mut libos)?;
+    safe_bind(&mut libos, tcp_qd, SocketAddr::V4(tcp_local))?;
+    safe_listen(&mut libos, tcp_qd)?;
+
+    // Open a UDP socket.
+    let udp_local: SocketAddrV4 = SocketAddrV4::new(ALICE_IPV4, PORT_BASE + 1);
+    let udp_qd: QDesc = match libos.socket(AF_INET, SOCK_DGRAM, 0) {
+        Ok(qd) => qd,
+        Err(e) => anyhow::bail!("failed to create socket: {:?}", e),
+    };
+    safe_bind(&mut libos, udp_qd, SocketAddr::V4(udp_local))?;
+
+    let queues: Vec<QueueInfo> = libos.list_queues();
+    demikernel::ensure_eq!(queues.len(), 2);
+
+    match queues.iter().find(|info| info.qd == tcp_qd) {
+        Some(info) => {
+            demikernel::ensure_eq!(info.qtype, QType::TcpSocket);
+            demikernel::ensure_eq!(info.local, Some(tcp_local));
+            demikernel::ensure_eq!(info.remote, None);
+            demikernel::ensure_eq!(info.state, Some(SocketState::PassiveListening));
+        },
+        None => anyhow::bail!("list_queues() should have reported the TCP socket"),
+    }
+
+    match queues.iter().find(|info| info.qd == udp_qd) {
+        Some(info) => {
+            demikernel::ensure_eq!(info.qtype, QType::UdpSocket);
+            demikernel::ensure_eq!(info.local, Some(udp_local));
+            demikernel::ensure_eq!(info.state, Some(SocketState::Bound));
+        },
+        None => anyhow::bail!("list_queues() should have reported the UDP socket"),
+    }
+
+    Ok(())
+}
+
 //======================================================================================================================
 // Establish Connection
 //======================================================================================================================